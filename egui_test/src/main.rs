@@ -6,22 +6,45 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 #![allow(rustdoc::missing_crate_level_docs)]
 
-use aes_gcm::{
-    aead::{Aead, KeyInit},
-    Aes256Gcm, Key, Nonce,
-};
-use argon2::{password_hash::SaltString, Argon2, PasswordHash, PasswordHasher, PasswordVerifier};
+use argon2::password_hash::SaltString;
 use egui::{ColorImage, TextureOptions, Vec2};
-use rand::{rngs::OsRng, RngCore};
+use rusqlite::{params, Connection, OptionalExtension};
+use secure_core::{aead, kdf, AeadAlgorithm, KdfParams, Key};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+/// Version of the database schema, stored in `PRAGMA user_version`.
+const SCHEMA_VERSION: i32 = 2;
+
+/// Tables of the current schema.
+const CREATE_TABLES: &str = "
+    CREATE TABLE IF NOT EXISTS profile (
+        id INTEGER PRIMARY KEY CHECK (id = 1),
+        salt TEXT NOT NULL,
+        password_hash TEXT NOT NULL,
+        updated_at INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS encrypted_fields (
+        field TEXT PRIMARY KEY,
+        ciphertext BLOB NOT NULL,
+        nonce BLOB NOT NULL
+    );";
+
+/// Field names of the encrypted profile.
+const FIELD_NAME: &str = "name";
+const FIELD_NOTES: &str = "notes";
+const FIELD_AGE: &str = "age";
+
+/// Highest age the slider allows.
+const MAX_AGE: u32 = 120;
 
 fn main() -> eframe::Result<()> {
     env_logger::init();
     let options = eframe::NativeOptions {
-        viewport: egui::ViewportBuilder::default().with_inner_size([400.0, 300.0]),
+        viewport: egui::ViewportBuilder::default().with_inner_size([400.0, 420.0]),
         ..Default::default()
     };
 
@@ -35,25 +58,60 @@ fn main() -> eframe::Result<()> {
     )
 }
 
-#[derive(Serialize, Deserialize)]
+/// A single encrypted value with its own nonce.
+struct EncryptedField {
+    ciphertext: Vec<u8>,
+    nonce: Vec<u8>,
+}
+
+/// The stored profile: encrypted fields by name, plus what is needed to
+/// check the password and derive the key.
 struct EncryptedData {
+    fields: BTreeMap<String, EncryptedField>,
+    salt: String,
+    password_hash: String,
+}
+
+/// Data file of older versions, which only held the encrypted name.
+#[derive(Serialize, Deserialize)]
+struct LegacyEncryptedData {
     encrypted_name: Vec<u8>,
     nonce: Vec<u8>,
     salt: String,
     password_hash: String,
 }
 
+impl From<LegacyEncryptedData> for EncryptedData {
+    fn from(legacy: LegacyEncryptedData) -> Self {
+        let name = EncryptedField {
+            ciphertext: legacy.encrypted_name,
+            nonce: legacy.nonce,
+        };
+        Self {
+            fields: BTreeMap::from([(FIELD_NAME.to_string(), name)]),
+            salt: legacy.salt,
+            password_hash: legacy.password_hash,
+        }
+    }
+}
+
 struct MyApp {
     name: String,
     age: u32,
+    notes: String,
     ferris_texture: Option<egui::TextureHandle>,
     password: String,
     is_unlocked: bool,
     password_input: String,
     show_password_dialog: bool,
-    encryption_key: Option<[u8; 32]>,
+    encryption_key: Option<Key>,
     error_message: String,
     salt: Option<String>,
+    show_change_password_dialog: bool,
+    old_password_input: String,
+    new_password_input: String,
+    confirm_password_input: String,
+    change_password_message: String,
 }
 
 impl MyApp {
@@ -61,6 +119,7 @@ impl MyApp {
         let mut app = Self {
             name: String::new(),
             age: 18,
+            notes: String::new(),
             ferris_texture: None,
             password: String::new(),
             is_unlocked: false,
@@ -69,14 +128,16 @@ impl MyApp {
             encryption_key: None,
             error_message: String::new(),
             salt: None,
+            show_change_password_dialog: false,
+            old_password_input: String::new(),
+            new_password_input: String::new(),
+            confirm_password_input: String::new(),
+            change_password_message: String::new(),
         };
 
-        // Check if encrypted data exists
-        if app.get_data_file_path().exists() {
-            app.show_password_dialog = true;
-        } else {
-            app.show_password_dialog = true;
-        }
+        // The password dialog is shown both to unlock existing data and to
+        // set the initial password when no data file exists yet
+        app.show_password_dialog = true;
 
         app
     }
@@ -87,98 +148,183 @@ impl MyApp {
         path
     }
 
-    fn derive_key_from_password(password: &str, salt: &[u8]) -> [u8; 32] {
-        let argon2 = Argon2::default();
-        let mut key = [0u8; 32];
-        argon2
-            .hash_password_into(password.as_bytes(), salt, &mut key)
-            .expect("Failed to derive key");
-        key
+    /// Derives the encryption key with the `argon2` crate defaults, which
+    /// the stored data has always been encrypted with.
+    fn derive_key_from_password(password: &str, salt: &[u8]) -> Result<Key, String> {
+        secure_core::derive_key(password, salt, &KdfParams::ARGON2_DEFAULT)
+            .map_err(|e| e.to_string())
     }
 
-    fn encrypt_text(&self, text: &str, key: &[u8; 32]) -> Result<(Vec<u8>, Vec<u8>), String> {
-        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
-        let mut nonce_bytes = [0u8; 12];
-        OsRng.fill_bytes(&mut nonce_bytes);
-        let nonce = Nonce::from_slice(&nonce_bytes);
+    fn encrypt_text(&self, text: &str, key: &Key) -> Result<(Vec<u8>, Vec<u8>), String> {
+        let (nonce, ciphertext) = aead::seal(AeadAlgorithm::Aes256Gcm, key, text.as_bytes(), &[])
+            .map_err(|e| e.to_string())?;
 
-        let ciphertext = cipher
-            .encrypt(nonce, text.as_bytes())
-            .map_err(|e| format!("Encryption failed: {}", e))?;
-
-        Ok((ciphertext, nonce_bytes.to_vec()))
+        Ok((ciphertext, nonce))
     }
 
-    fn decrypt_text(
-        &self,
-        ciphertext: &[u8],
-        nonce: &[u8],
-        key: &[u8; 32],
-    ) -> Result<String, String> {
-        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
-        let nonce = Nonce::from_slice(nonce);
-
-        let plaintext = cipher
-            .decrypt(nonce, ciphertext)
-            .map_err(|e| format!("Decryption failed: {}", e))?;
+    fn decrypt_text(&self, ciphertext: &[u8], nonce: &[u8], key: &Key) -> Result<String, String> {
+        let plaintext = aead::open(AeadAlgorithm::Aes256Gcm, key, nonce, ciphertext, &[])
+            .map_err(|e| e.to_string())?;
 
         String::from_utf8(plaintext).map_err(|e| format!("Invalid UTF-8: {}", e))
     }
 
-    fn save_encrypted_data(&self) -> Result<(), String> {
-        if let (Some(key), Some(salt_str)) = (&self.encryption_key, &self.salt) {
-            let (encrypted_name, nonce) = self.encrypt_text(&self.name, key)?;
-
-            let salt =
-                SaltString::from_b64(salt_str).map_err(|e| format!("Invalid salt: {}", e))?;
-            let argon2 = Argon2::default();
-            let password_hash = argon2
-                .hash_password(self.password.as_bytes(), &salt)
-                .map_err(|e| format!("Password hashing failed: {}", e))?
-                .to_string();
-
-            let encrypted_data = EncryptedData {
-                encrypted_name,
-                nonce,
-                salt: salt_str.clone(),
-                password_hash,
-            };
+    /// Returns the plaintext of every field that is stored encrypted.
+    fn field_values(&self) -> [(&'static str, String); 3] {
+        [
+            (FIELD_NAME, self.name.clone()),
+            (FIELD_NOTES, self.notes.clone()),
+            (FIELD_AGE, self.age.to_string()),
+        ]
+    }
 
-            let json_bytes = serde_json::to_vec(&encrypted_data)
-                .map_err(|e| format!("JSON serialization failed: {}", e))?;
+    /// Opens the SQLite database, creating its tables or upgrading an older
+    /// schema if needed.
+    fn open_database(path: &Path) -> Result<Connection, String> {
+        let conn = Connection::open(path).map_err(|e| format!("Database open failed: {}", e))?;
+
+        let version: i32 = conn
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .map_err(|e| format!("Database read failed: {}", e))?;
+        if version > SCHEMA_VERSION {
+            return Err(format!(
+                "Database was written by a newer version (schema {})",
+                version
+            ));
+        }
 
-            let mut binary_data = Vec::new();
+        // Schema 1 stored the encrypted name in a single row
+        let migration = if version == 1 {
+            "INSERT INTO profile (id, salt, password_hash, updated_at)
+                SELECT id, salt, password_hash, updated_at FROM encrypted_data;
+            INSERT INTO encrypted_fields (field, ciphertext, nonce)
+                SELECT 'name', encrypted_name, nonce FROM encrypted_data;
+            DROP TABLE encrypted_data;"
+        } else {
+            ""
+        };
 
-            binary_data.extend_from_slice(b"SQLite format 3\x00");
+        conn.execute_batch(&format!(
+            "BEGIN;
+            {}
+            {}
+            PRAGMA user_version = {};
+            COMMIT;",
+            CREATE_TABLES, migration, SCHEMA_VERSION
+        ))
+        .map_err(|e| format!("Database setup failed: {}", e))?;
+
+        Ok(conn)
+    }
 
-            binary_data.extend_from_slice(&[0x10, 0x00]);
-            binary_data.extend_from_slice(&[0x01, 0x01, 0x00, 0x40]);
-            binary_data.extend_from_slice(&[0x20, 0x20, 0x00, 0x20]);
+    /// Parses a data file written by older versions, which stored the JSON
+    /// behind a fake SQLite header. Returns None for anything else,
+    /// including real SQLite databases.
+    fn read_legacy_file(binary_data: &[u8]) -> Option<LegacyEncryptedData> {
+        if binary_data.len() < 136 || &binary_data[0..16] != b"SQLite format 3\x00" {
+            return None;
+        }
 
-            let timestamp = std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs();
-            binary_data.extend_from_slice(&timestamp.to_le_bytes());
+        let data_len = u32::from_le_bytes(binary_data[100..104].try_into().ok()?) as usize;
+        let stored_checksum = &binary_data[104..136];
+        let json_bytes = binary_data.get(136..136 + data_len)?;
 
-            binary_data.resize(100, 0x00);
+        let mut hasher = Sha256::new();
+        hasher.update(json_bytes);
+        if stored_checksum != hasher.finalize().as_slice() {
+            return None;
+        }
 
-            let mut hasher = Sha256::new();
-            hasher.update(&json_bytes);
-            let checksum = hasher.finalize();
+        serde_json::from_slice(json_bytes).ok()
+    }
 
-            binary_data.extend_from_slice(&(json_bytes.len() as u32).to_le_bytes());
+    /// Reads the stored profile from the database.
+    fn read_database(conn: &Connection) -> Result<EncryptedData, String> {
+        let (salt, password_hash) = conn
+            .query_row(
+                "SELECT salt, password_hash FROM profile WHERE id = 1",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+            .map_err(|e| format!("Database read failed: {}", e))?
+            .ok_or_else(|| "No encrypted data stored".to_string())?;
+
+        let mut statement = conn
+            .prepare("SELECT field, ciphertext, nonce FROM encrypted_fields")
+            .map_err(|e| format!("Database read failed: {}", e))?;
+        let fields = statement
+            .query_map([], |row| {
+                Ok((
+                    row.get(0)?,
+                    EncryptedField {
+                        ciphertext: row.get(1)?,
+                        nonce: row.get(2)?,
+                    },
+                ))
+            })
+            .and_then(|rows| rows.collect())
+            .map_err(|e| format!("Database read failed: {}", e))?;
+
+        Ok(EncryptedData {
+            fields,
+            salt,
+            password_hash,
+        })
+    }
 
-            binary_data.extend_from_slice(&checksum);
+    fn save_encrypted_data(&self) -> Result<(), String> {
+        if let (Some(key), Some(salt_str)) = (&self.encryption_key, &self.salt) {
+            let mut fields = Vec::new();
+            for (field, value) in self.field_values() {
+                fields.push((field, self.encrypt_text(&value, key)?));
+            }
 
-            binary_data.extend_from_slice(&json_bytes);
+            // The verification hash gets its own random salt: hashed with the
+            // key derivation salt, it would be the encryption key itself
+            let password_hash = kdf::hash_password(&self.password).map_err(|e| e.to_string())?;
+
+            let file_path = self.get_data_file_path();
+
+            // A file in the old format is replaced by a real database, and
+            // only removed once the database has been written
+            let legacy_backup = file_path.with_extension("db.old");
+            let is_legacy = fs::read(&file_path)
+                .ok()
+                .and_then(|data| Self::read_legacy_file(&data))
+                .is_some();
+            if is_legacy {
+                fs::rename(&file_path, &legacy_backup)
+                    .map_err(|e| format!("Moving old data file failed: {}", e))?;
+            }
 
-            let mut padding = vec![0u8; 50 + (timestamp % 200) as usize];
-            OsRng.fill_bytes(&mut padding);
-            binary_data.extend_from_slice(&padding);
+            let timestamp = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_secs() as i64;
+
+            let mut conn = Self::open_database(&file_path)?;
+            let write = |conn: &mut Connection| -> rusqlite::Result<()> {
+                let tx = conn.transaction()?;
+                tx.execute(
+                    "INSERT OR REPLACE INTO profile (id, salt, password_hash, updated_at)
+                     VALUES (1, ?1, ?2, ?3)",
+                    params![salt_str, password_hash, timestamp],
+                )?;
+                for (field, (ciphertext, nonce)) in &fields {
+                    tx.execute(
+                        "INSERT OR REPLACE INTO encrypted_fields (field, ciphertext, nonce)
+                         VALUES (?1, ?2, ?3)",
+                        params![field, ciphertext, nonce],
+                    )?;
+                }
+                tx.commit()
+            };
+            write(&mut conn).map_err(|e| format!("Database write failed: {}", e))?;
 
-            fs::write(self.get_data_file_path(), binary_data)
-                .map_err(|e| format!("File write failed: {}", e))?;
+            if is_legacy {
+                let _ = fs::remove_file(&legacy_backup);
+            }
         }
         Ok(())
     }
@@ -189,54 +335,40 @@ impl MyApp {
             return Err("No encrypted data file found".to_string());
         }
 
-        let binary_data = fs::read(file_path).map_err(|e| format!("File read failed: {}", e))?;
-
-        if binary_data.len() < 16 || &binary_data[0..16] != b"SQLite format 3\x00" {
-            return Err("Invalid file format".to_string());
-        }
-
-        if binary_data.len() < 100 + 4 + 32 {
-            return Err("File too small or corrupted".to_string());
-        }
-
-        let data_len = u32::from_le_bytes([
-            binary_data[100],
-            binary_data[101],
-            binary_data[102],
-            binary_data[103],
-        ]) as usize;
-
-        let stored_checksum = &binary_data[104..136];
-
-        if binary_data.len() < 136 + data_len {
-            return Err("File corrupted: insufficient data".to_string());
-        }
-
-        let json_bytes = &binary_data[136..136 + data_len];
-
-        let mut hasher = Sha256::new();
-        hasher.update(json_bytes);
-        let calculated_checksum = hasher.finalize();
-
-        if stored_checksum != calculated_checksum.as_slice() {
-            return Err("File corrupted: checksum mismatch".to_string());
-        }
-
-        let encrypted_data: EncryptedData = serde_json::from_slice(json_bytes)
-            .map_err(|e| format!("Data deserialization failed: {}", e))?;
+        let binary_data = fs::read(&file_path).map_err(|e| format!("File read failed: {}", e))?;
 
-        let parsed_hash = PasswordHash::new(&encrypted_data.password_hash)
-            .map_err(|e| format!("Invalid password hash: {}", e))?;
+        // Files of older versions are converted on the next save
+        let encrypted_data = match Self::read_legacy_file(&binary_data) {
+            Some(legacy) => EncryptedData::from(legacy),
+            None => Self::read_database(&Self::open_database(&file_path)?)?,
+        };
 
-        Argon2::default()
-            .verify_password(password.as_bytes(), &parsed_hash)
+        kdf::verify_password(password, &encrypted_data.password_hash)
             .map_err(|_| "Invalid password".to_string())?;
 
-        let key = Self::derive_key_from_password(password, encrypted_data.salt.as_bytes());
-        let decrypted_name =
-            self.decrypt_text(&encrypted_data.encrypted_name, &encrypted_data.nonce, &key)?;
-
-        self.name = decrypted_name;
+        let key = Self::derive_key_from_password(password, encrypted_data.salt.as_bytes())?;
+        // Fields missing from older data keep their defaults
+        let decrypt_field = |field: &str| -> Result<Option<String>, String> {
+            encrypted_data
+                .fields
+                .get(field)
+                .map(|value| self.decrypt_text(&value.ciphertext, &value.nonce, &key))
+                .transpose()
+        };
+        let name = decrypt_field(FIELD_NAME)?;
+        let notes = decrypt_field(FIELD_NOTES)?;
+        let age = decrypt_field(FIELD_AGE)?
+            .map(|age| {
+                age.parse::<u32>()
+                    .map_err(|_| "Invalid stored age".to_string())
+            })
+            .transpose()?;
+
+        self.name = name.unwrap_or_default();
+        self.notes = notes.unwrap_or_default();
+        if let Some(age) = age {
+            self.age = age.min(MAX_AGE);
+        }
         self.password = password.to_string();
         self.encryption_key = Some(key);
         self.salt = Some(encrypted_data.salt);
@@ -252,7 +384,7 @@ impl MyApp {
 
         let salt = SaltString::generate(&mut rand::thread_rng());
         let salt_str = salt.as_str().to_string();
-        let key = Self::derive_key_from_password(password, salt_str.as_bytes());
+        let key = Self::derive_key_from_password(password, salt_str.as_bytes())?;
 
         self.password = password.to_string();
         self.encryption_key = Some(key);
@@ -263,8 +395,75 @@ impl MyApp {
         Ok(())
     }
 
+    /// Changes the password and re-encrypts the stored data with a key
+    /// derived from the new password and a fresh salt.
+    fn change_password(&mut self, old: &str, new: &str, confirm: &str) -> Result<(), String> {
+        let (Some(key), Some(salt_str)) = (self.encryption_key, self.salt.clone()) else {
+            return Err("Data is locked".to_string());
+        };
+
+        // The old password is correct if it derives the key in use
+        if Self::derive_key_from_password(old, salt_str.as_bytes())? != key {
+            return Err("Current password is incorrect".to_string());
+        }
+        if new.len() < 8 {
+            return Err("New password must be at least 8 characters long".to_string());
+        }
+        if new != confirm {
+            return Err("New passwords do not match".to_string());
+        }
+
+        let new_salt = SaltString::generate(&mut rand::thread_rng())
+            .as_str()
+            .to_string();
+        let new_key = Self::derive_key_from_password(new, new_salt.as_bytes())?;
+
+        let old_password = std::mem::replace(&mut self.password, new.to_string());
+        self.encryption_key = Some(new_key);
+        self.salt = Some(new_salt);
+
+        // Keep using the old password if the data could not be re-encrypted
+        if let Err(e) = self.save_encrypted_data() {
+            self.password = old_password;
+            self.encryption_key = Some(key);
+            self.salt = Some(salt_str);
+            return Err(e);
+        }
+
+        Ok(())
+    }
+
+    fn try_change_password(&mut self) {
+        let old = self.old_password_input.clone();
+        let new = self.new_password_input.clone();
+        let confirm = self.confirm_password_input.clone();
+        match self.change_password(&old, &new, &confirm) {
+            Ok(()) => {
+                self.close_change_password_dialog();
+            }
+            Err(e) => {
+                self.change_password_message = e;
+            }
+        }
+    }
+
+    fn close_change_password_dialog(&mut self) {
+        self.show_change_password_dialog = false;
+        self.old_password_input.clear();
+        self.new_password_input.clear();
+        self.confirm_password_input.clear();
+        self.change_password_message.clear();
+    }
+
+    /// Saves after an edit, logging failures instead of interrupting.
+    fn autosave(&self) {
+        if let Err(e) = self.save_encrypted_data() {
+            eprintln!("Failed to save data: {}", e);
+        }
+    }
+
     fn increment(&mut self) {
-        if self.age < 120 {
+        if self.age < MAX_AGE {
             self.age += 1;
         }
     }
@@ -377,6 +576,57 @@ impl eframe::App for MyApp {
             return;
         }
 
+        if self.show_change_password_dialog {
+            let mut change = false;
+            let mut cancel = false;
+
+            egui::Window::new("Change Password")
+                .collapsible(false)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    ui.vertical_centered(|ui| {
+                        ui.label("Current password:");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.old_password_input).password(true),
+                        );
+                        ui.label("New password (minimum 8 characters):");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.new_password_input).password(true),
+                        );
+                        ui.label("Confirm new password:");
+                        let response = ui.add(
+                            egui::TextEdit::singleline(&mut self.confirm_password_input)
+                                .password(true),
+                        );
+                        if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                            change = true;
+                        }
+
+                        ui.add_space(10.0);
+
+                        ui.horizontal(|ui| {
+                            if ui.button("Change").clicked() {
+                                change = true;
+                            }
+                            if ui.button("Cancel").clicked() {
+                                cancel = true;
+                            }
+                        });
+
+                        if !self.change_password_message.is_empty() {
+                            ui.add_space(10.0);
+                            ui.colored_label(egui::Color32::RED, &self.change_password_message);
+                        }
+                    });
+                });
+
+            if change {
+                self.try_change_password();
+            } else if cancel {
+                self.close_change_password_dialog();
+            }
+        }
+
         if self.is_unlocked {
             egui::CentralPanel::default().show(ctx, |ui| {
                 ui.heading("Encrypted Text Application");
@@ -388,13 +638,26 @@ impl eframe::App for MyApp {
                         .labelled_by(name_label.id);
 
                     if response.changed() {
-                        if let Err(e) = self.save_encrypted_data() {
-                            eprintln!("Failed to save data: {}", e);
-                        }
+                        self.autosave();
                     }
                 });
 
-                ui.add(egui::Slider::new(&mut self.age, 0..=120).text("years old"));
+                ui.label("Notes:");
+                let response = ui.add(
+                    egui::TextEdit::multiline(&mut self.notes)
+                        .desired_rows(4)
+                        .desired_width(f32::INFINITY),
+                );
+                if response.changed() {
+                    self.autosave();
+                }
+
+                // Saved once dragging ends, not on every step of the drag
+                let response =
+                    ui.add(egui::Slider::new(&mut self.age, 0..=MAX_AGE).text("years old"));
+                if response.drag_stopped() || (response.changed() && !response.dragged()) {
+                    self.autosave();
+                }
                 ui.label(format!(
                     "Hello {}, you are {} years old!",
                     self.name, self.age
@@ -403,10 +666,12 @@ impl eframe::App for MyApp {
                 ui.horizontal(|ui| {
                     if ui.button("Increment").clicked() {
                         self.increment();
+                        self.autosave();
                     }
 
                     if ui.button("Decrement").clicked() {
                         self.decrement();
+                        self.autosave();
                     }
 
                     if ui.button("Save Data").clicked() {
@@ -419,6 +684,10 @@ impl eframe::App for MyApp {
                             }
                         }
                     }
+
+                    if ui.button("Change Password").clicked() {
+                        self.show_change_password_dialog = true;
+                    }
                 });
 
                 if let Some(texture) = &self.ferris_texture {
@@ -7,18 +7,96 @@
 //! Main application state and logic for the Secure Notes application.
 //! Handles authentication, note management, UI state, and application lifecycle.
 
-use crate::auth::{AuthMode, AuthResult};
-use crate::crypto::CryptoManager;
-use crate::note::Note;
-use crate::storage::StorageManager;
+use crate::attachment_preview::{self, AttachmentPreview, PreviewContent};
+use crate::attachments;
+use crate::auth::{AuthMode, AuthResult, AuthStage};
+use crate::capture_protection;
+use crate::clipboard::PendingClear;
+use crate::crash::{self, CrashReport};
+use crate::crypto::{AeadAlgorithm, CryptoManager, HardwareCheck};
+use crate::export::{self, ExportFormat};
+use crate::export_hook::RunningHook;
+use crate::health_check_ui::HealthCheckView;
+use crate::import::ImportJob;
+use crate::inbox;
+use crate::inbox_ui::InboxDialog;
+use crate::integrity_scan::{IntegrityReport, IntegrityScan, FIRST_SCAN_DELAY};
+use crate::journal::Journal;
+use crate::keymap::ShortcutAction;
+use crate::launch_args::{self, LaunchRequest};
+use crate::link_graph_ui::LinkGraphView;
+use crate::load_failures::LoadFailure;
+use crate::logging::{self, LogLevel};
+use crate::login_history::{self, LoginOutcome, LoginRecord};
+use crate::migration_ui::{LegacyMigration, MigrationWizard};
+use crate::note::{Attachment, Note, NoteKind, SecretEntry, UNTITLED_NOTE_TITLE};
+use crate::note_diagnostics::NoteDiagnostics;
+use crate::note_email_ui::NoteEmailDialog;
+use crate::note_history::RevisionTracker;
+use crate::note_history_ui::NoteHistoryDialog;
+use crate::note_lock::{NoteLock, LOCK_CHECK_INTERVAL};
+use crate::note_order;
+use crate::note_share_ui::NoteShareDialog;
+use crate::note_stats_ui::NoteStatsView;
+use crate::profiling;
+use crate::recovery_ui::RecoveryDialog;
+use crate::reencrypt::ReencryptJob;
+use crate::rename_ui::{RenameDialog, RenameReport, TitleEdit};
+use crate::sample_notes;
+use crate::search::SearchQuery;
+use crate::search_index::{IndexSnapshot, SearchIndex};
+use crate::session_monitor::{SessionEvent, SessionMonitor};
+use crate::settings::{LastSession, PinOverflow, UserSettings};
+use crate::settings_transfer::SettingsTransferDialog;
+use crate::shared_vault::SharedVault;
+use crate::shell_open;
+use crate::site_export;
+use crate::site_export_ui::SiteExportDialog;
+use crate::snapshot_ui::SnapshotViewer;
+use crate::speech::{Speaker, SpeechState};
+use crate::storage::{QuotaUsage, StorageManager, SHARED_VAULT_OWNER};
+use crate::storage_report::{self, StorageReport};
+use crate::text_cache::{EditorLayoutCache, NoteTextStats, PreviewBlocks};
+use crate::trusted_time::{self, TimeCheck, TimeVerifier};
+use crate::two_person_ui::TwoPersonDialog;
 use crate::user::{User, UserManager};
-use chrono::Utc;
+use chrono::{DateTime, TimeDelta, Utc};
 use chrono_tz::Europe::Zurich;
 use eframe::egui;
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
 use std::sync::mpsc;
 use std::thread;
 
+/// How often expired notes are purged from the trash while logged in.
+const TRASH_PURGE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+/// Shares of the storage quota, in percent, at which the user is warned.
+const QUOTA_WARNING_PERCENTS: [u64; 3] = [80, 90, 100];
+
+/// Shortest time between two quota checks after saving; measuring reads
+/// the size of every file of the user.
+const QUOTA_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Number of status messages kept for the "Recent messages" popover.
+const STATUS_HISTORY_LEN: usize = 20;
+
+/// How often relative times such as "5 minutes ago" are redrawn.
+const RELATIVE_TIME_REFRESH: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// How often background work without its own wake-up is checked on, e.g.
+/// a running integrity scan or the speech engine.
+const BACKGROUND_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// Shortest wait between frames no input asked for, so a timer that is
+/// already due (e.g. an auto-save that keeps failing) doesn't redraw
+/// continuously.
+const MIN_TIMER_DELAY: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// Shortest wait between such frames while the window is minimized.
+const MINIMIZED_TIMER_DELAY: std::time::Duration = std::time::Duration::from_secs(2);
+
 /// Time display format options for the UI.
 #[derive(Clone, Copy, PartialEq)]
 pub enum TimeFormat {
@@ -28,6 +106,21 @@ pub enum TimeFormat {
     Absolute,
 }
 
+/// Progress of the background note loader started after login.
+pub enum NoteLoadEvent {
+    /// The saved search index was decrypted, sent before any note
+    SearchIndex(Box<IndexSnapshot>),
+    /// A note was decrypted and can be shown
+    Loaded(Box<Note>),
+    /// Files in an outdated format or under the old key could not be
+    /// rewritten before loading; holds the files and why
+    UpgradeFailed(Vec<String>),
+    /// All notes were processed; holds the notes that failed to decrypt
+    Finished(Vec<LoadFailure>),
+    /// The notes could not be loaded at all
+    Failed(String),
+}
+
 /// Main application state structure.
 ///
 /// Contains all the state needed for the secure notes application including
@@ -40,6 +133,8 @@ pub struct NotesApp {
     pub selected_note_id: Option<String>,
     /// Cryptographic manager for encryption/decryption
     pub crypto_manager: Option<CryptoManager>,
+    /// The shared vault, unlocked if the current user is a member
+    pub shared_vault: Option<SharedVault>,
     /// Storage manager for file operations
     pub storage_manager: StorageManager,
     /// User management system
@@ -64,28 +159,190 @@ pub struct NotesApp {
     pub authentication_error: Option<String>,
     /// Whether authentication is in progress
     pub is_authenticating: bool,
+    /// Whether the running authentication registers a new account
+    pub is_registering: bool,
     /// Channel receiver for authentication results
     pub auth_receiver: Option<mpsc::Receiver<AuthResult>>,
     /// Start time of current authentication attempt
     pub auth_start_time: Option<std::time::Instant>,
+    /// Stage the running authentication is currently in
+    pub auth_stage: Option<AuthStage>,
+    /// Time the current authentication stage started
+    pub auth_stage_start: Option<std::time::Instant>,
+    /// Crypto manager and user of a login waiting for the second
+    /// passphrase of two-person unlock
+    pub pending_unlock: Option<(Box<CryptoManager>, User)>,
+    /// Second passphrase input field content
+    pub second_passphrase_input: String,
+    /// Channel receiver for notes decrypted in the background after login
+    pub note_loader: Option<mpsc::Receiver<NoteLoadEvent>>,
+    /// IDs of notes whose files could not be decrypted, kept on disk when saving
+    pub unreadable_note_ids: HashSet<String>,
+    /// IDs of private notes deleted this session whose files are removed by
+    /// the next full save
+    pub deleted_note_ids: HashSet<String>,
+    /// Files that could not be upgraded when the notes were loaded, which
+    /// postpone a key derivation upgrade
+    pub upgrade_failures: Vec<String>,
+    /// Why the notes in `unreadable_note_ids` could not be decrypted
+    pub load_failures: Vec<LoadFailure>,
+    /// The dialog offering to restore unreadable notes, None when closed
+    pub recovery_dialog: Option<RecoveryDialog>,
+    /// Whether loading the notes failed entirely, which disables pruning on save
+    pub notes_load_failed: bool,
+    /// Import writing notes in the background, None if no import is running
+    pub import_job: Option<ImportJob>,
+    /// Re-encryption of the vault files, kept until its summary is closed
+    pub reencrypt_job: Option<ReencryptJob>,
+    /// Full-text index narrowing down the notes the search has to scan
+    pub search_index: SearchIndex,
+    /// Random ID of this app instance, recorded in the note locks it holds
+    pub instance_id: String,
+    /// ID of the note this instance holds the edit lock of
+    pub held_note_lock: Option<String>,
+    /// Lock of another instance on the selected note, which is read-only here
+    pub foreign_note_lock: Option<(String, NoteLock)>,
+    /// Last time the lock of the selected note was refreshed or checked
+    pub last_lock_check: Option<std::time::Instant>,
+    /// Watcher of OS screen locks and suspends, started with the first frame
+    pub session_monitor: Option<SessionMonitor>,
+    /// Background check of the system clock, running while enabled
+    pub time_verifier: Option<TimeVerifier>,
+    /// Latest successful check of the system clock
+    pub time_check: Option<TimeCheck>,
+    /// Screen capture protection last applied to the window, None before the first frame
+    pub capture_protection_applied: Option<bool>,
 
     // Note management state
     /// Input field for new note title
     pub new_note_title: String,
-    /// Last time notes were saved
+    /// Type of note the new note dialog creates
+    pub new_note_kind: NoteKind,
+    /// Notebook the new note dialog files the note in, empty for none
+    pub new_note_notebook: String,
+    /// Whether the new note dialog was just opened and focuses its title
+    pub new_note_focus_pending: bool,
+    /// Widget that had the focus before the new note dialog opened, given
+    /// it back when the dialog is closed without creating a note
+    pub new_note_return_focus: Option<egui::Id>,
+    /// Last time notes were saved (or edited, which restarts the auto-save timer)
     pub last_save_time: std::time::Instant,
-    /// Delay before auto-saving
-    pub auto_save_delay: std::time::Duration,
+    /// When the oldest unsaved change was made, for the auto-save's
+    /// longest wait; None while everything is saved
+    pub unsaved_since: Option<std::time::Instant>,
+    /// Whether there are edits that have not been written to disk yet
+    pub has_unsaved_changes: bool,
     /// Whether to show the new note dialog
     pub show_new_note_dialog: bool,
+    /// Input for a new tag in the tag editor
+    pub new_tag_input: String,
+    /// Name input for a new custom field in the properties panel
+    pub new_field_key_input: String,
+    /// Value input for a new custom field in the properties panel
+    pub new_field_value_input: String,
+    /// Text of a new comment in the comments panel
+    pub new_comment_input: String,
+    /// Last time expired notes were purged from the trash, None before login
+    pub last_trash_purge: Option<std::time::Instant>,
+    /// Storage used against the quota when last measured, None without a quota
+    pub quota_usage: Option<QuotaUsage>,
+    /// Last time the storage was measured against the quota
+    pub last_quota_check: Option<std::time::Instant>,
+    /// Highest warning threshold already announced, see `QUOTA_WARNING_PERCENTS`
+    pub quota_warned_percent: u64,
+    /// Integrity scan running in the background, if any
+    pub integrity_scan: Option<IntegrityScan>,
+    /// Report of the last finished integrity scan
+    pub integrity_report: Option<IntegrityReport>,
+    /// When the next integrity scan is due, None while one runs or before
+    /// the notes are loaded
+    pub next_integrity_scan: Option<std::time::Instant>,
+    /// When the file of each note last decrypted successfully, by note ID
+    pub last_decrypted: HashMap<String, DateTime<Utc>>,
+    /// When the user logged in; notes modified since are listed under
+    /// "Edited This Session"
+    pub session_started_at: Option<DateTime<Utc>>,
+    /// Change journal of the private vault, loaded at login
+    pub journal: Option<Journal>,
+    /// Which notes need a revision in their history at the next save
+    pub revision_tracker: RevisionTracker,
+    /// Version history window of a note, open while Some
+    pub note_history_dialog: Option<NoteHistoryDialog>,
+    /// Dialog turning two-person unlock on or off, open while Some
+    pub two_person_dialog: Option<TwoPersonDialog>,
+    /// Text of the Quick Capture window, None while closed
+    pub quick_capture: Option<String>,
+    /// Process Inbox dialog, open while Some
+    pub inbox_dialog: Option<InboxDialog>,
+    /// Vault health check window, open while Some
+    pub health_check: Option<HealthCheckView>,
+    /// Encryption diagnostics of a note, shown in a window while Some
+    pub note_diagnostics: Option<NoteDiagnostics>,
+    /// Secret copied to the clipboard that is cleared after a delay
+    pub pending_clipboard_clear: Option<PendingClear>,
+    /// IDs of the secret entries shown unmasked
+    pub revealed_secrets: HashSet<String>,
+    /// Note being read aloud, if any
+    pub speaker: Option<Speaker>,
+    /// Highlighted layout of the editor text, kept while it doesn't change
+    pub editor_layout_cache: EditorLayoutCache,
+    /// Word count and headings of the selected note
+    pub note_text_stats: Option<NoteTextStats>,
+    /// Parsed Markdown of the selected note, for the preview
+    pub preview_blocks: Option<PreviewBlocks>,
+
+    // Search state
+    /// Query the sidebar note list is filtered by
+    pub search_query: SearchQuery,
+    /// Tag input of the search filters
+    pub search_tags_input: String,
+    /// "Modified from" date input of the search filters
+    pub search_from_input: String,
+    /// "Modified to" date input of the search filters
+    pub search_to_input: String,
+    /// Whether the tag and date filters are expanded
+    pub show_search_filters: bool,
+    /// Index of the smart filter whose query is currently applied
+    pub active_smart_filter: Option<usize>,
+    /// Name input for saving the current search as a smart filter
+    pub smart_filter_name_input: String,
+    /// Whether the search results window is open
+    pub show_search_results: bool,
+    /// Index of the highlighted snippet in the search results window
+    pub search_result_selection: usize,
+    /// Whether the search results window lists trashed notes too
+    pub search_include_trash: bool,
+    /// Note and byte range of its content the editor selects and scrolls to
+    /// the next time it shows that note
+    pub editor_jump: Option<(String, std::ops::Range<usize>)>,
+    /// Current vertical scroll position of the editor
+    pub editor_scroll_offset: f32,
+    /// Scroll position the editor jumps to the next time it is shown
+    pub editor_scroll_restore: Option<f32>,
+    /// Scroll position the Markdown preview jumps to the next time it is shown
+    pub preview_scroll_restore: Option<f32>,
+    /// Editor and preview scroll positions at the end of the last split
+    /// view frame, to tell which of them the user scrolled
+    pub split_scroll_offsets: Option<(f32, f32)>,
+    /// Session of the last logout, restored once its note has been loaded
+    pub pending_session_restore: Option<LastSession>,
+    /// Notes requested on the command line, created once the notes of the
+    /// first login are loaded
+    pub launch_request: LaunchRequest,
 
     // UI state
     /// Whether to show the security information panel
     pub show_security_panel: bool,
     /// List of current security warnings
     pub security_warnings: Vec<String>,
+    /// Recent login attempts of the current user, oldest first
+    pub login_history: Vec<LoginRecord>,
     /// Current time display format
     pub show_time_format: TimeFormat,
+    /// Whether the window is kept above other windows
+    pub always_on_top: bool,
+    /// Preferences of the current user (persisted encrypted)
+    pub settings: UserSettings,
 
     // Context menu state
     /// Note ID for which context menu is shown
@@ -98,10 +355,22 @@ pub struct NotesApp {
     // User settings state
     /// Whether to show user settings dialog
     pub show_user_settings: bool,
+    /// Storage usage shown in the settings window, measured when it opens
+    pub storage_report: Option<StorageReport>,
     /// Whether to show change password dialog
     pub show_change_password_dialog: bool,
+    /// Why the last password change failed, shown in the dialog
+    pub password_change_error: Option<String>,
     /// Whether to show delete account dialog
     pub show_delete_account_dialog: bool,
+    /// Whether to show the log viewer window
+    pub show_log_viewer: bool,
+    /// Minimum severity shown in the log viewer
+    pub log_viewer_level: LogLevel,
+    /// Whether to show the startup profile window
+    pub show_startup_profile: bool,
+    /// Whether the first frame was drawn, which is profiled
+    pub first_frame_rendered: bool,
     /// Old password input for password change
     pub old_password_input: String,
     /// New password input for password change
@@ -110,12 +379,79 @@ pub struct NotesApp {
     pub confirm_new_password_input: String,
     /// Confirmation input for account deletion
     pub delete_confirmation_input: String,
+    /// Username of the user to add to the shared vault
+    pub shared_vault_username_input: String,
+    /// Password of the user to add to the shared vault, typed in by them
+    pub shared_vault_password_input: String,
+    /// Whether to show the machine migration wizard
+    pub show_migration_wizard: bool,
+    /// State of the machine migration wizard
+    pub migration_wizard: MigrationWizard,
+    /// Pending legacy notes migration, shown as a dialog after login
+    pub legacy_migration: Option<LegacyMigration>,
+    /// Whether to show the read-only snapshot viewer
+    pub show_snapshot_viewer: bool,
+    /// State of the snapshot viewer, holding the opened bundle
+    pub snapshot_viewer: SnapshotViewer,
+    /// Dialog for copying a note as encrypted text or pasting one, if open
+    pub note_share_dialog: Option<NoteShareDialog>,
+    /// The "Send via Email" dialog, None while closed
+    pub note_email_dialog: Option<NoteEmailDialog>,
+    /// Settings export/import dialog, shown while Some
+    pub settings_transfer_dialog: Option<SettingsTransferDialog>,
+    /// Dialog for renaming a note, if open
+    pub rename_dialog: Option<RenameDialog>,
+    /// Title being edited in the note header, if any
+    pub title_edit: Option<TitleEdit>,
+    /// Links rewritten by the last rename, shown until closed
+    pub rename_report: Option<RenameReport>,
+    /// Link graph window, shown while Some
+    pub link_graph_view: Option<LinkGraphView>,
+    /// The statistics window, None while closed
+    pub note_stats_view: Option<NoteStatsView>,
+    /// The "Publish as Website" dialog, None while closed
+    pub site_export_dialog: Option<SiteExportDialog>,
+    /// Decrypted attachment shown in the note view, if any
+    pub attachment_preview: Option<AttachmentPreview>,
+
+    // Crash recovery
+    /// Crash reports from previous sessions that the user has not seen yet
+    pub crash_reports: Vec<CrashReport>,
+    /// Whether to show the crash recovery dialog
+    pub show_crash_dialog: bool,
 
     // Status and messaging
     /// Current status message to display
     pub status_message: Option<String>,
     /// Time when status message was set
     pub status_message_time: Option<std::time::Instant>,
+    /// Folder holding the file the status message reports as written,
+    /// offered to open in the file manager
+    pub status_folder: Option<PathBuf>,
+    /// Recent status messages, newest first, cleared at logout
+    pub status_history: VecDeque<StatusEntry>,
+    /// Export hook commands still running, see the export_hook module
+    pub export_hooks: Vec<RunningHook>,
+}
+
+/// A status message shown earlier, kept for review.
+pub struct StatusEntry {
+    /// When the message was shown
+    pub time: DateTime<Utc>,
+    /// The message
+    pub message: String,
+    /// Folder holding the file the message reports as written
+    pub folder: Option<PathBuf>,
+}
+
+impl StatusEntry {
+    /// Returns the time of the message in Swiss time, e.g. "14:30:05".
+    pub fn format_time(&self) -> String {
+        self.time
+            .with_timezone(&Zurich)
+            .format("%H:%M:%S")
+            .to_string()
+    }
 }
 
 impl NotesApp {
@@ -129,12 +465,22 @@ impl NotesApp {
     ///
     /// * `Self` - A new NotesApp instance
     pub fn new() -> Self {
+        let started = std::time::Instant::now();
         let user_manager = UserManager::new().ok();
+        profiling::record("Load users", started);
+        let crash_reports = crash::pending_reports();
+        if !crash_reports.is_empty() {
+            log::warn!(
+                "Found {} crash report(s) from previous sessions",
+                crash_reports.len()
+            );
+        }
 
         Self {
             notes: HashMap::new(),
             selected_note_id: None,
             crypto_manager: None,
+            shared_vault: None,
             storage_manager: StorageManager::new(),
             user_manager,
             current_user: None,
@@ -147,32 +493,137 @@ impl NotesApp {
             auth_mode: AuthMode::Login,
             authentication_error: None,
             is_authenticating: false,
+            is_registering: false,
             auth_receiver: None,
             auth_start_time: None,
+            auth_stage: None,
+            auth_stage_start: None,
+            pending_unlock: None,
+            second_passphrase_input: String::new(),
+            note_loader: None,
+            unreadable_note_ids: HashSet::new(),
+            deleted_note_ids: HashSet::new(),
+            upgrade_failures: Vec::new(),
+            load_failures: Vec::new(),
+            recovery_dialog: None,
+            notes_load_failed: false,
+            import_job: None,
+            reencrypt_job: None,
+            search_index: SearchIndex::spawn(),
+            instance_id: uuid::Uuid::new_v4().to_string(),
+            held_note_lock: None,
+            foreign_note_lock: None,
+            last_lock_check: None,
+            session_monitor: None,
+            time_verifier: None,
+            time_check: None,
+            capture_protection_applied: None,
 
             new_note_title: String::new(),
+            new_note_kind: NoteKind::Text,
+            new_note_notebook: String::new(),
+            new_note_focus_pending: false,
+            new_note_return_focus: None,
             last_save_time: std::time::Instant::now(),
-            auto_save_delay: std::time::Duration::from_secs(2),
+            unsaved_since: None,
+            has_unsaved_changes: false,
             show_new_note_dialog: false,
+            new_tag_input: String::new(),
+            new_field_key_input: String::new(),
+            new_field_value_input: String::new(),
+            new_comment_input: String::new(),
+            last_trash_purge: None,
+            quota_usage: None,
+            last_quota_check: None,
+            quota_warned_percent: 0,
+            integrity_scan: None,
+            integrity_report: None,
+            next_integrity_scan: None,
+            last_decrypted: HashMap::new(),
+            session_started_at: None,
+            journal: None,
+            revision_tracker: RevisionTracker::default(),
+            note_history_dialog: None,
+            two_person_dialog: None,
+            quick_capture: None,
+            inbox_dialog: None,
+            health_check: None,
+            note_diagnostics: None,
+            pending_clipboard_clear: None,
+            revealed_secrets: HashSet::new(),
+            speaker: None,
+            editor_layout_cache: EditorLayoutCache::default(),
+            note_text_stats: None,
+            preview_blocks: None,
+
+            search_query: SearchQuery::default(),
+            search_tags_input: String::new(),
+            search_from_input: String::new(),
+            search_to_input: String::new(),
+            show_search_filters: false,
+            active_smart_filter: None,
+            smart_filter_name_input: String::new(),
+            show_search_results: false,
+            search_result_selection: 0,
+            search_include_trash: false,
+            editor_jump: None,
+            editor_scroll_offset: 0.0,
+            editor_scroll_restore: None,
+            preview_scroll_restore: None,
+            split_scroll_offsets: None,
+            pending_session_restore: None,
+            launch_request: LaunchRequest::default(),
 
             show_security_panel: false,
             security_warnings: Vec::new(),
+            login_history: Vec::new(),
             show_time_format: TimeFormat::Relative,
+            always_on_top: false,
+            settings: UserSettings::default(),
 
             context_menu_note_id: None,
             show_context_menu: false,
             context_menu_pos: egui::Pos2::ZERO,
 
             show_user_settings: false,
+            storage_report: None,
             show_change_password_dialog: false,
+            password_change_error: None,
             show_delete_account_dialog: false,
+            show_log_viewer: false,
+            log_viewer_level: LogLevel::Trace,
+            show_startup_profile: false,
+            first_frame_rendered: false,
             old_password_input: String::new(),
             new_password_input: String::new(),
             confirm_new_password_input: String::new(),
             delete_confirmation_input: String::new(),
+            shared_vault_username_input: String::new(),
+            shared_vault_password_input: String::new(),
+            show_migration_wizard: false,
+            migration_wizard: MigrationWizard::default(),
+            legacy_migration: None,
+            show_snapshot_viewer: false,
+            snapshot_viewer: SnapshotViewer::default(),
+            note_share_dialog: None,
+            note_email_dialog: None,
+            rename_dialog: None,
+            title_edit: None,
+            rename_report: None,
+            link_graph_view: None,
+            note_stats_view: None,
+            site_export_dialog: None,
+            attachment_preview: None,
+            settings_transfer_dialog: None,
+
+            show_crash_dialog: !crash_reports.is_empty(),
+            crash_reports,
 
             status_message: None,
             status_message_time: None,
+            status_folder: None,
+            status_history: VecDeque::new(),
+            export_hooks: Vec::new(),
         }
     }
 
@@ -198,6 +649,7 @@ impl NotesApp {
         }
 
         self.is_authenticating = true;
+        self.is_registering = is_registration;
         self.authentication_error = None;
         self.auth_start_time = Some(std::time::Instant::now());
 
@@ -208,34 +660,32 @@ impl NotesApp {
 
         // Spawn background thread for authentication
         thread::spawn(move || {
-            println!("Starting authentication in background thread...");
+            log::debug!("Starting authentication in background thread");
+            let started = std::time::Instant::now();
+
+            let report = |stage: AuthStage| {
+                let _ = sender.send(AuthResult::Progress(stage));
+            };
 
             if let Some(mut user_manager) = user_manager {
                 let result = if is_registration {
                     // Registration flow
+                    report(AuthStage::CreatingAccount);
                     match user_manager.create_user(username.clone(), &password) {
                         Ok(_) => {
-                            println!("User created successfully, now authenticating...");
+                            log::info!("User account created, authenticating");
                             // After successful registration, authenticate the user
-                            match user_manager.authenticate(&username, &password) {
+                            report(AuthStage::VerifyingPassword);
+                            match verify_password(&user_manager, &username, &password) {
                                 Ok(user) => {
-                                    let mut crypto_manager = CryptoManager::new();
-                                    match crypto_manager.initialize_for_user(&user.id, &password) {
-                                        Ok(_) => {
-                                            println!("Registration and authentication successful!");
-                                            AuthResult::Success(crypto_manager, user)
-                                        }
-                                        Err(e) => {
-                                            println!("Crypto initialization failed: {}", e);
-                                            AuthResult::Error(format!(
-                                                "Crypto initialization failed: {}",
-                                                e
-                                            ))
-                                        }
+                                    let result = unlock_user_session(user, &password, &report);
+                                    if matches!(result, AuthResult::Success(..)) {
+                                        log::info!("Registration and authentication successful");
                                     }
+                                    result
                                 }
                                 Err(e) => {
-                                    println!("Authentication after registration failed: {}", e);
+                                    log::error!("Authentication after registration failed: {}", e);
                                     AuthResult::Error(format!(
                                         "Authentication after registration failed: {}",
                                         e
@@ -244,36 +694,35 @@ impl NotesApp {
                             }
                         }
                         Err(e) => {
-                            println!("Registration failed: {}", e);
+                            log::warn!("Registration failed: {}", e);
                             AuthResult::Error(format!("Registration failed: {}", e))
                         }
                     }
                 } else {
                     // Login flow
-                    match user_manager.authenticate(&username, &password) {
+                    report(AuthStage::VerifyingPassword);
+                    match verify_password(&user_manager, &username, &password) {
                         Ok(user) => {
-                            println!("User authenticated, initializing crypto...");
-                            let mut crypto_manager = CryptoManager::new();
-                            match crypto_manager.initialize_for_user(&user.id, &password) {
-                                Ok(_) => {
-                                    println!("Login successful!");
-                                    AuthResult::Success(crypto_manager, user)
-                                }
-                                Err(e) => {
-                                    println!("Crypto initialization failed: {}", e);
-                                    AuthResult::Error(format!("Authentication failed: {}", e))
-                                }
+                            log::debug!("User authenticated, initializing crypto");
+                            let result = unlock_user_session(user, &password, &report);
+                            if matches!(result, AuthResult::Success(..)) {
+                                log::info!("Login successful");
                             }
+                            result
                         }
                         Err(e) => {
-                            println!("Login failed: {}", e);
+                            log::warn!("Login failed: {}", e);
+                            if let Some(user_id) = user_manager.user_id_of(&username) {
+                                record_login(user_id, LoginOutcome::WrongPassword, None);
+                            }
                             AuthResult::Error(format!("Login failed: {}", e))
                         }
                     }
                 };
 
-                if let Err(_) = sender.send(result) {
-                    println!("Failed to send authentication result - UI may have closed");
+                profiling::record("Authentication", started);
+                if sender.send(result).is_err() {
+                    log::warn!("Failed to send authentication result - UI may have closed");
                 }
             } else {
                 let _ = sender.send(AuthResult::Error("User manager not available".to_string()));
@@ -287,20 +736,40 @@ impl NotesApp {
     /// to check if the background authentication process has completed
     /// and handle the results appropriately.
     pub fn check_authentication_result(&mut self) {
-        if let Some(receiver) = &self.auth_receiver {
+        while let Some(receiver) = &self.auth_receiver {
             match receiver.try_recv() {
+                Ok(AuthResult::Progress(stage)) => {
+                    self.auth_stage = Some(stage);
+                    self.auth_stage_start = Some(std::time::Instant::now());
+                }
+                Ok(AuthResult::SecondPassphrase(crypto_manager, user)) => {
+                    // The password opened the first key share only
+                    self.pending_unlock = Some((crypto_manager, user));
+                    self.password_input.clear();
+                    self.confirm_password_input.clear();
+                    self.finish_authentication();
+                }
                 Ok(AuthResult::Success(crypto_manager, user)) => {
                     if let Some(start_time) = self.auth_start_time {
-                        println!(
+                        log::info!(
                             "Authentication completed in {:.2}s",
                             start_time.elapsed().as_secs_f64()
                         );
                     }
 
-                    self.crypto_manager = Some(crypto_manager);
+                    self.crypto_manager = Some(*crypto_manager);
                     self.current_user = Some(user);
-                    self.load_notes();
-                    self.migrate_legacy_data_if_needed();
+                    self.load_settings();
+                    self.load_journal();
+                    self.open_shared_vault();
+                    self.pending_session_restore = Some(self.settings.last_session.clone())
+                        .filter(|session| session.note_id.is_some());
+                    self.start_note_loading();
+                    if self.is_registering {
+                        self.add_sample_notes();
+                    }
+                    // After the sample notes, which aren't the user's edits
+                    self.session_started_at = Some(trusted_time::now());
 
                     // Perform security audit
                     if let Some(ref crypto) = self.crypto_manager {
@@ -308,12 +777,11 @@ impl NotesApp {
                             self.security_warnings = warnings;
                         }
                     }
+                    self.load_login_history();
 
                     self.is_authenticated = true;
                     self.show_auth_dialog = false;
-                    self.is_authenticating = false;
-                    self.auth_receiver = None;
-                    self.auth_start_time = None;
+                    self.finish_authentication();
 
                     // Clear input fields
                     self.username_input.clear();
@@ -322,293 +790,2339 @@ impl NotesApp {
                 }
                 Ok(AuthResult::Error(error)) => {
                     self.authentication_error = Some(error);
-                    self.is_authenticating = false;
-                    self.auth_receiver = None;
-                    self.auth_start_time = None;
+                    self.finish_authentication();
                 }
                 Err(mpsc::TryRecvError::Empty) => {
                     // Still waiting for result
+                    break;
                 }
                 Err(mpsc::TryRecvError::Disconnected) => {
                     self.authentication_error = Some("Authentication process failed".to_string());
-                    self.is_authenticating = false;
-                    self.auth_receiver = None;
-                    self.auth_start_time = None;
+                    self.finish_authentication();
                 }
             }
         }
     }
 
-    /// Loads notes from storage for the current user.
+    /// Loads the login history of the current user after login.
     ///
-    /// Attempts to load encrypted notes from the user's storage directory.
-    /// If loading fails, an error is logged but the application continues
-    /// with an empty note set.
-    pub fn load_notes(&mut self) {
-        if let (Some(ref crypto_manager), Some(ref user)) =
-            (&self.crypto_manager, &self.current_user)
-        {
-            match self
-                .storage_manager
-                .load_user_notes(&user.id, crypto_manager)
-            {
-                Ok(notes) => {
-                    self.notes = notes;
-                    println!(
-                        "Loaded {} notes for user {}",
-                        self.notes.len(),
-                        user.username
-                    );
-                }
-                Err(e) => {
-                    eprintln!("Failed to load notes: {}", e);
-                }
-            }
+    /// Failed attempts since the previous login are added to the security
+    /// warnings and announced in the status bar.
+    fn load_login_history(&mut self) {
+        let Some(user) = &self.current_user else {
+            return;
+        };
+        self.login_history = self.storage_manager.load_login_history(&user.id);
+
+        let failed = login_history::failed_since_last_login(&self.login_history);
+        if failed > 0 {
+            let warning = format!(
+                "{} failed login {} since your last login, see Security Information",
+                failed,
+                if failed == 1 { "attempt" } else { "attempts" }
+            );
+            self.security_warnings.push(warning.clone());
+            self.show_status(warning);
         }
     }
 
+    /// Resets the state tracking a running authentication.
+    fn finish_authentication(&mut self) {
+        self.is_authenticating = false;
+        self.is_registering = false;
+        self.auth_receiver = None;
+        self.auth_start_time = None;
+        self.auth_stage = None;
+        self.auth_stage_start = None;
+    }
+
     /// Saves all notes to encrypted storage.
     ///
     /// Encrypts and saves all current notes to the user's storage directory.
-    /// If saving fails, an error is logged but the application continues.
-    pub fn save_notes(&self) {
+    /// Clears the unsaved-changes flag on success. If saving fails, an error
+    /// is logged but the application continues.
+    ///
+    /// While a `ReencryptJob` rewrites the files, nothing is written and
+    /// the unsaved-changes flag stays set, so the notes are saved once it
+    /// finished.
+    pub fn save_notes(&mut self) {
+        if self.reencrypt_job.as_ref().is_some_and(|job| !job.finished) {
+            return;
+        }
         if let (Some(ref crypto_manager), Some(ref user)) =
             (&self.crypto_manager, &self.current_user)
         {
-            if let Err(e) =
-                self.storage_manager
-                    .save_user_notes(&user.id, &self.notes, crypto_manager)
-            {
-                eprintln!("Failed to save notes: {}", e);
+            // Notes being edited elsewhere may be newer on disk than here
+            let locked = self
+                .storage_manager
+                .notes_locked_elsewhere(&user.id, &self.instance_id);
+            let private_notes = private_notes(&self.notes);
+
+            // While notes are still loading (or loading failed) or an import
+            // is writing notes, the in-memory set is incomplete, so only write
+            // it without pruning missing notes
+            let incomplete =
+                self.note_loader.is_some() || self.notes_load_failed || self.import_job.is_some();
+
+            // Keep the versions about to be overwritten, see note_history
+            self.revision_tracker.record_changed(
+                &self.storage_manager,
+                &user.id,
+                &private_notes,
+                &locked,
+                crypto_manager,
+            );
+
+            let result = if incomplete {
+                self.storage_manager.write_user_notes(
+                    &user.id,
+                    &private_notes,
+                    &locked,
+                    crypto_manager,
+                )
+            } else {
+                self.storage_manager.save_user_notes(
+                    &user.id,
+                    &private_notes,
+                    &self.deleted_note_ids,
+                    &locked,
+                    crypto_manager,
+                )
+            };
+
+            // Shared notes only ever get written, see the shared_vault module
+            let shared_result = match &mut self.shared_vault {
+                Some(vault) => {
+                    let locked = self
+                        .storage_manager
+                        .notes_locked_elsewhere(SHARED_VAULT_OWNER, &self.instance_id);
+                    vault.save_notes(
+                        &self.storage_manager,
+                        self.notes.values().filter(|note| note.shared),
+                        &locked,
+                    )
+                }
+                None => Ok(()),
+            };
+
+            // Deleted notes were only pruned if the save was complete; notes
+            // locked elsewhere were kept and are pruned by a later save
+            let pruned: HashSet<String> = if incomplete {
+                HashSet::new()
+            } else {
+                self.deleted_note_ids
+                    .iter()
+                    .filter(|id| !locked.contains(*id))
+                    .cloned()
+                    .collect()
+            };
+
+            if result.is_ok() {
+                self.revision_tracker.notes_saved(&private_notes, &locked);
+                self.deleted_note_ids.retain(|id| !pruned.contains(id));
+            }
+
+            // Journal what this save changed, once the private notes are written
+            if let (Ok(_), Some(journal)) = (&result, &mut self.journal) {
+                let recorded = journal.record_saved(&private_notes, &pruned);
+                if recorded > 0 {
+                    if let Err(e) =
+                        self.storage_manager
+                            .save_journal(&user.id, journal, crypto_manager)
+                    {
+                        log::error!("Failed to save the change journal: {}", e);
+                    }
+                }
+            }
+
+            self.search_index.save(&user.id, crypto_manager);
+
+            match result.and(shared_result) {
+                Ok(_) => {
+                    self.has_unsaved_changes = false;
+                    self.last_save_time = std::time::Instant::now();
+                    self.check_storage_quota(false);
+                }
+                Err(e) => {
+                    log::error!("Failed to save notes: {}", e);
+                }
             }
         }
     }
 
-    /// Creates a new note with the given title.
+    /// Measures the user's files against the storage quota and warns once
+    /// per threshold in `QUOTA_WARNING_PERCENTS` that is crossed.
     ///
-    /// Creates a new note, adds it to the notes collection, selects it
-    /// for editing, and saves the updated notes to storage.
+    /// A threshold is announced again after usage dropped below it.
     ///
     /// # Arguments
     ///
-    /// * `title` - The title for the new note. If empty, defaults to "Untitled Note"
-    pub fn create_new_note(&mut self, title: String) {
-        let final_title = if title.trim().is_empty() {
-            "Untitled Note".to_string()
-        } else {
-            title
+    /// * `force` - Measure even if the last check was less than
+    ///   `QUOTA_CHECK_INTERVAL` ago, e.g. after attaching files
+    pub fn check_storage_quota(&mut self, force: bool) {
+        let Some(user) = &self.current_user else {
+            return;
         };
+        if !force
+            && self
+                .last_quota_check
+                .is_some_and(|time| time.elapsed() < QUOTA_CHECK_INTERVAL)
+        {
+            return;
+        }
+        self.last_quota_check = Some(std::time::Instant::now());
+        self.quota_usage = self
+            .storage_manager
+            .quota_usage(&user.id, self.settings.storage_quota());
 
-        let note = Note::new(final_title);
-        let note_id = note.id.clone();
-        self.notes.insert(note_id.clone(), note);
-        self.selected_note_id = Some(note_id);
-        self.save_notes();
+        let Some(usage) = self.quota_usage else {
+            self.quota_warned_percent = 0;
+            return;
+        };
+        let reached = QUOTA_WARNING_PERCENTS
+            .into_iter()
+            .filter(|&threshold| usage.percent() >= threshold)
+            .max()
+            .unwrap_or(0);
+        if reached > self.quota_warned_percent {
+            log::warn!("Storage quota {}% used", usage.percent());
+            self.show_status(if usage.exceeded() {
+                format!(
+                    "Storage quota of {} exceeded, no more files can be attached",
+                    storage_report::format_size(usage.quota_bytes)
+                )
+            } else {
+                format!(
+                    "Storage {}% full: {} of {} used",
+                    usage.percent(),
+                    storage_report::format_size(usage.used_bytes),
+                    storage_report::format_size(usage.quota_bytes)
+                )
+            });
+        }
+        self.quota_warned_percent = reached;
     }
 
-    /// Deletes a note by its ID.
+    /// Re-encrypts all data with the upgraded key after login, if needed.
     ///
-    /// Removes the note from the collection, deselects it if it was selected,
-    /// and saves the updated notes to storage.
+    /// When the key derivation defaults were raised since the user's key
+    /// was derived, the crypto manager logs in with a stronger key but can
+    /// still decrypt with the old one. Attachments and history are rewritten
+    /// by `StorageManager::upgrade_user_files` before loading. Once every
+    /// note is loaded, notes and settings are written again with the new
+    /// key, the shared vault key is wrapped with it, and the upgrade is
+    /// recorded. If some notes could not be decrypted or any file fails to
+    /// be rewritten, the upgrade is postponed so nothing is lost with the
+    /// old key.
     ///
     /// # Arguments
     ///
-    /// * `note_id` - The ID of the note to delete
-    pub fn delete_note(&mut self, note_id: &str) {
-        if let Some(note) = self.notes.get(note_id) {
-            println!("Deleting note: {}", note.title);
+    /// * `failed_notes` - Number of notes the loader could not decrypt
+    fn finish_kdf_upgrade_if_pending(&mut self, failed_notes: usize) {
+        if !self
+            .crypto_manager
+            .as_ref()
+            .is_some_and(|crypto| crypto.kdf_upgrade_pending())
+        {
+            return;
         }
 
-        self.notes.remove(note_id);
-
-        if self.selected_note_id.as_ref() == Some(&note_id.to_string()) {
-            self.selected_note_id = None;
+        if failed_notes > 0 {
+            log::warn!("Postponing key derivation upgrade, some notes could not be decrypted");
+            return;
+        }
+        // Those files are still under the old key only
+        if !self.upgrade_failures.is_empty() {
+            log::warn!(
+                "Postponing key derivation upgrade, {} file(s) could not be rewritten",
+                self.upgrade_failures.len()
+            );
+            return;
         }
 
-        self.save_notes();
-    }
+        let (Some(crypto_manager), Some(user)) = (&mut self.crypto_manager, &self.current_user)
+        else {
+            return;
+        };
 
-    /// Performs auto-save if enough time has elapsed since the last save.
-    ///
-    /// Checks if the auto-save delay has passed and saves notes if needed.
-    /// This helps prevent data loss without constantly writing to disk.
-    pub fn auto_save_if_needed(&mut self) {
-        if self.last_save_time.elapsed() >= self.auto_save_delay {
-            self.save_notes();
-            self.last_save_time = std::time::Instant::now();
+        let result = self
+            .storage_manager
+            .save_user_notes(
+                &user.id,
+                &private_notes(&self.notes),
+                &self.deleted_note_ids,
+                // Every note must be re-encrypted before the old key is dropped
+                &HashSet::new(),
+                crypto_manager,
+            )
+            .and_then(|_| {
+                self.storage_manager
+                    .save_user_settings(&user.id, &self.settings, crypto_manager)
+            })
+            .and_then(|_| match &self.journal {
+                Some(journal) => {
+                    self.storage_manager
+                        .save_journal(&user.id, journal, crypto_manager)
+                }
+                None => Ok(()),
+            })
+            .and_then(|_| match &self.shared_vault {
+                Some(vault) => vault
+                    .add_member(&self.storage_manager, &user.id, crypto_manager)
+                    .map_err(|e| anyhow::anyhow!("Could not re-wrap the shared vault key: {}", e)),
+                // The key is wrapped with the old key, which must not be dropped
+                None if SharedVault::member_ids(&self.storage_manager).contains(&user.id) => Err(
+                    anyhow::anyhow!("The shared vault is locked, its key can't be re-wrapped"),
+                ),
+                None => Ok(()),
+            })
+            .and_then(|_| {
+                self.search_index.rewrite(&user.id, crypto_manager);
+                crypto_manager.complete_kdf_upgrade(&user.id)
+            });
+
+        match result {
+            Ok(_) => {
+                self.has_unsaved_changes = false;
+                self.last_save_time = std::time::Instant::now();
+            }
+            Err(e) => log::error!(
+                "Failed to upgrade key derivation, retrying at the next login: {}",
+                e
+            ),
         }
     }
 
-    /// Gets the current time formatted for display in Swiss timezone.
+    /// Stops accepting older ciphertext formats once none are left.
     ///
-    /// # Returns
+    /// Note, history and attachment files are upgraded by
+    /// `StorageManager::upgrade_user_files` and settings by `load_settings`.
+    /// Only when all of them succeeded and no file in an older format
+    /// remains is the change recorded, since those files could never be
+    /// read again afterwards.
     ///
-    /// * `String` - Current time in "DD.MM.YYYY HH:MM:SS" format
-    pub fn get_current_time(&self) -> String {
-        let now = Utc::now().with_timezone(&Zurich);
-        now.format("%d.%m.%Y %H:%M:%S").to_string()
+    /// # Arguments
+    ///
+    /// * `failed_notes` - Number of notes the loader could not decrypt
+    fn retire_legacy_formats_if_done(&mut self, failed_notes: usize) {
+        let (Some(crypto_manager), Some(user)) = (&mut self.crypto_manager, &self.current_user)
+        else {
+            return;
+        };
+        if !crypto_manager.legacy_formats_accepted()
+            || failed_notes > 0
+            || !self.upgrade_failures.is_empty()
+        {
+            return;
+        }
+
+        let result = self
+            .storage_manager
+            .count_outdated_files(&user.id)
+            .and_then(|outdated| match outdated {
+                0 => crypto_manager.retire_legacy_formats(&user.id),
+                _ => {
+                    log::info!(
+                        "{} file(s) still in an older ciphertext format, accepting it",
+                        outdated
+                    );
+                    Ok(())
+                }
+            });
+        if let Err(e) = result {
+            log::warn!("Could not retire older ciphertext formats: {}", e);
+        }
     }
 
-    /// Logs out the current user and resets application state.
+    /// Writes the current user's settings, journal and search index again
+    /// with the current key and wraps the shared vault key with it.
     ///
-    /// Clears all user-specific data, resets UI state, and returns
-    /// to the authentication dialog. This ensures no sensitive data
-    /// remains in memory after logout.
-    pub fn logout(&mut self) {
-        println!("User logging out");
-        self.is_authenticated = false;
-        self.show_auth_dialog = true;
-        self.crypto_manager = None;
-        self.current_user = None;
-        self.notes.clear();
-        self.selected_note_id = None;
-        self.username_input.clear();
-        self.password_input.clear();
-        self.confirm_password_input.clear();
-        self.authentication_error = None;
-        self.auth_mode = AuthMode::Login;
-        self.security_warnings.clear();
+    /// Completes a key change after the note and attachment files were
+    /// rewritten by a `ReencryptJob`.
+    ///
+    /// # Returns
+    ///
+    /// * `anyhow::Result<()>` - Ok if everything was rewritten, Err naming
+    ///   what failed
+    pub fn reencrypt_user_state(&self) -> anyhow::Result<()> {
+        let (Some(crypto_manager), Some(user)) = (&self.crypto_manager, &self.current_user) else {
+            return Err(anyhow::anyhow!("Not logged in"));
+        };
 
-        // Clear settings dialogs
-        self.show_user_settings = false;
-        self.show_change_password_dialog = false;
-        self.show_delete_account_dialog = false;
-        self.old_password_input.clear();
-        self.new_password_input.clear();
-        self.confirm_new_password_input.clear();
-        self.delete_confirmation_input.clear();
+        self.storage_manager
+            .save_user_settings(&user.id, &self.settings, crypto_manager)
+            .map_err(|e| anyhow::anyhow!("Could not re-encrypt the settings: {}", e))?;
+        if let Some(journal) = &self.journal {
+            self.storage_manager
+                .save_journal(&user.id, journal, crypto_manager)
+                .map_err(|e| anyhow::anyhow!("Could not re-encrypt the change journal: {}", e))?;
+        }
+        // Only a cache, rebuilt at the next login if this write fails
+        self.search_index.rewrite(&user.id, crypto_manager);
+        if let Some(vault) = &self.shared_vault {
+            vault
+                .add_member(&self.storage_manager, &user.id, crypto_manager)
+                .map_err(|e| anyhow::anyhow!("Could not re-wrap the shared vault key: {}", e))?;
+        }
+        Ok(())
     }
 
-    /// Migrates legacy data from old storage format if needed.
+    /// Loads the current user's preferences from encrypted storage.
     ///
-    /// Checks for notes stored in the old format (before user-specific storage)
-    /// and migrates them to the current user's storage directory.
-    pub fn migrate_legacy_data_if_needed(&mut self) {
-        if let (Some(ref user), Some(ref crypto_manager)) =
-            (&self.current_user, &self.crypto_manager)
+    /// Falls back to the default settings if the file is missing or cannot
+    /// be decrypted, so a damaged settings file never blocks the login.
+    /// Settings stored in an outdated ciphertext format are saved again.
+    pub fn load_settings(&mut self) {
+        let mut outdated = false;
+        if let (Some(ref crypto_manager), Some(ref user)) =
+            (&self.crypto_manager, &self.current_user)
         {
-            if let Err(e) = self
+            self.settings = match self
                 .storage_manager
-                .migrate_legacy_notes(&user.id, crypto_manager)
+                .load_user_settings(&user.id, crypto_manager)
             {
-                eprintln!("Failed to migrate legacy notes: {}", e);
+                Ok(settings) => {
+                    outdated = self
+                        .storage_manager
+                        .user_settings_outdated(&user.id, crypto_manager);
+                    settings
+                }
+                Err(e) => {
+                    log::warn!("Failed to load settings, using defaults: {}", e);
+                    UserSettings::default()
+                }
+            };
+            // 12-byte-nonce ChaCha20Poly1305 is decrypt-only now, move to XChaCha20
+            if self.settings.encryption_algorithm == AeadAlgorithm::ChaCha20Poly1305 {
+                self.settings.encryption_algorithm = AeadAlgorithm::XChaCha20Poly1305;
             }
+            logging::set_level(self.settings.log_level);
+        }
+
+        if let Some(ref mut crypto_manager) = self.crypto_manager {
+            crypto_manager.set_algorithm(self.settings.encryption_algorithm);
+        }
+        if outdated {
+            log::info!("Upgrading settings to the current format");
+            self.save_settings();
         }
     }
 
-    /// Exports a note to a text file.
+    /// Loads the change journal of the current user.
     ///
-    /// Opens a file dialog for the user to choose where to save the note,
-    /// then writes the note content along with metadata to the selected file.
+    /// A journal that cannot be decrypted is replaced by a new one, which
+    /// records every note again on the next save; sync backends then fall
+    /// back to comparing whole files once.
+    fn load_journal(&mut self) {
+        let (Some(crypto_manager), Some(user)) = (&self.crypto_manager, &self.current_user) else {
+            return;
+        };
+        self.journal = Some(
+            match self.storage_manager.load_journal(&user.id, crypto_manager) {
+                Ok(Some(journal)) => journal,
+                Ok(None) => Journal::new(),
+                Err(e) => {
+                    log::warn!(
+                        "Failed to load the change journal, starting a new one: {}",
+                        e
+                    );
+                    Journal::new()
+                }
+            },
+        );
+    }
+
+    /// Shows a message at the top of the main content and records it in
+    /// the recent messages.
+    ///
+    /// The message is hidden after the duration set in the settings.
     ///
     /// # Arguments
     ///
-    /// * `note_id` - The ID of the note to export
-    pub fn export_note_to_file(&self, note_id: &str) {
-        if let Some(note) = self.notes.get(note_id) {
-            // Create default filename from note title
-            let safe_title = note
-                .title
-                .chars()
-                .map(|c| {
-                    if c.is_alphanumeric() || c == ' ' || c == '-' || c == '_' {
-                        c
-                    } else {
-                        '_'
-                    }
-                })
-                .collect::<String>()
-                .trim()
-                .to_string();
+    /// * `message` - The message to show
+    pub fn show_status(&mut self, message: String) {
+        self.push_status(message, None);
+    }
 
-            let default_filename = if safe_title.is_empty() {
-                "Untitled_Note.txt".to_string()
-            } else {
-                format!("{}.txt", safe_title)
-            };
+    /// Reports a file written by an export: shows a message with a button
+    /// that opens the folder holding it and runs the export hook.
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - The message to show
+    /// * `path` - The file that was written
+    pub fn report_export(&mut self, message: String, path: &Path) {
+        let folder = path
+            .parent()
+            .filter(|folder| !folder.as_os_str().is_empty())
+            .map(Path::to_path_buf);
+        self.push_status(message, folder);
+        self.run_export_hook(path);
+    }
 
-            // Show save dialog
-            if let Some(path) = rfd::FileDialog::new()
-                .set_title("Export Note")
-                .set_file_name(&default_filename)
-                .add_filter("Text files", &["txt"])
-                .add_filter("All files", &["*"])
-                .save_file()
-            {
-                match self.write_note_to_file(note, &path) {
-                    Ok(_) => {
-                        println!("Note '{}' exported successfully to: {:?}", note.title, path);
-                    }
-                    Err(e) => {
-                        eprintln!("Failed to export note '{}': {}", note.title, e);
-                    }
-                }
+    /// Starts the export hook for a written file, if the user turned it on.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The file that was written
+    pub fn run_export_hook(&mut self, path: &Path) {
+        let hook = &self.settings.export_hook;
+        if !hook.enabled || hook.command.trim().is_empty() {
+            return;
+        }
+        match RunningHook::start(&hook.command, path) {
+            Ok(running) => self.export_hooks.push(running),
+            Err(e) => {
+                log::error!("Failed to run the export hook: {}", e);
+                self.show_status(format!("Export hook: {}", e));
             }
         }
     }
 
-    /// Writes a note to a file with metadata header.
+    /// Reports export hooks that finished and keeps polling running ones.
     ///
     /// # Arguments
     ///
-    /// * `note` - The note to write
-    /// * `path` - The file path to write to
+    /// * `ctx` - The egui context, to poll again while hooks run
+    fn poll_export_hooks(&mut self, ctx: &egui::Context) {
+        let mut failures = Vec::new();
+        self.export_hooks.retain_mut(|hook| match hook.poll() {
+            None => true,
+            Some(Ok(())) => {
+                log::info!("Export hook finished for {}", hook.path.display());
+                false
+            }
+            Some(Err(e)) => {
+                log::error!("{} for {}", e, hook.path.display());
+                failures.push(e.to_string());
+                false
+            }
+        });
+        for failure in failures {
+            self.show_status(failure);
+        }
+        if !self.export_hooks.is_empty() {
+            ctx.request_repaint_after(BACKGROUND_POLL_INTERVAL);
+        }
+    }
+
+    /// Shows a status message and records it in the recent messages.
+    fn push_status(&mut self, message: String, folder: Option<PathBuf>) {
+        self.status_history.push_front(StatusEntry {
+            time: Utc::now(),
+            message: message.clone(),
+            folder: folder.clone(),
+        });
+        self.status_history.truncate(STATUS_HISTORY_LEN);
+        self.status_message = Some(message);
+        self.status_message_time = Some(std::time::Instant::now());
+        self.status_folder = folder;
+    }
+
+    /// Opens a folder in the file manager, reporting failures as a status
+    /// message.
     ///
-    /// # Returns
+    /// # Arguments
     ///
-    /// * `Result<(), std::io::Error>` - Ok if successful, Err if file operation failed
-    fn write_note_to_file(
-        &self,
-        note: &Note,
-        path: &std::path::Path,
-    ) -> Result<(), std::io::Error> {
-        use std::io::Write;
+    /// * `folder` - The folder to open
+    pub fn open_folder(&mut self, folder: &Path) {
+        if let Err(e) = shell_open::open_folder(folder) {
+            log::error!("Failed to open folder {}: {}", folder.display(), e);
+            self.show_status(format!("Failed to open folder: {}", e));
+        }
+    }
 
-        let mut file = std::fs::File::create(path)?;
+    /// Persists the current user's preferences to encrypted storage.
+    pub fn save_settings(&self) {
+        if let (Some(ref crypto_manager), Some(ref user)) =
+            (&self.crypto_manager, &self.current_user)
+        {
+            if let Err(e) =
+                self.storage_manager
+                    .save_user_settings(&user.id, &self.settings, crypto_manager)
+            {
+                log::error!("Failed to save settings: {}", e);
+            }
+        }
+    }
 
-        // Write note with metadata header
-        writeln!(file, "Title: {}", note.title)?;
-        writeln!(file, "Created: {}", note.format_created_time())?;
-        writeln!(file, "Modified: {}", note.format_modified_time())?;
-        writeln!(file, "ID: {}", note.id)?;
-        writeln!(file, "{}", "=".repeat(50))?;
-        writeln!(file)?;
-        write!(file, "{}", note.content)?;
+    /// Replaces the current user's preferences, e.g. with imported ones,
+    /// and saves them.
+    ///
+    /// The log level and the encryption algorithm take effect right away;
+    /// notes are written with the new algorithm from the next save on. The
+    /// export hook is kept: imported preferences must not make the app
+    /// run a command.
+    ///
+    /// # Arguments
+    ///
+    /// * `settings` - The new preferences
+    pub fn apply_settings(&mut self, settings: UserSettings) {
+        self.settings = UserSettings {
+            export_hook: std::mem::take(&mut self.settings.export_hook),
+            ..settings
+        };
+        // 12-byte-nonce ChaCha20Poly1305 is decrypt-only now, move to XChaCha20
+        if self.settings.encryption_algorithm == AeadAlgorithm::ChaCha20Poly1305 {
+            self.settings.encryption_algorithm = AeadAlgorithm::XChaCha20Poly1305;
+        }
+        logging::set_level(self.settings.log_level);
+        if let Some(ref mut crypto_manager) = self.crypto_manager {
+            crypto_manager.set_algorithm(self.settings.encryption_algorithm);
+        }
+        if let Some(ref mut vault) = self.shared_vault {
+            vault.set_algorithm(self.settings.encryption_algorithm);
+        }
+        // The time server may have changed
+        self.time_verifier = None;
+        self.save_settings();
+    }
 
-        Ok(())
+    /// Remembers the open note and the editor scroll position for the next
+    /// login, in the encrypted settings.
+    fn remember_session(&mut self) {
+        if !self.is_authenticated {
+            return;
+        }
+        let last_session = match &self.pending_session_restore {
+            // Logged out before the last session was restored
+            Some(pending) if self.selected_note_id.is_none() => pending.clone(),
+            _ => LastSession {
+                note_id: self.selected_note_id.clone(),
+                scroll_offset: self.editor_scroll_offset,
+            },
+        };
+        if last_session != self.settings.last_session {
+            self.settings.last_session = last_session;
+            self.save_settings();
+        }
     }
-}
 
-impl eframe::App for NotesApp {
-    /// Main update loop for the application.
+    /// Creates a new note with the given title.
     ///
-    /// This method is called by eframe on every frame and handles:
-    /// - Authentication result checking
-    /// - Keyboard shortcuts
-    /// - UI rendering
-    /// - Auto-save functionality
-    /// - Status message management
+    /// Creates a new note, adds it to the notes collection, selects it
+    /// for editing, and saves the updated notes to storage.
+    ///
+    /// # Arguments
+    ///
+    /// * `title` - The title for the new note. If empty, defaults to "Untitled Note",
+    ///   and with auto-titling enabled the title follows the first line
+    /// * `kind` - The type of note; a secrets note starts with one empty entry
+    /// * `notebook` - Notebook path to file the note in, None for none
+    pub fn create_new_note(&mut self, title: String, kind: NoteKind, notebook: Option<String>) {
+        let is_untitled = title.trim().is_empty();
+        let final_title = if is_untitled {
+            UNTITLED_NOTE_TITLE.to_string()
+        } else {
+            title
+        };
+
+        let mut note = Note::new(final_title);
+        note.auto_title = is_untitled && self.settings.auto_title;
+        note.kind = kind;
+        note.notebook = notebook;
+        if kind == NoteKind::Secrets {
+            note.secrets.push(SecretEntry::new());
+        }
+        let note_id = note.id.clone();
+        self.search_index.note_changed(&note);
+        self.notes.insert(note_id.clone(), note);
+        self.selected_note_id = Some(note_id);
+        self.save_notes();
+    }
+
+    /// Creates the notes requested on the command line and opens the last
+    /// one.
+    ///
+    /// Runs once, after the notes of the first login are loaded, so the new
+    /// notes go into the vault of whoever unlocked the app. Files that can't
+    /// be read are reported as a status message.
+    fn apply_launch_request(&mut self) {
+        let request = std::mem::take(&mut self.launch_request);
+        if request.is_empty() {
+            return;
+        }
+
+        let mut created = 0;
+        for title in request.new_titles {
+            self.create_new_note(title, NoteKind::Text, None);
+            created += 1;
+        }
+
+        let mut failed = Vec::new();
+        for path in &request.files {
+            match launch_args::read_note_file(path) {
+                Ok((title, content)) => {
+                    let title = if title.is_empty() {
+                        UNTITLED_NOTE_TITLE.to_string()
+                    } else {
+                        title
+                    };
+                    let mut note = Note::new(title);
+                    note.content = content;
+                    let note_id = note.id.clone();
+                    self.search_index.note_changed(&note);
+                    self.notes.insert(note_id.clone(), note);
+                    self.selected_note_id = Some(note_id);
+                    created += 1;
+                }
+                Err(e) => {
+                    log::error!("Failed to open {}: {}", path.display(), e);
+                    failed.push(e.to_string());
+                }
+            }
+        }
+        for text in &request.captures {
+            if let Some(note) = inbox::capture(text) {
+                self.search_index.note_changed(&note);
+                self.notes.insert(note.id.clone(), note);
+                created += 1;
+            }
+        }
+        log::info!("Created {} notes from the command line", created);
+        self.save_notes();
+
+        if failed.is_empty() {
+            self.show_status(format!("Created {} note(s) from the command line", created));
+        } else {
+            self.show_status(format!(
+                "Created {} note(s); could not open {}",
+                created,
+                failed.join("; ")
+            ));
+        }
+    }
+
+    /// Adds the sample notes to a newly registered account.
+    fn add_sample_notes(&mut self) {
+        let Some(user) = &self.current_user else {
+            return;
+        };
+        let notes = sample_notes::sample_notes(&user.username, &self.settings.keymap);
+        log::info!("Adding {} sample notes to the new account", notes.len());
+        for note in notes {
+            self.search_index.note_changed(&note);
+            self.notes.insert(note.id.clone(), note);
+        }
+        self.save_notes();
+    }
+
+    /// Keeps the edit lock of the selected note up to date.
+    ///
+    /// Runs every frame but only touches the disk when the selection
+    /// changed or `LOCK_CHECK_INTERVAL` has passed. The lock of a note that
+    /// is no longer selected is released and the lock of the selected note
+    /// is taken or its heartbeat refreshed. If another instance holds it,
+    /// the note is read-only here and follows that instance's saves.
+    fn maintain_note_lock(&mut self) {
+        if self.current_user.is_none() {
+            return;
+        }
+        let selected = self.selected_note_id.clone();
+        let foreign_id = self.foreign_note_lock.as_ref().map(|(id, _)| id.clone());
+        let selection_changed = selected != self.held_note_lock && selected != foreign_id;
+        if !selection_changed
+            && self
+                .last_lock_check
+                .is_some_and(|time| time.elapsed() < LOCK_CHECK_INTERVAL)
+        {
+            return;
+        }
+        self.last_lock_check = Some(std::time::Instant::now());
+
+        if self.held_note_lock.is_some() && self.held_note_lock != selected {
+            self.release_note_lock();
+        }
+        if foreign_id.is_some() && foreign_id != selected {
+            self.foreign_note_lock = None;
+        }
+        let Some(note_id) = selected else {
+            return;
+        };
+        let Some(owner_id) = self.note_owner(&note_id) else {
+            return;
+        };
+
+        match self.storage_manager.read_note_lock(&owner_id, &note_id) {
+            Ok(Some(lock)) if lock.instance_id != self.instance_id && !lock.is_stale() => {
+                if self.held_note_lock.take().is_some() {
+                    log::warn!("Note {} was taken over by another instance", note_id);
+                    let mut message = format!(
+                        "This note is now being edited {} and is read-only here",
+                        lock.location()
+                    );
+                    if self.has_unsaved_changes {
+                        message.push_str("; unsaved changes to it were not saved");
+                    }
+                    self.show_status(message);
+                }
+                self.foreign_note_lock = Some((note_id.clone(), lock));
+                self.reload_note_if_newer(&owner_id, &note_id);
+            }
+            other => {
+                if let Err(e) = other {
+                    log::warn!("Replacing unreadable lock of note {}: {}", note_id, e);
+                }
+                // Pick up saves made elsewhere while this instance didn't hold the lock
+                if self.held_note_lock.is_none() {
+                    self.reload_note_if_newer(&owner_id, &note_id);
+                }
+                self.foreign_note_lock = None;
+                self.acquire_note_lock(&owner_id, &note_id);
+            }
+        }
+    }
+
+    /// Writes this instance's lock on a note, with a fresh heartbeat.
+    ///
+    /// # Arguments
+    ///
+    /// * `owner_id` - Owner of the note, a user or the shared vault
+    /// * `note_id` - ID of the note to lock
+    fn acquire_note_lock(&mut self, owner_id: &str, note_id: &str) {
+        let lock = NoteLock::new(&self.instance_id);
+        match self
+            .storage_manager
+            .write_note_lock(owner_id, note_id, &lock)
+        {
+            Ok(()) => self.held_note_lock = Some(note_id.to_string()),
+            Err(e) => log::warn!("Failed to lock note {}: {}", note_id, e),
+        }
+    }
+
+    /// Releases the edit lock held by this instance, if any.
+    ///
+    /// The lock file is only removed while it still belongs to this
+    /// instance, so a lock taken over by another instance is kept.
+    pub fn release_note_lock(&mut self) {
+        self.foreign_note_lock = None;
+        let Some(note_id) = self.held_note_lock.take() else {
+            return;
+        };
+        let Some(owner_id) = self.note_owner(&note_id) else {
+            return;
+        };
+
+        let still_ours = self
+            .storage_manager
+            .read_note_lock(&owner_id, &note_id)
+            .ok()
+            .flatten()
+            .is_some_and(|lock| lock.instance_id == self.instance_id);
+        if still_ours {
+            if let Err(e) = self.storage_manager.remove_note_lock(&owner_id, &note_id) {
+                log::warn!("Failed to release lock of note {}: {}", note_id, e);
+            }
+        }
+    }
+
+    /// Takes over the edit lock of a note from another instance.
+    ///
+    /// The note is reloaded first, so editing continues from the other
+    /// instance's last save. That instance notices on its next lock check
+    /// and makes the note read-only.
+    ///
+    /// # Arguments
+    ///
+    /// * `note_id` - ID of the note to take over
+    pub fn take_over_note_lock(&mut self, note_id: &str) {
+        let Some(owner_id) = self.note_owner(note_id) else {
+            return;
+        };
+
+        log::info!("Taking over lock of note {}", note_id);
+        self.reload_note_if_newer(&owner_id, note_id);
+        self.foreign_note_lock = None;
+        self.acquire_note_lock(&owner_id, note_id);
+        self.last_lock_check = Some(std::time::Instant::now());
+    }
+
+    /// Returns the lock of another instance on a note, if it is locked.
+    ///
+    /// # Arguments
+    ///
+    /// * `note_id` - ID of the note to check
+    ///
+    /// # Returns
+    ///
+    /// * `Option<&NoteLock>` - The other instance's lock; only known for
+    ///   the selected note
+    pub fn note_locked_elsewhere(&self, note_id: &str) -> Option<&NoteLock> {
+        self.foreign_note_lock
+            .as_ref()
+            .filter(|(id, _)| id == note_id)
+            .map(|(_, lock)| lock)
+    }
+
+    /// Replaces a note with its saved version if that one is newer.
+    ///
+    /// # Arguments
+    ///
+    /// * `owner_id` - Owner of the note, a user or the shared vault
+    /// * `note_id` - ID of the note to reload
+    fn reload_note_if_newer(&mut self, owner_id: &str, note_id: &str) {
+        let Some(crypto_manager) = self.owner_crypto(owner_id) else {
+            return;
+        };
+
+        match self
+            .storage_manager
+            .load_user_note(owner_id, note_id, crypto_manager)
+        {
+            Ok(mut saved) => {
+                saved.shared = owner_id == SHARED_VAULT_OWNER;
+                let is_newer = self
+                    .notes
+                    .get(note_id)
+                    .is_some_and(|note| saved.modified_at > note.modified_at);
+                if is_newer {
+                    log::debug!("Reloaded note {} saved by another instance", note_id);
+                    if let Some(vault) = self.shared_vault.as_mut().filter(|_| saved.shared) {
+                        vault.note_loaded(&saved);
+                    }
+                    self.search_index.note_changed(&saved);
+                    self.notes.insert(note_id.to_string(), saved);
+                }
+            }
+            // Not saved yet, or deleted elsewhere
+            Err(e) => log::debug!("Could not reload note {}: {}", note_id, e),
+        }
+    }
+
+    /// Unlocks the shared vault if the current user is a member.
+    ///
+    /// While a key derivation upgrade is pending, the vault key is wrapped
+    /// again with the user's new key once the notes are loaded, see
+    /// `finish_kdf_upgrade_if_pending`.
+    pub fn open_shared_vault(&mut self) {
+        self.shared_vault = None;
+        let (Some(crypto_manager), Some(user)) = (&self.crypto_manager, &self.current_user) else {
+            return;
+        };
+
+        match SharedVault::open(
+            &self.storage_manager,
+            &user.id,
+            crypto_manager,
+            self.settings.encryption_algorithm,
+        ) {
+            Ok(Some(vault)) => {
+                log::info!("Shared vault unlocked");
+                self.shared_vault = Some(vault);
+            }
+            Ok(None) => {}
+            Err(e) => {
+                log::error!("Failed to unlock the shared vault: {}", e);
+                self.show_status("The shared vault could not be unlocked".to_string());
+            }
+        }
+    }
+
+    /// Returns the storage owner of a note.
+    ///
+    /// # Arguments
+    ///
+    /// * `note_id` - ID of the note
+    ///
+    /// # Returns
+    ///
+    /// * `Option<String>` - The shared vault owner for shared notes, the
+    ///   current user's ID otherwise, None if logged out
+    pub fn note_owner(&self, note_id: &str) -> Option<String> {
+        if self.notes.get(note_id).is_some_and(|note| note.shared) {
+            Some(SHARED_VAULT_OWNER.to_string())
+        } else {
+            self.current_user.as_ref().map(|user| user.id.clone())
+        }
+    }
+
+    /// Returns the crypto manager for the data of a storage owner.
+    ///
+    /// # Arguments
+    ///
+    /// * `owner_id` - A user ID or the shared vault owner
+    ///
+    /// # Returns
+    ///
+    /// * `Option<&CryptoManager>` - The shared vault's or the user's crypto
+    ///   manager, None if it is not unlocked
+    pub fn owner_crypto(&self, owner_id: &str) -> Option<&CryptoManager> {
+        if owner_id == SHARED_VAULT_OWNER {
+            self.shared_vault.as_ref().map(SharedVault::crypto)
+        } else {
+            self.crypto_manager.as_ref()
+        }
+    }
+
+    /// Moves a note between the user's private notes and the shared vault.
+    ///
+    /// The note and its attachments are encrypted with the destination's
+    /// key, and only removed from the source once fully copied.
+    ///
+    /// # Arguments
+    ///
+    /// * `note_id` - ID of the note to move
+    /// * `shared` - True to move it into the shared vault
+    pub fn set_note_shared(&mut self, note_id: &str, shared: bool) {
+        let Some(user_id) = self.current_user.as_ref().map(|user| user.id.clone()) else {
+            return;
+        };
+        let needs_move = self
+            .notes
+            .get(note_id)
+            .is_some_and(|note| note.shared != shared);
+        if self.shared_vault.is_none() || !needs_move {
+            return;
+        }
+        // The lock lives with the note, so it moves too
+        if self.held_note_lock.as_deref() == Some(note_id) {
+            self.release_note_lock();
+        }
+
+        let (from, to) = if shared {
+            (user_id.as_str(), SHARED_VAULT_OWNER)
+        } else {
+            (SHARED_VAULT_OWNER, user_id.as_str())
+        };
+        let result = self.copy_note_to(note_id, from, to).and_then(|moved| {
+            match self.shared_vault.as_mut() {
+                Some(vault) if shared => vault.note_loaded(&moved),
+                Some(vault) => vault.delete_note(&self.storage_manager, note_id)?,
+                None => {}
+            }
+            if shared {
+                self.storage_manager.delete_user_note(from, note_id)?;
+                // Its history stays private and goes with the next save
+                self.deleted_note_ids.insert(note_id.to_string());
+            } else {
+                self.deleted_note_ids.remove(note_id);
+            }
+            self.notes.insert(note_id.to_string(), moved);
+            Ok(())
+        });
+
+        self.show_status(match result {
+            Ok(()) => {
+                log::info!("Moved note {} to {}", note_id, to);
+                if shared {
+                    "Note moved to the shared vault".to_string()
+                } else {
+                    "Note moved to your private notes".to_string()
+                }
+            }
+            Err(e) => {
+                log::error!("Failed to move note {}: {}", note_id, e);
+                format!("Failed to move note: {}", e)
+            }
+        });
+        // Take the lock again where the note lives now
+        self.last_lock_check = None;
+    }
+
+    /// Writes a note and its attachments to another storage owner.
+    ///
+    /// # Arguments
+    ///
+    /// * `note_id` - ID of the note to copy
+    /// * `from` - Current owner of the note
+    /// * `to` - New owner of the note
+    ///
+    /// # Returns
+    ///
+    /// * `anyhow::Result<Note>` - The note as stored for the new owner
+    fn copy_note_to(&self, note_id: &str, from: &str, to: &str) -> anyhow::Result<Note> {
+        let (Some(from_crypto), Some(to_crypto)) = (self.owner_crypto(from), self.owner_crypto(to))
+        else {
+            anyhow::bail!("The vault is locked");
+        };
+        let mut note = self
+            .notes
+            .get(note_id)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("Note not found"))?;
+
+        for attachment in &note.attachments {
+            let data =
+                self.storage_manager
+                    .load_attachment(from, note_id, attachment, from_crypto)?;
+            self.storage_manager
+                .save_attachment(to, note_id, attachment, &data, to_crypto)?;
+        }
+        note.shared = to == SHARED_VAULT_OWNER;
+        self.storage_manager.save_user_note(to, &note, to_crypto)?;
+        Ok(note)
+    }
+
+    /// Checks whether a note with the given title exists, ignoring case.
+    ///
+    /// Trashed notes are not counted.
+    ///
+    /// # Arguments
+    ///
+    /// * `title` - The title to look for
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - True if another note already uses the title
+    pub fn note_title_exists(&self, title: &str) -> bool {
+        let title = title.trim();
+        self.notes
+            .values()
+            .any(|note| !note.is_trashed() && note.title.trim().eq_ignore_ascii_case(title))
+    }
+
+    /// Makes a note title unique by appending " (2)", " (3)", ...
+    ///
+    /// # Arguments
+    ///
+    /// * `title` - The desired title
+    ///
+    /// # Returns
+    ///
+    /// * `String` - `title` if it is free, otherwise the first free suffixed title
+    pub fn unique_note_title(&self, title: &str) -> String {
+        let title = title.trim();
+        let mut candidate = title.to_string();
+        let mut counter = 2;
+        while self.note_title_exists(&candidate) {
+            candidate = format!("{} ({})", title, counter);
+            counter += 1;
+        }
+        candidate
+    }
+
+    /// Moves a note to the trash.
+    ///
+    /// The note stays on disk and can be restored until it is purged, see
+    /// `purge_expired_trash`.
+    ///
+    /// # Arguments
+    ///
+    /// * `note_id` - The ID of the note to trash
+    pub fn delete_note(&mut self, note_id: &str) {
+        log::debug!("Moving note {} to trash", note_id);
+
+        if let Some(note) = self.notes.get_mut(note_id) {
+            note.deleted_at = Some(trusted_time::now());
+        }
+
+        self.save_notes();
+    }
+
+    /// Restores a note from the trash.
+    ///
+    /// # Arguments
+    ///
+    /// * `note_id` - The ID of the note to restore
+    pub fn restore_note(&mut self, note_id: &str) {
+        log::debug!("Restoring note {} from trash", note_id);
+
+        if let Some(note) = self.notes.get_mut(note_id) {
+            note.deleted_at = None;
+            note.keep_forever = false;
+        }
+
+        self.save_notes();
+    }
+
+    /// Deletes notes permanently.
+    ///
+    /// Removes the notes from the collection, deselects them if selected,
+    /// and saves, which securely deletes their files.
+    ///
+    /// # Arguments
+    ///
+    /// * `note_ids` - The IDs of the notes to delete
+    pub fn delete_notes_permanently(&mut self, note_ids: &[String]) {
+        if note_ids.is_empty() {
+            return;
+        }
+        log::debug!("Permanently deleting {} note(s)", note_ids.len());
+
+        for note_id in note_ids {
+            if self.held_note_lock.as_ref() == Some(note_id) {
+                self.release_note_lock();
+            }
+            let Some(note) = self.notes.remove(note_id) else {
+                continue;
+            };
+            if !note.shared {
+                self.deleted_note_ids.insert(note_id.clone());
+            }
+            // Shared notes are not pruned by saving, delete them right away
+            if let Some(vault) = self.shared_vault.as_mut().filter(|_| note.shared) {
+                if let Err(e) = vault.delete_note(&self.storage_manager, note_id) {
+                    log::error!("Failed to delete shared note {}: {}", note_id, e);
+                }
+            }
+            self.search_index.note_removed(note_id);
+            if self.selected_note_id.as_ref() == Some(note_id) {
+                self.selected_note_id = None;
+            }
+        }
+
+        self.save_notes();
+    }
+
+    /// Toggles whether a note is pinned to the top of the sidebar.
+    ///
+    /// A newly pinned note goes to the top of the Pinned section. Once the
+    /// pinned limit is reached, pinning another note either unpins the one
+    /// at the bottom or is refused, as chosen in the settings.
+    ///
+    /// # Arguments
+    ///
+    /// * `note_id` - The ID of the note
+    pub fn toggle_pinned(&mut self, note_id: &str) {
+        let Some(pinned) = self.notes.get(note_id).map(|note| note.pinned) else {
+            return;
+        };
+        let mut order = note_order::pinned_ids(&self.notes, &self.settings.pinned_order);
+        order.retain(|id| id != note_id);
+
+        if !pinned {
+            let max_pinned = self.settings.max_pinned;
+            if max_pinned > 0 && order.len() >= max_pinned {
+                match self.settings.pin_overflow {
+                    PinOverflow::Refuse => {
+                        self.show_status(format!(
+                            "At most {} notes can be pinned; unpin one first",
+                            max_pinned
+                        ));
+                        return;
+                    }
+                    PinOverflow::UnpinLowest => {
+                        let unpinned = self.unpin_notes(order.split_off(max_pinned - 1));
+                        self.show_status(format!(
+                            "Unpinned {} to stay within {} pinned notes",
+                            unpinned, max_pinned
+                        ));
+                    }
+                }
+            }
+            order.insert(0, note_id.to_string());
+        }
+
+        if let Some(note) = self.notes.get_mut(note_id) {
+            note.pinned = !pinned;
+        }
+        self.settings.pinned_order = order;
+        self.save_settings();
+        self.save_notes();
+    }
+
+    /// Unpins the notes at the bottom of the Pinned section that exceed the
+    /// pinned limit, if pinning beyond it unpins notes.
+    pub fn enforce_pin_limit(&mut self) {
+        let max_pinned = self.settings.max_pinned;
+        if max_pinned == 0 || self.settings.pin_overflow != PinOverflow::UnpinLowest {
+            return;
+        }
+        let mut order = note_order::pinned_ids(&self.notes, &self.settings.pinned_order);
+        if order.len() <= max_pinned {
+            return;
+        }
+        let unpinned = self.unpin_notes(order.split_off(max_pinned));
+        self.show_status(format!(
+            "Unpinned {} to stay within {} pinned notes",
+            unpinned, max_pinned
+        ));
+        self.settings.pinned_order = order;
+        self.save_settings();
+        self.save_notes();
+    }
+
+    /// Unpins notes without saving them.
+    ///
+    /// # Arguments
+    ///
+    /// * `note_ids` - IDs of the notes to unpin
+    ///
+    /// # Returns
+    ///
+    /// * `String` - The quoted title of the only note, or the number of
+    ///   notes, for a status message
+    fn unpin_notes(&mut self, note_ids: Vec<String>) -> String {
+        for note_id in &note_ids {
+            if let Some(note) = self.notes.get_mut(note_id) {
+                note.pinned = false;
+            }
+        }
+        match note_ids.as_slice() {
+            [note_id] => format!(
+                "\"{}\"",
+                self.notes
+                    .get(note_id)
+                    .map(|note| note.display_title())
+                    .unwrap_or_default()
+            ),
+            _ => format!("{} notes", note_ids.len()),
+        }
+    }
+
+    /// Toggles whether a note is a favorite.
+    ///
+    /// # Arguments
+    ///
+    /// * `note_id` - The ID of the note
+    pub fn toggle_favorite(&mut self, note_id: &str) {
+        if let Some(note) = self.notes.get_mut(note_id) {
+            note.favorite = !note.favorite;
+            self.save_notes();
+        }
+    }
+
+    /// Moves a note into a notebook.
+    ///
+    /// # Arguments
+    ///
+    /// * `note_id` - The ID of the note
+    /// * `notebook` - Path of the notebook, None to take the note out of
+    ///   its notebook
+    pub fn move_to_notebook(&mut self, note_id: &str, notebook: Option<String>) {
+        if let Some(note) = self.notes.get_mut(note_id) {
+            if note.notebook == notebook {
+                return;
+            }
+            note.notebook = notebook;
+            note.update_modified_time();
+            self.search_index.note_changed(note);
+            self.save_notes();
+        }
+    }
+
+    /// Returns the IDs of all notes in the trash.
+    pub fn trashed_note_ids(&self) -> Vec<String> {
+        self.notes
+            .values()
+            .filter(|note| note.is_trashed())
+            .map(|note| note.id.clone())
+            .collect()
+    }
+
+    /// Purges trashed notes whose retention period has expired.
+    ///
+    /// Runs once all notes are loaded after login and then periodically in
+    /// the background. Notes marked "keep forever" are never purged.
+    pub fn purge_expired_trash(&mut self) {
+        self.last_trash_purge = Some(std::time::Instant::now());
+
+        let now = trusted_time::now();
+        let retention_days = self.settings.trash_retention_days;
+        let expired: Vec<String> = self
+            .notes
+            .values()
+            .filter(|note| note.purge_at(retention_days).is_some_and(|at| at <= now))
+            .map(|note| note.id.clone())
+            .collect();
+
+        if expired.is_empty() {
+            return;
+        }
+
+        log::info!("Purging {} expired note(s) from trash", expired.len());
+        self.delete_notes_permanently(&expired);
+        self.show_status(format!("{} note(s) purged from trash", expired.len()));
+    }
+
+    /// Purges the trash if the background purge interval has elapsed.
+    ///
+    /// Does nothing until notes have finished loading, since purging saves
+    /// the vault and an incomplete note set must not be saved.
+    pub fn purge_trash_if_due(&mut self) {
+        if self.note_loader.is_some() {
+            return;
+        }
+        if self
+            .last_trash_purge
+            .is_some_and(|last| last.elapsed() >= TRASH_PURGE_INTERVAL)
+        {
+            self.purge_expired_trash();
+        }
+    }
+
+    /// Performs auto-save if enough time has elapsed since the last edit.
+    ///
+    /// Saving encrypts the whole vault, so it waits for a pause in typing
+    /// of the auto-save delay. While the user keeps typing, the changes are
+    /// saved anyway once the oldest of them is as old as the longest wait.
+    ///
+    /// Only writes to disk when there are unsaved changes and auto-save is
+    /// enabled in the user settings. In manual-save-only mode, notes are
+    /// written on Ctrl+S, on logout and when the application is closed.
+    pub fn auto_save_if_needed(&mut self) {
+        if !self.has_unsaved_changes {
+            self.unsaved_since = None;
+            return;
+        }
+        let unsaved_since = *self
+            .unsaved_since
+            .get_or_insert_with(std::time::Instant::now);
+
+        if let (Some(delay), Some(max_wait)) = (
+            self.settings.auto_save_delay(),
+            self.settings.auto_save_max_wait(),
+        ) {
+            if self.last_save_time.elapsed() >= delay || unsaved_since.elapsed() >= max_wait {
+                self.save_notes();
+            }
+        }
+    }
+
+    /// Gets the current time formatted for display in Swiss timezone.
+    ///
+    /// # Returns
+    ///
+    /// * `String` - Current time in "DD.MM.YYYY HH:MM:SS" format
+    pub fn get_current_time(&self) -> String {
+        let now = Utc::now().with_timezone(&Zurich);
+        now.format("%d.%m.%Y %H:%M:%S").to_string()
+    }
+
+    /// Logs out the current user and resets application state.
+    ///
+    /// Clears all user-specific data, resets UI state, and returns
+    /// to the authentication dialog. This ensures no sensitive data
+    /// remains in memory after logout.
+    pub fn logout(&mut self) {
+        log::info!("User logging out");
+        self.settle_reencrypt_job();
+        if self.has_unsaved_changes {
+            self.save_notes();
+        }
+        self.remember_session();
+        self.release_note_lock();
+        self.has_unsaved_changes = false;
+        self.settings = UserSettings::default();
+        self.is_authenticated = false;
+        self.show_auth_dialog = true;
+        self.crypto_manager = None;
+        self.shared_vault = None;
+        self.current_user = None;
+        self.note_loader = None;
+        if let Some(job) = self.import_job.take() {
+            job.cancel();
+        }
+        self.reencrypt_job = None;
+        self.notes.clear();
+        self.deleted_note_ids.clear();
+        self.search_index.clear();
+        self.selected_note_id = None;
+        self.username_input.clear();
+        self.password_input.clear();
+        self.confirm_password_input.clear();
+        self.pending_unlock = None;
+        self.second_passphrase_input.clear();
+        self.authentication_error = None;
+        self.auth_mode = AuthMode::Login;
+        self.security_warnings.clear();
+        self.login_history.clear();
+        self.clear_search();
+        self.last_trash_purge = None;
+        self.quota_usage = None;
+        self.last_quota_check = None;
+        self.quota_warned_percent = 0;
+        if let Some(scan) = self.integrity_scan.take() {
+            scan.cancel();
+        }
+        self.integrity_report = None;
+        self.next_integrity_scan = None;
+        self.last_decrypted.clear();
+        self.session_started_at = None;
+        self.time_verifier = None;
+        self.time_check = None;
+        self.status_history.clear();
+        self.journal = None;
+        self.revision_tracker.clear();
+        self.note_history_dialog = None;
+        self.two_person_dialog = None;
+        self.quick_capture = None;
+        self.inbox_dialog = None;
+        self.health_check = None;
+        self.load_failures.clear();
+        self.recovery_dialog = None;
+        self.note_diagnostics = None;
+        // Kept until the next frame clears the clipboard
+        self.clear_clipboard_now();
+        self.revealed_secrets.clear();
+        self.speaker = None;
+        // All hold note text
+        self.editor_layout_cache.clear();
+        self.note_text_stats = None;
+        self.preview_blocks = None;
+
+        // Clear settings dialogs
+        self.show_user_settings = false;
+        self.storage_report = None;
+        self.show_change_password_dialog = false;
+        self.password_change_error = None;
+        self.show_delete_account_dialog = false;
+        self.show_log_viewer = false;
+        self.show_startup_profile = false;
+        self.show_search_results = false;
+        self.search_include_trash = false;
+        self.editor_jump = None;
+        self.editor_scroll_offset = 0.0;
+        self.editor_scroll_restore = None;
+        self.preview_scroll_restore = None;
+        self.split_scroll_offsets = None;
+        self.pending_session_restore = None;
+        self.show_migration_wizard = false;
+        self.migration_wizard.reset();
+        self.legacy_migration = None;
+        self.show_snapshot_viewer = false;
+        self.snapshot_viewer.reset();
+        self.note_share_dialog = None;
+        self.note_email_dialog = None;
+        self.rename_dialog = None;
+        self.title_edit = None;
+        self.rename_report = None;
+        self.link_graph_view = None;
+        self.note_stats_view = None;
+        self.site_export_dialog = None;
+        self.attachment_preview = None;
+        self.settings_transfer_dialog = None;
+        self.old_password_input.clear();
+        self.new_password_input.clear();
+        self.confirm_new_password_input.clear();
+        self.delete_confirmation_input.clear();
+        self.new_comment_input.clear();
+        self.shared_vault_username_input.clear();
+        self.shared_vault_password_input.clear();
+    }
+
+    /// Locks the vault after the OS session was locked or suspended.
+    ///
+    /// Saves pending edits and logs out, which drops the encryption key and
+    /// the decrypted notes. The login screen keeps the username and tells
+    /// why the password is needed again.
+    ///
+    /// # Arguments
+    ///
+    /// * `event` - The session event that triggered the lock
+    pub fn lock_vault(&mut self, event: SessionEvent) {
+        log::info!("Locking vault because {}", event.description());
+        let username = self
+            .current_user
+            .as_ref()
+            .map(|user| user.username.clone())
+            .unwrap_or_default();
+
+        self.logout();
+        self.username_input = username;
+        self.authentication_error = Some(format!(
+            "Locked because {}. Enter your password to continue.",
+            event.description()
+        ));
+    }
+
+    /// Locks the vault on OS screen locks and suspends, if enabled.
+    ///
+    /// The session monitor only runs while a user is logged in with the
+    /// lock enabled.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The egui context, woken up by the session monitor
+    fn handle_session_events(&mut self, ctx: &egui::Context) {
+        if !self.is_authenticated || !self.settings.lock_on_session_lock {
+            // Dropping the monitor ends its thread
+            self.session_monitor = None;
+            return;
+        }
+
+        let event = self
+            .session_monitor
+            .get_or_insert_with(|| SessionMonitor::start(ctx))
+            .poll();
+        if let Some(event) = event {
+            self.lock_vault(event);
+        }
+    }
+
+    /// Checks the system clock against the time server while enabled.
+    ///
+    /// Applies the measured offset to the app's timestamps and warns when
+    /// the clock is off by more than a few minutes. A failed check leaves
+    /// the previous offset in place. Dropping the verifier, e.g. after the
+    /// server was changed, starts a new check on the next frame.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The egui context, woken up by the verifier
+    fn poll_time_check(&mut self, ctx: &egui::Context) {
+        if !self.is_authenticated || !self.settings.verify_time {
+            self.time_verifier = None;
+            // A user without verification gets the local clock
+            if self.is_authenticated && trusted_time::offset().is_some() {
+                self.apply_time_check(None);
+            }
+            return;
+        }
+
+        let Some(result) = self
+            .time_verifier
+            .get_or_insert_with(|| TimeVerifier::start(ctx, self.settings.time_server.trim()))
+            .poll()
+        else {
+            return;
+        };
+
+        match result {
+            Ok(check) => {
+                log::info!(
+                    "Clock checked with {}: offset {} ms, round trip {} ms",
+                    check.server,
+                    check.offset.num_milliseconds(),
+                    check.round_trip.num_milliseconds()
+                );
+                self.apply_time_check(Some(check));
+            }
+            Err(e) => {
+                log::warn!("Could not verify the time: {}", e);
+                self.show_status(format!("Could not verify the time: {}", e));
+            }
+        }
+    }
+
+    /// Makes a clock check the source of the app's timestamps.
+    ///
+    /// # Arguments
+    ///
+    /// * `check` - The latest check, or None to go back to the local clock
+    fn apply_time_check(&mut self, check: Option<TimeCheck>) {
+        let old_offset = trusted_time::offset().unwrap_or_else(TimeDelta::zero);
+        let new_offset = check.as_ref().map(|check| check.offset);
+        trusted_time::set_offset(new_offset);
+        // Keeps notes edited before the check out of "Edited This Session"
+        if let Some(started) = self.session_started_at.as_mut() {
+            *started += new_offset.unwrap_or_else(TimeDelta::zero) - old_offset;
+        }
+
+        let old_warning = self.time_check.as_ref().and_then(TimeCheck::warning);
+        let new_warning = check.as_ref().and_then(TimeCheck::warning);
+        if old_warning != new_warning {
+            self.security_warnings
+                .retain(|warning| Some(warning) != old_warning.as_ref());
+            if let Some(warning) = &new_warning {
+                self.security_warnings.push(warning.clone());
+                self.show_status(format!("⚠ {}", warning));
+            }
+        }
+        self.time_check = check;
+    }
+
+    /// Hides the window from screen capture while a user with the privacy
+    /// setting enabled is logged in.
+    ///
+    /// Only calls into the OS when the wanted state changes. Failures are
+    /// logged and shown once as a status message.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - The eframe frame, providing the native window handle
+    fn apply_capture_protection(&mut self, frame: &eframe::Frame) {
+        if !capture_protection::SUPPORTED {
+            return;
+        }
+        let wanted = self.is_authenticated && self.settings.block_screen_capture;
+        if self.capture_protection_applied == Some(wanted) {
+            return;
+        }
+        self.capture_protection_applied = Some(wanted);
+
+        if let Err(e) = capture_protection::set_excluded_from_capture(frame, wanted) {
+            log::warn!("Failed to set screen capture protection: {}", e);
+            if wanted {
+                self.show_status(format!("Screen capture protection failed: {}", e));
+            }
+        }
+    }
+
+    /// Keeps the window above other windows, or stops doing so.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The egui context to send the viewport command to
+    /// * `on_top` - Whether the window should stay on top
+    pub fn set_always_on_top(&mut self, ctx: &egui::Context, on_top: bool) {
+        self.always_on_top = on_top;
+        let level = if on_top {
+            egui::WindowLevel::AlwaysOnTop
+        } else {
+            egui::WindowLevel::Normal
+        };
+        ctx.send_viewport_cmd(egui::ViewportCommand::WindowLevel(level));
+
+        self.show_status(if on_top {
+            "Window kept on top".to_string()
+        } else {
+            "Window no longer on top".to_string()
+        });
+    }
+
+    /// Starts decrypting the current user's notes in the background.
+    ///
+    /// Legacy data from the old storage format is previewed for the legacy
+    /// migration dialog. Notes are decrypted in parallel and streamed into
+    /// the sidebar by `poll_note_loading` as they arrive, so large vaults
+    /// become usable before every note has been decrypted. Files in an
+    /// outdated ciphertext format are upgraded first.
+    pub fn start_note_loading(&mut self) {
+        let (Some(crypto_manager), Some(user)) = (&self.crypto_manager, &self.current_user) else {
+            return;
+        };
+
+        let crypto_manager = crypto_manager.clone();
+        let shared_crypto = self
+            .shared_vault
+            .as_ref()
+            .map(|vault| vault.crypto().clone());
+        let user_id = user.id.clone();
+        let (sender, receiver) = mpsc::channel();
+        self.note_loader = Some(receiver);
+        self.notes.clear();
+        self.search_index.clear();
+        self.revision_tracker.clear();
+        self.unreadable_note_ids.clear();
+        self.deleted_note_ids.clear();
+        self.upgrade_failures.clear();
+        self.load_failures.clear();
+        self.recovery_dialog = None;
+        self.notes_load_failed = false;
+
+        self.legacy_migration = match self.storage_manager.preview_legacy_notes(&crypto_manager) {
+            Ok(Some(entries)) => {
+                log::info!("Found legacy notes file with {} entries", entries.len());
+                Some(LegacyMigration::Preview(entries))
+            }
+            Ok(None) => None,
+            Err(e) => {
+                log::error!("Failed to read legacy notes: {}", e);
+                Some(LegacyMigration::Unreadable(e.to_string()))
+            }
+        };
+
+        thread::spawn(move || {
+            let started = std::time::Instant::now();
+            let storage_manager = StorageManager::new();
+            let upgrade_failures =
+                match storage_manager.upgrade_user_files(&user_id, &crypto_manager) {
+                    Ok(report) => report.failed,
+                    Err(e) => {
+                        log::error!("Failed to upgrade note files: {}", e);
+                        vec![format!("The vault's files could not be listed: {}", e)]
+                    }
+                };
+            if !upgrade_failures.is_empty() {
+                let _ = sender.send(NoteLoadEvent::UpgradeFailed(upgrade_failures));
+            }
+            // Notes matching the saved index don't need to be indexed again
+            match storage_manager.load_search_index(&user_id, &crypto_manager) {
+                Ok(Some(index)) => {
+                    let _ = sender.send(NoteLoadEvent::SearchIndex(Box::new(index)));
+                }
+                Ok(None) => {}
+                Err(e) => log::warn!("Failed to load the search index, rebuilding it: {}", e),
+            }
+            let mut result =
+                storage_manager.stream_user_notes(&user_id, &crypto_manager, &|note| {
+                    let _ = sender.send(NoteLoadEvent::Loaded(Box::new(note)));
+                });
+
+            // Unreadable shared notes count as failed, but the private notes
+            // are usable even if the shared vault cannot be read at all
+            if let (Ok(failed), Some(shared_crypto)) = (&mut result, &shared_crypto) {
+                match storage_manager.stream_user_notes(
+                    SHARED_VAULT_OWNER,
+                    shared_crypto,
+                    &|mut note| {
+                        note.shared = true;
+                        let _ = sender.send(NoteLoadEvent::Loaded(Box::new(note)));
+                    },
+                ) {
+                    Ok(shared_failed) => failed.extend(shared_failed.into_iter().map(|mut f| {
+                        f.shared = true;
+                        f
+                    })),
+                    Err(e) => log::error!("Failed to load shared notes: {}", e),
+                }
+            }
+
+            profiling::record("Notes load", started);
+            let _ = sender.send(match result {
+                Ok(failed) => NoteLoadEvent::Finished(failed),
+                Err(e) => NoteLoadEvent::Failed(e.to_string()),
+            });
+        });
+    }
+
+    /// Moves notes decrypted by the background loader into the note list.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The egui context, repainted while notes are still arriving
+    pub fn poll_note_loading(&mut self, ctx: &egui::Context) {
+        while let Some(receiver) = &self.note_loader {
+            match receiver.try_recv() {
+                Ok(NoteLoadEvent::SearchIndex(index)) => self.search_index.restore(*index),
+                Ok(NoteLoadEvent::UpgradeFailed(failures)) => self.upgrade_failures = failures,
+                Ok(NoteLoadEvent::Loaded(note)) => {
+                    self.last_decrypted.insert(note.id.clone(), Utc::now());
+                    // Keep notes the user already created or edited meanwhile
+                    if !self.notes.contains_key(&note.id) {
+                        if let Some(vault) = self.shared_vault.as_mut().filter(|_| note.shared) {
+                            vault.note_loaded(&note);
+                        }
+                        if !note.shared {
+                            self.revision_tracker.note_loaded(&note);
+                        }
+                        self.search_index.note_changed(&note);
+                        self.notes.insert(note.id.clone(), *note);
+                    }
+                    self.restore_session_if_loaded();
+                }
+                Ok(NoteLoadEvent::Finished(failed)) => {
+                    log::info!("Loaded {} notes", self.notes.len());
+                    self.note_loader = None;
+                    // The note of the last session no longer exists
+                    self.pending_session_restore = None;
+                    if !failed.is_empty() {
+                        self.show_status(format!("{} notes could not be decrypted", failed.len()));
+                    }
+                    self.finish_kdf_upgrade_if_pending(failed.len());
+                    self.retire_legacy_formats_if_done(failed.len());
+                    self.unreadable_note_ids = failed
+                        .iter()
+                        .map(|failure| failure.note_id.clone())
+                        .collect();
+                    self.load_failures = failed;
+                    if !self.load_failures.is_empty() {
+                        self.open_recovery_dialog();
+                    }
+                    self.search_index
+                        .retain(self.notes.keys().cloned().collect());
+                    self.purge_expired_trash();
+                    self.check_storage_quota(true);
+                    self.next_integrity_scan = Some(std::time::Instant::now() + FIRST_SCAN_DELAY);
+                    self.apply_launch_request();
+                }
+                Ok(NoteLoadEvent::Failed(error)) => {
+                    log::error!("Failed to load notes: {}", error);
+                    self.show_status("Failed to load notes".to_string());
+                    self.note_loader = None;
+                    self.notes_load_failed = true;
+                }
+                Err(mpsc::TryRecvError::Empty) => {
+                    ctx.request_repaint();
+                    break;
+                }
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    // The loader stopped without finishing, e.g. it panicked
+                    log::error!("Note loader stopped unexpectedly");
+                    self.note_loader = None;
+                    self.notes_load_failed = true;
+                }
+            }
+        }
+    }
+
+    /// Reopens the note of the last session once it has been loaded.
+    ///
+    /// Nothing is restored if the user opened another note in the meantime.
+    fn restore_session_if_loaded(&mut self) {
+        let Some(session) = &self.pending_session_restore else {
+            return;
+        };
+        if self.selected_note_id.is_some() {
+            self.pending_session_restore = None;
+            return;
+        }
+        if let Some(note_id) = session
+            .note_id
+            .as_ref()
+            .filter(|id| self.notes.contains_key(*id))
+        {
+            log::info!("Restoring the last open note");
+            self.selected_note_id = Some(note_id.clone());
+            self.editor_scroll_restore = Some(session.scroll_offset);
+            self.pending_session_restore = None;
+        }
+    }
+
+    /// Exports a note to a text file.
+    ///
+    /// Opens a file dialog for the user to choose where to save the note,
+    /// suggesting a file name built from the export file name template, then
+    /// writes the note content along with metadata to the selected file.
+    /// Attachments are decrypted into a `<name>_attachments` folder next to
+    /// the exported file and listed in its metadata; the entries of a
+    /// secrets note follow the content, with their secrets in plain text.
+    ///
+    /// The format follows the extension of the chosen file. The dialog
+    /// offers the format the note was last exported in first, and the
+    /// format used is remembered in the note.
+    ///
+    /// # Arguments
+    ///
+    /// * `note_id` - The ID of the note to export
+    pub fn export_note_to_file(&mut self, note_id: &str) {
+        let Some(note) = self.notes.get(note_id) else {
+            return;
+        };
+        let last_format = note.export_format.unwrap_or_default();
+        let default_filename = export::render_file_name(
+            &self.settings.export_filename_template,
+            note,
+            last_format.extension(),
+        );
+
+        // Show save dialog
+        let Some(path) = last_format
+            .add_filters(
+                rfd::FileDialog::new()
+                    .set_title("Export Note")
+                    .set_file_name(&default_filename),
+            )
+            .save_file()
+        else {
+            return;
+        };
+        let format = ExportFormat::from_path(&path).unwrap_or(last_format);
+
+        match self.write_note_to_file(note, &path, format) {
+            Ok(_) => {
+                log::info!("Note {} exported successfully", note_id);
+                if let Some(note) = self.notes.get_mut(note_id) {
+                    if note.export_format != Some(format) {
+                        note.export_format = Some(format);
+                        self.save_notes();
+                    }
+                }
+                self.report_export(format!("Note exported to {}", path.display()), &path);
+            }
+            Err(e) => {
+                log::error!("Failed to export note {}: {}", note_id, e);
+                self.show_status(format!("Failed to export note: {}", e));
+            }
+        }
+    }
+
+    /// Writes a note to a file with its metadata.
+    ///
+    /// # Arguments
+    ///
+    /// * `note` - The note to write
+    /// * `path` - The file path to write to
+    /// * `format` - The format to write the note in
+    ///
+    /// # Returns
+    ///
+    /// * `anyhow::Result<()>` - Ok if successful, Err if an attachment could
+    ///   not be decrypted or a file operation failed
+    fn write_note_to_file(
+        &self,
+        note: &Note,
+        path: &std::path::Path,
+        format: ExportFormat,
+    ) -> anyhow::Result<()> {
+        use std::io::Write;
+
+        // Attachments go into a folder named after the exported file
+        let mut attachment_paths = Vec::new();
+        if !note.attachments.is_empty() {
+            let owner_id = self.note_owner(&note.id);
+            let crypto_manager = owner_id.as_deref().and_then(|id| self.owner_crypto(id));
+            if let (Some(crypto_manager), Some(owner_id)) = (crypto_manager, &owner_id) {
+                let stem = path
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .unwrap_or("note");
+                let folder_name = format!("{}_attachments", stem);
+                let file_names = attachments::export_attachments(
+                    &self.storage_manager,
+                    crypto_manager,
+                    owner_id,
+                    note,
+                    &path.with_file_name(&folder_name),
+                )?;
+                attachment_paths = file_names
+                    .iter()
+                    .map(|name| format!("{}/{}", folder_name, name))
+                    .collect();
+            }
+        }
+
+        match format {
+            ExportFormat::Markdown => {
+                std::fs::write(path, export::markdown_document(note, &attachment_paths))?;
+            }
+            ExportFormat::Html => {
+                std::fs::write(path, site_export::note_document(note, &attachment_paths))?;
+            }
+            ExportFormat::Text => {
+                let mut file = std::fs::File::create(path)?;
+
+                // Write note with metadata header
+                writeln!(file, "Title: {}", note.title)?;
+                writeln!(file, "Created: {}", note.format_created_time())?;
+                writeln!(file, "Modified: {}", note.format_modified_time())?;
+                writeln!(file, "ID: {}", note.id)?;
+                if !note.tags.is_empty() {
+                    writeln!(file, "Tags: {}", note.tags.join(", "))?;
+                }
+                for (key, value) in &note.fields {
+                    writeln!(file, "{}: {}", key, value)?;
+                }
+                if !attachment_paths.is_empty() {
+                    writeln!(file, "Attachments: {}", attachment_paths.join(", "))?;
+                }
+                writeln!(file, "{}", "=".repeat(50))?;
+                writeln!(file)?;
+                write!(file, "{}", note.content)?;
+                for entry in &note.secrets {
+                    writeln!(file)?;
+                    writeln!(file, "{}", "-".repeat(50))?;
+                    writeln!(file, "Label: {}", entry.label)?;
+                    writeln!(file, "Username: {}", entry.username)?;
+                    writeln!(file, "Secret: {}", entry.secret)?;
+                    writeln!(file, "URL: {}", entry.url)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Opens a web link in the default browser.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The `http` or `https` URL to open
+    pub fn open_url(&mut self, url: &str) {
+        log::debug!("Opening link in browser");
+        if let Err(e) = webbrowser::open(url) {
+            log::error!("Failed to open link: {}", e);
+            self.show_status(format!("Failed to open link: {}", e));
+        }
+    }
+
+    /// Starts reading a note aloud.
+    ///
+    /// Reads the text selected in the editor, or the whole note if nothing
+    /// is selected. Reading another note stops the current one.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The egui context, which holds the editor selection
+    /// * `note_id` - The ID of the note to read
+    pub fn read_note_aloud(&mut self, ctx: &egui::Context, note_id: &str) {
+        let Some(note) = self.notes.get(note_id) else {
+            return;
+        };
+        self.speaker = None;
+
+        let editor_id = egui::Id::new(("note_editor", note_id));
+        let selection = egui::text_edit::TextEditState::load(ctx, editor_id)
+            .and_then(|state| state.cursor.char_range())
+            .filter(|range| range.primary != range.secondary)
+            .map(|range| {
+                let [start, end] = range.sorted();
+                note.content
+                    .chars()
+                    .skip(start.index)
+                    .take(end.index - start.index)
+                    .collect::<String>()
+            });
+        let text = selection.unwrap_or_else(|| format!("{}.\n\n{}", note.title, note.content));
+
+        match Speaker::start(note_id, &text) {
+            Ok(speaker) => self.speaker = Some(speaker),
+            Err(e) => {
+                log::warn!("Failed to read note aloud: {}", e);
+                self.show_status(format!("Could not read aloud: {}", e));
+            }
+        }
+    }
+
+    /// Advances reading aloud and drops the speaker once it is done.
+    fn poll_speech(&mut self) {
+        if self
+            .speaker
+            .as_mut()
+            .is_some_and(|speaker| speaker.poll() == SpeechState::Finished)
+        {
+            self.speaker = None;
+        }
+    }
+
+    /// Attaches files chosen in a file dialog to a note.
+    ///
+    /// Each file is encrypted into its own attachment file and the note is
+    /// saved. Files that cannot be read are reported in the status message.
+    ///
+    /// # Arguments
+    ///
+    /// * `note_id` - The ID of the note to attach the files to
+    pub fn attach_files_to_note(&mut self, note_id: &str) {
+        let Some(owner_id) = self.note_owner(note_id) else {
+            return;
+        };
+        let Some(crypto_manager) = self.owner_crypto(&owner_id) else {
+            return;
+        };
+        let Some(paths) = rfd::FileDialog::new()
+            .set_title("Attach Files")
+            .pick_files()
+        else {
+            return;
+        };
+
+        // The quota covers the user's own files, not the shared vault
+        let mut quota = self
+            .current_user
+            .as_ref()
+            .filter(|user| user.id == owner_id)
+            .and_then(|user| {
+                self.storage_manager
+                    .quota_usage(&user.id, self.settings.storage_quota())
+            });
+
+        let mut attached = Vec::new();
+        let mut failed = 0;
+        let mut over_quota = 0;
+        for path in paths {
+            let file_name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "attachment".to_string());
+            if let Some(usage) = &mut quota {
+                let size = std::fs::metadata(&path).map_or(0, |metadata| metadata.len());
+                if !usage.fits(size) {
+                    log::warn!("Not attaching {}: storage quota exceeded", path.display());
+                    over_quota += 1;
+                    continue;
+                }
+                usage.used_bytes += size;
+            }
+            let result = std::fs::read(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|data| {
+                    let attachment = Attachment::new(file_name, data.len() as u64);
+                    self.storage_manager.save_attachment(
+                        &owner_id,
+                        note_id,
+                        &attachment,
+                        &data,
+                        crypto_manager,
+                    )?;
+                    Ok(attachment)
+                });
+            match result {
+                Ok(attachment) => attached.push(attachment),
+                Err(e) => {
+                    log::error!("Failed to attach {}: {}", path.display(), e);
+                    failed += 1;
+                }
+            }
+        }
+
+        let count = attached.len();
+        if let Some(note) = self.notes.get_mut(note_id) {
+            note.attachments.extend(attached);
+            note.update_modified_time();
+            self.search_index.note_changed(note);
+        }
+        self.save_notes();
+
+        self.show_status(if over_quota > 0 {
+            format!(
+                "{} file(s) attached, {} not attached: storage quota exceeded",
+                count, over_quota
+            )
+        } else if failed > 0 {
+            format!("{} file(s) attached, {} failed", count, failed)
+        } else {
+            format!("{} file(s) attached", count)
+        });
+        // A crossed quota threshold replaces the message above
+        self.check_storage_quota(true);
+    }
+
+    /// Removes an attachment from a note and securely deletes its file.
+    ///
+    /// # Arguments
+    ///
+    /// * `note_id` - The ID of the note
+    /// * `attachment_id` - The ID of the attachment to remove
+    pub fn remove_attachment(&mut self, note_id: &str, attachment_id: &str) {
+        let Some(note) = self.notes.get_mut(note_id) else {
+            return;
+        };
+        note.attachments
+            .retain(|attachment| attachment.id != attachment_id);
+        if self
+            .attachment_preview
+            .as_ref()
+            .is_some_and(|preview| preview.attachment_id == attachment_id)
+        {
+            self.attachment_preview = None;
+        }
+        note.update_modified_time();
+        self.search_index.note_changed(note);
+        self.save_notes();
+
+        if let Some(owner_id) = self.note_owner(note_id) {
+            if let Err(e) =
+                self.storage_manager
+                    .delete_attachment(&owner_id, note_id, attachment_id)
+            {
+                log::error!("Failed to delete attachment {}: {}", attachment_id, e);
+            }
+        }
+        self.check_storage_quota(true);
+    }
+
+    /// Decrypts an attachment into memory and shows its preview.
+    ///
+    /// Previewing the attachment already shown closes the preview.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The egui context, used to upload images
+    /// * `note_id` - The ID of the note
+    /// * `attachment_id` - The ID of the attachment to preview
+    pub fn toggle_attachment_preview(
+        &mut self,
+        ctx: &egui::Context,
+        note_id: &str,
+        attachment_id: &str,
+    ) {
+        if self
+            .attachment_preview
+            .take()
+            .is_some_and(|preview| preview.attachment_id == attachment_id)
+        {
+            return;
+        }
+        let Some(owner_id) = self.note_owner(note_id) else {
+            return;
+        };
+        let Some(crypto_manager) = self.owner_crypto(&owner_id) else {
+            return;
+        };
+        let Some(attachment) = self
+            .notes
+            .get(note_id)
+            .and_then(|note| note.attachments.iter().find(|a| a.id == attachment_id))
+        else {
+            return;
+        };
+
+        let content = if attachment.size > attachment_preview::MAX_PREVIEW_SIZE {
+            PreviewContent::Unsupported("The file is too large to preview".to_string())
+        } else {
+            match self.storage_manager.load_attachment(
+                &owner_id,
+                note_id,
+                attachment,
+                crypto_manager,
+            ) {
+                Ok(data) => attachment_preview::decode(ctx, attachment, &data),
+                Err(e) => {
+                    log::error!("Failed to preview attachment {}: {}", attachment_id, e);
+                    PreviewContent::Unsupported(format!("The file can't be decrypted: {}", e))
+                }
+            }
+        };
+        self.attachment_preview = Some(AttachmentPreview {
+            note_id: note_id.to_string(),
+            attachment_id: attachment_id.to_string(),
+            file_name: attachment.file_name.clone(),
+            content,
+        });
+    }
+
+    /// Decrypts an attachment and saves it to a file chosen in a dialog.
+    ///
+    /// # Arguments
+    ///
+    /// * `note_id` - The ID of the note
+    /// * `attachment_id` - The ID of the attachment to save
+    pub fn save_attachment_as(&mut self, note_id: &str, attachment_id: &str) {
+        let Some(owner_id) = self.note_owner(note_id) else {
+            return;
+        };
+        let Some(crypto_manager) = self.owner_crypto(&owner_id) else {
+            return;
+        };
+        let Some(attachment) = self
+            .notes
+            .get(note_id)
+            .and_then(|note| note.attachments.iter().find(|a| a.id == attachment_id))
+        else {
+            return;
+        };
+        let Some(path) = rfd::FileDialog::new()
+            .set_title("Save Attachment")
+            .set_file_name(attachments::sanitize_file_name(&attachment.file_name))
+            .save_file()
+        else {
+            return;
+        };
+
+        let result = self
+            .storage_manager
+            .load_attachment(&owner_id, note_id, attachment, crypto_manager)
+            .and_then(|data| Ok(std::fs::write(&path, data)?));
+        match result {
+            Ok(()) => self.report_export(format!("Saved {}", path.display()), &path),
+            Err(e) => {
+                log::error!("Failed to save attachment {}: {}", attachment_id, e);
+                self.show_status(format!("Failed to save attachment: {}", e));
+            }
+        }
+    }
+}
+
+impl NotesApp {
+    /// Renders one frame of the application.
+    ///
+    /// Handles:
+    /// - Authentication result checking
+    /// - Keyboard shortcuts
+    /// - UI rendering
+    /// - Auto-save functionality
+    /// - Status message management
     ///
     /// # Arguments
     ///
     /// * `ctx` - The egui context
-    /// * `_frame` - The eframe frame (unused)
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+    fn update_frame(&mut self, ctx: &egui::Context) {
         // Check for authentication results
         self.check_authentication_result();
+        self.poll_note_loading(ctx);
+        self.poll_migration_import(ctx);
+        self.poll_reencrypt_job(ctx);
+        self.poll_export_hooks(ctx);
+        self.maintain_note_lock();
+        self.handle_session_events(ctx);
+        self.poll_time_check(ctx);
 
         if self.is_authenticated {
+            let mut toggle_always_on_top = false;
+            // Shortcuts follow the user's keymap, defaults noted below
+            let keymap = self.settings.keymap.clone();
             ctx.input(|i| {
-                // Ctrl+N for new note
-                if i.modifiers.ctrl && i.key_pressed(egui::Key::N) {
-                    self.show_new_note_dialog = true;
-                    self.new_note_title.clear();
+                // New note (Ctrl+N by default)
+                if keymap.pressed(i, ShortcutAction::NewNote) {
+                    self.open_new_note_dialog();
                 }
 
-                // Ctrl+S for manual save
-                if i.modifiers.ctrl && i.key_pressed(egui::Key::S) {
+                // Manual save (Ctrl+S)
+                if keymap.pressed(i, ShortcutAction::Save) {
                     self.save_notes();
-                    self.status_message = Some("Note saved!".to_string());
+                    self.show_status("Note saved!".to_string());
                 }
 
                 // Escape to close dialogs
@@ -623,76 +3137,357 @@ impl eframe::App for NotesApp {
                     if self.show_user_settings {
                         self.show_user_settings = false;
                     }
+                    if self.show_log_viewer {
+                        self.show_log_viewer = false;
+                    }
+                    if self.show_startup_profile {
+                        self.show_startup_profile = false;
+                    }
+                    if self.show_search_results {
+                        self.show_search_results = false;
+                    }
+                    // A running import keeps the wizard open for its progress
+                    if self.show_migration_wizard && self.import_job.is_none() {
+                        self.show_migration_wizard = false;
+                        self.migration_wizard.reset();
+                    }
                 }
 
-                // Ctrl+T for switching between time display modes
-                if i.modifiers.ctrl && i.key_pressed(egui::Key::T) {
+                // Switch between time display modes (Ctrl+T)
+                if keymap.pressed(i, ShortcutAction::ToggleTimeFormat) {
                     self.show_time_format = match self.show_time_format {
                         TimeFormat::Relative => {
-                            self.status_message =
-                                Some("Time format: Absolute (15.12.2024 14:30)".to_string());
+                            self.show_status(
+                                "Time format: Absolute (15.12.2024 14:30)".to_string(),
+                            );
                             TimeFormat::Absolute
                         }
                         TimeFormat::Absolute => {
-                            self.status_message =
-                                Some("Time format: Relative (2 hours ago)".to_string());
+                            self.show_status("Time format: Relative (2 hours ago)".to_string());
                             TimeFormat::Relative
                         }
                     };
-                    self.status_message_time = Some(std::time::Instant::now());
                 }
 
-                // Ctrl+R for Relative time format
-                if i.modifiers.ctrl && i.key_pressed(egui::Key::R) {
+                // Relative time format (Ctrl+R)
+                if keymap.pressed(i, ShortcutAction::RelativeTime) {
                     self.show_time_format = TimeFormat::Relative;
-                    self.status_message =
-                        Some("Time format: Relative (X [minutes | hours | days] ago)".to_string());
-                    self.status_message_time = Some(std::time::Instant::now());
+                    self.show_status(
+                        "Time format: Relative (X [minutes | hours | days] ago)".to_string(),
+                    );
                 }
 
-                // Ctrl+Alt+A for Absolute time format
-                if i.modifiers.ctrl && i.modifiers.alt && i.key_pressed(egui::Key::A) {
+                // Absolute time format (Ctrl+Alt+A)
+                if keymap.pressed(i, ShortcutAction::AbsoluteTime) {
                     self.show_time_format = TimeFormat::Absolute;
-                    self.status_message =
-                        Some("Time format: Absolute (dd.mm.YYYY hh:mm)".to_string());
-                    self.status_message_time = Some(std::time::Instant::now());
+                    self.show_status("Time format: Absolute (dd.mm.YYYY hh:mm)".to_string());
                 }
 
-                // Ctrl+E to export note
-                if i.modifiers.ctrl && i.key_pressed(egui::Key::E) {
-                    if let Some(ref note_id) = self.selected_note_id {
-                        self.export_note_to_file(note_id);
+                // Export note (Ctrl+E)
+                if keymap.pressed(i, ShortcutAction::ExportNote) {
+                    if let Some(note_id) = self.selected_note_id.clone() {
+                        self.export_note_to_file(&note_id);
                     }
                 }
+
+                // Keep the window on top (Ctrl+Shift+P)
+                if keymap.pressed(i, ShortcutAction::AlwaysOnTop) {
+                    toggle_always_on_top = true;
+                }
+
+                // Quick capture to the inbox (Ctrl+Shift+I)
+                if keymap.pressed(i, ShortcutAction::QuickCapture) {
+                    self.open_quick_capture();
+                }
             });
 
-            // Clear status message after 3 seconds
+            // Viewport commands can't be sent while the input is borrowed
+            if toggle_always_on_top {
+                self.set_always_on_top(ctx, !self.always_on_top);
+            }
+
+            // Clear the status message after the configured duration
             if let Some(message_time) = self.status_message_time {
-                if message_time.elapsed() > std::time::Duration::from_secs(3) {
+                if message_time.elapsed() > self.settings.status_message_duration() {
                     self.status_message = None;
                     self.status_message_time = None;
+                    self.status_folder = None;
                 }
             }
         }
 
+        self.clear_clipboard_if_due(ctx);
+
         if self.show_auth_dialog {
             self.render_auth_dialog(ctx);
+            self.render_crash_recovery_dialog(ctx);
             return;
         }
 
         // Render the main application UI
         self.render_notes_sidebar(ctx);
+        self.render_outline_panel(ctx);
         self.render_main_content(ctx);
         self.render_security_panel(ctx);
         self.render_new_note_dialog(ctx);
         self.render_user_settings(ctx);
         self.render_change_password_dialog(ctx);
         self.render_delete_account_dialog(ctx);
+        self.render_log_viewer(ctx);
+        self.render_startup_profile(ctx);
+        self.render_migration_wizard(ctx);
+        self.render_legacy_migration_dialog(ctx);
+        self.render_snapshot_viewer(ctx);
+        self.render_note_share_dialog(ctx);
+        self.render_note_email_dialog(ctx);
+        self.render_note_history_dialog(ctx);
+        self.render_two_person_dialog(ctx);
+        self.render_quick_capture(ctx);
+        self.render_inbox_dialog(ctx);
+        self.render_health_check(ctx);
+        self.render_recovery_dialog(ctx);
+        self.render_settings_transfer_dialog(ctx);
+        self.render_rename_dialog(ctx);
+        self.render_rename_report(ctx);
+        self.render_link_graph(ctx);
+        self.render_note_stats(ctx);
+        self.render_site_export_dialog(ctx);
+        self.render_note_diagnostics(ctx);
+        self.render_search_results(ctx);
+        // Last, as it blocks everything else while it runs
+        self.render_reencrypt_dialog(ctx);
 
         // Auto-save functionality
         self.auto_save_if_needed();
+        self.purge_trash_if_due();
+        self.scan_integrity_if_due();
+        self.poll_speech();
+    }
+
+    /// Asks for the next frame when something has to happen without input.
+    ///
+    /// egui draws a frame on input; timers only run when a frame is drawn.
+    /// The next frame is requested for the earliest of: a pending auto-save,
+    /// clearing the clipboard, the next note lock check, trash purge or
+    /// integrity scan, polling a running scan or the speech engine, and,
+    /// while the window is not minimized, hiding the status message and
+    /// updating a visible clock or relative times. With none of these the
+    /// application sleeps until the next input.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The egui context
+    fn schedule_repaint(&self, ctx: &egui::Context) {
+        let now = std::time::Instant::now();
+        let minimized = ctx.input(|i| i.viewport().minimized.unwrap_or(false));
+        let mut deadlines = Vec::new();
+
+        // Work that is due also while the window is minimized
+        if self.has_unsaved_changes {
+            if let Some(delay) = self.settings.auto_save_delay() {
+                deadlines.push(self.last_save_time + delay);
+            }
+            if let (Some(max_wait), Some(since)) =
+                (self.settings.auto_save_max_wait(), self.unsaved_since)
+            {
+                deadlines.push(since + max_wait);
+            }
+        }
+        deadlines.extend(self.clipboard_clear_deadline());
+        if self.held_note_lock.is_some() || self.foreign_note_lock.is_some() {
+            deadlines.extend(self.last_lock_check.map(|time| time + LOCK_CHECK_INTERVAL));
+        }
+        deadlines.extend(
+            self.last_trash_purge
+                .map(|time| time + TRASH_PURGE_INTERVAL),
+        );
+        deadlines.extend(self.next_integrity_scan);
+        let speaking = self.speaker.as_ref().is_some_and(|s| !s.is_paused());
+        if self.integrity_scan.is_some() || speaking {
+            deadlines.push(now + BACKGROUND_POLL_INTERVAL);
+        }
+
+        // What only changes how the window looks
+        if !minimized {
+            deadlines.extend(
+                self.status_message_time
+                    .map(|time| time + self.settings.status_message_duration()),
+            );
+            let clock_visible = if self.show_auth_dialog {
+                !self.is_authenticating
+            } else {
+                self.selected_note_id.is_none() || self.show_security_panel
+            };
+            if clock_visible {
+                deadlines.push(now + std::time::Duration::from_secs(1));
+            }
+            if self.is_authenticated && self.show_time_format == TimeFormat::Relative {
+                deadlines.push(now + RELATIVE_TIME_REFRESH);
+            }
+        }
+
+        if let Some(next) = deadlines.into_iter().min() {
+            let min_delay = if minimized {
+                MINIMIZED_TIMER_DELAY
+            } else {
+                MIN_TIMER_DELAY
+            };
+            ctx.request_repaint_after(next.saturating_duration_since(now).max(min_delay));
+        }
+    }
+}
+
+impl eframe::App for NotesApp {
+    /// Main update loop for the application.
+    ///
+    /// Called by eframe on every frame. Delegates to `update_frame` and, if
+    /// rendering panics, makes a best-effort attempt to write unsaved notes
+    /// before letting the panic continue. The crash report itself is written
+    /// by the panic hook installed in `main`.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The egui context
+    /// * `frame` - The eframe frame, used for native window flags
+    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        let started = std::time::Instant::now();
+        let result =
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| self.update_frame(ctx)));
+        if !self.first_frame_rendered {
+            self.first_frame_rendered = true;
+            profiling::record("First frame", started);
+        }
+        self.schedule_repaint(ctx);
+        self.apply_capture_protection(frame);
+
+        if let Err(payload) = result {
+            if self.is_authenticated && self.has_unsaved_changes {
+                log::warn!("Attempting emergency save of unsaved notes after panic");
+                self.save_notes();
+            }
+            std::panic::resume_unwind(payload);
+        }
+    }
+
+    /// Flushes pending edits when the application window is closed.
+    ///
+    /// Ensures that edits made in manual-save-only mode (or within the
+    /// auto-save delay) are not lost on exit.
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        self.settle_reencrypt_job();
+        if self.is_authenticated && self.has_unsaved_changes {
+            self.save_notes();
+        }
+        self.remember_session();
+        self.release_note_lock();
+    }
+}
+
+/// Leaves out the notes of the shared vault, which are saved separately.
+///
+/// # Arguments
+///
+/// * `notes` - All notes in memory
+///
+/// # Returns
+///
+/// * `Cow<HashMap<String, Note>>` - The user's private notes, borrowed if
+///   there are no shared notes
+fn private_notes(notes: &HashMap<String, Note>) -> Cow<'_, HashMap<String, Note>> {
+    if notes.values().any(|note| note.shared) {
+        Cow::Owned(
+            notes
+                .iter()
+                .filter(|(_, note)| !note.shared)
+                .map(|(id, note)| (id.clone(), note.clone()))
+                .collect(),
+        )
+    } else {
+        Cow::Borrowed(notes)
+    }
+}
+
+/// Unlocks the encrypted session of an authenticated user.
+///
+/// Runs on the authentication thread and derives the encryption key (the
+/// slow part), reporting progress through `report`. The notes themselves are
+/// decrypted afterwards by the background note loader.
+///
+/// # Arguments
+///
+/// * `user` - The authenticated user
+/// * `password` - The user's password for key derivation
+/// * `report` - Callback receiving progress updates
+///
+/// # Returns
+///
+/// * `AuthResult` - Success with crypto manager, a request for the second
+///   passphrase of two-person unlock, or an error
+fn unlock_user_session(user: User, password: &str, report: &dyn Fn(AuthStage)) -> AuthResult {
+    let mut crypto_manager = CryptoManager::new();
+    let started = std::time::Instant::now();
+    let initialized =
+        crypto_manager.initialize_for_user_with_progress(&user.id, password, &|estimate| {
+            report(AuthStage::DerivingKey(estimate))
+        });
+    profiling::record("Key derivation", started);
+    if initialized.is_ok() && crypto_manager.awaiting_second_passphrase() {
+        // Recorded once the second passphrase was checked
+        return AuthResult::SecondPassphrase(Box::new(crypto_manager), user);
+    }
+    if let Err(e) = initialized {
+        log::error!("Crypto initialization failed: {}", e);
+        record_login(
+            &user.id,
+            LoginOutcome::Failed,
+            crypto_manager.hardware_check(),
+        );
+        return AuthResult::Error(format!("Authentication failed: {}", e));
+    }
+
+    record_login(
+        &user.id,
+        LoginOutcome::Success,
+        crypto_manager.hardware_check(),
+    );
+    AuthResult::Success(Box::new(crypto_manager), user)
+}
+
+/// Checks a password against the user database, profiling the check.
+///
+/// # Arguments
+///
+/// * `user_manager` - The user database
+/// * `username` - The username entered
+/// * `password` - The password entered
+///
+/// # Returns
+///
+/// * `anyhow::Result<User>` - The user, or error if the password is wrong
+fn verify_password(
+    user_manager: &UserManager,
+    username: &str,
+    password: &str,
+) -> anyhow::Result<User> {
+    let started = std::time::Instant::now();
+    let result = user_manager.authenticate(username, password);
+    profiling::record("Password verification", started);
+    result
+}
 
-        // Request repaint for auto-save timing and relative time updates
-        ctx.request_repaint_after(std::time::Duration::from_millis(500));
+/// Adds a login attempt to a user's login history.
+///
+/// Runs on an authentication thread. A history that cannot be written
+/// doesn't stop the login.
+///
+/// # Arguments
+///
+/// * `user_id` - The user who tried to log in
+/// * `outcome` - Result of the attempt
+/// * `hardware` - Outcome of the hardware check, if it ran
+pub fn record_login(user_id: &str, outcome: LoginOutcome, hardware: Option<HardwareCheck>) {
+    let record = LoginRecord::new(outcome, hardware);
+    if let Err(e) = StorageManager::new().record_login(user_id, record) {
+        log::warn!("Could not record login attempt: {}", e);
     }
 }
@@ -0,0 +1,638 @@
+//! # Attachment Preview Module
+//!
+//! Previews of attachments shown inside the note view, so a file doesn't
+//! have to be saved and opened elsewhere just to see what it is. The
+//! decrypted content is only held in memory while the preview is open and
+//! is never written to disk.
+//!
+//! Supported are images (PNG, JPEG, GIF, BMP, WebP, ...), text files and
+//! PDFs. Rendering a PDF page needs a full PDF engine, so for PDFs the page
+//! count and the text of the first page are shown instead. The text is
+//! read from the first content stream with text in it, using the fonts'
+//! `ToUnicode` maps where present, which works for the PDFs written by
+//! office programs, browsers and LaTeX. Scanned pages have no text.
+
+use crate::note::Attachment;
+use flate2::read::ZlibDecoder;
+use std::collections::HashMap;
+use std::io::Read;
+
+/// Largest attachment that is previewed, in bytes.
+pub const MAX_PREVIEW_SIZE: u64 = 25 * 1024 * 1024;
+
+/// Longest image side in pixels; larger images are scaled down.
+const MAX_IMAGE_SIDE: u32 = 1600;
+
+/// Number of characters of a text file that are shown.
+const MAX_TEXT_CHARS: usize = 100_000;
+
+/// Extensions of files shown as text even if they are not valid UTF-8.
+const TEXT_EXTENSIONS: &[&str] = &[
+    "txt", "md", "markdown", "csv", "tsv", "log", "json", "xml", "yaml", "yml", "toml", "ini",
+    "cfg", "conf", "html", "htm", "css", "js", "ts", "rs", "py", "c", "h", "cpp", "java", "sh",
+];
+
+/// An open attachment preview.
+pub struct AttachmentPreview {
+    /// ID of the note the attachment belongs to
+    pub note_id: String,
+    /// ID of the previewed attachment
+    pub attachment_id: String,
+    /// File name of the attachment
+    pub file_name: String,
+    /// What is shown
+    pub content: PreviewContent,
+}
+
+/// The decoded content of a preview.
+pub enum PreviewContent {
+    /// An image uploaded to the GPU, freed when dropped
+    Image {
+        /// The image texture
+        texture: eframe::egui::TextureHandle,
+        /// Width and height of the original image
+        original_size: [u32; 2],
+    },
+    /// The start of a text file
+    Text {
+        /// The text
+        text: String,
+        /// Whether the file is longer than `text`
+        truncated: bool,
+    },
+    /// Summary of a PDF
+    Pdf {
+        /// Number of pages, if found
+        page_count: Option<usize>,
+        /// Text of the first page, empty if none could be read
+        first_page_text: String,
+    },
+    /// The file can't be previewed
+    Unsupported(String),
+}
+
+/// Decodes a decrypted attachment for previewing.
+///
+/// # Arguments
+///
+/// * `ctx` - The egui context, used to upload images
+/// * `attachment` - Metadata of the attachment
+/// * `data` - The decrypted content
+///
+/// # Returns
+///
+/// * `PreviewContent` - The preview, or `Unsupported` with the reason
+pub fn decode(ctx: &eframe::egui::Context, attachment: &Attachment, data: &[u8]) -> PreviewContent {
+    let extension = attachment
+        .file_name
+        .rsplit_once('.')
+        .map(|(_, extension)| extension.to_lowercase())
+        .unwrap_or_default();
+
+    if data.starts_with(b"%PDF-") {
+        return PreviewContent::Pdf {
+            page_count: pdf_page_count(data),
+            first_page_text: pdf_first_page_text(data),
+        };
+    }
+
+    if let Ok(format) = image::guess_format(data) {
+        return match image::load_from_memory_with_format(data, format) {
+            Ok(image) => {
+                let original_size = [image.width(), image.height()];
+                let image = if image.width() > MAX_IMAGE_SIDE || image.height() > MAX_IMAGE_SIDE {
+                    image.thumbnail(MAX_IMAGE_SIDE, MAX_IMAGE_SIDE)
+                } else {
+                    image
+                };
+                let rgba = image.to_rgba8();
+                let color_image = eframe::egui::ColorImage::from_rgba_unmultiplied(
+                    [rgba.width() as usize, rgba.height() as usize],
+                    rgba.as_raw(),
+                );
+                PreviewContent::Image {
+                    texture: ctx.load_texture(
+                        format!("attachment_preview_{}", attachment.id),
+                        color_image,
+                        Default::default(),
+                    ),
+                    original_size,
+                }
+            }
+            Err(e) => PreviewContent::Unsupported(format!("The image can't be read: {}", e)),
+        };
+    }
+
+    let text = match std::str::from_utf8(data) {
+        Ok(text) if !text.contains('\0') => Some(text.to_string()),
+        _ if TEXT_EXTENSIONS.contains(&extension.as_str()) => {
+            Some(String::from_utf8_lossy(data).into_owned())
+        }
+        _ => None,
+    };
+    match text {
+        Some(text) => {
+            let truncated = text.chars().count() > MAX_TEXT_CHARS;
+            PreviewContent::Text {
+                text: if truncated {
+                    text.chars().take(MAX_TEXT_CHARS).collect()
+                } else {
+                    text
+                },
+                truncated,
+            }
+        }
+        None => {
+            PreviewContent::Unsupported("No preview is available for this file type".to_string())
+        }
+    }
+}
+
+/// Reads the number of pages of a PDF.
+///
+/// The root page tree node has the `/Count` of all pages, which is the
+/// largest `/Count` in the file. PDFs that keep their page tree in
+/// compressed object streams have none readable.
+///
+/// # Arguments
+///
+/// * `data` - The PDF file
+///
+/// # Returns
+///
+/// * `Option<usize>` - The page count, if found
+fn pdf_page_count(data: &[u8]) -> Option<usize> {
+    let mut count = None;
+    let mut rest = data;
+    while let Some(position) = find(rest, b"/Count") {
+        rest = &rest[position + b"/Count".len()..];
+        let digits: Vec<u8> = rest
+            .iter()
+            .skip_while(|byte| byte.is_ascii_whitespace())
+            .take_while(|byte| byte.is_ascii_digit())
+            .copied()
+            .collect();
+        if let Some(value) = std::str::from_utf8(&digits)
+            .ok()
+            .and_then(|digits| digits.parse::<usize>().ok())
+        {
+            count = count.max(Some(value));
+        }
+    }
+    count
+}
+
+/// Extracts the text of the first page of a PDF.
+///
+/// Goes through the streams of the file in order and returns the text of
+/// the first one drawing text. Fonts that number their glyphs instead of
+/// using a standard encoding come with a `ToUnicode` map, which is used to
+/// turn the glyph numbers back into characters.
+///
+/// # Arguments
+///
+/// * `data` - The PDF file
+///
+/// # Returns
+///
+/// * `String` - The text, empty if none was found or it is not readable
+fn pdf_first_page_text(data: &[u8]) -> String {
+    let unicode_maps: Vec<UnicodeMap> = pdf_streams(data)
+        .filter(|content| find(content, b"begincmap").is_some())
+        .map(|content| UnicodeMap::parse(&content))
+        .filter(|map| !map.chars.is_empty())
+        .collect();
+
+    for content in pdf_streams(data) {
+        if find(&content, b"BT").is_none() || find(&content, b"begincmap").is_some() {
+            continue;
+        }
+        let text = content_stream_text(&content, &unicode_maps);
+        if !text.trim().is_empty() {
+            return if is_readable(&text) {
+                text
+            } else {
+                String::new()
+            };
+        }
+    }
+    String::new()
+}
+
+/// Iterates over the decoded streams of a PDF, in file order.
+///
+/// Images, embedded fonts, metadata and cross-reference data are skipped,
+/// as are streams with filters other than `FlateDecode`.
+fn pdf_streams(data: &[u8]) -> impl Iterator<Item = Vec<u8>> + '_ {
+    let mut offset = 0;
+    std::iter::from_fn(move || {
+        while let Some(start) = find(&data[offset..], b"stream") {
+            let keyword = offset + start;
+            offset = keyword + b"stream".len();
+            // Skip "endstream" and anything that is not a stream start
+            if keyword >= 3 && &data[keyword - 3..keyword] == b"end" {
+                continue;
+            }
+            let mut body = offset;
+            if data.get(body) == Some(&b'\r') {
+                body += 1;
+            }
+            if data.get(body) != Some(&b'\n') {
+                continue;
+            }
+            body += 1;
+            let length = find(&data[body..], b"endstream")?;
+            let raw = &data[body..body + length];
+            offset = body + length;
+
+            let dictionary = stream_dictionary(&data[..keyword]);
+            if [
+                b"/Image".as_slice(),
+                b"/XRef",
+                b"/ObjStm",
+                b"/Length1",
+                b"/Metadata",
+            ]
+            .iter()
+            .any(|name| find(dictionary, name).is_some())
+            {
+                continue;
+            }
+            if find(dictionary, b"/FlateDecode").is_some() {
+                return Some(inflate(raw));
+            } else if find(dictionary, b"/Filter").is_none() {
+                return Some(raw.to_vec());
+            }
+        }
+        None
+    })
+}
+
+/// A `ToUnicode` map of a font, from character codes to text.
+struct UnicodeMap {
+    /// Number of bytes per character code
+    code_length: usize,
+    /// Text of each code
+    chars: HashMap<u32, String>,
+}
+
+impl UnicodeMap {
+    /// Reads the `bfchar` and `bfrange` sections of a CMap.
+    ///
+    /// # Arguments
+    ///
+    /// * `cmap` - The inflated CMap stream
+    ///
+    /// # Returns
+    ///
+    /// * `Self` - The map, empty if the CMap has no mappings
+    fn parse(cmap: &[u8]) -> Self {
+        let mut map = Self {
+            code_length: 1,
+            chars: HashMap::new(),
+        };
+        let text = String::from_utf8_lossy(cmap);
+        let mut tokens = text
+            .split(|c: char| c.is_whitespace() || c == '<' || c == '>')
+            .filter(|token| !token.is_empty())
+            .peekable();
+        let mut section = "";
+        while let Some(token) = tokens.next() {
+            match token {
+                "beginbfchar" | "beginbfrange" => section = token,
+                "endbfchar" | "endbfrange" => section = "",
+                _ if section == "beginbfchar" => {
+                    let Some(target) = tokens.next() else {
+                        break;
+                    };
+                    map.insert(token, hex_utf16(target));
+                }
+                _ if section == "beginbfrange" => {
+                    let (Some(last), Some(target)) = (tokens.next(), tokens.next()) else {
+                        break;
+                    };
+                    let (Ok(first_code), Ok(last_code)) = (
+                        u32::from_str_radix(token, 16),
+                        u32::from_str_radix(last, 16),
+                    ) else {
+                        continue;
+                    };
+                    if let Some(target) = target.strip_prefix('[') {
+                        // One target per code: [<0041> <0042> ...]
+                        let mut targets = vec![target.to_string()];
+                        while let Some(next) = tokens.next_if(|token| !token.ends_with(']')) {
+                            targets.push(next.to_string());
+                        }
+                        if let Some(next) = tokens.next() {
+                            targets.push(next.trim_end_matches(']').to_string());
+                        }
+                        let targets = targets.iter().filter(|target| !target.is_empty());
+                        for (code, target) in (first_code..=last_code).zip(targets) {
+                            map.insert_code(token.len(), code, hex_utf16(target));
+                        }
+                    } else {
+                        // Consecutive codes map to consecutive characters
+                        let target = hex_utf16(target);
+                        let Some(base) = target.chars().last() else {
+                            continue;
+                        };
+                        let prefix: String =
+                            target.chars().take(target.chars().count() - 1).collect();
+                        for (step, code) in
+                            (first_code..=last_code.min(first_code + 0xFFFF)).enumerate()
+                        {
+                            if let Some(c) = char::from_u32(base as u32 + step as u32) {
+                                map.insert_code(token.len(), code, format!("{}{}", prefix, c));
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        map
+    }
+
+    /// Adds the text of a hex character code.
+    fn insert(&mut self, code: &str, text: String) {
+        if let Ok(value) = u32::from_str_radix(code, 16) {
+            self.insert_code(code.len(), value, text);
+        }
+    }
+
+    /// Adds the text of a character code written with `hex_digits` digits.
+    fn insert_code(&mut self, hex_digits: usize, code: u32, text: String) {
+        self.code_length = (hex_digits / 2).max(1);
+        self.chars.insert(code, text);
+    }
+
+    /// Decodes a string if this map has every code in it.
+    fn decode(&self, string: &[u8]) -> Option<String> {
+        if string.is_empty() || !string.len().is_multiple_of(self.code_length) {
+            return None;
+        }
+        string
+            .chunks(self.code_length)
+            .map(|code| {
+                let code = code
+                    .iter()
+                    .fold(0u32, |code, &byte| code << 8 | u32::from(byte));
+                self.chars.get(&code).map(String::as_str)
+            })
+            .collect()
+    }
+}
+
+/// Decodes hex UTF-16BE text as written in CMaps.
+fn hex_utf16(hex: &str) -> String {
+    let units: Vec<u16> = hex
+        .as_bytes()
+        .chunks(4)
+        .filter_map(|unit| u16::from_str_radix(std::str::from_utf8(unit).ok()?, 16).ok())
+        .collect();
+    String::from_utf16_lossy(&units)
+}
+
+/// Decodes a string drawn by a text operator.
+///
+/// Uses the first `ToUnicode` map that has every code of the string, and
+/// Latin-1 if none has.
+fn decode_pdf_string(string: &[u8], unicode_maps: &[UnicodeMap]) -> String {
+    unicode_maps
+        .iter()
+        .find_map(|map| map.decode(string))
+        .unwrap_or_else(|| string.iter().map(|&byte| byte as char).collect())
+}
+
+/// Returns the dictionary before a `stream` keyword.
+///
+/// # Arguments
+///
+/// * `before` - The file up to the keyword
+///
+/// # Returns
+///
+/// * `&[u8]` - The bytes from the last `obj` keyword, or the last 512 bytes
+fn stream_dictionary(before: &[u8]) -> &[u8] {
+    let window = &before[before.len().saturating_sub(512)..];
+    match window.windows(3).rposition(|bytes| bytes == b"obj") {
+        Some(position) => &window[position..],
+        None => window,
+    }
+}
+
+/// Inflates a zlib stream, keeping what was read before an error.
+fn inflate(raw: &[u8]) -> Vec<u8> {
+    let mut output = Vec::new();
+    let mut decoder = ZlibDecoder::new(raw);
+    let mut buffer = [0u8; 8192];
+    while let Ok(read) = decoder.read(&mut buffer) {
+        if read == 0 {
+            break;
+        }
+        output.extend_from_slice(&buffer[..read]);
+    }
+    output
+}
+
+/// Collects the strings drawn by the text operators of a content stream.
+///
+/// Lines end at line moves and text objects, words at large gaps in `TJ`
+/// arrays.
+///
+/// # Arguments
+///
+/// * `content` - The inflated content stream
+/// * `unicode_maps` - The `ToUnicode` maps of the fonts in the file
+///
+/// # Returns
+///
+/// * `String` - The text
+fn content_stream_text(content: &[u8], unicode_maps: &[UnicodeMap]) -> String {
+    let mut text = String::new();
+    // Strings and gaps since the last operator
+    let mut operands: Vec<Result<Vec<u8>, f32>> = Vec::new();
+    let mut i = 0;
+
+    let new_line = |text: &mut String| {
+        if !text.is_empty() && !text.ends_with('\n') {
+            text.push('\n');
+        }
+    };
+
+    while i < content.len() {
+        let byte = content[i];
+        match byte {
+            b'(' => {
+                let (string, end) = literal_string(content, i + 1);
+                operands.push(Ok(string));
+                i = end;
+            }
+            b'<' if content.get(i + 1) != Some(&b'<') => {
+                let end = find(&content[i..], b">").map_or(content.len(), |end| i + end);
+                let digits: Vec<u8> = content[i + 1..end]
+                    .iter()
+                    .filter(|byte| byte.is_ascii_hexdigit())
+                    .copied()
+                    .collect();
+                let string = digits
+                    .chunks(2)
+                    .filter_map(|pair| {
+                        let pair = std::str::from_utf8(pair).ok()?;
+                        u8::from_str_radix(&format!("{:0<2}", pair), 16).ok()
+                    })
+                    .collect();
+                operands.push(Ok(string));
+                i = end + 1;
+            }
+            b'/' => {
+                i += 1;
+                while i < content.len()
+                    && !content[i].is_ascii_whitespace()
+                    && !b"/[]()<>{}%".contains(&content[i])
+                {
+                    i += 1;
+                }
+            }
+            b'%' => {
+                while i < content.len() && content[i] != b'\n' && content[i] != b'\r' {
+                    i += 1;
+                }
+            }
+            b'-' | b'+' | b'.' | b'0'..=b'9' => {
+                let start = i;
+                i += 1;
+                while i < content.len() && (content[i].is_ascii_digit() || content[i] == b'.') {
+                    i += 1;
+                }
+                if let Some(number) = std::str::from_utf8(&content[start..i])
+                    .ok()
+                    .and_then(|number| number.parse::<f32>().ok())
+                {
+                    operands.push(Err(number));
+                }
+            }
+            byte if byte.is_ascii_alphabetic() || byte == b'\'' || byte == b'"' || byte == b'*' => {
+                let start = i;
+                i += 1;
+                while i < content.len() && (content[i].is_ascii_alphabetic() || content[i] == b'*')
+                {
+                    i += 1;
+                }
+                match &content[start..i] {
+                    b"Tj" | b"'" | b"\"" | b"TJ" => {
+                        let operator = &content[start..i];
+                        if operator != b"Tj" && operator != b"TJ" {
+                            new_line(&mut text);
+                        }
+                        for operand in &operands {
+                            match operand {
+                                Ok(string) => {
+                                    text.push_str(&decode_pdf_string(string, unicode_maps))
+                                }
+                                // TJ gaps are in thousandths of the font size
+                                Err(gap) if operator == b"TJ" && *gap < -200.0 => {
+                                    if !text.ends_with(' ') {
+                                        text.push(' ');
+                                    }
+                                }
+                                Err(_) => {}
+                            }
+                        }
+                    }
+                    b"Td" | b"TD" => {
+                        if let Some(Err(y)) = operands.last() {
+                            if *y != 0.0 {
+                                new_line(&mut text);
+                            }
+                        }
+                    }
+                    b"T*" | b"ET" => new_line(&mut text),
+                    _ => {}
+                }
+                operands.clear();
+            }
+            _ => i += 1,
+        }
+    }
+
+    text.trim().to_string()
+}
+
+/// Reads a literal string, starting after its opening parenthesis.
+///
+/// # Returns
+///
+/// * `(Vec<u8>, usize)` - The unescaped bytes and the index after the
+///   closing parenthesis
+fn literal_string(content: &[u8], start: usize) -> (Vec<u8>, usize) {
+    let mut string = Vec::new();
+    let mut depth = 1;
+    let mut i = start;
+    while i < content.len() {
+        let byte = content[i];
+        i += 1;
+        match byte {
+            b'\\' => {
+                let Some(&escaped) = content.get(i) else {
+                    break;
+                };
+                i += 1;
+                match escaped {
+                    b'n' => string.push(b'\n'),
+                    b'r' => string.push(b'\r'),
+                    b't' => string.push(b'\t'),
+                    b'b' | b'f' => {}
+                    b'0'..=b'7' => {
+                        let mut value = u32::from(escaped - b'0');
+                        for _ in 0..2 {
+                            match content.get(i) {
+                                Some(&digit @ b'0'..=b'7') => {
+                                    value = value * 8 + u32::from(digit - b'0');
+                                    i += 1;
+                                }
+                                _ => break,
+                            }
+                        }
+                        string.push(value as u8);
+                    }
+                    b'\r' | b'\n' => {}
+                    other => string.push(other),
+                }
+            }
+            b'(' => {
+                depth += 1;
+                string.push(byte);
+            }
+            b')' => {
+                depth -= 1;
+                if depth == 0 {
+                    break;
+                }
+                string.push(byte);
+            }
+            _ => string.push(byte),
+        }
+    }
+    (string, i)
+}
+
+/// Tells whether extracted text is mostly printable.
+///
+/// Fonts with their own encodings give glyph numbers instead of
+/// characters, which come out as control characters.
+fn is_readable(text: &str) -> bool {
+    let total = text.chars().count();
+    let printable = text
+        .chars()
+        .filter(|c| !c.is_control() || c.is_whitespace())
+        .count();
+    printable * 10 >= total * 9
+}
+
+/// Finds the first occurrence of a byte string.
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
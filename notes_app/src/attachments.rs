@@ -0,0 +1,117 @@
+//! # Attachments Module
+//!
+//! Helpers for writing the decrypted attachments of a note to a folder,
+//! used when exporting. Attachment names come from the original files and
+//! may collide (two `image.png` from different folders) or contain
+//! characters that are invalid on the target file system, so they are
+//! sanitized and made unique before writing.
+
+use crate::crypto::CryptoManager;
+use crate::note::Note;
+use crate::storage::StorageManager;
+use anyhow::Result;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+/// Decrypts the attachments of a note into a directory.
+///
+/// The directory is created if needed. Files already in it are never
+/// overwritten; colliding names get a ` (2)`, ` (3)`, ... suffix.
+///
+/// # Arguments
+///
+/// * `storage` - Storage manager holding the encrypted attachments
+/// * `crypto` - CryptoManager instance for decryption
+/// * `user_id` - Owner of the note
+/// * `note` - The note whose attachments to write
+/// * `dir` - Destination directory
+///
+/// # Returns
+///
+/// * `Result<Vec<String>>` - File names written, in attachment order, or
+///   error if an attachment cannot be decrypted or written
+pub fn export_attachments(
+    storage: &StorageManager,
+    crypto: &CryptoManager,
+    user_id: &str,
+    note: &Note,
+    dir: &Path,
+) -> Result<Vec<String>> {
+    fs::create_dir_all(dir)?;
+
+    let mut taken: HashSet<String> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.file_name().to_str().map(str::to_lowercase))
+        .collect();
+
+    let mut written = Vec::with_capacity(note.attachments.len());
+    for attachment in &note.attachments {
+        let data = storage.load_attachment(user_id, &note.id, attachment, crypto)?;
+        let file_name = unique_file_name(&sanitize_file_name(&attachment.file_name), &mut taken);
+        fs::write(dir.join(&file_name), data)?;
+        written.push(file_name);
+    }
+
+    Ok(written)
+}
+
+/// Replaces characters that are not allowed in file names.
+///
+/// # Arguments
+///
+/// * `name` - The original file name
+///
+/// # Returns
+///
+/// * `String` - A file name valid on Windows, macOS and Linux
+pub fn sanitize_file_name(name: &str) -> String {
+    let sanitized: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let sanitized = sanitized.trim().trim_end_matches('.');
+
+    if sanitized.is_empty() {
+        "attachment".to_string()
+    } else {
+        sanitized.to_string()
+    }
+}
+
+/// Makes a file name unique among the names already taken.
+///
+/// Names are compared case-insensitively, since Windows and macOS file
+/// systems usually are. The returned name is added to `taken`.
+///
+/// # Arguments
+///
+/// * `name` - The desired file name
+/// * `taken` - Lowercase names already in use
+///
+/// # Returns
+///
+/// * `String` - `name`, or `name (n).ext` for the first free `n`
+fn unique_file_name(name: &str, taken: &mut HashSet<String>) -> String {
+    let (stem, extension) = match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => (stem, Some(extension)),
+        _ => (name, None),
+    };
+
+    let mut candidate = name.to_string();
+    let mut counter = 2;
+    while taken.contains(&candidate.to_lowercase()) {
+        candidate = match extension {
+            Some(extension) => format!("{} ({}).{}", stem, counter, extension),
+            None => format!("{} ({})", stem, counter),
+        };
+        counter += 1;
+    }
+
+    taken.insert(candidate.to_lowercase());
+    candidate
+}
@@ -8,9 +8,11 @@
 //! login, registration, and authentication state management.
 
 use crate::app::NotesApp;
+use crate::crash;
 use crate::crypto::CryptoManager;
 use crate::user::User;
 use eframe::egui;
+use std::time::Duration;
 
 /// Represents the current authentication mode in the UI.
 #[derive(Clone, Copy, PartialEq)]
@@ -21,13 +23,73 @@ pub enum AuthMode {
     Register,
 }
 
+/// Stage of a running authentication, reported by the background thread.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AuthStage {
+    /// Creating the account record (registration only)
+    CreatingAccount,
+    /// Checking the password against the stored hash
+    VerifyingPassword,
+    /// Running the expensive Argon2 key derivation, with its estimated duration
+    DerivingKey(Duration),
+}
+
+impl AuthStage {
+    /// Label shown above the progress bar.
+    pub fn label(&self) -> &'static str {
+        match self {
+            AuthStage::CreatingAccount => "Creating account",
+            AuthStage::VerifyingPassword => "Verifying password",
+            AuthStage::DerivingKey(_) => "Deriving key",
+        }
+    }
+
+    /// Portion of the overall progress bar covered by this stage.
+    ///
+    /// Key derivation dominates the total time, so it gets most of the bar.
+    fn progress_range(&self) -> (f32, f32) {
+        match self {
+            AuthStage::CreatingAccount => (0.0, 0.05),
+            AuthStage::VerifyingPassword => (0.05, 0.1),
+            AuthStage::DerivingKey(_) => (0.1, 1.0),
+        }
+    }
+
+    /// Computes the overall progress for this stage.
+    ///
+    /// # Arguments
+    ///
+    /// * `elapsed` - Time spent in this stage so far
+    ///
+    /// # Returns
+    ///
+    /// * `f32` - Progress between 0.0 and 1.0
+    pub fn progress(&self, elapsed: Duration) -> f32 {
+        let (start, end) = self.progress_range();
+        let within_stage = match self {
+            AuthStage::DerivingKey(estimated) if !estimated.is_zero() => {
+                // Never show a finished bar while the derivation is still running
+                (elapsed.as_secs_f32() / estimated.as_secs_f32()).min(0.99)
+            }
+            _ => 0.0,
+        };
+        start + (end - start) * within_stage
+    }
+}
+
 /// Result of an authentication attempt.
 ///
-/// Contains either successful authentication data (crypto manager and user)
-/// or an error message describing what went wrong.
+/// Contains either progress updates, successful authentication data (crypto
+/// manager and user) or an error message describing what
+/// went wrong.
 pub enum AuthResult {
+    /// Authentication entered a new stage
+    Progress(AuthStage),
     /// Authentication succeeded with crypto manager and user data
-    Success(CryptoManager, User),
+    Success(Box<CryptoManager>, User),
+    /// The password was right, but two-person unlock needs the second
+    /// passphrase before the key exists
+    SecondPassphrase(Box<CryptoManager>, User),
     /// Authentication failed with error message
     Error(String),
 }
@@ -56,10 +118,51 @@ impl NotesApp {
                 ui.heading("Secure Notes");
                 ui.add_space(20.0);
 
+                // Tampering with the user database must not go unnoticed
+                if let Some(warning) = self
+                    .user_manager
+                    .as_ref()
+                    .and_then(|manager| manager.integrity_warning())
+                {
+                    egui::Frame::none()
+                        .fill(egui::Color32::from_rgb(90, 20, 20))
+                        .rounding(6.0)
+                        .inner_margin(10.0)
+                        .show(ui, |ui| {
+                            ui.set_max_width(420.0);
+                            ui.colored_label(
+                                egui::Color32::from_rgb(255, 120, 120),
+                                egui::RichText::new("⚠ Security warning").strong(),
+                            );
+                            ui.label(warning);
+                            ui.small(
+                                "If you did not change these files yourself, do not log in \
+                                 and restore users.json from a trusted backup.",
+                            );
+                        });
+                    ui.add_space(15.0);
+                }
+
                 if self.is_authenticating {
-                    // Show loading state with progress information
-                    ui.label("Processing... Please wait");
-                    ui.spinner();
+                    // Show the current stage with a progress bar
+                    let (stage_label, progress) = match self.auth_stage {
+                        Some(stage) => (
+                            stage.label(),
+                            stage.progress(
+                                self.auth_stage_start
+                                    .map(|start| start.elapsed())
+                                    .unwrap_or_default(),
+                            ),
+                        ),
+                        None => ("Processing... Please wait", 0.0),
+                    };
+                    ui.label(stage_label);
+                    ui.add(
+                        egui::ProgressBar::new(progress)
+                            .desired_width(300.0)
+                            .show_percentage()
+                            .animate(true),
+                    );
 
                     // Show elapsed time for user feedback
                     if let Some(start_time) = self.auth_start_time {
@@ -84,12 +187,16 @@ impl NotesApp {
                                 self.is_authenticating = false;
                                 self.auth_receiver = None;
                                 self.auth_start_time = None;
+                                self.auth_stage = None;
+                                self.auth_stage_start = None;
                             }
                         }
                     }
 
                     // Request repaint to update timer
                     ctx.request_repaint_after(std::time::Duration::from_millis(100));
+                } else if self.pending_unlock.is_some() {
+                    self.render_second_passphrase_prompt(ui);
                 } else {
                     let screen_width = ui.available_width();
 
@@ -232,4 +339,84 @@ impl NotesApp {
             });
         });
     }
+
+    /// Renders the crash recovery dialog shown after an unexpected exit.
+    ///
+    /// Informs the user that the previous session crashed, where the
+    /// sanitized crash report was written and that edits made within the
+    /// auto-save delay before the crash may be missing. The report can be
+    /// copied for a bug report, dismissed (kept on disk) or deleted.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The egui context for rendering UI elements
+    pub fn render_crash_recovery_dialog(&mut self, ctx: &egui::Context) {
+        if !self.show_crash_dialog || self.crash_reports.is_empty() {
+            return;
+        }
+
+        let mut dismiss = false;
+        let mut delete_reports = false;
+
+        egui::Window::new("Secure Notes closed unexpectedly")
+            .collapsible(false)
+            .resizable(true)
+            .default_width(450.0)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "The application crashed {} time(s) since you last saw this message.",
+                    self.crash_reports.len()
+                ));
+                ui.label(
+                    "Your notes were saved up to the last auto-save. Changes made in the \
+                     last few seconds before the crash may be missing - please check your \
+                     most recently edited notes after logging in.",
+                );
+
+                ui.add_space(10.0);
+                ui.small(format!("Reports folder: {}", crash::crash_dir().display()));
+                ui.small("Reports contain no note content, passwords or usernames.");
+
+                if let Some(latest) = self.crash_reports.last() {
+                    ui.add_space(5.0);
+                    egui::CollapsingHeader::new("Latest report").show(ui, |ui| {
+                        egui::ScrollArea::vertical()
+                            .max_height(200.0)
+                            .show(ui, |ui| {
+                                ui.label(egui::RichText::new(&latest.contents).monospace().small());
+                            });
+                    });
+                }
+
+                ui.add_space(10.0);
+                ui.horizontal(|ui| {
+                    if ui.button("Copy Report").clicked() {
+                        if let Some(latest) = self.crash_reports.last() {
+                            ui.ctx().copy_text(latest.contents.clone());
+                        }
+                    }
+
+                    if ui.button("Dismiss").clicked() {
+                        dismiss = true;
+                    }
+
+                    if ui.button("Delete Reports").clicked() {
+                        delete_reports = true;
+                    }
+                });
+            });
+
+        if delete_reports {
+            crash::delete_all_reports();
+            dismiss = true;
+        } else if dismiss {
+            crash::acknowledge_reports(&self.crash_reports);
+        }
+
+        if dismiss {
+            self.crash_reports.clear();
+            self.show_crash_dialog = false;
+        }
+    }
 }
@@ -0,0 +1,138 @@
+//! # Screen Capture Protection Module
+//!
+//! Excludes the application window from screenshots, screen recordings and
+//! screen sharing, using the window flags of the operating system:
+//!
+//! - **Windows**: `SetWindowDisplayAffinity` with `WDA_EXCLUDEFROMCAPTURE`
+//!   (Windows 10 2004 and later), falling back to `WDA_MONITOR`, which
+//!   shows the window as a black area in captures
+//! - **macOS**: the `sharingType` of the `NSWindow` set to
+//!   `NSWindowSharingNone`
+//!
+//! Other platforms have no such flag. The protection is best effort: it
+//! does not stop a camera pointed at the screen, and capture tools that
+//! bypass the window server (or the macOS 15 capture APIs) may ignore it.
+
+use anyhow::{anyhow, Result};
+use raw_window_handle::HasWindowHandle;
+
+/// Whether this platform can exclude windows from screen capture.
+pub const SUPPORTED: bool = cfg!(any(windows, target_os = "macos"));
+
+/// Excludes a window from screen capture or allows capturing it again.
+///
+/// # Arguments
+///
+/// * `window` - The window, e.g. the eframe `Frame`
+/// * `excluded` - True to hide the window from captures
+///
+/// # Returns
+///
+/// * `Result<()>` - Ok if the flag was set, Err if the platform does not
+///   support it or the call failed
+pub fn set_excluded_from_capture(window: &impl HasWindowHandle, excluded: bool) -> Result<()> {
+    let handle = window
+        .window_handle()
+        .map_err(|e| anyhow!("Window handle unavailable: {}", e))?;
+    platform::set_excluded(handle.as_raw(), excluded)
+}
+
+/// Display affinity of Win32 windows.
+#[cfg(windows)]
+mod platform {
+    use anyhow::{bail, Result};
+    use raw_window_handle::RawWindowHandle;
+    use std::io;
+    use windows_sys::Win32::UI::WindowsAndMessaging::{
+        SetWindowDisplayAffinity, WDA_EXCLUDEFROMCAPTURE, WDA_MONITOR, WDA_NONE,
+    };
+
+    pub fn set_excluded(handle: RawWindowHandle, excluded: bool) -> Result<()> {
+        let RawWindowHandle::Win32(handle) = handle else {
+            bail!("Unexpected window handle type");
+        };
+        let hwnd = handle.hwnd.get() as _;
+
+        if !excluded {
+            // SAFETY: `hwnd` is the live top-level window of this process
+            if unsafe { SetWindowDisplayAffinity(hwnd, WDA_NONE) } == 0 {
+                return Err(io::Error::last_os_error().into());
+            }
+            return Ok(());
+        }
+
+        // WDA_EXCLUDEFROMCAPTURE needs Windows 10 2004, older versions black
+        // the window out instead
+        // SAFETY: `hwnd` is the live top-level window of this process
+        if unsafe { SetWindowDisplayAffinity(hwnd, WDA_EXCLUDEFROMCAPTURE) } == 0
+            && unsafe { SetWindowDisplayAffinity(hwnd, WDA_MONITOR) } == 0
+        {
+            return Err(io::Error::last_os_error().into());
+        }
+        Ok(())
+    }
+}
+
+/// Sharing type of AppKit windows.
+#[cfg(target_os = "macos")]
+mod platform {
+    use anyhow::{bail, Result};
+    use raw_window_handle::RawWindowHandle;
+    use std::ffi::{c_char, c_void};
+
+    /// `NSWindowSharingNone`: the window contents cannot be read by other processes.
+    const NS_WINDOW_SHARING_NONE: usize = 0;
+    /// `NSWindowSharingReadOnly`: the default sharing type.
+    const NS_WINDOW_SHARING_READ_ONLY: usize = 1;
+
+    #[link(name = "objc", kind = "dylib")]
+    extern "C" {
+        fn sel_registerName(name: *const c_char) -> *mut c_void;
+        fn objc_msgSend();
+    }
+
+    pub fn set_excluded(handle: RawWindowHandle, excluded: bool) -> Result<()> {
+        let RawWindowHandle::AppKit(handle) = handle else {
+            bail!("Unexpected window handle type");
+        };
+        let view = handle.ns_view.as_ptr();
+        let sharing_type = if excluded {
+            NS_WINDOW_SHARING_NONE
+        } else {
+            NS_WINDOW_SHARING_READ_ONLY
+        };
+
+        // SAFETY: `view` is the live NSView of the eframe window, and
+        // objc_msgSend is called through the exact signatures of
+        // `-[NSView window]` and `-[NSWindow setSharingType:]`. eframe calls
+        // `update` on the main thread, as AppKit requires.
+        unsafe {
+            let send_for_object: unsafe extern "C" fn(*mut c_void, *mut c_void) -> *mut c_void =
+                std::mem::transmute(objc_msgSend as unsafe extern "C" fn());
+            let window = send_for_object(view, sel_registerName(c"window".as_ptr()));
+            if window.is_null() {
+                bail!("The view is not attached to a window");
+            }
+
+            let send_with_usize: unsafe extern "C" fn(*mut c_void, *mut c_void, usize) =
+                std::mem::transmute(objc_msgSend as unsafe extern "C" fn());
+            send_with_usize(
+                window,
+                sel_registerName(c"setSharingType:".as_ptr()),
+                sharing_type,
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Platforms without capture protection.
+#[cfg(not(any(windows, target_os = "macos")))]
+mod platform {
+    use anyhow::{bail, Result};
+    use raw_window_handle::RawWindowHandle;
+
+    pub fn set_excluded(_handle: RawWindowHandle, _excluded: bool) -> Result<()> {
+        bail!("Screen capture protection is not supported on this platform");
+    }
+}
@@ -0,0 +1,91 @@
+//! # Clipboard Module
+//!
+//! Copies secrets to the clipboard and removes them again after the delay
+//! chosen in the settings, so a password doesn't stay there to be pasted
+//! by accident or read by another program. The clipboard is only cleared
+//! if it still holds the secret; text copied in the meantime is left
+//! alone. Logging out clears it right away.
+//!
+//! Only a SHA-256 of the copied secret is kept to recognize it.
+
+use crate::app::NotesApp;
+use eframe::egui;
+use sha2::{Digest, Sha256};
+use std::time::Instant;
+
+/// A secret in the clipboard that is waiting to be cleared.
+pub struct PendingClear {
+    /// SHA-256 of the copied text
+    digest: [u8; 32],
+    /// When the clipboard is cleared
+    clear_at: Instant,
+}
+
+impl NotesApp {
+    /// Copies a secret to the clipboard and schedules clearing it.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The egui context, which owns the clipboard
+    /// * `text` - The secret to copy
+    pub fn copy_sensitive(&mut self, ctx: &egui::Context, text: &str) {
+        ctx.copy_text(text.to_string());
+        self.pending_clipboard_clear =
+            self.settings
+                .clipboard_clear_delay()
+                .map(|delay| PendingClear {
+                    digest: Sha256::digest(text.as_bytes()).into(),
+                    clear_at: Instant::now() + delay,
+                });
+
+        self.show_status(match self.settings.clipboard_clear_delay() {
+            Some(delay) => format!("Copied, clipboard clears in {} s", delay.as_secs()),
+            None => "Copied".to_string(),
+        });
+    }
+
+    /// Makes a pending clear happen on the next frame, e.g. at logout.
+    pub fn clear_clipboard_now(&mut self) {
+        if let Some(pending) = &mut self.pending_clipboard_clear {
+            pending.clear_at = Instant::now();
+        }
+    }
+
+    /// Returns when the copied secret is due to be cleared.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Instant>` - The time, None if nothing waits to be cleared
+    pub fn clipboard_clear_deadline(&self) -> Option<Instant> {
+        self.pending_clipboard_clear
+            .as_ref()
+            .map(|pending| pending.clear_at)
+    }
+
+    /// Clears the clipboard once the copied secret's delay has passed.
+    ///
+    /// If the clipboard can't be read, it is cleared anyway rather than
+    /// risking the secret staying there.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The egui context, which owns the clipboard
+    pub fn clear_clipboard_if_due(&mut self, ctx: &egui::Context) {
+        let Some(pending) = &self.pending_clipboard_clear else {
+            return;
+        };
+        if Instant::now() < pending.clear_at {
+            return;
+        }
+
+        let still_copied = match arboard::Clipboard::new().and_then(|mut c| c.get_text()) {
+            Ok(text) => <[u8; 32]>::from(Sha256::digest(text.as_bytes())) == pending.digest,
+            Err(_) => true,
+        };
+        if still_copied {
+            ctx.copy_text(String::new());
+            log::info!("Cleared copied secret from the clipboard");
+        }
+        self.pending_clipboard_clear = None;
+    }
+}
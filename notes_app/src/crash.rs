@@ -0,0 +1,178 @@
+//! # Crash Reporting Module
+//!
+//! Installs a panic hook that writes a local crash report to the data
+//! directory and provides access to unacknowledged reports so the next
+//! launch can show a recovery dialog.
+//!
+//! Reports are sanitized: they contain the application version, platform,
+//! panic location and backtrace, but never note content, passwords or
+//! usernames. Panic messages built at runtime (`panic!("{}", value)`) may
+//! embed user data and are therefore redacted; only compile-time literal
+//! messages are kept.
+
+use crate::secure_fs;
+use chrono::Local;
+use std::fs;
+use std::path::PathBuf;
+
+/// File extension of crash reports the user has not seen yet.
+const PENDING_EXTENSION: &str = "txt";
+/// File extension of crash reports that were acknowledged in the dialog.
+const SEEN_EXTENSION: &str = "seen";
+
+/// A crash report found on disk.
+#[derive(Debug, Clone)]
+pub struct CrashReport {
+    /// Location of the report file
+    pub path: PathBuf,
+    /// Full text of the report
+    pub contents: String,
+}
+
+/// Returns the directory where crash reports are stored.
+pub fn crash_dir() -> PathBuf {
+    let mut dir = dirs::config_dir().unwrap_or_else(|| PathBuf::from("."));
+    dir.push("secure_notes");
+    dir.push("crash_reports");
+    dir
+}
+
+/// Installs the panic hook that writes crash reports.
+///
+/// The default hook is still invoked afterwards, so panics keep showing up
+/// on stderr during development.
+pub fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+
+    std::panic::set_hook(Box::new(move |info| {
+        let message = if let Some(message) = info.payload().downcast_ref::<&'static str>() {
+            message.to_string()
+        } else if let Some(message) = info.payload().downcast_ref::<String>() {
+            // Formatted messages may contain runtime data, keep only their size
+            format!("<redacted formatted message, {} bytes>", message.len())
+        } else {
+            "<non-string panic payload>".to_string()
+        };
+
+        let location = info
+            .location()
+            .map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column()))
+            .unwrap_or_else(|| "unknown".to_string());
+
+        let thread = std::thread::current()
+            .name()
+            .unwrap_or("unnamed")
+            .to_string();
+
+        let report = format!(
+            "Secure Notes crash report\n\
+             =========================\n\
+             Version:  {}\n\
+             Time:     {}\n\
+             Platform: {} ({})\n\
+             Thread:   {}\n\
+             Location: {}\n\
+             Message:  {}\n\
+             \n\
+             Backtrace:\n{}\n",
+            env!("CARGO_PKG_VERSION"),
+            Local::now().format("%Y-%m-%d %H:%M:%S %:z"),
+            std::env::consts::OS,
+            std::env::consts::ARCH,
+            thread,
+            location,
+            message,
+            std::backtrace::Backtrace::force_capture(),
+        );
+
+        match write_report(&report) {
+            Ok(path) => log::error!(
+                "Application panicked at {}, crash report written to {}",
+                location,
+                path.display()
+            ),
+            Err(e) => log::error!(
+                "Application panicked at {}, failed to write crash report: {}",
+                location,
+                e
+            ),
+        }
+        log::logger().flush();
+
+        default_hook(info);
+    }));
+}
+
+/// Writes a crash report file with owner-only permissions.
+///
+/// # Arguments
+///
+/// * `report` - The sanitized report text
+///
+/// # Returns
+///
+/// * `std::io::Result<PathBuf>` - Path of the written report
+fn write_report(report: &str) -> std::io::Result<PathBuf> {
+    let dir = crash_dir();
+    fs::create_dir_all(&dir)?;
+
+    let path = dir.join(format!(
+        "crash-{}.{}",
+        Local::now().format("%Y%m%d-%H%M%S%.3f"),
+        PENDING_EXTENSION
+    ));
+    secure_fs::write_private_file(&path, report)?;
+
+    Ok(path)
+}
+
+/// Loads all crash reports that have not been acknowledged yet.
+///
+/// # Returns
+///
+/// * `Vec<CrashReport>` - Pending reports, oldest first
+pub fn pending_reports() -> Vec<CrashReport> {
+    let Ok(entries) = fs::read_dir(crash_dir()) else {
+        return Vec::new();
+    };
+
+    let mut reports: Vec<CrashReport> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().and_then(|e| e.to_str()) == Some(PENDING_EXTENSION))
+        .filter_map(|path| {
+            fs::read_to_string(&path)
+                .ok()
+                .map(|contents| CrashReport { path, contents })
+        })
+        .collect();
+
+    reports.sort_by(|a, b| a.path.cmp(&b.path));
+    reports
+}
+
+/// Marks crash reports as seen so the recovery dialog is not shown again.
+///
+/// The reports stay on disk for later troubleshooting.
+///
+/// # Arguments
+///
+/// * `reports` - The reports to acknowledge
+pub fn acknowledge_reports(reports: &[CrashReport]) {
+    for report in reports {
+        if let Err(e) = fs::rename(&report.path, report.path.with_extension(SEEN_EXTENSION)) {
+            log::warn!("Failed to acknowledge crash report: {}", e);
+        }
+    }
+}
+
+/// Deletes all crash reports, pending and acknowledged.
+pub fn delete_all_reports() {
+    if let Ok(entries) = fs::read_dir(crash_dir()) {
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            if let Err(e) = fs::remove_file(entry.path()) {
+                log::warn!("Failed to delete crash report: {}", e);
+            }
+        }
+    }
+}
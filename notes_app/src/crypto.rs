@@ -5,28 +5,265 @@
 //! # Cryptographic Module
 //!
 //! Provides secure encryption, decryption, and key management functionality.
-//! Uses ChaCha20Poly1305 for encryption and Argon2 for key derivation.
-//! Implements hardware fingerprinting for additional security.
-
+//! Uses XChaCha20Poly1305 (or AES-256-GCM) for encryption and Argon2 for key
+//! derivation. Implements hardware fingerprinting for additional security.
+//!
+//! The algorithms, the ciphertext format and the key derivation itself live
+//! in the shared `secure_core` crate; this module adds the per-user
+//! metadata, hardware binding and the [`BlobContext`] of each blob.
+//!
+//! ## Ciphertext Format
+//!
+//! Every blob produced by [`CryptoManager::encrypt`] starts with a small
+//! header so the algorithm can change without breaking existing data:
+//!
+//! ```text
+//! +-------+---------+-----------+-------+------------+
+//! | magic | version | algorithm | nonce | ciphertext |
+//! | 4 B   | 1 B     | 1 B       | 24 B* | ...        |
+//! +-------+---------+-----------+-------+------------+
+//! ```
+//!
+//! \* 24 bytes for XChaCha20Poly1305, 12 bytes for AES-256-GCM and data
+//! written with plain ChaCha20Poly1305 by earlier versions.
+//!
+//! Since format version 2, a [`BlobContext`] naming the owner and role of
+//! the blob is bound into the AEAD tag as associated data, so ciphertexts
+//! cannot be swapped between users, notes or files without detection.
+//! Version 1 blobs carry no associated data and are accepted until they
+//! are rewritten.
+//!
+//! Blobs written before the header existed (bare `nonce || ciphertext`,
+//! always ChaCha20Poly1305) are still decrypted transparently.
+//!
+//! Once no blob of a user is left in either older format, this is recorded
+//! in the user's `security.meta` and both are rejected from then on (see
+//! [`CryptoManager::retire_legacy_formats`]), so an old blob can't be
+//! swapped in for another one. Accounts created since, and data keys such
+//! as the shared vault's, never accept them.
+//!
+//! ## Format Upgrades
+//!
+//! Older blobs are not only read but also upgraded: when the notes are
+//! loaded after login, every note and attachment file for which
+//! [`CryptoManager::needs_rewrite`] is true is decrypted and written again
+//! in the current format (see `StorageManager::upgrade_user_files`), and
+//! outdated settings are saved again right after loading. A future format
+//! change only needs a new header version in `secure_core`; existing
+//! vaults are brought up to date on their next login.
+//!
+//! ## Key Derivation
+//!
+//! The Argon2id parameters a key was derived with are stored in the user's
+//! `security.meta`. When [`KdfParams::CURRENT`] is raised, the next login
+//! derives both the old and a stronger key, re-encrypts all data and then
+//! records the new parameters.
+//!
+//! ## Two-Person Unlock
+//!
+//! Optionally, the key isn't derived from the password at all. It is
+//! random and split into two Shamir shares (see `secure_core::shamir`),
+//! stored in the user's `split.key`: one wrapped with a key derived from
+//! the password, one with a key derived from a second passphrase held by
+//! another person. Login then unwraps the first share and waits for the
+//! second passphrase before the key exists. Both shares are wrapped with
+//! the hardware salt too, so the file is as bound to the computer as the
+//! derived key.
+
+use crate::secure_fs;
 use anyhow::{anyhow, Result};
-use argon2::{password_hash::SaltString, Argon2, PasswordHash, PasswordHasher, PasswordVerifier};
-use chacha20poly1305::{
-    aead::{Aead, AeadCore, KeyInit, OsRng},
-    ChaCha20Poly1305, Nonce,
-};
+use chacha20poly1305::aead::{rand_core::RngCore, OsRng};
 use dirs::config_dir;
+use secure_core::kdf::{self, Key};
+use secure_core::shamir::{self, Share};
+use secure_core::Cipher;
 use serde::{Deserialize, Serialize};
 use std::collections::hash_map::DefaultHasher;
 use std::env;
 use std::fs;
 use std::hash::{Hash, Hasher};
 
+pub use secure_core::{AeadAlgorithm, KdfParams};
+
+/// Current version of the security metadata format.
+const SECURITY_METADATA_VERSION: u32 = 2;
+
+/// Current version of the split key file format.
+const SPLIT_KEY_VERSION: u32 = 1;
+
+/// Length of the random part of a key share's wrapping salt, in bytes.
+const SHARE_SALT_LEN: usize = 16;
+
+/// Owner and role of an encrypted blob.
+///
+/// Bound into every AEAD operation as associated data, so a ciphertext
+/// copied to another user, note or file fails to decrypt.
+#[derive(Debug, Clone, Copy)]
+pub enum BlobContext<'a> {
+    /// A single note file
+    Note {
+        /// Owner of the note
+        user_id: &'a str,
+        /// ID of the note
+        note_id: &'a str,
+    },
+    /// A file attached to a note
+    Attachment {
+        /// Owner of the note
+        user_id: &'a str,
+        /// ID of the note the file is attached to
+        note_id: &'a str,
+        /// ID of the attachment
+        attachment_id: &'a str,
+    },
+    /// Earlier versions of a single note
+    NoteHistory {
+        /// Owner of the note
+        user_id: &'a str,
+        /// ID of the note
+        note_id: &'a str,
+    },
+    /// The user's encrypted preferences
+    Settings {
+        /// Owner of the settings
+        user_id: &'a str,
+    },
+    /// The change journal of the user's private vault
+    Journal {
+        /// Owner of the journal
+        user_id: &'a str,
+    },
+    /// The saved search index of the user's notes
+    SearchIndex {
+        /// Owner of the index
+        user_id: &'a str,
+    },
+    /// The single-file note bundle written by older versions
+    NoteBundle {
+        /// Owner of the bundle
+        user_id: &'a str,
+    },
+    /// The pre-multi-user `notes.enc` in the data directory root
+    LegacyNotes,
+    /// The data key of the shared vault, wrapped for one of its members
+    VaultKey {
+        /// Member the key is wrapped for
+        user_id: &'a str,
+    },
+    /// One share of a key split for two-person unlock
+    KeyShare {
+        /// Owner of the key
+        user_id: &'a str,
+        /// Index of the share
+        index: u8,
+    },
+}
+
+impl BlobContext<'_> {
+    /// Serializes the context into the associated data bytes.
+    fn associated_data(&self) -> Vec<u8> {
+        match self {
+            BlobContext::Note { user_id, note_id } => {
+                format!("secure_notes|note|{}|{}", user_id, note_id)
+            }
+            BlobContext::Attachment {
+                user_id,
+                note_id,
+                attachment_id,
+            } => format!(
+                "secure_notes|attachment|{}|{}|{}",
+                user_id, note_id, attachment_id
+            ),
+            BlobContext::NoteHistory { user_id, note_id } => {
+                format!("secure_notes|note_history|{}|{}", user_id, note_id)
+            }
+            BlobContext::Settings { user_id } => format!("secure_notes|settings|{}", user_id),
+            BlobContext::Journal { user_id } => format!("secure_notes|journal|{}", user_id),
+            BlobContext::SearchIndex { user_id } => {
+                format!("secure_notes|search_index|{}", user_id)
+            }
+            BlobContext::NoteBundle { user_id } => format!("secure_notes|bundle|{}", user_id),
+            BlobContext::LegacyNotes => "secure_notes|legacy".to_string(),
+            BlobContext::VaultKey { user_id } => format!("secure_notes|vault_key|{}", user_id),
+            BlobContext::KeyShare { user_id, index } => {
+                format!("secure_notes|key_share|{}|{}", user_id, index)
+            }
+        }
+        .into_bytes()
+    }
+}
+
+/// Serde default for metadata written before KDF parameters were recorded.
+fn legacy_kdf_params() -> KdfParams {
+    KdfParams::LEGACY
+}
+
+/// Key derivation upgrade or password change waiting for the user's data to
+/// be re-encrypted.
+#[derive(Clone)]
+struct PendingKdfUpgrade {
+    /// Key derived with the outdated parameters or the old password, still
+    /// needed for decryption
+    previous_key: Key,
+    /// Parameters the new key was derived with
+    params: KdfParams,
+    /// Duration of the derivation with the new parameters in milliseconds
+    duration_ms: u64,
+}
+
+/// A key share encrypted with a key derived from a password or passphrase.
+#[derive(Clone, Serialize, Deserialize)]
+struct WrappedShare {
+    /// Index of the share
+    index: u8,
+    /// Random part of the wrapping key's salt, hex-encoded
+    salt: String,
+    /// The encrypted share bytes, hex-encoded
+    wrapped: String,
+}
+
+/// Contents of `split.key`, the shares of a key split for two-person
+/// unlock.
+#[derive(Clone, Serialize, Deserialize)]
+struct SplitKeyFile {
+    /// Version of the file format
+    version: u32,
+    /// Argon2 parameters the wrapping keys are derived with
+    kdf_params: KdfParams,
+    /// Share 1, wrapped with the password, and share 2, wrapped with the
+    /// second passphrase
+    shares: Vec<WrappedShare>,
+}
+
+/// Change of `split.key` waiting for the user's data to be re-encrypted.
+#[derive(Clone)]
+enum PendingSplitKey {
+    /// Write the file, turning two-person unlock on
+    Enable(SplitKeyFile),
+    /// Remove the file, going back to the password-derived key
+    Disable,
+}
+
+/// Outcome of the hardware fingerprint check during initialization.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HardwareCheck {
+    /// The fingerprint matched the one stored for the user
+    Matched,
+    /// Non-critical components changed; access was allowed and the stored
+    /// fingerprint updated
+    Changed,
+    /// Critical components changed; access was refused
+    Mismatch,
+    /// No comparison was made: first login or an upgraded legacy account
+    NotChecked,
+}
+
 /// Security metadata stored alongside encrypted data.
 ///
 /// Contains version information, creation timestamp, and hardware fingerprint
 /// data used to verify that the encrypted data is being accessed from the
 /// same system where it was created.
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 struct SecurityMetadata {
     /// Version of the security metadata format
     version: u32,
@@ -37,6 +274,16 @@ struct SecurityMetadata {
     /// List of hardware components used for fingerprinting
     #[serde(default)] // This makes the field optional for backward compatibility
     hardware_components: Vec<String>,
+    /// Duration of the last key derivation in milliseconds, used for progress estimation
+    #[serde(default)]
+    last_kdf_duration_ms: Option<u64>,
+    /// Argon2 parameters the user's key is derived with
+    #[serde(default = "legacy_kdf_params")]
+    kdf_params: KdfParams,
+    /// Set once every blob of the user is in the current format, so
+    /// version 1 and headerless blobs are rejected
+    #[serde(default)]
+    legacy_formats_retired: bool,
 }
 
 /// Main cryptographic manager for the application.
@@ -47,13 +294,30 @@ struct SecurityMetadata {
 /// - Hardware fingerprinting for device binding
 /// - Security metadata management
 /// - Password verification and changes
+#[derive(Clone)]
 pub struct CryptoManager {
-    /// The encryption cipher instance
-    cipher: Option<ChaCha20Poly1305>,
+    /// The derived encryption key
+    key: Option<Key>,
+    /// Algorithm used for newly encrypted data
+    algorithm: AeadAlgorithm,
+    /// Whether blobs without associated data are still decrypted
+    legacy_formats: bool,
     /// Path to the configuration directory
     config_path: std::path::PathBuf,
     /// Security metadata for the current session
     security_metadata: Option<SecurityMetadata>,
+    /// Key upgrade started at login or by a password change, completed
+    /// once all data is re-encrypted
+    pending_kdf_upgrade: Option<PendingKdfUpgrade>,
+    /// Result of the hardware check of the last initialization, None if it
+    /// stopped before the check (e.g. wrong password)
+    hardware_check: Option<HardwareCheck>,
+    /// First share of a split key, unwrapped at login and waiting for the
+    /// second passphrase
+    key_share: Option<Share>,
+    /// Two-person unlock being turned on or off, completed once all data is
+    /// re-encrypted
+    pending_split_key: Option<PendingSplitKey>,
 }
 
 impl CryptoManager {
@@ -74,9 +338,40 @@ impl CryptoManager {
         }
 
         Self {
-            cipher: None,
+            key: None,
+            algorithm: AeadAlgorithm::XChaCha20Poly1305,
+            legacy_formats: true,
             config_path,
             security_metadata: None,
+            pending_kdf_upgrade: None,
+            hardware_check: None,
+            key_share: None,
+            pending_split_key: None,
+        }
+    }
+
+    /// Creates a crypto manager for a random data key, such as the key of
+    /// the shared vault.
+    ///
+    /// The manager has no password, metadata or hardware binding of its
+    /// own; the key is protected by whoever stores it. Such keys are newer
+    /// than associated data binding, so older ciphertext formats are
+    /// rejected.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The data key
+    /// * `algorithm` - Algorithm used for newly encrypted data
+    ///
+    /// # Returns
+    ///
+    /// * `Self` - A crypto manager ready to encrypt and decrypt
+    pub fn with_key(key: Key, algorithm: AeadAlgorithm) -> Self {
+        Self {
+            key: Some(key),
+            algorithm,
+            legacy_formats: false,
+            ..Self::new()
         }
     }
 
@@ -87,7 +382,7 @@ impl CryptoManager {
     /// 2. Loads or creates security metadata with hardware fingerprinting
     /// 3. Verifies password against stored hash (for existing users)
     /// 4. Derives encryption key using Argon2
-    /// 5. Initializes the ChaCha20Poly1305 cipher
+    /// 5. Stores the derived key for the selected AEAD cipher
     ///
     /// The process is designed to be secure but may take several seconds due to
     /// the intentionally expensive key derivation process.
@@ -108,8 +403,36 @@ impl CryptoManager {
     /// * File system operations fail
     /// * Key derivation fails
     pub fn initialize_for_user(&mut self, user_id: &str, password: &str) -> Result<()> {
-        println!("Starting crypto initialization for user: {}", user_id);
+        self.initialize_for_user_with_progress(user_id, password, &|_| {})
+    }
+
+    /// Initializes the crypto manager for a user, reporting derivation progress.
+    ///
+    /// Behaves like `initialize_for_user`, but calls `on_key_derivation` with
+    /// the estimated duration right before the expensive Argon2 derivation
+    /// starts, so the UI can show a progress bar. The estimate is the
+    /// duration of the previous derivation on this machine, or a quick
+    /// scaled-down benchmark on first use.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - Unique identifier for the user
+    /// * `password` - User's password for key derivation
+    /// * `on_key_derivation` - Callback receiving the estimated derivation time
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - Ok if initialization succeeds, Err with details if it fails
+    pub fn initialize_for_user_with_progress(
+        &mut self,
+        user_id: &str,
+        password: &str,
+        on_key_derivation: &dyn Fn(std::time::Duration),
+    ) -> Result<()> {
+        log::debug!("Starting crypto initialization for user {}", user_id);
         let start_time = std::time::Instant::now();
+        self.hardware_check = None;
+        self.key_share = None;
 
         // Create user-specific config directory
         let mut user_config_path = self.config_path.clone();
@@ -124,20 +447,16 @@ impl CryptoManager {
         let metadata_file = user_config_path.join("security.meta");
 
         let key = if key_file.exists() && metadata_file.exists() {
-            println!("Loading existing user configuration...");
+            log::debug!("Loading existing user configuration");
 
             // Load existing setup
             let stored_hash = fs::read_to_string(&key_file)?;
-            let parsed_hash = PasswordHash::new(&stored_hash)
-                .map_err(|e| anyhow!("Failed to parse password hash: {}", e))?;
 
-            println!("Verifying password...");
+            log::debug!("Verifying password");
             // Verify password (this should be fast with default Argon2)
-            Argon2::default()
-                .verify_password(password.as_bytes(), &parsed_hash)
-                .map_err(|e| anyhow!("Password verification failed: {}", e))?;
+            kdf::verify_password(password, &stored_hash)?;
 
-            println!("Loading metadata...");
+            log::debug!("Loading security metadata");
             // Load metadata
             let metadata_content = fs::read_to_string(&metadata_file)?;
             let mut metadata: SecurityMetadata = serde_json::from_str(&metadata_content)
@@ -145,7 +464,7 @@ impl CryptoManager {
 
             // Handle backward compatibility - if hardware_components is empty, regenerate it
             if metadata.hardware_components.is_empty() {
-                println!("Upgrading old metadata format...");
+                log::info!("Upgrading old metadata format");
                 let (current_hash, current_components) =
                     self.generate_stable_hardware_fingerprint()?;
 
@@ -155,55 +474,42 @@ impl CryptoManager {
 
                 // Save updated metadata
                 fs::write(&metadata_file, serde_json::to_string_pretty(&metadata)?)?;
-                println!("Metadata upgraded successfully");
+                log::info!("Metadata upgraded successfully");
+                self.hardware_check = Some(HardwareCheck::NotChecked);
             } else {
-                println!("Checking hardware fingerprint...");
+                log::debug!("Checking hardware fingerprint");
                 // Get current hardware components
                 let (current_hash, current_components) =
                     self.generate_stable_hardware_fingerprint()?;
 
-                // Debug output
-                println!(
-                    "Stored hardware components: {:?}",
-                    metadata.hardware_components
-                );
-                println!("Current hardware components: {:?}", current_components);
-                println!("Stored hash: {}", metadata.hardware_fingerprint_hash);
-                println!("Current hash: {}", current_hash);
-
                 // Check if hardware fingerprint matches
                 if metadata.hardware_fingerprint_hash != current_hash {
-                    // Try to identify what changed
-                    let mut changed_components = Vec::new();
-                    for (i, (stored, current)) in metadata
-                        .hardware_components
-                        .iter()
-                        .zip(current_components.iter())
-                        .enumerate()
-                    {
-                        if stored != current {
-                            changed_components
-                                .push(format!("Component {}: '{}' -> '{}'", i, stored, current));
-                        }
-                    }
+                    self.hardware_check = Some(HardwareCheck::Changed);
+                    // Try to identify what changed (component kinds only, never
+                    // their values, since errors end up in the log file)
+                    let changed_components =
+                        changed_component_kinds(&metadata.hardware_components, &current_components);
 
                     if !changed_components.is_empty() {
-                        println!("Hardware changes detected:");
-                        for change in &changed_components {
-                            println!("  {}", change);
-                        }
+                        log::warn!(
+                            "Hardware changes detected in: {}",
+                            changed_components.join(", ")
+                        );
 
                         // For now, let's be more lenient and only fail if critical components changed
                         if self.is_critical_hardware_change(
                             &metadata.hardware_components,
                             &current_components,
                         ) {
+                            self.hardware_check = Some(HardwareCheck::Mismatch);
                             return Err(anyhow!(
-                                "Critical hardware components changed: {}",
+                                "Critical hardware components changed: {}. Copied data \
+                                 cannot be opened on another computer, use Settings → \
+                                 Move to Another Computer on the original one",
                                 changed_components.join(", ")
                             ));
                         } else {
-                            println!("Non-critical hardware changes detected, allowing access...");
+                            log::info!("Non-critical hardware changes detected, allowing access");
                             // Update the stored fingerprint
                             metadata.hardware_fingerprint_hash = current_hash;
                             metadata.hardware_components = current_components;
@@ -213,17 +519,80 @@ impl CryptoManager {
                         }
                     }
                 } else {
-                    println!("Hardware fingerprint matches!");
+                    log::debug!("Hardware fingerprint matches");
+                    self.hardware_check = Some(HardwareCheck::Matched);
                 }
             }
 
+            self.legacy_formats = !metadata.legacy_formats_retired;
+
+            if let Some(split_key) = self.load_split_key(user_id)? {
+                // The key is random; only the wrapping keys of its shares
+                // are derived, with the parameters recorded in the file
+                log::debug!("Unwrapping the first key share");
+                on_key_derivation(
+                    self.estimate_key_derivation_time(&split_key.kdf_params, Some(&metadata)),
+                );
+                let derivation_start = std::time::Instant::now();
+                let share = self.unwrap_share(user_id, &split_key, 1, password)?;
+
+                metadata.last_kdf_duration_ms = Some(derivation_start.elapsed().as_millis() as u64);
+                metadata.version = SECURITY_METADATA_VERSION;
+                fs::write(&metadata_file, serde_json::to_string_pretty(&metadata)?)?;
+                self.security_metadata = Some(metadata);
+                self.pending_kdf_upgrade = None;
+                self.key = None;
+                self.key_share = Some(share);
+
+                log::info!(
+                    "First key share unwrapped in {:.2}s, waiting for the second passphrase",
+                    start_time.elapsed().as_secs_f64()
+                );
+                return Ok(());
+            }
+
+            log::debug!("Deriving encryption key");
+            let stored_params = metadata.kdf_params;
+            let upgrade_params = stored_params.is_outdated().then_some(KdfParams::CURRENT);
+
+            let mut estimate = self.estimate_key_derivation_time(&stored_params, Some(&metadata));
+            if let Some(params) = &upgrade_params {
+                estimate += self.estimate_key_derivation_time(params, None);
+            }
+            on_key_derivation(estimate);
+
+            // Derive the key the existing data is encrypted with
+            let derivation_start = std::time::Instant::now();
+            let key = self.derive_secure_key(password, &stored_params)?;
+
+            // Remember how long it took for the next progress estimate
+            metadata.last_kdf_duration_ms = Some(derivation_start.elapsed().as_millis() as u64);
+            metadata.version = SECURITY_METADATA_VERSION;
+            fs::write(&metadata_file, serde_json::to_string_pretty(&metadata)?)?;
             self.security_metadata = Some(metadata);
 
-            println!("Deriving encryption key...");
-            // Use standard security key derivation
-            self.derive_secure_key(password)
+            match upgrade_params {
+                Some(params) => {
+                    // Defaults were raised since this key was derived. Switch to a
+                    // stronger key now; the metadata is only updated once all data
+                    // has been re-encrypted (see `complete_kdf_upgrade`).
+                    log::info!("Key derivation parameters are outdated, upgrading key");
+                    let upgrade_start = std::time::Instant::now();
+                    let new_key = self.derive_secure_key(password, &params)?;
+                    self.pending_kdf_upgrade = Some(PendingKdfUpgrade {
+                        previous_key: key,
+                        params,
+                        duration_ms: upgrade_start.elapsed().as_millis() as u64,
+                    });
+                    new_key
+                }
+                None => {
+                    self.pending_kdf_upgrade = None;
+                    key
+                }
+            }
         } else {
-            println!("First time setup for user...");
+            log::info!("First time setup for user {}", user_id);
 
             let current_time = std::time::SystemTime::now()
                 .duration_since(std::time::UNIX_EPOCH)
@@ -232,41 +601,50 @@ impl CryptoManager {
 
             let (hardware_hash, hardware_components) =
                 self.generate_stable_hardware_fingerprint()?;
+            self.hardware_check = Some(HardwareCheck::NotChecked);
 
-            println!("Initial hardware components: {:?}", hardware_components);
-            println!("Initial hardware hash: {}", hardware_hash);
+            log::debug!(
+                "Initial hardware fingerprint built from {} components",
+                hardware_components.len()
+            );
 
-            let metadata = SecurityMetadata {
-                version: 1,
+            let mut metadata = SecurityMetadata {
+                version: SECURITY_METADATA_VERSION,
                 created_timestamp: current_time,
                 hardware_fingerprint_hash: hardware_hash,
                 hardware_components,
+                last_kdf_duration_ms: None,
+                kdf_params: KdfParams::CURRENT,
+                // A new account has no data in older formats
+                legacy_formats_retired: true,
             };
 
-            let key = self.derive_secure_key(password);
+            on_key_derivation(self.estimate_key_derivation_time(&KdfParams::CURRENT, None));
 
-            println!("Storing password hash...");
+            let derivation_start = std::time::Instant::now();
+            let key = self.derive_secure_key(password, &KdfParams::CURRENT)?;
+            metadata.last_kdf_duration_ms = Some(derivation_start.elapsed().as_millis() as u64);
+
+            log::debug!("Storing password hash");
             // Store password hash
-            let verification_salt = SaltString::generate(&mut OsRng);
-            let argon2 = Argon2::default();
-            let password_hash = argon2
-                .hash_password(password.as_bytes(), &verification_salt)
-                .map_err(|e| anyhow!("Failed to hash password: {}", e))?;
+            let password_hash = kdf::hash_password(password)?;
 
-            fs::write(&key_file, password_hash.to_string())?;
+            fs::write(&key_file, password_hash)?;
             fs::write(&metadata_file, serde_json::to_string_pretty(&metadata)?)?;
 
             self.secure_file_permissions(&key_file)?;
             self.secure_file_permissions(&metadata_file)?;
 
             self.security_metadata = Some(metadata);
+            self.pending_kdf_upgrade = None;
+            self.legacy_formats = false;
             key
         };
 
-        self.cipher = Some(ChaCha20Poly1305::new(&key));
+        self.key = Some(key);
 
         let elapsed = start_time.elapsed();
-        println!(
+        log::info!(
             "Crypto initialization completed in {:.2}s",
             elapsed.as_secs_f64()
         );
@@ -274,6 +652,335 @@ impl CryptoManager {
         Ok(())
     }
 
+    /// Returns whether the login waits for the second passphrase of a
+    /// split key, see `unlock_second_share`.
+    pub fn awaiting_second_passphrase(&self) -> bool {
+        self.key_share.is_some()
+    }
+
+    /// Completes a two-person unlock with the second passphrase.
+    ///
+    /// Unwraps the second share and combines it with the first one,
+    /// unwrapped by `initialize_for_user_with_progress`, into the key.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - Unique identifier for the user
+    /// * `passphrase` - The second passphrase
+    /// * `on_key_derivation` - Callback receiving the estimated derivation time
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - Ok if the key is in place, Err if the passphrase is
+    ///   wrong or the shares don't fit together
+    pub fn unlock_second_share(
+        &mut self,
+        user_id: &str,
+        passphrase: &str,
+        on_key_derivation: &dyn Fn(std::time::Duration),
+    ) -> Result<()> {
+        let first = self
+            .key_share
+            .clone()
+            .ok_or_else(|| anyhow!("No key share is waiting to be completed"))?;
+        let split_key = self
+            .load_split_key(user_id)?
+            .ok_or_else(|| anyhow!("Two-person unlock is not set up"))?;
+
+        on_key_derivation(
+            self.estimate_key_derivation_time(
+                &split_key.kdf_params,
+                self.security_metadata.as_ref(),
+            ),
+        );
+        let second = self
+            .unwrap_share(user_id, &split_key, 2, passphrase)
+            .map_err(|_| anyhow!("Second passphrase is incorrect"))?;
+
+        let secret = shamir::combine(&[first, second])?;
+        if secret.len() != Key::default().len() {
+            return Err(anyhow!("The key shares don't fit together"));
+        }
+        self.key = Some(Key::clone_from_slice(&secret));
+        self.key_share = None;
+
+        log::info!("Split key combined for user {}", user_id);
+        Ok(())
+    }
+
+    /// Returns whether a user's key is split for two-person unlock.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - Unique identifier for the user
+    pub fn two_person_unlock_enabled(&self, user_id: &str) -> bool {
+        self.split_key_path(user_id).exists()
+    }
+
+    /// Starts turning two-person unlock on by creating a random key and
+    /// splitting it.
+    ///
+    /// Nothing is written to disk. As with `begin_password_change`, the new
+    /// key becomes the current key and the old one stays available for
+    /// decryption while the data is re-encrypted. The split key file is
+    /// written by `complete_split_key_change`, or the change is undone with
+    /// `revert_key_change`.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - User ID for file operations
+    /// * `password` - Current password, wrapping the first share
+    /// * `second_passphrase` - Passphrase wrapping the second share
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - Ok if the new key is in place, Err with details if
+    ///   nothing was changed
+    pub fn begin_two_person_unlock(
+        &mut self,
+        user_id: &str,
+        password: &str,
+        second_passphrase: &str,
+    ) -> Result<()> {
+        let previous_key = self.prepare_key_change(user_id, password)?;
+        if self.two_person_unlock_enabled(user_id) {
+            return Err(anyhow!("Two-person unlock is on already"));
+        }
+
+        let mut key = Key::default();
+        OsRng.fill_bytes(&mut key);
+        let shares = shamir::split(&key, 2, 2)?;
+
+        let derivation_start = std::time::Instant::now();
+        let params = KdfParams::CURRENT;
+        let split_key = SplitKeyFile {
+            version: SPLIT_KEY_VERSION,
+            kdf_params: params,
+            shares: vec![
+                self.wrap_share(user_id, &shares[0], password, &params)?,
+                self.wrap_share(user_id, &shares[1], second_passphrase, &params)?,
+            ],
+        };
+        // Login derives one wrapping key at a time
+        self.pending_kdf_upgrade = Some(PendingKdfUpgrade {
+            previous_key,
+            params,
+            duration_ms: derivation_start.elapsed().as_millis() as u64 / 2,
+        });
+        self.pending_split_key = Some(PendingSplitKey::Enable(split_key));
+        self.key = Some(key);
+
+        log::info!("Two-person unlock set up for user {}", user_id);
+        Ok(())
+    }
+
+    /// Starts turning two-person unlock off by deriving the key from the
+    /// password again.
+    ///
+    /// Both passphrases are needed, so one person can't remove the
+    /// protection alone. Like `begin_two_person_unlock`, nothing is written
+    /// until `complete_split_key_change`.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - User ID for file operations
+    /// * `password` - Current password
+    /// * `second_passphrase` - The second passphrase
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - Ok if the new key is in place, Err with details if
+    ///   nothing was changed
+    pub fn begin_two_person_removal(
+        &mut self,
+        user_id: &str,
+        password: &str,
+        second_passphrase: &str,
+    ) -> Result<()> {
+        let previous_key = self.prepare_key_change(user_id, password)?;
+        let split_key = self
+            .load_split_key(user_id)?
+            .ok_or_else(|| anyhow!("Two-person unlock is not on"))?;
+        self.unwrap_share(user_id, &split_key, 2, second_passphrase)
+            .map_err(|_| anyhow!("Second passphrase is incorrect"))?;
+
+        let derivation_start = std::time::Instant::now();
+        let new_key = self.derive_secure_key(password, &KdfParams::CURRENT)?;
+        self.pending_kdf_upgrade = Some(PendingKdfUpgrade {
+            previous_key,
+            params: KdfParams::CURRENT,
+            duration_ms: derivation_start.elapsed().as_millis() as u64,
+        });
+        self.pending_split_key = Some(PendingSplitKey::Disable);
+        self.key = Some(new_key);
+
+        log::info!("Two-person unlock removal started for user {}", user_id);
+        Ok(())
+    }
+
+    /// Writes or removes the split key file of a pending two-person unlock
+    /// change.
+    ///
+    /// Must only be called after every piece of encrypted data has been
+    /// re-encrypted with the new key, followed by `complete_kdf_upgrade`.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - User ID for file operations
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - Ok if successful, Err if the file could not be
+    ///   written or removed
+    pub fn complete_split_key_change(&mut self, user_id: &str) -> Result<()> {
+        let path = self.split_key_path(user_id);
+        match &self.pending_split_key {
+            Some(PendingSplitKey::Enable(split_key)) => {
+                secure_fs::write_private_file(&path, serde_json::to_string_pretty(split_key)?)?;
+            }
+            Some(PendingSplitKey::Disable) => secure_fs::secure_remove_file(&path)?,
+            None => return Ok(()),
+        }
+        self.pending_split_key = None;
+        Ok(())
+    }
+
+    /// Checks that a key change may start and returns the current key.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - User ID for file operations
+    /// * `password` - Current password for verification
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Key>` - The key in use, or error if the password is wrong
+    ///   or a key derivation upgrade is still pending
+    fn prepare_key_change(&self, user_id: &str, password: &str) -> Result<Key> {
+        let previous_key = self.key.ok_or_else(|| anyhow!("Cipher not initialized"))?;
+        if self.pending_kdf_upgrade.is_some() {
+            return Err(anyhow!(
+                "Some data is still encrypted with an older key. Log in again \
+                 once all notes can be decrypted, then retry"
+            ));
+        }
+
+        let stored_hash = fs::read_to_string(self.password_hash_path(user_id))
+            .map_err(|_| anyhow!("User configuration not found"))?;
+        kdf::verify_password(password, &stored_hash)
+            .map_err(|_| anyhow!("Current password is incorrect"))?;
+        Ok(previous_key)
+    }
+
+    /// Encrypts a key share with a key derived from a passphrase.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - Owner of the key
+    /// * `share` - The share to wrap
+    /// * `passphrase` - Password or passphrase the wrapping key is derived from
+    /// * `params` - The Argon2 parameters to derive with
+    ///
+    /// # Returns
+    ///
+    /// * `Result<WrappedShare>` - The wrapped share, or error
+    fn wrap_share(
+        &self,
+        user_id: &str,
+        share: &Share,
+        passphrase: &str,
+        params: &KdfParams,
+    ) -> Result<WrappedShare> {
+        let mut salt = [0u8; SHARE_SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        let wrapping_key = self.derive_share_key(passphrase, &salt, params)?;
+        let wrapped = CryptoManager::with_key(wrapping_key, self.algorithm).encrypt(
+            &share.bytes,
+            BlobContext::KeyShare {
+                user_id,
+                index: share.index,
+            },
+        )?;
+
+        Ok(WrappedShare {
+            index: share.index,
+            salt: hex::encode(salt),
+            wrapped: hex::encode(wrapped),
+        })
+    }
+
+    /// Decrypts one share of a split key file with a passphrase.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - Owner of the key
+    /// * `split_key` - The split key file
+    /// * `index` - Index of the share to unwrap
+    /// * `passphrase` - Password or passphrase the share is wrapped with
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Share>` - The share, or error if the passphrase is wrong
+    fn unwrap_share(
+        &self,
+        user_id: &str,
+        split_key: &SplitKeyFile,
+        index: u8,
+        passphrase: &str,
+    ) -> Result<Share> {
+        let wrapped = split_key
+            .shares
+            .iter()
+            .find(|share| share.index == index)
+            .ok_or_else(|| anyhow!("Key share {} is missing", index))?;
+        let salt = hex::decode(&wrapped.salt)?;
+        let data = hex::decode(&wrapped.wrapped)?;
+
+        let wrapping_key = self.derive_share_key(passphrase, &salt, &split_key.kdf_params)?;
+        let bytes = CryptoManager::with_key(wrapping_key, self.algorithm)
+            .decrypt(&data, BlobContext::KeyShare { user_id, index })?;
+        Ok(Share { index, bytes })
+    }
+
+    /// Derives the key wrapping a key share.
+    ///
+    /// The salt is the hardware salt followed by the share's random salt,
+    /// so the two wrapping keys differ even for the same passphrase.
+    fn derive_share_key(&self, passphrase: &str, salt: &[u8], params: &KdfParams) -> Result<Key> {
+        let mut full_salt = self.generate_hardware_salt().to_vec();
+        full_salt.extend_from_slice(salt);
+        kdf::derive_key(passphrase, &full_salt, params)
+    }
+
+    /// Reads a user's split key file.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Option<SplitKeyFile>>` - The file, None if two-person
+    ///   unlock is off, or error if it can't be read
+    fn load_split_key(&self, user_id: &str) -> Result<Option<SplitKeyFile>> {
+        let path = self.split_key_path(user_id);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let split_key: SplitKeyFile = serde_json::from_str(&fs::read_to_string(&path)?)
+            .map_err(|e| anyhow!("Failed to parse the split key: {}", e))?;
+        if split_key.version > SPLIT_KEY_VERSION {
+            return Err(anyhow!(
+                "The split key was written by a newer version of the app"
+            ));
+        }
+        Ok(Some(split_key))
+    }
+
+    /// Returns the path of a user's split key file.
+    fn split_key_path(&self, user_id: &str) -> std::path::PathBuf {
+        self.config_path
+            .join("users")
+            .join(user_id)
+            .join("split.key")
+    }
+
     /// Generates a stable hardware fingerprint for device binding.
     ///
     /// Creates a fingerprint based on stable system characteristics that
@@ -292,7 +999,7 @@ impl CryptoManager {
     /// * OS and architecture - Very stable
     /// * Computer name - Usually stable but can change
     fn generate_stable_hardware_fingerprint(&self) -> Result<(u64, Vec<String>)> {
-        println!("Generating stable hardware fingerprint...");
+        log::trace!("Generating stable hardware fingerprint");
 
         // Use only the most stable components
         let mut components = Vec::new();
@@ -330,9 +1037,6 @@ impl CryptoManager {
         combined.hash(&mut hasher);
         let hash = hasher.finish();
 
-        println!("Hardware fingerprint components: {:?}", components);
-        println!("Generated hash: {}", hash);
-
         Ok((hash, components))
     }
 
@@ -368,7 +1072,8 @@ impl CryptoManager {
 
     /// Derives a secure encryption key from a password using Argon2.
     ///
-    /// Uses production-grade parameters that balance security and performance:
+    /// New keys use production-grade parameters that balance security and
+    /// performance (see `KdfParams::CURRENT`):
     /// - Memory cost: 128 MB
     /// - Iterations: 3
     /// - Parallelism: 4 threads
@@ -379,32 +1084,49 @@ impl CryptoManager {
     /// # Arguments
     ///
     /// * `password` - The user's password
+    /// * `kdf_params` - The Argon2 parameters to derive with
     ///
     /// # Returns
     ///
-    /// * `chacha20poly1305::Key` - 32-byte encryption key
-    fn derive_secure_key(&self, password: &str) -> chacha20poly1305::Key {
-        println!("Using standard security key derivation...");
-
-        // Standard security parameters - should take ~5-10 seconds on most hardware
-        let memory_cost = 131072; // 128 MB
-        let iterations = 3; // 3 iterations
-        let parallelism = 4; // 4 parallel threads
-
-        let params = argon2::Params::new(memory_cost, iterations, parallelism, Some(32))
-            .expect("Invalid Argon2 parameters");
-
-        let argon2 = Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params);
+    /// * `Result<Key>` - 32-byte encryption key, or error if the parameters
+    ///   are invalid
+    fn derive_secure_key(&self, password: &str, kdf_params: &KdfParams) -> Result<Key> {
+        log::debug!("Using standard security key derivation");
 
         // Generate a hardware-bound salt
         let hardware_salt = self.generate_hardware_salt();
 
-        let mut key = [0u8; 32];
-        argon2
-            .hash_password_into(password.as_bytes(), &hardware_salt, &mut key)
-            .expect("Failed to derive key");
+        kdf::derive_key(password, &hardware_salt, kdf_params)
+    }
 
-        key.into()
+    /// Estimates how long `derive_secure_key` will take on this machine.
+    ///
+    /// Uses the duration of the previous derivation stored in the security
+    /// metadata if it was done with the same parameters. Otherwise runs a
+    /// single Argon2 pass with a fraction of the memory cost and
+    /// extrapolates, which takes only a few tens of milliseconds.
+    ///
+    /// # Arguments
+    ///
+    /// * `kdf_params` - The parameters the derivation will use
+    /// * `metadata` - The user's security metadata, if it exists already
+    ///
+    /// # Returns
+    ///
+    /// * `Duration` - Estimated key derivation time
+    fn estimate_key_derivation_time(
+        &self,
+        kdf_params: &KdfParams,
+        metadata: Option<&SecurityMetadata>,
+    ) -> std::time::Duration {
+        if let Some(ms) = metadata
+            .filter(|m| m.kdf_params == *kdf_params)
+            .and_then(|m| m.last_kdf_duration_ms)
+        {
+            return std::time::Duration::from_millis(ms);
+        }
+
+        kdf::estimate_derivation_time(kdf_params)
     }
 
     /// Generates a deterministic salt based on hardware fingerprint.
@@ -439,70 +1161,73 @@ impl CryptoManager {
         }
     }
 
-    /// Sets secure file permissions on Unix systems.
+    /// Restricts a file to the current user.
     ///
-    /// Sets file permissions to 0o600 (read/write for owner only) on Unix systems.
-    /// On other systems, this is a no-op.
+    /// Sets file permissions to 0o600 (read/write for owner only) on Unix
+    /// systems and an owner-only ACL on Windows.
     ///
     /// # Arguments
     ///
-    /// * `_file_path` - Path to the file to secure
+    /// * `file_path` - Path to the file to secure
     ///
     /// # Returns
     ///
     /// * `Result<()>` - Ok if successful, Err if permission setting failed
-    fn secure_file_permissions(&self, _file_path: &std::path::Path) -> Result<()> {
-        #[cfg(unix)]
-        {
-            use std::os::unix::fs::PermissionsExt;
-            let mut perms = fs::metadata(_file_path)?.permissions();
-            perms.set_mode(0o600);
-            fs::set_permissions(_file_path, perms)?;
-        }
+    fn secure_file_permissions(&self, file_path: &std::path::Path) -> Result<()> {
+        secure_fs::restrict_to_owner(file_path)?;
         Ok(())
     }
 
-    /// Encrypts data using ChaCha20Poly1305.
+    /// Selects the algorithm used for newly encrypted data.
     ///
-    /// Generates a random nonce and encrypts the data, prepending the nonce
-    /// to the ciphertext for later decryption.
+    /// Existing data keeps decrypting with whatever algorithm is recorded in
+    /// its header; it is migrated to the new algorithm the next time it is
+    /// written.
+    ///
+    /// # Arguments
+    ///
+    /// * `algorithm` - The algorithm to use for future encryptions
+    pub fn set_algorithm(&mut self, algorithm: AeadAlgorithm) {
+        self.algorithm = algorithm;
+    }
+
+    /// Encrypts data with the selected AEAD algorithm.
+    ///
+    /// Generates a random nonce and encrypts the data, prepending the
+    /// versioned header and the nonce to the ciphertext for later decryption.
+    /// The context is authenticated along with the data and must be passed
+    /// again to `decrypt`.
     ///
     /// # Arguments
     ///
     /// * `data` - The plaintext data to encrypt
+    /// * `context` - Owner and role of the blob
     ///
     /// # Returns
     ///
-    /// * `Result<Vec<u8>>` - Encrypted data with nonce prepended, or error
+    /// * `Result<Vec<u8>>` - Header, nonce and ciphertext, or error
     ///
     /// # Errors
     ///
     /// * Cipher not initialized (call `initialize_for_user` first)
     /// * Encryption operation fails
-    pub fn encrypt(&self, data: &[u8]) -> Result<Vec<u8>> {
-        let cipher = self
-            .cipher
-            .as_ref()
-            .ok_or_else(|| anyhow!("Cipher not initialized"))?;
-        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
-        let ciphertext = cipher
-            .encrypt(&nonce, data)
-            .map_err(|e| anyhow!("Encryption failed: {}", e))?;
+    pub fn encrypt(&self, data: &[u8], context: BlobContext) -> Result<Vec<u8>> {
+        let key = self.key.ok_or_else(|| anyhow!("Cipher not initialized"))?;
 
-        let mut result = Vec::new();
-        result.extend_from_slice(&nonce);
-        result.extend_from_slice(&ciphertext);
-        Ok(result)
+        self.cipher(key).encrypt(data, &context.associated_data())
     }
 
-    /// Decrypts data using ChaCha20Poly1305.
+    /// Decrypts data produced by `encrypt`.
     ///
-    /// Extracts the nonce from the beginning of the data and decrypts
-    /// the remaining ciphertext.
+    /// Reads the algorithm from the versioned header and decrypts the
+    /// remaining ciphertext. Data without a header is treated as the
+    /// legacy `nonce || ciphertext` ChaCha20Poly1305 format, unless the
+    /// older formats were retired.
     ///
     /// # Arguments
     ///
-    /// * `data` - The encrypted data with nonce prepended
+    /// * `data` - The encrypted data
+    /// * `context` - Owner and role the blob was encrypted for
     ///
     /// # Returns
     ///
@@ -511,24 +1236,85 @@ impl CryptoManager {
     /// # Errors
     ///
     /// * Cipher not initialized
-    /// * Invalid data format (too short or corrupted)
-    /// * Decryption operation fails (wrong key, tampered data, etc.)
-    pub fn decrypt(&self, data: &[u8]) -> Result<Vec<u8>> {
-        let cipher = self
-            .cipher
-            .as_ref()
-            .ok_or_else(|| anyhow!("Cipher not initialized"))?;
-
-        if data.len() < 12 {
-            return Err(anyhow!("Invalid encrypted data"));
+    /// * Invalid data format (too short, unknown version or algorithm)
+    /// * Decryption operation fails (wrong key, wrong context, tampered data)
+    pub fn decrypt(&self, data: &[u8], context: BlobContext) -> Result<Vec<u8>> {
+        let key = self.key.ok_or_else(|| anyhow!("Cipher not initialized"))?;
+
+        let aad = context.associated_data();
+        match self.cipher(key).decrypt(data, &aad) {
+            // Data not re-encrypted yet during a key upgrade uses the old key
+            Err(e) => match &self.pending_kdf_upgrade {
+                Some(upgrade) => self
+                    .cipher(upgrade.previous_key)
+                    .decrypt(data, &aad)
+                    .map_err(|_| e),
+                None => Err(e),
+            },
+            plaintext => plaintext,
         }
+    }
+
+    /// Checks whether a blob should be rewritten with `encrypt`.
+    ///
+    /// True for blobs in an older ciphertext format and, while a key
+    /// derivation upgrade is pending, for every blob, since it may still be
+    /// encrypted with the old key.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The encrypted blob as read from disk
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - Whether the blob should be decrypted and encrypted again
+    pub fn needs_rewrite(&self, data: &[u8]) -> bool {
+        !Cipher::is_current_format(data) || self.pending_kdf_upgrade.is_some()
+    }
+
+    /// Returns whether blobs in an older ciphertext format are still
+    /// decrypted, see `retire_legacy_formats`.
+    pub fn legacy_formats_accepted(&self) -> bool {
+        self.legacy_formats
+    }
+
+    /// Stops decrypting version 1 and headerless blobs for good.
+    ///
+    /// Those carry no associated data, so an old blob of another note or
+    /// file could be swapped in without detection. Must only be called
+    /// once every blob of the user has been rewritten in the current
+    /// format; the decision is recorded in the security metadata.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - User ID for file operations
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - Ok if successful, Err if the metadata could not be written
+    pub fn retire_legacy_formats(&mut self, user_id: &str) -> Result<()> {
+        let Some(metadata) = self.security_metadata.as_mut() else {
+            return Err(anyhow!("Security metadata not loaded"));
+        };
+
+        metadata.legacy_formats_retired = true;
+        let metadata_file = self
+            .config_path
+            .join("users")
+            .join(user_id)
+            .join("security.meta");
+        fs::write(&metadata_file, serde_json::to_string_pretty(&metadata)?)?;
 
-        let (nonce_bytes, ciphertext) = data.split_at(12);
-        let nonce = Nonce::from_slice(nonce_bytes);
-        let plaintext = cipher
-            .decrypt(nonce, ciphertext)
-            .map_err(|e| anyhow!("Decryption failed: {}", e))?;
-        Ok(plaintext)
+        self.legacy_formats = false;
+        log::info!("Older ciphertext formats retired for user {}", user_id);
+        Ok(())
+    }
+
+    /// Creates a cipher for a key with the current algorithm and formats.
+    fn cipher(&self, key: Key) -> Cipher {
+        let mut cipher = Cipher::new(key, self.algorithm);
+        cipher.set_legacy_formats(self.legacy_formats);
+        cipher
     }
 
     /// Performs a security audit of the current session.
@@ -581,82 +1367,192 @@ impl CryptoManager {
             };
 
             format!(
-                "Security Level: Standard (Production)\nVersion: {}\nCreated: {}\nHardware Bound: Yes\nMemory Cost: 128 MB\nIterations: 3\nParallelism: 4\nHardware Components: {}",
+                "Security Level: Standard (Production)\nVersion: {}\nCreated: {}\nEncryption: {}\nHardware Bound: Yes\nMemory Cost: {} MB\nIterations: {}\nParallelism: {}\nHardware Components: {}",
                 metadata.version,
                 chrono::DateTime::from_timestamp(metadata.created_timestamp as i64, 0)
                     .map(|dt| dt.format("%Y-%m-%d %H:%M:%S UTC").to_string())
                     .unwrap_or_else(|| "Unknown".to_string()),
+                self.algorithm.label(),
+                metadata.kdf_params.memory_cost_kib / 1024,
+                metadata.kdf_params.iterations,
+                metadata.kdf_params.parallelism,
                 components_str
             )
         })
     }
 
-    /// Changes the user's password and re-initializes encryption.
+    /// Returns the result of the hardware check of the last initialization.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<HardwareCheck>` - The result, or None if initialization
+    ///   failed before the check, e.g. because of a wrong password
+    pub fn hardware_check(&self) -> Option<HardwareCheck> {
+        self.hardware_check
+    }
+
+    /// Returns whether a key derivation upgrade is waiting for re-encryption.
+    ///
+    /// When true, all notes and settings must be written again with the
+    /// current key before calling `complete_kdf_upgrade`.
+    pub fn kdf_upgrade_pending(&self) -> bool {
+        self.pending_kdf_upgrade.is_some()
+    }
+
+    /// Records the upgraded key derivation parameters in the security metadata.
+    ///
+    /// Must only be called after every piece of encrypted data has been
+    /// re-encrypted with the new key, since the old key is no longer
+    /// derived afterwards.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - User ID for file operations
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - Ok if successful, Err if the metadata could not be written
+    pub fn complete_kdf_upgrade(&mut self, user_id: &str) -> Result<()> {
+        let (Some(upgrade), Some(metadata)) =
+            (&self.pending_kdf_upgrade, self.security_metadata.as_mut())
+        else {
+            return Ok(());
+        };
+
+        metadata.kdf_params = upgrade.params;
+        metadata.last_kdf_duration_ms = Some(upgrade.duration_ms);
+
+        let metadata_file = self
+            .config_path
+            .join("users")
+            .join(user_id)
+            .join("security.meta");
+        fs::write(&metadata_file, serde_json::to_string_pretty(&metadata)?)?;
+
+        self.pending_kdf_upgrade = None;
+        log::info!("Key derivation upgrade completed for user {}", user_id);
+        Ok(())
+    }
+
+    /// Starts a password change by deriving the key for the new password.
     ///
-    /// Verifies the old password, generates a new password hash, saves it,
-    /// and re-initializes the crypto manager with the new password.
+    /// Nothing is written to disk. The new key becomes the current key and
+    /// the old one stays available for decryption, as during a key
+    /// derivation upgrade, so the caller can re-encrypt all data. The change
+    /// is then made permanent with `write_password_hash` and
+    /// `complete_kdf_upgrade`, or undone with `revert_key_change`.
     ///
     /// # Arguments
     ///
     /// * `old_password` - Current password for verification
-    /// * `new_password` - New password to set
+    /// * `new_password` - New password to derive the key from
     /// * `user_id` - User ID for file operations
     ///
     /// # Returns
     ///
-    /// * `Result<()>` - Ok if successful, Err with details if failed
+    /// * `Result<()>` - Ok if the new key is in place, Err with details if
+    ///   nothing was changed
     ///
     /// # Errors
     ///
     /// * Old password verification fails
-    /// * File operations fail
-    /// * Re-initialization with new password fails
-    pub fn change_password(
+    /// * A key derivation upgrade is still pending
+    /// * Two-person unlock is on
+    /// * Key derivation fails
+    pub fn begin_password_change(
         &mut self,
         old_password: &str,
         new_password: &str,
         user_id: &str,
     ) -> Result<()> {
-        // Verify old password first
-        let user_config_path = self.config_path.join("users").join(user_id);
-        let key_file = user_config_path.join("auth.hash");
-
-        if !key_file.exists() {
-            return Err(anyhow!("User configuration not found"));
+        let previous_key = self.prepare_key_change(user_id, old_password)?;
+        if self.two_person_unlock_enabled(user_id) {
+            return Err(anyhow!(
+                "Turn off two-person unlock before changing the password"
+            ));
         }
 
-        let stored_hash = fs::read_to_string(&key_file)?;
-        let parsed_hash = PasswordHash::new(&stored_hash)
-            .map_err(|e| anyhow!("Failed to parse password hash: {}", e))?;
+        let derivation_start = std::time::Instant::now();
+        let new_key = self.derive_secure_key(new_password, &KdfParams::CURRENT)?;
+        self.pending_kdf_upgrade = Some(PendingKdfUpgrade {
+            previous_key,
+            params: KdfParams::CURRENT,
+            duration_ms: derivation_start.elapsed().as_millis() as u64,
+        });
+        self.key = Some(new_key);
 
-        // Verify old password
-        Argon2::default()
-            .verify_password(old_password.as_bytes(), &parsed_hash)
-            .map_err(|_| anyhow!("Current password is incorrect"))?;
+        log::info!("Password change started for user {}", user_id);
+        Ok(())
+    }
+
+    /// Switches back to the key in use before `begin_password_change` or
+    /// a two-person unlock change.
+    ///
+    /// The key of the abandoned change stays available for decryption, so
+    /// files that were already re-encrypted can be written back with the
+    /// old key. Call `finish_key_revert` once they are.
+    pub fn revert_key_change(&mut self) {
+        if let (Some(upgrade), Some(key)) = (&mut self.pending_kdf_upgrade, &mut self.key) {
+            std::mem::swap(&mut upgrade.previous_key, key);
+        }
+    }
 
-        // Generate new password hash
-        let verification_salt = SaltString::generate(&mut OsRng);
-        let argon2 = Argon2::default();
-        let new_password_hash = argon2
-            .hash_password(new_password.as_bytes(), &verification_salt)
-            .map_err(|e| anyhow!("Failed to hash new password: {}", e))?;
+    /// Drops the key of an abandoned change, see `revert_key_change`.
+    pub fn finish_key_revert(&mut self) {
+        self.pending_kdf_upgrade = None;
+        self.pending_split_key = None;
+    }
 
-        // Save new password hash
-        fs::write(&key_file, new_password_hash.to_string())?;
+    /// Replaces the stored password hash with one of the new password.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - User ID for file operations
+    /// * `new_password` - The new password
+    ///
+    /// # Returns
+    ///
+    /// * `Result<String>` - The previous hash, for `restore_password_hash`,
+    ///   or error if the hash could not be written
+    pub fn write_password_hash(&self, user_id: &str, new_password: &str) -> Result<String> {
+        let key_file = self.password_hash_path(user_id);
+        let previous_hash = fs::read_to_string(&key_file)?;
+
+        fs::write(&key_file, kdf::hash_password(new_password)?)?;
         self.secure_file_permissions(&key_file)?;
+        Ok(previous_hash)
+    }
 
-        // Re-initialize with new password
-        self.initialize_for_user(user_id, new_password)?;
+    /// Writes back a password hash replaced by `write_password_hash`.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - User ID for file operations
+    /// * `previous_hash` - The hash to restore
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - Ok if successful, Err if the file could not be written
+    pub fn restore_password_hash(&self, user_id: &str, previous_hash: &str) -> Result<()> {
+        let key_file = self.password_hash_path(user_id);
+        fs::write(&key_file, previous_hash)?;
+        self.secure_file_permissions(&key_file)
+    }
 
-        println!("Password changed successfully for user {}", user_id);
-        Ok(())
+    /// Returns the path of a user's password verification hash.
+    fn password_hash_path(&self, user_id: &str) -> std::path::PathBuf {
+        self.config_path
+            .join("users")
+            .join(user_id)
+            .join("auth.hash")
     }
 
     /// Deletes all cryptographic data for a user.
     ///
     /// Removes the user's entire cryptographic configuration directory,
     /// including password hashes, security metadata, and any other
-    /// crypto-related files.
+    /// crypto-related files. Files are overwritten before removal (best
+    /// effort, see the `secure_fs` module).
     ///
     /// # Arguments
     ///
@@ -669,10 +1565,32 @@ impl CryptoManager {
         let user_config_path = self.config_path.join("users").join(user_id);
 
         if user_config_path.exists() {
-            fs::remove_dir_all(&user_config_path)?;
-            println!("Deleted crypto data for user {}", user_id);
+            secure_fs::secure_remove_dir_all(&user_config_path)?;
+            log::info!("Deleted crypto data for user {}", user_id);
         }
 
         Ok(())
     }
 }
+
+/// Lists the kinds of hardware components (e.g. `home`, `computer`) that differ.
+///
+/// Used for logging fingerprint changes without writing the component values
+/// (usernames, paths, host names) to the log file.
+///
+/// # Arguments
+///
+/// * `stored` - Previously stored hardware components
+/// * `current` - Current hardware components
+///
+/// # Returns
+///
+/// * `Vec<String>` - Prefixes of the components that changed
+fn changed_component_kinds(stored: &[String], current: &[String]) -> Vec<String> {
+    stored
+        .iter()
+        .zip(current.iter())
+        .filter(|(stored, current)| stored != current)
+        .map(|(stored, _)| stored.split(':').next().unwrap_or("unknown").to_string())
+        .collect()
+}
@@ -0,0 +1,336 @@
+//! # Editor UI Module
+//!
+//! The multiline text editor of the main content area, with the optional
+//! line number gutter and current-line highlight drawn around egui's
+//! `TextEdit` from the laid-out text. Code notes and fenced code blocks
+//! are syntax-highlighted, see the highlight module, and web links open
+//! in the browser on Ctrl+click. Other views can ask the editor to select
+//! and scroll to a part of the note, see `NotesApp::editor_jump`.
+//!
+//! With typewriter scrolling, the editor scrolls so the cursor line stays
+//! in the middle of the view while typing or moving with the keyboard.
+//! Half a view of empty space below the text lets the last lines reach
+//! the middle too. Mouse clicks don't scroll, so the text doesn't move
+//! away from the pointer.
+
+use crate::app::NotesApp;
+use crate::links;
+use crate::note::UNTITLED_NOTE_TITLE;
+use crate::text_cache::LARGE_NOTE_BYTES;
+use eframe::egui;
+use egui::scroll_area::ScrollAreaOutput;
+
+/// Space between the line numbers and the text, in points.
+const GUTTER_PADDING: f32 = 8.0;
+
+impl NotesApp {
+    /// Renders the note editor in a scroll area of a fixed height.
+    ///
+    /// Applies and clears `editor_scroll_restore` and keeps
+    /// `editor_scroll_offset` up to date for the next session.
+    ///
+    /// # Arguments
+    ///
+    /// * `ui` - The UI of the main content area or of the split view's left pane
+    /// * `note_id` - The ID of the note to edit
+    /// * `read_only` - Whether editing is disabled (trashed or locked notes)
+    /// * `height` - Height of the scroll area
+    ///
+    /// # Returns
+    ///
+    /// * `ScrollAreaOutput<()>` - Scroll position and sizes of the editor
+    pub fn render_editor_pane(
+        &mut self,
+        ui: &mut egui::Ui,
+        note_id: &str,
+        read_only: bool,
+        height: f32,
+    ) -> ScrollAreaOutput<()> {
+        let mut scroll_area = egui::ScrollArea::vertical()
+            .max_height(height)
+            .auto_shrink([false, false]);
+        if let Some(offset) = self.editor_scroll_restore.take() {
+            scroll_area = scroll_area.vertical_scroll_offset(offset);
+        }
+        let scroll_output = scroll_area.show(ui, |ui| {
+            self.render_note_editor(ui, note_id, read_only, height);
+        });
+        self.editor_scroll_offset = scroll_output.state.offset.y;
+        scroll_output
+    }
+
+    /// Renders the text editor for a note's content.
+    ///
+    /// Edits update the modification time, the auto-title and the search
+    /// index, and schedule an auto-save. With line numbers enabled, each
+    /// logical line is numbered at its first visual row, so wrapped lines
+    /// keep a single number. A pending `editor_jump` for this note selects
+    /// its range and scrolls it into view. With typewriter scrolling, the
+    /// cursor line is scrolled to the middle of `min_height`.
+    ///
+    /// # Arguments
+    ///
+    /// * `ui` - The scrollable editor UI
+    /// * `note_id` - The ID of the note to edit
+    /// * `read_only` - Whether editing is disabled (trashed notes)
+    /// * `min_height` - Minimum height of the editor
+    pub fn render_note_editor(
+        &mut self,
+        ui: &mut egui::Ui,
+        note_id: &str,
+        read_only: bool,
+        min_height: f32,
+    ) {
+        let show_line_numbers = self.settings.show_line_numbers;
+        let highlight_current_line = self.settings.highlight_current_line;
+        let typewriter_scrolling = self.settings.typewriter_scrolling;
+        let jump = self
+            .editor_jump
+            .take_if(|(id, _)| id == note_id)
+            .map(|(_, range)| range);
+        let layout_cache = &mut self.editor_layout_cache;
+        let Some(note) = self.notes.get_mut(note_id) else {
+            return;
+        };
+        if note.content.len() > LARGE_NOTE_BYTES {
+            ui.small("Large note: highlighting and link underlining are off to keep typing fast.");
+        }
+
+        // The jump target as character positions, as the cursor counts them
+        let editor_id = egui::Id::new(("note_editor", note_id));
+        let jump = jump.and_then(|range| {
+            let start = note.content.get(..range.start)?.chars().count();
+            let length = note.content.get(range)?.chars().count();
+            Some(egui::text::CCursorRange::two(
+                egui::text::CCursor::new(start),
+                egui::text::CCursor::new(start + length),
+            ))
+        });
+        if let Some(range) = jump {
+            let mut state =
+                egui::text_edit::TextEditState::load(ui.ctx(), editor_id).unwrap_or_default();
+            state.cursor.set_char_range(Some(range));
+            state.store(ui.ctx(), editor_id);
+            ui.memory_mut(|memory| memory.request_focus(editor_id));
+        }
+
+        let number_font = egui::TextStyle::Monospace.resolve(ui.style());
+        let gutter_width = if show_line_numbers {
+            let digits = layout_cache.line_count().to_string().len().max(2);
+            let digit_width = ui.fonts(|fonts| fonts.glyph_width(&number_font, '0'));
+            digits as f32 * digit_width + GUTTER_PADDING
+        } else {
+            0.0
+        };
+
+        // Code notes and fenced code blocks are syntax-highlighted; the
+        // layout is cached, so idle frames don't highlight the text again
+        let language = note.language.clone();
+        let mut layouter = |ui: &egui::Ui, text: &str, wrap_width: f32| {
+            layout_cache.layout(ui, text, wrap_width, language.as_deref())
+        };
+
+        let (gutter_rect, output) = ui
+            .horizontal_top(|ui| {
+                let (gutter_rect, _) =
+                    ui.allocate_exact_size(egui::vec2(gutter_width, 0.0), egui::Sense::hover());
+                let mut text_edit = egui::TextEdit::multiline(&mut note.content)
+                    .id(editor_id)
+                    .desired_width(f32::INFINITY)
+                    .desired_rows(20) // Minimum number of visible rows
+                    .min_size(egui::vec2(0.0, min_height))
+                    .interactive(!read_only);
+                text_edit = text_edit.layouter(&mut layouter);
+                if language.is_some() {
+                    text_edit = text_edit.code_editor();
+                }
+                let output = text_edit.show(ui);
+                (gutter_rect, output)
+            })
+            .inner;
+
+        if let Some(range) = jump {
+            let cursor = output.galley.from_ccursor(range.primary);
+            let rect = output
+                .galley
+                .pos_from_cursor(&cursor)
+                .translate(output.galley_pos.to_vec2());
+            ui.scroll_to_rect(rect, Some(egui::Align::Center));
+        }
+
+        if typewriter_scrolling {
+            // Room to scroll the last line up to the middle
+            ui.add_space(min_height / 2.0);
+
+            if let Some(range) = output
+                .cursor_range
+                .filter(|_| output.response.has_focus() && jump.is_none())
+            {
+                let cursor_id = editor_id.with("typewriter_cursor");
+                let index = range.primary.ccursor.index;
+                let moved = ui.data_mut(|data| {
+                    let previous = data.get_temp::<usize>(cursor_id);
+                    data.insert_temp(cursor_id, index);
+                    previous != Some(index)
+                });
+                let pointer_used = ui.input(|i| i.pointer.any_down() || i.pointer.any_released());
+
+                if (moved || output.response.changed()) && !pointer_used {
+                    let rect = output
+                        .galley
+                        .pos_from_cursor(&range.primary)
+                        .translate(output.galley_pos.to_vec2());
+                    ui.scroll_to_rect(rect, Some(egui::Align::Center));
+                }
+            }
+        }
+
+        // Rows of the logical line holding the cursor
+        let current_rows = output
+            .cursor_range
+            .filter(|_| output.response.has_focus())
+            .map(|range| logical_line_rows(&output.galley, range.primary.rcursor.row));
+
+        if highlight_current_line {
+            if let Some((first, last)) = current_rows.clone().and_then(|rows| {
+                Some((
+                    output.galley.rows.get(*rows.start())?,
+                    output.galley.rows.get(*rows.end())?,
+                ))
+            }) {
+                let rect = egui::Rect::from_x_y_ranges(
+                    output.response.rect.x_range(),
+                    output.galley_pos.y + first.rect.min.y..=output.galley_pos.y + last.rect.max.y,
+                );
+                ui.painter()
+                    .rect_filled(rect, 0.0, egui::Color32::from_white_alpha(12));
+            }
+        }
+
+        if show_line_numbers {
+            let painter = ui.painter();
+            let mut line_number = 1;
+            let mut starts_line = true;
+            for (index, row) in output.galley.rows.iter().enumerate() {
+                if starts_line {
+                    let is_current = current_rows
+                        .as_ref()
+                        .is_some_and(|rows| rows.contains(&index));
+                    painter.text(
+                        egui::pos2(
+                            gutter_rect.right() - GUTTER_PADDING / 2.0,
+                            output.galley_pos.y + row.rect.min.y,
+                        ),
+                        egui::Align2::RIGHT_TOP,
+                        line_number.to_string(),
+                        number_font.clone(),
+                        if is_current {
+                            egui::Color32::LIGHT_GRAY
+                        } else {
+                            egui::Color32::DARK_GRAY
+                        },
+                    );
+                    line_number += 1;
+                }
+                starts_line = row.ends_with_newline;
+            }
+        }
+
+        // Links show their URL on hover and open on Ctrl+click
+        let mut clicked_url = None;
+        let hovered_url = output
+            .response
+            .hover_pos()
+            .and_then(|pointer| url_at_pointer(&output, &note.content, pointer));
+        if let Some(url) = hovered_url {
+            let ctrl_held = ui.input(|i| i.modifiers.command);
+            egui::show_tooltip_at_pointer(
+                ui.ctx(),
+                ui.layer_id(),
+                output.response.id.with("link_tooltip"),
+                |ui| {
+                    ui.label(&url);
+                    ui.small("Ctrl + click to open");
+                },
+            );
+            if ctrl_held {
+                ui.ctx().set_cursor_icon(egui::CursorIcon::PointingHand);
+                if output.response.clicked() {
+                    clicked_url = Some(url);
+                }
+            }
+        }
+
+        if output.response.changed() {
+            // Notes still called "Untitled Note" take their first line
+            if self.settings.auto_title {
+                note.auto_title |= note.title == UNTITLED_NOTE_TITLE;
+                note.sync_auto_title();
+            }
+            note.update_modified_time();
+            self.search_index.note_changed(note);
+            self.has_unsaved_changes = true;
+            self.last_save_time = std::time::Instant::now();
+        }
+
+        if let Some(url) = clicked_url {
+            self.open_url(&url);
+        }
+    }
+}
+
+/// Finds the link under the mouse pointer in the editor.
+///
+/// # Arguments
+///
+/// * `output` - The editor's output of this frame
+/// * `text` - The edited text
+/// * `pointer` - Position of the mouse pointer
+///
+/// # Returns
+///
+/// * `Option<String>` - The URL under the pointer, if any
+fn url_at_pointer(
+    output: &egui::text_edit::TextEditOutput,
+    text: &str,
+    pointer: egui::Pos2,
+) -> Option<String> {
+    let position = pointer - output.galley_pos;
+    let cursor = output.galley.cursor_from_pos(position);
+    let row = output.galley.rows.get(cursor.rcursor.row)?;
+    if !row.rect.contains(position.to_pos2()) {
+        return None;
+    }
+
+    // The cursor sits between two characters; check the one on either side
+    let index = cursor.ccursor.index;
+    [index, index.saturating_sub(1)]
+        .into_iter()
+        .find_map(|index| {
+            let (byte_index, _) = text.char_indices().nth(index)?;
+            links::url_at(text, byte_index).map(str::to_string)
+        })
+}
+
+/// Finds the visual rows making up the logical line that contains a row.
+///
+/// # Arguments
+///
+/// * `galley` - The laid-out text
+/// * `row` - Index of a visual row
+///
+/// # Returns
+///
+/// * `RangeInclusive<usize>` - Indices of the first and last row of the line
+fn logical_line_rows(galley: &egui::Galley, row: usize) -> std::ops::RangeInclusive<usize> {
+    let row = row.min(galley.rows.len().saturating_sub(1));
+    let mut first = row;
+    while first > 0 && !galley.rows[first - 1].ends_with_newline {
+        first -= 1;
+    }
+    let mut last = row;
+    while last + 1 < galley.rows.len() && !galley.rows[last].ends_with_newline {
+        last += 1;
+    }
+    first..=last
+}
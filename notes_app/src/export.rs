@@ -0,0 +1,274 @@
+//! # Export Module
+//!
+//! Helpers shared by every export. Exported files are named after a
+//! user-configurable template such as `{date}-{title}.{ext}`, in which
+//! these placeholders are replaced:
+//!
+//! | Placeholder  | Replaced by                                  |
+//! | ------------ | -------------------------------------------- |
+//! | `{title}`    | Note title, reduced to file-name-safe chars  |
+//! | `{date}`     | Export date, `YYYY-MM-DD`                    |
+//! | `{time}`     | Export time, `HH-MM`                         |
+//! | `{created}`  | Creation date of the note, `YYYY-MM-DD`      |
+//! | `{modified}` | Last modification date, `YYYY-MM-DD`         |
+//! | `{notebook}` | Notebook of the note, empty if it has none   |
+//! | `{id}`       | First 8 characters of the note ID            |
+//! | `{ext}`      | File extension of the export format          |
+//!
+//! Unknown placeholders are kept as typed. Dates are in Swiss time, like
+//! everywhere else in the app.
+//!
+//! A note can be exported as plain text with a metadata header, as Markdown
+//! with the metadata as front matter, or as a standalone HTML page. The
+//! format last used for a note is kept in the note and offered first the
+//! next time it is exported.
+
+use crate::attachments;
+use crate::note::Note;
+use chrono::Utc;
+use chrono_tz::Europe::Zurich;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+use std::path::Path;
+
+/// Template used until the user configures another one.
+pub const DEFAULT_FILENAME_TEMPLATE: &str = "{title}.{ext}";
+
+/// Placeholders understood by [`render_file_name`], for display.
+pub const FILENAME_PLACEHOLDERS: [&str; 8] = [
+    "{title}",
+    "{date}",
+    "{time}",
+    "{created}",
+    "{modified}",
+    "{notebook}",
+    "{id}",
+    "{ext}",
+];
+
+/// Title used for notes whose title has no file-name-safe characters.
+const UNTITLED_FILE_TITLE: &str = "Untitled_Note";
+
+/// File format of an exported note.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    /// Plain text with a metadata header
+    #[default]
+    Text,
+    /// Markdown with the metadata as front matter
+    Markdown,
+    /// Standalone HTML page with the Markdown rendered
+    Html,
+}
+
+impl ExportFormat {
+    /// All formats, in the order the save dialog lists them.
+    pub const ALL: [ExportFormat; 3] = [
+        ExportFormat::Text,
+        ExportFormat::Markdown,
+        ExportFormat::Html,
+    ];
+
+    /// Returns the file extension, without the dot.
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Text => "txt",
+            ExportFormat::Markdown => "md",
+            ExportFormat::Html => "html",
+        }
+    }
+
+    /// Returns the name of the file type in the save dialog.
+    pub fn label(self) -> &'static str {
+        match self {
+            ExportFormat::Text => "Text files",
+            ExportFormat::Markdown => "Markdown files",
+            ExportFormat::Html => "HTML pages",
+        }
+    }
+
+    /// Extensions accepted for the format, the usual one first.
+    fn extensions(self) -> &'static [&'static str] {
+        match self {
+            ExportFormat::Text => &["txt"],
+            ExportFormat::Markdown => &["md", "markdown"],
+            ExportFormat::Html => &["html", "htm"],
+        }
+    }
+
+    /// Finds the format of a chosen file by its extension.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The file chosen in the save dialog
+    ///
+    /// # Returns
+    ///
+    /// * `Option<ExportFormat>` - The format, None for other extensions
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        Self::ALL
+            .into_iter()
+            .find(|format| format.extensions().contains(&extension.as_str()))
+    }
+
+    /// Adds the file type filters to a save dialog, this format first so
+    /// the dialog preselects it.
+    ///
+    /// # Arguments
+    ///
+    /// * `dialog` - The save dialog
+    ///
+    /// # Returns
+    ///
+    /// * `rfd::FileDialog` - The dialog with a filter for every format
+    pub fn add_filters(self, mut dialog: rfd::FileDialog) -> rfd::FileDialog {
+        dialog = dialog.add_filter(self.label(), self.extensions());
+        for format in Self::ALL.into_iter().filter(|format| *format != self) {
+            dialog = dialog.add_filter(format.label(), format.extensions());
+        }
+        dialog.add_filter("All files", &["*"])
+    }
+}
+
+/// Renders a note as Markdown with its metadata as front matter.
+///
+/// Front matter values are written as quoted strings, which YAML reads
+/// back unchanged. Entries of a secrets note follow the content, with
+/// their secrets in plain text like in the text export.
+///
+/// # Arguments
+///
+/// * `note` - The note to export
+/// * `attachment_paths` - Exported attachments, relative to the file
+///
+/// # Returns
+///
+/// * `String` - The Markdown document
+pub fn markdown_document(note: &Note, attachment_paths: &[String]) -> String {
+    let quote = |value: &str| serde_json::to_string(value).unwrap_or_default();
+
+    let mut markdown = String::from("---\n");
+    let _ = writeln!(markdown, "title: {}", quote(&note.title));
+    let _ = writeln!(markdown, "created: {}", quote(&note.format_created_time()));
+    let _ = writeln!(
+        markdown,
+        "modified: {}",
+        quote(&note.format_modified_time())
+    );
+    let _ = writeln!(markdown, "id: {}", quote(&note.id));
+    if let Some(notebook) = &note.notebook {
+        let _ = writeln!(markdown, "notebook: {}", quote(notebook));
+    }
+    if !note.tags.is_empty() {
+        let tags: Vec<String> = note.tags.iter().map(|tag| quote(tag)).collect();
+        let _ = writeln!(markdown, "tags: [{}]", tags.join(", "));
+    }
+    for (key, value) in &note.fields {
+        let _ = writeln!(markdown, "{}: {}", quote(key), quote(value));
+    }
+    if !attachment_paths.is_empty() {
+        let paths: Vec<String> = attachment_paths.iter().map(|path| quote(path)).collect();
+        let _ = writeln!(markdown, "attachments: [{}]", paths.join(", "));
+    }
+    markdown.push_str("---\n\n");
+    markdown.push_str(&note.content);
+
+    if !note.secrets.is_empty() {
+        markdown.push_str("\n\n## Secrets\n");
+        for entry in &note.secrets {
+            let _ = write!(
+                markdown,
+                "\n- **{}**\n  - Username: {}\n  - Secret: `{}`\n  - URL: {}\n",
+                entry.label, entry.username, entry.secret, entry.url
+            );
+        }
+    }
+    markdown
+}
+
+/// Builds the default file name of an exported note from a template.
+///
+/// # Arguments
+///
+/// * `template` - Template with placeholders, the default if blank
+/// * `note` - The note being exported
+/// * `extension` - Extension of the export format, without the dot
+///
+/// # Returns
+///
+/// * `String` - A file name valid on Windows, macOS and Linux
+pub fn render_file_name(template: &str, note: &Note, extension: &str) -> String {
+    let template = if template.trim().is_empty() {
+        DEFAULT_FILENAME_TEMPLATE
+    } else {
+        template
+    };
+    let now = Utc::now().with_timezone(&Zurich);
+
+    let mut name = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}').map(|end| start + end) else {
+            break;
+        };
+        name.push_str(&rest[..start]);
+
+        let placeholder = &rest[start..=end];
+        match placeholder {
+            "{title}" => name.push_str(&safe_title(&note.title)),
+            "{date}" => name.push_str(&now.format("%Y-%m-%d").to_string()),
+            "{time}" => name.push_str(&now.format("%H-%M").to_string()),
+            "{created}" => name.push_str(&note.created_at_local().format("%Y-%m-%d").to_string()),
+            "{modified}" => name.push_str(&note.modified_at_local().format("%Y-%m-%d").to_string()),
+            "{notebook}" => {
+                if let Some(notebook) = &note.notebook {
+                    name.push_str(&safe_title(notebook));
+                }
+            }
+            "{id}" => name.push_str(&note.id.chars().take(8).collect::<String>()),
+            "{ext}" => name.push_str(extension),
+            unknown => name.push_str(unknown),
+        }
+        rest = &rest[end + 1..];
+    }
+    name.push_str(rest);
+
+    // A template without a name part would give an empty or hidden file
+    if name.trim().trim_matches('.').is_empty() || name.starts_with('.') {
+        return format!("{}.{}", UNTITLED_FILE_TITLE, extension);
+    }
+
+    // Separators and reserved characters typed into the template itself
+    attachments::sanitize_file_name(&name)
+}
+
+/// Reduces a title to letters, digits, spaces, dashes and underscores.
+///
+/// # Arguments
+///
+/// * `title` - The note title
+///
+/// # Returns
+///
+/// * `String` - The safe title, "Untitled_Note" if nothing is left
+fn safe_title(title: &str) -> String {
+    let safe: String = title
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == ' ' || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let safe = safe.trim();
+
+    if safe.is_empty() {
+        UNTITLED_FILE_TITLE.to_string()
+    } else {
+        safe.to_string()
+    }
+}
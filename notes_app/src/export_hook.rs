@@ -0,0 +1,135 @@
+//! # Export Hook Module
+//!
+//! Runs a command chosen by the user after every export or backup, e.g. to
+//! copy the file to a network share or into a backup tool. The command gets
+//! the path of the written file as its last argument.
+//!
+//! The hook is off until the user turns it on in the settings and is never
+//! taken from imported settings. No shell is involved: the command is split
+//! into words (double quotes keep spaces together) and the first word is
+//! started as the program, so the file name can't inject anything. The
+//! program runs with the user's permissions and without input; it is
+//! stopped if it takes longer than [`HOOK_TIMEOUT`].
+
+use crate::shell_open::hide_console;
+use anyhow::{anyhow, Result};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// How long a hook may run before it is stopped.
+pub const HOOK_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// A hook command started for an exported file.
+pub struct RunningHook {
+    /// The hook process
+    child: Child,
+    /// The exported file passed to the hook
+    pub path: PathBuf,
+    /// When the hook was started
+    started: Instant,
+}
+
+impl RunningHook {
+    /// Starts the hook command for an exported file.
+    ///
+    /// # Arguments
+    ///
+    /// * `command` - Program and arguments as configured
+    /// * `path` - The exported file
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self>` - The running hook, Err if the command is empty or
+    ///   the program could not be started
+    pub fn start(command: &str, path: &Path) -> Result<Self> {
+        let words = split_command(command);
+        let Some((program, args)) = words.split_first() else {
+            return Err(anyhow!("No export hook command configured"));
+        };
+
+        let mut command = Command::new(program);
+        command
+            .args(args)
+            .arg(path)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        hide_console(&mut command);
+        let child = command
+            .spawn()
+            .map_err(|e| anyhow!("Could not start {}: {}", program, e))?;
+        log::info!("Started export hook {} for {}", program, path.display());
+
+        Ok(Self {
+            child,
+            path: path.to_path_buf(),
+            started: Instant::now(),
+        })
+    }
+
+    /// Checks whether the hook has finished, stopping it after the timeout.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Result<()>>` - None while it runs, Ok if it exited
+    ///   successfully, Err if it failed or was stopped
+    pub fn poll(&mut self) -> Option<Result<()>> {
+        match self.child.try_wait() {
+            Ok(Some(status)) if status.success() => Some(Ok(())),
+            Ok(Some(status)) => Some(Err(anyhow!("Export hook failed ({})", status))),
+            Ok(None) if self.started.elapsed() > HOOK_TIMEOUT => {
+                let _ = self.child.kill();
+                let _ = self.child.wait();
+                Some(Err(anyhow!(
+                    "Export hook stopped after {} seconds",
+                    HOOK_TIMEOUT.as_secs()
+                )))
+            }
+            Ok(None) => None,
+            Err(e) => Some(Err(anyhow!("Export hook failed: {}", e))),
+        }
+    }
+}
+
+/// Splits a command line into words.
+///
+/// Words are separated by whitespace; text in double quotes is kept
+/// together, without the quotes. Nothing else is interpreted.
+///
+/// # Arguments
+///
+/// * `command` - The command line
+///
+/// # Returns
+///
+/// * `Vec<String>` - The program followed by its arguments
+pub fn split_command(command: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut quoted = false;
+
+    for c in command.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                in_word = true;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            c => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if in_word {
+        words.push(word);
+    }
+    words
+}
@@ -0,0 +1,666 @@
+//! # Health Check Module
+//!
+//! Checks the private vault of the logged-in user for problems that don't
+//! show up while working with the notes, run on demand from the settings:
+//!
+//! - Directory structure: the vault and `notes/` directories exist, and no
+//!   stray files or an old single-file `notes.enc` bundle are left
+//! - File permissions: every file is readable by its owner only (Unix; on
+//!   Windows the access lists are set when writing but not checked)
+//! - Metadata consistency: every loaded note has its file, every note file
+//!   was loaded, and every attachment a note lists has its file
+//! - Orphaned data: attachment and history files no note refers to, and
+//!   the folders and shared vault keys of accounts that no longer exist
+//! - Search index: the saved index decrypts and is up to date
+//!
+//! Each problem comes with a repair where one is safe: creating
+//! directories, restricting permissions, saving the notes, removing
+//! attachment entries whose file is gone, deleting unreferenced files and
+//! rebuilding the index. Removing the data of a deleted account can't be
+//! undone and is confirmed first, see `Repair::needs_confirmation`. Unlike
+//! the integrity scan, nothing but the search index is decrypted, so the
+//! check runs in the UI thread.
+//!
+//! Accounts are only checked against a user database that is read anew,
+//! passes its integrity check and lists the logged-in user, so a damaged
+//! database can't make every account look deleted and an account another
+//! instance just registered isn't taken for a deleted one. Folders that
+//! are locked or were written to recently are left alone as well.
+
+use crate::crypto::CryptoManager;
+use crate::note::Note;
+use crate::shared_vault::SharedVault;
+use crate::storage::{EncryptedFile, StorageManager};
+use crate::storage_report::format_size;
+use crate::trusted_time;
+use chrono::{DateTime, Utc};
+use chrono_tz::Europe::Zurich;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// How long an account folder must have been left untouched before it can
+/// count as left behind by a deleted account.
+pub const ACCOUNT_IDLE_TIME: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// The area a problem belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HealthCategory {
+    /// Missing directories and stray files
+    Structure,
+    /// Files readable by other users
+    Permissions,
+    /// Notes and their files disagreeing
+    Metadata,
+    /// Files no note or account refers to
+    OrphanedData,
+    /// The saved search index
+    SearchIndex,
+}
+
+impl HealthCategory {
+    /// Every category, in the order the checks run.
+    pub const ALL: [HealthCategory; 5] = [
+        HealthCategory::Structure,
+        HealthCategory::Permissions,
+        HealthCategory::Metadata,
+        HealthCategory::OrphanedData,
+        HealthCategory::SearchIndex,
+    ];
+
+    /// Returns the name shown in the health check window.
+    pub fn label(&self) -> &'static str {
+        match self {
+            HealthCategory::Structure => "Directory structure",
+            HealthCategory::Permissions => "File permissions",
+            HealthCategory::Metadata => "Metadata",
+            HealthCategory::OrphanedData => "Orphaned data",
+            HealthCategory::SearchIndex => "Search index",
+        }
+    }
+}
+
+/// A repair that is safe to apply with one click.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Repair {
+    /// Create the directories
+    CreateDirectories(Vec<PathBuf>),
+    /// Make the files readable by their owner only
+    RestrictPermissions(Vec<PathBuf>),
+    /// Save all notes, which writes missing note files and replaces the
+    /// old single-file bundle
+    SaveNotes,
+    /// Remove attachments whose file is gone from their notes, as note ID
+    /// and attachment ID
+    RemoveAttachmentEntries(Vec<(String, String)>),
+    /// Securely delete files no note refers to
+    DeleteFiles(Vec<PathBuf>),
+    /// Index all notes again and save the index
+    RebuildSearchIndex,
+    /// Securely delete the data folders of accounts that no longer exist,
+    /// by user ID
+    DeleteAccountFolders(Vec<String>),
+    /// Remove accounts that no longer exist from the shared vault, by user
+    /// ID
+    RemoveVaultMembers(Vec<String>),
+}
+
+impl Repair {
+    /// Returns the label of the repair button.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Repair::CreateDirectories(_) => "Create",
+            Repair::RestrictPermissions(_) => "Restrict",
+            Repair::SaveNotes => "Save Notes",
+            Repair::RemoveAttachmentEntries(_) => "Remove from Notes",
+            Repair::DeleteFiles(_) => "Delete Files",
+            Repair::RebuildSearchIndex => "Rebuild",
+            Repair::DeleteAccountFolders(_) => "Delete Folders…",
+            Repair::RemoveVaultMembers(_) => "Remove Members…",
+        }
+    }
+
+    /// Checks whether the repair deletes data that might still be wanted,
+    /// so it has to be confirmed.
+    pub fn needs_confirmation(&self) -> bool {
+        matches!(
+            self,
+            Repair::DeleteAccountFolders(_) | Repair::RemoveVaultMembers(_)
+        )
+    }
+}
+
+/// A problem found by the health check.
+#[derive(Debug, Clone)]
+pub struct HealthIssue {
+    /// Area of the problem
+    pub category: HealthCategory,
+    /// What is wrong
+    pub problem: String,
+    /// The affected files or notes, one per line
+    pub details: Vec<String>,
+    /// How to fix it, None if it has to be fixed by hand
+    pub repair: Option<Repair>,
+}
+
+/// Result of a health check.
+#[derive(Debug, Clone)]
+pub struct HealthReport {
+    /// UTC timestamp of the check
+    pub checked_at: DateTime<Utc>,
+    /// Number of encrypted note, history and attachment files found
+    pub files_checked: usize,
+    /// Problems found, empty if the vault is healthy
+    pub issues: Vec<HealthIssue>,
+}
+
+impl HealthReport {
+    /// Formats the time of the check for display in Swiss timezone.
+    ///
+    /// # Returns
+    ///
+    /// * `String` - Time as "DD.MM.YYYY HH:MM"
+    pub fn format_time(&self) -> String {
+        self.checked_at
+            .with_timezone(&Zurich)
+            .format("%d.%m.%Y %H:%M")
+            .to_string()
+    }
+}
+
+/// Checks the private vault of a user.
+///
+/// Must only run once all notes are loaded, or loaded notes would be
+/// reported as missing.
+///
+/// # Arguments
+///
+/// * `storage` - The storage manager
+/// * `user_id` - Owner of the vault
+/// * `crypto` - CryptoManager of the user, to read the search index
+/// * `notes` - All loaded notes, private and shared
+/// * `unreadable` - IDs of the notes whose files failed to decrypt
+/// * `locked` - IDs of the notes another instance is editing
+/// * `accounts` - IDs of all registered users as just read from the user
+///   database, None if it can't be read or trusted
+///
+/// # Returns
+///
+/// * `HealthReport` - The problems found
+pub fn run(
+    storage: &StorageManager,
+    user_id: &str,
+    crypto: &CryptoManager,
+    notes: &HashMap<String, Note>,
+    unreadable: &HashSet<String>,
+    locked: &HashSet<String>,
+    accounts: Option<&HashSet<String>>,
+) -> HealthReport {
+    let mut checker = Checker {
+        storage,
+        user_id,
+        vault_dir: storage.owner_dir(user_id),
+        private: notes
+            .values()
+            .filter(|note| !note.shared)
+            .map(|note| (note.id.as_str(), note))
+            .collect(),
+        unreadable,
+        locked,
+        issues: Vec::new(),
+    };
+
+    checker.check_structure();
+    checker.check_permissions();
+    let files = match storage.list_encrypted_files(user_id) {
+        Ok(files) => files,
+        Err(e) => {
+            checker.issue(
+                HealthCategory::Structure,
+                format!("The vault's files can't be listed: {}", e),
+                Vec::new(),
+                None,
+            );
+            Vec::new()
+        }
+    };
+    checker.check_metadata(&files);
+    checker.check_orphaned_files(&files);
+    checker.check_orphaned_accounts(accounts);
+    checker.check_search_index(crypto, notes);
+
+    log::info!(
+        "Health check of user {} found {} problem(s)",
+        user_id,
+        checker.issues.len()
+    );
+    HealthReport {
+        checked_at: trusted_time::now(),
+        files_checked: files.len(),
+        issues: checker.issues,
+    }
+}
+
+/// State shared by the checks.
+struct Checker<'a> {
+    storage: &'a StorageManager,
+    user_id: &'a str,
+    /// Directory of the vault, for showing relative paths
+    vault_dir: PathBuf,
+    /// The user's private notes, which are stored in the vault
+    private: HashMap<&'a str, &'a Note>,
+    unreadable: &'a HashSet<String>,
+    locked: &'a HashSet<String>,
+    issues: Vec<HealthIssue>,
+}
+
+impl Checker<'_> {
+    /// Records a problem.
+    fn issue(
+        &mut self,
+        category: HealthCategory,
+        problem: String,
+        details: Vec<String>,
+        repair: Option<Repair>,
+    ) {
+        self.issues.push(HealthIssue {
+            category,
+            problem,
+            details,
+            repair,
+        });
+    }
+
+    /// Returns a path relative to the vault directory, for display.
+    fn relative(&self, path: &Path) -> String {
+        path.strip_prefix(&self.vault_dir)
+            .unwrap_or(path)
+            .display()
+            .to_string()
+    }
+
+    /// Returns the title of a note, for display.
+    fn title(&self, note_id: &str) -> String {
+        self.private
+            .get(note_id)
+            .map_or_else(|| note_id.to_string(), |note| note.display_title())
+    }
+
+    /// Checks for missing directories, stray files and the old bundle.
+    fn check_structure(&mut self) {
+        let notes_dir = self.storage.user_notes_dir(self.user_id);
+        let missing: Vec<PathBuf> = [self.vault_dir.clone(), notes_dir.clone()]
+            .into_iter()
+            .filter(|dir| !dir.is_dir())
+            .collect();
+        if !missing.is_empty() {
+            let details = missing
+                .iter()
+                .map(|dir| dir.display().to_string())
+                .collect();
+            self.issue(
+                HealthCategory::Structure,
+                "Directories of the vault are missing".to_string(),
+                details,
+                Some(Repair::CreateDirectories(missing)),
+            );
+        }
+
+        // Notes are `.enc` files; attachments live in per-note directories
+        let mut stray: Vec<PathBuf> = entries(&notes_dir)
+            .filter(|path| !path.is_file() || !has_extension(path, "enc"))
+            .collect();
+        stray.extend(entries(&self.vault_dir.join("attachments")).filter(|path| !path.is_dir()));
+        if !stray.is_empty() {
+            let details = stray.iter().map(|path| self.relative(path)).collect();
+            self.issue(
+                HealthCategory::Structure,
+                format!(
+                    "{} item(s) in the vault are not used by the app; move them \
+                     elsewhere if they are yours",
+                    stray.len()
+                ),
+                details,
+                None,
+            );
+        }
+
+        if self.vault_dir.join("notes.enc").exists() {
+            self.issue(
+                HealthCategory::Structure,
+                "Notes are still stored in the old single-file bundle as well".to_string(),
+                vec!["notes.enc".to_string()],
+                Some(Repair::SaveNotes),
+            );
+        }
+    }
+
+    /// Checks that every file of the vault is readable by its owner only.
+    #[cfg(unix)]
+    fn check_permissions(&mut self) {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mut exposed = Vec::new();
+        let mut dirs = vec![self.vault_dir.clone()];
+        while let Some(dir) = dirs.pop() {
+            for path in entries(&dir) {
+                // Symlinks are not ours to change
+                let Ok(metadata) = fs::symlink_metadata(&path) else {
+                    continue;
+                };
+                if metadata.is_dir() {
+                    dirs.push(path);
+                } else if metadata.is_file() && metadata.permissions().mode() & 0o077 != 0 {
+                    exposed.push(path);
+                }
+            }
+        }
+        if !exposed.is_empty() {
+            let details = exposed.iter().map(|path| self.relative(path)).collect();
+            self.issue(
+                HealthCategory::Permissions,
+                format!(
+                    "{} file(s) can be read by other users of this computer",
+                    exposed.len()
+                ),
+                details,
+                Some(Repair::RestrictPermissions(exposed)),
+            );
+        }
+    }
+
+    /// Windows access lists are set when writing but not read back.
+    #[cfg(not(unix))]
+    fn check_permissions(&mut self) {}
+
+    /// Checks that notes, note files and attachment files agree.
+    fn check_metadata(&mut self, files: &[EncryptedFile]) {
+        let on_disk = note_files(files);
+
+        let mut without_file: Vec<&str> = self
+            .private
+            .keys()
+            .copied()
+            .filter(|id| !self.locked.contains(*id) && !on_disk.contains(id))
+            .collect();
+        without_file.sort();
+        if !without_file.is_empty() {
+            let details = without_file.iter().map(|id| self.title(id)).collect();
+            self.issue(
+                HealthCategory::Metadata,
+                format!("{} note(s) have no file on disk", without_file.len()),
+                details,
+                Some(Repair::SaveNotes),
+            );
+        }
+
+        let mut not_loaded: Vec<&str> = on_disk
+            .iter()
+            .copied()
+            .filter(|id| !self.private.contains_key(id) && !self.unreadable.contains(*id))
+            .collect();
+        not_loaded.sort();
+        if !not_loaded.is_empty() {
+            let details = not_loaded
+                .iter()
+                .map(|id| format!("notes/{}.enc", id))
+                .collect();
+            self.issue(
+                HealthCategory::Metadata,
+                format!(
+                    "{} note file(s) were not loaded, probably written by another \
+                     window since you logged in; log in again to load them",
+                    not_loaded.len()
+                ),
+                details,
+                None,
+            );
+        }
+
+        if !self.unreadable.is_empty() {
+            let mut details: Vec<String> = self
+                .unreadable
+                .iter()
+                .map(|id| format!("notes/{}.enc", id))
+                .collect();
+            details.sort();
+            self.issue(
+                HealthCategory::Metadata,
+                format!(
+                    "{} note file(s) can't be decrypted; they are kept untouched, \
+                     restore them from a backup",
+                    self.unreadable.len()
+                ),
+                details,
+                None,
+            );
+        }
+
+        // A note being edited elsewhere may have just gained an attachment
+        let mut missing = Vec::new();
+        let mut details = Vec::new();
+        for note in self
+            .private
+            .values()
+            .filter(|note| !self.locked.contains(&note.id))
+        {
+            for attachment in &note.attachments {
+                if !self
+                    .storage
+                    .attachment_exists(self.user_id, &note.id, &attachment.id)
+                {
+                    missing.push((note.id.clone(), attachment.id.clone()));
+                    details.push(format!(
+                        "{}: {}",
+                        note.display_title(),
+                        attachment.file_name
+                    ));
+                }
+            }
+        }
+        if !missing.is_empty() {
+            details.sort();
+            self.issue(
+                HealthCategory::Metadata,
+                format!(
+                    "{} attachment(s) are listed by a note but their file is gone",
+                    missing.len()
+                ),
+                details,
+                Some(Repair::RemoveAttachmentEntries(missing)),
+            );
+        }
+    }
+
+    /// Checks for attachment and history files no note refers to.
+    fn check_orphaned_files(&mut self, files: &[EncryptedFile]) {
+        let on_disk = note_files(files);
+        // Files of notes that failed to decrypt or weren't loaded are kept
+        let note_exists = |note_id: &str| {
+            self.private.contains_key(note_id)
+                || self.unreadable.contains(note_id)
+                || on_disk.contains(note_id)
+        };
+
+        let orphaned: Vec<&EncryptedFile> = files
+            .iter()
+            .filter(|file| match &file.attachment_id {
+                Some(attachment_id) => match self.private.get(file.note_id.as_str()) {
+                    Some(note) => {
+                        !self.locked.contains(&note.id)
+                            && !note
+                                .attachments
+                                .iter()
+                                .any(|attachment| &attachment.id == attachment_id)
+                    }
+                    None => !note_exists(&file.note_id),
+                },
+                None => file.history && !note_exists(&file.note_id),
+            })
+            .collect();
+        if !orphaned.is_empty() {
+            let mut details: Vec<String> =
+                orphaned.iter().map(|file| file.relative_path()).collect();
+            details.sort();
+            self.issue(
+                HealthCategory::OrphanedData,
+                format!(
+                    "{} attachment or history file(s) belong to no note",
+                    orphaned.len()
+                ),
+                details,
+                Some(Repair::DeleteFiles(
+                    orphaned.iter().map(|file| file.path.clone()).collect(),
+                )),
+            );
+        }
+    }
+
+    /// Checks for data of accounts that are no longer registered.
+    fn check_orphaned_accounts(&mut self, accounts: Option<&HashSet<String>>) {
+        let Some(accounts) = accounts.filter(|accounts| accounts.contains(self.user_id)) else {
+            self.issue(
+                HealthCategory::OrphanedData,
+                "Data of deleted accounts was not looked for, since the user \
+                 database can't be read or failed its integrity check"
+                    .to_string(),
+                Vec::new(),
+                None,
+            );
+            return;
+        };
+
+        match self.storage.list_user_dirs() {
+            Ok(dirs) => {
+                let orphaned: Vec<String> = dirs
+                    .into_iter()
+                    .filter(|id| !accounts.contains(id) && !account_folder_in_use(self.storage, id))
+                    .collect();
+                if !orphaned.is_empty() {
+                    let details = orphaned
+                        .iter()
+                        .map(|id| {
+                            format!(
+                                "users/{} ({})",
+                                id,
+                                format_size(self.storage.get_user_data_size(id).unwrap_or(0))
+                            )
+                        })
+                        .collect();
+                    self.issue(
+                        HealthCategory::OrphanedData,
+                        format!(
+                            "{} folder(s) belong to accounts that no longer exist; \
+                             without the account their notes can't be opened",
+                            orphaned.len()
+                        ),
+                        details,
+                        Some(Repair::DeleteAccountFolders(orphaned)),
+                    );
+                }
+            }
+            Err(e) => self.issue(
+                HealthCategory::Structure,
+                format!("The account folders can't be listed: {}", e),
+                Vec::new(),
+                None,
+            ),
+        }
+
+        let departed: Vec<String> = SharedVault::member_ids(self.storage)
+            .into_iter()
+            .filter(|id| !accounts.contains(id))
+            .collect();
+        if !departed.is_empty() {
+            let details = departed
+                .iter()
+                .map(|id| format!("shared/members/{}.key", id))
+                .collect();
+            self.issue(
+                HealthCategory::OrphanedData,
+                format!(
+                    "The shared vault is still shared with {} deleted account(s); \
+                     a vault left without members is deleted with its notes",
+                    departed.len()
+                ),
+                details,
+                Some(Repair::RemoveVaultMembers(departed)),
+            );
+        }
+    }
+
+    /// Checks that the saved search index decrypts and is up to date.
+    fn check_search_index(&mut self, crypto: &CryptoManager, notes: &HashMap<String, Note>) {
+        let problem = match self.storage.load_search_index(self.user_id, crypto) {
+            Err(e) => format!("The saved search index can't be read: {}", e),
+            Ok(None) if notes.is_empty() => return,
+            Ok(None) => "No search index has been saved yet".to_string(),
+            Ok(Some(snapshot)) if !snapshot.is_current_format() => {
+                "The saved search index is in an older format".to_string()
+            }
+            Ok(Some(snapshot)) => match snapshot.outdated_entries(notes) {
+                0 => return,
+                outdated => format!(
+                    "The saved search index is outdated for {} note(s)",
+                    outdated
+                ),
+            },
+        };
+        self.issue(
+            HealthCategory::SearchIndex,
+            format!("{}; searching still works, but is slower at login", problem),
+            Vec::new(),
+            Some(Repair::RebuildSearchIndex),
+        );
+    }
+}
+
+/// Returns the IDs of the notes that have a file.
+/// Checks whether an account folder may still be in use, e.g. by an
+/// instance that registered the account after the user database was read.
+///
+/// # Arguments
+///
+/// * `storage` - The storage manager
+/// * `user_id` - ID of the account, the folder name
+///
+/// # Returns
+///
+/// * `bool` - True if a note in it is locked, a file in it was written
+///   within [`ACCOUNT_IDLE_TIME`], or that can't be told
+pub fn account_folder_in_use(storage: &StorageManager, user_id: &str) -> bool {
+    if !storage.notes_locked_elsewhere(user_id, "").is_empty() {
+        return true;
+    }
+    match storage.user_data_modified_at(user_id) {
+        // A time in the future counts as recent
+        Ok(Some(modified_at)) => modified_at
+            .elapsed()
+            .map_or(true, |idle| idle < ACCOUNT_IDLE_TIME),
+        Ok(None) => false,
+        Err(_) => true,
+    }
+}
+
+fn note_files(files: &[EncryptedFile]) -> HashSet<&str> {
+    files
+        .iter()
+        .filter(|file| file.attachment_id.is_none() && !file.history)
+        .map(|file| file.note_id.as_str())
+        .collect()
+}
+
+/// Lists the entries of a directory, or nothing if it can't be read.
+fn entries(dir: &Path) -> impl Iterator<Item = PathBuf> {
+    fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+}
+
+/// Checks the extension of a file.
+fn has_extension(path: &Path, extension: &str) -> bool {
+    path.extension().and_then(|e| e.to_str()) == Some(extension)
+}
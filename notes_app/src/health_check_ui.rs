@@ -0,0 +1,367 @@
+//! # Health Check UI Module
+//!
+//! The Vault Health Check window opened from the troubleshooting section
+//! of the settings. It lists the problems found by the health_check module
+//! by category, with a button for each repair that is safe to apply.
+//! Repairs that delete data of other accounts ask for confirmation first.
+//! Repaired problems move to a list of fixes; "Check Again" runs the
+//! checks anew.
+
+use crate::app::NotesApp;
+use crate::health_check::{self, HealthCategory, HealthReport, Repair};
+use crate::secure_fs;
+use crate::shared_vault::SharedVault;
+use eframe::egui;
+use std::collections::HashSet;
+use std::fs;
+
+/// State of the health check window.
+pub struct HealthCheckView {
+    /// Result of the last check, None if it couldn't run
+    report: Option<HealthReport>,
+    /// Why the check couldn't run or the last repair failed
+    error: Option<String>,
+    /// Repairs applied since the check ran
+    repaired: Vec<String>,
+    /// Problem whose repair waits for confirmation
+    confirm: Option<usize>,
+}
+
+impl NotesApp {
+    /// Runs the health check and opens its window.
+    pub fn run_health_check(&mut self) {
+        let (report, error) = match self.check_vault_health() {
+            Ok(report) => (Some(report), None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+        self.health_check = Some(HealthCheckView {
+            report,
+            error,
+            repaired: Vec::new(),
+            confirm: None,
+        });
+    }
+
+    /// Checks the private vault once it is safe to compare it with the
+    /// loaded notes.
+    fn check_vault_health(&self) -> anyhow::Result<HealthReport> {
+        let (Some(user), Some(crypto_manager)) = (&self.current_user, &self.crypto_manager) else {
+            anyhow::bail!("Not logged in");
+        };
+        if self.note_loader.is_some() || self.import_job.is_some() {
+            anyhow::bail!("Wait until the notes are loaded or imported, then check again");
+        }
+        if self.notes_load_failed {
+            anyhow::bail!("The notes could not be loaded; log in again before checking");
+        }
+        if self.reencrypt_job.is_some() {
+            anyhow::bail!("Wait until the vault is re-encrypted, then check again");
+        }
+        let locked = self
+            .storage_manager
+            .notes_locked_elsewhere(&user.id, &self.instance_id);
+        // Read anew, another instance may have registered accounts since
+        let accounts = self
+            .user_manager
+            .as_ref()
+            .filter(|user_manager| user_manager.integrity_warning().is_none())
+            .and_then(|user_manager| match user_manager.reload_user_ids() {
+                Ok(accounts) => Some(accounts),
+                Err(e) => {
+                    log::warn!(
+                        "Could not read the user database for the health check: {}",
+                        e
+                    );
+                    None
+                }
+            });
+        Ok(health_check::run(
+            &self.storage_manager,
+            &user.id,
+            crypto_manager,
+            &self.notes,
+            &self.unreadable_note_ids,
+            &locked,
+            accounts.as_ref(),
+        ))
+    }
+
+    /// Renders the health check window, if open.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The egui context for rendering
+    pub fn render_health_check(&mut self, ctx: &egui::Context) {
+        let Some(view) = &self.health_check else {
+            return;
+        };
+
+        let mut open = true;
+        let mut check_again = false;
+        let mut repair = None;
+        let mut confirm = view.confirm;
+        egui::Window::new("🩺 Vault Health Check")
+            .open(&mut open)
+            .default_width(460.0)
+            .show(ctx, |ui| {
+                if let Some(error) = &view.error {
+                    ui.colored_label(egui::Color32::from_rgb(255, 100, 100), error);
+                }
+
+                if let Some(report) = &view.report {
+                    if report.issues.is_empty() {
+                        ui.colored_label(
+                            egui::Color32::from_rgb(0, 200, 0),
+                            format!(
+                                "✔ No problems found in {} files (checked {})",
+                                report.files_checked,
+                                report.format_time()
+                            ),
+                        );
+                    } else {
+                        ui.label(format!(
+                            "{} problem(s) in {} files (checked {}):",
+                            report.issues.len(),
+                            report.files_checked,
+                            report.format_time()
+                        ));
+                    }
+
+                    egui::ScrollArea::vertical()
+                        .max_height(360.0)
+                        .show(ui, |ui| {
+                            for category in HealthCategory::ALL {
+                                let issues: Vec<(usize, _)> = report
+                                    .issues
+                                    .iter()
+                                    .enumerate()
+                                    .filter(|(_, issue)| issue.category == category)
+                                    .collect();
+                                if issues.is_empty() {
+                                    continue;
+                                }
+                                ui.add_space(5.0);
+                                ui.strong(category.label());
+                                for (index, issue) in issues {
+                                    ui.horizontal_wrapped(|ui| {
+                                        ui.colored_label(egui::Color32::YELLOW, "⚠");
+                                        ui.label(&issue.problem);
+                                        if let Some(fix) = &issue.repair {
+                                            if ui.button(fix.label()).clicked() {
+                                                if fix.needs_confirmation() {
+                                                    confirm = Some(index);
+                                                } else {
+                                                    repair = Some(index);
+                                                }
+                                            }
+                                        }
+                                    });
+                                    if confirm == Some(index) {
+                                        ui.horizontal_wrapped(|ui| {
+                                            ui.colored_label(
+                                                egui::Color32::from_rgb(255, 100, 100),
+                                                "This can't be undone.",
+                                            );
+                                            if ui.button("Delete").clicked() {
+                                                repair = Some(index);
+                                                confirm = None;
+                                            }
+                                            if ui.button("Cancel").clicked() {
+                                                confirm = None;
+                                            }
+                                        });
+                                    }
+                                    if !issue.details.is_empty() {
+                                        egui::CollapsingHeader::new(format!(
+                                            "{} item(s)",
+                                            issue.details.len()
+                                        ))
+                                        .id_salt(("health_issue", index))
+                                        .show(ui, |ui| {
+                                            for detail in &issue.details {
+                                                ui.small(detail);
+                                            }
+                                        });
+                                    }
+                                }
+                            }
+                        });
+                }
+
+                if !view.repaired.is_empty() {
+                    ui.separator();
+                    for fix in &view.repaired {
+                        ui.colored_label(egui::Color32::from_rgb(0, 200, 0), format!("✔ {}", fix));
+                    }
+                }
+
+                ui.separator();
+                if ui.button("Check Again").clicked() {
+                    check_again = true;
+                }
+            });
+
+        if let Some(view) = &mut self.health_check {
+            view.confirm = confirm;
+        }
+        if !open {
+            self.health_check = None;
+        } else if check_again {
+            self.run_health_check();
+        } else if let Some(index) = repair {
+            self.apply_health_repair(index);
+        }
+    }
+
+    /// Applies the repair of a problem in the report and moves the problem
+    /// to the list of fixes.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Position of the problem in the report
+    fn apply_health_repair(&mut self, index: usize) {
+        let Some(issue) = self
+            .health_check
+            .as_mut()
+            .and_then(|view| view.report.as_mut())
+            .filter(|report| index < report.issues.len())
+            .map(|report| report.issues.remove(index))
+        else {
+            return;
+        };
+        let Some(repair) = issue.repair.clone() else {
+            return;
+        };
+
+        let result = self.apply_repair(&repair);
+        let Some(view) = &mut self.health_check else {
+            return;
+        };
+        match result {
+            Ok(summary) => {
+                log::info!("Health check repair: {}", summary);
+                view.repaired.push(summary);
+                view.error = None;
+            }
+            Err(e) => {
+                log::error!("Health check repair failed: {}", e);
+                view.error = Some(format!("Repair failed: {}", e));
+                if let Some(report) = &mut view.report {
+                    report.issues.insert(index, issue);
+                }
+            }
+        }
+    }
+
+    /// Reads the registered accounts anew before deleting data of others.
+    ///
+    /// # Returns
+    ///
+    /// * `anyhow::Result<HashSet<String>>` - The user IDs, or error if the
+    ///   user database can't be read or trusted
+    fn registered_accounts(&self) -> anyhow::Result<HashSet<String>> {
+        let Some(user_manager) = &self.user_manager else {
+            anyhow::bail!("The user database is not available");
+        };
+        if user_manager.integrity_warning().is_some() {
+            anyhow::bail!("The user database failed its integrity check");
+        }
+        user_manager.reload_user_ids()
+    }
+
+    /// Applies a repair.
+    ///
+    /// # Returns
+    ///
+    /// * `anyhow::Result<String>` - What was done, or error
+    fn apply_repair(&mut self, repair: &Repair) -> anyhow::Result<String> {
+        match repair {
+            Repair::CreateDirectories(dirs) => {
+                for dir in dirs {
+                    fs::create_dir_all(dir)?;
+                }
+                Ok(format!("Created {} directories", dirs.len()))
+            }
+            Repair::RestrictPermissions(paths) => {
+                for path in paths {
+                    secure_fs::restrict_to_owner(path)?;
+                }
+                Ok(format!("Restricted {} file(s) to you", paths.len()))
+            }
+            Repair::SaveNotes => {
+                self.save_notes();
+                if self.has_unsaved_changes {
+                    anyhow::bail!("The notes could not be saved, see the log");
+                }
+                Ok("Saved all notes".to_string())
+            }
+            Repair::RemoveAttachmentEntries(entries) => {
+                for (note_id, attachment_id) in entries {
+                    if let Some(note) = self.notes.get_mut(note_id) {
+                        note.attachments
+                            .retain(|attachment| &attachment.id != attachment_id);
+                        note.update_modified_time();
+                        self.search_index.note_changed(note);
+                    }
+                }
+                self.save_notes();
+                Ok(format!(
+                    "Removed {} missing attachment(s) from their notes",
+                    entries.len()
+                ))
+            }
+            Repair::DeleteFiles(paths) => {
+                for path in paths {
+                    match secure_fs::secure_remove_file(path) {
+                        Ok(()) => {}
+                        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                        Err(e) => return Err(e.into()),
+                    }
+                }
+                Ok(format!("Deleted {} orphaned file(s)", paths.len()))
+            }
+            Repair::RebuildSearchIndex => {
+                let (Some(user), Some(crypto_manager)) = (&self.current_user, &self.crypto_manager)
+                else {
+                    anyhow::bail!("Not logged in");
+                };
+                self.search_index
+                    .rebuild(self.notes.values(), &user.id, crypto_manager);
+                Ok("Rebuilding the search index in the background".to_string())
+            }
+            Repair::DeleteAccountFolders(user_ids) => {
+                // The accounts may have been registered since the check ran
+                let accounts = self.registered_accounts()?;
+                let mut deleted = 0;
+                for user_id in user_ids {
+                    if accounts.contains(user_id)
+                        || health_check::account_folder_in_use(&self.storage_manager, user_id)
+                    {
+                        log::info!("Kept the folder of account {}, it is in use", user_id);
+                        continue;
+                    }
+                    self.storage_manager.delete_user_data(user_id)?;
+                    deleted += 1;
+                }
+                Ok(format!(
+                    "Deleted the folders of {} of {} deleted account(s)",
+                    deleted,
+                    user_ids.len()
+                ))
+            }
+            Repair::RemoveVaultMembers(user_ids) => {
+                let accounts = self.registered_accounts()?;
+                let mut removed = 0;
+                for user_id in user_ids.iter().filter(|id| !accounts.contains(*id)) {
+                    SharedVault::remove_member(&self.storage_manager, user_id)?;
+                    removed += 1;
+                }
+                Ok(format!(
+                    "Removed {} of {} deleted account(s) from the shared vault",
+                    removed,
+                    user_ids.len()
+                ))
+            }
+        }
+    }
+}
@@ -0,0 +1,212 @@
+//! # Highlight Module
+//!
+//! Syntax highlighting for the note editor, based on syntect through
+//! `egui_extras`. A code note (a note with a language) is highlighted as a
+//! whole; in other notes only fenced code blocks are highlighted, using the
+//! language named after the opening fence:
+//!
+//! ````text
+//! ```rust
+//! fn main() {}
+//! ```
+//! ````
+//!
+//! Web links outside code are underlined. Highlighted blocks are cached by
+//! egui, so the editor can lay out the note every frame.
+
+use crate::links;
+use eframe::egui;
+use egui::text::{LayoutJob, TextFormat};
+use egui_extras::syntax_highlighting::{self, CodeTheme};
+use std::ops::Range;
+
+/// Languages offered for code notes: syntax token and display name.
+pub const LANGUAGES: &[(&str, &str)] = &[
+    ("c", "C"),
+    ("cpp", "C++"),
+    ("cs", "C#"),
+    ("css", "CSS"),
+    ("go", "Go"),
+    ("html", "HTML"),
+    ("java", "Java"),
+    ("js", "JavaScript"),
+    ("json", "JSON"),
+    ("md", "Markdown"),
+    ("php", "PHP"),
+    ("py", "Python"),
+    ("rb", "Ruby"),
+    ("rs", "Rust"),
+    ("sh", "Shell"),
+    ("sql", "SQL"),
+    ("xml", "XML"),
+    ("yaml", "YAML"),
+];
+
+/// Fence language names that differ from both token and display name.
+const LANGUAGE_ALIASES: &[(&str, &str)] = &[
+    ("bash", "sh"),
+    ("shell", "sh"),
+    ("zsh", "sh"),
+    ("c++", "cpp"),
+    ("csharp", "cs"),
+    ("golang", "go"),
+    ("yml", "yaml"),
+];
+
+/// Marker opening and closing a fenced code block.
+pub const FENCE: &str = "```";
+
+/// Returns the display name of a language token.
+///
+/// # Arguments
+///
+/// * `token` - A token from [`LANGUAGES`]
+///
+/// # Returns
+///
+/// * `&str` - The display name, or the token itself if it is unknown
+pub fn language_label(token: &str) -> &str {
+    LANGUAGES
+        .iter()
+        .find(|(known, _)| *known == token)
+        .map_or(token, |(_, label)| label)
+}
+
+/// Checks whether a note needs the highlighting layouter.
+///
+/// # Arguments
+///
+/// * `text` - The note content
+/// * `language` - The language of a code note
+///
+/// # Returns
+///
+/// * `bool` - True for code notes and notes with fenced code blocks or links
+pub fn needs_highlighting(text: &str, language: Option<&str>) -> bool {
+    language.is_some() || text.contains(FENCE) || text.contains("://")
+}
+
+/// Lays out note text with syntax highlighting.
+///
+/// # Arguments
+///
+/// * `ui` - The editor UI, for the style and the highlighting cache
+/// * `text` - The note content
+/// * `language` - The language of a code note, None for fenced blocks only
+///
+/// # Returns
+///
+/// * `LayoutJob` - The text with highlighted code and underlined links,
+///   without wrapping set
+pub fn layout_job(ui: &egui::Ui, text: &str, language: Option<&str>) -> LayoutJob {
+    let theme = CodeTheme::from_style(ui.style());
+
+    if let Some(language) = language {
+        return syntax_highlighting::highlight(ui.ctx(), ui.style(), &theme, text, language);
+    }
+
+    let plain = TextFormat::simple(
+        egui::TextStyle::Body.resolve(ui.style()),
+        ui.visuals()
+            .override_text_color
+            .unwrap_or_else(|| ui.visuals().widgets.inactive.text_color()),
+    );
+    let link = TextFormat {
+        color: ui.visuals().hyperlink_color,
+        underline: egui::Stroke::new(1.0, ui.visuals().hyperlink_color),
+        ..plain.clone()
+    };
+
+    let mut job = LayoutJob::default();
+    let mut position = 0;
+    for (code, language) in fenced_blocks(text) {
+        append_text(&mut job, &text[position..code.start], &plain, &link);
+        let block = syntax_highlighting::highlight(
+            ui.ctx(),
+            ui.style(),
+            &theme,
+            &text[code.clone()],
+            fence_token(language),
+        );
+        append_job(&mut job, block);
+        position = code.end;
+    }
+    append_text(&mut job, &text[position..], &plain, &link);
+    job
+}
+
+/// Appends text outside code blocks, underlining the links in it.
+fn append_text(job: &mut LayoutJob, text: &str, plain: &TextFormat, link: &TextFormat) {
+    let mut position = 0;
+    for url in links::find_urls(text) {
+        job.append(&text[position..url.start], 0.0, plain.clone());
+        job.append(&text[url.clone()], 0.0, link.clone());
+        position = url.end;
+    }
+    job.append(&text[position..], 0.0, plain.clone());
+}
+
+/// Finds the fenced code blocks in a text.
+///
+/// An unclosed block extends to the end of the text, so code being typed
+/// is highlighted before the closing fence exists.
+///
+/// # Arguments
+///
+/// * `text` - The text to scan
+///
+/// # Returns
+///
+/// * `Vec<(Range<usize>, &str)>` - Byte range of each block's code (without
+///   the fence lines) and the language after its opening fence
+pub fn fenced_blocks(text: &str) -> Vec<(Range<usize>, &str)> {
+    let mut blocks = Vec::new();
+    let mut open: Option<(usize, &str)> = None;
+    let mut line_start = 0;
+
+    for line in text.split_inclusive('\n') {
+        let line_end = line_start + line.len();
+        if let Some(info) = line.trim_start().strip_prefix(FENCE) {
+            match open.take() {
+                Some((code_start, language)) => blocks.push((code_start..line_start, language)),
+                None => open = Some((line_end, info.trim())),
+            }
+        }
+        line_start = line_end;
+    }
+    if let Some((code_start, language)) = open {
+        blocks.push((code_start.min(text.len())..text.len(), language));
+    }
+
+    blocks
+}
+
+/// Maps the language after a fence to a syntax token.
+///
+/// Accepts tokens (`rs`), display names (`Rust`, any case) and common
+/// aliases (`bash`). Unknown names are passed on unchanged.
+pub fn fence_token(language: &str) -> &str {
+    let lowercase = language.to_lowercase();
+    LANGUAGES
+        .iter()
+        .find(|(token, label)| *token == lowercase || label.to_lowercase() == lowercase)
+        .map(|(token, _)| *token)
+        .or_else(|| {
+            LANGUAGE_ALIASES
+                .iter()
+                .find(|(alias, _)| *alias == lowercase)
+                .map(|(_, token)| *token)
+        })
+        .unwrap_or(language)
+}
+
+/// Appends the text and formatting of one layout job to another.
+fn append_job(job: &mut LayoutJob, other: LayoutJob) {
+    let offset = job.text.len();
+    job.text.push_str(&other.text);
+    job.sections
+        .extend(other.sections.into_iter().map(|mut section| {
+            section.byte_range = section.byte_range.start + offset..section.byte_range.end + offset;
+            section
+        }));
+}
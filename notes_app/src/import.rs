@@ -0,0 +1,337 @@
+//! # Import Module
+//!
+//! Helpers shared by every importer. Imported notes are hashed by content
+//! and compared against the notes already in the vault (and against each
+//! other), so importing the same export twice does not fill the vault with
+//! copies. Depending on the [`DuplicatePolicy`] duplicates are skipped or
+//! imported with a marked title, and importers report them in their summary.
+//!
+//! Importers hand their parsed notes to an [`ImportJob`], which encrypts and
+//! writes them on a background thread so large imports don't block the UI.
+//! Every note is written as soon as it is processed, so cancelling keeps
+//! the notes imported up to that point.
+
+use crate::crypto::CryptoManager;
+use crate::note::Note;
+use crate::storage::StorageManager;
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+
+/// Suffix appended to the title of flagged duplicates.
+const DUPLICATE_TITLE_SUFFIX: &str = " (duplicate)";
+
+/// What an importer does with notes identical to existing ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicatePolicy {
+    /// Don't import the duplicate
+    #[default]
+    Skip,
+    /// Import it with " (duplicate)" appended to the title
+    Flag,
+}
+
+impl DuplicatePolicy {
+    /// All policies in the order they are offered in the UI.
+    pub const ALL: [DuplicatePolicy; 2] = [DuplicatePolicy::Skip, DuplicatePolicy::Flag];
+
+    /// Returns the display name of the policy.
+    pub fn label(self) -> &'static str {
+        match self {
+            DuplicatePolicy::Skip => "Skip duplicates",
+            DuplicatePolicy::Flag => "Import and mark as duplicate",
+        }
+    }
+}
+
+/// An imported note whose content matched a note already present.
+#[derive(Debug, Clone)]
+pub struct DuplicateNote {
+    /// Title of the imported note
+    pub title: String,
+    /// Title of the note it duplicates
+    pub duplicate_of: String,
+}
+
+/// Detects imported notes whose content already exists.
+pub struct DuplicateDetector {
+    /// Content hashes mapped to the title of the first note with that content
+    known: HashMap<[u8; 32], String>,
+    /// Duplicates found so far
+    duplicates: Vec<DuplicateNote>,
+}
+
+impl DuplicateDetector {
+    /// Creates a detector that knows the content of the existing notes.
+    ///
+    /// # Arguments
+    ///
+    /// * `existing` - Notes already in the vault
+    ///
+    /// # Returns
+    ///
+    /// * `Self` - A new detector
+    pub fn new<'a>(existing: impl IntoIterator<Item = &'a Note>) -> Self {
+        let mut known = HashMap::new();
+        for note in existing {
+            if let Some(hash) = content_hash(&note.content) {
+                known.entry(hash).or_insert_with(|| note.title.clone());
+            }
+        }
+
+        Self {
+            known,
+            duplicates: Vec::new(),
+        }
+    }
+
+    /// Applies the policy to an imported note.
+    ///
+    /// Notes with new content are remembered, so later duplicates within
+    /// the same import are detected as well. Empty notes are never treated
+    /// as duplicates.
+    ///
+    /// # Arguments
+    ///
+    /// * `note` - The imported note
+    /// * `policy` - What to do if it is a duplicate
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Note>` - The note to import, or None if it was skipped
+    pub fn filter(&mut self, mut note: Note, policy: DuplicatePolicy) -> Option<Note> {
+        let Some(hash) = content_hash(&note.content) else {
+            return Some(note);
+        };
+
+        let Some(existing_title) = self.known.get(&hash) else {
+            self.known.insert(hash, note.title.clone());
+            return Some(note);
+        };
+
+        self.duplicates.push(DuplicateNote {
+            title: note.title.clone(),
+            duplicate_of: existing_title.clone(),
+        });
+
+        match policy {
+            DuplicatePolicy::Skip => None,
+            DuplicatePolicy::Flag => {
+                note.title.push_str(DUPLICATE_TITLE_SUFFIX);
+                Some(note)
+            }
+        }
+    }
+
+    /// Returns the duplicates found, for the import summary.
+    pub fn into_duplicates(self) -> Vec<DuplicateNote> {
+        self.duplicates
+    }
+}
+
+/// Progress reported by the import thread, one event per note.
+enum ImportEvent {
+    /// The note was written and can be added to the note list
+    Imported(Box<Note>),
+    /// The note was skipped because the local copy is newer
+    Skipped,
+    /// The note was skipped as a duplicate
+    Duplicate,
+    /// The note could not be written
+    Failed(String),
+    /// All notes were processed or the job was cancelled
+    Finished(Vec<DuplicateNote>),
+}
+
+/// An import running on a background thread.
+///
+/// The UI polls the job every frame with [`ImportJob::poll`] and shows the
+/// public counters as progress.
+pub struct ImportJob {
+    /// Events from the import thread
+    receiver: mpsc::Receiver<ImportEvent>,
+    /// Set to stop the import thread after the current note
+    cancel: Arc<AtomicBool>,
+    /// Number of notes to import
+    pub total: usize,
+    /// Number of notes processed so far
+    pub processed: usize,
+    /// Number of notes added or updated
+    pub imported: usize,
+    /// Number of notes skipped because the local copy is newer
+    pub skipped: usize,
+    /// Errors of notes that could not be imported
+    pub errors: Vec<String>,
+    /// Duplicates found, available once the job finished
+    pub duplicates: Vec<DuplicateNote>,
+    /// Whether the import thread is done
+    pub finished: bool,
+}
+
+impl ImportJob {
+    /// Starts importing notes on a background thread.
+    ///
+    /// Notes that exist already are only replaced if the imported version
+    /// is newer; new notes go through duplicate detection first.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - Owner of the vault to import into
+    /// * `crypto` - CryptoManager used to encrypt the imported notes
+    /// * `notes` - The notes to import
+    /// * `existing` - Notes already in the vault
+    /// * `policy` - What to do with duplicates
+    ///
+    /// # Returns
+    ///
+    /// * `Self` - The running job
+    pub fn spawn(
+        user_id: &str,
+        crypto: CryptoManager,
+        notes: Vec<Note>,
+        existing: &HashMap<String, Note>,
+        policy: DuplicatePolicy,
+    ) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let total = notes.len();
+
+        let user_id = user_id.to_string();
+        let modified: HashMap<String, DateTime<Utc>> = existing
+            .values()
+            .map(|note| (note.id.clone(), note.modified_at))
+            .collect();
+        let mut detector = DuplicateDetector::new(existing.values());
+        let cancelled = Arc::clone(&cancel);
+
+        thread::spawn(move || {
+            let storage_manager = StorageManager::new();
+            for note in notes {
+                if cancelled.load(Ordering::Relaxed) {
+                    break;
+                }
+
+                let note = match modified.get(&note.id) {
+                    Some(local) if *local >= note.modified_at => {
+                        let _ = sender.send(ImportEvent::Skipped);
+                        continue;
+                    }
+                    // Newer version of a note that exists here already
+                    Some(_) => note,
+                    None => match detector.filter(note, policy) {
+                        Some(note) => note,
+                        None => {
+                            let _ = sender.send(ImportEvent::Duplicate);
+                            continue;
+                        }
+                    },
+                };
+
+                let event = match storage_manager.save_user_note(&user_id, &note, &crypto) {
+                    Ok(()) => ImportEvent::Imported(Box::new(note)),
+                    Err(e) => {
+                        log::error!("Failed to import note {}: {}", note.id, e);
+                        ImportEvent::Failed(format!("{}: {}", note.title, e))
+                    }
+                };
+                if sender.send(event).is_err() {
+                    // The job was dropped, e.g. on logout
+                    return;
+                }
+            }
+            let _ = sender.send(ImportEvent::Finished(detector.into_duplicates()));
+        });
+
+        Self {
+            receiver,
+            cancel,
+            total,
+            processed: 0,
+            imported: 0,
+            skipped: 0,
+            errors: Vec::new(),
+            duplicates: Vec::new(),
+            finished: false,
+        }
+    }
+
+    /// Receives the progress made since the last poll.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<Note>` - Notes written since the last poll, to be added to
+    ///   the note list
+    pub fn poll(&mut self) -> Vec<Note> {
+        let mut notes = Vec::new();
+        loop {
+            match self.receiver.try_recv() {
+                Ok(ImportEvent::Imported(note)) => {
+                    notes.push(*note);
+                    self.imported += 1;
+                    self.processed += 1;
+                }
+                Ok(ImportEvent::Skipped) => {
+                    self.skipped += 1;
+                    self.processed += 1;
+                }
+                Ok(ImportEvent::Duplicate) => self.processed += 1,
+                Ok(ImportEvent::Failed(error)) => {
+                    self.errors.push(error);
+                    self.processed += 1;
+                }
+                Ok(ImportEvent::Finished(duplicates)) => {
+                    self.duplicates = duplicates;
+                    self.finished = true;
+                }
+                Err(mpsc::TryRecvError::Empty) => break,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    if !self.finished {
+                        self.errors.push("Import stopped unexpectedly".to_string());
+                        self.finished = true;
+                    }
+                    break;
+                }
+            }
+        }
+        notes
+    }
+
+    /// Asks the import thread to stop after the current note.
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns whether the job was cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+}
+
+/// Hashes note content for duplicate detection.
+///
+/// Line endings and trailing whitespace are normalized so the same text
+/// exported on Windows and Unix hashes identically.
+///
+/// # Arguments
+///
+/// * `content` - The note content
+///
+/// # Returns
+///
+/// * `Option<[u8; 32]>` - SHA-256 of the normalized content, None if empty
+fn content_hash(content: &str) -> Option<[u8; 32]> {
+    let content = content.trim_end();
+    if content.is_empty() {
+        return None;
+    }
+
+    let mut hasher = Sha256::new();
+    for line in content.lines() {
+        hasher.update(line.trim_end().as_bytes());
+        hasher.update(b"\n");
+    }
+    Some(hasher.finalize().into())
+}
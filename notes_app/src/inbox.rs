@@ -0,0 +1,65 @@
+//! # Inbox Module
+//!
+//! A place for quick thoughts that are sorted out later. Quick capture
+//! (the Quick Capture window and `--capture` on the command line) adds each
+//! text as a note to the [`INBOX_NOTEBOOK`] notebook, titled after its
+//! first line. Inbox items are ordinary notes and encrypted like any other;
+//! processing the inbox files them into another notebook, or none, which
+//! turns them into regular notes.
+
+use crate::note::Note;
+use crate::notebook::NOTEBOOK_SEPARATOR;
+use std::collections::HashMap;
+
+/// Notebook quick capture adds notes to.
+pub const INBOX_NOTEBOOK: &str = "Inbox";
+
+/// Creates an inbox item from captured text.
+///
+/// # Arguments
+///
+/// * `text` - The captured text; the first line becomes the title
+///
+/// # Returns
+///
+/// * `Option<Note>` - The new note, None if the text is blank
+pub fn capture(text: &str) -> Option<Note> {
+    let text = text.trim();
+    if text.is_empty() {
+        return None;
+    }
+    let mut note = Note::new(String::new());
+    note.content = text.to_string();
+    note.notebook = Some(INBOX_NOTEBOOK.to_string());
+    note.auto_title = true;
+    note.sync_auto_title();
+    Some(note)
+}
+
+/// Returns whether a notebook path is the inbox or nested in it.
+pub fn is_inbox_path(path: &str) -> bool {
+    path.split(NOTEBOOK_SEPARATOR).next().map(str::trim) == Some(INBOX_NOTEBOOK)
+}
+
+/// Returns whether a note is waiting in the inbox.
+///
+/// Notes in notebooks nested in the inbox count too; trashed notes don't.
+pub fn is_inbox_item(note: &Note) -> bool {
+    !note.is_trashed() && note.notebook.as_deref().is_some_and(is_inbox_path)
+}
+
+/// Lists the notes waiting in the inbox.
+///
+/// # Arguments
+///
+/// * `notes` - All notes of the user
+///
+/// # Returns
+///
+/// * `Vec<String>` - IDs of the inbox items, oldest first, so they are
+///   processed in the order they were captured
+pub fn items(notes: &HashMap<String, Note>) -> Vec<String> {
+    let mut items: Vec<&Note> = notes.values().filter(|note| is_inbox_item(note)).collect();
+    items.sort_by_key(|note| note.created_at);
+    items.into_iter().map(|note| note.id.clone()).collect()
+}
@@ -0,0 +1,394 @@
+//! # Inbox UI Module
+//!
+//! The Quick Capture window, which adds a text to the inbox without leaving
+//! the current note, the inbox badge in the sidebar and the Process Inbox
+//! dialog. The dialog goes through the inbox items oldest first and files
+//! each into a notebook under a proper title, opens it, trashes it or skips
+//! it for now.
+
+use crate::app::NotesApp;
+use crate::inbox;
+use crate::keymap::ShortcutAction;
+use crate::notebook;
+use eframe::egui;
+use std::collections::HashSet;
+
+/// State of the Process Inbox dialog.
+pub struct InboxDialog {
+    /// ID of the item being processed, None once the inbox is done
+    note_id: Option<String>,
+    /// Title to file the item under
+    title: String,
+    /// Notebook to file the item into, empty for none
+    notebook: String,
+    /// Items skipped while the dialog is open
+    skipped: HashSet<String>,
+    /// Number of items filed or trashed while the dialog is open
+    processed: usize,
+    /// Why the last attempt to file the item was refused
+    error: Option<String>,
+}
+
+/// What to do with the current inbox item.
+enum InboxAction {
+    File,
+    Open,
+    Trash,
+    Skip,
+}
+
+impl NotesApp {
+    /// Opens the Quick Capture window.
+    pub fn open_quick_capture(&mut self) {
+        if self.quick_capture.is_none() {
+            self.quick_capture = Some(String::new());
+        }
+    }
+
+    /// Adds a text to the inbox and saves.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The captured text
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - True if an item was added, false if the text was blank
+    pub fn capture_to_inbox(&mut self, text: &str) -> bool {
+        let Some(note) = inbox::capture(text) else {
+            return false;
+        };
+        log::info!("Captured note {} to the inbox", note.id);
+        self.search_index.note_changed(&note);
+        self.notes.insert(note.id.clone(), note);
+        self.save_notes();
+        true
+    }
+
+    /// Renders the Quick Capture window, if open.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The egui context for rendering
+    pub fn render_quick_capture(&mut self, ctx: &egui::Context) {
+        let Some(text) = &mut self.quick_capture else {
+            return;
+        };
+
+        let mut open = true;
+        let mut capture = false;
+        let mut cancel = false;
+        egui::Window::new("📥 Quick Capture")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .default_width(360.0)
+            .show(ctx, |ui| {
+                let response = ui.add(
+                    egui::TextEdit::multiline(text)
+                        .hint_text("A thought to sort out later…")
+                        .desired_rows(4)
+                        .desired_width(f32::INFINITY),
+                );
+                if ui.memory(|memory| memory.focused().is_none()) {
+                    response.request_focus();
+                }
+                if response.has_focus()
+                    && ui.input(|i| i.modifiers.command && i.key_pressed(egui::Key::Enter))
+                {
+                    capture = true;
+                }
+                ui.small("The first line becomes the title. Ctrl+Enter adds it.");
+
+                ui.add_space(5.0);
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(!text.trim().is_empty(), egui::Button::new("Add to Inbox"))
+                        .clicked()
+                    {
+                        capture = true;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        cancel = true;
+                    }
+                });
+            });
+
+        if !open || cancel || ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+            self.quick_capture = None;
+        } else if capture {
+            let text = self.quick_capture.take().unwrap_or_default();
+            if self.capture_to_inbox(&text) {
+                self.show_status("Added to the inbox".to_string());
+            }
+        }
+    }
+
+    /// Renders the inbox badge of the sidebar, which opens the Process
+    /// Inbox dialog.
+    ///
+    /// # Arguments
+    ///
+    /// * `ui` - The sidebar's button row
+    pub fn render_inbox_badge(&mut self, ui: &mut egui::Ui) {
+        let count = self
+            .notes
+            .values()
+            .filter(|note| inbox::is_inbox_item(note))
+            .count();
+        let hover = format!(
+            "{} item(s) in the inbox; click to process them. Quick capture: {}",
+            count,
+            self.settings.keymap.shortcut(ShortcutAction::QuickCapture)
+        );
+        let button = if count > 0 {
+            egui::Button::new(egui::RichText::new(format!("📥 {}", count)).strong())
+        } else {
+            egui::Button::new("📥")
+        };
+        let response = ui.add(button).on_hover_text(hover);
+        if response.clicked() {
+            if count > 0 {
+                self.open_inbox_dialog();
+            } else {
+                self.open_quick_capture();
+            }
+        }
+    }
+
+    /// Opens the Process Inbox dialog at the oldest item.
+    pub fn open_inbox_dialog(&mut self) {
+        self.inbox_dialog = Some(InboxDialog {
+            note_id: None,
+            title: String::new(),
+            notebook: String::new(),
+            skipped: HashSet::new(),
+            processed: 0,
+            error: None,
+        });
+    }
+
+    /// Moves the Process Inbox dialog to the oldest item not skipped, if the
+    /// current one is no longer in the inbox.
+    fn advance_inbox_dialog(&mut self) {
+        let Some(dialog) = &mut self.inbox_dialog else {
+            return;
+        };
+        let items = inbox::items(&self.notes);
+        if dialog
+            .note_id
+            .as_ref()
+            .is_some_and(|note_id| items.contains(note_id))
+        {
+            return;
+        }
+        dialog.note_id = items
+            .into_iter()
+            .find(|note_id| !dialog.skipped.contains(note_id));
+        dialog.title = dialog
+            .note_id
+            .as_ref()
+            .and_then(|note_id| self.notes.get(note_id))
+            .map(|note| note.title.clone())
+            .unwrap_or_default();
+        dialog.notebook.clear();
+        dialog.error = None;
+    }
+
+    /// Renders the Process Inbox dialog, if open.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The egui context for rendering
+    pub fn render_inbox_dialog(&mut self, ctx: &egui::Context) {
+        self.advance_inbox_dialog();
+        let Some(dialog) = &mut self.inbox_dialog else {
+            return;
+        };
+        let remaining = inbox::items(&self.notes).len();
+        let notebook_paths: Vec<String> = notebook::all_paths(self.notes.values())
+            .into_iter()
+            .filter(|path| !inbox::is_inbox_path(path))
+            .collect();
+        let note = dialog
+            .note_id
+            .as_ref()
+            .and_then(|note_id| self.notes.get(note_id));
+
+        let mut open = true;
+        let mut action = None;
+        egui::Window::new("📥 Process Inbox")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .default_width(400.0)
+            .show(ctx, |ui| {
+                let Some(note) = note else {
+                    if dialog.skipped.is_empty() {
+                        ui.label("The inbox is empty.");
+                    } else {
+                        ui.label(format!(
+                            "{} skipped item(s) are left in the inbox.",
+                            remaining
+                        ));
+                    }
+                    if dialog.processed > 0 {
+                        ui.label(format!("Processed {} item(s).", dialog.processed));
+                    }
+                    return;
+                };
+
+                ui.label(format!(
+                    "{} item(s) left, captured {}",
+                    remaining,
+                    note.format_created_time()
+                ));
+                ui.add_space(5.0);
+                egui::ScrollArea::vertical()
+                    .max_height(160.0)
+                    .show(ui, |ui| {
+                        ui.add(egui::Label::new(&note.content).wrap());
+                    });
+                ui.separator();
+
+                egui::Grid::new("inbox_item_grid")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label("Title:");
+                        ui.add(egui::TextEdit::singleline(&mut dialog.title).desired_width(260.0));
+                        ui.end_row();
+
+                        ui.label("Notebook:");
+                        ui.horizontal(|ui| {
+                            ui.add(
+                                egui::TextEdit::singleline(&mut dialog.notebook)
+                                    .hint_text("None")
+                                    .desired_width(230.0),
+                            );
+                            ui.menu_button("⏷", |ui| {
+                                if ui.button("None").clicked() {
+                                    dialog.notebook.clear();
+                                    ui.close_menu();
+                                }
+                                for path in &notebook_paths {
+                                    if ui.button(path).clicked() {
+                                        dialog.notebook = path.clone();
+                                        ui.close_menu();
+                                    }
+                                }
+                            });
+                        });
+                        ui.end_row();
+                    });
+
+                if let Some(error) = &dialog.error {
+                    ui.colored_label(egui::Color32::RED, error.as_str());
+                }
+
+                ui.add_space(5.0);
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(
+                            !dialog.title.trim().is_empty(),
+                            egui::Button::new("File as Note"),
+                        )
+                        .on_hover_text("Give the note this title and move it out of the inbox")
+                        .clicked()
+                    {
+                        action = Some(InboxAction::File);
+                    }
+                    if ui.button("Open").clicked() {
+                        action = Some(InboxAction::Open);
+                    }
+                    if ui.button("🗑 Trash").clicked() {
+                        action = Some(InboxAction::Trash);
+                    }
+                    if ui.button("Skip").clicked() {
+                        action = Some(InboxAction::Skip);
+                    }
+                });
+            });
+
+        if !open {
+            self.inbox_dialog = None;
+            return;
+        }
+        let Some(action) = action else {
+            return;
+        };
+        let Some(dialog) = &mut self.inbox_dialog else {
+            return;
+        };
+        let Some(note_id) = dialog.note_id.clone() else {
+            return;
+        };
+
+        match action {
+            InboxAction::File => {
+                if let Err(error) = self.file_inbox_item(&note_id) {
+                    if let Some(dialog) = &mut self.inbox_dialog {
+                        dialog.error = Some(error);
+                    }
+                }
+            }
+            InboxAction::Open => {
+                self.selected_note_id = Some(note_id);
+                self.inbox_dialog = None;
+            }
+            InboxAction::Trash => {
+                dialog.processed += 1;
+                self.delete_note(&note_id);
+            }
+            InboxAction::Skip => {
+                dialog.skipped.insert(note_id);
+                dialog.note_id = None;
+            }
+        }
+    }
+
+    /// Files the current inbox item under the dialog's title and notebook.
+    ///
+    /// # Arguments
+    ///
+    /// * `note_id` - ID of the item
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - Ok if the item left the inbox, or why it
+    ///   was refused
+    fn file_inbox_item(&mut self, note_id: &str) -> Result<(), String> {
+        let Some(dialog) = &self.inbox_dialog else {
+            return Ok(());
+        };
+        let title = dialog.title.trim().to_string();
+        let notebook = notebook::normalize_path(&dialog.notebook);
+        let current = self
+            .notes
+            .get(note_id)
+            .map(|note| note.title.clone())
+            .unwrap_or_default();
+
+        if title.is_empty() {
+            return Err("The title can't be empty".to_string());
+        }
+        if notebook.as_deref().is_some_and(inbox::is_inbox_path) {
+            return Err("Choose a notebook outside the inbox".to_string());
+        }
+        if !title.eq_ignore_ascii_case(current.trim()) && self.note_title_exists(&title) {
+            return Err(format!("Another note is already called \"{}\"", title));
+        }
+
+        if title != current {
+            self.rename_note(note_id, &title);
+        }
+        self.move_to_notebook(note_id, notebook.clone());
+        if let Some(dialog) = &mut self.inbox_dialog {
+            dialog.processed += 1;
+        }
+        self.show_status(match notebook {
+            Some(path) => format!("Filed \"{}\" in {}", title, path),
+            None => format!("Filed \"{}\"", title),
+        });
+        Ok(())
+    }
+}
@@ -0,0 +1,379 @@
+//! # Integrity Scan Module
+//!
+//! Periodically decrypts every stored file of the vault on a background
+//! thread, so damaged files are reported in the security panel early
+//! instead of when the note is opened. Every file is encrypted with an
+//! authenticated cipher bound to its owner and ID, so a file that decrypts
+//! is intact; the notes themselves act as the manifest of the attachments,
+//! and each attachment a note lists must have its file.
+//!
+//! The scan checks:
+//! - Every note file decrypts and holds a valid note
+//! - Every attachment file decrypts
+//! - Every attachment listed by a note has its file
+//! - The settings file decrypts and holds valid settings
+//!
+//! The app keeps saving while the scan runs. A file that fails is checked
+//! once more after a short pause, in case it was read while being written.
+
+use crate::app::NotesApp;
+use crate::crypto::CryptoManager;
+use crate::note::Note;
+use crate::note_history::NoteHistory;
+use crate::storage::{EncryptedFile, StorageManager, SHARED_VAULT_OWNER};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use chrono_tz::Europe::Zurich;
+use rayon::prelude::*;
+use std::collections::HashSet;
+use std::fs;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Time between two scans.
+pub const INTEGRITY_SCAN_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// Time from loading the notes to the first scan of a session.
+pub const FIRST_SCAN_DELAY: Duration = Duration::from_secs(2 * 60);
+
+/// Pause before a failed file is checked again.
+const RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// A damaged or missing file found by the scan.
+#[derive(Debug, Clone)]
+pub struct IntegrityIssue {
+    /// "Private" or "Shared", the vault the file belongs to
+    pub vault: &'static str,
+    /// The file, relative to the vault directory
+    pub file: String,
+    /// What is wrong with it
+    pub problem: String,
+}
+
+/// Result of a finished scan.
+#[derive(Debug, Clone)]
+pub struct IntegrityReport {
+    /// UTC timestamp when the scan finished
+    pub finished_at: DateTime<Utc>,
+    /// Number of files decrypted
+    pub files_checked: usize,
+    /// Problems found, empty if everything is intact
+    pub issues: Vec<IntegrityIssue>,
+    /// IDs of the notes whose files decrypted, taken by the app to record
+    /// when each note was last verified
+    pub intact_note_ids: Vec<String>,
+}
+
+impl IntegrityReport {
+    /// Formats the time of the scan for display in Swiss timezone.
+    ///
+    /// # Returns
+    ///
+    /// * `String` - Time as "DD.MM.YYYY HH:MM"
+    pub fn format_time(&self) -> String {
+        self.finished_at
+            .with_timezone(&Zurich)
+            .format("%d.%m.%Y %H:%M")
+            .to_string()
+    }
+}
+
+/// A scan running on a background thread.
+pub struct IntegrityScan {
+    /// Receives the report once the scan is done
+    receiver: mpsc::Receiver<IntegrityReport>,
+    /// Set to stop the scan, e.g. on logout
+    cancel: Arc<AtomicBool>,
+}
+
+impl IntegrityScan {
+    /// Starts scanning the vaults of a user on a background thread.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - Owner of the private vault
+    /// * `crypto` - The user's crypto manager
+    /// * `shared_crypto` - Crypto manager of the shared vault, if unlocked
+    ///
+    /// # Returns
+    ///
+    /// * `Self` - The running scan, see `poll`
+    pub fn spawn(
+        user_id: &str,
+        crypto: CryptoManager,
+        shared_crypto: Option<CryptoManager>,
+    ) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let cancelled = Arc::clone(&cancel);
+        let user_id = user_id.to_string();
+
+        thread::spawn(move || {
+            let storage = StorageManager::new();
+            let mut issues = Vec::new();
+            let mut intact_note_ids = Vec::new();
+
+            let mut files_checked = scan_vault(
+                &storage,
+                &user_id,
+                &crypto,
+                &cancelled,
+                &mut issues,
+                &mut intact_note_ids,
+            );
+            files_checked += 1;
+            if let Err(e) = check_with_retry(|| storage.load_user_settings(&user_id, &crypto)) {
+                issues.push(IntegrityIssue {
+                    vault: "Private",
+                    file: "settings.enc".to_string(),
+                    problem: e.to_string(),
+                });
+            }
+            if let Some(shared_crypto) = &shared_crypto {
+                files_checked += scan_vault(
+                    &storage,
+                    SHARED_VAULT_OWNER,
+                    shared_crypto,
+                    &cancelled,
+                    &mut issues,
+                    &mut intact_note_ids,
+                );
+            }
+            if cancelled.load(Ordering::Relaxed) {
+                return;
+            }
+
+            if issues.is_empty() {
+                log::info!("Integrity scan checked {} files, all intact", files_checked);
+            } else {
+                log::error!(
+                    "Integrity scan checked {} files, {} problem(s) found",
+                    files_checked,
+                    issues.len()
+                );
+            }
+            let _ = sender.send(IntegrityReport {
+                finished_at: Utc::now(),
+                files_checked,
+                issues,
+                intact_note_ids,
+            });
+        });
+
+        Self { receiver, cancel }
+    }
+
+    /// Checks whether the scan is done.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<IntegrityReport>` - The report once the scan finished,
+    ///   None while it is running
+    pub fn poll(&self) -> Option<IntegrityReport> {
+        match self.receiver.try_recv() {
+            Ok(report) => Some(report),
+            Err(mpsc::TryRecvError::Empty) => None,
+            Err(mpsc::TryRecvError::Disconnected) => Some(IntegrityReport {
+                finished_at: Utc::now(),
+                files_checked: 0,
+                issues: vec![IntegrityIssue {
+                    vault: "Private",
+                    file: String::new(),
+                    problem: "The scan stopped unexpectedly".to_string(),
+                }],
+                intact_note_ids: Vec::new(),
+            }),
+        }
+    }
+
+    /// Stops the scan; files being checked are finished first.
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+impl NotesApp {
+    /// Starts an integrity scan of the current user's vaults.
+    ///
+    /// Does nothing while a scan is running or before login.
+    pub fn start_integrity_scan(&mut self) {
+        if self.integrity_scan.is_some() {
+            return;
+        }
+        let (Some(crypto_manager), Some(user)) = (&self.crypto_manager, &self.current_user) else {
+            return;
+        };
+
+        log::info!("Starting integrity scan");
+        self.integrity_scan = Some(IntegrityScan::spawn(
+            &user.id,
+            crypto_manager.clone(),
+            self.shared_vault
+                .as_ref()
+                .map(|vault| vault.crypto().clone()),
+        ));
+    }
+
+    /// Collects the report of a finished scan and starts the next one when
+    /// it is due.
+    ///
+    /// The first scan of a session starts `FIRST_SCAN_DELAY` after the notes
+    /// finished loading, then every `INTEGRITY_SCAN_INTERVAL`.
+    pub fn scan_integrity_if_due(&mut self) {
+        if let Some(mut report) = self.integrity_scan.as_ref().and_then(|scan| scan.poll()) {
+            self.integrity_scan = None;
+            for note_id in std::mem::take(&mut report.intact_note_ids) {
+                self.last_decrypted.insert(note_id, report.finished_at);
+            }
+            self.next_integrity_scan = Some(Instant::now() + INTEGRITY_SCAN_INTERVAL);
+            if !report.issues.is_empty() {
+                self.show_status(format!(
+                    "Integrity scan found {} damaged or missing file(s), see Security Information",
+                    report.issues.len()
+                ));
+            }
+            self.integrity_report = Some(report);
+        }
+
+        if self.note_loader.is_none()
+            && self
+                .next_integrity_scan
+                .is_some_and(|next| Instant::now() >= next)
+        {
+            self.next_integrity_scan = None;
+            self.start_integrity_scan();
+        }
+    }
+}
+
+/// Content of a file that passed the check.
+enum CheckedFile {
+    /// A note and the IDs of its attachments
+    Note(Box<Note>),
+    /// An attachment
+    Attachment,
+    /// The earlier versions of a note
+    History,
+    /// The file was deleted since the scan listed it, or the scan was
+    /// cancelled before checking it
+    Skipped,
+}
+
+/// Checks the note, history and attachment files of a vault.
+///
+/// # Arguments
+///
+/// * `storage` - The storage manager
+/// * `owner_id` - User ID, or `SHARED_VAULT_OWNER` for the shared vault
+/// * `crypto` - Crypto manager of the vault
+/// * `cancelled` - Set when the scan should stop
+/// * `issues` - Problems found are appended here
+/// * `intact_note_ids` - IDs of the notes that decrypted are appended here
+///
+/// # Returns
+///
+/// * `usize` - Number of files checked
+fn scan_vault(
+    storage: &StorageManager,
+    owner_id: &str,
+    crypto: &CryptoManager,
+    cancelled: &AtomicBool,
+    issues: &mut Vec<IntegrityIssue>,
+    intact_note_ids: &mut Vec<String>,
+) -> usize {
+    let vault = if owner_id == SHARED_VAULT_OWNER {
+        "Shared"
+    } else {
+        "Private"
+    };
+    let files = match storage.list_encrypted_files(owner_id) {
+        Ok(files) => files,
+        Err(e) => {
+            issues.push(IntegrityIssue {
+                vault,
+                file: String::new(),
+                problem: format!("Could not list the files: {}", e),
+            });
+            return 0;
+        }
+    };
+
+    let results: Vec<(&EncryptedFile, Result<CheckedFile>)> = files
+        .par_iter()
+        .map(|file| {
+            if cancelled.load(Ordering::Relaxed) {
+                return (file, Ok(CheckedFile::Skipped));
+            }
+            (
+                file,
+                check_with_retry(|| check_file(file, owner_id, crypto)),
+            )
+        })
+        .collect();
+
+    let present: HashSet<(&str, &str)> = files
+        .iter()
+        .filter_map(|file| Some((file.note_id.as_str(), file.attachment_id.as_deref()?)))
+        .collect();
+
+    for (file, result) in &results {
+        match result {
+            Ok(CheckedFile::Note(note)) => {
+                intact_note_ids.push(note.id.clone());
+                for attachment in &note.attachments {
+                    let listed = present.contains(&(note.id.as_str(), attachment.id.as_str()));
+                    if !listed && !storage.attachment_exists(owner_id, &note.id, &attachment.id) {
+                        issues.push(IntegrityIssue {
+                            vault,
+                            file: file.relative_path(),
+                            problem: format!(
+                                "The attachment \"{}\" of \"{}\" is missing",
+                                attachment.file_name, note.title
+                            ),
+                        });
+                    }
+                }
+            }
+            Ok(CheckedFile::Attachment | CheckedFile::History | CheckedFile::Skipped) => {}
+            Err(e) => issues.push(IntegrityIssue {
+                vault,
+                file: file.relative_path(),
+                problem: e.to_string(),
+            }),
+        }
+    }
+
+    files.len()
+}
+
+/// Reads and decrypts one file.
+fn check_file(file: &EncryptedFile, owner_id: &str, crypto: &CryptoManager) -> Result<CheckedFile> {
+    let data = match fs::read(&file.path) {
+        Ok(data) => data,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(CheckedFile::Skipped),
+        Err(e) => return Err(anyhow!("Could not read the file: {}", e)),
+    };
+    let plaintext = crypto
+        .decrypt(&data, file.context(owner_id))
+        .map_err(|_| anyhow!("Decryption failed, the file is damaged or was modified"))?;
+
+    match file.attachment_id {
+        Some(_) => Ok(CheckedFile::Attachment),
+        None if file.history => serde_json::from_slice::<NoteHistory>(&plaintext)
+            .map(|_| CheckedFile::History)
+            .map_err(|e| anyhow!("Decrypted, but not a valid note history: {}", e)),
+        None => serde_json::from_slice(&plaintext)
+            .map(|note| CheckedFile::Note(Box::new(note)))
+            .map_err(|e| anyhow!("Decrypted, but not a valid note: {}", e)),
+    }
+}
+
+/// Runs a check, and once more after `RETRY_DELAY` if it fails.
+fn check_with_retry<T>(check: impl Fn() -> Result<T>) -> Result<T> {
+    check().or_else(|_| {
+        thread::sleep(RETRY_DELAY);
+        check()
+    })
+}
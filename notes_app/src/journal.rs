@@ -0,0 +1,331 @@
+//! # Change Journal Module
+//!
+//! Records which notes were created, updated and deleted, so a sync
+//! backend can send only the changes since the last transfer instead of
+//! comparing every note file. The journal is stored encrypted as
+//! `journal.enc` next to the user's notes.
+//!
+//! Every installation is a replica with its own random ID, kept in the
+//! local data directory (see [`local_replica_id`]) rather than in the
+//! journal, so copies of the vault synced to other computers don't share
+//! it. Each recorded
+//! operation advances the replica's counter in the journal's vector clock
+//! and stamps the note with the clock at that point, so a replica that
+//! has seen counter N only needs the operations after it, and two copies
+//! of a note can be told apart as newer, older or changed independently.
+//!
+//! Operations are derived from saves: after the notes are written, the
+//! saved notes are compared with the versions the journal knows. Only the
+//! private vault is journaled; the shared vault is written by several
+//! users and has no single owner to keep a journal.
+
+use crate::note::Note;
+use crate::secure_fs;
+use crate::trusted_time;
+use chrono::{DateTime, Utc};
+use chrono_tz::Europe::Zurich;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use uuid::Uuid;
+
+/// Number of operations kept; older ones are dropped, and a backend that
+/// has not seen them has to fall back to comparing whole files.
+const MAX_JOURNAL_ENTRIES: usize = 5000;
+
+/// Replica ID of this installation, read once per process.
+static REPLICA_ID: OnceLock<String> = OnceLock::new();
+
+/// Returns the file holding the replica ID of this installation.
+///
+/// It lives in the local data directory, which is neither the synced
+/// vault nor, on Windows, part of the roaming profile.
+fn replica_id_path() -> PathBuf {
+    let mut path = dirs::data_local_dir().unwrap_or_else(|| PathBuf::from("."));
+    path.push("secure_notes");
+    path.push("replica_id");
+    path
+}
+
+/// Returns the replica ID of this installation, creating it on first use.
+///
+/// If the ID can't be stored, a new one is used for this run only, so
+/// the installation still never records under another replica's ID.
+///
+/// # Returns
+///
+/// * `String` - The replica ID
+pub fn local_replica_id() -> String {
+    REPLICA_ID
+        .get_or_init(|| {
+            let path = replica_id_path();
+            if let Some(id) = fs::read_to_string(&path)
+                .ok()
+                .and_then(|id| Uuid::parse_str(id.trim()).ok())
+            {
+                return id.to_string();
+            }
+
+            let id = Uuid::new_v4().to_string();
+            let stored = path
+                .parent()
+                .map_or(Ok(()), fs::create_dir_all)
+                .and_then(|_| secure_fs::write_private_file(&path, id.as_bytes()));
+            if let Err(e) = stored {
+                log::warn!(
+                    "Could not store the replica ID, using one for this run: {}",
+                    e
+                );
+            }
+            id
+        })
+        .clone()
+}
+
+/// A version vector: per replica ID, the number of operations of that
+/// replica that happened before.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct VectorClock(BTreeMap<String, u64>);
+
+impl VectorClock {
+    /// Returns the counter of a replica.
+    ///
+    /// # Arguments
+    ///
+    /// * `replica_id` - ID of the replica
+    ///
+    /// # Returns
+    ///
+    /// * `u64` - The counter, 0 if the replica is unknown
+    pub fn get(&self, replica_id: &str) -> u64 {
+        self.0.get(replica_id).copied().unwrap_or(0)
+    }
+
+    /// Advances the counter of a replica by one.
+    ///
+    /// # Arguments
+    ///
+    /// * `replica_id` - ID of the replica
+    ///
+    /// # Returns
+    ///
+    /// * `u64` - The new counter
+    pub fn increment(&mut self, replica_id: &str) -> u64 {
+        let counter = self.0.entry(replica_id.to_string()).or_insert(0);
+        *counter += 1;
+        *counter
+    }
+
+    /// Takes the larger counter of each replica from another clock, e.g.
+    /// after applying a change received from another replica.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The other clock
+    pub fn merge(&mut self, other: &VectorClock) {
+        for (replica_id, &counter) in &other.0 {
+            let own = self.0.entry(replica_id.clone()).or_insert(0);
+            *own = (*own).max(counter);
+        }
+    }
+}
+
+/// What happened to a note.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JournalOp {
+    /// The note was saved for the first time
+    Create,
+    /// The note was saved with a newer modification time
+    Update,
+    /// The note was deleted permanently
+    Delete,
+}
+
+impl JournalOp {
+    /// Returns the display name of the operation.
+    ///
+    /// # Returns
+    ///
+    /// * `&'static str` - E.g. "Created"
+    pub fn label(&self) -> &'static str {
+        match self {
+            JournalOp::Create => "Created",
+            JournalOp::Update => "Updated",
+            JournalOp::Delete => "Deleted",
+        }
+    }
+}
+
+/// One recorded operation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    /// What happened
+    pub op: JournalOp,
+    /// ID of the note
+    pub note_id: String,
+    /// Version of the note after the operation
+    pub clock: VectorClock,
+    /// Modification time of the note, None for deletions
+    pub modified_at: Option<DateTime<Utc>>,
+    /// UTC timestamp when the operation was recorded
+    pub recorded_at: DateTime<Utc>,
+}
+
+impl JournalEntry {
+    /// Formats the time the operation was recorded for display in Swiss
+    /// timezone.
+    ///
+    /// # Returns
+    ///
+    /// * `String` - Time as "DD.MM.YYYY HH:MM"
+    pub fn format_time(&self) -> String {
+        self.recorded_at
+            .with_timezone(&Zurich)
+            .format("%d.%m.%Y %H:%M")
+            .to_string()
+    }
+}
+
+/// Version of a note the journal knows.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct NoteVersion {
+    /// Clock of the last operation on the note
+    clock: VectorClock,
+    /// Modification time the note was last saved with
+    modified_at: DateTime<Utc>,
+}
+
+/// The change journal of a user's private vault.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Journal {
+    /// ID of this installation in the vector clocks, not stored in the
+    /// journal, see `local_replica_id`
+    #[serde(skip, default = "local_replica_id")]
+    pub replica_id: String,
+    /// Operations seen by this replica, from every replica
+    pub clock: VectorClock,
+    /// Latest version of every existing note
+    notes: BTreeMap<String, NoteVersion>,
+    /// Recorded operations, oldest first
+    entries: Vec<JournalEntry>,
+}
+
+impl Default for Journal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Journal {
+    /// Creates an empty journal.
+    ///
+    /// # Returns
+    ///
+    /// * `Self` - A journal recording as this installation's replica
+    pub fn new() -> Self {
+        Self {
+            replica_id: local_replica_id(),
+            clock: VectorClock::default(),
+            notes: BTreeMap::new(),
+            entries: Vec::new(),
+        }
+    }
+
+    /// Records the operations that led from the known versions to the
+    /// saved notes.
+    ///
+    /// # Arguments
+    ///
+    /// * `notes` - The private notes that were saved
+    /// * `deleted` - IDs of the notes whose files this save removed; other
+    ///   notes missing from `notes` are not deleted, e.g. ones created by
+    ///   another instance
+    ///
+    /// # Returns
+    ///
+    /// * `usize` - Number of operations recorded
+    pub fn record_saved(
+        &mut self,
+        notes: &HashMap<String, Note>,
+        deleted: &HashSet<String>,
+    ) -> usize {
+        let mut changes: Vec<(JournalOp, String, Option<DateTime<Utc>>)> = notes
+            .values()
+            .filter_map(|note| match self.notes.get(&note.id) {
+                None => Some((JournalOp::Create, note.id.clone(), Some(note.modified_at))),
+                Some(version) if version.modified_at != note.modified_at => {
+                    Some((JournalOp::Update, note.id.clone(), Some(note.modified_at)))
+                }
+                Some(_) => None,
+            })
+            .collect();
+        changes.extend(
+            self.notes
+                .keys()
+                .filter(|id| deleted.contains(*id) && !notes.contains_key(*id))
+                .map(|id| (JournalOp::Delete, id.clone(), None)),
+        );
+        // Oldest change first, so the sequence follows the edits
+        changes.sort_by_key(|(_, _, modified_at)| *modified_at);
+
+        let recorded = changes.len();
+        for (op, note_id, modified_at) in changes {
+            self.clock.increment(&self.replica_id);
+            let mut clock = self
+                .notes
+                .remove(&note_id)
+                .map(|version| version.clock)
+                .unwrap_or_default();
+            clock.merge(&self.clock);
+            if let Some(modified_at) = modified_at {
+                self.notes.insert(
+                    note_id.clone(),
+                    NoteVersion {
+                        clock: clock.clone(),
+                        modified_at,
+                    },
+                );
+            }
+            self.entries.push(JournalEntry {
+                op,
+                note_id,
+                clock,
+                modified_at,
+                recorded_at: trusted_time::now(),
+            });
+        }
+
+        let excess = self.entries.len().saturating_sub(MAX_JOURNAL_ENTRIES);
+        self.entries.drain(..excess);
+        recorded
+    }
+
+    /// Returns the recorded operations, for a sync backend to send the
+    /// ones another replica hasn't seen.
+    ///
+    /// # Returns
+    ///
+    /// * `&[JournalEntry]` - The operations, oldest first; each entry's
+    ///   clock tells whether a replica has seen it
+    pub fn entries(&self) -> &[JournalEntry] {
+        &self.entries
+    }
+
+    /// Returns the version of a note.
+    ///
+    /// # Arguments
+    ///
+    /// * `note_id` - ID of the note
+    ///
+    /// # Returns
+    ///
+    /// * `Option<&VectorClock>` - Clock of the last operation on the note,
+    ///   None if the journal doesn't know it
+    pub fn note_clock(&self, note_id: &str) -> Option<&VectorClock> {
+        self.notes.get(note_id).map(|version| &version.clock)
+    }
+}
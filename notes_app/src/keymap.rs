@@ -0,0 +1,434 @@
+//! # Keymap Module
+//!
+//! The keyboard shortcuts of the app and the user's bindings for them.
+//! Bindings are stored in the user settings as readable text such as
+//! `Ctrl + Shift + P` and can be exported to a JSON file and imported on
+//! another computer or account:
+//!
+//! ```json
+//! {
+//!   "format_version": 1,
+//!   "bindings": {
+//!     "new_note": "Ctrl + N",
+//!     "save": "Ctrl + S"
+//!   }
+//! }
+//! ```
+//!
+//! Every shortcut needs Ctrl or Alt, so bindings never fire while typing.
+//! Actions missing from a keymap keep their default binding, and unknown
+//! actions (e.g. from a newer version) are ignored.
+
+use crate::app::NotesApp;
+use anyhow::{anyhow, bail, Result};
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Version of the exported keymap file.
+const KEYMAP_FILE_VERSION: u32 = 1;
+
+/// An action that can be bound to a keyboard shortcut.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ShortcutAction {
+    /// Open the new note dialog
+    NewNote,
+    /// Save all notes
+    Save,
+    /// Export the selected note to a file
+    ExportNote,
+    /// Switch between relative and absolute times
+    ToggleTimeFormat,
+    /// Show relative times
+    RelativeTime,
+    /// Show absolute times
+    AbsoluteTime,
+    /// Keep the window above other windows
+    AlwaysOnTop,
+    /// Open the Quick Capture window
+    QuickCapture,
+}
+
+impl ShortcutAction {
+    /// Every action, in the order shown in the settings window.
+    pub const ALL: [ShortcutAction; 8] = [
+        ShortcutAction::NewNote,
+        ShortcutAction::Save,
+        ShortcutAction::ExportNote,
+        ShortcutAction::ToggleTimeFormat,
+        ShortcutAction::RelativeTime,
+        ShortcutAction::AbsoluteTime,
+        ShortcutAction::AlwaysOnTop,
+        ShortcutAction::QuickCapture,
+    ];
+
+    /// Returns the name used in keymap files.
+    pub fn id(&self) -> &'static str {
+        match self {
+            ShortcutAction::NewNote => "new_note",
+            ShortcutAction::Save => "save",
+            ShortcutAction::ExportNote => "export_note",
+            ShortcutAction::ToggleTimeFormat => "toggle_time_format",
+            ShortcutAction::RelativeTime => "relative_time",
+            ShortcutAction::AbsoluteTime => "absolute_time",
+            ShortcutAction::AlwaysOnTop => "always_on_top",
+            ShortcutAction::QuickCapture => "quick_capture",
+        }
+    }
+
+    /// Returns the description shown in the settings window.
+    pub fn label(&self) -> &'static str {
+        match self {
+            ShortcutAction::NewNote => "New note",
+            ShortcutAction::Save => "Save",
+            ShortcutAction::ExportNote => "Export note",
+            ShortcutAction::ToggleTimeFormat => "Switch time format",
+            ShortcutAction::RelativeTime => "Relative times",
+            ShortcutAction::AbsoluteTime => "Absolute times",
+            ShortcutAction::AlwaysOnTop => "Keep window on top",
+            ShortcutAction::QuickCapture => "Quick capture to the inbox",
+        }
+    }
+
+    /// Returns the binding used unless the user changed it.
+    fn default_shortcut(&self) -> Shortcut {
+        let ctrl = |key| Shortcut {
+            ctrl: true,
+            shift: false,
+            alt: false,
+            key,
+        };
+        match self {
+            ShortcutAction::NewNote => ctrl(egui::Key::N),
+            ShortcutAction::Save => ctrl(egui::Key::S),
+            ShortcutAction::ExportNote => ctrl(egui::Key::E),
+            ShortcutAction::ToggleTimeFormat => ctrl(egui::Key::T),
+            ShortcutAction::RelativeTime => ctrl(egui::Key::R),
+            ShortcutAction::AbsoluteTime => Shortcut {
+                alt: true,
+                ..ctrl(egui::Key::A)
+            },
+            ShortcutAction::AlwaysOnTop => Shortcut {
+                shift: true,
+                ..ctrl(egui::Key::P)
+            },
+            ShortcutAction::QuickCapture => Shortcut {
+                shift: true,
+                ..ctrl(egui::Key::I)
+            },
+        }
+    }
+
+    /// Looks up an action by its name in keymap files.
+    fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|action| action.id() == id)
+    }
+}
+
+/// A key combination such as Ctrl + Shift + P.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Shortcut {
+    /// Whether Ctrl must be held
+    pub ctrl: bool,
+    /// Whether Shift must be held
+    pub shift: bool,
+    /// Whether Alt must be held
+    pub alt: bool,
+    /// The key to press
+    pub key: egui::Key,
+}
+
+impl Shortcut {
+    /// Parses a shortcut written like `Ctrl + Shift + P` or `ctrl+alt+a`.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - Modifiers and the key, separated by `+`
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self>` - The shortcut, or error if a part is unknown or
+    ///   neither Ctrl nor Alt is used
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut parts: Vec<&str> = text.split('+').map(str::trim).collect();
+        let key_name = parts.pop().unwrap_or_default();
+        let key = egui::Key::from_name(key_name)
+            .or_else(|| egui::Key::from_name(&key_name.to_uppercase()))
+            .ok_or_else(|| anyhow!("Unknown key \"{}\" in \"{}\"", key_name, text))?;
+
+        let mut shortcut = Shortcut {
+            ctrl: false,
+            shift: false,
+            alt: false,
+            key,
+        };
+        for modifier in parts {
+            match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" => shortcut.ctrl = true,
+                "shift" => shortcut.shift = true,
+                "alt" | "option" => shortcut.alt = true,
+                _ => bail!("Unknown modifier \"{}\" in \"{}\"", modifier, text),
+            }
+        }
+
+        if !shortcut.ctrl && !shortcut.alt {
+            bail!("\"{}\" needs Ctrl or Alt", text);
+        }
+        Ok(shortcut)
+    }
+
+    /// Checks whether the shortcut was pressed this frame.
+    ///
+    /// The held modifiers must match exactly, so Ctrl + S doesn't also
+    /// fire on Ctrl + Shift + S.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - The input state of the frame
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - True if the key was pressed with these modifiers
+    pub fn pressed(&self, input: &egui::InputState) -> bool {
+        input.modifiers.ctrl == self.ctrl
+            && input.modifiers.shift == self.shift
+            && input.modifiers.alt == self.alt
+            && input.key_pressed(self.key)
+    }
+}
+
+impl fmt::Display for Shortcut {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.ctrl {
+            write!(f, "Ctrl + ")?;
+        }
+        if self.alt {
+            write!(f, "Alt + ")?;
+        }
+        if self.shift {
+            write!(f, "Shift + ")?;
+        }
+        write!(f, "{}", self.key.name())
+    }
+}
+
+/// The user's keyboard shortcuts.
+///
+/// Stored in the settings as a map from action name to shortcut text.
+/// Entries that cannot be read fall back to the default binding.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(from = "BTreeMap<String, String>", into = "BTreeMap<String, String>")]
+pub struct Keymap {
+    /// Bindings that differ from the defaults
+    custom: BTreeMap<ShortcutAction, Shortcut>,
+}
+
+impl Keymap {
+    /// Returns the shortcut bound to an action.
+    ///
+    /// # Arguments
+    ///
+    /// * `action` - The action
+    ///
+    /// # Returns
+    ///
+    /// * `Shortcut` - The user's binding, or the default one
+    pub fn shortcut(&self, action: ShortcutAction) -> Shortcut {
+        self.custom
+            .get(&action)
+            .copied()
+            .unwrap_or_else(|| action.default_shortcut())
+    }
+
+    /// Checks whether the shortcut of an action was pressed this frame.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - The input state of the frame
+    /// * `action` - The action
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - True if its shortcut was pressed
+    pub fn pressed(&self, input: &egui::InputState, action: ShortcutAction) -> bool {
+        self.shortcut(action).pressed(input)
+    }
+
+    /// Returns whether any shortcut differs from the defaults.
+    pub fn is_customized(&self) -> bool {
+        !self.custom.is_empty()
+    }
+
+    /// Serializes the keymap into the JSON of an exported keymap file.
+    ///
+    /// All actions are written, including those with default bindings, so
+    /// the file documents every shortcut and can be edited by hand.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<String>` - The file content
+    pub fn to_file_json(&self) -> Result<String> {
+        let file = KeymapFile {
+            format_version: KEYMAP_FILE_VERSION,
+            bindings: ShortcutAction::ALL
+                .iter()
+                .map(|action| (action.id().to_string(), self.shortcut(*action).to_string()))
+                .collect(),
+        };
+        Ok(serde_json::to_string_pretty(&file)?)
+    }
+
+    /// Reads an exported keymap file.
+    ///
+    /// # Arguments
+    ///
+    /// * `json` - The file content
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(Self, usize)>` - The keymap and the number of unknown
+    ///   actions that were ignored, or error if a shortcut is invalid or
+    ///   two actions share a shortcut
+    pub fn from_file_json(json: &str) -> Result<(Self, usize)> {
+        let file: KeymapFile =
+            serde_json::from_str(json).map_err(|e| anyhow!("Not a keymap file: {}", e))?;
+        if file.format_version > KEYMAP_FILE_VERSION {
+            bail!(
+                "The keymap file was written by a newer version (format {})",
+                file.format_version
+            );
+        }
+
+        let mut keymap = Keymap::default();
+        let mut unknown = 0;
+        for (id, text) in &file.bindings {
+            match ShortcutAction::from_id(id) {
+                Some(action) => keymap.set(action, Shortcut::parse(text)?),
+                None => unknown += 1,
+            }
+        }
+
+        for (index, action) in ShortcutAction::ALL.iter().enumerate() {
+            let shortcut = keymap.shortcut(*action);
+            if let Some(other) = ShortcutAction::ALL[index + 1..]
+                .iter()
+                .find(|other| keymap.shortcut(**other) == shortcut)
+            {
+                bail!(
+                    "{} is used for both \"{}\" and \"{}\"",
+                    shortcut,
+                    action.label(),
+                    other.label()
+                );
+            }
+        }
+
+        Ok((keymap, unknown))
+    }
+
+    /// Binds an action, keeping only bindings that differ from the default.
+    fn set(&mut self, action: ShortcutAction, shortcut: Shortcut) {
+        if shortcut == action.default_shortcut() {
+            self.custom.remove(&action);
+        } else {
+            self.custom.insert(action, shortcut);
+        }
+    }
+}
+
+impl From<BTreeMap<String, String>> for Keymap {
+    fn from(bindings: BTreeMap<String, String>) -> Self {
+        let mut keymap = Keymap::default();
+        for (id, text) in bindings {
+            let Some(action) = ShortcutAction::from_id(&id) else {
+                continue;
+            };
+            match Shortcut::parse(&text) {
+                Ok(shortcut) => keymap.set(action, shortcut),
+                Err(e) => log::warn!("Ignoring shortcut for {}: {}", id, e),
+            }
+        }
+        keymap
+    }
+}
+
+impl From<Keymap> for BTreeMap<String, String> {
+    fn from(keymap: Keymap) -> Self {
+        keymap
+            .custom
+            .iter()
+            .map(|(action, shortcut)| (action.id().to_string(), shortcut.to_string()))
+            .collect()
+    }
+}
+
+/// Content of an exported keymap file.
+#[derive(Serialize, Deserialize)]
+struct KeymapFile {
+    /// Version of the file format
+    format_version: u32,
+    /// Shortcut text by action name
+    bindings: BTreeMap<String, String>,
+}
+
+impl NotesApp {
+    /// Exports the keyboard shortcuts to a JSON file chosen by the user.
+    pub fn export_keymap(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .set_title("Export Keyboard Shortcuts")
+            .set_file_name("secure_notes_shortcuts.json")
+            .add_filter("JSON files", &["json"])
+            .save_file()
+        else {
+            return;
+        };
+
+        let result = self
+            .settings
+            .keymap
+            .to_file_json()
+            .and_then(|json| Ok(std::fs::write(&path, json)?));
+        match result {
+            Ok(()) => {
+                self.report_export(format!("Shortcuts exported to {}", path.display()), &path)
+            }
+            Err(e) => {
+                log::error!("Failed to export shortcuts: {}", e);
+                self.show_status(format!("Failed to export shortcuts: {}", e));
+            }
+        }
+    }
+
+    /// Replaces the keyboard shortcuts with those of a JSON file chosen by
+    /// the user.
+    pub fn import_keymap(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .set_title("Import Keyboard Shortcuts")
+            .add_filter("JSON files", &["json"])
+            .pick_file()
+        else {
+            return;
+        };
+
+        let result = std::fs::read_to_string(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| Keymap::from_file_json(&json));
+        let message = match result {
+            Ok((keymap, unknown)) => {
+                self.settings.keymap = keymap;
+                self.save_settings();
+                if unknown > 0 {
+                    format!("Shortcuts imported, {} unknown actions ignored", unknown)
+                } else {
+                    "Shortcuts imported".to_string()
+                }
+            }
+            Err(e) => {
+                log::warn!("Failed to import shortcuts: {}", e);
+                format!("Failed to import shortcuts: {}", e)
+            }
+        };
+        self.show_status(message);
+    }
+}
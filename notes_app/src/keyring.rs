@@ -0,0 +1,119 @@
+//! # Keyring Module
+//!
+//! Stores small secrets (local keys, wrapped session keys, credentials) in
+//! the platform keychain: Windows Credential Manager, macOS Keychain or the
+//! Secret Service on Linux (GNOME Keyring, KWallet).
+//!
+//! When no keychain is available, for example on a headless Linux system
+//! without a Secret Service daemon, secrets fall back to owner-only files in
+//! the `secrets/` directory of the data directory. Secrets found in fallback
+//! files are moved into the keychain as soon as it becomes available.
+//!
+//! A secret only counts as missing when the keychain confirms it has no
+//! entry. If the keychain can't be queried (e.g. it is locked) and there is
+//! no fallback file, loading fails, so a caller never mistakes a locked
+//! keychain for a secret that was never stored.
+
+use crate::secure_fs;
+use anyhow::{anyhow, Result};
+use std::fs;
+use std::path::PathBuf;
+
+/// Service name under which all secrets are stored in the keychain.
+const SERVICE_NAME: &str = "secure_notes";
+
+/// Where a secret ended up being stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecretLocation {
+    /// The platform keychain
+    Keychain,
+    /// An owner-only file in the data directory
+    File,
+}
+
+/// Stores a secret, preferring the platform keychain.
+///
+/// # Arguments
+///
+/// * `name` - Unique name of the secret
+/// * `secret` - The secret bytes
+///
+/// # Returns
+///
+/// * `Result<SecretLocation>` - Where the secret was stored, or error if
+///   neither the keychain nor the fallback file could be written
+pub fn store_secret(name: &str, secret: &[u8]) -> Result<SecretLocation> {
+    match keychain_entry(name).and_then(|entry| entry.set_secret(secret)) {
+        Ok(()) => {
+            // Don't leave a stale copy behind on disk
+            let path = fallback_path(name);
+            if path.exists() {
+                secure_fs::secure_remove_file(&path)?;
+            }
+            Ok(SecretLocation::Keychain)
+        }
+        Err(e) => {
+            log::warn!(
+                "Keychain unavailable, storing secret {} in a file: {}",
+                name,
+                e
+            );
+            let path = fallback_path(name);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            secure_fs::write_private_file(&path, secret)?;
+            Ok(SecretLocation::File)
+        }
+    }
+}
+
+/// Loads a secret from the keychain or the fallback file.
+///
+/// # Arguments
+///
+/// * `name` - Unique name of the secret
+///
+/// # Returns
+///
+/// * `Result<Option<Vec<u8>>>` - The secret, None if the keychain has no
+///   entry for it and there is no fallback file, or error if the keychain
+///   lookup failed (e.g. the keychain is locked) without a fallback file,
+///   or the fallback file cannot be read
+pub fn load_secret(name: &str) -> Result<Option<Vec<u8>>> {
+    let lookup = match keychain_entry(name).and_then(|entry| entry.get_secret()) {
+        Ok(secret) => return Ok(Some(secret)),
+        Err(::keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(e),
+    };
+
+    let path = fallback_path(name);
+    if !path.exists() {
+        // The secret may well be in a keychain that can't be read right now
+        return match lookup {
+            Ok(()) => Ok(None),
+            Err(e) => Err(anyhow!("Keychain lookup for {} failed: {}", name, e)),
+        };
+    }
+
+    let secret = fs::read(&path)?;
+    // Move the secret into the keychain if it is available by now
+    if let Err(e) = store_secret(name, &secret) {
+        log::debug!("Keeping secret {} in its file: {}", name, e);
+    }
+    Ok(Some(secret))
+}
+
+/// Opens the keychain entry for a secret.
+fn keychain_entry(name: &str) -> ::keyring::Result<::keyring::Entry> {
+    ::keyring::Entry::new(SERVICE_NAME, name)
+}
+
+/// Returns the path of the fallback file for a secret.
+fn fallback_path(name: &str) -> PathBuf {
+    let mut path = dirs::config_dir().unwrap_or_else(|| PathBuf::from("."));
+    path.push("secure_notes");
+    path.push("secrets");
+    path.push(format!("{}.secret", name));
+    path
+}
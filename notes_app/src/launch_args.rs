@@ -0,0 +1,126 @@
+//! # Launch Arguments Module
+//!
+//! Notes to create from the command line, so the app can be started from
+//! scripts and registered with the OS for "Open with Secure Notes":
+//!
+//! ```bash
+//! notes_app --new "Meeting notes"    # an empty note titled "Meeting notes"
+//! notes_app todo.md ideas.txt        # one note per file, named after it
+//! notes_app --capture "Call Anna"    # a note in the inbox, see `inbox`
+//! ```
+//!
+//! The vault is locked at startup, so the request is kept until the user
+//! has logged in and the notes are loaded. The files are read only then
+//! and never changed or deleted. Other arguments are logged and ignored.
+
+use anyhow::{anyhow, Result};
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Extensions of files that can be opened as notes.
+pub const NOTE_FILE_EXTENSIONS: [&str; 3] = ["md", "markdown", "txt"];
+
+/// Largest file opened as a note, in bytes.
+const MAX_NOTE_FILE_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Notes requested on the command line.
+#[derive(Debug, Default)]
+pub struct LaunchRequest {
+    /// Titles of new empty notes; empty for an untitled note
+    pub new_titles: Vec<String>,
+    /// Text files to create notes from
+    pub files: Vec<PathBuf>,
+    /// Texts to add to the inbox
+    pub captures: Vec<String>,
+}
+
+impl LaunchRequest {
+    /// Parses the command line arguments, without the program name.
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - The arguments, e.g. `std::env::args_os().skip(1)`
+    ///
+    /// # Returns
+    ///
+    /// * `Self` - The requested notes, empty if none
+    pub fn from_args(args: impl IntoIterator<Item = OsString>) -> Self {
+        let mut request = Self::default();
+        let mut args = args.into_iter().peekable();
+
+        while let Some(arg) = args.next() {
+            let text = arg.to_string_lossy();
+            if text == "--new" {
+                // A following option or file is not the title
+                let title = args
+                    .next_if(|next| {
+                        let next = next.to_string_lossy();
+                        !next.starts_with("--") && !is_note_file(Path::new(next.as_ref()))
+                    })
+                    .map(|title| title.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                request.new_titles.push(title);
+            } else if let Some(title) = text.strip_prefix("--new=") {
+                request.new_titles.push(title.to_string());
+            } else if text == "--capture" {
+                match args.next() {
+                    Some(capture) => request
+                        .captures
+                        .push(capture.to_string_lossy().into_owned()),
+                    None => log::warn!("Ignoring --capture without a text"),
+                }
+            } else if let Some(capture) = text.strip_prefix("--capture=") {
+                request.captures.push(capture.to_string());
+            } else if is_note_file(Path::new(&arg)) {
+                request.files.push(PathBuf::from(&arg));
+            } else {
+                log::warn!("Ignoring unknown command line argument {:?}", text);
+            }
+        }
+        request
+    }
+
+    /// Returns whether no notes were requested.
+    pub fn is_empty(&self) -> bool {
+        self.new_titles.is_empty() && self.files.is_empty() && self.captures.is_empty()
+    }
+}
+
+/// Checks whether a path has the extension of a note file.
+fn is_note_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| NOTE_FILE_EXTENSIONS.contains(&extension.to_lowercase().as_str()))
+}
+
+/// Reads a text file to create a note from.
+///
+/// # Arguments
+///
+/// * `path` - The file
+///
+/// # Returns
+///
+/// * `Result<(String, String)>` - The title, taken from the file name, and
+///   the content; Err if the file is too large, not UTF-8 text or can't be
+///   read
+pub fn read_note_file(path: &Path) -> Result<(String, String)> {
+    let size = fs::metadata(path)?.len();
+    if size > MAX_NOTE_FILE_BYTES {
+        return Err(anyhow!(
+            "{} is larger than {} MB",
+            path.display(),
+            MAX_NOTE_FILE_BYTES / 1024 / 1024
+        ));
+    }
+    let content = String::from_utf8(fs::read(path)?)
+        .map_err(|_| anyhow!("{} is not a UTF-8 text file", path.display()))?;
+    // Editors on Windows like to start files with a byte order mark
+    let content = content.trim_start_matches('\u{feff}').to_string();
+    let title = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().trim().to_string())
+        .unwrap_or_default();
+    Ok((title, content))
+}
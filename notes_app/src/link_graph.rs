@@ -0,0 +1,185 @@
+//! # Link Graph Module
+//!
+//! The graph of the wiki links between notes: every note is a node and
+//! every note linking to another is an edge, see the wiki_links module.
+//! The graph is shown in the link graph window and can be exported as
+//! Graphviz DOT or as JSON for other tools.
+//!
+//! Links are resolved by title, ignoring case. A title shared by several
+//! notes links to all of them; links to titles no note has are kept as
+//! unresolved links. Trashed notes are left out.
+
+use crate::note::Note;
+use crate::wiki_links;
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// A note in the graph.
+#[derive(Debug, Clone, Serialize)]
+pub struct GraphNode {
+    /// ID of the note
+    pub note_id: String,
+    /// Title of the note
+    pub title: String,
+}
+
+/// Links from one note to another.
+#[derive(Debug, Clone, Serialize)]
+pub struct GraphEdge {
+    /// Index of the linking note in `LinkGraph::nodes`
+    pub from: usize,
+    /// Index of the linked note in `LinkGraph::nodes`
+    pub to: usize,
+    /// Number of links from `from` to `to`
+    pub count: usize,
+}
+
+/// A link to a title no note has.
+#[derive(Debug, Clone, Serialize)]
+pub struct UnresolvedLink {
+    /// Index of the linking note in `LinkGraph::nodes`
+    pub from: usize,
+    /// The title in the link
+    pub target: String,
+}
+
+/// The notes and the links between them.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LinkGraph {
+    /// The notes, sorted by title
+    pub nodes: Vec<GraphNode>,
+    /// Links between notes, sorted by linking and linked note
+    pub edges: Vec<GraphEdge>,
+    /// Links to missing notes
+    pub unresolved: Vec<UnresolvedLink>,
+}
+
+impl LinkGraph {
+    /// Builds the graph of the links between notes.
+    ///
+    /// # Arguments
+    ///
+    /// * `notes` - All notes; trashed ones are skipped
+    ///
+    /// # Returns
+    ///
+    /// * `Self` - The graph
+    pub fn build<'a>(notes: impl IntoIterator<Item = &'a Note>) -> Self {
+        let mut notes: Vec<&Note> = notes
+            .into_iter()
+            .filter(|note| !note.is_trashed())
+            .collect();
+        notes.sort_by_key(|note| (note.title.to_lowercase(), note.id.clone()));
+
+        let mut by_title: HashMap<String, Vec<usize>> = HashMap::new();
+        for (index, note) in notes.iter().enumerate() {
+            by_title
+                .entry(note.title.trim().to_lowercase())
+                .or_default()
+                .push(index);
+        }
+
+        let mut edge_counts: BTreeMap<(usize, usize), usize> = BTreeMap::new();
+        let mut unresolved = Vec::new();
+        for (from, note) in notes.iter().enumerate() {
+            for link in wiki_links::find_links(&note.content) {
+                let target = &note.content[link.target];
+                match by_title.get(&target.to_lowercase()) {
+                    Some(targets) => {
+                        for &to in targets.iter().filter(|&&to| to != from) {
+                            *edge_counts.entry((from, to)).or_default() += 1;
+                        }
+                    }
+                    None => unresolved.push(UnresolvedLink {
+                        from,
+                        target: target.to_string(),
+                    }),
+                }
+            }
+        }
+
+        Self {
+            nodes: notes
+                .iter()
+                .map(|note| GraphNode {
+                    note_id: note.id.clone(),
+                    title: note.title.clone(),
+                })
+                .collect(),
+            edges: edge_counts
+                .into_iter()
+                .map(|((from, to), count)| GraphEdge { from, to, count })
+                .collect(),
+            unresolved,
+        }
+    }
+
+    /// Writes the graph in the Graphviz DOT language.
+    ///
+    /// # Returns
+    ///
+    /// * `String` - A directed graph with the note IDs as node names and
+    ///   the titles as labels; links to missing notes are dashed
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph notes {\n    node [shape=box];\n");
+        for node in &self.nodes {
+            dot.push_str(&format!(
+                "    {} [label={}];\n",
+                dot_string(&node.note_id),
+                dot_string(&node.title)
+            ));
+        }
+        for edge in &self.edges {
+            let attributes = if edge.count > 1 {
+                format!(" [label=\"{}\"]", edge.count)
+            } else {
+                String::new()
+            };
+            dot.push_str(&format!(
+                "    {} -> {}{};\n",
+                dot_string(&self.nodes[edge.from].note_id),
+                dot_string(&self.nodes[edge.to].note_id),
+                attributes
+            ));
+        }
+        for link in &self.unresolved {
+            dot.push_str(&format!(
+                "    {} -> {} [style=dashed];\n",
+                dot_string(&self.nodes[link.from].note_id),
+                dot_string(&format!("missing: {}", link.target))
+            ));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Writes the graph as JSON.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<String>` - Pretty-printed JSON with `nodes`, `edges` and
+    ///   `unresolved`; edges refer to nodes by index
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Returns the number of edges each node is part of.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<usize>` - Incoming and outgoing edges per node
+    pub fn degrees(&self) -> Vec<usize> {
+        let mut degrees = vec![0; self.nodes.len()];
+        for edge in &self.edges {
+            degrees[edge.from] += 1;
+            degrees[edge.to] += 1;
+        }
+        degrees
+    }
+}
+
+/// Quotes a string for DOT, escaping quotes and backslashes.
+fn dot_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
@@ -0,0 +1,376 @@
+//! # Link Graph UI Module
+//!
+//! The link graph window: notes are drawn as dots, sized by how many links
+//! they have, and wiki links as arrows between them. Dragging moves the
+//! graph, scrolling zooms, and clicking a note opens it. The window also
+//! exports the graph as DOT or JSON.
+//!
+//! The layout is a force-directed one (Fruchterman-Reingold): linked notes
+//! pull each other close while all notes push each other apart. It is
+//! computed once when the window opens or is refreshed, so the graph
+//! doesn't move around while being looked at.
+
+use crate::app::NotesApp;
+use crate::link_graph::LinkGraph;
+use crate::secure_fs;
+use eframe::egui;
+
+/// Number of steps the layout is refined in.
+const LAYOUT_ITERATIONS: usize = 120;
+
+/// Number of notes up to which every title is shown; above it, titles are
+/// shown when hovering or zoomed in.
+const ALWAYS_LABELED_NODES: usize = 40;
+
+/// The link graph and how it is laid out and viewed.
+pub struct LinkGraphView {
+    /// The graph
+    graph: LinkGraph,
+    /// Position of each node, within -1.0..=1.0 on both axes
+    positions: Vec<egui::Vec2>,
+    /// Number of links of each node
+    degrees: Vec<usize>,
+    /// Offset of the graph from the center of the canvas, in points
+    pan: egui::Vec2,
+    /// Zoom factor, 1.0 fits the graph into the canvas
+    zoom: f32,
+    /// Whether notes without links are drawn
+    show_unlinked: bool,
+}
+
+impl LinkGraphView {
+    /// Lays out a graph for viewing.
+    ///
+    /// # Arguments
+    ///
+    /// * `graph` - The graph to show
+    ///
+    /// # Returns
+    ///
+    /// * `Self` - The view, centered and not zoomed
+    pub fn new(graph: LinkGraph) -> Self {
+        Self {
+            positions: layout(&graph),
+            degrees: graph.degrees(),
+            graph,
+            pan: egui::Vec2::ZERO,
+            zoom: 1.0,
+            show_unlinked: false,
+        }
+    }
+}
+
+/// Computes a force-directed layout of a graph.
+///
+/// Nodes start on a sunflower spiral, so the result is the same every time
+/// for the same graph.
+///
+/// # Arguments
+///
+/// * `graph` - The graph to lay out
+///
+/// # Returns
+///
+/// * `Vec<egui::Vec2>` - Position of each node, within -1.0..=1.0
+fn layout(graph: &LinkGraph) -> Vec<egui::Vec2> {
+    let count = graph.nodes.len();
+    let golden_angle = std::f32::consts::PI * (3.0 - 5.0_f32.sqrt());
+    let mut positions: Vec<egui::Vec2> = (0..count)
+        .map(|i| {
+            let radius = ((i as f32 + 0.5) / count as f32).sqrt() * 0.9;
+            egui::Vec2::angled(i as f32 * golden_angle) * radius
+        })
+        .collect();
+    if count < 2 {
+        return positions;
+    }
+
+    // Ideal distance between nodes, for an area of 2 × 2
+    let k = 2.0 / (count as f32).sqrt();
+    for iteration in 0..LAYOUT_ITERATIONS {
+        let mut displacement = vec![egui::Vec2::ZERO; count];
+
+        for i in 0..count {
+            for j in i + 1..count {
+                let delta = positions[i] - positions[j];
+                let distance = delta.length().max(0.01);
+                let push = delta / distance * (k * k / distance);
+                displacement[i] += push;
+                displacement[j] -= push;
+            }
+        }
+        for edge in &graph.edges {
+            let delta = positions[edge.from] - positions[edge.to];
+            let distance = delta.length().max(0.01);
+            let pull = delta / distance * (distance * distance / k);
+            displacement[edge.from] -= pull;
+            displacement[edge.to] += pull;
+        }
+
+        // Moves get smaller as the layout settles
+        let temperature = 0.1 * (1.0 - iteration as f32 / LAYOUT_ITERATIONS as f32);
+        for (position, displacement) in positions.iter_mut().zip(&displacement) {
+            // Gravity keeps unlinked notes from drifting to the edges
+            let displacement = *displacement - *position * k;
+            let length = displacement.length();
+            if length > 0.0 {
+                *position += displacement / length * length.min(temperature);
+            }
+            *position = position.clamp(egui::vec2(-1.0, -1.0), egui::vec2(1.0, 1.0));
+        }
+    }
+
+    positions
+}
+
+/// Button or click in the window, handled after the window closure.
+enum GraphAction {
+    Refresh,
+    ExportDot,
+    ExportJson,
+    Open(String),
+}
+
+impl NotesApp {
+    /// Builds the link graph of the notes and opens the graph window.
+    pub fn open_link_graph(&mut self) {
+        let graph = LinkGraph::build(self.notes.values());
+        self.link_graph_view = Some(LinkGraphView::new(graph));
+    }
+
+    /// Renders the link graph window, if open.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The egui context for rendering
+    pub fn render_link_graph(&mut self, ctx: &egui::Context) {
+        let Some(view) = &mut self.link_graph_view else {
+            return;
+        };
+
+        let selected = self.selected_note_id.as_deref();
+        let mut open = true;
+        let mut action = None;
+
+        egui::Window::new("Link Graph")
+            .open(&mut open)
+            .default_size([700.0, 500.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(format!(
+                        "{} notes, {} links",
+                        view.graph.nodes.len(),
+                        view.graph.edges.len()
+                    ));
+                    if !view.graph.unresolved.is_empty() {
+                        ui.colored_label(
+                            ui.visuals().warn_fg_color,
+                            format!("{} to missing notes", view.graph.unresolved.len()),
+                        )
+                        .on_hover_text(
+                            view.graph
+                                .unresolved
+                                .iter()
+                                .map(|link| {
+                                    format!(
+                                        "{} → [[{}]]",
+                                        view.graph.nodes[link.from].title, link.target
+                                    )
+                                })
+                                .collect::<Vec<_>>()
+                                .join("\n"),
+                        );
+                    }
+                    ui.checkbox(&mut view.show_unlinked, "Show unlinked notes");
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if ui.button("Export JSON…").clicked() {
+                            action = Some(GraphAction::ExportJson);
+                        }
+                        if ui.button("Export DOT…").clicked() {
+                            action = Some(GraphAction::ExportDot);
+                        }
+                        if ui
+                            .button("Refresh")
+                            .on_hover_text("Read the links again and redo the layout")
+                            .clicked()
+                        {
+                            action = Some(GraphAction::Refresh);
+                        }
+                    });
+                });
+                ui.small("Drag to move, scroll to zoom, click a note to open it.");
+                ui.separator();
+
+                if let Some(note_id) = render_canvas(ui, view, selected) {
+                    action = Some(GraphAction::Open(note_id));
+                }
+            });
+
+        if !open {
+            self.link_graph_view = None;
+            return;
+        }
+        match action {
+            Some(GraphAction::Refresh) => self.open_link_graph(),
+            Some(GraphAction::ExportDot) => self.export_link_graph(false),
+            Some(GraphAction::ExportJson) => self.export_link_graph(true),
+            Some(GraphAction::Open(note_id)) => self.selected_note_id = Some(note_id),
+            None => {}
+        }
+    }
+
+    /// Lets the user pick a file and writes the link graph to it.
+    ///
+    /// # Arguments
+    ///
+    /// * `json` - Whether to write JSON rather than DOT
+    fn export_link_graph(&mut self, json: bool) {
+        let Some(view) = &self.link_graph_view else {
+            return;
+        };
+        let (extension, filter) = if json {
+            ("json", "JSON files")
+        } else {
+            ("dot", "Graphviz files")
+        };
+        let Some(path) = rfd::FileDialog::new()
+            .set_title("Export Link Graph")
+            .set_file_name(format!("note_links.{}", extension))
+            .add_filter(filter, &[extension])
+            .save_file()
+        else {
+            return;
+        };
+
+        let result = if json {
+            view.graph.to_json()
+        } else {
+            Ok(view.graph.to_dot())
+        }
+        .and_then(|data| Ok(secure_fs::write_private_file(&path, data)?));
+
+        match result {
+            Ok(()) => {
+                log::info!("Exported the link graph");
+                self.report_export(format!("Link graph exported to {}", path.display()), &path);
+            }
+            Err(e) => {
+                log::error!("Failed to export the link graph: {}", e);
+                self.show_status(format!("Export failed: {}", e));
+            }
+        }
+    }
+}
+
+/// Draws the graph and handles moving, zooming and clicking it.
+///
+/// # Arguments
+///
+/// * `ui` - The window UI; the canvas takes the remaining space
+/// * `view` - The graph and its view state
+/// * `selected` - ID of the open note, highlighted
+///
+/// # Returns
+///
+/// * `Option<String>` - ID of the clicked note
+fn render_canvas(
+    ui: &mut egui::Ui,
+    view: &mut LinkGraphView,
+    selected: Option<&str>,
+) -> Option<String> {
+    let (response, painter) =
+        ui.allocate_painter(ui.available_size(), egui::Sense::click_and_drag());
+    let rect = response.rect;
+
+    view.pan += response.drag_delta();
+    if response.hovered() {
+        let scroll = ui.input(|i| i.smooth_scroll_delta.y);
+        if scroll != 0.0 {
+            view.zoom = (view.zoom * (scroll * 0.002).exp()).clamp(0.2, 8.0);
+        }
+    }
+
+    let scale = rect.width().min(rect.height()) * 0.45 * view.zoom;
+    let to_screen = |position: egui::Vec2| rect.center() + view.pan + position * scale;
+    let visible = |index: usize| view.show_unlinked || view.degrees[index] > 0;
+    let radius = |index: usize| 4.0 + view.degrees[index].min(10) as f32 * 0.8;
+
+    let hovered = response.hover_pos().and_then(|pointer| {
+        (0..view.graph.nodes.len())
+            .filter(|&index| visible(index))
+            .map(|index| (index, to_screen(view.positions[index]).distance(pointer)))
+            .filter(|&(index, distance)| distance <= radius(index) + 4.0)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(index, _)| index)
+    });
+
+    let visuals = ui.visuals();
+    let edge_stroke = egui::Stroke::new(1.0, visuals.weak_text_color());
+    for edge in &view.graph.edges {
+        let from = to_screen(view.positions[edge.from]);
+        let to = to_screen(view.positions[edge.to]);
+        let direction = (to - from).normalized();
+        let stroke = if hovered == Some(edge.from) || hovered == Some(edge.to) {
+            egui::Stroke::new(1.5, visuals.hyperlink_color)
+        } else {
+            edge_stroke
+        };
+        painter.arrow(
+            from + direction * radius(edge.from),
+            to - from - direction * (radius(edge.from) + radius(edge.to)),
+            stroke,
+        );
+    }
+
+    let show_all_labels = view.graph.nodes.len() <= ALWAYS_LABELED_NODES || view.zoom >= 2.0;
+    let font = egui::TextStyle::Small.resolve(ui.style());
+    for (index, node) in view.graph.nodes.iter().enumerate() {
+        if !visible(index) {
+            continue;
+        }
+        let center = to_screen(view.positions[index]);
+        if !rect.expand(50.0).contains(center) {
+            continue;
+        }
+        let fill = if selected == Some(node.note_id.as_str()) {
+            visuals.selection.bg_fill
+        } else if hovered == Some(index) {
+            visuals.hyperlink_color
+        } else {
+            visuals.widgets.inactive.fg_stroke.color
+        };
+        painter.circle_filled(center, radius(index), fill);
+        if show_all_labels || hovered == Some(index) {
+            painter.text(
+                center + egui::vec2(0.0, radius(index) + 2.0),
+                egui::Align2::CENTER_TOP,
+                &node.title,
+                font.clone(),
+                visuals.text_color(),
+            );
+        }
+    }
+
+    if view
+        .graph
+        .nodes
+        .iter()
+        .enumerate()
+        .all(|(index, _)| !visible(index))
+    {
+        painter.text(
+            rect.center(),
+            egui::Align2::CENTER_CENTER,
+            "No links yet. Link notes with [[Title]].",
+            egui::TextStyle::Body.resolve(ui.style()),
+            visuals.weak_text_color(),
+        );
+    }
+
+    if hovered.is_some() {
+        ui.ctx().set_cursor_icon(egui::CursorIcon::PointingHand);
+    }
+    hovered
+        .filter(|_| response.clicked())
+        .map(|index| view.graph.nodes[index].note_id.clone())
+}
@@ -0,0 +1,87 @@
+//! # Links Module
+//!
+//! Detection of web links in note text. The editor underlines `http://`
+//! and `https://` URLs, shows the full URL on hover and opens it in the
+//! default browser on Ctrl+click.
+
+use std::ops::Range;
+
+/// Schemes recognized as the start of a link.
+const URL_SCHEMES: [&str; 2] = ["https://", "http://"];
+
+/// Finds the web links in a text.
+///
+/// A link runs from its scheme to the next whitespace or quote character.
+/// Trailing punctuation such as a sentence's full stop is not part of the
+/// link, and neither is a closing parenthesis without an opening one in
+/// the link, so `(see https://example.com)` works as expected.
+///
+/// # Arguments
+///
+/// * `text` - The text to scan
+///
+/// # Returns
+///
+/// * `Vec<Range<usize>>` - Byte ranges of the links, in order
+pub fn find_urls(text: &str) -> Vec<Range<usize>> {
+    let mut urls = Vec::new();
+    let mut position = 0;
+
+    while let Some((start, scheme_len)) = URL_SCHEMES
+        .iter()
+        .filter_map(|scheme| {
+            text[position..]
+                .find(scheme)
+                .map(|i| (position + i, scheme.len()))
+        })
+        .min_by_key(|(start, _)| *start)
+    {
+        let rest = &text[start..];
+        let mut end = start
+            + rest
+                .find(|c: char| c.is_whitespace() || matches!(c, '"' | '\'' | '<' | '>'))
+                .unwrap_or(rest.len());
+
+        // Drop trailing punctuation and unbalanced closing brackets
+        loop {
+            let url = &text[start..end];
+            let Some(last) = url.chars().last() else {
+                break;
+            };
+            let unbalanced = match last {
+                ')' => url.matches(')').count() > url.matches('(').count(),
+                ']' => url.matches(']').count() > url.matches('[').count(),
+                '.' | ',' | ';' | ':' | '!' | '?' => true,
+                _ => false,
+            };
+            if !unbalanced {
+                break;
+            }
+            end -= last.len_utf8();
+        }
+
+        if end > start + scheme_len {
+            urls.push(start..end);
+        }
+        position = end.max(start + scheme_len);
+    }
+
+    urls
+}
+
+/// Finds the link at a byte position in a text.
+///
+/// # Arguments
+///
+/// * `text` - The text to scan
+/// * `byte_index` - Position to look at
+///
+/// # Returns
+///
+/// * `Option<&str>` - The link covering the position, if any
+pub fn url_at(text: &str, byte_index: usize) -> Option<&str> {
+    find_urls(text)
+        .into_iter()
+        .find(|range| range.contains(&byte_index))
+        .map(|range| &text[range])
+}
@@ -0,0 +1,201 @@
+//! # Load Failures Module
+//!
+//! Why note files failed to decrypt when the notes were loaded. Each
+//! failure is classified from the file's header and size, so the recovery
+//! dialog can tell a cut-off file from one that was modified or encrypted
+//! with another key, and the log gets a few lines and a summary instead of
+//! one error per file.
+//!
+//! The encryption can't tell a wrong key from a modified file: both fail
+//! the authentication tag. A failure is only put down to the key when no
+//! note of the same vault decrypted at all.
+
+use anyhow::Error;
+use secure_core::Cipher;
+use std::collections::BTreeMap;
+
+/// Length of the authentication tag at the end of every ciphertext.
+const TAG_LEN: usize = 16;
+
+/// Number of failures logged one by one; the rest are only counted.
+pub const LOGGED_FAILURES: usize = 5;
+
+/// Why a note file could not be loaded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FailureReason {
+    /// The file could not be read from disk
+    Unreadable,
+    /// The file is shorter than its header and tag
+    Truncated,
+    /// The file was written in a format or with an algorithm this version
+    /// doesn't know
+    UnsupportedFormat,
+    /// The authentication tag didn't match: the file was modified or
+    /// damaged, or belongs to another note or vault
+    BadTag,
+    /// No file of the vault decrypted, so the key is wrong
+    WrongKey,
+    /// The file decrypted, but doesn't hold a note
+    DamagedContent,
+}
+
+impl FailureReason {
+    /// Short name of the reason.
+    pub fn label(self) -> &'static str {
+        match self {
+            FailureReason::Unreadable => "Unreadable file",
+            FailureReason::Truncated => "Truncated file",
+            FailureReason::UnsupportedFormat => "Unsupported format",
+            FailureReason::BadTag => "Bad authentication tag",
+            FailureReason::WrongKey => "Wrong key",
+            FailureReason::DamagedContent => "Damaged content",
+        }
+    }
+
+    /// What the reason usually means for the user.
+    pub fn explanation(self) -> &'static str {
+        match self {
+            FailureReason::Unreadable => {
+                "The file could not be read; check its permissions and the disk."
+            }
+            FailureReason::Truncated => {
+                "The file was cut off, e.g. by a full disk, a crash while saving or \
+                 an incomplete sync."
+            }
+            FailureReason::UnsupportedFormat => {
+                "The file was written by a newer version of the app; update it \
+                 before restoring anything."
+            }
+            FailureReason::BadTag => {
+                "The file was modified or damaged after it was saved, or copied \
+                 from another note or vault."
+            }
+            FailureReason::WrongKey => {
+                "None of the notes decrypted, so they were encrypted with another \
+                 key, e.g. on another computer or before the password was reset."
+            }
+            FailureReason::DamagedContent => {
+                "The file decrypted, but its content is not a note. It was saved \
+                 by a faulty version or damaged before encryption."
+            }
+        }
+    }
+
+    /// Classifies a file that failed to decrypt.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The file's content
+    ///
+    /// # Returns
+    ///
+    /// * `Self` - Truncated, UnsupportedFormat or BadTag; WrongKey is only
+    ///   decided by `settle` once all files are known
+    pub fn of_ciphertext(data: &[u8]) -> Self {
+        match Cipher::inspect(data) {
+            Err(_) => FailureReason::Truncated,
+            Ok(info) if info.algorithm.is_none() || !info.format.is_supported() => {
+                FailureReason::UnsupportedFormat
+            }
+            Ok(info) if info.ciphertext_len < TAG_LEN => FailureReason::Truncated,
+            Ok(_) => FailureReason::BadTag,
+        }
+    }
+}
+
+/// A note whose file failed to load.
+#[derive(Debug, Clone)]
+pub struct LoadFailure {
+    /// ID of the note, from the file name
+    pub note_id: String,
+    /// Whether the note is in the shared vault
+    pub shared: bool,
+    /// Why it failed
+    pub reason: FailureReason,
+    /// The error, for the log and diagnostics
+    pub error: String,
+}
+
+impl LoadFailure {
+    /// Describes a failure.
+    ///
+    /// # Arguments
+    ///
+    /// * `note_id` - ID of the note
+    /// * `reason` - Why it failed
+    /// * `error` - The error
+    pub fn new(note_id: &str, reason: FailureReason, error: &Error) -> Self {
+        Self {
+            note_id: note_id.to_string(),
+            shared: false,
+            reason,
+            error: error.to_string(),
+        }
+    }
+}
+
+/// Puts bad tags down to a wrong key when no file of the vault decrypted.
+///
+/// # Arguments
+///
+/// * `failures` - The failures of one vault
+/// * `files` - Number of note files in the vault
+pub fn settle(failures: &mut [LoadFailure], files: usize) {
+    let all_failed = files > 0 && failures.len() == files;
+    if all_failed && failures.iter().any(|f| f.reason == FailureReason::BadTag) {
+        for failure in failures
+            .iter_mut()
+            .filter(|f| f.reason == FailureReason::BadTag)
+        {
+            failure.reason = FailureReason::WrongKey;
+        }
+    }
+}
+
+/// Counts failures by reason.
+///
+/// # Returns
+///
+/// * `BTreeMap<FailureReason, usize>` - Number of failures per reason, in
+///   the order of the reasons
+pub fn count_by_reason(failures: &[LoadFailure]) -> BTreeMap<FailureReason, usize> {
+    let mut counts = BTreeMap::new();
+    for failure in failures {
+        *counts.entry(failure.reason).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// Logs the failures of a load: the first [`LOGGED_FAILURES`] one by one,
+/// then a summary by reason.
+///
+/// # Arguments
+///
+/// * `owner` - Whose notes were loaded, for the log
+/// * `failures` - The failures
+pub fn log_failures(owner: &str, failures: &[LoadFailure]) {
+    if failures.is_empty() {
+        return;
+    }
+    for failure in failures.iter().take(LOGGED_FAILURES) {
+        log::error!(
+            "Failed to load note {} of {} ({}): {}",
+            failure.note_id,
+            owner,
+            failure.reason.label(),
+            failure.error
+        );
+    }
+    if failures.len() > LOGGED_FAILURES {
+        let summary: Vec<String> = count_by_reason(failures)
+            .into_iter()
+            .map(|(reason, count)| format!("{} {}", count, reason.label().to_lowercase()))
+            .collect();
+        log::error!(
+            "{} notes of {} failed to load in total: {}",
+            failures.len(),
+            owner,
+            summary.join(", ")
+        );
+    }
+}
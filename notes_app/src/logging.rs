@@ -0,0 +1,277 @@
+//! # Logging Module
+//!
+//! Application-wide logger built on the `log` facade. Records are written to a
+//! size-rotated log file in the data directory and kept in a small in-memory
+//! buffer that backs the in-app log viewer.
+//!
+//! Log messages must never contain secrets or personal data (passwords, note
+//! content, usernames, hardware fingerprint values). Use user IDs or counts
+//! when a message needs to identify what was affected.
+
+use crate::secure_fs;
+use chrono::Local;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+
+/// Name of the active log file inside the logs directory.
+const LOG_FILE_NAME: &str = "secure_notes.log";
+/// Size at which the active log file is rotated (1 MB).
+const MAX_LOG_FILE_SIZE: u64 = 1024 * 1024;
+/// Number of rotated log files kept next to the active one.
+const MAX_ROTATED_FILES: usize = 3;
+/// Number of records kept in memory for the log viewer.
+const MAX_BUFFERED_RECORDS: usize = 1000;
+
+/// Log verbosity selectable in the settings dialog.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LogLevel {
+    /// Only errors
+    Error,
+    /// Errors and warnings
+    Warn,
+    /// Regular operational messages (default)
+    Info,
+    /// Detailed troubleshooting output
+    Debug,
+    /// Everything, including very chatty internals
+    Trace,
+}
+
+impl LogLevel {
+    /// All levels in ascending verbosity, for combo boxes.
+    pub const ALL: [LogLevel; 5] = [
+        LogLevel::Error,
+        LogLevel::Warn,
+        LogLevel::Info,
+        LogLevel::Debug,
+        LogLevel::Trace,
+    ];
+
+    /// Human-readable name of the level.
+    pub fn label(&self) -> &'static str {
+        match self {
+            LogLevel::Error => "Error",
+            LogLevel::Warn => "Warning",
+            LogLevel::Info => "Info",
+            LogLevel::Debug => "Debug",
+            LogLevel::Trace => "Trace",
+        }
+    }
+
+    /// Converts the level to the corresponding `log` filter.
+    pub fn to_filter(self) -> LevelFilter {
+        match self {
+            LogLevel::Error => LevelFilter::Error,
+            LogLevel::Warn => LevelFilter::Warn,
+            LogLevel::Info => LevelFilter::Info,
+            LogLevel::Debug => LevelFilter::Debug,
+            LogLevel::Trace => LevelFilter::Trace,
+        }
+    }
+}
+
+/// A single log record kept in memory for the log viewer.
+#[derive(Debug, Clone)]
+pub struct LogEntry {
+    /// Local time the record was emitted, formatted for display
+    pub timestamp: String,
+    /// Severity of the record
+    pub level: Level,
+    /// Module that emitted the record
+    pub target: String,
+    /// The formatted message
+    pub message: String,
+}
+
+/// Mutable state of the file logger.
+struct LoggerState {
+    /// Directory containing the active and rotated log files
+    log_dir: PathBuf,
+    /// Handle of the active log file, if it could be opened
+    file: Option<File>,
+    /// Current size of the active log file in bytes
+    file_size: u64,
+    /// Most recent records for the in-app viewer
+    buffer: VecDeque<LogEntry>,
+}
+
+/// Logger writing to a rotating file and an in-memory ring buffer.
+struct AppLogger {
+    state: Mutex<LoggerState>,
+}
+
+static LOGGER: OnceLock<AppLogger> = OnceLock::new();
+
+impl Log for AppLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        // Keep third-party crates (winit, wgpu, ...) out of the log unless tracing
+        if !record.target().starts_with(env!("CARGO_CRATE_NAME"))
+            && log::max_level() < LevelFilter::Trace
+        {
+            return;
+        }
+
+        let entry = LogEntry {
+            timestamp: Local::now().format("%Y-%m-%d %H:%M:%S%.3f").to_string(),
+            level: record.level(),
+            target: record.target().to_string(),
+            message: record.args().to_string(),
+        };
+        let line = format!(
+            "{} {:<5} [{}] {}\n",
+            entry.timestamp, entry.level, entry.target, entry.message
+        );
+
+        #[cfg(debug_assertions)]
+        eprint!("{}", line);
+
+        if let Ok(mut state) = self.state.lock() {
+            state.write_line(&line);
+
+            if state.buffer.len() >= MAX_BUFFERED_RECORDS {
+                state.buffer.pop_front();
+            }
+            state.buffer.push_back(entry);
+        }
+    }
+
+    fn flush(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            if let Some(file) = state.file.as_mut() {
+                let _ = file.flush();
+            }
+        }
+    }
+}
+
+impl LoggerState {
+    /// Appends a line to the active log file, rotating it first if needed.
+    fn write_line(&mut self, line: &str) {
+        if self.file_size + line.len() as u64 > MAX_LOG_FILE_SIZE {
+            self.rotate();
+        }
+
+        if let Some(file) = self.file.as_mut() {
+            if file.write_all(line.as_bytes()).is_ok() {
+                self.file_size += line.len() as u64;
+            }
+        }
+    }
+
+    /// Rotates `secure_notes.log` -> `.1` -> `.2` ... and opens a fresh file.
+    fn rotate(&mut self) {
+        self.file = None;
+
+        let base = self.log_dir.join(LOG_FILE_NAME);
+        let _ = fs::remove_file(rotated_path(&base, MAX_ROTATED_FILES));
+        for index in (1..MAX_ROTATED_FILES).rev() {
+            let _ = fs::rename(rotated_path(&base, index), rotated_path(&base, index + 1));
+        }
+        let _ = fs::rename(&base, rotated_path(&base, 1));
+
+        self.file = open_log_file(&base).ok();
+        self.file_size = 0;
+    }
+}
+
+/// Returns the path of the rotated log file with the given index.
+fn rotated_path(base: &std::path::Path, index: usize) -> PathBuf {
+    let mut name = base.as_os_str().to_owned();
+    name.push(format!(".{}", index));
+    PathBuf::from(name)
+}
+
+/// Opens (or creates) a log file for appending with owner-only permissions.
+fn open_log_file(path: &std::path::Path) -> std::io::Result<File> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    secure_fs::restrict_to_owner(path)?;
+
+    Ok(file)
+}
+
+/// Returns the directory where log files are written.
+pub fn log_dir() -> PathBuf {
+    let mut dir = dirs::config_dir().unwrap_or_else(|| PathBuf::from("."));
+    dir.push("secure_notes");
+    dir.push("logs");
+    dir
+}
+
+/// Installs the application logger.
+///
+/// Must be called once at startup before any log macros are used. The
+/// initial level is `Info`, or the value of the `RUST_LOG` environment
+/// variable if it names a valid level (e.g. `RUST_LOG=debug`). The level
+/// can later be changed with [`set_level`].
+///
+/// Failing to open the log file is not fatal: records are then only kept
+/// in memory (and printed to stderr in debug builds).
+pub fn init() {
+    let log_dir = log_dir();
+    let _ = fs::create_dir_all(&log_dir);
+
+    let path = log_dir.join(LOG_FILE_NAME);
+    let file = open_log_file(&path).ok();
+    let file_size = file
+        .as_ref()
+        .and_then(|f| f.metadata().ok())
+        .map(|m| m.len())
+        .unwrap_or(0);
+
+    let logger = LOGGER.get_or_init(|| AppLogger {
+        state: Mutex::new(LoggerState {
+            log_dir,
+            file,
+            file_size,
+            buffer: VecDeque::new(),
+        }),
+    });
+
+    if log::set_logger(logger).is_ok() {
+        let level = std::env::var("RUST_LOG")
+            .ok()
+            .and_then(|value| value.parse::<LevelFilter>().ok())
+            .unwrap_or(LevelFilter::Info);
+        log::set_max_level(level);
+    }
+}
+
+/// Changes the active log level.
+///
+/// # Arguments
+///
+/// * `level` - The new verbosity
+pub fn set_level(level: LogLevel) {
+    log::set_max_level(level.to_filter());
+}
+
+/// Returns a snapshot of the most recent log records, oldest first.
+pub fn recent_entries() -> Vec<LogEntry> {
+    LOGGER
+        .get()
+        .and_then(|logger| logger.state.lock().ok())
+        .map(|state| state.buffer.iter().cloned().collect())
+        .unwrap_or_default()
+}
+
+/// Clears the in-memory records shown in the log viewer.
+///
+/// The log file on disk is left untouched.
+pub fn clear_entries() {
+    if let Some(mut state) = LOGGER.get().and_then(|logger| logger.state.lock().ok()) {
+        state.buffer.clear();
+    }
+}
@@ -0,0 +1,121 @@
+//! # Login History Module
+//!
+//! Records the recent login attempts of each user so they can spot access
+//! attempts they didn't make in the security panel. Attempts with a wrong
+//! password happen before any key exists, so the history is stored
+//! unencrypted in `users/<id>/logins.json`. It holds only times, results
+//! and the outcome of the hardware check, never passwords.
+//!
+//! Attempts with an unknown username cannot be attributed to a user and
+//! are not recorded.
+
+use crate::crypto::HardwareCheck;
+use crate::trusted_time;
+use chrono::{DateTime, Utc};
+use chrono_tz::Europe::Zurich;
+use serde::{Deserialize, Serialize};
+
+/// Number of attempts kept per user, older ones are dropped.
+pub const MAX_LOGIN_RECORDS: usize = 20;
+
+/// Result of a login attempt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LoginOutcome {
+    /// The vault was unlocked
+    Success,
+    /// The password was wrong
+    WrongPassword,
+    /// The password was right, but unlocking failed, e.g. because critical
+    /// hardware components changed
+    Failed,
+}
+
+impl LoginOutcome {
+    /// Returns the name shown in the security panel.
+    pub fn label(&self) -> &'static str {
+        match self {
+            LoginOutcome::Success => "Success",
+            LoginOutcome::WrongPassword => "Wrong password",
+            LoginOutcome::Failed => "Failed",
+        }
+    }
+}
+
+/// One recorded login attempt.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoginRecord {
+    /// UTC timestamp of the attempt
+    pub time: DateTime<Utc>,
+    /// Result of the attempt
+    pub outcome: LoginOutcome,
+    /// Outcome of the hardware check, None if the attempt stopped before it
+    pub hardware: Option<HardwareCheck>,
+}
+
+impl LoginRecord {
+    /// Creates a record of an attempt made now.
+    ///
+    /// # Arguments
+    ///
+    /// * `outcome` - Result of the attempt
+    /// * `hardware` - Outcome of the hardware check, if it ran
+    ///
+    /// # Returns
+    ///
+    /// * `Self` - A new LoginRecord
+    pub fn new(outcome: LoginOutcome, hardware: Option<HardwareCheck>) -> Self {
+        Self {
+            time: trusted_time::now(),
+            outcome,
+            hardware,
+        }
+    }
+
+    /// Formats the time of the attempt for display in Swiss timezone.
+    ///
+    /// # Returns
+    ///
+    /// * `String` - Time as "DD.MM.YYYY HH:MM:SS"
+    pub fn format_time(&self) -> String {
+        self.time
+            .with_timezone(&Zurich)
+            .format("%d.%m.%Y %H:%M:%S")
+            .to_string()
+    }
+
+    /// Describes the hardware check for the security panel.
+    ///
+    /// # Returns
+    ///
+    /// * `&'static str` - A short description
+    pub fn hardware_label(&self) -> &'static str {
+        match self.hardware {
+            Some(HardwareCheck::Matched) => "Same computer",
+            Some(HardwareCheck::Changed) => "Hardware changed",
+            Some(HardwareCheck::Mismatch) => "Other computer",
+            Some(HardwareCheck::NotChecked) => "Not checked",
+            None => "—",
+        }
+    }
+}
+
+/// Counts the failed attempts since the last successful login.
+///
+/// # Arguments
+///
+/// * `history` - Recorded attempts, oldest first, including the current
+///   login as the last entry
+///
+/// # Returns
+///
+/// * `usize` - Failed attempts between the previous and current login
+pub fn failed_since_last_login(history: &[LoginRecord]) -> usize {
+    let Some((_, earlier)) = history.split_last() else {
+        return 0;
+    };
+    earlier
+        .iter()
+        .rev()
+        .take_while(|record| record.outcome != LoginOutcome::Success)
+        .count()
+}
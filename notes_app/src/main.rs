@@ -11,23 +11,73 @@
 //! ## Features
 //!
 //! - **Multi-user Support**: Individual user accounts with secure authentication
-//! - **End-to-End Encryption**: All notes are encrypted using ChaCha20Poly1305
+//! - **End-to-End Encryption**: All notes are encrypted using XChaCha20Poly1305 or AES-256-GCM
 //! - **Hardware Binding**: Cryptographic keys are bound to hardware fingerprints
 //! - **Secure Key Derivation**: Uses Argon2id for password-based key derivation
-//! - **Auto-save**: Automatic saving with configurable intervals
-//! - **Export Functionality**: Export notes to plain text files
+//! - **Two-Person Unlock**: Optionally a random key split into two Shamir shares, opened with the password and a second passphrase
+//! - **Auto-save**: Automatic saving with configurable intervals or manual-save-only mode
+//! - **Version History**: The last 50 versions of each note kept encrypted, compared line by line and restorable
+//! - **Link-Safe Renames**: `[[Title]]` links in other notes follow a renamed note, with a report
+//! - **Sidebar Sorting**: By modified or created time, title, or a manual order changed by dragging
+//! - **Pinned Order**: Pinned notes in their own draggable order, with an optional pinned limit
+//! - **Nested Notebooks**: Notebooks as folders in a sidebar tree, e.g. `Work/Clients`
+//! - **Tags**: Tags edited below the note title or written as `#tags`, filtering the sidebar
+//! - **Scheduled Review**: Notes resurfaced for re-reading at growing intervals, listed under Review when due
+//! - **Statistics**: Notes, words and last activity per tag and notebook
+//! - **Link Graph**: Notes and their `[[Title]]` links drawn as a graph, exported as DOT or JSON
+//! - **Note Icons**: An emoji before a note's title in the sidebar and header
+//! - **Quick Capture Inbox**: Thoughts captured with a shortcut or `--capture` into an Inbox notebook, filed later
+//! - **Command Line Notes**: `--new "Title"` and text files passed as arguments become notes after login
+//! - **Sample Notes**: Shortcuts, Markdown and security notes from templates for new accounts
+//! - **Search and Smart Filters**: Filter notes by text, `#tags` and date, and save searches in the sidebar
+//! - **Search Results**: Matches listed with highlighted excerpts, opening the note at the match, optionally including the trash
+//! - **Outline**: Side panel listing the Markdown headings of a note, jumping to the clicked one
+//! - **Markdown Preview**: Rendered preview beside the editor or instead of it, scrolled in step with the text, remembered per note
+//! - **Code Notes**: Syntax highlighting for code notes and fenced code blocks
+//! - **Read Aloud**: Text-to-speech of a note or the selection through the OS speech engine
+//! - **Clickable Links**: Web links in notes open in the browser on Ctrl+click
+//! - **Shared Vault**: Notes shared by users of the same computer, opened with each member's own password
+//! - **Note Locking**: A note open in one window is read-only in others until taken over
+//! - **Auto-Lock**: The vault locks when the screen locks or the computer suspends
+//! - **Capture Protection**: Optionally hides the window from screenshots and screen sharing
+//! - **Attachments**: Encrypted file attachments, exported alongside their note
+//! - **Attachment Preview**: Images, text and PDF first-page text shown in the note view, decrypted in memory
+//! - **Background Re-encryption**: Password and algorithm changes rewrite the vault with a progress dialog
+//! - **Storage Quotas**: Optional per-user quota, warnings at thresholds, no attachments beyond it
+//! - **Comments**: Timestamped comments on a note, kept apart from its content
+//! - **Secrets Notes**: Masked password entries with copy buttons, cleared from the clipboard after a delay
+//! - **Export Functionality**: Export notes to plain text, Markdown or HTML files
+//! - **Website Publishing**: Selected notes as a read-only static HTML site
+//! - **Encrypted Sharing**: A note copied as passphrase-protected text, pasted in by another user
+//! - **Send via Email**: A note opened as a new message in the mail app or saved as an `.eml` draft
+//! - **Machine Migration**: Guided export and import of all notes when moving to a new computer
+//! - **Settings Export**: Preferences alone in a passphrase-encrypted file, importable into any account
+//! - **Snapshot Viewer**: A migration bundle opened read-only and compared with the vault
+//! - **Keyboard Shortcuts**: Bindings that can be exported to and imported from a JSON file
 //! - **Time Display Options**: Relative ("2 hours ago") or absolute timestamps
 //! - **Security Auditing**: Built-in security monitoring and warnings
+//! - **Trusted Time**: Optional check of the system clock against a time server, used for timestamps
+//! - **Encryption Diagnostics**: Header, checksum and decryption status of a note's files for bug reports
+//! - **Unreadable Note Recovery**: Failures to decrypt grouped by reason, with restore from history or a migration bundle
+//! - **Health Check**: Directory structure, permissions, metadata, orphaned files, leftovers of deleted accounts and search index checked on demand, with safe repairs
+//! - **Integrity Scan**: Periodic background decryption of every stored file to find damage early
+//! - **Change Journal**: Encrypted create/update/delete log with vector clocks for incremental sync
+//! - **Large Notes**: Editor layout, word count and outline cached between frames
+//! - **Logging**: Rotating log file with configurable level and in-app log viewer
+//! - **Startup Profile**: Timed unlock steps in the log file and a debug window
+//! - **Event-Driven Repaints**: Frames only on input or due timers, coarser while minimized
+//! - **Crash Reports**: Sanitized local crash reports with a recovery dialog on next launch
 //!
 //! ## Security Architecture
 //!
 //! The application implements multiple layers of security:
 //!
 //! 1. **User Authentication**: Argon2id password hashing with individual salts
-//! 2. **Data Encryption**: ChaCha20Poly1305 authenticated encryption
+//! 2. **Data Encryption**: XChaCha20Poly1305 authenticated encryption
 //! 3. **Key Derivation**: Hardware-bound Argon2id key derivation (128MB memory, 3 iterations)
 //! 4. **Hardware Fingerprinting**: Device binding to prevent unauthorized access
 //! 5. **Secure Storage**: User-isolated encrypted storage with secure file permissions
+//! 6. **Secure Deletion**: Deleted data files are overwritten before removal (best effort on SSDs)
 //!
 //! ## Usage
 //!
@@ -46,35 +96,131 @@
 //! ```text
 //! ~/.config/secure_notes/          (Linux/macOS) or %APPDATA%/secure_notes/ (Windows)
 //! ├── users.json                   # User account database (encrypted hashes)
+//! ├── users.mac                    # HMAC-SHA256 integrity tag of users.json
+//! ├── secrets/                     # Fallback for keychain secrets when no keychain is available
+//! ├── logs/
+//! │   └── secure_notes.log         # Rotating application log (no secrets)
+//! ├── crash_reports/               # Sanitized crash reports (no secrets)
 //! └── users/
 //!     └── <user_id>/
 //!         ├── auth.hash            # Password verification hash
 //!         ├── security.meta        # Hardware fingerprint and security metadata
-//!         └── notes.enc            # Encrypted notes data
+//!         ├── split.key            # Wrapped key shares, with two-person unlock only
+//!         ├── settings.enc         # Encrypted user preferences
+//!         ├── journal.enc          # Encrypted log of note changes for sync
+//!         ├── search_index.enc     # Encrypted search index, rebuilt if missing
+//!         ├── logins.json          # Recent login attempts (times and results only)
+//!         ├── notes/
+//!         │   └── <note_id>.enc    # One encrypted file per note
+//!         ├── history/
+//!         │   └── <note_id>.enc    # Encrypted earlier versions of a note
+//!         ├── attachments/
+//!         │   └── <note_id>/       # One encrypted file per attachment
+//!         └── locks/
+//!             └── <note_id>.lock   # Note being edited by a running instance
 //! ```
 //!
 //! ## Dependencies
 //!
 //! - `eframe/egui`: Cross-platform GUI framework
+//! - `secure_core`: Shared key derivation and authenticated encryption
 //! - `chacha20poly1305`: Authenticated encryption
 //! - `argon2`: Password hashing and key derivation
 //! - `uuid`: Unique identifier generation
 //! - `chrono`: Date and time handling
 //! - `serde`: Serialization framework
+//! - `rayon`: Parallel note decryption
+//! - `keyring`: OS keychain access (Credential Manager, Keychain, Secret Service)
+//! - `egui_extras` (syntect): Syntax highlighting in the editor
+//! - `webbrowser`: Opening links in the default browser
+//! - `raw-window-handle`: Native window access for screen capture protection
+//! - `image`, `flate2`: Decoding image and PDF attachments for previews
 
 use eframe::egui;
 use egui::IconData;
 
 mod app;
+mod attachment_preview;
+mod attachments;
 mod auth;
+mod capture_protection;
+mod clipboard;
+mod crash;
 mod crypto;
+mod editor_ui;
+mod export;
+mod export_hook;
+mod health_check;
+mod health_check_ui;
+mod highlight;
+mod import;
+mod inbox;
+mod inbox_ui;
+mod integrity_scan;
+mod journal;
+mod keymap;
+mod keyring;
+mod launch_args;
+mod link_graph;
+mod link_graph_ui;
+mod links;
+mod load_failures;
+mod logging;
+mod login_history;
+mod markdown;
+mod migration;
+mod migration_ui;
 mod note;
+mod note_diagnostics;
+mod note_email;
+mod note_email_ui;
+mod note_history;
+mod note_history_ui;
+mod note_lock;
+mod note_order;
+mod note_share;
+mod note_share_ui;
+mod note_stats;
+mod note_stats_ui;
+mod notebook;
 mod notes_ui;
+mod outline;
+mod passphrase;
+mod preview_ui;
+mod profiling;
+mod recovery_ui;
+mod reencrypt;
+mod reencrypt_ui;
+mod rename_ui;
+mod review;
+mod review_ui;
+mod sample_notes;
+mod search;
+mod search_index;
+mod search_ui;
+mod secrets_ui;
+mod secure_fs;
+mod session_monitor;
+mod settings;
+mod settings_transfer;
 mod settings_ui;
+mod shared_vault;
+mod shared_vault_ui;
+mod shell_open;
+mod site_export;
+mod site_export_ui;
+mod snapshot_ui;
+mod speech;
 mod storage;
+mod storage_report;
+mod text_cache;
+mod trusted_time;
+mod two_person_ui;
 mod user;
+mod wiki_links;
 
 use app::NotesApp;
+use launch_args::LaunchRequest;
 
 /// Loads the application icon from embedded PNG data.
 ///
@@ -196,7 +342,13 @@ fn load_icon() -> IconData {
 /// cargo run
 /// ```
 ///
-/// Run with debug logging:
+/// Create a note, or one note per text file, after logging in:
+/// ```bash
+/// cargo run -- --new "Meeting notes"
+/// cargo run -- todo.md ideas.txt
+/// ```
+///
+/// Run with debug logging (the level can also be changed in the settings):
 /// ```bash
 /// RUST_LOG=debug cargo run
 /// ```
@@ -207,8 +359,17 @@ fn load_icon() -> IconData {
 /// - GUI rendering is optimized for 60 FPS
 /// - Memory usage scales with the number of notes
 /// - Startup time includes key derivation (5-10 seconds for security)
-
 fn main() -> Result<(), eframe::Error> {
+    // Startup timings count from here
+    profiling::init();
+
+    // Install the file logger before anything else can emit log records
+    logging::init();
+    log::info!("Starting Secure Notes {}", env!("CARGO_PKG_VERSION"));
+
+    // Write a local crash report if the application ever panics
+    crash::install_panic_hook();
+
     // Configure the native window options
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
@@ -235,7 +396,10 @@ fn main() -> Result<(), eframe::Error> {
             // App creation closure
             // Create and return the main application instance
             // The _cc parameter contains creation context (currently unused)
-            Ok(Box::new(NotesApp::new()))
+            let mut app = NotesApp::new();
+            // Notes to create from `--new "Title"` or files to open, see launch_args
+            app.launch_request = LaunchRequest::from_args(std::env::args_os().skip(1));
+            Ok(Box::new(app))
         }),
     )
 }
@@ -0,0 +1,346 @@
+//! # Markdown Module
+//!
+//! Parses note text for the Markdown preview: the text is split into
+//! blocks (headings, paragraphs, list items, quotes, fenced code blocks
+//! and rules), and the text of a block into bold, italic, code and link
+//! spans. It covers the conventions of the Markdown guide among the sample
+//! notes, not all of CommonMark; anything it doesn't recognize is shown as
+//! text.
+//!
+//! Line breaks inside a paragraph are kept, since notes are mostly written
+//! line by line rather than in reflowed paragraphs.
+
+use crate::highlight::FENCE;
+use crate::links;
+use crate::outline;
+
+/// A block of the preview.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Block {
+    /// An ATX heading, level 1 to 6
+    Heading { level: usize, text: String },
+    /// Consecutive text lines
+    Paragraph(String),
+    /// A bullet, numbered or task list item
+    ListItem {
+        /// Nesting depth, 0 for top-level items
+        depth: usize,
+        /// The marker of the item
+        marker: ListMarker,
+        /// Text of the item, including continuation lines
+        text: String,
+    },
+    /// Consecutive lines starting with `>`
+    Quote(String),
+    /// A fenced code block; an unclosed block extends to the end of the note
+    Code { language: String, code: String },
+    /// A line of three or more `-`, `*` or `_`
+    Rule,
+}
+
+/// Marker of a list item.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListMarker {
+    /// `-`, `*` or `+`
+    Bullet,
+    /// `1.` or `1)`
+    Number(u64),
+    /// `- [ ]` or `- [x]`, with whether it is checked
+    Task(bool),
+}
+
+/// A span of text inside a block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Inline<'a> {
+    /// Text without formatting
+    Text(&'a str),
+    /// `**bold**` or `__bold__`
+    Strong(&'a str),
+    /// `*italic*` or `_italic_`
+    Emphasis(&'a str),
+    /// `` `code` ``
+    Code(&'a str),
+    /// `[text](https://…)` or a bare web address
+    Link { text: &'a str, url: &'a str },
+}
+
+/// Splits a text into the blocks of the preview.
+///
+/// # Arguments
+///
+/// * `text` - The note content
+///
+/// # Returns
+///
+/// * `Vec<Block>` - The blocks, in order
+pub fn parse(text: &str) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    let mut quote: Vec<&str> = Vec::new();
+    let mut code: Option<(String, String)> = None;
+    let mut previous_blank = false;
+
+    for line in text.lines() {
+        if let Some((language, mut code_text)) = code.take() {
+            if line.trim_start().starts_with(FENCE) {
+                code_text.pop(); // Line break before the closing fence
+                blocks.push(Block::Code {
+                    language,
+                    code: code_text,
+                });
+            } else {
+                code_text.push_str(line);
+                code_text.push('\n');
+                code = Some((language, code_text));
+            }
+            continue;
+        }
+
+        let trimmed = line.trim();
+        if !trimmed.starts_with('>') {
+            flush(&mut blocks, &mut quote, Block::Quote);
+        }
+        if let Some(rest) = trimmed.strip_prefix('>') {
+            flush(&mut blocks, &mut paragraph, Block::Paragraph);
+            quote.push(rest.trim());
+            continue;
+        }
+
+        let follows_blank = previous_blank;
+        previous_blank = trimmed.is_empty();
+        if trimmed.is_empty() {
+            flush(&mut blocks, &mut paragraph, Block::Paragraph);
+            continue;
+        }
+
+        // Indented lines right after a list item continue it
+        if let Some(Block::ListItem { text, .. }) = blocks.last_mut() {
+            if !follows_blank
+                && paragraph.is_empty()
+                && line.starts_with([' ', '\t'])
+                && !trimmed.starts_with(FENCE)
+                && list_item(trimmed).is_none()
+            {
+                text.push('\n');
+                text.push_str(trimmed);
+                continue;
+            }
+        }
+
+        if let Some(info) = line.trim_start().strip_prefix(FENCE) {
+            flush(&mut blocks, &mut paragraph, Block::Paragraph);
+            code = Some((info.trim().to_string(), String::new()));
+        } else if let Some((level, title)) = outline::parse_heading(line) {
+            flush(&mut blocks, &mut paragraph, Block::Paragraph);
+            blocks.push(Block::Heading { level, text: title });
+        } else if is_rule(trimmed) {
+            flush(&mut blocks, &mut paragraph, Block::Paragraph);
+            blocks.push(Block::Rule);
+        } else if let Some((marker, item)) = list_item(trimmed) {
+            flush(&mut blocks, &mut paragraph, Block::Paragraph);
+            blocks.push(Block::ListItem {
+                depth: indent_width(line) / 2,
+                marker,
+                text: item.to_string(),
+            });
+        } else {
+            paragraph.push(trimmed);
+        }
+    }
+
+    if let Some((language, mut code_text)) = code {
+        code_text.pop();
+        blocks.push(Block::Code {
+            language,
+            code: code_text,
+        });
+    }
+    flush(&mut blocks, &mut paragraph, Block::Paragraph);
+    flush(&mut blocks, &mut quote, Block::Quote);
+    blocks
+}
+
+/// Turns collected lines into a block, if there are any.
+///
+/// # Arguments
+///
+/// * `blocks` - The blocks to append to
+/// * `lines` - The collected lines, emptied
+/// * `block` - Creates the block from the joined lines
+fn flush(blocks: &mut Vec<Block>, lines: &mut Vec<&str>, block: fn(String) -> Block) {
+    if !lines.is_empty() {
+        blocks.push(block(lines.join("\n")));
+        lines.clear();
+    }
+}
+
+/// Checks whether a trimmed line is a horizontal rule.
+fn is_rule(trimmed: &str) -> bool {
+    let mut marks = trimmed.chars().filter(|c| !c.is_whitespace());
+    let Some(first) = marks.next() else {
+        return false;
+    };
+    matches!(first, '-' | '*' | '_') && marks.clone().count() >= 2 && marks.all(|c| c == first)
+}
+
+/// Parses a trimmed line as a list item.
+///
+/// # Arguments
+///
+/// * `trimmed` - The line without surrounding whitespace
+///
+/// # Returns
+///
+/// * `Option<(ListMarker, &str)>` - Marker and text of the item, or None
+///   if the line is not a list item
+fn list_item(trimmed: &str) -> Option<(ListMarker, &str)> {
+    if let Some(rest) = ["- ", "* ", "+ "]
+        .iter()
+        .find_map(|bullet| trimmed.strip_prefix(bullet))
+    {
+        let rest = rest.trim_start();
+        for (box_text, checked) in [("[ ]", false), ("[x]", true), ("[X]", true)] {
+            if let Some(task) = rest.strip_prefix(box_text) {
+                if task.is_empty() || task.starts_with(' ') {
+                    return Some((ListMarker::Task(checked), task.trim_start()));
+                }
+            }
+        }
+        return Some((ListMarker::Bullet, rest));
+    }
+
+    let digits = trimmed.chars().take_while(char::is_ascii_digit).count();
+    if digits == 0 || digits > 9 {
+        return None;
+    }
+    let rest = trimmed[digits..]
+        .strip_prefix(". ")
+        .or_else(|| trimmed[digits..].strip_prefix(") "))?;
+    let number = trimmed[..digits].parse().ok()?;
+    Some((ListMarker::Number(number), rest.trim_start()))
+}
+
+/// Returns the width of a line's indentation, counting a tab as four spaces.
+fn indent_width(line: &str) -> usize {
+    line.chars()
+        .take_while(|c| matches!(c, ' ' | '\t'))
+        .map(|c| if c == '\t' { 4 } else { 1 })
+        .sum()
+}
+
+/// Splits the text of a block into formatted spans.
+///
+/// # Arguments
+///
+/// * `text` - A line of a block
+///
+/// # Returns
+///
+/// * `Vec<Inline>` - The spans, in order; unmatched markers stay text
+pub fn inlines(text: &str) -> Vec<Inline<'_>> {
+    let urls = links::find_urls(text);
+    let mut spans = Vec::new();
+    let mut text_start = 0;
+    let mut position = 0;
+
+    while position < text.len() {
+        let span = match urls.iter().find(|url| url.start == position) {
+            Some(url) => Some((
+                Inline::Link {
+                    text: &text[url.clone()],
+                    url: &text[url.clone()],
+                },
+                url.end,
+            )),
+            None => delimited(text, position),
+        };
+        match span {
+            Some((span, end)) => {
+                if text_start < position {
+                    spans.push(Inline::Text(&text[text_start..position]));
+                }
+                spans.push(span);
+                position = end;
+                text_start = end;
+            }
+            None => position += text[position..].chars().next().map_or(1, char::len_utf8),
+        }
+    }
+    if text_start < text.len() {
+        spans.push(Inline::Text(&text[text_start..]));
+    }
+
+    spans
+}
+
+/// Parses a formatted span starting at a position.
+///
+/// # Arguments
+///
+/// * `text` - The line
+/// * `position` - Byte position of a possible opening marker
+///
+/// # Returns
+///
+/// * `Option<(Inline, usize)>` - The span and the byte position after it,
+///   or None if no span starts there
+fn delimited(text: &str, position: usize) -> Option<(Inline<'_>, usize)> {
+    let rest = &text[position..];
+
+    if rest.starts_with('`') {
+        let (inner, len) = enclosed(rest, "`")?;
+        return Some((Inline::Code(inner), position + len));
+    }
+    if rest.starts_with("**") || rest.starts_with("__") {
+        let (inner, len) = enclosed(rest, &rest[..2])?;
+        return Some((Inline::Strong(inner), position + len));
+    }
+    if rest.starts_with(['*', '_']) {
+        // An underscore inside a word, as in snake_case, is not emphasis
+        if rest.starts_with('_') && text[..position].ends_with(char::is_alphanumeric) {
+            return None;
+        }
+        let (inner, len) = enclosed(rest, &rest[..1])?;
+        return Some((Inline::Emphasis(inner), position + len));
+    }
+    if rest.starts_with('[') {
+        let label_end = rest.find("](")?;
+        let url_start = label_end + 2;
+        let url_end = url_start + rest[url_start..].find(')')?;
+        let label = &rest[1..label_end];
+        let url = &rest[url_start..url_end];
+        // Only web links, so a click can't open local files or programs
+        let is_web_link = links::find_urls(url).first() == Some(&(0..url.len()));
+        if label.is_empty() || !is_web_link {
+            return None;
+        }
+        return Some((Inline::Link { text: label, url }, position + url_end + 1));
+    }
+
+    None
+}
+
+/// Finds the text between a marker at the start of `text` and the next one.
+///
+/// # Arguments
+///
+/// * `text` - Text starting with the opening marker
+/// * `marker` - The marker, e.g. "**"
+///
+/// # Returns
+///
+/// * `Option<(&str, usize)>` - The enclosed text and the length of the span
+///   including both markers, or None if the marker isn't closed or
+///   encloses nothing but whitespace
+fn enclosed<'a>(text: &'a str, marker: &str) -> Option<(&'a str, usize)> {
+    let inner_start = marker.len();
+    let inner_len = text[inner_start..].find(marker)?;
+    let inner = &text[inner_start..inner_start + inner_len];
+    if inner.is_empty()
+        || inner.starts_with(char::is_whitespace)
+        || inner.ends_with(char::is_whitespace)
+    {
+        return None;
+    }
+    Some((inner, inner_start + inner_len + marker.len()))
+}
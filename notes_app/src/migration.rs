@@ -0,0 +1,173 @@
+//! # Migration Module
+//!
+//! Portable migration bundles for moving an account to another computer.
+//!
+//! Notes on disk are encrypted with a key derived from the password *and*
+//! the hardware fingerprint, so copying the `secure_notes` directory to a
+//! new computer leaves the data unreadable there. A migration bundle
+//! instead holds the decrypted notes and settings encrypted with a key
+//! derived from a passphrase only. On the new computer the bundle is opened
+//! with the passphrase and the notes are saved again, which encrypts them
+//! with a key bound to the new hardware.
+//!
+//! ## Bundle Format
+//!
+//! The bundle is the JSON of `MigrationBundle` encrypted by the passphrase
+//! module with the magic bytes "SNMB", see `passphrase` for the layout.
+
+use crate::note::Note;
+use crate::passphrase::{self, SealedFormat};
+use crate::secure_fs;
+use crate::settings::{ExportHook, UserSettings};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+/// File extension of migration bundles.
+pub const BUNDLE_EXTENSION: &str = "snmig";
+
+/// Format of migration bundles.
+const BUNDLE_FORMAT: SealedFormat = SealedFormat {
+    magic: b"SNMB",
+    version: 1,
+    name: "migration bundle",
+};
+
+/// Everything that is moved to the new computer.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MigrationBundle {
+    /// Username of the exporting account
+    pub username: String,
+    /// UTC timestamp of the export
+    pub exported_at: DateTime<Utc>,
+    /// All notes of the account
+    pub notes: Vec<Note>,
+    /// The account's preferences
+    pub settings: UserSettings,
+}
+
+impl MigrationBundle {
+    /// Creates a bundle from the notes and settings of an account.
+    ///
+    /// # Arguments
+    ///
+    /// * `username` - Username of the exporting account
+    /// * `notes` - Notes to include
+    /// * `settings` - Preferences to include
+    ///
+    /// # Returns
+    ///
+    /// * `Self` - A new bundle stamped with the current time
+    pub fn new<'a>(
+        username: &str,
+        notes: impl IntoIterator<Item = &'a Note>,
+        settings: &UserSettings,
+    ) -> Self {
+        Self {
+            username: username.to_string(),
+            exported_at: Utc::now(),
+            notes: notes.into_iter().cloned().collect(),
+            // The export hook is set up again on the new computer
+            settings: UserSettings {
+                export_hook: ExportHook::default(),
+                ..settings.clone()
+            },
+        }
+    }
+}
+
+/// Encrypts a bundle with a passphrase and writes it to a file.
+///
+/// # Arguments
+///
+/// * `path` - Destination file
+/// * `bundle` - The bundle to write
+/// * `passphrase` - Passphrase protecting the bundle
+///
+/// # Returns
+///
+/// * `Result<()>` - Ok if the bundle was written, Err otherwise
+pub fn write_bundle(path: &Path, bundle: &MigrationBundle, passphrase: &str) -> Result<()> {
+    let plaintext = serde_json::to_vec(bundle)?;
+    let data = passphrase::seal(&BUNDLE_FORMAT, &plaintext, passphrase)?;
+    secure_fs::write_private_file(path, data)?;
+    Ok(())
+}
+
+/// Reads and decrypts a bundle written by `write_bundle`.
+///
+/// # Arguments
+///
+/// * `path` - The bundle file
+/// * `passphrase` - Passphrase the bundle was written with
+///
+/// # Returns
+///
+/// * `Result<MigrationBundle>` - The decrypted bundle, or error if the file
+///   is not a bundle, the passphrase is wrong or the file was modified
+pub fn read_bundle(path: &Path, passphrase: &str) -> Result<MigrationBundle> {
+    let data = fs::read(path)?;
+    let plaintext = passphrase::open(&BUNDLE_FORMAT, &data, passphrase)?;
+    Ok(serde_json::from_slice(&plaintext)?)
+}
+
+/// Checks that a written bundle opens and contains every expected note.
+///
+/// # Arguments
+///
+/// * `path` - The bundle file
+/// * `passphrase` - Passphrase the bundle was written with
+/// * `expected` - The bundle that was written
+///
+/// # Returns
+///
+/// * `Result<MigrationBundle>` - The bundle as read back from disk, or
+///   error if it cannot be opened or notes are missing
+pub fn verify_bundle(
+    path: &Path,
+    passphrase: &str,
+    expected: &MigrationBundle,
+) -> Result<MigrationBundle> {
+    let bundle = read_bundle(path, passphrase)?;
+    let ids: HashSet<&str> = bundle.notes.iter().map(|n| n.id.as_str()).collect();
+    let missing = expected
+        .notes
+        .iter()
+        .filter(|n| !ids.contains(n.id.as_str()))
+        .count();
+
+    if missing > 0 {
+        return Err(anyhow!("{} note(s) are missing from the bundle", missing));
+    }
+    Ok(bundle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::KdfParams;
+    use crate::passphrase::tests::crafted;
+
+    #[test]
+    fn read_bundle_rejects_oversized_kdf_params() {
+        let params = KdfParams {
+            memory_cost_kib: u32::MAX,
+            iterations: u32::MAX,
+            ..KdfParams::CURRENT
+        };
+        let path = std::env::temp_dir().join(format!(
+            "oversized-{}.{}",
+            std::process::id(),
+            BUNDLE_EXTENSION
+        ));
+        fs::write(&path, crafted(&BUNDLE_FORMAT, params)).unwrap();
+
+        let result = read_bundle(&path, "passphrase");
+        fs::remove_file(&path).unwrap();
+        let error = result.expect_err("bundle should be rejected").to_string();
+        assert!(error.starts_with("Unsupported key derivation parameters"));
+    }
+}
@@ -0,0 +1,879 @@
+//! # Migration UI Module
+//!
+//! Dialogs for moving notes between storage locations:
+//!
+//! - Machine migration wizard: on the old computer it exports a
+//!   passphrase-protected migration bundle and verifies it by reading it
+//!   back; on the new computer it imports the bundle and saves the notes
+//!   again, which binds them to the new hardware. Writing, verifying and
+//!   opening the bundle derive the passphrase key, so they run in a
+//!   [`PassphraseTask`] while the wizard shows their progress
+//! - Legacy migration dialog: previews the notes found in the storage
+//!   format of older versions, lets the user confirm or skip the migration
+//!   and reports the result of every note
+
+use crate::app::NotesApp;
+use crate::import::{DuplicateNote, DuplicatePolicy, ImportJob};
+use crate::migration::{self, MigrationBundle, BUNDLE_EXTENSION};
+use crate::passphrase::{PassphraseTask, MIN_PASSPHRASE_LEN};
+use crate::secure_fs;
+use crate::settings::UserSettings;
+use crate::storage::LegacyNoteEntry;
+use eframe::egui;
+use std::path::PathBuf;
+
+/// Current page of the migration wizard.
+#[derive(Default)]
+pub enum MigrationStep {
+    /// Choose between exporting and importing
+    #[default]
+    Start,
+    /// Enter a passphrase and export the bundle
+    Export,
+    /// The bundle is being written and read back in the background
+    Exporting {
+        /// Yields the location of the bundle and the number of notes read
+        /// back
+        task: PassphraseTask<(PathBuf, usize)>,
+    },
+    /// The bundle was written and verified
+    Exported {
+        /// Location of the bundle
+        path: PathBuf,
+        /// Number of notes found when reading the bundle back
+        note_count: usize,
+    },
+    /// Select a bundle and enter its passphrase
+    Import,
+    /// The bundle is being decrypted in the background
+    Opening {
+        /// Yields the location of the bundle and its content
+        task: PassphraseTask<(PathBuf, MigrationBundle)>,
+    },
+    /// The bundle was opened; waiting for confirmation
+    ImportPreview {
+        /// Location of the bundle
+        path: PathBuf,
+        /// The decrypted bundle
+        bundle: Box<MigrationBundle>,
+    },
+    /// The notes are being imported in the background, see `NotesApp::import_job`
+    Importing {
+        /// Location of the bundle
+        path: PathBuf,
+        /// Settings to apply once the import completes, if requested
+        settings: Option<Box<UserSettings>>,
+    },
+    /// The notes were imported and re-encrypted on this computer
+    Imported {
+        /// Location of the bundle, cleared once it was deleted
+        path: Option<PathBuf>,
+        /// Number of notes added or updated
+        imported: usize,
+        /// Number of notes skipped because the local copy is newer
+        skipped: usize,
+        /// Notes whose content already existed on this computer
+        duplicates: Vec<DuplicateNote>,
+        /// Notes that could not be imported, with the reason
+        errors: Vec<String>,
+        /// Whether the import was cancelled before all notes were processed
+        cancelled: bool,
+    },
+}
+
+/// State of the migration wizard dialog.
+pub struct MigrationWizard {
+    /// Current page
+    pub step: MigrationStep,
+    /// Bundle passphrase input
+    pub passphrase: String,
+    /// Passphrase confirmation input (export only)
+    pub confirm_passphrase: String,
+    /// Whether to apply the settings stored in the bundle
+    pub import_settings: bool,
+    /// What to do with imported notes identical to existing ones
+    pub duplicate_policy: DuplicatePolicy,
+    /// Error of the last action on the current page
+    pub error: Option<String>,
+}
+
+impl Default for MigrationWizard {
+    fn default() -> Self {
+        Self {
+            step: MigrationStep::Start,
+            passphrase: String::new(),
+            confirm_passphrase: String::new(),
+            import_settings: true,
+            duplicate_policy: DuplicatePolicy::default(),
+            error: None,
+        }
+    }
+}
+
+impl MigrationWizard {
+    /// Returns to the first page and forgets entered passphrases.
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// State of the legacy migration dialog.
+pub enum LegacyMigration {
+    /// Entries found in the legacy file, waiting for confirmation
+    Preview(Vec<LegacyNoteEntry>),
+    /// The legacy file exists but could not be decrypted or parsed
+    Unreadable(String),
+    /// Per-note results of the finished migration
+    Report(Vec<LegacyNoteEntry>),
+}
+
+/// Button pressed in the wizard, handled after the window closure.
+enum WizardAction {
+    GoTo(MigrationStep),
+    Export,
+    OpenBundle,
+    ViewSnapshot,
+    Import,
+    CancelImport,
+    DeleteBundle,
+    OpenFolder(PathBuf),
+    Close,
+}
+
+impl NotesApp {
+    /// Renders the machine migration wizard.
+    ///
+    /// Walks through the steps of moving an account:
+    /// - Old computer: export the notes and settings to a bundle protected
+    ///   by a passphrase, then verify the bundle by reading it back
+    /// - New computer: open the bundle, preview its contents and import it,
+    ///   which re-encrypts the notes with this computer's hardware-bound key
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The egui context for rendering
+    pub fn render_migration_wizard(&mut self, ctx: &egui::Context) {
+        if !self.show_migration_wizard {
+            return;
+        }
+        self.poll_migration_task();
+
+        let mut action = None;
+        let notes_loading = self.note_loader.is_some();
+        let import_job = self.import_job.as_ref();
+        let wizard = &mut self.migration_wizard;
+
+        egui::Window::new("Move to Another Computer")
+            .open(&mut self.show_migration_wizard)
+            .collapsible(false)
+            .resizable(false)
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                match &wizard.step {
+                    MigrationStep::Start => {
+                        ui.label(
+                            "Notes are encrypted with a key bound to this computer's hardware. \
+                             Copying the data folder to a new computer does not work; \
+                             move your notes with a migration bundle instead.",
+                        );
+                        ui.add_space(10.0);
+
+                        ui.strong("1. On the old computer");
+                        ui.label("Export a bundle protected by a passphrase.");
+                        if ui.button("Export Notes…").clicked() {
+                            action = Some(WizardAction::GoTo(MigrationStep::Export));
+                        }
+                        ui.add_space(10.0);
+
+                        ui.strong("2. On the new computer");
+                        ui.label("Create an account or log in, then import the bundle.");
+                        if ui.button("Import Bundle…").clicked() {
+                            action = Some(WizardAction::GoTo(MigrationStep::Import));
+                        }
+                    }
+                    MigrationStep::Export => {
+                        ui.label(format!(
+                            "Choose a passphrase for the bundle (at least {} characters). \
+                             You need it again on the new computer.",
+                            MIN_PASSPHRASE_LEN
+                        ));
+                        ui.add_space(5.0);
+
+                        ui.label("Passphrase:");
+                        ui.add(egui::TextEdit::singleline(&mut wizard.passphrase).password(true));
+                        ui.label("Confirm passphrase:");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut wizard.confirm_passphrase)
+                                .password(true),
+                        );
+
+                        if notes_loading {
+                            ui.colored_label(
+                                egui::Color32::YELLOW,
+                                "Wait until all notes are loaded before exporting.",
+                            );
+                        }
+
+                        ui.add_space(5.0);
+                        ui.horizontal(|ui| {
+                            if ui.button("Back").clicked() {
+                                action = Some(WizardAction::GoTo(MigrationStep::Start));
+                            }
+                            if ui
+                                .add_enabled(!notes_loading, egui::Button::new("Export…"))
+                                .clicked()
+                            {
+                                action = Some(WizardAction::Export);
+                            }
+                        });
+                    }
+                    MigrationStep::Exporting { .. } => {
+                        ui.horizontal(|ui| {
+                            ui.spinner();
+                            ui.label("Writing the bundle and reading it back…");
+                        });
+                        ui.small("Deriving the passphrase key takes a few seconds, twice.");
+                    }
+                    MigrationStep::Opening { .. } => {
+                        ui.horizontal(|ui| {
+                            ui.spinner();
+                            ui.label("Opening the bundle…");
+                        });
+                        ui.small("Deriving the passphrase key takes a few seconds.");
+                    }
+                    MigrationStep::Exported { path, note_count } => {
+                        ui.colored_label(
+                            egui::Color32::GREEN,
+                            format!("✓ Bundle verified: {} note(s)", note_count),
+                        );
+                        ui.horizontal(|ui| {
+                            ui.small(format!("Saved to {}", path.display()));
+                            if let Some(folder) = path.parent() {
+                                if ui.small_button("📂 Open folder").clicked() {
+                                    action = Some(WizardAction::OpenFolder(folder.to_path_buf()));
+                                }
+                            }
+                        });
+                        ui.add_space(10.0);
+
+                        ui.label("Next steps:");
+                        ui.label(
+                            "1. Copy the bundle to the new computer (USB drive, network share).",
+                        );
+                        ui.label(
+                            "2. Install Secure Notes there, then create an account or log in.",
+                        );
+                        ui.label("3. Open Settings → Move to Another Computer → Import Bundle.");
+                        ui.label("4. Delete the bundle once the import succeeded.");
+
+                        ui.add_space(5.0);
+                        if ui.button("Done").clicked() {
+                            action = Some(WizardAction::Close);
+                        }
+                    }
+                    MigrationStep::Import => {
+                        ui.label("Select the bundle exported on the old computer.");
+                        ui.label("Passphrase:");
+                        ui.add(egui::TextEdit::singleline(&mut wizard.passphrase).password(true));
+
+                        if notes_loading {
+                            ui.colored_label(
+                                egui::Color32::YELLOW,
+                                "Wait until all notes are loaded before importing.",
+                            );
+                        }
+
+                        ui.add_space(5.0);
+                        ui.horizontal(|ui| {
+                            if ui.button("Back").clicked() {
+                                action = Some(WizardAction::GoTo(MigrationStep::Start));
+                            }
+                            if ui
+                                .add_enabled(
+                                    !notes_loading && !wizard.passphrase.is_empty(),
+                                    egui::Button::new("Open Bundle…"),
+                                )
+                                .clicked()
+                            {
+                                action = Some(WizardAction::OpenBundle);
+                            }
+                        });
+                    }
+                    MigrationStep::ImportPreview { bundle, .. } => {
+                        ui.label(format!("Account: {}", bundle.username));
+                        ui.label(format!(
+                            "Exported: {}",
+                            bundle.exported_at.format("%d.%m.%Y %H:%M")
+                        ));
+                        ui.label(format!("Notes: {}", bundle.notes.len()));
+                        ui.checkbox(&mut wizard.import_settings, "Also import settings");
+                        ui.horizontal(|ui| {
+                            ui.label("Identical notes:");
+                            egui::ComboBox::from_id_salt("migration_duplicate_policy")
+                                .selected_text(wizard.duplicate_policy.label())
+                                .show_ui(ui, |ui| {
+                                    for policy in DuplicatePolicy::ALL {
+                                        ui.selectable_value(
+                                            &mut wizard.duplicate_policy,
+                                            policy,
+                                            policy.label(),
+                                        );
+                                    }
+                                });
+                        });
+                        ui.small(
+                            "Notes that already exist here are only replaced if the bundle \
+                             has a newer version.",
+                        );
+
+                        ui.add_space(5.0);
+                        ui.horizontal(|ui| {
+                            if ui.button("Back").clicked() {
+                                action = Some(WizardAction::GoTo(MigrationStep::Import));
+                            }
+                            if ui.button("Import").clicked() {
+                                action = Some(WizardAction::Import);
+                            }
+                            if ui
+                                .button("View Notes…")
+                                .on_hover_text("Browse the bundle read-only without importing")
+                                .clicked()
+                            {
+                                action = Some(WizardAction::ViewSnapshot);
+                            }
+                        });
+                    }
+                    MigrationStep::Importing { .. } => {
+                        if let Some(job) = import_job {
+                            ui.label(format!(
+                                "Importing notes: {} of {}",
+                                job.processed, job.total
+                            ));
+                            ui.add(
+                                egui::ProgressBar::new(
+                                    job.processed as f32 / job.total.max(1) as f32,
+                                )
+                                .show_percentage(),
+                            );
+                            if !job.errors.is_empty() {
+                                ui.colored_label(
+                                    egui::Color32::YELLOW,
+                                    format!("Errors so far: {}", job.errors.len()),
+                                );
+                            }
+
+                            ui.add_space(5.0);
+                            if job.is_cancelled() {
+                                ui.label("Cancelling…");
+                            } else if ui
+                                .button("Cancel")
+                                .on_hover_text("Stop importing; notes imported so far are kept")
+                                .clicked()
+                            {
+                                action = Some(WizardAction::CancelImport);
+                            }
+                        }
+                    }
+                    MigrationStep::Imported {
+                        path,
+                        imported,
+                        skipped,
+                        duplicates,
+                        errors,
+                        cancelled,
+                    } => {
+                        if *cancelled {
+                            ui.colored_label(
+                                egui::Color32::YELLOW,
+                                format!("Import cancelled after {} note(s)", imported),
+                            );
+                            ui.label("The notes imported so far were kept.");
+                        } else {
+                            ui.colored_label(
+                                egui::Color32::GREEN,
+                                format!("✓ Imported {} note(s)", imported),
+                            );
+                        }
+                        if *skipped > 0 {
+                            ui.label(format!(
+                                "{} note(s) skipped, the copy on this computer is newer.",
+                                skipped
+                            ));
+                        }
+                        if !duplicates.is_empty() {
+                            ui.collapsing(
+                                format!("{} duplicate note(s) found", duplicates.len()),
+                                |ui| {
+                                    egui::ScrollArea::vertical()
+                                        .max_height(160.0)
+                                        .show(ui, |ui| {
+                                            for duplicate in duplicates {
+                                                ui.label(format!(
+                                                    "{} = {}",
+                                                    duplicate.title, duplicate.duplicate_of
+                                                ));
+                                            }
+                                        });
+                                },
+                            );
+                        }
+                        if !errors.is_empty() {
+                            ui.collapsing(
+                                format!("{} note(s) could not be imported", errors.len()),
+                                |ui| {
+                                    egui::ScrollArea::vertical()
+                                        .max_height(160.0)
+                                        .show(ui, |ui| {
+                                            for error in errors {
+                                                ui.colored_label(egui::Color32::RED, error);
+                                            }
+                                        });
+                                },
+                            );
+                        }
+                        ui.label("The notes are now encrypted with a key bound to this computer.");
+
+                        ui.add_space(5.0);
+                        if let Some(path) = path {
+                            ui.label("The bundle is no longer needed and should be deleted:");
+                            ui.small(path.display().to_string());
+                            if ui.button("Securely Delete Bundle").clicked() {
+                                action = Some(WizardAction::DeleteBundle);
+                            }
+                        }
+
+                        if ui.button("Done").clicked() {
+                            action = Some(WizardAction::Close);
+                        }
+                    }
+                }
+
+                if let Some(error) = &wizard.error {
+                    ui.add_space(5.0);
+                    ui.colored_label(egui::Color32::RED, error);
+                }
+            });
+
+        // Handle button actions outside the window closure
+        match action {
+            Some(WizardAction::GoTo(step)) => {
+                self.migration_wizard.reset();
+                self.migration_wizard.step = step;
+            }
+            Some(WizardAction::Export) => self.export_migration_bundle(ctx),
+            Some(WizardAction::OpenBundle) => self.open_migration_bundle(ctx),
+            Some(WizardAction::ViewSnapshot) => {
+                if let MigrationStep::ImportPreview { path, bundle } = &self.migration_wizard.step {
+                    let (path, bundle) = (path.clone(), (**bundle).clone());
+                    self.view_snapshot(path, bundle);
+                }
+            }
+            Some(WizardAction::Import) => self.import_migration_bundle(),
+            Some(WizardAction::CancelImport) => {
+                if let Some(ref job) = self.import_job {
+                    job.cancel();
+                }
+            }
+            Some(WizardAction::DeleteBundle) => self.delete_migration_bundle(),
+            Some(WizardAction::OpenFolder(folder)) => self.open_folder(&folder),
+            Some(WizardAction::Close) => self.show_migration_wizard = false,
+            None => {}
+        }
+
+        // The progress and summary of a running export or import must stay
+        // reachable
+        if self.import_job.is_some()
+            || matches!(self.migration_wizard.step, MigrationStep::Exporting { .. })
+        {
+            self.show_migration_wizard = true;
+        }
+        if !self.show_migration_wizard {
+            self.migration_wizard.reset();
+        }
+    }
+
+    /// Starts writing the migration bundle and verifying it by reading it
+    /// back.
+    fn export_migration_bundle(&mut self, ctx: &egui::Context) {
+        let wizard = &mut self.migration_wizard;
+        if wizard.passphrase.len() < MIN_PASSPHRASE_LEN {
+            wizard.error = Some(format!(
+                "Passphrase must be at least {} characters long",
+                MIN_PASSPHRASE_LEN
+            ));
+            return;
+        }
+        if wizard.passphrase != wizard.confirm_passphrase {
+            wizard.error = Some("Passphrases do not match".to_string());
+            return;
+        }
+
+        let Some(ref user) = self.current_user else {
+            return;
+        };
+
+        let Some(path) = rfd::FileDialog::new()
+            .set_title("Export Migration Bundle")
+            .set_file_name(format!(
+                "secure_notes_{}.{}",
+                user.username, BUNDLE_EXTENSION
+            ))
+            .add_filter("Migration bundles", &[BUNDLE_EXTENSION])
+            .save_file()
+        else {
+            return;
+        };
+
+        let bundle = MigrationBundle::new(&user.username, self.notes.values(), &self.settings);
+        let passphrase = wizard.passphrase.clone();
+        wizard.error = None;
+        wizard.step = MigrationStep::Exporting {
+            task: PassphraseTask::spawn(ctx, move || {
+                migration::write_bundle(&path, &bundle, &passphrase)?;
+                let written = migration::verify_bundle(&path, &passphrase, &bundle)?;
+                Ok((path, written.notes.len()))
+            }),
+        };
+    }
+
+    /// Lets the user pick a bundle and starts decrypting it for the
+    /// preview page.
+    fn open_migration_bundle(&mut self, ctx: &egui::Context) {
+        let Some(path) = rfd::FileDialog::new()
+            .set_title("Open Migration Bundle")
+            .add_filter("Migration bundles", &[BUNDLE_EXTENSION])
+            .add_filter("All files", &["*"])
+            .pick_file()
+        else {
+            return;
+        };
+
+        let wizard = &mut self.migration_wizard;
+        let passphrase = wizard.passphrase.clone();
+        wizard.error = None;
+        wizard.step = MigrationStep::Opening {
+            task: PassphraseTask::spawn(ctx, move || {
+                let bundle = migration::read_bundle(&path, &passphrase)?;
+                Ok((path, bundle))
+            }),
+        };
+    }
+
+    /// Moves the wizard on once a bundle was exported or opened.
+    ///
+    /// On error the wizard returns to the page the action was started
+    /// from, with the passphrases kept and the error shown.
+    fn poll_migration_task(&mut self) {
+        let wizard = &mut self.migration_wizard;
+        match &wizard.step {
+            MigrationStep::Exporting { task } => match task.poll() {
+                Some(Ok((path, note_count))) => {
+                    log::info!("Exported migration bundle with {} notes", note_count);
+                    wizard.reset();
+                    wizard.step = MigrationStep::Exported {
+                        path: path.clone(),
+                        note_count,
+                    };
+                    self.run_export_hook(&path);
+                }
+                Some(Err(e)) => {
+                    log::error!("Migration bundle export failed: {}", e);
+                    wizard.step = MigrationStep::Export;
+                    wizard.error = Some(format!("Export failed: {}", e));
+                }
+                None => {}
+            },
+            MigrationStep::Opening { task } => match task.poll() {
+                Some(Ok((path, bundle))) => {
+                    wizard.reset();
+                    wizard.step = MigrationStep::ImportPreview {
+                        path,
+                        bundle: Box::new(bundle),
+                    };
+                }
+                Some(Err(e)) => {
+                    log::warn!("Failed to open migration bundle: {}", e);
+                    wizard.step = MigrationStep::Import;
+                    wizard.error = Some(e.to_string());
+                }
+                None => {}
+            },
+            _ => {}
+        }
+    }
+
+    /// Starts importing the previewed bundle in the background.
+    ///
+    /// The notes are merged into the vault and saved one by one, which
+    /// encrypts them with the key of this computer and completes the
+    /// hardware re-binding. See `poll_migration_import` for the completion.
+    fn import_migration_bundle(&mut self) {
+        let (Some(crypto_manager), Some(user)) = (&self.crypto_manager, &self.current_user) else {
+            return;
+        };
+        let step = std::mem::take(&mut self.migration_wizard.step);
+        let MigrationStep::ImportPreview { path, bundle } = step else {
+            self.migration_wizard.step = step;
+            return;
+        };
+
+        let bundle = *bundle;
+        self.import_job = Some(ImportJob::spawn(
+            &user.id,
+            crypto_manager.clone(),
+            bundle.notes,
+            &self.notes,
+            self.migration_wizard.duplicate_policy,
+        ));
+
+        let settings = self
+            .migration_wizard
+            .import_settings
+            .then(|| Box::new(bundle.settings));
+        self.migration_wizard.reset();
+        self.migration_wizard.step = MigrationStep::Importing { path, settings };
+    }
+
+    /// Moves notes written by the running import into the note list.
+    ///
+    /// When the import is done, the bundle's settings are applied (unless
+    /// the import was cancelled) and the wizard shows the summary.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The egui context, repainted while the import is running
+    pub fn poll_migration_import(&mut self, ctx: &egui::Context) {
+        let Some(ref mut job) = self.import_job else {
+            return;
+        };
+
+        for note in job.poll() {
+            self.search_index.note_changed(&note);
+            self.notes.insert(note.id.clone(), note);
+        }
+        if !job.finished {
+            ctx.request_repaint();
+            return;
+        }
+
+        let Some(job) = self.import_job.take() else {
+            return;
+        };
+        let cancelled = job.is_cancelled();
+        log::info!(
+            "Imported migration bundle: {} notes imported, {} skipped, {} duplicates, {} failed{}",
+            job.imported,
+            job.skipped,
+            job.duplicates.len(),
+            job.errors.len(),
+            if cancelled { " (cancelled)" } else { "" }
+        );
+
+        let step = std::mem::take(&mut self.migration_wizard.step);
+        let path = match step {
+            MigrationStep::Importing { path, settings } => {
+                if let Some(settings) = settings.filter(|_| !cancelled) {
+                    self.apply_settings(*settings);
+                }
+                Some(path)
+            }
+            _ => None,
+        };
+
+        self.migration_wizard.reset();
+        self.migration_wizard.step = MigrationStep::Imported {
+            path,
+            imported: job.imported,
+            skipped: job.skipped,
+            duplicates: job.duplicates,
+            errors: job.errors,
+            cancelled,
+        };
+    }
+
+    /// Securely deletes the imported bundle file.
+    fn delete_migration_bundle(&mut self) {
+        let wizard = &mut self.migration_wizard;
+        if let MigrationStep::Imported { path, .. } = &mut wizard.step {
+            if let Some(bundle_path) = path.take() {
+                if let Err(e) = secure_fs::secure_remove_file(&bundle_path) {
+                    log::error!("Failed to delete migration bundle: {}", e);
+                    wizard.error = Some(format!("Failed to delete bundle: {}", e));
+                    *path = Some(bundle_path);
+                }
+            }
+        }
+    }
+
+    /// Renders the legacy migration dialog.
+    ///
+    /// Shown after login when notes in the storage format of older versions
+    /// are found. Previews which notes can be migrated and which entries are
+    /// damaged, then reports the result of every note. Skipping keeps the
+    /// legacy file so the migration is offered again on the next login;
+    /// declining moves it aside as a backup.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The egui context for rendering
+    pub fn render_legacy_migration_dialog(&mut self, ctx: &egui::Context) {
+        let Some(ref state) = self.legacy_migration else {
+            return;
+        };
+
+        let mut migrate = false;
+        let mut archive = false;
+        let mut close = false;
+
+        egui::Window::new("Migrate Notes from Older Version")
+            .collapsible(false)
+            .resizable(false)
+            .default_width(420.0)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| match state {
+                LegacyMigration::Preview(entries) => {
+                    let readable = entries.iter().filter(|e| e.result.is_ok()).count();
+                    ui.label(
+                        "Notes saved by an older version of Secure Notes were found. \
+                             They can be moved into your account.",
+                    );
+                    ui.label(format!("{} note(s) can be migrated.", readable));
+                    if readable < entries.len() {
+                        ui.colored_label(
+                            egui::Color32::YELLOW,
+                            format!(
+                                "Damaged entries that will be skipped: {}",
+                                entries.len() - readable
+                            ),
+                        );
+                    }
+
+                    render_legacy_entries(ui, entries);
+
+                    ui.small("The original file is kept as notes.enc.backup.");
+                    ui.horizontal(|ui| {
+                        if ui
+                            .add_enabled(readable > 0, egui::Button::new("Migrate"))
+                            .clicked()
+                        {
+                            migrate = true;
+                        }
+                        if ui
+                            .button("Not Now")
+                            .on_hover_text("Ask again on the next login")
+                            .clicked()
+                        {
+                            close = true;
+                        }
+                        if ui
+                            .button("Don't Migrate")
+                            .on_hover_text("Keep the file as a backup without migrating")
+                            .clicked()
+                        {
+                            archive = true;
+                        }
+                    });
+                }
+                LegacyMigration::Unreadable(error) => {
+                    ui.label(
+                        "Notes saved by an older version of Secure Notes were found, \
+                             but the file could not be read.",
+                    );
+                    ui.colored_label(egui::Color32::RED, error);
+                    ui.horizontal(|ui| {
+                        if ui.button("Not Now").clicked() {
+                            close = true;
+                        }
+                        if ui
+                            .button("Keep as Backup")
+                            .on_hover_text("Rename the file to notes.enc.backup")
+                            .clicked()
+                        {
+                            archive = true;
+                        }
+                    });
+                }
+                LegacyMigration::Report(entries) => {
+                    let migrated = entries.iter().filter(|e| e.result.is_ok()).count();
+                    ui.label(format!(
+                        "Migrated {} of {} note(s).",
+                        migrated,
+                        entries.len()
+                    ));
+
+                    render_legacy_entries(ui, entries);
+
+                    if migrated < entries.len() {
+                        ui.small("Failed entries remain in notes.enc.backup.");
+                    }
+                    if ui.button("Close").clicked() {
+                        close = true;
+                    }
+                }
+            });
+
+        // Handle button actions outside the window closure
+        if migrate {
+            self.migrate_legacy_notes();
+        }
+
+        if archive {
+            if let Err(e) = self.storage_manager.archive_legacy_notes() {
+                log::error!("Failed to back up legacy notes: {}", e);
+                self.show_status("Failed to back up legacy notes".to_string());
+            }
+            self.legacy_migration = None;
+        }
+
+        if close {
+            self.legacy_migration = None;
+        }
+    }
+
+    /// Migrates the previewed legacy notes and switches to the report.
+    fn migrate_legacy_notes(&mut self) {
+        let (Some(LegacyMigration::Preview(entries)), Some(crypto_manager), Some(user)) = (
+            self.legacy_migration.take(),
+            &self.crypto_manager,
+            &self.current_user,
+        ) else {
+            return;
+        };
+
+        match self
+            .storage_manager
+            .migrate_legacy_notes(&user.id, entries, crypto_manager)
+        {
+            Ok(report) => {
+                for note in report.iter().filter_map(|e| e.result.as_ref().ok()) {
+                    self.search_index.note_changed(note);
+                    self.notes.insert(note.id.clone(), note.clone());
+                }
+                self.legacy_migration = Some(LegacyMigration::Report(report));
+            }
+            Err(e) => {
+                log::error!("Failed to migrate legacy notes: {}", e);
+                self.legacy_migration = Some(LegacyMigration::Unreadable(e.to_string()));
+            }
+        }
+    }
+}
+
+/// Lists legacy entries with a status mark per note.
+fn render_legacy_entries(ui: &mut egui::Ui, entries: &[LegacyNoteEntry]) {
+    egui::ScrollArea::vertical()
+        .max_height(240.0)
+        .show(ui, |ui| {
+            for entry in entries {
+                match &entry.result {
+                    Ok(_) => {
+                        ui.label(format!("✓ {}", entry.label));
+                    }
+                    Err(error) => {
+                        ui.colored_label(egui::Color32::RED, format!("✗ {}", entry.label))
+                            .on_hover_text(error);
+                    }
+                }
+            }
+        });
+}
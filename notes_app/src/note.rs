@@ -7,11 +7,54 @@
 //! Defines the Note structure and related functionality for managing individual notes
 //! including creation, modification tracking, and time formatting.
 
-use chrono::{DateTime, Utc};
+use crate::export::ExportFormat;
+use crate::notebook;
+use crate::review::Review;
+use crate::settings::EditorView;
+use crate::trusted_time;
+use chrono::{DateTime, Datelike, Days, Utc};
 use chrono_tz::Europe::Zurich;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use uuid::Uuid;
 
+/// Title given to notes created without a title.
+pub const UNTITLED_NOTE_TITLE: &str = "Untitled Note";
+
+/// Maximum length of a title taken from the first line, in characters.
+const AUTO_TITLE_MAX_CHARS: usize = 80;
+
+/// Icons offered in a note's properties; any other emoji can be typed in.
+pub const NOTE_ICONS: [&str; 24] = [
+    "📝", "📌", "⭐", "💡", "📅", "✅", "🔑", "💼", "🏠", "📚", "🎯", "🔥", "❤", "🚀", "🐛", "💰",
+    "✈", "🎵", "📷", "🛒", "🔧", "📈", "🎓", "⚠",
+];
+
+/// Maximum length of a note icon, in characters; emoji sequences such as
+/// flags take several.
+const MAX_ICON_CHARS: usize = 8;
+
+/// Average silent reading speed used for reading time estimates.
+const READING_WORDS_PER_MINUTE: usize = 200;
+
+/// Reduces a tag to its leading letters, digits, `-` and `_`, lowercased.
+///
+/// # Arguments
+///
+/// * `tag` - The tag without the `#`
+///
+/// # Returns
+///
+/// * `Option<String>` - The tag, None if nothing is left
+pub fn normalize_tag(tag: &str) -> Option<String> {
+    let tag: String = tag
+        .chars()
+        .take_while(|c| c.is_alphanumeric() || *c == '-' || *c == '_')
+        .collect::<String>()
+        .to_lowercase();
+    (!tag.is_empty()).then_some(tag)
+}
+
 /// Represents a single note with metadata and content.
 ///
 /// Each note has a unique ID, title, content, and timestamps for creation
@@ -29,6 +72,218 @@ pub struct Note {
     pub created_at: DateTime<Utc>,
     /// UTC timestamp when the note was last modified
     pub modified_at: DateTime<Utc>,
+    /// UTC timestamp when the note was moved to the trash, None if not trashed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<DateTime<Utc>>,
+    /// Exempts the note from automatic purging while it is in the trash
+    #[serde(default)]
+    pub keep_forever: bool,
+    /// Whether the note is shown in the sidebar's Pinned section
+    #[serde(default)]
+    pub pinned: bool,
+    /// Whether the note is shown in the sidebar's Favorites section
+    #[serde(default)]
+    pub favorite: bool,
+    /// Path of the notebook the note belongs to, e.g. "Work/Clients", if
+    /// any; see the notebook module
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "notebook::deserialize_path"
+    )]
+    pub notebook: Option<String>,
+    /// Tags added in the tag editor, lowercase without `#`; tags written
+    /// in the content are not stored here, see [`Note::tags`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Custom key/value properties such as "source" or "client"
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub fields: BTreeMap<String, String>,
+    /// Files attached to the note; their content is stored encrypted separately
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<Attachment>,
+    /// Comments on the note, oldest first; kept apart from the content
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub comments: Vec<Comment>,
+    /// Whether the title follows the first line of the content; cleared
+    /// when the note is renamed manually
+    #[serde(default)]
+    pub auto_title: bool,
+    /// Emoji shown before the title in the sidebar and the note header
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+    /// Syntax token of a code note, e.g. "rs"; None for plain notes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// Whether this is a plain note or a secrets note
+    #[serde(default, skip_serializing_if = "NoteKind::is_text")]
+    pub kind: NoteKind,
+    /// Entries of a secrets note, shown above the content
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub secrets: Vec<SecretEntry>,
+    /// Format the note was last exported in, offered first next time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub export_format: Option<ExportFormat>,
+    /// Review schedule, None if the note is not being reviewed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub review: Option<Review>,
+    /// View the note was last shown in; None for the default view of the
+    /// settings
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub editor_view: Option<EditorView>,
+    /// Whether the note is stored in the shared vault; set when loading
+    /// and not part of the saved note
+    #[serde(skip)]
+    pub shared: bool,
+}
+
+/// The type of a note, which decides how it is shown.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NoteKind {
+    /// A note with free text content
+    #[default]
+    Text,
+    /// A note holding small secrets such as passwords or API keys, shown as
+    /// structured entries with masked values above the free text content
+    Secrets,
+}
+
+impl NoteKind {
+    /// Checks whether this is the plain note type, which isn't saved.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - True for `NoteKind::Text`
+    pub fn is_text(&self) -> bool {
+        *self == NoteKind::Text
+    }
+}
+
+/// One entry of a secrets note, e.g. the login of a website.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SecretEntry {
+    /// Unique identifier, used to tell entries apart in the UI
+    pub id: String,
+    /// What the secret is for, e.g. "Router admin"
+    pub label: String,
+    /// User name or account the secret belongs to
+    #[serde(default)]
+    pub username: String,
+    /// The secret itself; masked in the UI and never indexed for search
+    #[serde(default)]
+    pub secret: String,
+    /// Website or address where the secret is used
+    #[serde(default)]
+    pub url: String,
+}
+
+impl SecretEntry {
+    /// Creates an empty entry.
+    ///
+    /// # Returns
+    ///
+    /// * `Self` - A new SecretEntry with a unique ID
+    pub fn new() -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            ..Self::default()
+        }
+    }
+
+    /// Returns the fields of the entry that search looks at.
+    ///
+    /// # Returns
+    ///
+    /// * `String` - Label, user name and URL on separate lines; the secret
+    ///   is left out so it never ends up in the search index
+    pub fn searchable_text(&self) -> String {
+        format!("{}\n{}\n{}", self.label, self.username, self.url)
+    }
+}
+
+/// Metadata of a file attached to a note.
+///
+/// The file content is encrypted into its own file by the storage manager,
+/// so loading the note list doesn't decrypt attachments.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Attachment {
+    /// Unique identifier, also the name of the encrypted file
+    pub id: String,
+    /// Original file name, shown in the UI and used when exporting
+    pub file_name: String,
+    /// Size of the unencrypted content in bytes
+    pub size: u64,
+    /// UTC timestamp when the file was attached
+    pub added_at: DateTime<Utc>,
+}
+
+impl Attachment {
+    /// Creates attachment metadata for a newly attached file.
+    ///
+    /// # Arguments
+    ///
+    /// * `file_name` - Original file name
+    /// * `size` - Size of the file content in bytes
+    ///
+    /// # Returns
+    ///
+    /// * `Self` - A new Attachment with a unique ID
+    pub fn new(file_name: String, size: u64) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            file_name,
+            size,
+            added_at: trusted_time::now(),
+        }
+    }
+}
+
+/// A timestamped comment on a note, such as a review remark or a
+/// progress log entry.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Comment {
+    /// Unique identifier, used to remove the comment
+    pub id: String,
+    /// Username of the author, which matters for shared notes
+    pub author: String,
+    /// The comment text
+    pub text: String,
+    /// UTC timestamp when the comment was added
+    pub created_at: DateTime<Utc>,
+}
+
+impl Comment {
+    /// Creates a comment written now.
+    ///
+    /// # Arguments
+    ///
+    /// * `author` - Username of the author
+    /// * `text` - The comment text
+    ///
+    /// # Returns
+    ///
+    /// * `Self` - A new Comment with a unique ID
+    pub fn new(author: String, text: String) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            author,
+            text,
+            created_at: trusted_time::now(),
+        }
+    }
+
+    /// Formats the time of the comment for display in Swiss timezone.
+    ///
+    /// # Returns
+    ///
+    /// * `String` - Time as "DD.MM.YYYY HH:MM"
+    pub fn format_created_time(&self) -> String {
+        self.created_at
+            .with_timezone(&Zurich)
+            .format("%d.%m.%Y %H:%M")
+            .to_string()
+    }
 }
 
 impl Note {
@@ -53,21 +308,225 @@ impl Note {
     /// assert!(note.content.is_empty());
     /// ```
     pub fn new(title: String) -> Self {
-        let now = Utc::now();
+        let now = trusted_time::now();
         Self {
             id: Uuid::new_v4().to_string(),
             title,
             content: String::new(),
             created_at: now,
             modified_at: now,
+            deleted_at: None,
+            keep_forever: false,
+            pinned: false,
+            favorite: false,
+            notebook: None,
+            tags: Vec::new(),
+            fields: BTreeMap::new(),
+            attachments: Vec::new(),
+            comments: Vec::new(),
+            auto_title: false,
+            icon: None,
+            language: None,
+            kind: NoteKind::Text,
+            secrets: Vec::new(),
+            export_format: None,
+            review: None,
+            editor_view: None,
+            shared: false,
         }
     }
 
+    /// Returns the title with the note's icon in front, if it has one.
+    ///
+    /// # Returns
+    ///
+    /// * `String` - E.g. "📌 Groceries"
+    pub fn display_title(&self) -> String {
+        match &self.icon {
+            Some(icon) => format!("{} {}", icon, self.title),
+            None => self.title.clone(),
+        }
+    }
+
+    /// Sets the icon from user input.
+    ///
+    /// Only the first word of the input is kept, shortened to a few
+    /// characters, so the icon can't push the title out of the sidebar.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - An emoji or other short symbol; empty removes the icon
+    pub fn set_icon(&mut self, input: &str) {
+        self.icon = input
+            .split_whitespace()
+            .next()
+            .map(|icon| icon.chars().take(MAX_ICON_CHARS).collect());
+    }
+
+    /// Returns the text of the note that search looks at.
+    ///
+    /// # Returns
+    ///
+    /// * `String` - Title, content and the searchable fields of the
+    ///   secret entries, on separate lines
+    pub fn searchable_text(&self) -> String {
+        let mut text = format!("{}\n{}", self.title, self.content);
+        for entry in &self.secrets {
+            text.push('\n');
+            text.push_str(&entry.searchable_text());
+        }
+        text
+    }
+
     /// Updates the modification timestamp to the current time.
     ///
     /// This should be called whenever the note's content or title is changed.
     pub fn update_modified_time(&mut self) {
-        self.modified_at = Utc::now();
+        self.modified_at = trusted_time::now();
+    }
+
+    /// Updates an auto-titled note's title from its first non-empty line.
+    ///
+    /// Markdown heading markers are stripped and long lines are shortened.
+    /// Notes without content fall back to "Untitled Note".
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - Whether the title changed
+    pub fn sync_auto_title(&mut self) -> bool {
+        if !self.auto_title {
+            return false;
+        }
+
+        let first_line = self
+            .content
+            .lines()
+            .map(|line| line.trim().trim_start_matches('#').trim())
+            .find(|line| !line.is_empty());
+        let title = match first_line {
+            Some(line) if line.chars().count() > AUTO_TITLE_MAX_CHARS => {
+                let shortened: String = line.chars().take(AUTO_TITLE_MAX_CHARS).collect();
+                format!("{}…", shortened.trim_end())
+            }
+            Some(line) => line.to_string(),
+            None => UNTITLED_NOTE_TITLE.to_string(),
+        };
+
+        if title == self.title {
+            return false;
+        }
+        self.title = title;
+        true
+    }
+
+    /// Looks up a custom field by name, ignoring case.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Field name
+    ///
+    /// # Returns
+    ///
+    /// * `Option<&str>` - The field value, if the note has the field
+    pub fn field(&self, key: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(key))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns whether the note is in the trash.
+    pub fn is_trashed(&self) -> bool {
+        self.deleted_at.is_some()
+    }
+
+    /// Returns when a trashed note becomes due for automatic purging.
+    ///
+    /// # Arguments
+    ///
+    /// * `retention_days` - Days notes stay in the trash, 0 for forever
+    ///
+    /// # Returns
+    ///
+    /// * `Option<DateTime<Utc>>` - The purge time, or None if the note is not
+    ///   trashed, marked "keep forever" or retention is disabled
+    pub fn purge_at(&self, retention_days: u32) -> Option<DateTime<Utc>> {
+        if self.keep_forever || retention_days == 0 {
+            return None;
+        }
+        self.deleted_at
+            .map(|deleted_at| deleted_at + chrono::Duration::days(i64::from(retention_days)))
+    }
+
+    /// Returns all tags of the note: those added in the tag editor and
+    /// those used in the content.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<String>` - Lowercase tags without the `#`, sorted and deduplicated
+    pub fn tags(&self) -> Vec<String> {
+        let mut tags = self.inline_tags();
+        tags.extend(self.tags.iter().cloned());
+        tags.sort();
+        tags.dedup();
+        tags
+    }
+
+    /// Returns the tags used in the note content.
+    ///
+    /// Tags are written inline as `#tag` (letters, digits, `-` and `_`).
+    /// A `#` followed by a space, as in Markdown headings, is not a tag.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<String>` - Lowercase tags without the `#`, sorted and deduplicated
+    pub fn inline_tags(&self) -> Vec<String> {
+        let mut tags: Vec<String> = self
+            .content
+            .split(|c: char| c.is_whitespace() || c == ',' || c == ';')
+            .filter_map(|word| word.strip_prefix('#'))
+            .filter_map(normalize_tag)
+            .collect();
+        tags.sort();
+        tags.dedup();
+        tags
+    }
+
+    /// Adds a tag in the tag editor.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - The tag as typed, with or without `#`
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - True if the tag was added, false if it was empty or the
+    ///   note already has it
+    pub fn add_tag(&mut self, input: &str) -> bool {
+        let Some(tag) = normalize_tag(input.trim().trim_start_matches('#')) else {
+            return false;
+        };
+        if self.tags.contains(&tag) {
+            return false;
+        }
+        self.tags.push(tag);
+        self.tags.sort();
+        true
+    }
+
+    /// Removes a tag added in the tag editor.
+    ///
+    /// # Arguments
+    ///
+    /// * `tag` - The normalized tag
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - True if the note had the tag
+    pub fn remove_tag(&mut self, tag: &str) -> bool {
+        let before = self.tags.len();
+        self.tags.retain(|existing| existing != tag);
+        self.tags.len() != before
     }
 
     /// Converts the creation timestamp to Swiss timezone.
@@ -120,6 +579,15 @@ impl Note {
         self.created_at_local().format("%d.%m.%Y %H:%M").to_string()
     }
 
+    /// Counts the words in the note content.
+    ///
+    /// # Returns
+    ///
+    /// * `usize` - Number of whitespace-separated words
+    pub fn word_count(&self) -> usize {
+        self.content.split_whitespace().count()
+    }
+
     /// Generates a human-readable relative time description.
     ///
     /// Converts the time difference between now and the last modification
@@ -138,7 +606,7 @@ impl Note {
     /// // Returns "Just now" for a newly created note
     /// ```
     pub fn relative_time(&self) -> String {
-        let now = Utc::now().with_timezone(&Zurich);
+        let now = trusted_time::now().with_timezone(&Zurich);
         let modified = self.modified_at_local();
         let duration = now.signed_duration_since(modified);
 
@@ -177,4 +645,82 @@ impl Note {
             self.format_modified_time()
         }
     }
+
+    /// Sorts the note into a sidebar date group by its modification day.
+    ///
+    /// # Returns
+    ///
+    /// * `DateBucket` - The group, based on calendar days in Swiss timezone
+    pub fn date_bucket(&self) -> DateBucket {
+        let today = trusted_time::now().with_timezone(&Zurich).date_naive();
+        let modified = self.modified_at_local().date_naive();
+
+        if modified >= today {
+            DateBucket::Today
+        } else if Some(modified) == today.checked_sub_days(Days::new(1)) {
+            DateBucket::Yesterday
+        } else if modified.iso_week() == today.iso_week() {
+            DateBucket::ThisWeek
+        } else {
+            DateBucket::Earlier
+        }
+    }
+}
+
+/// Groups of the sidebar note list when grouping by date.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateBucket {
+    /// Modified today
+    Today,
+    /// Modified yesterday
+    Yesterday,
+    /// Modified earlier in the current week (weeks start on Monday)
+    ThisWeek,
+    /// Modified before the current week
+    Earlier,
+}
+
+impl DateBucket {
+    /// All groups, newest first.
+    pub const ALL: [DateBucket; 4] = [
+        DateBucket::Today,
+        DateBucket::Yesterday,
+        DateBucket::ThisWeek,
+        DateBucket::Earlier,
+    ];
+
+    /// Returns the display name of the group.
+    ///
+    /// # Returns
+    ///
+    /// * `&'static str` - Name shown as the section title
+    pub fn label(&self) -> &'static str {
+        match self {
+            DateBucket::Today => "Today",
+            DateBucket::Yesterday => "Yesterday",
+            DateBucket::ThisWeek => "This Week",
+            DateBucket::Earlier => "Earlier",
+        }
+    }
+}
+
+/// Estimates how long a note takes to read.
+///
+/// Based on the word count at 200 words per minute, rounded up to whole
+/// minutes so that any non-empty note takes at least a minute.
+///
+/// # Arguments
+///
+/// * `word_count` - Number of words in the note, see `Note::word_count`
+///
+/// # Returns
+///
+/// * `String` - Estimate like "3 min read", or "< 1 min read" if empty
+pub fn format_reading_time(word_count: usize) -> String {
+    let minutes = word_count.div_ceil(READING_WORDS_PER_MINUTE);
+    if minutes == 0 {
+        "< 1 min read".to_string()
+    } else {
+        format!("{} min read", minutes)
+    }
 }
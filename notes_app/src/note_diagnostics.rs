@@ -0,0 +1,401 @@
+//! # Note Diagnostics Module
+//!
+//! Shows how a note and its attachments are stored, to help track down
+//! "Decryption failed" reports: the size and SHA-256 checksum of each
+//! encrypted file, the format version, algorithm and nonce from its
+//! header, and whether it decrypts right now. The report can be copied
+//! into a bug report; it holds no key material and no note content.
+//!
+//! Opened from the note context menu, or from the security panel for notes
+//! that could not be decrypted and are therefore missing from the list.
+
+use crate::app::NotesApp;
+use crate::crypto::CryptoManager;
+use crate::note::Note;
+use crate::storage::{EncryptedFile, StorageManager, SHARED_VAULT_OWNER};
+use chrono::{DateTime, Utc};
+use chrono_tz::Europe::Zurich;
+use eframe::egui;
+use secure_core::{BlobInfo, Cipher};
+use sha2::{Digest, Sha256};
+use std::fmt::Write;
+use std::fs;
+
+/// Diagnostics of one encrypted file.
+#[derive(Debug, Clone)]
+pub struct BlobDiagnostics {
+    /// What the file holds, e.g. "Note" or "Attachment report.pdf"
+    pub label: String,
+    /// The file, relative to the vault directory
+    pub file: String,
+    /// Size of the file in bytes, None if it is missing
+    pub file_size: Option<u64>,
+    /// Last modification time of the file
+    pub file_modified: Option<DateTime<Utc>>,
+    /// Hex SHA-256 of the file
+    pub sha256: Option<String>,
+    /// Header of the file, or why it could not be read
+    pub header: Result<BlobInfo, String>,
+    /// Whether the file decrypted and its authentication tag matched
+    pub decrypt: Result<(), String>,
+}
+
+impl BlobDiagnostics {
+    /// Reads and decrypts a file.
+    ///
+    /// # Arguments
+    ///
+    /// * `label` - What the file holds
+    /// * `file` - The file
+    /// * `owner_id` - Owner the file was encrypted for
+    /// * `crypto` - Crypto manager of the owner, None if the vault is locked
+    ///
+    /// # Returns
+    ///
+    /// * `Self` - The diagnostics; a missing or unreadable file is
+    ///   reported in the fields rather than as an error
+    fn check(
+        label: String,
+        file: &EncryptedFile,
+        owner_id: &str,
+        crypto: Option<&CryptoManager>,
+    ) -> Self {
+        let metadata = fs::metadata(&file.path).ok();
+        let data = fs::read(&file.path);
+
+        let (sha256, header, decrypt) = match &data {
+            Ok(data) => (
+                Some(hex::encode(Sha256::digest(data))),
+                Cipher::inspect(data).map_err(|e| e.to_string()),
+                match crypto {
+                    Some(crypto) => crypto
+                        .decrypt(data, file.context(owner_id))
+                        .map(|_| ())
+                        .map_err(|e| e.to_string()),
+                    None => Err("The vault is locked".to_string()),
+                },
+            ),
+            Err(e) => {
+                let error = if e.kind() == std::io::ErrorKind::NotFound {
+                    "The file does not exist".to_string()
+                } else {
+                    format!("Could not read the file: {}", e)
+                };
+                (None, Err(error.clone()), Err(error))
+            }
+        };
+
+        Self {
+            label,
+            file: file.relative_path(),
+            file_size: metadata.as_ref().map(|m| m.len()),
+            file_modified: metadata
+                .and_then(|m| m.modified().ok())
+                .map(DateTime::<Utc>::from),
+            sha256,
+            header,
+            decrypt,
+        }
+    }
+}
+
+/// Diagnostics of a note and its attachments.
+#[derive(Debug, Clone)]
+pub struct NoteDiagnostics {
+    /// ID of the note
+    pub note_id: String,
+    /// Title of the note, None if it could not be decrypted
+    pub title: Option<String>,
+    /// "Private" or "Shared"
+    pub vault: &'static str,
+    /// When the note last decrypted before this check, in this session
+    pub last_decrypted: Option<DateTime<Utc>>,
+    /// This replica's counter in the note's version in the change journal
+    pub journal_version: Option<u64>,
+    /// UTC timestamp of this check
+    pub checked_at: DateTime<Utc>,
+    /// The note file first, then its attachments
+    pub blobs: Vec<BlobDiagnostics>,
+}
+
+impl NoteDiagnostics {
+    /// Formats the diagnostics as plain text for a bug report.
+    ///
+    /// # Returns
+    ///
+    /// * `String` - One block per file, without note content
+    pub fn to_report(&self) -> String {
+        let mut report = String::new();
+        let _ = writeln!(report, "Note: {}", self.note_id);
+        let _ = writeln!(report, "Vault: {}", self.vault);
+        let _ = writeln!(report, "Checked: {}", format_time(&self.checked_at));
+        let _ = writeln!(
+            report,
+            "Last decrypted: {}",
+            self.last_decrypted
+                .as_ref()
+                .map_or("not in this session".to_string(), format_time)
+        );
+        let _ = writeln!(report, "Journal version: {}", journal_version(self));
+        for blob in &self.blobs {
+            let _ = writeln!(report);
+            let _ = writeln!(report, "[{}] {}", blob.label, blob.file);
+            for (name, value) in blob_rows(blob) {
+                let _ = writeln!(report, "{}: {}", name, value);
+            }
+        }
+        report
+    }
+}
+
+impl NotesApp {
+    /// Checks the files of a note and opens the diagnostics window.
+    ///
+    /// Notes in the list are checked in their own vault. A note that could
+    /// not be decrypted is looked for in the private vault first, then in
+    /// the shared vault.
+    ///
+    /// # Arguments
+    ///
+    /// * `note_id` - ID of the note
+    pub fn open_note_diagnostics(&mut self, note_id: &str) {
+        let Some(user) = &self.current_user else {
+            return;
+        };
+        let owner_id = match self.notes.get(note_id) {
+            Some(note) if note.shared => SHARED_VAULT_OWNER.to_string(),
+            Some(_) => user.id.clone(),
+            None => {
+                let private = self.storage_manager.encrypted_file(&user.id, note_id, None);
+                if !private.path.exists() && self.shared_vault.is_some() {
+                    SHARED_VAULT_OWNER.to_string()
+                } else {
+                    user.id.clone()
+                }
+            }
+        };
+
+        let mut diagnostics = diagnose_note(
+            &self.storage_manager,
+            &owner_id,
+            note_id,
+            self.notes.get(note_id),
+            self.owner_crypto(&owner_id),
+            self.last_decrypted.get(note_id).copied(),
+        );
+        diagnostics.journal_version = self
+            .journal
+            .as_ref()
+            .filter(|_| owner_id != SHARED_VAULT_OWNER)
+            .and_then(|journal| {
+                let clock = journal.note_clock(note_id)?;
+                Some(clock.get(&journal.replica_id))
+            });
+        if diagnostics.blobs[0].decrypt.is_ok() {
+            self.last_decrypted
+                .insert(note_id.to_string(), diagnostics.checked_at);
+        }
+        self.note_diagnostics = Some(diagnostics);
+    }
+
+    /// Renders the diagnostics window, if open.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The egui context for rendering
+    pub fn render_note_diagnostics(&mut self, ctx: &egui::Context) {
+        let Some(diagnostics) = &self.note_diagnostics else {
+            return;
+        };
+
+        let mut open = true;
+        let mut recheck = false;
+        egui::Window::new("Encryption Diagnostics")
+            .open(&mut open)
+            .default_width(460.0)
+            .show(ctx, |ui| {
+                ui.heading(
+                    diagnostics
+                        .title
+                        .as_deref()
+                        .unwrap_or("(could not be decrypted)"),
+                );
+                ui.small(format!(
+                    "{} vault · {}",
+                    diagnostics.vault, diagnostics.note_id
+                ));
+                ui.label(format!(
+                    "Last decrypted: {}",
+                    diagnostics
+                        .last_decrypted
+                        .as_ref()
+                        .map_or("not in this session".to_string(), format_time)
+                ));
+                ui.label(format!("Journal version: {}", journal_version(diagnostics)));
+                ui.separator();
+
+                egui::ScrollArea::vertical()
+                    .max_height(360.0)
+                    .show(ui, |ui| {
+                        for (index, blob) in diagnostics.blobs.iter().enumerate() {
+                            let (color, status) = match &blob.decrypt {
+                                Ok(()) => (egui::Color32::from_rgb(100, 200, 100), "✓"),
+                                Err(_) => (egui::Color32::from_rgb(255, 100, 100), "✗"),
+                            };
+                            ui.horizontal(|ui| {
+                                ui.colored_label(color, status);
+                                ui.strong(&blob.label);
+                            });
+                            egui::Grid::new(("note_diagnostics_blob", index))
+                                .num_columns(2)
+                                .striped(true)
+                                .show(ui, |ui| {
+                                    ui.label("File");
+                                    ui.monospace(&blob.file);
+                                    ui.end_row();
+                                    for (name, value) in blob_rows(blob) {
+                                        ui.label(name);
+                                        ui.monospace(value);
+                                        ui.end_row();
+                                    }
+                                });
+                            ui.add_space(6.0);
+                        }
+                    });
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.button("Check Again").clicked() {
+                        recheck = true;
+                    }
+                    if ui
+                        .button("Copy Report")
+                        .on_hover_text("Contains no note content or keys")
+                        .clicked()
+                    {
+                        ui.ctx().copy_text(diagnostics.to_report());
+                    }
+                    ui.small(format!("Checked {}", format_time(&diagnostics.checked_at)));
+                });
+            });
+
+        if recheck {
+            let note_id = diagnostics.note_id.clone();
+            self.open_note_diagnostics(&note_id);
+        }
+        if !open {
+            self.note_diagnostics = None;
+        }
+    }
+}
+
+/// Checks the note file and the attachment files of a note.
+///
+/// # Arguments
+///
+/// * `storage` - The storage manager
+/// * `owner_id` - Owner of the note
+/// * `note_id` - ID of the note
+/// * `note` - The note in memory, None if it could not be decrypted
+/// * `crypto` - Crypto manager of the owner
+/// * `last_decrypted` - When the note last decrypted in this session
+///
+/// # Returns
+///
+/// * `NoteDiagnostics` - Diagnostics of every file of the note
+fn diagnose_note(
+    storage: &StorageManager,
+    owner_id: &str,
+    note_id: &str,
+    note: Option<&Note>,
+    crypto: Option<&CryptoManager>,
+    last_decrypted: Option<DateTime<Utc>>,
+) -> NoteDiagnostics {
+    let mut blobs = vec![BlobDiagnostics::check(
+        "Note".to_string(),
+        &storage.encrypted_file(owner_id, note_id, None),
+        owner_id,
+        crypto,
+    )];
+    for attachment in note
+        .map(|note| note.attachments.as_slice())
+        .unwrap_or_default()
+    {
+        blobs.push(BlobDiagnostics::check(
+            format!("Attachment {}", attachment.file_name),
+            &storage.encrypted_file(owner_id, note_id, Some(&attachment.id)),
+            owner_id,
+            crypto,
+        ));
+    }
+
+    NoteDiagnostics {
+        note_id: note_id.to_string(),
+        title: note.map(|note| note.title.clone()),
+        vault: if owner_id == SHARED_VAULT_OWNER {
+            "Shared"
+        } else {
+            "Private"
+        },
+        last_decrypted,
+        journal_version: None,
+        checked_at: Utc::now(),
+        blobs,
+    }
+}
+
+/// Formats the journal version of the diagnosed note.
+fn journal_version(diagnostics: &NoteDiagnostics) -> String {
+    match diagnostics.journal_version {
+        Some(version) => version.to_string(),
+        None if diagnostics.vault == "Shared" => "shared notes are not journaled".to_string(),
+        None => "not recorded".to_string(),
+    }
+}
+
+/// Lists the properties of a file as (name, value) rows.
+fn blob_rows(blob: &BlobDiagnostics) -> Vec<(&'static str, String)> {
+    let mut rows = vec![(
+        "Size",
+        blob.file_size
+            .map_or("—".to_string(), |size| format!("{} bytes", size)),
+    )];
+    if let Some(modified) = &blob.file_modified {
+        rows.push(("Written", format_time(modified)));
+    }
+    match &blob.header {
+        Ok(info) => {
+            rows.push(("Format", info.format.label()));
+            rows.push((
+                "Algorithm",
+                info.algorithm.map_or("Unknown".to_string(), |algorithm| {
+                    algorithm.label().to_string()
+                }),
+            ));
+            rows.push(("Nonce", hex::encode(&info.nonce)));
+            rows.push((
+                "Ciphertext",
+                format!("{} bytes incl. tag", info.ciphertext_len),
+            ));
+        }
+        Err(e) => rows.push(("Header", e.clone())),
+    }
+    if let Some(sha256) = &blob.sha256 {
+        rows.push(("SHA-256", sha256.clone()));
+    }
+    rows.push((
+        "Checksum",
+        match &blob.decrypt {
+            Ok(()) => "Authentication tag valid".to_string(),
+            Err(e) => format!("Failed: {}", e),
+        },
+    ));
+    rows
+}
+
+/// Formats a timestamp for display in Swiss timezone.
+fn format_time(time: &DateTime<Utc>) -> String {
+    time.with_timezone(&Zurich)
+        .format("%d.%m.%Y %H:%M:%S")
+        .to_string()
+}
@@ -0,0 +1,141 @@
+//! # Note Email Module
+//!
+//! Hands a note to the user's mail app as a new message, with the title as
+//! the subject and the content as the body. Two ways are offered:
+//!
+//! - A `mailto:` link opened in the default mail app. Mail apps and the
+//!   operating system cut off long links, so it is only used for notes up
+//!   to [`MAX_MAILTO_LEN`] characters once encoded.
+//! - An `.eml` file: a draft message marked as unsent that mail apps such
+//!   as Outlook and Thunderbird open for editing, for notes of any length.
+//!
+//! Either way the content leaves the encrypted vault in plain text. Only
+//! the title and content are included; properties, comments, attachments
+//! and secrets entries are not, and secrets notes can't be sent at all.
+
+use crate::note::Note;
+use anyhow::{anyhow, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::Utc;
+use std::fs;
+use std::path::Path;
+
+/// Longest `mailto:` link that mail apps reliably accept.
+///
+/// Windows passes at most about 2000 characters to the mail app.
+pub const MAX_MAILTO_LEN: usize = 2000;
+
+/// Builds the `mailto:` link for a note, without a recipient.
+///
+/// # Arguments
+///
+/// * `note` - The note to send
+///
+/// # Returns
+///
+/// * `Result<String>` - The link, e.g. `mailto:?subject=Groceries&body=Milk`,
+///   or Err for a secrets note
+pub fn mailto_url(note: &Note) -> Result<String> {
+    if !note.kind.is_text() {
+        return Err(anyhow!("Secrets notes can't be sent by email"));
+    }
+
+    Ok(format!(
+        "mailto:?subject={}&body={}",
+        percent_encode(&note.title),
+        percent_encode(&crlf(&note.content))
+    ))
+}
+
+/// Checks whether a note is short enough for a `mailto:` link.
+///
+/// # Arguments
+///
+/// * `note` - The note to send
+///
+/// # Returns
+///
+/// * `bool` - True if the note can be sent and the link is at most
+///   [`MAX_MAILTO_LEN`] characters
+pub fn fits_mailto(note: &Note) -> bool {
+    mailto_url(note).is_ok_and(|url| url.len() <= MAX_MAILTO_LEN)
+}
+
+/// Writes a note as an unsent email message.
+///
+/// # Arguments
+///
+/// * `note` - The note to send
+/// * `path` - The `.eml` file to write
+///
+/// # Returns
+///
+/// * `Result<()>` - Ok if written, Err for a secrets note or if writing
+///   failed
+pub fn write_eml(note: &Note, path: &Path) -> Result<()> {
+    if !note.kind.is_text() {
+        return Err(anyhow!("Secrets notes can't be sent by email"));
+    }
+
+    let message = format!(
+        "Subject: {}\r\n\
+         Date: {}\r\n\
+         MIME-Version: 1.0\r\n\
+         Content-Type: text/plain; charset=utf-8\r\n\
+         Content-Transfer-Encoding: base64\r\n\
+         X-Unsent: 1\r\n\
+         \r\n\
+         {}",
+        encode_header(&note.title),
+        Utc::now().to_rfc2822(),
+        wrap_base64(&crlf(&note.content))
+    );
+    fs::write(path, message)?;
+    log::info!("Note {} written as email to {}", note.id, path.display());
+    Ok(())
+}
+
+/// Turns all line breaks into CRLF, as email expects.
+fn crlf(text: &str) -> String {
+    text.lines().collect::<Vec<_>>().join("\r\n")
+}
+
+/// Percent-encodes text for a `mailto:` header value.
+///
+/// Everything except unreserved ASCII characters is encoded, UTF-8 byte by
+/// byte.
+fn percent_encode(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+/// Encodes a header value as a MIME encoded word if it isn't plain ASCII.
+///
+/// Line breaks are replaced by spaces so the value can't start new headers.
+fn encode_header(value: &str) -> String {
+    let value = value.replace(['\r', '\n'], " ");
+    if value.is_ascii() {
+        value
+    } else {
+        format!("=?UTF-8?B?{}?=", STANDARD.encode(value))
+    }
+}
+
+/// Base64-encodes a message body in lines of 76 characters.
+fn wrap_base64(body: &str) -> String {
+    let encoded = STANDARD.encode(body);
+    encoded
+        .as_bytes()
+        .chunks(76)
+        .map(|line| String::from_utf8_lossy(line).into_owned())
+        .collect::<Vec<_>>()
+        .join("\r\n")
+}
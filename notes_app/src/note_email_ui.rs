@@ -0,0 +1,180 @@
+//! # Note Email UI Module
+//!
+//! The "Send via Email" dialog, opened from the note context menu. It warns
+//! that the note leaves the encrypted vault before anything is handed to
+//! the mail app, then opens a new message or saves it as an `.eml` file.
+
+use crate::app::NotesApp;
+use crate::export;
+use crate::note_email::{self, MAX_MAILTO_LEN};
+use crate::shell_open;
+use eframe::egui;
+
+/// State of the "Send via Email" dialog.
+pub struct NoteEmailDialog {
+    /// ID of the note to send
+    note_id: String,
+    /// Why the last attempt failed
+    error: Option<String>,
+}
+
+/// Button pressed in the dialog, handled after the window closure.
+enum EmailAction {
+    OpenMailApp,
+    SaveEml,
+    Close,
+}
+
+impl NotesApp {
+    /// Opens the "Send via Email" dialog for a note.
+    ///
+    /// Secrets notes are refused with a status message.
+    ///
+    /// # Arguments
+    ///
+    /// * `note_id` - ID of the note to send
+    pub fn open_note_email(&mut self, note_id: &str) {
+        let Some(note) = self.notes.get(note_id) else {
+            return;
+        };
+        if !note.kind.is_text() {
+            self.show_status("Secrets notes can't be sent by email".to_string());
+            return;
+        }
+        self.note_email_dialog = Some(NoteEmailDialog {
+            note_id: note_id.to_string(),
+            error: None,
+        });
+    }
+
+    /// Renders the "Send via Email" dialog, if open.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The egui context for rendering
+    pub fn render_note_email_dialog(&mut self, ctx: &egui::Context) {
+        let Some(dialog) = &self.note_email_dialog else {
+            return;
+        };
+        let Some(note) = self.notes.get(&dialog.note_id) else {
+            self.note_email_dialog = None;
+            return;
+        };
+        let fits_mailto = note_email::fits_mailto(note);
+
+        let mut open = true;
+        let mut action = None;
+        egui::Window::new("✉ Send via Email")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "Creates a new email with \"{}\" as the subject and the note's text as \
+                     the message.",
+                    note.display_title()
+                ));
+                ui.add_space(5.0);
+                ui.colored_label(
+                    egui::Color32::from_rgb(255, 180, 0),
+                    "⚠ The note leaves encryption: your mail app, your mail provider and \
+                     the recipient's get it in plain text and may keep copies.",
+                );
+                ui.small("Properties, comments and attachments are not included.");
+
+                if let Some(error) = &dialog.error {
+                    ui.colored_label(egui::Color32::from_rgb(255, 100, 100), error);
+                }
+                ui.separator();
+                ui.horizontal(|ui| {
+                    let open_button = ui
+                        .add_enabled(fits_mailto, egui::Button::new("Open in Mail App"))
+                        .on_hover_text("Opens a new message in your default mail app")
+                        .on_disabled_hover_text(format!(
+                            "The note is too long for a mail link (over {} characters \
+                             encoded), save it as an .eml file instead",
+                            MAX_MAILTO_LEN
+                        ));
+                    if open_button.clicked() {
+                        action = Some(EmailAction::OpenMailApp);
+                    }
+                    if ui
+                        .button("Save as .eml…")
+                        .on_hover_text("Saves a draft message to open in your mail app")
+                        .clicked()
+                    {
+                        action = Some(EmailAction::SaveEml);
+                    }
+                    if ui.button("Cancel").clicked() {
+                        action = Some(EmailAction::Close);
+                    }
+                });
+            });
+
+        if !open {
+            action = Some(EmailAction::Close);
+        }
+        match action {
+            Some(EmailAction::OpenMailApp) => self.send_note_to_mail_app(),
+            Some(EmailAction::SaveEml) => self.save_note_as_eml(),
+            Some(EmailAction::Close) => self.note_email_dialog = None,
+            None => {}
+        }
+    }
+
+    /// Opens the note of the dialog as a new message in the mail app.
+    fn send_note_to_mail_app(&mut self) {
+        let Some(dialog) = &mut self.note_email_dialog else {
+            return;
+        };
+        let Some(note) = self.notes.get(&dialog.note_id) else {
+            return;
+        };
+        let result =
+            note_email::mailto_url(note).and_then(|url| Ok(shell_open::open_mailto(&url)?));
+        match result {
+            Ok(()) => {
+                log::info!("Note {} handed to the mail app", note.id);
+                self.note_email_dialog = None;
+                self.show_status("Note opened in your mail app".to_string());
+            }
+            Err(e) => {
+                log::error!("Failed to open the mail app: {}", e);
+                dialog.error = Some(format!("Could not open your mail app: {}", e));
+            }
+        }
+    }
+
+    /// Lets the user choose a file and saves the note of the dialog as an
+    /// unsent email.
+    fn save_note_as_eml(&mut self) {
+        let Some(dialog) = &mut self.note_email_dialog else {
+            return;
+        };
+        let Some(note) = self.notes.get(&dialog.note_id) else {
+            return;
+        };
+        let default_filename =
+            export::render_file_name(&self.settings.export_filename_template, note, "eml");
+        let Some(path) = rfd::FileDialog::new()
+            .set_title("Save Note as Email")
+            .set_file_name(&default_filename)
+            .add_filter("Email messages", &["eml"])
+            .save_file()
+        else {
+            return;
+        };
+
+        match note_email::write_eml(note, &path) {
+            Ok(()) => {
+                self.note_email_dialog = None;
+                self.report_export(format!("Email saved to {}", path.display()), &path);
+            }
+            Err(e) => {
+                log::error!("Failed to save note as email: {}", e);
+                dialog.error = Some(format!("Saving failed: {}", e));
+            }
+        }
+    }
+}
@@ -0,0 +1,276 @@
+//! # Note History Module
+//!
+//! Earlier versions of each private note, kept encrypted in
+//! `history/<note_id>.enc` next to the note files so an overwritten or
+//! accidentally cleared note can be brought back.
+//!
+//! A revision is the version of a note that was on disk before a save
+//! overwrote it. Auto-save writes every few seconds while typing, so a
+//! version is only kept if the last one is at least
+//! [`REVISION_INTERVAL_MINUTES`] old; a burst of edits leaves the version
+//! from before it. At most [`MAX_REVISIONS`] revisions are kept per note,
+//! the oldest are dropped first.
+//!
+//! Shared vault notes have no history, and the history of a note is
+//! deleted together with the note when the trash is emptied.
+
+use crate::crypto::CryptoManager;
+use crate::note::Note;
+use crate::storage::StorageManager;
+use crate::trusted_time;
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Number of revisions kept per note.
+pub const MAX_REVISIONS: usize = 50;
+
+/// Minimum time between two revisions of a note, in minutes.
+pub const REVISION_INTERVAL_MINUTES: i64 = 10;
+
+/// Largest number of line pairs compared when diffing; longer changes are
+/// shown as the old lines removed and the new ones added.
+const MAX_DIFF_CELLS: usize = 4_000_000;
+
+/// An earlier version of a note.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Revision {
+    /// When the version was replaced by a newer one
+    pub recorded_at: DateTime<Utc>,
+    /// The note as it was saved
+    pub note: Note,
+}
+
+/// The revisions of one note, oldest first.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct NoteHistory {
+    /// The revisions, at most [`MAX_REVISIONS`]
+    pub revisions: Vec<Revision>,
+}
+
+impl NoteHistory {
+    /// Adds a revision, dropping the oldest ones beyond [`MAX_REVISIONS`].
+    ///
+    /// # Arguments
+    ///
+    /// * `note` - The version to keep
+    pub fn push(&mut self, note: Note) {
+        self.revisions.push(Revision {
+            recorded_at: trusted_time::now(),
+            note,
+        });
+        let excess = self.revisions.len().saturating_sub(MAX_REVISIONS);
+        self.revisions.drain(..excess);
+    }
+
+    /// Returns when the latest revision was recorded.
+    pub fn latest_recorded_at(&self) -> Option<DateTime<Utc>> {
+        self.revisions.last().map(|revision| revision.recorded_at)
+    }
+}
+
+/// Keeps track of which notes need a revision at the next save.
+///
+/// Knows the version of every private note on disk, so only notes changed
+/// since are read back, and when each note's last revision was recorded,
+/// so the history files are only decrypted once per session.
+#[derive(Default)]
+pub struct RevisionTracker {
+    /// Modification time of each note as last read from or written to disk
+    saved: HashMap<String, DateTime<Utc>>,
+    /// When the latest revision of each note was recorded, once looked up
+    latest: HashMap<String, Option<DateTime<Utc>>>,
+}
+
+impl RevisionTracker {
+    /// Remembers the version of a note read from disk.
+    ///
+    /// # Arguments
+    ///
+    /// * `note` - The loaded note
+    pub fn note_loaded(&mut self, note: &Note) {
+        self.saved.insert(note.id.clone(), note.modified_at);
+    }
+
+    /// Keeps the versions on disk of the notes about to be overwritten.
+    ///
+    /// Called before the notes are written. Notes not on disk yet, locked
+    /// elsewhere, unchanged or with a revision recorded less than
+    /// [`REVISION_INTERVAL_MINUTES`] ago are skipped. Failures are logged,
+    /// since a missing revision must not keep the notes from being saved.
+    ///
+    /// # Arguments
+    ///
+    /// * `storage` - The storage manager
+    /// * `user_id` - Owner of the notes
+    /// * `notes` - The private notes being saved
+    /// * `locked` - IDs of notes locked by another instance, not written
+    /// * `crypto` - CryptoManager of the user
+    pub fn record_changed(
+        &mut self,
+        storage: &StorageManager,
+        user_id: &str,
+        notes: &HashMap<String, Note>,
+        locked: &HashSet<String>,
+        crypto: &CryptoManager,
+    ) {
+        let interval = TimeDelta::minutes(REVISION_INTERVAL_MINUTES);
+        for note in notes.values().filter(|note| !locked.contains(&note.id)) {
+            // Only notes on disk that changed since they were read or written
+            let changed = self
+                .saved
+                .get(&note.id)
+                .is_some_and(|saved_at| *saved_at != note.modified_at);
+            if !changed {
+                continue;
+            }
+            let latest = *self.latest.entry(note.id.clone()).or_insert_with(|| {
+                storage
+                    .load_note_history(user_id, &note.id, crypto)
+                    .map(|history| history.latest_recorded_at())
+                    .unwrap_or_else(|e| {
+                        log::warn!("Failed to read the history of note {}: {}", note.id, e);
+                        None
+                    })
+            });
+            if latest.is_some_and(|recorded_at| trusted_time::now() - recorded_at < interval) {
+                continue;
+            }
+            self.record(storage, user_id, &note.id, crypto);
+        }
+    }
+
+    /// Keeps the version of a note on disk, however recent the last
+    /// revision is, e.g. before restoring an older one over it.
+    ///
+    /// # Arguments
+    ///
+    /// * `storage` - The storage manager
+    /// * `user_id` - Owner of the note
+    /// * `note_id` - ID of the note
+    /// * `crypto` - CryptoManager of the user
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - Whether a revision was recorded
+    pub fn record(
+        &mut self,
+        storage: &StorageManager,
+        user_id: &str,
+        note_id: &str,
+        crypto: &CryptoManager,
+    ) -> bool {
+        match storage.record_note_revision(user_id, note_id, crypto) {
+            Ok(recorded_at) => {
+                self.latest.insert(note_id.to_string(), Some(recorded_at));
+                true
+            }
+            Err(e) => {
+                log::error!("Failed to record a revision of note {}: {}", note_id, e);
+                false
+            }
+        }
+    }
+
+    /// Remembers the versions of the notes just written.
+    ///
+    /// # Arguments
+    ///
+    /// * `notes` - The private notes that were saved
+    /// * `locked` - IDs of notes locked by another instance, not written
+    pub fn notes_saved(&mut self, notes: &HashMap<String, Note>, locked: &HashSet<String>) {
+        for note in notes.values().filter(|note| !locked.contains(&note.id)) {
+            self.saved.insert(note.id.clone(), note.modified_at);
+        }
+    }
+
+    /// Forgets all notes, e.g. on logout.
+    pub fn clear(&mut self) {
+        self.saved.clear();
+        self.latest.clear();
+    }
+}
+
+/// A line of a diff between two versions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffLine<'a> {
+    /// The line is in both versions
+    Same(&'a str),
+    /// The line is only in the older version
+    Removed(&'a str),
+    /// The line is only in the newer version
+    Added(&'a str),
+}
+
+/// Compares two texts line by line.
+///
+/// Lines common to the start and end are matched first; the rest is
+/// compared with a longest common subsequence, unless it is too large.
+///
+/// # Arguments
+///
+/// * `old` - The older text
+/// * `new` - The newer text
+///
+/// # Returns
+///
+/// * `Vec<DiffLine>` - All lines of both texts, in order
+pub fn diff_lines<'a>(old: &'a str, new: &'a str) -> Vec<DiffLine<'a>> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_middle = &old[prefix..old.len() - suffix];
+    let new_middle = &new[prefix..new.len() - suffix];
+
+    let mut lines: Vec<DiffLine> = old[..prefix].iter().map(|l| DiffLine::Same(l)).collect();
+    if old_middle.len().saturating_mul(new_middle.len()) > MAX_DIFF_CELLS {
+        lines.extend(old_middle.iter().map(|l| DiffLine::Removed(l)));
+        lines.extend(new_middle.iter().map(|l| DiffLine::Added(l)));
+    } else {
+        lines.extend(lcs_diff(old_middle, new_middle));
+    }
+    lines.extend(old[old.len() - suffix..].iter().map(|l| DiffLine::Same(l)));
+    lines
+}
+
+/// Diffs two line lists with a longest common subsequence table.
+fn lcs_diff<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<DiffLine<'a>> {
+    // lengths[i][j]: common lines of old[i..] and new[j..]
+    let width = new.len() + 1;
+    let mut lengths = vec![0u32; (old.len() + 1) * width];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i * width + j] = if old[i] == new[j] {
+                lengths[(i + 1) * width + j + 1] + 1
+            } else {
+                lengths[(i + 1) * width + j].max(lengths[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut lines = Vec::with_capacity(old.len() + new.len());
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            lines.push(DiffLine::Same(old[i]));
+            i += 1;
+            j += 1;
+        } else if lengths[(i + 1) * width + j] >= lengths[i * width + j + 1] {
+            lines.push(DiffLine::Removed(old[i]));
+            i += 1;
+        } else {
+            lines.push(DiffLine::Added(new[j]));
+            j += 1;
+        }
+    }
+    lines.extend(old[i..].iter().map(|l| DiffLine::Removed(l)));
+    lines.extend(new[j..].iter().map(|l| DiffLine::Added(l)));
+    lines
+}
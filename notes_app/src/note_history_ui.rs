@@ -0,0 +1,266 @@
+//! # Note History UI Module
+//!
+//! The "Version History" window of a note, opened from the context menu.
+//! It lists the revisions kept by the note history module, newest first,
+//! and shows what changed between the selected one and the current note or
+//! the version that followed it. Restoring a revision keeps the current
+//! version in the history first, so a restore can be undone the same way.
+
+use crate::app::NotesApp;
+use crate::note_history::{self, DiffLine, NoteHistory};
+use eframe::egui;
+
+/// State of the "Version History" window.
+pub struct NoteHistoryDialog {
+    /// ID of the note
+    note_id: String,
+    /// The revisions, decrypted when the window was opened
+    history: NoteHistory,
+    /// Index of the selected revision
+    selected: Option<usize>,
+    /// Whether the selected revision is compared with the version after
+    /// it rather than with the current note
+    compare_with_next: bool,
+}
+
+impl NotesApp {
+    /// Opens the version history of a note.
+    ///
+    /// Unsaved changes are saved first, so the history is up to date.
+    ///
+    /// # Arguments
+    ///
+    /// * `note_id` - ID of the note
+    pub fn open_note_history(&mut self, note_id: &str) {
+        if self.notes.get(note_id).is_some_and(|note| note.shared) {
+            self.show_status("Notes in the shared vault have no version history".to_string());
+            return;
+        }
+        if self.has_unsaved_changes {
+            self.save_notes();
+        }
+        let (Some(crypto_manager), Some(user)) = (&self.crypto_manager, &self.current_user) else {
+            return;
+        };
+
+        match self
+            .storage_manager
+            .load_note_history(&user.id, note_id, crypto_manager)
+        {
+            Ok(history) => {
+                let selected = history.revisions.len().checked_sub(1);
+                self.note_history_dialog = Some(NoteHistoryDialog {
+                    note_id: note_id.to_string(),
+                    history,
+                    selected,
+                    compare_with_next: false,
+                });
+            }
+            Err(e) => {
+                log::error!("Failed to load the history of note {}: {}", note_id, e);
+                self.show_status(format!("Could not open the version history: {}", e));
+            }
+        }
+    }
+
+    /// Renders the "Version History" window, if open.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The egui context for rendering
+    pub fn render_note_history_dialog(&mut self, ctx: &egui::Context) {
+        let Some(note_id) = self
+            .note_history_dialog
+            .as_ref()
+            .map(|dialog| dialog.note_id.clone())
+        else {
+            return;
+        };
+        let locked_elsewhere = self.note_locked_elsewhere(&note_id).is_some();
+        let (Some(dialog), Some(note)) = (&mut self.note_history_dialog, self.notes.get(&note_id))
+        else {
+            self.note_history_dialog = None;
+            return;
+        };
+        let read_only = note.is_trashed() || locked_elsewhere;
+
+        let mut open = true;
+        let mut restore = None;
+        egui::Window::new(format!("🕓 Version History — {}", note.display_title()))
+            .open(&mut open)
+            .default_size([720.0, 480.0])
+            .show(ctx, |ui| {
+                if dialog.history.revisions.is_empty() {
+                    ui.label("No earlier versions yet.");
+                    ui.small(format!(
+                        "A version is kept when a save overwrites the note, at most one every \
+                         {} minutes and the last {} per note.",
+                        note_history::REVISION_INTERVAL_MINUTES,
+                        note_history::MAX_REVISIONS
+                    ));
+                    return;
+                }
+
+                ui.horizontal_top(|ui| {
+                    // Revisions, newest first
+                    ui.vertical(|ui| {
+                        ui.set_width(220.0);
+                        egui::ScrollArea::vertical()
+                            .id_salt("note_history_revisions")
+                            .auto_shrink([false, false])
+                            .show(ui, |ui| {
+                                for (index, revision) in
+                                    dialog.history.revisions.iter().enumerate().rev()
+                                {
+                                    let label = format!(
+                                        "{}\n{}",
+                                        revision.note.format_modified_time(),
+                                        revision.note.display_title()
+                                    );
+                                    if ui
+                                        .selectable_label(dialog.selected == Some(index), label)
+                                        .clicked()
+                                    {
+                                        dialog.selected = Some(index);
+                                    }
+                                }
+                            });
+                    });
+                    ui.separator();
+
+                    ui.vertical(|ui| {
+                        let Some(index) = dialog.selected else {
+                            ui.weak("Select a version to see what changed");
+                            return;
+                        };
+                        let revision = &dialog.history.revisions[index].note;
+                        let (newer, newer_label) = match dialog.history.revisions.get(index + 1) {
+                            Some(next) if dialog.compare_with_next => {
+                                (&next.note, "the version after it")
+                            }
+                            _ => (note, "the current note"),
+                        };
+
+                        ui.horizontal(|ui| {
+                            ui.label("Compare with:");
+                            ui.radio_value(&mut dialog.compare_with_next, false, "Current note");
+                            ui.radio_value(&mut dialog.compare_with_next, true, "Version after it");
+                            ui.with_layout(
+                                egui::Layout::right_to_left(egui::Align::Center),
+                                |ui| {
+                                    if ui
+                                        .add_enabled(
+                                            !read_only,
+                                            egui::Button::new("Restore This Version"),
+                                        )
+                                        .on_hover_text(
+                                            "Replaces the title and text of the note; the \
+                                             current version is kept in the history",
+                                        )
+                                        .on_disabled_hover_text(
+                                            "The note is in the trash or being edited elsewhere",
+                                        )
+                                        .clicked()
+                                    {
+                                        restore = Some(index);
+                                    }
+                                },
+                            );
+                        });
+                        ui.small(format!(
+                            "Changes from the version of {} to {}: − removed, + added",
+                            revision.format_modified_time(),
+                            newer_label
+                        ));
+                        if revision.title != newer.title {
+                            ui.label(format!(
+                                "Title: \"{}\" → \"{}\"",
+                                revision.title, newer.title
+                            ));
+                        }
+                        ui.separator();
+
+                        let lines = note_history::diff_lines(&revision.content, &newer.content);
+                        if lines.iter().all(|line| matches!(line, DiffLine::Same(_))) {
+                            ui.weak("The text is the same");
+                        }
+                        let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+                        egui::ScrollArea::both()
+                            .id_salt("note_history_diff")
+                            .auto_shrink([false, false])
+                            .show_rows(ui, row_height, lines.len(), |ui, rows| {
+                                for line in &lines[rows] {
+                                    let (text, color) = match line {
+                                        DiffLine::Same(text) => {
+                                            (format!("  {}", text), ui.visuals().weak_text_color())
+                                        }
+                                        DiffLine::Removed(text) => (
+                                            format!("− {}", text),
+                                            egui::Color32::from_rgb(255, 100, 100),
+                                        ),
+                                        DiffLine::Added(text) => (
+                                            format!("+ {}", text),
+                                            egui::Color32::from_rgb(100, 200, 100),
+                                        ),
+                                    };
+                                    ui.label(egui::RichText::new(text).monospace().color(color));
+                                }
+                            });
+                    });
+                });
+            });
+
+        if !open {
+            self.note_history_dialog = None;
+        } else if let Some(index) = restore {
+            self.restore_revision(index);
+        }
+    }
+
+    /// Replaces the note of the history window with one of its revisions.
+    ///
+    /// Only the title, text and secrets entries are restored; pins, tags,
+    /// attachments and other properties stay as they are now.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Index of the revision in the window's history
+    fn restore_revision(&mut self, index: usize) {
+        let Some(dialog) = self.note_history_dialog.take() else {
+            return;
+        };
+        let Some(revision) = dialog.history.revisions.into_iter().nth(index) else {
+            return;
+        };
+        let note_id = dialog.note_id;
+        let version = revision.note.format_modified_time();
+
+        // The current version must be on disk to be kept in the history
+        if self.has_unsaved_changes {
+            self.save_notes();
+        }
+        let (Some(crypto_manager), Some(user)) = (&self.crypto_manager, &self.current_user) else {
+            return;
+        };
+        if !self
+            .revision_tracker
+            .record(&self.storage_manager, &user.id, &note_id, crypto_manager)
+        {
+            self.show_status("Could not keep the current version, nothing was restored".into());
+            return;
+        }
+
+        let Some(note) = self.notes.get_mut(&note_id) else {
+            return;
+        };
+        note.title = revision.note.title;
+        note.content = revision.note.content;
+        note.secrets = revision.note.secrets;
+        note.auto_title = revision.note.auto_title;
+        note.update_modified_time();
+        self.search_index.note_changed(note);
+        self.save_notes();
+        log::info!("Restored a revision of note {}", note_id);
+        self.show_status(format!("Restored the version of {}", version));
+    }
+}
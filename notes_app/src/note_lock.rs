@@ -0,0 +1,86 @@
+//! # Note Lock Module
+//!
+//! Advisory locks marking a note as being edited. Each running instance of
+//! the app holds a lock on the note it has open and refreshes it with a
+//! heartbeat. Another instance (a second window, or another device sharing
+//! the data directory) that opens the same note finds the lock and shows
+//! the note read-only until the lock is released or taken over.
+//!
+//! Locks are advisory: nothing stops a file from being written, but the app
+//! checks them before editing and saving. A lock whose heartbeat stopped,
+//! e.g. because its instance crashed, expires after [`LOCK_TIMEOUT_SECS`].
+//!
+//! Lock files only hold an instance ID, a device name and a timestamp, so
+//! they are stored unencrypted.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// How often the lock on the open note is refreshed and re-checked.
+pub const LOCK_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Seconds without a heartbeat after which a lock is considered abandoned.
+pub const LOCK_TIMEOUT_SECS: i64 = 30;
+
+/// Lock on a note held by one running instance of the app.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NoteLock {
+    /// Random ID of the app instance holding the lock
+    pub instance_id: String,
+    /// Name of the computer the instance runs on
+    pub device: String,
+    /// UTC timestamp of the last heartbeat
+    pub heartbeat_at: DateTime<Utc>,
+}
+
+impl NoteLock {
+    /// Creates a lock for an instance on this computer, with a fresh heartbeat.
+    ///
+    /// # Arguments
+    ///
+    /// * `instance_id` - ID of the app instance taking the lock
+    ///
+    /// # Returns
+    ///
+    /// * `Self` - A new NoteLock
+    pub fn new(instance_id: &str) -> Self {
+        Self {
+            instance_id: instance_id.to_string(),
+            device: device_name(),
+            heartbeat_at: Utc::now(),
+        }
+    }
+
+    /// Checks whether the lock was abandoned by its instance.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - True if the last heartbeat is older than the lock timeout
+    pub fn is_stale(&self) -> bool {
+        Utc::now()
+            .signed_duration_since(self.heartbeat_at)
+            .num_seconds()
+            > LOCK_TIMEOUT_SECS
+    }
+
+    /// Describes where the locked note is being edited, for display.
+    ///
+    /// # Returns
+    ///
+    /// * `String` - "in another window" on this computer, or "on <device>"
+    pub fn location(&self) -> String {
+        if self.device == device_name() {
+            "in another window".to_string()
+        } else {
+            format!("on {}", self.device)
+        }
+    }
+}
+
+/// Returns the name of this computer, as far as the environment tells.
+fn device_name() -> String {
+    std::env::var("COMPUTERNAME")
+        .or_else(|_| std::env::var("HOSTNAME"))
+        .unwrap_or_else(|_| "another device".to_string())
+}
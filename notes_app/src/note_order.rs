@@ -0,0 +1,108 @@
+//! # Note Order Module
+//!
+//! Sorting the sidebar by the order chosen in the sort selector, and
+//! moving a note in the manual order when it is dragged onto another one.
+//!
+//! The manual order is a list of note IDs kept in the encrypted user
+//! settings. Notes missing from it, e.g. created since the last drag, come
+//! first, newest first, so a new note shows up at the top as with the
+//! other orders. Pinned notes have an order of their own, kept the same
+//! way, which doesn't depend on the sort selector.
+
+use crate::note::Note;
+use crate::settings::NoteSort;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+/// Sorts notes for the sidebar.
+///
+/// # Arguments
+///
+/// * `notes` - The notes to sort
+/// * `sort` - The order chosen in the sort selector
+/// * `manual_order` - Note IDs in the manual order, for `NoteSort::Manual`
+pub fn sort_notes(notes: &mut [&Note], sort: NoteSort, manual_order: &[String]) {
+    match sort {
+        NoteSort::Modified => notes.sort_by_key(|note| Reverse(note.modified_at)),
+        NoteSort::Created => notes.sort_by_key(|note| Reverse(note.created_at)),
+        NoteSort::TitleAscending => notes.sort_by_cached_key(|note| title_key(note)),
+        NoteSort::TitleDescending => notes.sort_by_cached_key(|note| Reverse(title_key(note))),
+        NoteSort::Manual => {
+            let positions: HashMap<&str, usize> = manual_order
+                .iter()
+                .enumerate()
+                .map(|(position, id)| (id.as_str(), position))
+                .collect();
+            // None sorts before any position
+            notes.sort_by_key(|note| {
+                (
+                    positions.get(note.id.as_str()).copied(),
+                    Reverse(note.modified_at),
+                )
+            });
+        }
+    }
+}
+
+/// Key sorting titles case-insensitively, ties broken by the exact title.
+fn title_key(note: &Note) -> (String, String) {
+    let title = note.display_title();
+    (title.to_lowercase(), title)
+}
+
+/// Lists the pinned notes in the order of the Pinned section.
+///
+/// # Arguments
+///
+/// * `notes` - All notes of the user
+/// * `pinned_order` - The order the pinned notes were dragged to
+///
+/// # Returns
+///
+/// * `Vec<String>` - IDs of the pinned notes not in the trash, top first
+pub fn pinned_ids(notes: &HashMap<String, Note>, pinned_order: &[String]) -> Vec<String> {
+    let mut pinned: Vec<&Note> = notes
+        .values()
+        .filter(|note| note.pinned && !note.is_trashed())
+        .collect();
+    sort_notes(&mut pinned, NoteSort::Manual, pinned_order);
+    pinned.into_iter().map(|note| note.id.clone()).collect()
+}
+
+/// Moves a note to the place of another one in a manual order.
+///
+/// A note dragged down ends up below the target, a note dragged up above
+/// it, as in any list reordered by dragging.
+///
+/// # Arguments
+///
+/// * `notes` - The notes in the order, e.g. all notes or the pinned ones
+/// * `manual_order` - The current order
+/// * `dragged` - ID of the dragged note
+/// * `target` - ID of the note it was dropped on
+///
+/// # Returns
+///
+/// * `Option<Vec<String>>` - The new order of the notes, None if either
+///   note isn't among them or they are the same
+pub fn move_note<'a>(
+    notes: impl IntoIterator<Item = &'a Note>,
+    manual_order: &[String],
+    dragged: &str,
+    target: &str,
+) -> Option<Vec<String>> {
+    if dragged == target {
+        return None;
+    }
+
+    // The order as shown, so notes missing from it keep their place
+    let mut all: Vec<&Note> = notes.into_iter().collect();
+    sort_notes(&mut all, NoteSort::Manual, manual_order);
+    let mut order: Vec<String> = all.into_iter().map(|note| note.id.clone()).collect();
+
+    let from = order.iter().position(|id| id == dragged)?;
+    let to = order.iter().position(|id| id == target)?;
+    let id = order.remove(from);
+    order.insert(to, id);
+    Some(order)
+}
@@ -0,0 +1,186 @@
+//! # Note Share Module
+//!
+//! Shares a single note as a block of encrypted text that can be pasted
+//! into an email or a chat and imported by another Secure Notes user who
+//! knows the passphrase:
+//!
+//! ```text
+//! -----BEGIN SECURE NOTES MESSAGE-----
+//! U05TSAEAAAIAAwAAAAQAAAD...
+//! -----END SECURE NOTES MESSAGE-----
+//! ```
+//!
+//! Between the armor lines is the base64 of the note's title, content,
+//! properties and secret entries encrypted by the passphrase module with
+//! the magic bytes "SNSH". Attachments, comments and the notebook stay
+//! behind.
+
+use crate::note::{Note, NoteKind, SecretEntry};
+use crate::passphrase::{self, SealedFormat};
+use anyhow::{anyhow, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// First line of an encrypted note.
+pub const ARMOR_BEGIN: &str = "-----BEGIN SECURE NOTES MESSAGE-----";
+
+/// Last line of an encrypted note.
+pub const ARMOR_END: &str = "-----END SECURE NOTES MESSAGE-----";
+
+/// Number of base64 characters per line between the armor lines.
+const LINE_WIDTH: usize = 64;
+
+/// Format of shared notes.
+const SHARE_FORMAT: SealedFormat = SealedFormat {
+    magic: b"SNSH",
+    version: 1,
+    name: "encrypted note",
+};
+
+/// The part of a note that is shared.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SharedNote {
+    /// Title of the note
+    pub title: String,
+    /// Content of the note
+    pub content: String,
+    /// Syntax token of a code note
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// Custom properties of the note
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub fields: BTreeMap<String, String>,
+    /// Tags added in the tag editor
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Whether this is a plain note or a secrets note
+    #[serde(default, skip_serializing_if = "NoteKind::is_text")]
+    pub kind: NoteKind,
+    /// Entries of a secrets note
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub secrets: Vec<SecretEntry>,
+    /// UTC timestamp when the note was created
+    pub created_at: DateTime<Utc>,
+    /// UTC timestamp when the note was last modified
+    pub modified_at: DateTime<Utc>,
+}
+
+impl SharedNote {
+    /// Takes the shared part of a note.
+    ///
+    /// # Arguments
+    ///
+    /// * `note` - The note to share
+    ///
+    /// # Returns
+    ///
+    /// * `Self` - Title, content, properties and tags of the note
+    pub fn from_note(note: &Note) -> Self {
+        Self {
+            title: note.title.clone(),
+            content: note.content.clone(),
+            language: note.language.clone(),
+            fields: note.fields.clone(),
+            tags: note.tags.clone(),
+            kind: note.kind,
+            secrets: note.secrets.clone(),
+            created_at: note.created_at,
+            modified_at: note.modified_at,
+        }
+    }
+
+    /// Creates a new note from the shared one.
+    ///
+    /// # Arguments
+    ///
+    /// * `title` - Title of the new note, made unique by the caller
+    ///
+    /// # Returns
+    ///
+    /// * `Note` - A note with a new ID and the shared content
+    pub fn into_note(self, title: String) -> Note {
+        let mut note = Note::new(title);
+        note.content = self.content;
+        note.language = self.language;
+        note.fields = self.fields;
+        note.tags = self.tags;
+        note.kind = self.kind;
+        note.secrets = self.secrets;
+        note.created_at = self.created_at;
+        note.modified_at = self.modified_at;
+        note
+    }
+}
+
+/// Encrypts a note into armored text.
+///
+/// # Arguments
+///
+/// * `note` - The note to share
+/// * `passphrase` - Passphrase the recipient needs to open it
+///
+/// # Returns
+///
+/// * `Result<String>` - The armored text, or error if the passphrase is
+///   too short
+pub fn seal_note(note: &Note, passphrase: &str) -> Result<String> {
+    let plaintext = serde_json::to_vec(&SharedNote::from_note(note))?;
+    let data = passphrase::seal(&SHARE_FORMAT, &plaintext, passphrase)?;
+    let encoded = STANDARD.encode(data);
+
+    let mut armored = String::with_capacity(encoded.len() + encoded.len() / LINE_WIDTH + 80);
+    armored.push_str(ARMOR_BEGIN);
+    armored.push('\n');
+    for line in encoded.as_bytes().chunks(LINE_WIDTH) {
+        armored.push_str(std::str::from_utf8(line)?);
+        armored.push('\n');
+    }
+    armored.push_str(ARMOR_END);
+    armored.push('\n');
+    Ok(armored)
+}
+
+/// Decrypts a note from armored text written by `seal_note`.
+///
+/// Text around the armor lines is ignored, as are the "> " prefixes an
+/// email client adds when quoting.
+///
+/// # Arguments
+///
+/// * `text` - The pasted text
+/// * `passphrase` - Passphrase the note was encrypted with
+///
+/// # Returns
+///
+/// * `Result<SharedNote>` - The shared note, or error if no encrypted note
+///   was found, the passphrase is wrong or the text was altered
+pub fn open_note(text: &str, passphrase: &str) -> Result<SharedNote> {
+    let data = STANDARD
+        .decode(armored_body(text)?)
+        .map_err(|_| anyhow!("The encrypted note is damaged, copy it again"))?;
+    let plaintext = passphrase::open(&SHARE_FORMAT, &data, passphrase)?;
+    Ok(serde_json::from_slice(&plaintext)?)
+}
+
+/// Extracts the base64 between the armor lines.
+fn armored_body(text: &str) -> Result<String> {
+    let mut lines = text
+        .lines()
+        .map(|line| line.trim_start_matches(['>', ' ', '\t']).trim_end());
+
+    if !lines.by_ref().any(|line| line == ARMOR_BEGIN) {
+        return Err(anyhow!("No encrypted note found in the text"));
+    }
+
+    let mut body = String::new();
+    for line in lines {
+        if line == ARMOR_END {
+            return Ok(body);
+        }
+        body.extend(line.chars().filter(|c| !c.is_whitespace()));
+    }
+    Err(anyhow!("The encrypted note is incomplete, copy all of it"))
+}
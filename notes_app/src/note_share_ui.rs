@@ -0,0 +1,338 @@
+//! # Note Share UI Module
+//!
+//! Dialogs for sharing a note as encrypted text: one encrypts the note
+//! from the context menu with a passphrase and copies the text to the
+//! clipboard, the other opens pasted text and adds the note it contains.
+//! Deriving the passphrase key takes a moment, so it runs in a
+//! [`PassphraseTask`] while the dialog shows a spinner.
+
+use crate::app::NotesApp;
+use crate::note::UNTITLED_NOTE_TITLE;
+use crate::note_share::{self, SharedNote};
+use crate::passphrase::{PassphraseTask, MIN_PASSPHRASE_LEN};
+use eframe::egui;
+
+/// State of the note share dialog.
+pub enum NoteShareDialog {
+    /// Encrypting a note for the clipboard
+    Copy {
+        /// ID of the note to share
+        note_id: String,
+        /// Passphrase input
+        passphrase: String,
+        /// Passphrase confirmation input
+        confirm_passphrase: String,
+        /// Error of the last attempt
+        error: Option<String>,
+        /// Encryption in progress, yielding the armored text
+        task: Option<PassphraseTask<String>>,
+    },
+    /// Importing a note from pasted text
+    Paste {
+        /// The pasted text
+        text: String,
+        /// Passphrase input
+        passphrase: String,
+        /// Error of the last attempt
+        error: Option<String>,
+        /// Decryption in progress
+        task: Option<PassphraseTask<SharedNote>>,
+    },
+}
+
+impl NoteShareDialog {
+    /// Creates the dialog for encrypting a note.
+    ///
+    /// # Arguments
+    ///
+    /// * `note_id` - ID of the note to share
+    ///
+    /// # Returns
+    ///
+    /// * `Self` - The dialog with empty inputs
+    pub fn copy(note_id: String) -> Self {
+        Self::Copy {
+            note_id,
+            passphrase: String::new(),
+            confirm_passphrase: String::new(),
+            error: None,
+            task: None,
+        }
+    }
+
+    /// Creates the dialog for importing pasted text.
+    pub fn paste() -> Self {
+        Self::Paste {
+            text: String::new(),
+            passphrase: String::new(),
+            error: None,
+            task: None,
+        }
+    }
+}
+
+/// Button pressed in the dialog, handled after the window closure.
+enum ShareAction {
+    Copy,
+    Import,
+    Close,
+}
+
+impl NotesApp {
+    /// Renders the note share dialog, if open.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The egui context for rendering
+    pub fn render_note_share_dialog(&mut self, ctx: &egui::Context) {
+        self.poll_note_share_task(ctx);
+        let Some(dialog) = &mut self.note_share_dialog else {
+            return;
+        };
+
+        let mut action = None;
+        let mut open = true;
+        let title = match dialog {
+            NoteShareDialog::Copy { .. } => "Copy as Encrypted Text",
+            NoteShareDialog::Paste { .. } => "Paste Encrypted Note",
+        };
+        let note_title = match dialog {
+            NoteShareDialog::Copy { note_id, .. } => self
+                .notes
+                .get(note_id)
+                .map(|note| note.title.clone())
+                .unwrap_or_default(),
+            NoteShareDialog::Paste { .. } => String::new(),
+        };
+
+        egui::Window::new(title)
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .default_width(420.0)
+            .show(ctx, |ui| match dialog {
+                NoteShareDialog::Copy {
+                    passphrase,
+                    confirm_passphrase,
+                    error,
+                    task,
+                    ..
+                } => {
+                    ui.label(format!(
+                        "Encrypts \"{}\" into text you can paste into an email or a chat. \
+                         The recipient needs Secure Notes and the passphrase.",
+                        note_title
+                    ));
+                    ui.small("Attachments and comments are not included.");
+                    ui.add_space(5.0);
+
+                    ui.label(format!(
+                        "Passphrase (at least {} characters):",
+                        MIN_PASSPHRASE_LEN
+                    ));
+                    ui.add(egui::TextEdit::singleline(passphrase).password(true));
+                    ui.label("Confirm passphrase:");
+                    ui.add(egui::TextEdit::singleline(confirm_passphrase).password(true));
+
+                    if let Some(error) = error {
+                        ui.colored_label(egui::Color32::RED, error.as_str());
+                    }
+
+                    ui.add_space(5.0);
+                    ui.horizontal(|ui| {
+                        if ui
+                            .add_enabled(task.is_none(), egui::Button::new("Copy"))
+                            .on_hover_text("Takes a few seconds")
+                            .clicked()
+                        {
+                            action = Some(ShareAction::Copy);
+                        }
+                        if ui.button("Cancel").clicked() {
+                            action = Some(ShareAction::Close);
+                        }
+                        if task.is_some() {
+                            ui.spinner();
+                            ui.label("Encrypting…");
+                        }
+                    });
+                    ui.small("Send the passphrase separately, e.g. by phone.");
+                }
+                NoteShareDialog::Paste {
+                    text,
+                    passphrase,
+                    error,
+                    task,
+                } => {
+                    ui.label(format!(
+                        "Paste the text starting with {}.",
+                        note_share::ARMOR_BEGIN
+                    ));
+                    egui::ScrollArea::vertical()
+                        .max_height(200.0)
+                        .show(ui, |ui| {
+                            ui.add(
+                                egui::TextEdit::multiline(text)
+                                    .font(egui::TextStyle::Monospace)
+                                    .desired_rows(8)
+                                    .desired_width(f32::INFINITY),
+                            );
+                        });
+                    ui.label("Passphrase:");
+                    ui.add(egui::TextEdit::singleline(passphrase).password(true));
+
+                    if let Some(error) = error {
+                        ui.colored_label(egui::Color32::RED, error.as_str());
+                    }
+
+                    ui.add_space(5.0);
+                    ui.horizontal(|ui| {
+                        let can_import =
+                            !text.trim().is_empty() && !passphrase.is_empty() && task.is_none();
+                        if ui
+                            .add_enabled(can_import, egui::Button::new("Import"))
+                            .clicked()
+                        {
+                            action = Some(ShareAction::Import);
+                        }
+                        if ui.button("Cancel").clicked() {
+                            action = Some(ShareAction::Close);
+                        }
+                        if task.is_some() {
+                            ui.spinner();
+                            ui.label("Decrypting…");
+                        }
+                    });
+                }
+            });
+
+        if !open {
+            action = Some(ShareAction::Close);
+        }
+        match action {
+            Some(ShareAction::Copy) => self.copy_note_as_encrypted_text(ctx),
+            Some(ShareAction::Import) => self.import_encrypted_note(ctx),
+            Some(ShareAction::Close) => self.note_share_dialog = None,
+            None => {}
+        }
+    }
+
+    /// Starts encrypting the note of the dialog for the clipboard.
+    fn copy_note_as_encrypted_text(&mut self, ctx: &egui::Context) {
+        let Some(NoteShareDialog::Copy {
+            note_id,
+            passphrase,
+            confirm_passphrase,
+            error,
+            task,
+        }) = &mut self.note_share_dialog
+        else {
+            return;
+        };
+
+        if passphrase != confirm_passphrase {
+            *error = Some("The passphrases don't match".to_string());
+            return;
+        }
+        let Some(note) = self.notes.get(note_id.as_str()) else {
+            *error = Some("The note no longer exists".to_string());
+            return;
+        };
+
+        let note = note.clone();
+        let passphrase = passphrase.clone();
+        *error = None;
+        *task = Some(PassphraseTask::spawn(ctx, move || {
+            note_share::seal_note(&note, &passphrase)
+        }));
+    }
+
+    /// Starts opening the pasted text.
+    fn import_encrypted_note(&mut self, ctx: &egui::Context) {
+        let Some(NoteShareDialog::Paste {
+            text,
+            passphrase,
+            error,
+            task,
+        }) = &mut self.note_share_dialog
+        else {
+            return;
+        };
+
+        let text = text.clone();
+        let passphrase = passphrase.clone();
+        *error = None;
+        *task = Some(PassphraseTask::spawn(ctx, move || {
+            note_share::open_note(&text, &passphrase)
+        }));
+    }
+
+    /// Handles the result of a finished encryption or decryption.
+    ///
+    /// Copies the encrypted text to the clipboard, or adds the note of the
+    /// pasted text. On error the dialog stays open with the message.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The egui context, for the clipboard
+    fn poll_note_share_task(&mut self, ctx: &egui::Context) {
+        match &mut self.note_share_dialog {
+            Some(NoteShareDialog::Copy {
+                note_id,
+                error,
+                task,
+                ..
+            }) => match task.as_ref().and_then(PassphraseTask::poll) {
+                Some(Ok(armored)) => {
+                    ctx.copy_text(armored);
+                    log::info!("Copied note {} as encrypted text", note_id);
+                    self.note_share_dialog = None;
+                    self.show_status(
+                        "Encrypted note copied, share the passphrase separately".to_string(),
+                    );
+                }
+                Some(Err(e)) => {
+                    *error = Some(e.to_string());
+                    *task = None;
+                }
+                None => {}
+            },
+            Some(NoteShareDialog::Paste { error, task, .. }) => {
+                match task.as_ref().and_then(PassphraseTask::poll) {
+                    Some(Ok(shared)) => {
+                        self.note_share_dialog = None;
+                        self.add_shared_note(shared);
+                    }
+                    Some(Err(e)) => {
+                        *error = Some(e.to_string());
+                        *task = None;
+                    }
+                    None => {}
+                }
+            }
+            None => {}
+        }
+    }
+
+    /// Adds the note of opened encrypted text and selects it.
+    ///
+    /// # Arguments
+    ///
+    /// * `shared` - The decrypted note
+    fn add_shared_note(&mut self, shared: SharedNote) {
+        let title = if shared.title.trim().is_empty() {
+            UNTITLED_NOTE_TITLE
+        } else {
+            shared.title.as_str()
+        };
+        let title = self.unique_note_title(title);
+        let note = shared.into_note(title);
+        let note_id = note.id.clone();
+        log::info!("Imported note {} from encrypted text", note_id);
+
+        self.show_status(format!("Imported \"{}\"", note.title));
+        self.search_index.note_changed(&note);
+        self.notes.insert(note_id.clone(), note);
+        self.selected_note_id = Some(note_id);
+        self.save_notes();
+    }
+}
@@ -0,0 +1,169 @@
+//! # Note Statistics Module
+//!
+//! Counts notes and words per tag and per notebook, for the statistics
+//! window. A note with several tags counts towards each of them, so the
+//! tag rows can add up to more than the total; every note is in exactly
+//! one notebook row. Notes in the trash are left out.
+
+use crate::note::Note;
+use chrono::{DateTime, Utc};
+use chrono_tz::Europe::Zurich;
+use std::collections::BTreeMap;
+
+/// Notes and words of one tag or notebook.
+#[derive(Debug, Clone)]
+pub struct GroupStats {
+    /// The tag or notebook, None for untagged notes or notes outside any
+    /// notebook
+    pub name: Option<String>,
+    /// Number of notes
+    pub note_count: usize,
+    /// Number of words of the notes
+    pub word_count: usize,
+    /// Latest modification of the notes
+    pub last_activity: DateTime<Utc>,
+}
+
+impl GroupStats {
+    /// Starts a group with its first note.
+    fn new(name: Option<String>, note: &Note, words: usize) -> Self {
+        Self {
+            name,
+            note_count: 1,
+            word_count: words,
+            last_activity: note.modified_at,
+        }
+    }
+
+    /// Returns the latest modification in Swiss time, e.g. "15.12.2024 14:30".
+    pub fn format_last_activity(&self) -> String {
+        self.last_activity
+            .with_timezone(&Zurich)
+            .format("%d.%m.%Y %H:%M")
+            .to_string()
+    }
+
+    /// Adds a note to the group.
+    fn add(&mut self, note: &Note, words: usize) {
+        self.note_count += 1;
+        self.word_count += words;
+        self.last_activity = self.last_activity.max(note.modified_at);
+    }
+}
+
+/// Order of the rows of a breakdown.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StatsOrder {
+    /// Most words first
+    #[default]
+    Words,
+    /// Most notes first
+    Notes,
+    /// Most recently modified first
+    LastActivity,
+    /// Alphabetically
+    Name,
+}
+
+impl StatsOrder {
+    /// All orders, in the order they are offered.
+    pub const ALL: [StatsOrder; 4] = [
+        StatsOrder::Words,
+        StatsOrder::Notes,
+        StatsOrder::LastActivity,
+        StatsOrder::Name,
+    ];
+
+    /// Returns the name shown in the order selector.
+    pub fn label(self) -> &'static str {
+        match self {
+            StatsOrder::Words => "Words",
+            StatsOrder::Notes => "Notes",
+            StatsOrder::LastActivity => "Last activity",
+            StatsOrder::Name => "Name",
+        }
+    }
+}
+
+/// Notes and words in total, per tag and per notebook.
+#[derive(Debug, Clone, Default)]
+pub struct NoteStats {
+    /// Number of notes outside the trash
+    pub note_count: usize,
+    /// Number of words of those notes
+    pub word_count: usize,
+    /// One row per tag, plus one for untagged notes
+    pub by_tag: Vec<GroupStats>,
+    /// One row per notebook, plus one for notes outside any notebook
+    pub by_notebook: Vec<GroupStats>,
+}
+
+impl NoteStats {
+    /// Counts the notes.
+    ///
+    /// # Arguments
+    ///
+    /// * `notes` - All notes in memory
+    ///
+    /// # Returns
+    ///
+    /// * `Self` - The statistics, rows ordered by `StatsOrder::Words`
+    pub fn collect<'a>(notes: impl IntoIterator<Item = &'a Note>) -> Self {
+        let mut stats = Self::default();
+        let mut by_tag: BTreeMap<Option<String>, GroupStats> = BTreeMap::new();
+        let mut by_notebook: BTreeMap<Option<String>, GroupStats> = BTreeMap::new();
+
+        for note in notes.into_iter().filter(|note| !note.is_trashed()) {
+            let words = note.word_count();
+            stats.note_count += 1;
+            stats.word_count += words;
+
+            let mut tags: Vec<Option<String>> = note.tags().into_iter().map(Some).collect();
+            if tags.is_empty() {
+                tags.push(None);
+            }
+            for tag in tags {
+                by_tag
+                    .entry(tag.clone())
+                    .and_modify(|group| group.add(note, words))
+                    .or_insert_with(|| GroupStats::new(tag, note, words));
+            }
+
+            let notebook = note.notebook.clone();
+            by_notebook
+                .entry(notebook.clone())
+                .and_modify(|group| group.add(note, words))
+                .or_insert_with(|| GroupStats::new(notebook, note, words));
+        }
+
+        stats.by_tag = by_tag.into_values().collect();
+        stats.by_notebook = by_notebook.into_values().collect();
+        stats.sort(StatsOrder::default());
+        stats
+    }
+
+    /// Orders the rows of both breakdowns.
+    ///
+    /// Rows for untagged notes and notes outside any notebook always come
+    /// last.
+    ///
+    /// # Arguments
+    ///
+    /// * `order` - The order to sort by
+    pub fn sort(&mut self, order: StatsOrder) {
+        for groups in [&mut self.by_tag, &mut self.by_notebook] {
+            groups.sort_by(|a, b| {
+                a.name
+                    .is_none()
+                    .cmp(&b.name.is_none())
+                    .then_with(|| match order {
+                        StatsOrder::Words => b.word_count.cmp(&a.word_count),
+                        StatsOrder::Notes => b.note_count.cmp(&a.note_count),
+                        StatsOrder::LastActivity => b.last_activity.cmp(&a.last_activity),
+                        StatsOrder::Name => std::cmp::Ordering::Equal,
+                    })
+                    .then_with(|| a.name.cmp(&b.name))
+            });
+        }
+    }
+}
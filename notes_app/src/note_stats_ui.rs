@@ -0,0 +1,168 @@
+//! # Note Statistics UI Module
+//!
+//! The statistics window: the number of notes and words in total and a
+//! breakdown per tag and per notebook, with a bar showing each row's share
+//! of all words. The statistics are counted when the window opens or is
+//! refreshed, not on every frame.
+
+use crate::app::NotesApp;
+use crate::note_stats::{GroupStats, NoteStats, StatsOrder};
+use eframe::egui;
+
+/// The statistics and how their rows are ordered.
+pub struct NoteStatsView {
+    /// The counted statistics
+    stats: NoteStats,
+    /// Order of the rows
+    order: StatsOrder,
+}
+
+impl NotesApp {
+    /// Counts the notes and opens the statistics window.
+    pub fn open_note_stats(&mut self) {
+        let order = self
+            .note_stats_view
+            .as_ref()
+            .map_or(StatsOrder::default(), |view| view.order);
+        let mut stats = NoteStats::collect(self.notes.values());
+        stats.sort(order);
+        self.note_stats_view = Some(NoteStatsView { stats, order });
+    }
+
+    /// Renders the statistics window, if open.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The egui context for rendering
+    pub fn render_note_stats(&mut self, ctx: &egui::Context) {
+        let Some(view) = &mut self.note_stats_view else {
+            return;
+        };
+
+        let mut open = true;
+        let mut refresh = false;
+        egui::Window::new("📊 Statistics")
+            .open(&mut open)
+            .default_width(480.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(format!(
+                        "{} notes, {} words",
+                        view.stats.note_count, view.stats.word_count
+                    ));
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if ui.button("⟳").on_hover_text("Count again").clicked() {
+                            refresh = true;
+                        }
+                        let previous = view.order;
+                        egui::ComboBox::from_id_salt("note_stats_order")
+                            .selected_text(view.order.label())
+                            .show_ui(ui, |ui| {
+                                for order in StatsOrder::ALL {
+                                    ui.selectable_value(&mut view.order, order, order.label());
+                                }
+                            });
+                        ui.label("Sort by:");
+                        if view.order != previous {
+                            view.stats.sort(view.order);
+                        }
+                    });
+                });
+                ui.small("Notes in the trash are not counted.");
+                ui.separator();
+
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    egui::CollapsingHeader::new(format!("Tags ({})", view.stats.by_tag.len()))
+                        .default_open(true)
+                        .show(ui, |ui| {
+                            ui.small("A note with several tags counts towards each of them.");
+                            render_groups(
+                                ui,
+                                "note_stats_tags",
+                                &view.stats.by_tag,
+                                view.stats.word_count,
+                                |name| format!("#{}", name),
+                                "(untagged)",
+                            );
+                        });
+                    egui::CollapsingHeader::new(format!(
+                        "Notebooks ({})",
+                        view.stats.by_notebook.len()
+                    ))
+                    .default_open(true)
+                    .show(ui, |ui| {
+                        render_groups(
+                            ui,
+                            "note_stats_notebooks",
+                            &view.stats.by_notebook,
+                            view.stats.word_count,
+                            |name| name.to_string(),
+                            "(no notebook)",
+                        );
+                    });
+                });
+            });
+
+        if !open {
+            self.note_stats_view = None;
+        } else if refresh {
+            self.open_note_stats();
+        }
+    }
+}
+
+/// Draws the rows of a breakdown as a table.
+///
+/// # Arguments
+///
+/// * `ui` - The UI to draw into
+/// * `id` - ID of the table
+/// * `groups` - The rows
+/// * `total_words` - Words of all notes, for the share bars
+/// * `label` - Turns a tag or notebook name into the text shown
+/// * `unnamed` - Shown for the row without a name
+fn render_groups(
+    ui: &mut egui::Ui,
+    id: &str,
+    groups: &[GroupStats],
+    total_words: usize,
+    label: impl Fn(&str) -> String,
+    unnamed: &str,
+) {
+    if groups.is_empty() {
+        ui.label("No notes");
+        return;
+    }
+    egui::Grid::new(id)
+        .num_columns(5)
+        .striped(true)
+        .show(ui, |ui| {
+            ui.strong("Name");
+            ui.strong("Notes");
+            ui.strong("Words");
+            ui.strong("Share");
+            ui.strong("Last activity");
+            ui.end_row();
+
+            for group in groups {
+                match &group.name {
+                    Some(name) => ui.label(label(name)),
+                    None => ui.weak(unnamed),
+                };
+                ui.label(group.note_count.to_string());
+                ui.label(group.word_count.to_string());
+                let share = if total_words > 0 {
+                    group.word_count as f32 / total_words as f32
+                } else {
+                    0.0
+                };
+                ui.add(
+                    egui::ProgressBar::new(share)
+                        .desired_width(80.0)
+                        .text(format!("{:.0}%", share * 100.0)),
+                );
+                ui.label(group.format_last_activity());
+                ui.end_row();
+            }
+        });
+}
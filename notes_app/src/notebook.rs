@@ -0,0 +1,163 @@
+//! # Notebook Module
+//!
+//! Notebooks group notes and can be nested like folders. A note stores the
+//! path of its notebook, with the names of the enclosing notebooks
+//! separated by `/`:
+//!
+//! ```text
+//! Work/Clients/Acme   →   Work
+//!                         └── Clients
+//!                             └── Acme
+//! ```
+//!
+//! Notebooks exist as long as a note is in them or in one of their nested
+//! notebooks; the tree shown in the sidebar is built from the notes. Flat
+//! notebook names of earlier versions are top-level notebooks, so they need
+//! no migration. Paths are normalized when notes are loaded, so stray
+//! spaces around separators or empty names don't create separate notebooks.
+
+use crate::note::Note;
+use serde::{Deserialize, Deserializer};
+use std::collections::BTreeMap;
+
+/// Separator between the names of nested notebooks.
+pub const NOTEBOOK_SEPARATOR: char = '/';
+
+/// A notebook with its nested notebooks and the notes directly in it.
+#[derive(Debug, Default)]
+pub struct Notebook {
+    /// Name of the notebook, the last part of its path
+    pub name: String,
+    /// Full path, e.g. "Work/Clients/Acme"
+    pub path: String,
+    /// Nested notebooks by name
+    pub children: BTreeMap<String, Notebook>,
+    /// IDs of the notes directly in this notebook, in the order given
+    pub note_ids: Vec<String>,
+}
+
+impl Notebook {
+    /// Builds the notebook tree of a list of notes.
+    ///
+    /// # Arguments
+    ///
+    /// * `notes` - The notes, in the order they should be listed
+    ///
+    /// # Returns
+    ///
+    /// * `BTreeMap<String, Notebook>` - The top-level notebooks by name
+    pub fn build_tree<'a>(notes: impl IntoIterator<Item = &'a Note>) -> BTreeMap<String, Notebook> {
+        let mut roots: BTreeMap<String, Notebook> = BTreeMap::new();
+        for note in notes {
+            // Paths being typed in the properties panel aren't normalized yet
+            let Some(path) = note.notebook.as_deref().and_then(normalize_path) else {
+                continue;
+            };
+            let mut names = path.split(NOTEBOOK_SEPARATOR);
+            let Some(first) = names.next() else {
+                continue;
+            };
+            let mut notebook = roots
+                .entry(first.to_string())
+                .or_insert_with(|| Notebook::new(first, first.to_string()));
+            for name in names {
+                let child_path = format!("{}{}{}", notebook.path, NOTEBOOK_SEPARATOR, name);
+                notebook = notebook
+                    .children
+                    .entry(name.to_string())
+                    .or_insert_with(|| Notebook::new(name, child_path));
+            }
+            notebook.note_ids.push(note.id.clone());
+        }
+        roots
+    }
+
+    /// Creates an empty notebook.
+    fn new(name: &str, path: String) -> Self {
+        Self {
+            name: name.to_string(),
+            path,
+            ..Self::default()
+        }
+    }
+
+    /// Counts the notes in this notebook and all nested notebooks.
+    pub fn note_count(&self) -> usize {
+        self.note_ids.len()
+            + self
+                .children
+                .values()
+                .map(Notebook::note_count)
+                .sum::<usize>()
+    }
+}
+
+/// Returns all notebook paths used by the notes, including the enclosing
+/// notebooks of nested ones.
+///
+/// # Arguments
+///
+/// * `notes` - The notes
+///
+/// # Returns
+///
+/// * `Vec<String>` - The paths, sorted so nested notebooks follow their
+///   parent
+pub fn all_paths<'a>(notes: impl IntoIterator<Item = &'a Note>) -> Vec<String> {
+    let mut paths: Vec<String> = Vec::new();
+    for path in notes
+        .into_iter()
+        .filter_map(|note| note.notebook.as_deref().and_then(normalize_path))
+    {
+        let mut end = 0;
+        for name in path.split(NOTEBOOK_SEPARATOR) {
+            end += name.len();
+            paths.push(path[..end].to_string());
+            end += NOTEBOOK_SEPARATOR.len_utf8();
+        }
+    }
+    paths.sort();
+    paths.dedup();
+    paths
+}
+
+/// Normalizes a notebook path as typed by the user.
+///
+/// Names are trimmed and empty names dropped, so " Work / /Acme " becomes
+/// "Work/Acme".
+///
+/// # Arguments
+///
+/// * `input` - The path
+///
+/// # Returns
+///
+/// * `Option<String>` - The path, None if no name is left
+pub fn normalize_path(input: &str) -> Option<String> {
+    let names: Vec<&str> = input
+        .split(NOTEBOOK_SEPARATOR)
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .collect();
+    (!names.is_empty()).then(|| names.join(&NOTEBOOK_SEPARATOR.to_string()))
+}
+
+/// Reads a note's notebook path, normalizing it.
+///
+/// Used as the `deserialize_with` of [`Note::notebook`], so every note read
+/// from storage, a migration bundle or the shared vault has a clean path.
+///
+/// # Arguments
+///
+/// * `deserializer` - The serde deserializer
+///
+/// # Returns
+///
+/// * `Result<Option<String>, D::Error>` - The normalized path
+pub fn deserialize_path<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let path: Option<String> = Option::deserialize(deserializer)?;
+    Ok(path.as_deref().and_then(normalize_path))
+}
@@ -8,16 +8,42 @@
 //! main content area, context menus, and various dialogs.
 
 use crate::app::{NotesApp, TimeFormat};
+use crate::attachment_preview::{AttachmentPreview, PreviewContent};
+use crate::highlight;
+use crate::keymap::ShortcutAction;
+use crate::login_history::LoginOutcome;
+use crate::note::{format_reading_time, Comment, DateBucket, Note, NoteKind, NOTE_ICONS};
+use crate::note_order;
+use crate::note_share_ui::NoteShareDialog;
+use crate::notebook::{self, Notebook};
+use crate::search::{self, SearchQuery, SmartFilter, DATE_INPUT_FORMAT};
+use crate::search_ui;
+use crate::settings::{EditorView, NoteSort};
+use crate::speech;
+use chrono_tz::Europe::Zurich;
 use eframe::egui;
+use std::collections::{BTreeMap, HashMap};
+
+/// A sidebar list whose notes can be reordered by dragging.
+#[derive(Clone, Copy)]
+enum NoteList {
+    /// All notes, in the manual order
+    All,
+    /// The pinned notes, in their own order
+    Pinned,
+}
 
 impl NotesApp {
     /// Renders the notes sidebar with user info, controls, and note list.
     ///
     /// The sidebar contains:
     /// - Header with username and logout button
-    /// - Action buttons (New Note, Settings)
-    /// - Time format toggle
-    /// - Scrollable list of notes sorted by modification time
+    /// - Action buttons (New Note, Settings, always-on-top toggle)
+    /// - Time format toggle and date grouping option
+    /// - Search box with tag and date filters, and saved smart filters
+    /// - Scrollable, collapsible sections (Pinned, Favorites, Edited This
+    ///   Session, Review, Tags, Notebooks, All, Trash) listing matching
+    ///   notes by modification time
     /// - Security information and warnings at the bottom
     ///
     /// Notes are displayed with title, modification time, and selection state.
@@ -48,15 +74,38 @@ impl NotesApp {
             ui.separator();
 
             // Action buttons at the top
+            let keymap = self.settings.keymap.clone();
             ui.horizontal(|ui| {
-                if ui.button("New Note").on_hover_text("Ctrl + N").clicked() {
-                    self.show_new_note_dialog = true;
-                    self.new_note_title.clear();
+                if ui
+                    .button("New Note")
+                    .on_hover_text(keymap.shortcut(ShortcutAction::NewNote).to_string())
+                    .clicked()
+                {
+                    self.open_new_note_dialog();
                 }
 
                 if ui.button("Settings").clicked() {
                     self.show_user_settings = true;
+                    self.storage_report = None;
+                }
+
+                if ui
+                    .selectable_label(self.always_on_top, "📌 On Top")
+                    .on_hover_text(format!(
+                        "Keep the window above other windows ({})",
+                        keymap.shortcut(ShortcutAction::AlwaysOnTop)
+                    ))
+                    .clicked()
+                {
+                    let ctx = ui.ctx().clone();
+                    self.set_always_on_top(&ctx, !self.always_on_top);
                 }
+
+                self.render_inbox_badge(ui);
+
+                ui.menu_button("🕘", |ui| self.render_status_history(ui))
+                    .response
+                    .on_hover_text("Recent messages");
             });
 
             ui.separator();
@@ -65,13 +114,54 @@ impl NotesApp {
             ui.horizontal(|ui| {
                 ui.label("Time format:");
                 ui.selectable_value(&mut self.show_time_format, TimeFormat::Relative, "Relative")
-                    .on_hover_text("Ctrl + R");
+                    .on_hover_text(keymap.shortcut(ShortcutAction::RelativeTime).to_string());
                 ui.selectable_value(&mut self.show_time_format, TimeFormat::Absolute, "Absolute")
-                    .on_hover_text("Ctrl + Alt + A");
+                    .on_hover_text(keymap.shortcut(ShortcutAction::AbsoluteTime).to_string());
+            });
+
+            if ui
+                .checkbox(&mut self.settings.group_by_date, "Group by date")
+                .on_hover_text("Split All Notes into Today, Yesterday, This Week and Earlier")
+                .changed()
+            {
+                self.save_settings();
+            }
+
+            let mut sort_changed = false;
+            ui.horizontal(|ui| {
+                ui.label("Sort by:");
+                egui::ComboBox::from_id_salt("note_sort")
+                    .selected_text(self.settings.note_sort.label())
+                    .show_ui(ui, |ui| {
+                        for sort in NoteSort::ALL {
+                            sort_changed |= ui
+                                .selectable_value(&mut self.settings.note_sort, sort, sort.label())
+                                .changed();
+                        }
+                    })
+                    .response
+                    .on_hover_text("Manual: drag notes in All Notes to reorder them");
             });
+            if sort_changed {
+                self.save_settings();
+            }
 
             ui.separator();
 
+            self.render_search_controls(ui);
+            self.render_filter_summary(ui);
+
+            ui.separator();
+
+            // Notes are still being decrypted in the background
+            if self.note_loader.is_some() {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.small(format!("Decrypting notes... ({} loaded)", self.notes.len()));
+                });
+                ui.separator();
+            }
+
             // Calculate available height for notes list
             let available_height = ui.available_height();
             let bottom_section_height = 80.0; // Reserve space for bottom buttons
@@ -85,100 +175,7 @@ impl NotesApp {
                     egui::ScrollArea::vertical()
                         .max_height(notes_list_height)
                         .auto_shrink([false, false])
-                        .show(ui, |ui| {
-                            let mut notes_vec: Vec<_> = self.notes.iter().collect();
-                            notes_vec.sort_by(|a, b| b.1.modified_at.cmp(&a.1.modified_at));
-
-                            if notes_vec.is_empty() {
-                                ui.vertical_centered(|ui| {
-                                    ui.add_space(50.0);
-                                    ui.label("No notes yet");
-                                    ui.small("Create your first note!");
-                                });
-                            } else {
-                                for (note_id, note) in notes_vec {
-                                    let is_selected =
-                                        self.selected_note_id.as_ref() == Some(note_id);
-
-                                    // Use a simple button approach but with better text handling
-                                    let response = ui.add_sized(
-                                        [ui.available_width(), 60.0],
-                                        egui::Button::new("")
-                                            .fill(if is_selected {
-                                                egui::Color32::from_rgb(70, 130, 180)
-                                            } else {
-                                                egui::Color32::from_rgb(45, 45, 45)
-                                            })
-                                            .stroke(egui::Stroke::new(
-                                                1.0,
-                                                if is_selected {
-                                                    egui::Color32::from_rgb(100, 150, 200)
-                                                } else {
-                                                    egui::Color32::from_rgb(80, 80, 80)
-                                                },
-                                            )),
-                                    );
-
-                                    // Handle interactions
-                                    if response.secondary_clicked() {
-                                        self.context_menu_note_id = Some(note_id.clone());
-                                        self.show_context_menu = true;
-                                        self.context_menu_pos =
-                                            ui.input(|i| i.pointer.hover_pos().unwrap_or_default());
-                                    }
-
-                                    if response.clicked() {
-                                        self.selected_note_id = Some(note_id.clone());
-                                    }
-
-                                    // Draw text on top of the button, but properly clipped
-                                    let button_rect = response.rect;
-                                    let text_rect = button_rect.shrink(8.0);
-
-                                    // Use the painter to draw text with proper clipping
-                                    let painter = ui.painter_at(text_rect);
-
-                                    // Title text
-                                    let title_color = if is_selected {
-                                        egui::Color32::WHITE
-                                    } else {
-                                        egui::Color32::LIGHT_GRAY
-                                    };
-
-                                    let title_pos = text_rect.left_top() + egui::vec2(0.0, 8.0);
-                                    painter.text(
-                                        title_pos,
-                                        egui::Align2::LEFT_TOP,
-                                        &note.title,
-                                        egui::FontId::proportional(14.0),
-                                        title_color,
-                                    );
-
-                                    // Time text
-                                    let time_text = match self.show_time_format {
-                                        TimeFormat::Relative => note.relative_time(),
-                                        TimeFormat::Absolute => note.format_modified_time(),
-                                    };
-
-                                    let time_color = if is_selected {
-                                        egui::Color32::from_rgb(200, 200, 200)
-                                    } else {
-                                        egui::Color32::GRAY
-                                    };
-
-                                    let time_pos = text_rect.left_top() + egui::vec2(0.0, 32.0);
-                                    painter.text(
-                                        time_pos,
-                                        egui::Align2::LEFT_TOP,
-                                        &time_text,
-                                        egui::FontId::proportional(11.0),
-                                        time_color,
-                                    );
-
-                                    ui.add_space(4.0); // Space between notes
-                                }
-                            }
-                        });
+                        .show(ui, |ui| self.render_note_sections(ui));
                 },
             );
 
@@ -186,9 +183,25 @@ impl NotesApp {
             ui.separator();
 
             // Security button and warnings at the bottom
-            if ui.button("Security Info").clicked() {
-                self.show_security_panel = !self.show_security_panel;
-            }
+            ui.horizontal(|ui| {
+                if ui.button("Security Info").clicked() {
+                    self.show_security_panel = !self.show_security_panel;
+                }
+                if ui
+                    .button("Link Graph")
+                    .on_hover_text("Show how notes link to each other with [[Title]]")
+                    .clicked()
+                {
+                    self.open_link_graph();
+                }
+                if ui
+                    .button("Statistics")
+                    .on_hover_text("Notes and words per tag and notebook")
+                    .clicked()
+                {
+                    self.open_note_stats();
+                }
+            });
 
             // Display security warnings if any (but limit the space they take)
             if !self.security_warnings.is_empty() {
@@ -220,188 +233,1992 @@ impl NotesApp {
         self.render_context_menu(ctx);
     }
 
-    /// Renders the context menu for note operations.
+    /// Renders the note list as collapsible sidebar sections.
     ///
-    /// The context menu appears when right-clicking on a note and provides
-    /// options for:
-    /// - Exporting the note to a file
-    /// - Deleting the note
-    /// - Canceling the menu
+    /// Sections with a count badge, in order: Pinned, Favorites, Edited
+    /// This Session (latest edit first), Review, Tags, Notebooks, All and
+    /// Trash. Empty sections other than All are hidden. All lists pinned
+    /// notes first, also within each date group. Pinned notes are listed in
+    /// their own order, changed by dragging them in Pinned; the others in
+    /// the order of the sort selector, which in manual order can be changed
+    /// by dragging notes in All.
+    /// Every section except Trash only lists notes matching the search.
     ///
-    /// The menu automatically closes when clicking elsewhere or after
-    /// selecting an action.
+    /// # Arguments
+    ///
+    /// * `ui` - The scrollable note list UI
+    fn render_note_sections(&mut self, ui: &mut egui::Ui) {
+        let has_notes = self.notes.values().any(|note| !note.is_trashed());
+        if !has_notes && self.note_loader.is_none() {
+            ui.vertical_centered(|ui| {
+                ui.add_space(50.0);
+                ui.label("No notes yet");
+                ui.small("Create your first note!");
+            });
+            self.render_trash_section(ui);
+            return;
+        }
+
+        let mut visible = self.matching_notes(false);
+        note_order::sort_notes(
+            &mut visible,
+            self.settings.note_sort,
+            &self.settings.note_order,
+        );
+
+        let ids_where = |predicate: &dyn Fn(&Note) -> bool| -> Vec<String> {
+            visible
+                .iter()
+                .filter(|note| predicate(note))
+                .map(|note| note.id.clone())
+                .collect()
+        };
+        // Position of each pinned note in the Pinned section
+        let pin_rank: HashMap<String, usize> =
+            note_order::pinned_ids(&self.notes, &self.settings.pinned_order)
+                .into_iter()
+                .enumerate()
+                .map(|(rank, id)| (id, rank))
+                .collect();
+        let by_pin_rank = |id: &String| pin_rank.get(id).copied().unwrap_or(usize::MAX);
+        let mut pinned = ids_where(&|note| note.pinned);
+        pinned.sort_by_key(by_pin_rank);
+        let favorites = ids_where(&|note| note.favorite);
+        let due_for_review = self.due_for_review(&ids_where(&|note| note.review.is_some()));
+        let edited_this_session = match self.session_started_at {
+            Some(started_at) => {
+                let mut edited: Vec<&Note> = visible
+                    .iter()
+                    .filter(|note| note.modified_at >= started_at)
+                    .copied()
+                    .collect();
+                edited.sort_by_key(|note| std::cmp::Reverse(note.modified_at));
+                edited.into_iter().map(|note| note.id.clone()).collect()
+            }
+            None => Vec::new(),
+        };
+        let shared = ids_where(&|note| note.shared);
+        // Pinned notes first in their order, the stable sort keeps the rest
+        // in the chosen order
+        let pinned_first = |mut ids: Vec<String>| {
+            ids.sort_by_key(by_pin_rank);
+            ids
+        };
+        let all = pinned_first(ids_where(&|_| true));
+
+        // Notes are sorted already, so every date group stays sorted
+        let date_groups: Vec<(DateBucket, Vec<String>)> = if self.settings.group_by_date {
+            DateBucket::ALL
+                .into_iter()
+                .map(|bucket| {
+                    let ids = ids_where(&|note| note.date_bucket() == bucket);
+                    (bucket, pinned_first(ids))
+                })
+                .filter(|(_, ids)| !ids.is_empty())
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        let notebooks = Notebook::build_tree(visible.iter().copied());
+        let mut tags: BTreeMap<String, usize> = BTreeMap::new();
+        for note in &visible {
+            for tag in note.tags() {
+                *tags.entry(tag).or_default() += 1;
+            }
+        }
+
+        if !pinned.is_empty() {
+            self.sidebar_section(ui, "pinned", "📌 Pinned", pinned.len(), |app, ui| {
+                app.render_note_buttons(ui, &pinned, Some(NoteList::Pinned))
+            });
+        }
+
+        if !favorites.is_empty() {
+            self.sidebar_section(
+                ui,
+                "favorites",
+                "★ Favorites",
+                favorites.len(),
+                |app, ui| app.render_note_buttons(ui, &favorites, None),
+            );
+        }
+
+        if !edited_this_session.is_empty() {
+            self.sidebar_section(
+                ui,
+                "session",
+                "✎ Edited This Session",
+                edited_this_session.len(),
+                |app, ui| app.render_note_buttons(ui, &edited_this_session, None),
+            );
+        }
+
+        if !due_for_review.is_empty() {
+            self.sidebar_section(
+                ui,
+                "review",
+                "🔁 Review",
+                due_for_review.len(),
+                |app, ui| app.render_note_buttons(ui, &due_for_review, None),
+            );
+        }
+
+        if !tags.is_empty() {
+            self.sidebar_section(ui, "tags", "# Tags", tags.len(), |app, ui| {
+                app.render_tag_list(ui, &tags)
+            });
+        }
+
+        if !notebooks.is_empty() {
+            self.sidebar_section(
+                ui,
+                "notebooks",
+                "📓 Notebooks",
+                notebooks.len(),
+                |app, ui| {
+                    for notebook in notebooks.values() {
+                        app.render_notebook(ui, notebook);
+                    }
+                },
+            );
+        }
+
+        if self.shared_vault.is_some() {
+            self.sidebar_section(ui, "shared", "👥 Shared", shared.len(), |app, ui| {
+                if shared.is_empty() {
+                    ui.small("Right-click a note → Move to Shared Vault");
+                } else {
+                    app.render_note_buttons(ui, &shared, None);
+                }
+            });
+        }
+
+        self.sidebar_section(ui, "all", "All Notes", all.len(), |app, ui| {
+            if all.is_empty() && app.note_loader.is_none() {
+                ui.vertical_centered(|ui| {
+                    ui.add_space(20.0);
+                    ui.label("No matching notes");
+                });
+            } else if app.settings.group_by_date {
+                for (bucket, ids) in &date_groups {
+                    app.sidebar_section(
+                        ui,
+                        &format!("date:{}", bucket.label()),
+                        bucket.label(),
+                        ids.len(),
+                        |app, ui| app.render_note_buttons(ui, ids, Some(NoteList::All)),
+                    );
+                }
+            } else {
+                app.render_note_buttons(ui, &all, Some(NoteList::All));
+            }
+        });
+
+        self.render_trash_section(ui);
+    }
+
+    /// Renders a collapsible sidebar section with a count badge.
+    ///
+    /// The collapsed state is stored in the user's settings, so it is kept
+    /// across sessions.
     ///
     /// # Arguments
     ///
-    /// * `ctx` - The egui context for rendering
-    pub fn render_context_menu(&mut self, ctx: &egui::Context) {
-        if !self.show_context_menu {
+    /// * `ui` - The UI to add the section to
+    /// * `id` - Stable section ID used for the remembered state
+    /// * `title` - Display title
+    /// * `count` - Number shown in the badge
+    /// * `add_contents` - Renders the section body
+    fn sidebar_section(
+        &mut self,
+        ui: &mut egui::Ui,
+        id: &str,
+        title: &str,
+        count: usize,
+        add_contents: impl FnOnce(&mut Self, &mut egui::Ui),
+    ) {
+        let collapsed = self.settings.collapsed_sections.contains(id);
+        let response = egui::CollapsingHeader::new(format!("{} ({})", title, count))
+            .id_salt(("sidebar_section", id))
+            .open(Some(!collapsed))
+            .show(ui, |ui| add_contents(self, ui));
+
+        if response.header_response.clicked() {
+            if collapsed {
+                self.settings.collapsed_sections.remove(id);
+            } else {
+                self.settings.collapsed_sections.insert(id.to_string());
+            }
+            self.save_settings();
+        }
+    }
+
+    /// Renders a notebook as a collapsible section, its nested notebooks
+    /// above its notes.
+    ///
+    /// # Arguments
+    ///
+    /// * `ui` - The parent section UI
+    /// * `notebook` - The notebook to render
+    fn render_notebook(&mut self, ui: &mut egui::Ui, notebook: &Notebook) {
+        self.sidebar_section(
+            ui,
+            &format!("notebook:{}", notebook.path),
+            &notebook.name,
+            notebook.note_count(),
+            |app, ui| {
+                for child in notebook.children.values() {
+                    app.render_notebook(ui, child);
+                }
+                app.render_note_buttons(ui, &notebook.note_ids, None);
+            },
+        );
+    }
+
+    /// Renders the tags of the listed notes as a filter row.
+    ///
+    /// Clicking a tag adds it to the tag filter or removes it again; with
+    /// several tags selected, only notes having all of them are listed.
+    ///
+    /// # Arguments
+    ///
+    /// * `ui` - The section UI
+    /// * `tags` - Tags mapped to the number of notes using them
+    fn render_tag_list(&mut self, ui: &mut egui::Ui, tags: &BTreeMap<String, usize>) {
+        let mut clicked_tag = None;
+        let mut clear = false;
+        ui.horizontal_wrapped(|ui| {
+            for (tag, count) in tags {
+                let is_active = self.search_query.tags.contains(tag);
+                if ui
+                    .selectable_label(is_active, format!("#{} ({})", tag, count))
+                    .on_hover_text(if is_active {
+                        "Remove from the filter"
+                    } else {
+                        "Add to the filter"
+                    })
+                    .clicked()
+                {
+                    clicked_tag = Some(tag.clone());
+                }
+            }
+            if !self.search_query.tags.is_empty()
+                && ui
+                    .small_button("✕ Clear")
+                    .on_hover_text("Show notes with any tags")
+                    .clicked()
+            {
+                clear = true;
+            }
+        });
+
+        if clicked_tag.is_none() && !clear {
             return;
         }
+        let mut selected = self.search_query.tags.clone();
+        if let Some(tag) = clicked_tag {
+            if let Some(index) = selected.iter().position(|active| *active == tag) {
+                selected.remove(index);
+            } else {
+                selected.push(tag);
+            }
+        }
+        if clear {
+            selected.clear();
+        }
+        self.search_tags_input = selected
+            .iter()
+            .map(|tag| format!("#{}", tag))
+            .collect::<Vec<_>>()
+            .join(" ");
+        self.apply_search_inputs();
+    }
 
-        let mut close_menu = false;
-        let mut delete_note_id = None;
-        let mut export_note_id = None;
+    /// Renders a list of note buttons with title and modification time.
+    ///
+    /// Pinned notes show a 📌 at the end of the title row. Clicking a button selects the note, right-clicking opens the context
+    /// menu. In a reorderable list, notes can be dragged onto each other to
+    /// move them: always in Pinned, in All only in manual order.
+    ///
+    /// # Arguments
+    ///
+    /// * `ui` - The UI to add the buttons to
+    /// * `note_ids` - IDs of the notes to show, in display order
+    /// * `list` - The order the list shows, None if it can't be reordered
+    fn render_note_buttons(
+        &mut self,
+        ui: &mut egui::Ui,
+        note_ids: &[String],
+        list: Option<NoteList>,
+    ) {
+        let search_text = self.search_query.free_text();
+        let reorderable = match list {
+            Some(NoteList::All) => self.settings.note_sort == NoteSort::Manual,
+            Some(NoteList::Pinned) => true,
+            None => false,
+        };
+        let mut dropped = None;
+        for (index, note_id) in note_ids.iter().enumerate() {
+            let Some(note) = self.notes.get(note_id) else {
+                continue;
+            };
+            let is_selected = self.selected_note_id.as_ref() == Some(note_id);
+
+            // The first match in the content, shown instead of the time
+            let content_match = if search_text.is_empty() {
+                None
+            } else {
+                self.search_query
+                    .snippets(note, 1)
+                    .into_iter()
+                    .find(|snippet| !snippet.content_range.is_empty())
+            };
+
+            // Use a simple button approach but with better text handling
+            let response = ui.add_sized(
+                [ui.available_width(), 60.0],
+                egui::Button::new("")
+                    .fill(if is_selected {
+                        egui::Color32::from_rgb(70, 130, 180)
+                    } else {
+                        egui::Color32::from_rgb(45, 45, 45)
+                    })
+                    .stroke(egui::Stroke::new(
+                        1.0,
+                        if is_selected {
+                            egui::Color32::from_rgb(100, 150, 200)
+                        } else {
+                            egui::Color32::from_rgb(80, 80, 80)
+                        },
+                    )),
+            );
 
-        egui::Area::new("context_menu".into())
-            .fixed_pos(self.context_menu_pos)
-            .order(egui::Order::Foreground)
-            .show(ctx, |ui| {
-                egui::Frame::popup(ui.style()).show(ui, |ui| {
-                    ui.set_min_width(150.0);
+            if reorderable {
+                let response = response.interact(egui::Sense::click_and_drag());
+                response.dnd_set_drag_payload(note_id.clone());
+                if response.dragged() {
+                    ui.ctx().set_cursor_icon(egui::CursorIcon::Grabbing);
+                }
+                // A line where the dragged note will go
+                if let Some(dragged) = response.dnd_hover_payload::<String>() {
+                    let from = note_ids.iter().position(|id| *id == *dragged);
+                    if from != Some(index) {
+                        let y = if from.is_some_and(|from| from < index) {
+                            response.rect.bottom() + 2.0
+                        } else {
+                            response.rect.top() - 2.0
+                        };
+                        ui.painter().hline(
+                            response.rect.x_range(),
+                            y,
+                            egui::Stroke::new(2.0, egui::Color32::from_rgb(100, 150, 200)),
+                        );
+                    }
+                }
+                if let Some(dragged) = response.dnd_release_payload::<String>() {
+                    dropped = Some(((*dragged).clone(), note_id.clone()));
+                }
+            }
 
-                    if let Some(ref note_id) = self.context_menu_note_id {
-                        if let Some(note) = self.notes.get(note_id) {
-                            ui.label(format!("Note: {}", note.title));
-                            ui.separator();
-                        }
+            // Draw text on top of the button, but properly clipped
+            let button_rect = response.rect;
+            let text_rect = button_rect.shrink(8.0);
 
-                        // Export option
-                        if ui.button("Export to file").clicked() {
-                            export_note_id = Some(note_id.clone());
-                            close_menu = true;
+            // Use the painter to draw text with proper clipping
+            let painter = ui.painter_at(text_rect);
+
+            // Title text
+            let title_color = if is_selected {
+                egui::Color32::WHITE
+            } else {
+                egui::Color32::LIGHT_GRAY
+            };
+
+            let title_pos = text_rect.left_top() + egui::vec2(0.0, 8.0);
+            // Pinned notes keep room for the pin at the end of the title
+            let title_painter = if note.pinned {
+                painter.text(
+                    text_rect.right_top() + egui::vec2(0.0, 8.0),
+                    egui::Align2::RIGHT_TOP,
+                    "📌",
+                    egui::FontId::proportional(12.0),
+                    title_color,
+                );
+                painter.with_clip_rect(text_rect.with_max_x(text_rect.max.x - 20.0))
+            } else {
+                painter.clone()
+            };
+            let title = note.display_title();
+            let title_job = search_ui::highlight_job(
+                &title,
+                &search::find_matches(&title, &search_text),
+                egui::FontId::proportional(14.0),
+                title_color,
+            );
+            title_painter.galley(
+                title_pos,
+                ui.fonts(|f| f.layout_job(title_job)),
+                title_color,
+            );
+
+            // Time text
+            let time_text = match self.show_time_format {
+                TimeFormat::Relative => note.relative_time(),
+                TimeFormat::Absolute => note.format_modified_time(),
+            };
+
+            let time_color = if is_selected {
+                egui::Color32::from_rgb(200, 200, 200)
+            } else {
+                egui::Color32::GRAY
+            };
+
+            let time_pos = text_rect.left_top() + egui::vec2(0.0, 32.0);
+            match &content_match {
+                Some(snippet) => {
+                    let job = search_ui::highlight_job(
+                        &snippet.text,
+                        &snippet.highlights,
+                        egui::FontId::proportional(11.0),
+                        time_color,
+                    );
+                    painter.galley(time_pos, ui.fonts(|f| f.layout_job(job)), time_color);
+                }
+                None => {
+                    painter.text(
+                        time_pos,
+                        egui::Align2::LEFT_TOP,
+                        &time_text,
+                        egui::FontId::proportional(11.0),
+                        time_color,
+                    );
+                }
+            }
+
+            // Handle interactions
+            if response.secondary_clicked() {
+                self.context_menu_note_id = Some(note_id.clone());
+                self.show_context_menu = true;
+                self.context_menu_pos = ui.input(|i| i.pointer.hover_pos().unwrap_or_default());
+            }
+
+            if response.clicked() {
+                match content_match {
+                    Some(snippet) if self.settings.open_at_first_match => {
+                        self.open_note_at(note_id, snippet.content_range);
+                    }
+                    _ => self.selected_note_id = Some(note_id.clone()),
+                }
+            }
+
+            ui.add_space(4.0); // Space between notes
+        }
+
+        if let (Some(list), Some((dragged, target))) = (list, dropped) {
+            self.move_note_in_order(list, &dragged, &target);
+        }
+    }
+
+    /// Moves a note to the place of another one in the manual order or the
+    /// order of the pinned notes, and saves the order.
+    ///
+    /// # Arguments
+    ///
+    /// * `list` - The list the note was dropped in
+    /// * `dragged` - ID of the dragged note
+    /// * `target` - ID of the note it was dropped on
+    fn move_note_in_order(&mut self, list: NoteList, dragged: &str, target: &str) {
+        match list {
+            NoteList::All => {
+                if let Some(order) = note_order::move_note(
+                    self.notes.values(),
+                    &self.settings.note_order,
+                    dragged,
+                    target,
+                ) {
+                    self.settings.note_order = order;
+                    self.save_settings();
+                }
+            }
+            NoteList::Pinned => {
+                let pinned = self
+                    .notes
+                    .values()
+                    .filter(|note| note.pinned && !note.is_trashed());
+                if let Some(order) =
+                    note_order::move_note(pinned, &self.settings.pinned_order, dragged, target)
+                {
+                    self.settings.pinned_order = order;
+                    self.save_settings();
+                }
+            }
+        }
+    }
+
+    /// Renders the collapsible trash section at the end of the note list.
+    ///
+    /// Lists trashed notes, most recently deleted first. Selecting one opens
+    /// it read-only in the main area, where it can be restored.
+    ///
+    /// # Arguments
+    ///
+    /// * `ui` - The note list UI
+    fn render_trash_section(&mut self, ui: &mut egui::Ui) {
+        let mut trashed: Vec<(String, String, bool, _)> = self
+            .notes
+            .values()
+            .filter(|note| note.is_trashed())
+            .map(|note| {
+                (
+                    note.id.clone(),
+                    note.display_title(),
+                    note.keep_forever,
+                    note.deleted_at,
+                )
+            })
+            .collect();
+        if trashed.is_empty() {
+            return;
+        }
+        trashed.sort_by_key(|(_, _, _, deleted_at)| std::cmp::Reverse(*deleted_at));
+
+        let mut empty_trash = false;
+
+        ui.add_space(8.0);
+        self.sidebar_section(ui, "trash", "🗑 Trash", trashed.len(), |app, ui| {
+            for (id, title, keep_forever, _) in trashed {
+                let is_selected = app.selected_note_id.as_ref() == Some(&id);
+                let label = if keep_forever {
+                    format!("{} 📌", title)
+                } else {
+                    title
+                };
+                if ui.selectable_label(is_selected, label).clicked() {
+                    app.selected_note_id = Some(id);
+                }
+            }
+
+            if ui
+                .small_button("Empty Trash")
+                .on_hover_text("Permanently delete all notes in the trash")
+                .clicked()
+            {
+                empty_trash = true;
+            }
+        });
+
+        if empty_trash {
+            let trashed_ids = self.trashed_note_ids();
+            self.delete_notes_permanently(&trashed_ids);
+        }
+    }
+
+    /// Renders the search box, filters and smart filter list of the sidebar.
+    ///
+    /// Editing any search field deselects the active smart filter. Clicking
+    /// a smart filter loads its query into the fields, so it can be refined
+    /// and saved again under a new name.
+    ///
+    /// # Arguments
+    ///
+    /// * `ui` - The sidebar UI
+    fn render_search_controls(&mut self, ui: &mut egui::Ui) {
+        let mut inputs_changed = false;
+        let mut open_results = false;
+
+        ui.horizontal(|ui| {
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut self.search_query.text)
+                    .hint_text("Search notes...")
+                    .desired_width(ui.available_width() - 80.0),
+            );
+            inputs_changed |= response.changed();
+
+            // Enter lists the matches with snippets; the key is consumed so
+            // the results window doesn't take it as opening the first result
+            if response.lost_focus()
+                && ui.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::Enter))
+            {
+                open_results = true;
+            }
+            response.on_hover_text("Enter: show results with excerpts");
+
+            if ui
+                .selectable_label(self.show_search_filters, "Filters")
+                .on_hover_text("Tag and date filters")
+                .clicked()
+            {
+                self.show_search_filters = !self.show_search_filters;
+            }
+            if !self.search_query.is_empty() && ui.small_button("✕").clicked() {
+                self.clear_search();
+            }
+        });
+
+        if self.show_search_filters {
+            egui::Grid::new("search_filters")
+                .num_columns(2)
+                .show(ui, |ui| {
+                    ui.label("Tags:");
+                    inputs_changed |= ui
+                        .add(
+                            egui::TextEdit::singleline(&mut self.search_tags_input)
+                                .hint_text("#work #todo"),
+                        )
+                        .changed();
+                    ui.end_row();
+
+                    ui.label("Modified from:");
+                    inputs_changed |= date_input(ui, &mut self.search_from_input);
+                    ui.end_row();
+
+                    ui.label("Modified to:");
+                    inputs_changed |= date_input(ui, &mut self.search_to_input);
+                    ui.end_row();
+                });
+
+            if ui
+                .checkbox(
+                    &mut self.settings.open_at_first_match,
+                    "Open notes at the first match",
+                )
+                .on_hover_text("Clicking a listed note selects the first match in the editor")
+                .changed()
+            {
+                self.save_settings();
+            }
+
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.smart_filter_name_input)
+                        .hint_text("Filter name")
+                        .desired_width(ui.available_width() - 60.0),
+                );
+                let can_save = !self.search_query.is_empty()
+                    && !self.smart_filter_name_input.trim().is_empty();
+                if ui
+                    .add_enabled(can_save, egui::Button::new("Save"))
+                    .on_hover_text("Save the current search as a smart filter")
+                    .clicked()
+                {
+                    self.settings.smart_filters.push(SmartFilter {
+                        name: self.smart_filter_name_input.trim().to_string(),
+                        query: self.search_query.clone(),
+                    });
+                    self.active_smart_filter = Some(self.settings.smart_filters.len() - 1);
+                    self.smart_filter_name_input.clear();
+                    self.save_settings();
+                }
+            });
+        }
+
+        if inputs_changed {
+            self.apply_search_inputs();
+        }
+        if open_results {
+            self.open_search_results();
+        }
+
+        if self.settings.smart_filters.is_empty() {
+            return;
+        }
+
+        let mut apply_filter = None;
+        let mut remove_filter = None;
+
+        egui::CollapsingHeader::new("Smart Filters")
+            .default_open(true)
+            .show(ui, |ui| {
+                for (index, filter) in self.settings.smart_filters.iter().enumerate() {
+                    let count = self
+                        .notes
+                        .values()
+                        .filter(|note| !note.is_trashed() && filter.query.matches(note))
+                        .count();
+
+                    ui.horizontal(|ui| {
+                        let is_active = self.active_smart_filter == Some(index);
+                        if ui
+                            .selectable_label(is_active, format!("{} ({})", filter.name, count))
+                            .clicked()
+                        {
+                            apply_filter = Some(index);
                         }
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            if ui
+                                .small_button("✕")
+                                .on_hover_text("Delete smart filter")
+                                .clicked()
+                            {
+                                remove_filter = Some(index);
+                            }
+                        });
+                    });
+                }
+            });
 
-                        ui.separator();
+        if let Some(index) = apply_filter {
+            if self.active_smart_filter == Some(index) {
+                self.clear_search();
+            } else {
+                let query = self.settings.smart_filters[index].query.clone();
+                self.set_search_query(query);
+                self.active_smart_filter = Some(index);
+            }
+        }
+
+        if let Some(index) = remove_filter {
+            self.settings.smart_filters.remove(index);
+            self.active_smart_filter = match self.active_smart_filter {
+                Some(active) if active == index => None,
+                Some(active) if active > index => Some(active - 1),
+                active => active,
+            };
+            self.save_settings();
+        }
+    }
+
+    /// Renders how many notes the active search lists, e.g.
+    /// "12 of 248 notes — filter: #work", with a button to clear it.
+    ///
+    /// Nothing is shown while no search or filter is active.
+    ///
+    /// # Arguments
+    ///
+    /// * `ui` - The sidebar UI
+    fn render_filter_summary(&mut self, ui: &mut egui::Ui) {
+        if self.search_query.is_empty() {
+            return;
+        }
+
+        let visible = self.matching_notes(false).len();
+        let total = self
+            .notes
+            .values()
+            .filter(|note| !note.is_trashed())
+            .count();
+        let filter = match self
+            .active_smart_filter
+            .and_then(|index| self.settings.smart_filters.get(index))
+        {
+            Some(smart_filter) => smart_filter.name.clone(),
+            None => self.search_query.summary(),
+        };
+
+        let mut clear = false;
+        ui.horizontal_wrapped(|ui| {
+            ui.strong(format!(
+                "{} of {} {}",
+                visible,
+                total,
+                if total == 1 { "note" } else { "notes" }
+            ));
+            ui.label(format!("— filter: {}", filter));
+            if ui
+                .small_button("✕ Clear")
+                .on_hover_text("Show all notes")
+                .clicked()
+            {
+                clear = true;
+            }
+        });
+        if clear {
+            self.clear_search();
+        }
+    }
+
+    /// Rebuilds the search query from the tag and date inputs.
+    ///
+    /// Deselects the active smart filter, since the query no longer is the
+    /// saved one.
+    fn apply_search_inputs(&mut self) {
+        self.search_query.tags = search::parse_tags(&self.search_tags_input);
+        self.search_query.modified_from = search::parse_date(&self.search_from_input);
+        self.search_query.modified_to = search::parse_date(&self.search_to_input);
+        self.active_smart_filter = None;
+    }
+
+    /// Applies a query and fills the search fields from it.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - The query to apply
+    fn set_search_query(&mut self, query: SearchQuery) {
+        self.search_tags_input = query
+            .tags
+            .iter()
+            .map(|tag| format!("#{}", tag))
+            .collect::<Vec<_>>()
+            .join(" ");
+        self.search_from_input = query
+            .modified_from
+            .map(|date| date.format(DATE_INPUT_FORMAT).to_string())
+            .unwrap_or_default();
+        self.search_to_input = query
+            .modified_to
+            .map(|date| date.format(DATE_INPUT_FORMAT).to_string())
+            .unwrap_or_default();
+        self.search_query = query;
+    }
+
+    /// Clears the search so the sidebar lists all notes again.
+    pub fn clear_search(&mut self) {
+        self.set_search_query(SearchQuery::default());
+        self.active_smart_filter = None;
+    }
+
+    /// Renders the context menu for note operations.
+    ///
+    /// The context menu appears when right-clicking on a note and provides
+    /// options for:
+    /// - Pinning the note and adding it to the favorites
+    /// - Exporting the note to a file
+    /// - Moving the note to the trash
+    /// - Canceling the menu
+    ///
+    /// The menu automatically closes when clicking elsewhere or after
+    /// selecting an action.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The egui context for rendering
+    pub fn render_context_menu(&mut self, ctx: &egui::Context) {
+        if !self.show_context_menu {
+            return;
+        }
+
+        let mut close_menu = false;
+        let mut delete_note_id = None;
+        let mut export_note_id = None;
+        let mut share_note_id = None;
+        let mut email_note_id = None;
+        let mut rename_note_id = None;
+        let mut history_note_id = None;
+        let mut diagnose_note_id = None;
+        let mut toggle_pin_id = None;
+        let mut toggle_favorite_id = None;
+        let mut toggle_review_id = None;
+        let mut move_shared = None;
+        let mut move_notebook = None;
+        let notebook_paths = notebook::all_paths(self.notes.values());
+
+        egui::Area::new("context_menu".into())
+            .fixed_pos(self.context_menu_pos)
+            .order(egui::Order::Foreground)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.set_min_width(150.0);
+
+                    if let Some(ref note_id) = self.context_menu_note_id {
+                        if let Some(note) = self.notes.get(note_id) {
+                            ui.label(format!("Note: {}", note.display_title()));
+                            ui.separator();
+                        }
+
+                        if let Some(note) = self.notes.get(note_id) {
+                            let pin_label = if note.pinned { "Unpin" } else { "Pin" };
+                            if ui.button(pin_label).clicked() {
+                                toggle_pin_id = Some(note_id.clone());
+                                close_menu = true;
+                            }
+
+                            let favorite_label = if note.favorite {
+                                "Remove from Favorites"
+                            } else {
+                                "Add to Favorites"
+                            };
+                            if ui.button(favorite_label).clicked() {
+                                toggle_favorite_id = Some(note_id.clone());
+                                close_menu = true;
+                            }
+
+                            let review_label = if note.review.is_some() {
+                                "Stop Reviewing"
+                            } else {
+                                "Add to Review"
+                            };
+                            if ui
+                                .button(review_label)
+                                .on_hover_text(
+                                    "Resurface the note for re-reading at growing intervals",
+                                )
+                                .clicked()
+                            {
+                                toggle_review_id = Some(note_id.clone());
+                                close_menu = true;
+                            }
+
+                            if self.shared_vault.is_some() {
+                                let share_label = if note.shared {
+                                    "Move to Private Notes"
+                                } else {
+                                    "Move to Shared Vault"
+                                };
+                                if ui.button(share_label).clicked() {
+                                    move_shared = Some((note_id.clone(), !note.shared));
+                                    close_menu = true;
+                                }
+                            }
+
+                            ui.separator();
+                        }
+
+                        if ui.button("Rename…").clicked() {
+                            rename_note_id = Some(note_id.clone());
+                            close_menu = true;
+                        }
+
+                        let current_notebook = self
+                            .notes
+                            .get(note_id)
+                            .and_then(|note| note.notebook.clone());
+                        ui.menu_button("Move to Notebook", |ui| {
+                            if ui
+                                .add_enabled(current_notebook.is_some(), egui::Button::new("None"))
+                                .clicked()
+                            {
+                                move_notebook = Some((note_id.clone(), None));
+                                close_menu = true;
+                            }
+                            if !notebook_paths.is_empty() {
+                                ui.separator();
+                            }
+                            for path in &notebook_paths {
+                                let depth = path.matches(notebook::NOTEBOOK_SEPARATOR).count();
+                                let name = path
+                                    .rsplit(notebook::NOTEBOOK_SEPARATOR)
+                                    .next()
+                                    .unwrap_or(path);
+                                let is_current = current_notebook.as_ref() == Some(path);
+                                if ui
+                                    .add_enabled(
+                                        !is_current,
+                                        egui::Button::new(format!(
+                                            "{}📓 {}",
+                                            "    ".repeat(depth),
+                                            name
+                                        )),
+                                    )
+                                    .on_hover_text(path.as_str())
+                                    .clicked()
+                                {
+                                    move_notebook = Some((note_id.clone(), Some(path.clone())));
+                                    close_menu = true;
+                                }
+                            }
+                            ui.separator();
+                            ui.small("New notebooks: Properties → Notebook");
+                        });
+
+                        if ui
+                            .button("Version History…")
+                            .on_hover_text("Compare and restore earlier versions of the note")
+                            .clicked()
+                        {
+                            history_note_id = Some(note_id.clone());
+                            close_menu = true;
+                        }
+
+                        // Export option
+                        if ui.button("Export to file").clicked() {
+                            export_note_id = Some(note_id.clone());
+                            close_menu = true;
+                        }
+
+                        if ui.button("Copy as Encrypted Text…").clicked() {
+                            share_note_id = Some(note_id.clone());
+                            close_menu = true;
+                        }
+
+                        if ui.button("Send via Email…").clicked() {
+                            email_note_id = Some(note_id.clone());
+                            close_menu = true;
+                        }
+
+                        if ui
+                            .button("Encryption Diagnostics")
+                            .on_hover_text(
+                                "How the note is stored, for reporting decryption problems",
+                            )
+                            .clicked()
+                        {
+                            diagnose_note_id = Some(note_id.clone());
+                            close_menu = true;
+                        }
+
+                        ui.separator();
+
+                        // Delete option
+                        if ui.button("Move to Trash").clicked() {
+                            delete_note_id = Some(note_id.clone());
+                            close_menu = true;
+                        }
+
+                        ui.separator();
+                    }
+
+                    if ui.button("Cancel").clicked() {
+                        close_menu = true;
+                    }
+                });
+            });
+
+        // Handle actions
+        if let Some(note_id) = rename_note_id {
+            self.open_rename_dialog(&note_id);
+        }
+
+        if let Some(note_id) = history_note_id {
+            self.open_note_history(&note_id);
+        }
+
+        if let Some(note_id) = export_note_id {
+            self.export_note_to_file(&note_id);
+        }
+
+        if let Some(note_id) = share_note_id {
+            self.note_share_dialog = Some(NoteShareDialog::copy(note_id));
+        }
+
+        if let Some(note_id) = email_note_id {
+            self.open_note_email(&note_id);
+        }
+
+        if let Some(note_id) = diagnose_note_id {
+            self.open_note_diagnostics(&note_id);
+        }
+
+        if let Some(note_id) = toggle_pin_id {
+            self.toggle_pinned(&note_id);
+        }
+
+        if let Some(note_id) = toggle_favorite_id {
+            self.toggle_favorite(&note_id);
+        }
+
+        if let Some(note_id) = toggle_review_id {
+            self.toggle_review(&note_id);
+        }
+
+        if let Some((note_id, shared)) = move_shared {
+            self.set_note_shared(&note_id, shared);
+        }
+
+        if let Some((note_id, notebook)) = move_notebook {
+            self.move_to_notebook(&note_id, notebook);
+        }
+
+        if let Some(note_id) = delete_note_id {
+            self.delete_note(&note_id);
+        }
+
+        if close_menu {
+            self.show_context_menu = false;
+            self.context_menu_note_id = None;
+        }
+
+        // Close menu if clicked elsewhere
+        if ctx.input(|i| i.pointer.any_click()) && !self.show_context_menu {
+            self.show_context_menu = false;
+            self.context_menu_note_id = None;
+        }
+    }
+
+    /// Renders the outline of the selected note in a right side panel.
+    ///
+    /// Lists the Markdown headings of the note, indented by level. Clicking
+    /// a heading moves the editor cursor to it and scrolls it into view.
+    /// Must run before the central panel so the panel gets its space.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The egui context for rendering
+    pub fn render_outline_panel(&mut self, ctx: &egui::Context) {
+        if !self.settings.show_outline {
+            return;
+        }
+        let Some(note_id) = self.selected_note_id.clone() else {
+            return;
+        };
+        let Some(stats) = self.note_text_stats(&note_id) else {
+            return;
+        };
+        let headings = &stats.headings;
+        let mut jump_to = None;
+
+        egui::SidePanel::right("outline")
+            .default_width(200.0)
+            .show(ctx, |ui| {
+                ui.heading("Outline");
+                ui.separator();
+
+                if headings.is_empty() {
+                    ui.small("No headings. Start a line with # to add one.");
+                    return;
+                }
+
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for heading in headings {
+                        ui.horizontal(|ui| {
+                            ui.add_space((heading.level - 1) as f32 * 12.0);
+                            let text = if heading.level == 1 {
+                                egui::RichText::new(&heading.title).strong()
+                            } else {
+                                egui::RichText::new(&heading.title)
+                            };
+                            if ui
+                                .add(
+                                    egui::Label::new(text)
+                                        .truncate()
+                                        .sense(egui::Sense::click()),
+                                )
+                                .on_hover_cursor(egui::CursorIcon::PointingHand)
+                                .clicked()
+                            {
+                                jump_to = Some(heading.line.start);
+                            }
+                        });
+                    }
+                });
+            });
+
+        if let Some(position) = jump_to {
+            self.open_note_at(&note_id, position..position);
+        }
+    }
+
+    /// Renders the recent status messages, newest first.
+    ///
+    /// # Arguments
+    ///
+    /// * `ui` - The popover to render into
+    fn render_status_history(&mut self, ui: &mut egui::Ui) {
+        ui.set_max_width(400.0);
+        if self.status_history.is_empty() {
+            ui.label("No messages yet");
+            return;
+        }
+        let mut open_folder = None;
+        egui::ScrollArea::vertical()
+            .max_height(300.0)
+            .show(ui, |ui| {
+                for entry in &self.status_history {
+                    ui.horizontal_wrapped(|ui| {
+                        ui.small(entry.format_time());
+                        ui.label(&entry.message);
+                        if let Some(folder) = &entry.folder {
+                            if ui.small_button("📂").on_hover_text("Open folder").clicked() {
+                                open_folder = Some(folder.clone());
+                            }
+                        }
+                    });
+                }
+            });
+        if let Some(folder) = open_folder {
+            self.open_folder(&folder);
+            ui.close_menu();
+        }
+        ui.separator();
+        if ui.button("Clear").clicked() {
+            self.status_history.clear();
+            ui.close_menu();
+        }
+    }
+
+    /// Renders the main content area for note editing.
+    ///
+    /// The main content area displays:
+    /// - Status messages at the top (if any)
+    /// - Note header with title, timestamps, reading time and export button
+    /// - Properties and attachments panels
+    /// - Large text editor for note content
+    /// - Welcome message when no note is selected
+    ///
+    /// The text editor automatically updates the note's modification time
+    /// when content changes and triggers auto-save functionality.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The egui context for rendering
+    pub fn render_main_content(&mut self, ctx: &egui::Context) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            // Show status message at the top if present
+            if let Some(message) = self.status_message.clone() {
+                ui.horizontal(|ui| {
+                    ui.colored_label(egui::Color32::from_rgb(100, 200, 100), "ℹ");
+                    ui.label(message);
+                    if let Some(folder) = self.status_folder.clone() {
+                        if ui.small_button("📂 Open folder").clicked() {
+                            self.open_folder(&folder);
+                        }
+                    }
+                });
+                ui.separator();
+            }
+
+            // Clone the selected note ID to avoid borrowing issues
+            if let Some(note_id) = self.selected_note_id.clone() {
+                // Get the note data we need for display (immutable borrow)
+                let (
+                    note_title,
+                    note_created_time,
+                    note_modified_time,
+                    reading_time,
+                    word_count,
+                    is_secrets_note,
+                ) = {
+                    let word_count = self
+                        .note_text_stats(&note_id)
+                        .map_or(0, |stats| stats.word_count);
+                    if let Some(note) = self.notes.get(&note_id) {
+                        (
+                            note.display_title(),
+                            note.format_created_time(),
+                            note.format_modified_time(),
+                            format_reading_time(word_count),
+                            word_count,
+                            note.kind == NoteKind::Secrets,
+                        )
+                    } else {
+                        return; // Note doesn't exist anymore
+                    }
+                };
+
+                // Display the header with note info and export button
+                let mut view_change = None;
+                let editor_view = self.editor_view_of(&note_id);
+                ui.horizontal(|ui| {
+                    self.render_note_title(ui, &note_id, &note_title);
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        self.render_speech_controls(ui, &note_id);
+
+                        // Export button
+                        let keymap = self.settings.keymap.clone();
+                        if ui
+                            .button(format!(
+                                "Export ({})",
+                                keymap.shortcut(ShortcutAction::ExportNote)
+                            ))
+                            .on_hover_text("Export note to a text, Markdown or HTML file")
+                            .clicked()
+                        {
+                            self.export_note_to_file(&note_id);
+                        }
+
+                        if ui
+                            .selectable_label(self.settings.show_outline, "Outline")
+                            .on_hover_text("Show the headings of the note")
+                            .clicked()
+                        {
+                            self.settings.show_outline = !self.settings.show_outline;
+                            self.save_settings();
+                        }
+
+                        ui.separator();
+
+                        // Right to left, so the views are added in reverse
+                        for view in EditorView::ALL.into_iter().rev() {
+                            if ui
+                                .selectable_label(editor_view == view, view.label())
+                                .on_hover_text(view.description())
+                                .clicked()
+                            {
+                                view_change = Some(view);
+                            }
+                        }
+
+                        ui.separator();
+
+                        // Save state indicator (most relevant in manual-save-only mode)
+                        if self.has_unsaved_changes {
+                            ui.colored_label(egui::Color32::from_rgb(255, 165, 0), "● Unsaved")
+                                .on_hover_text(format!(
+                                    "Press {} to save",
+                                    keymap.shortcut(ShortcutAction::Save)
+                                ));
+                        } else {
+                            ui.colored_label(egui::Color32::from_rgb(100, 200, 100), "✔ Saved");
+                        }
+
+                        ui.separator();
+
+                        // Reading time estimate, recalculated every frame while editing
+                        ui.small(reading_time).on_hover_text(format!(
+                            "{} {}",
+                            word_count,
+                            if word_count == 1 { "word" } else { "words" }
+                        ));
+
+                        ui.separator();
+
+                        // Show both created and modified times
+                        ui.vertical(|ui| {
+                            ui.small(format!("Modified: {}", note_modified_time));
+                            ui.small(format!("Created: {}", note_created_time));
+                        });
+                    });
+                });
+                if let Some(view) = view_change {
+                    self.set_editor_view(view);
+                }
+                ui.separator();
+
+                let is_trashed = self.render_trash_banner(ui, &note_id);
+                let read_only = self.render_lock_banner(ui, &note_id) || is_trashed;
+                self.render_review_banner(ui, &note_id, read_only);
+                self.render_tag_editor(ui, &note_id, read_only);
+                if is_secrets_note {
+                    self.render_secrets_panel(ui, &note_id, read_only);
+                }
+                self.render_properties_panel(ui, &note_id, read_only);
+                self.render_attachments_panel(ui, &note_id, read_only);
+                self.render_comments_panel(ui, &note_id, read_only);
+
+                // Calculate available space for the text editor
+                let available_height = ui.available_height();
+                let header_height = 80.0; // Approximate height for header and separator
+                let text_area_height = (available_height - header_height).max(200.0);
+
+                // Editor, preview or both, with a fixed height
+                match self.editor_view_of(&note_id) {
+                    EditorView::Edit => {
+                        self.render_editor_pane(ui, &note_id, read_only, text_area_height);
+                    }
+                    EditorView::Split => {
+                        self.render_split_view(ui, &note_id, read_only, text_area_height);
+                    }
+                    EditorView::Preview => {
+                        self.render_preview_pane(ui, &note_id, text_area_height);
+                    }
+                }
+            } else {
+                ui.vertical_centered(|ui| {
+                    ui.add_space(200.0);
+                    ui.heading("Select a note to edit");
+                    ui.label("Or create a new note using the sidebar");
+                    ui.add_space(20.0);
+                    ui.small(format!("Current time: {}", self.get_current_time()));
+                });
+            }
+        });
+    }
+
+    /// Renders the read-aloud buttons of the note header.
+    ///
+    /// Shows "Read Aloud" while nothing is read, and pause/resume and stop
+    /// buttons while a note is read, whichever note that is.
+    ///
+    /// # Arguments
+    ///
+    /// * `ui` - The right-to-left header UI
+    /// * `note_id` - The ID of the displayed note
+    fn render_speech_controls(&mut self, ui: &mut egui::Ui, note_id: &str) {
+        let Some(speaker) = &mut self.speaker else {
+            if ui
+                .add_enabled(speech::is_available(), egui::Button::new("🔊 Read Aloud"))
+                .on_hover_text("Read the selected text, or the whole note, aloud")
+                .on_disabled_hover_text(
+                    "No speech engine found. On Linux, install espeak-ng or speech-dispatcher",
+                )
+                .clicked()
+            {
+                self.read_note_aloud(ui.ctx(), note_id);
+            }
+            return;
+        };
+
+        let reading = self
+            .notes
+            .get(&speaker.note_id)
+            .map_or_else(String::new, |note| note.title.clone());
+        let (paragraph, paragraphs) = speaker.progress();
+        let hover = format!(
+            "Reading \"{}\", paragraph {} of {}",
+            reading, paragraph, paragraphs
+        );
+
+        let mut error = None;
+        if ui.button("⏹").on_hover_text("Stop reading").clicked() {
+            self.speaker = None;
+            return;
+        }
+        if speaker.is_paused() {
+            if ui.button("▶").on_hover_text(hover).clicked() {
+                error = speaker.resume().err();
+            }
+        } else if ui.button("⏸").on_hover_text(hover).clicked() {
+            speaker.pause();
+        }
+
+        if let Some(e) = error {
+            self.speaker = None;
+            self.show_status(format!("Could not read aloud: {}", e));
+        }
+    }
+
+    /// Renders the collapsible panel with the custom fields of a note.
+    ///
+    /// Shows the note's notebook and code language followed by its custom
+    /// fields. Field values can be edited in place, fields removed and new
+    /// fields added. Fields are searchable with `key:value` in the search box.
+    ///
+    /// # Arguments
+    ///
+    /// * `ui` - The main content UI
+    /// * `note_id` - The ID of the displayed note
+    /// * `read_only` - Whether editing is disabled (trashed notes)
+    fn render_properties_panel(&mut self, ui: &mut egui::Ui, note_id: &str, read_only: bool) {
+        let Some(note) = self.notes.get_mut(note_id) else {
+            return;
+        };
+
+        let mut changed = false;
+        let mut remove_field = None;
+        let mut add_field = false;
+
+        egui::CollapsingHeader::new(format!("Properties ({})", note.fields.len()))
+            .id_salt("note_properties")
+            .show(ui, |ui| {
+                ui.add_enabled_ui(!read_only, |ui| {
+                    egui::Grid::new("note_fields")
+                        .num_columns(3)
+                        .show(ui, |ui| {
+                            ui.label("Icon");
+                            ui.horizontal(|ui| {
+                                let mut icon = note.icon.clone().unwrap_or_default();
+                                if ui
+                                    .add(
+                                        egui::TextEdit::singleline(&mut icon)
+                                            .hint_text("None")
+                                            .desired_width(40.0),
+                                    )
+                                    .on_hover_text("Type or paste an emoji")
+                                    .changed()
+                                {
+                                    note.set_icon(&icon);
+                                    changed = true;
+                                }
+                                ui.menu_button("Choose…", |ui| {
+                                    egui::Grid::new("note_icons").show(ui, |ui| {
+                                        for (i, preset) in NOTE_ICONS.iter().enumerate() {
+                                            if ui.button(*preset).clicked() {
+                                                note.set_icon(preset);
+                                                changed = true;
+                                                ui.close_menu();
+                                            }
+                                            if i % 6 == 5 {
+                                                ui.end_row();
+                                            }
+                                        }
+                                    });
+                                    if ui
+                                        .add_enabled(
+                                            note.icon.is_some(),
+                                            egui::Button::new("No icon"),
+                                        )
+                                        .clicked()
+                                    {
+                                        note.icon = None;
+                                        changed = true;
+                                        ui.close_menu();
+                                    }
+                                });
+                            });
+                            ui.end_row();
+
+                            ui.label("Notebook");
+                            let mut notebook = note.notebook.clone().unwrap_or_default();
+                            let response = ui
+                                .add(
+                                    egui::TextEdit::singleline(&mut notebook)
+                                        .hint_text("None")
+                                        .desired_width(240.0),
+                                )
+                                .on_hover_text("Nest notebooks with /, e.g. Work/Clients");
+                            if response.changed() {
+                                // Kept as typed while editing, normalized when done
+                                note.notebook = (!notebook.trim().is_empty()).then_some(notebook);
+                                changed = true;
+                            }
+                            if response.lost_focus() {
+                                let normalized =
+                                    note.notebook.as_deref().and_then(notebook::normalize_path);
+                                if note.notebook != normalized {
+                                    note.notebook = normalized;
+                                    changed = true;
+                                }
+                            }
+                            ui.end_row();
+
+                            ui.label("Language");
+                            let selected = note
+                                .language
+                                .as_deref()
+                                .map_or("Plain text", highlight::language_label);
+                            egui::ComboBox::from_id_salt("note_language")
+                                .selected_text(selected.to_string())
+                                .show_ui(ui, |ui| {
+                                    changed |= ui
+                                        .selectable_value(&mut note.language, None, "Plain text")
+                                        .changed();
+                                    for (token, label) in highlight::LANGUAGES {
+                                        changed |= ui
+                                            .selectable_value(
+                                                &mut note.language,
+                                                Some(token.to_string()),
+                                                *label,
+                                            )
+                                            .changed();
+                                    }
+                                });
+                            ui.end_row();
+
+                            for (key, value) in note.fields.iter_mut() {
+                                ui.label(key.as_str());
+                                changed |= ui
+                                    .add(egui::TextEdit::singleline(value).desired_width(240.0))
+                                    .changed();
+                                if ui.small_button("✕").on_hover_text("Remove field").clicked() {
+                                    remove_field = Some(key.clone());
+                                }
+                                ui.end_row();
+                            }
+
+                            ui.add(
+                                egui::TextEdit::singleline(&mut self.new_field_key_input)
+                                    .hint_text("Field")
+                                    .desired_width(100.0),
+                            );
+                            ui.add(
+                                egui::TextEdit::singleline(&mut self.new_field_value_input)
+                                    .hint_text("Value")
+                                    .desired_width(240.0),
+                            );
+                            if ui
+                                .add_enabled(
+                                    self.new_field_key_input.chars().any(char::is_alphanumeric),
+                                    egui::Button::new("Add"),
+                                )
+                                .clicked()
+                            {
+                                add_field = true;
+                            }
+                            ui.end_row();
+                        });
+                });
+                ui.small("Search by field with key:value, e.g. client:acme");
+            });
+
+        if let Some(key) = remove_field {
+            note.fields.remove(&key);
+            changed = true;
+        }
+
+        if add_field {
+            // Keep names usable as search operators: no spaces or colons
+            let key: String = self
+                .new_field_key_input
+                .trim()
+                .chars()
+                .map(|c| if c.is_whitespace() { '_' } else { c })
+                .filter(|c| c.is_alphanumeric() || *c == '_' || *c == '-')
+                .collect();
+            note.fields
+                .insert(key, self.new_field_value_input.trim().to_string());
+            self.new_field_key_input.clear();
+            self.new_field_value_input.clear();
+            changed = true;
+        }
+
+        if changed {
+            note.update_modified_time();
+            self.search_index.note_changed(note);
+            self.has_unsaved_changes = true;
+            self.last_save_time = std::time::Instant::now();
+        }
+        ui.separator();
+    }
+
+    /// Renders the tags of a note below its header.
+    ///
+    /// Tags added here can be removed with their ✕ and new ones typed into
+    /// the field and added with Enter. Tags written as `#tag` in the content
+    /// are shown too, but are changed by editing the text.
+    ///
+    /// # Arguments
+    ///
+    /// * `ui` - The main content UI
+    /// * `note_id` - The ID of the displayed note
+    /// * `read_only` - Whether editing is disabled (trashed or locked notes)
+    fn render_tag_editor(&mut self, ui: &mut egui::Ui, note_id: &str, read_only: bool) {
+        let Some(note) = self.notes.get_mut(note_id) else {
+            return;
+        };
+
+        let mut remove_tag = None;
+        let mut add_tag = false;
+        ui.horizontal_wrapped(|ui| {
+            ui.label("🏷").on_hover_text("Tags");
+            for tag in &note.tags {
+                if ui
+                    .add_enabled(!read_only, egui::Button::new(format!("#{} ✕", tag)).small())
+                    .on_hover_text("Remove tag")
+                    .clicked()
+                {
+                    remove_tag = Some(tag.clone());
+                }
+            }
+            for tag in note.inline_tags() {
+                if !note.tags.contains(&tag) {
+                    ui.weak(format!("#{}", tag))
+                        .on_hover_text("Written in the text; edit the text to remove it");
+                }
+            }
+            if !read_only {
+                let response = ui.add(
+                    egui::TextEdit::singleline(&mut self.new_tag_input)
+                        .hint_text("Add tag")
+                        .desired_width(90.0),
+                );
+                if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                    add_tag = true;
+                    response.request_focus();
+                }
+            }
+        });
+
+        let mut changed = false;
+        if let Some(tag) = remove_tag {
+            changed |= note.remove_tag(&tag);
+        }
+        if add_tag {
+            changed |= note.add_tag(&self.new_tag_input);
+            self.new_tag_input.clear();
+        }
+        if changed {
+            note.update_modified_time();
+            self.search_index.note_changed(note);
+            self.has_unsaved_changes = true;
+            self.last_save_time = std::time::Instant::now();
+        }
+    }
+
+    /// Renders the collapsible panel listing the attachments of a note.
+    ///
+    /// Attachments can be previewed, saved to a file, removed, and new files
+    /// attached. Only previewing and saving are available for trashed notes.
+    ///
+    /// # Arguments
+    ///
+    /// * `ui` - The main content UI
+    /// * `note_id` - The ID of the displayed note
+    /// * `read_only` - Whether adding and removing is disabled (trashed notes)
+    fn render_attachments_panel(&mut self, ui: &mut egui::Ui, note_id: &str, read_only: bool) {
+        let Some(note) = self.notes.get(note_id) else {
+            return;
+        };
+        // The quota covers the user's own files, not the shared vault
+        let over_quota = !note.shared && self.quota_usage.is_some_and(|usage| usage.exceeded());
 
-                        // Delete option
-                        if ui.button("Delete Note").clicked() {
-                            delete_note_id = Some(note_id.clone());
-                            close_menu = true;
+        let previewed_id = self
+            .attachment_preview
+            .as_ref()
+            .filter(|preview| preview.note_id == note_id)
+            .map(|preview| preview.attachment_id.clone());
+
+        let mut attach = false;
+        let mut preview_id = None;
+        let mut save_id = None;
+        let mut remove_id = None;
+
+        egui::CollapsingHeader::new(format!("Attachments ({})", note.attachments.len()))
+            .id_salt("note_attachments")
+            .show(ui, |ui| {
+                egui::Grid::new("note_attachments_grid")
+                    .num_columns(3)
+                    .show(ui, |ui| {
+                        for attachment in &note.attachments {
+                            ui.label(&attachment.file_name);
+                            ui.small(format!("{} bytes", attachment.size));
+                            ui.horizontal(|ui| {
+                                let previewed = previewed_id.as_deref() == Some(&attachment.id);
+                                if ui
+                                    .selectable_label(previewed, "👁 Preview")
+                                    .on_hover_text("Show the file here, decrypted in memory only")
+                                    .clicked()
+                                {
+                                    preview_id = Some(attachment.id.clone());
+                                }
+                                if ui.small_button("Save As…").clicked() {
+                                    save_id = Some(attachment.id.clone());
+                                }
+                                if ui
+                                    .add_enabled(!read_only, egui::Button::new("✕").small())
+                                    .on_hover_text("Remove attachment")
+                                    .clicked()
+                                {
+                                    remove_id = Some(attachment.id.clone());
+                                }
+                            });
+                            ui.end_row();
                         }
+                    });
 
-                        ui.separator();
-                    }
+                if let Some(preview) = self
+                    .attachment_preview
+                    .as_ref()
+                    .filter(|preview| preview.note_id == note_id)
+                {
+                    ui.separator();
+                    render_attachment_preview(ui, preview);
+                    ui.separator();
+                }
 
-                    if ui.button("Cancel").clicked() {
-                        close_menu = true;
-                    }
-                });
+                if ui
+                    .add_enabled(
+                        !read_only && !over_quota,
+                        egui::Button::new("Attach Files…"),
+                    )
+                    .on_disabled_hover_text(if over_quota {
+                        "Your storage quota is used up. Remove attachments or raise the \
+                         quota in Settings → Storage."
+                    } else {
+                        "This note can't be changed"
+                    })
+                    .clicked()
+                {
+                    attach = true;
+                }
             });
+        ui.separator();
 
-        // Handle actions
-        if let Some(note_id) = export_note_id {
-            self.export_note_to_file(&note_id);
+        if attach {
+            self.attach_files_to_note(note_id);
         }
-
-        if let Some(note_id) = delete_note_id {
-            self.delete_note(&note_id);
+        if let Some(attachment_id) = preview_id {
+            self.toggle_attachment_preview(ui.ctx(), note_id, &attachment_id);
         }
-
-        if close_menu {
-            self.show_context_menu = false;
-            self.context_menu_note_id = None;
+        if let Some(attachment_id) = save_id {
+            self.save_attachment_as(note_id, &attachment_id);
         }
-
-        // Close menu if clicked elsewhere
-        if ctx.input(|i| i.pointer.any_click()) && !self.show_context_menu {
-            self.show_context_menu = false;
-            self.context_menu_note_id = None;
+        if let Some(attachment_id) = remove_id {
+            self.remove_attachment(note_id, &attachment_id);
         }
     }
 
-    /// Renders the main content area for note editing.
-    ///
-    /// The main content area displays:
-    /// - Status messages at the top (if any)
-    /// - Note header with title, timestamps, and export button
-    /// - Large text editor for note content
-    /// - Welcome message when no note is selected
+    /// Renders the collapsible panel with the comments on a note.
     ///
-    /// The text editor automatically updates the note's modification time
-    /// when content changes and triggers auto-save functionality.
+    /// Comments are listed oldest first with their author and time. New
+    /// comments are added with the text field below the list; users can
+    /// remove their own comments.
     ///
     /// # Arguments
     ///
-    /// * `ctx` - The egui context for rendering
-    pub fn render_main_content(&mut self, ctx: &egui::Context) {
-        egui::CentralPanel::default().show(ctx, |ui| {
-            // Show status message at the top if present
-            if let Some(ref message) = self.status_message {
-                ui.horizontal(|ui| {
-                    ui.colored_label(egui::Color32::from_rgb(100, 200, 100), "ℹ");
-                    ui.label(message);
-                });
-                ui.separator();
-            }
+    /// * `ui` - The main content UI
+    /// * `note_id` - The ID of the displayed note
+    /// * `read_only` - Whether adding and removing is disabled (trashed notes)
+    fn render_comments_panel(&mut self, ui: &mut egui::Ui, note_id: &str, read_only: bool) {
+        let username = self
+            .current_user
+            .as_ref()
+            .map(|user| user.username.clone())
+            .unwrap_or_default();
+        let Some(note) = self.notes.get_mut(note_id) else {
+            return;
+        };
 
-            // Clone the selected note ID to avoid borrowing issues
-            if let Some(note_id) = self.selected_note_id.clone() {
-                // Get the note data we need for display (immutable borrow)
-                let (note_title, note_created_time, note_modified_time) = {
-                    if let Some(note) = self.notes.get(&note_id) {
-                        (
-                            note.title.clone(),
-                            note.format_created_time(),
-                            note.format_modified_time(),
-                        )
-                    } else {
-                        return; // Note doesn't exist anymore
-                    }
-                };
+        let mut add_comment = false;
+        let mut remove_id = None;
 
-                // Display the header with note info and export button
-                ui.horizontal(|ui| {
-                    ui.heading(&note_title);
-                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                        // Export button
+        egui::CollapsingHeader::new(format!("Comments ({})", note.comments.len()))
+            .id_salt("note_comments")
+            .show(ui, |ui| {
+                egui::ScrollArea::vertical()
+                    .id_salt("note_comments_list")
+                    .max_height(160.0)
+                    .auto_shrink([false, true])
+                    .show(ui, |ui| {
+                        for comment in &note.comments {
+                            ui.horizontal(|ui| {
+                                ui.strong(&comment.author);
+                                ui.small(comment.format_created_time());
+                                if comment.author == username
+                                    && ui
+                                        .add_enabled(!read_only, egui::Button::new("✕").small())
+                                        .on_hover_text("Remove comment")
+                                        .clicked()
+                                {
+                                    remove_id = Some(comment.id.clone());
+                                }
+                            });
+                            ui.label(&comment.text);
+                            ui.add_space(4.0);
+                        }
+                    });
+
+                ui.add_enabled_ui(!read_only, |ui| {
+                    ui.horizontal(|ui| {
+                        let response = ui.add(
+                            egui::TextEdit::singleline(&mut self.new_comment_input)
+                                .hint_text("Add a comment")
+                                .desired_width(320.0),
+                        );
+                        let has_text = !self.new_comment_input.trim().is_empty();
+                        let submitted =
+                            response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
                         if ui
-                            .button("Export (Ctrl + E)")
-                            .on_hover_text("Export note to .txt file")
+                            .add_enabled(has_text, egui::Button::new("Comment"))
                             .clicked()
+                            || (submitted && has_text)
                         {
-                            self.export_note_to_file(&note_id);
+                            add_comment = true;
                         }
-
-                        ui.separator();
-
-                        // Show both created and modified times
-                        ui.vertical(|ui| {
-                            ui.small(format!("Modified: {}", note_modified_time));
-                            ui.small(format!("Created: {}", note_created_time));
-                        });
                     });
                 });
-                ui.separator();
+            });
 
-                // Calculate available space for the text editor
-                let available_height = ui.available_height();
-                let header_height = 80.0; // Approximate height for header and separator
-                let text_area_height = (available_height - header_height).max(200.0);
+        let mut changed = false;
+        if add_comment {
+            let text = std::mem::take(&mut self.new_comment_input);
+            note.comments
+                .push(Comment::new(username, text.trim().to_string()));
+            changed = true;
+        }
+        if let Some(comment_id) = remove_id {
+            note.comments.retain(|comment| comment.id != comment_id);
+            changed = true;
+        }
 
-                // Create a scrollable text area with fixed height
-                egui::ScrollArea::vertical()
-                    .max_height(text_area_height)
-                    .auto_shrink([false, false])
-                    .show(ui, |ui| {
-                        // Now get mutable access to the note content
-                        if let Some(note) = self.notes.get_mut(&note_id) {
-                            let response = ui.add_sized(
-                                [
-                                    ui.available_width(),
-                                    ui.available_height().max(text_area_height),
-                                ],
-                                egui::TextEdit::multiline(&mut note.content)
-                                    .desired_width(f32::INFINITY)
-                                    .desired_rows(20), // Minimum number of visible rows
-                            );
+        if changed {
+            note.update_modified_time();
+            self.has_unsaved_changes = true;
+            self.last_save_time = std::time::Instant::now();
+        }
+        ui.separator();
+    }
 
-                            if response.changed() {
-                                note.update_modified_time();
-                                self.last_save_time = std::time::Instant::now();
-                            }
-                        }
-                    });
-            } else {
-                ui.vertical_centered(|ui| {
-                    ui.add_space(200.0);
-                    ui.heading("Select a note to edit");
-                    ui.label("Or create a new note using the sidebar");
-                    ui.add_space(20.0);
-                    ui.small(format!("Current time: {}", self.get_current_time()));
-                });
+    /// Renders the banner shown above a trashed note.
+    ///
+    /// Offers restoring the note, deleting it permanently and exempting it
+    /// from automatic purging.
+    ///
+    /// # Arguments
+    ///
+    /// * `ui` - The main content UI
+    /// * `note_id` - The ID of the displayed note
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - Whether the note is in the trash (and thus read-only)
+    fn render_trash_banner(&mut self, ui: &mut egui::Ui, note_id: &str) -> bool {
+        let retention_days = self.settings.trash_retention_days;
+        let Some(note) = self.notes.get_mut(note_id) else {
+            return false;
+        };
+        let Some(deleted_at) = note.deleted_at else {
+            return false;
+        };
+
+        let mut restore = false;
+        let mut delete = false;
+        let mut keep_forever_changed = false;
+
+        ui.horizontal(|ui| {
+            ui.colored_label(
+                egui::Color32::from_rgb(255, 165, 0),
+                format!(
+                    "🗑 In trash since {}",
+                    deleted_at.with_timezone(&Zurich).format("%d.%m.%Y %H:%M")
+                ),
+            );
+            if let Some(purge_at) = note.purge_at(retention_days) {
+                ui.small(format!(
+                    "(purged on {})",
+                    purge_at.with_timezone(&Zurich).format("%d.%m.%Y")
+                ));
             }
+
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if ui.button("Delete Permanently").clicked() {
+                    delete = true;
+                }
+                if ui.button("Restore").clicked() {
+                    restore = true;
+                }
+                keep_forever_changed = ui
+                    .checkbox(&mut note.keep_forever, "Keep forever")
+                    .on_hover_text("Never purge this note automatically")
+                    .changed();
+            });
         });
+        ui.separator();
+
+        if keep_forever_changed {
+            self.save_notes();
+        }
+        if restore {
+            self.restore_note(note_id);
+        }
+        if delete {
+            self.delete_notes_permanently(&[note_id.to_string()]);
+        }
+
+        !restore
+    }
+
+    /// Renders the banner shown above a note being edited by another instance.
+    ///
+    /// Offers taking over the lock, which makes the note editable here and
+    /// read-only in the other instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `ui` - The main content UI
+    /// * `note_id` - The ID of the displayed note
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - Whether the note is locked elsewhere (and thus read-only)
+    fn render_lock_banner(&mut self, ui: &mut egui::Ui, note_id: &str) -> bool {
+        let Some(lock) = self.note_locked_elsewhere(note_id) else {
+            return false;
+        };
+        let location = lock.location();
+
+        let mut take_over = false;
+        ui.horizontal(|ui| {
+            ui.colored_label(
+                egui::Color32::from_rgb(255, 165, 0),
+                format!("🔒 Being edited {}, read-only here", location),
+            );
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if ui
+                    .button("Take Over")
+                    .on_hover_text(
+                        "Edit the note here instead; it becomes read-only in the other instance",
+                    )
+                    .clicked()
+                {
+                    take_over = true;
+                }
+            });
+        });
+        ui.separator();
+
+        if take_over {
+            self.take_over_note_lock(note_id);
+        }
+        !take_over
+    }
+
+    /// Opens the new note dialog with empty fields.
+    pub fn open_new_note_dialog(&mut self) {
+        self.show_new_note_dialog = true;
+        self.new_note_title.clear();
+        self.new_note_kind = NoteKind::Text;
+        self.new_note_notebook.clear();
+        self.new_note_focus_pending = true;
     }
 
     /// Renders the new note creation dialog.
@@ -409,23 +2226,43 @@ impl NotesApp {
     /// A modal dialog that allows users to enter a title for a new note.
     /// Features:
     /// - Text input field with placeholder text
-    /// - Auto-focus on the input field
-    /// - Enter key to create the note
+    /// - Focus on the title when the dialog opens; Tab moves on to the
+    ///   notebook, the note type and the buttons
+    /// - Enter in the title or notebook to create the note, Escape to cancel
+    /// - Warning when the title is already used, with a suffixed suggestion
     /// - Create and Cancel buttons
-    /// - Automatic dialog closure after creation
+    /// - Automatic dialog closure after creation, with the cursor in the
+    ///   new note's editor; after cancelling, the focus goes back to where
+    ///   it was
     ///
     /// # Arguments
     ///
     /// * `ctx` - The egui context for rendering
     pub fn render_new_note_dialog(&mut self, ctx: &egui::Context) {
         if !self.show_new_note_dialog {
+            // Closed without creating a note, e.g. with Escape
+            if let Some(focus) = self.new_note_return_focus.take() {
+                ctx.memory_mut(|memory| memory.request_focus(focus));
+            }
             return;
         }
 
+        let focus_title = std::mem::take(&mut self.new_note_focus_pending);
+        if focus_title {
+            self.new_note_return_focus = ctx.memory(|memory| memory.focused());
+        }
+
         // Extract the current title to avoid borrowing issues
         let mut current_title = self.new_note_title.clone();
+        let notebook_paths = notebook::all_paths(self.notes.values());
         let mut create_note = false;
         let mut cancel_dialog = false;
+        let mut paste_note = false;
+
+        // Warn about titles already in use, which would make links ambiguous
+        let suggested_title = (!current_title.trim().is_empty()
+            && self.note_title_exists(&current_title))
+        .then(|| self.unique_note_title(&current_title));
 
         egui::Window::new("Create New Note")
             .open(&mut self.show_new_note_dialog)
@@ -444,14 +2281,66 @@ impl NotesApp {
                         egui::TextEdit::singleline(&mut current_title).hint_text("My new note..."),
                     );
 
-                    // Auto-focus the text field when dialog opens
-                    response.request_focus();
+                    // Focus the title when the dialog opens, then let Tab move on
+                    if focus_title {
+                        response.request_focus();
+                    }
 
                     // Handle Enter key
                     if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
                         create_note = true;
                     }
 
+                    if let Some(suggested_title) = &suggested_title {
+                        ui.add_space(5.0);
+                        ui.colored_label(
+                            egui::Color32::YELLOW,
+                            "⚠ A note with this title already exists",
+                        );
+                        if ui.button(format!("Use \"{}\"", suggested_title)).clicked() {
+                            current_title = suggested_title.clone();
+                        }
+                    }
+
+                    ui.add_space(10.0);
+                    ui.horizontal(|ui| {
+                        ui.label("Notebook:");
+                        let response = ui.add(
+                            egui::TextEdit::singleline(&mut self.new_note_notebook)
+                                .hint_text("None")
+                                .desired_width(160.0),
+                        );
+                        if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                            create_note = true;
+                        }
+                        ui.menu_button("⏷", |ui| {
+                            if ui.button("None").clicked() {
+                                self.new_note_notebook.clear();
+                                ui.close_menu();
+                            }
+                            for path in &notebook_paths {
+                                if ui.button(path).clicked() {
+                                    self.new_note_notebook = path.clone();
+                                    ui.close_menu();
+                                }
+                            }
+                        });
+                    });
+
+                    ui.add_space(10.0);
+                    let mut secrets = self.new_note_kind == NoteKind::Secrets;
+                    if ui
+                        .checkbox(&mut secrets, "Secrets note")
+                        .on_hover_text("Passwords and keys as entries with hidden values")
+                        .changed()
+                    {
+                        self.new_note_kind = if secrets {
+                            NoteKind::Secrets
+                        } else {
+                            NoteKind::Text
+                        };
+                    }
+
                     ui.add_space(15.0);
 
                     ui.horizontal(|ui| {
@@ -462,9 +2351,19 @@ impl NotesApp {
                         if ui.button("Cancel").clicked() {
                             cancel_dialog = true;
                         }
+
+                        if ui
+                            .button("Paste Encrypted…")
+                            .on_hover_text("Import a note someone sent you as encrypted text")
+                            .clicked()
+                        {
+                            paste_note = true;
+                        }
                     });
 
-                    ui.add_space(10.0);
+                    ui.add_space(5.0);
+                    ui.small("Enter creates, Tab moves to the next field, Esc cancels");
+                    ui.add_space(5.0);
                 });
             });
 
@@ -474,15 +2373,28 @@ impl NotesApp {
         // Handle actions outside the window closure
         if create_note {
             let title = self.new_note_title.clone();
-            self.create_new_note(title);
+            let notebook = notebook::normalize_path(&self.new_note_notebook);
+            self.create_new_note(title, self.new_note_kind, notebook);
             self.show_new_note_dialog = false;
             self.new_note_title.clear();
+            // Start typing in the new note right away
+            self.new_note_return_focus = None;
+            if let Some(note_id) = self.selected_note_id.clone() {
+                self.editor_jump = Some((note_id, 0..0));
+            }
         }
 
         if cancel_dialog {
             self.show_new_note_dialog = false;
             self.new_note_title.clear();
         }
+
+        if paste_note {
+            self.show_new_note_dialog = false;
+            self.new_note_title.clear();
+            self.new_note_return_focus = None;
+            self.note_share_dialog = Some(NoteShareDialog::paste());
+        }
     }
 
     /// Renders the security information panel.
@@ -517,9 +2429,44 @@ impl NotesApp {
             .current_user
             .as_ref()
             .map(|u| (u.username.clone(), u.created_at));
+        let login_history = self.login_history.clone();
+        let integrity_report = self.integrity_report.clone();
+        let integrity_scan_running = self.integrity_scan.is_some();
+        let mut unreadable_note_ids: Vec<String> =
+            self.unreadable_note_ids.iter().cloned().collect();
+        unreadable_note_ids.sort();
+        let journal_summary = self.journal.as_ref().map(|journal| {
+            let recent: Vec<String> = journal
+                .entries()
+                .iter()
+                .rev()
+                .take(5)
+                .map(|entry| {
+                    let title = self
+                        .notes
+                        .get(&entry.note_id)
+                        .map_or(entry.note_id.as_str(), |note| note.title.as_str());
+                    format!(
+                        "{} · {} \"{}\"",
+                        entry.format_time(),
+                        entry.op.label(),
+                        title
+                    )
+                })
+                .collect();
+            (
+                journal.replica_id.chars().take(8).collect::<String>(),
+                journal.clock.get(&journal.replica_id),
+                journal.entries().len(),
+                recent,
+            )
+        });
 
-        // Track if we need to run a security audit
+        // Track if we need to run a security audit or an integrity scan
         let mut run_audit = false;
+        let mut run_integrity_scan = false;
+        let mut diagnose_note_id = None;
+        let mut open_recovery = false;
 
         egui::Window::new("Security Information")
             .open(&mut self.show_security_panel)
@@ -544,6 +2491,37 @@ impl NotesApp {
                     ));
                 }
 
+                if !login_history.is_empty() {
+                    ui.separator();
+                    ui.heading("Recent Logins");
+                    egui::ScrollArea::vertical()
+                        .id_salt("login_history")
+                        .max_height(180.0)
+                        .show(ui, |ui| {
+                            egui::Grid::new("login_history_grid")
+                                .num_columns(3)
+                                .striped(true)
+                                .show(ui, |ui| {
+                                    for record in login_history.iter().rev() {
+                                        ui.label(record.format_time());
+                                        let color = match record.outcome {
+                                            LoginOutcome::Success => {
+                                                egui::Color32::from_rgb(100, 200, 100)
+                                            }
+                                            _ => egui::Color32::from_rgb(255, 100, 100),
+                                        };
+                                        ui.colored_label(color, record.outcome.label());
+                                        ui.label(record.hardware_label());
+                                        ui.end_row();
+                                    }
+                                });
+                        });
+                    ui.small(
+                        "Attempts with an unknown username are not recorded. \
+                         If you don't recognize an attempt, change your password.",
+                    );
+                }
+
                 ui.separator();
                 ui.heading("Security Audit");
 
@@ -569,10 +2547,124 @@ impl NotesApp {
                     }
                 }
 
+                ui.separator();
+                ui.heading("Integrity Scan");
+                ui.label(
+                    "Decrypts every stored file in the background to find damaged files early.",
+                );
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(
+                            has_crypto_manager && !integrity_scan_running,
+                            egui::Button::new("Scan Now"),
+                        )
+                        .clicked()
+                    {
+                        run_integrity_scan = true;
+                    }
+                    if integrity_scan_running {
+                        ui.spinner();
+                        ui.label("Scanning…");
+                    }
+                });
+
+                match &integrity_report {
+                    Some(report) if report.issues.is_empty() => {
+                        ui.colored_label(
+                            egui::Color32::from_rgb(0, 200, 0),
+                            format!(
+                                "{} files intact (scanned {})",
+                                report.files_checked,
+                                report.format_time()
+                            ),
+                        );
+                    }
+                    Some(report) => {
+                        ui.colored_label(
+                            egui::Color32::from_rgb(255, 100, 100),
+                            format!(
+                                "⚠ {} problem(s) in {} files (scanned {}):",
+                                report.issues.len(),
+                                report.files_checked,
+                                report.format_time()
+                            ),
+                        );
+                        egui::ScrollArea::vertical()
+                            .id_salt("integrity_issues")
+                            .max_height(150.0)
+                            .show(ui, |ui| {
+                                for issue in &report.issues {
+                                    ui.colored_label(
+                                        egui::Color32::from_rgb(255, 150, 150),
+                                        format!(
+                                            "• {} {}: {}",
+                                            issue.vault, issue.file, issue.problem
+                                        ),
+                                    );
+                                }
+                            });
+                        ui.small(
+                            "Restore damaged files from a backup. Notes that cannot be \
+                             decrypted are kept on disk and not overwritten.",
+                        );
+                    }
+                    None if !integrity_scan_running => {
+                        ui.label("Not scanned yet in this session");
+                    }
+                    None => {}
+                }
+
+                if let Some((replica, counter, kept, recent)) = &journal_summary {
+                    ui.separator();
+                    ui.heading("Change Journal");
+                    ui.label(format!(
+                        "Replica {} · {} change(s) recorded, the last {} kept",
+                        replica, counter, kept
+                    ));
+                    for line in recent {
+                        ui.small(line);
+                    }
+                    ui.small("Lets sync transfer only the notes changed since the last sync.");
+                }
+
+                if !unreadable_note_ids.is_empty() {
+                    ui.add_space(5.0);
+                    ui.colored_label(
+                        egui::Color32::from_rgb(255, 100, 100),
+                        format!(
+                            "⚠ {} note(s) could not be decrypted when loading:",
+                            unreadable_note_ids.len()
+                        ),
+                    );
+                    for note_id in &unreadable_note_ids {
+                        ui.horizontal(|ui| {
+                            ui.monospace(note_id);
+                            if ui.small_button("Diagnostics").clicked() {
+                                diagnose_note_id = Some(note_id.clone());
+                            }
+                        });
+                    }
+                    if ui.button("Recover…").clicked() {
+                        open_recovery = true;
+                    }
+                }
+
                 ui.separator();
                 ui.small(format!("Local time: {}", current_time));
             });
 
+        if run_integrity_scan {
+            self.start_integrity_scan();
+        }
+
+        if let Some(note_id) = diagnose_note_id {
+            self.open_note_diagnostics(&note_id);
+        }
+
+        if open_recovery {
+            self.open_recovery_dialog();
+        }
+
         // Run the security audit outside the window closure
         if run_audit {
             if let Some(ref crypto_manager) = self.crypto_manager {
@@ -583,3 +2675,71 @@ impl NotesApp {
         }
     }
 }
+
+/// Renders a `dd.mm.yyyy` date field, highlighted red while invalid.
+///
+/// # Returns
+///
+/// * `bool` - Whether the text was changed
+fn date_input(ui: &mut egui::Ui, input: &mut String) -> bool {
+    let invalid = !input.trim().is_empty() && search::parse_date(input).is_none();
+    let mut text_edit = egui::TextEdit::singleline(input)
+        .hint_text("dd.mm.yyyy")
+        .desired_width(90.0);
+    if invalid {
+        text_edit = text_edit.text_color(egui::Color32::RED);
+    }
+    ui.add(text_edit).changed()
+}
+
+/// Renders the preview of an attachment below the attachment list.
+///
+/// # Arguments
+///
+/// * `ui` - The attachments panel UI
+/// * `preview` - The decoded attachment
+fn render_attachment_preview(ui: &mut egui::Ui, preview: &AttachmentPreview) {
+    ui.strong(&preview.file_name);
+    egui::ScrollArea::both()
+        .id_salt("attachment_preview")
+        .max_height(360.0)
+        .auto_shrink([false, true])
+        .show(ui, |ui| match &preview.content {
+            PreviewContent::Image {
+                texture,
+                original_size,
+            } => {
+                ui.small(format!(
+                    "{} × {} pixels",
+                    original_size[0], original_size[1]
+                ));
+                let size = texture.size_vec2();
+                let scale = (ui.available_width() / size.x).min(1.0);
+                ui.add(egui::Image::new((texture.id(), size * scale)));
+            }
+            PreviewContent::Text { text, truncated } => {
+                ui.add(egui::Label::new(egui::RichText::new(text).monospace()).wrap());
+                if *truncated {
+                    ui.small("… The rest of the file is not shown.");
+                }
+            }
+            PreviewContent::Pdf {
+                page_count,
+                first_page_text,
+            } => {
+                if let Some(count) = page_count {
+                    ui.small(format!("PDF, {} page(s). Text of the first page:", count));
+                } else {
+                    ui.small("PDF. Text of the first page:");
+                }
+                if first_page_text.is_empty() {
+                    ui.weak("No readable text, e.g. a scanned page. Save the file to view it.");
+                } else {
+                    ui.label(first_page_text);
+                }
+            }
+            PreviewContent::Unsupported(reason) => {
+                ui.weak(reason);
+            }
+        });
+}
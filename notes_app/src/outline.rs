@@ -0,0 +1,101 @@
+//! # Outline Module
+//!
+//! Extracts the Markdown headings of a note for the outline panel. Only
+//! ATX headings are recognized: a line starting with one to six `#`
+//! followed by a space, indented by at most three spaces, as in
+//! `## Meeting notes`. Closing `#`s are dropped. Lines inside fenced code
+//! blocks are skipped, so comments like `# TODO` in shell code don't show
+//! up as headings.
+//!
+//! A `#` directly followed by a word is a tag (`#work`), not a heading.
+
+use crate::highlight;
+use std::ops::Range;
+
+/// Deepest heading level, as in Markdown.
+const MAX_HEADING_LEVEL: usize = 6;
+
+/// A heading found in a note.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Heading {
+    /// Level from 1 (`#`) to 6 (`######`)
+    pub level: usize,
+    /// Heading text without the markers
+    pub title: String,
+    /// Byte range of the whole heading line in the note content
+    pub line: Range<usize>,
+}
+
+/// Finds the headings of a text, in order.
+///
+/// # Arguments
+///
+/// * `text` - The note content
+///
+/// # Returns
+///
+/// * `Vec<Heading>` - The headings outside of fenced code blocks
+pub fn headings(text: &str) -> Vec<Heading> {
+    let code_blocks = highlight::fenced_blocks(text);
+    let mut headings = Vec::new();
+    let mut line_start = 0;
+
+    for line in text.split_inclusive('\n') {
+        let line_range = line_start..line_start + line.trim_end_matches(['\n', '\r']).len();
+        line_start += line.len();
+
+        if code_blocks
+            .iter()
+            .any(|(code, _)| code.contains(&line_range.start))
+        {
+            continue;
+        }
+        if let Some((level, title)) = parse_heading(&text[line_range.clone()]) {
+            headings.push(Heading {
+                level,
+                title,
+                line: line_range,
+            });
+        }
+    }
+
+    headings
+}
+
+/// Parses a single line as an ATX heading.
+///
+/// # Arguments
+///
+/// * `line` - The line without its line break
+///
+/// # Returns
+///
+/// * `Option<(usize, String)>` - Level and text, or None if the line is
+///   not a heading or has no text
+pub fn parse_heading(line: &str) -> Option<(usize, String)> {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+
+    let level = trimmed.chars().take_while(|&c| c == '#').count();
+    if level == 0 || level > MAX_HEADING_LEVEL {
+        return None;
+    }
+    let rest = &trimmed[level..];
+    if !rest.starts_with([' ', '\t']) {
+        return None;
+    }
+
+    // Closing #s only count after a space, so "# C#" keeps its title
+    let title = rest.trim();
+    let title = match title.trim_end_matches('#') {
+        stripped if stripped.is_empty() || stripped.ends_with([' ', '\t']) => stripped.trim_end(),
+        _ => title,
+    };
+    if title.is_empty() {
+        None
+    } else {
+        Some((level, title.to_string()))
+    }
+}
@@ -0,0 +1,264 @@
+//! # Passphrase Module
+//!
+//! Encrypts data that leaves the vault with a key derived from a passphrase
+//! only, so it can be opened on another computer or by another user. Used
+//! by migration bundles and by notes shared as encrypted text.
+//!
+//! ## Format
+//!
+//! ```text
+//! magic [4] | version u8 | memory KiB u32 | iterations u32 | parallelism u32
+//!           | salt [16] | nonce [24] | XChaCha20Poly1305 ciphertext
+//! ```
+//!
+//! Deriving the key takes seconds, so dialogs run `seal` and `open` in a
+//! [`PassphraseTask`] on a background thread instead of the UI thread.
+//!
+//! All integers are little endian. Everything before the nonce is bound to
+//! the ciphertext as associated data, so the KDF parameters cannot be
+//! altered without the data failing to open. Since they are only
+//! authenticated after the key is derived, parameters outside
+//! `KdfParams::is_within_bounds` are rejected before deriving.
+
+use crate::crypto::{AeadAlgorithm, KdfParams};
+use anyhow::{anyhow, Result};
+use chacha20poly1305::aead::{rand_core::RngCore, OsRng};
+use eframe::egui;
+use secure_core::{aead, kdf};
+use std::sync::mpsc;
+use std::thread;
+
+/// Minimum length of a passphrase.
+pub const MIN_PASSPHRASE_LEN: usize = 8;
+
+/// Length of the random passphrase salt in bytes.
+const SALT_LEN: usize = 16;
+
+/// Length of the header before the nonce (magic, version, KDF params, salt).
+const HEADER_LEN: usize = 4 + 1 + 3 * 4 + SALT_LEN;
+
+/// Length of the XChaCha20Poly1305 nonce in bytes.
+const NONCE_LEN: usize = 24;
+
+/// Identifies a kind of passphrase-protected data.
+pub struct SealedFormat {
+    /// Magic bytes at the start of the data
+    pub magic: &'static [u8; 4],
+    /// Current format version
+    pub version: u8,
+    /// Name used in error messages, e.g. "migration bundle"
+    pub name: &'static str,
+}
+
+/// A passphrase operation running on a background thread.
+///
+/// Dropping the task discards its result; the thread still runs to the
+/// end of the key derivation.
+pub struct PassphraseTask<T> {
+    /// Receives the result once the operation is done
+    receiver: mpsc::Receiver<Result<T>>,
+}
+
+impl<T: Send + 'static> PassphraseTask<T> {
+    /// Starts an operation on a background thread.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The egui context to wake up when the operation is done
+    /// * `operation` - The work to do, usually a call to `seal` or `open`
+    ///
+    /// # Returns
+    ///
+    /// * `Self` - The running task, see `poll`
+    pub fn spawn(
+        ctx: &egui::Context,
+        operation: impl FnOnce() -> Result<T> + Send + 'static,
+    ) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let ctx = ctx.clone();
+        thread::spawn(move || {
+            let _ = sender.send(operation());
+            ctx.request_repaint();
+        });
+        Self { receiver }
+    }
+
+    /// Checks whether the operation is done.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Result<T>>` - The result once the operation finished, None
+    ///   while it is running
+    pub fn poll(&self) -> Option<Result<T>> {
+        match self.receiver.try_recv() {
+            Ok(result) => Some(result),
+            Err(mpsc::TryRecvError::Empty) => None,
+            Err(mpsc::TryRecvError::Disconnected) => {
+                Some(Err(anyhow!("The operation stopped unexpectedly")))
+            }
+        }
+    }
+}
+
+/// Encrypts data with a passphrase.
+///
+/// # Arguments
+///
+/// * `format` - The kind of data, written into the header
+/// * `plaintext` - The data to encrypt
+/// * `passphrase` - Passphrase protecting the data
+///
+/// # Returns
+///
+/// * `Result<Vec<u8>>` - Header, nonce and ciphertext, or error if the
+///   passphrase is too short
+pub fn seal(format: &SealedFormat, plaintext: &[u8], passphrase: &str) -> Result<Vec<u8>> {
+    if passphrase.len() < MIN_PASSPHRASE_LEN {
+        return Err(anyhow!(
+            "Passphrase must be at least {} characters long",
+            MIN_PASSPHRASE_LEN
+        ));
+    }
+
+    let params = KdfParams::CURRENT;
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+
+    let mut header = Vec::with_capacity(HEADER_LEN);
+    header.extend_from_slice(format.magic);
+    header.push(format.version);
+    header.extend_from_slice(&params.memory_cost_kib.to_le_bytes());
+    header.extend_from_slice(&params.iterations.to_le_bytes());
+    header.extend_from_slice(&params.parallelism.to_le_bytes());
+    header.extend_from_slice(&salt);
+
+    let key = derive_passphrase_key(format, passphrase, &salt, &params)?;
+    let (nonce, ciphertext) =
+        aead::seal(AeadAlgorithm::XChaCha20Poly1305, &key, plaintext, &header)?;
+
+    let mut data = header;
+    data.extend_from_slice(&nonce);
+    data.extend_from_slice(&ciphertext);
+    Ok(data)
+}
+
+/// Decrypts data written by `seal`.
+///
+/// # Arguments
+///
+/// * `format` - The kind of data expected
+/// * `data` - Header, nonce and ciphertext
+/// * `passphrase` - Passphrase the data was sealed with
+///
+/// # Returns
+///
+/// * `Result<Vec<u8>>` - The plaintext, or error if the data is of another
+///   kind, its key derivation parameters are out of range, the passphrase
+///   is wrong or the data was modified
+pub fn open(format: &SealedFormat, data: &[u8], passphrase: &str) -> Result<Vec<u8>> {
+    if data.len() < HEADER_LEN + NONCE_LEN || &data[..4] != format.magic {
+        return Err(anyhow!("Not a Secure Notes {}", format.name));
+    }
+    if data[4] != format.version {
+        return Err(anyhow!(
+            "Unsupported {} version {}, update the app on this computer",
+            format.name,
+            data[4]
+        ));
+    }
+
+    let read_u32 = |offset: usize| {
+        u32::from_le_bytes([
+            data[offset],
+            data[offset + 1],
+            data[offset + 2],
+            data[offset + 3],
+        ])
+    };
+    let params = KdfParams {
+        memory_cost_kib: read_u32(5),
+        iterations: read_u32(9),
+        parallelism: read_u32(13),
+    };
+    // Not authenticated yet, a crafted header could exhaust memory or time
+    if !params.is_within_bounds() {
+        return Err(anyhow!(
+            "Unsupported key derivation parameters in {}",
+            format.name
+        ));
+    }
+
+    let (header, rest) = data.split_at(HEADER_LEN);
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+    let salt = &header[HEADER_LEN - SALT_LEN..];
+
+    let key = derive_passphrase_key(format, passphrase, salt, &params)?;
+    aead::open(
+        AeadAlgorithm::XChaCha20Poly1305,
+        &key,
+        nonce,
+        ciphertext,
+        header,
+    )
+    .map_err(|_| anyhow!("Wrong passphrase or damaged {}", format.name))
+}
+
+/// Derives the key from the passphrase.
+///
+/// Unlike the vault key, the salt is random and stored in the header, so
+/// the key does not depend on the hardware of any computer.
+fn derive_passphrase_key(
+    format: &SealedFormat,
+    passphrase: &str,
+    salt: &[u8],
+    params: &KdfParams,
+) -> Result<kdf::Key> {
+    kdf::derive_key(passphrase, salt, params)
+        .map_err(|e| anyhow!("Invalid {} parameters: {}", format.name, e))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    const TEST_FORMAT: SealedFormat = SealedFormat {
+        magic: b"SNTS",
+        version: 1,
+        name: "test file",
+    };
+
+    /// Builds sealed data with the given header parameters and a dummy
+    /// ciphertext, without deriving a key.
+    pub(crate) fn crafted(format: &SealedFormat, params: KdfParams) -> Vec<u8> {
+        let mut data = format.magic.to_vec();
+        data.push(format.version);
+        data.extend_from_slice(&params.memory_cost_kib.to_le_bytes());
+        data.extend_from_slice(&params.iterations.to_le_bytes());
+        data.extend_from_slice(&params.parallelism.to_le_bytes());
+        data.extend_from_slice(&[0u8; SALT_LEN + NONCE_LEN + 32]);
+        data
+    }
+
+    #[test]
+    fn open_rejects_huge_kdf_params_before_deriving() {
+        for params in [
+            KdfParams {
+                memory_cost_kib: u32::MAX,
+                ..KdfParams::CURRENT
+            },
+            KdfParams {
+                iterations: u32::MAX,
+                ..KdfParams::CURRENT
+            },
+            KdfParams {
+                parallelism: 0,
+                ..KdfParams::CURRENT
+            },
+        ] {
+            let error = open(&TEST_FORMAT, &crafted(&TEST_FORMAT, params), "passphrase")
+                .unwrap_err()
+                .to_string();
+            assert!(error.starts_with("Unsupported key derivation parameters"));
+        }
+    }
+}
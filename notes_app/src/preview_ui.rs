@@ -0,0 +1,320 @@
+//! # Preview UI Module
+//!
+//! The Markdown preview of the main content area, shown instead of the
+//! editor or next to it, see `EditorView`. Each note remembers the view it
+//! was last shown in; notes never switched use the default of the settings. The preview is rendered from the
+//! note text every frame, so it follows typing without a delay; the parsed
+//! blocks are cached until the note changes, see the text cache module.
+//!
+//! In the split view, scrolling one pane scrolls the other to the same
+//! relative position. The panes don't line up line by line, since headings
+//! and code blocks take more room rendered than as text, but close enough
+//! to keep the paragraph being edited in view.
+
+use crate::app::NotesApp;
+use crate::highlight;
+use crate::markdown::{self, Block, Inline, ListMarker};
+use crate::settings::EditorView;
+use crate::text_cache::LARGE_NOTE_BYTES;
+use eframe::egui;
+use egui::scroll_area::ScrollAreaOutput;
+
+/// Font sizes of heading levels 1 to 6, in points.
+const HEADING_SIZES: [f32; 6] = [24.0, 20.0, 17.0, 15.0, 14.0, 13.0];
+
+/// Indentation per nesting level of list items, in points.
+const LIST_INDENT: f32 = 16.0;
+
+/// Scroll distance below which a pane counts as not scrolled, in points.
+const SCROLL_SYNC_TOLERANCE: f32 = 0.5;
+
+impl NotesApp {
+    /// Returns the view a note is shown in.
+    ///
+    /// # Arguments
+    ///
+    /// * `note_id` - The ID of the note
+    ///
+    /// # Returns
+    ///
+    /// * `EditorView` - The note's own view, or the default of the settings
+    pub fn editor_view_of(&self, note_id: &str) -> EditorView {
+        self.notes
+            .get(note_id)
+            .and_then(|note| note.editor_view)
+            .unwrap_or(self.settings.editor_view)
+    }
+
+    /// Switches the open note between the editor, the split view and the
+    /// preview.
+    ///
+    /// The view is remembered with the note, without changing its
+    /// modification time. The editor keeps its scroll position, although
+    /// the editor of the split view is a different scroll area than the
+    /// full-width one.
+    ///
+    /// # Arguments
+    ///
+    /// * `view` - The view to show
+    pub fn set_editor_view(&mut self, view: EditorView) {
+        let Some(note) = self
+            .selected_note_id
+            .as_ref()
+            .and_then(|id| self.notes.get_mut(id))
+        else {
+            return;
+        };
+        if note.editor_view.unwrap_or(self.settings.editor_view) == view {
+            return;
+        }
+        note.editor_view = Some(view);
+        self.save_notes();
+        self.editor_scroll_restore = Some(self.editor_scroll_offset);
+        self.split_scroll_offsets = None;
+    }
+
+    /// Renders the editor and the preview side by side with synchronized
+    /// scrolling.
+    ///
+    /// # Arguments
+    ///
+    /// * `ui` - The UI of the main content area
+    /// * `note_id` - The ID of the note
+    /// * `read_only` - Whether editing is disabled (trashed or locked notes)
+    /// * `height` - Height of both panes
+    pub fn render_split_view(
+        &mut self,
+        ui: &mut egui::Ui,
+        note_id: &str,
+        read_only: bool,
+        height: f32,
+    ) {
+        let (editor, preview) = ui.columns(2, |columns| {
+            let editor = self.render_editor_pane(&mut columns[0], note_id, read_only, height);
+            let preview = self.render_preview_pane(&mut columns[1], note_id, height);
+            (editor, preview)
+        });
+        self.sync_split_scroll(ui.ctx(), &editor, &preview);
+    }
+
+    /// Scrolls the pane the user didn't scroll to the relative position of
+    /// the one they did, in the next frame.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The egui context, to request the next frame
+    /// * `editor` - Scroll output of the editor pane
+    /// * `preview` - Scroll output of the preview pane
+    fn sync_split_scroll(
+        &mut self,
+        ctx: &egui::Context,
+        editor: &ScrollAreaOutput<()>,
+        preview: &ScrollAreaOutput<()>,
+    ) {
+        let editor_offset = editor.state.offset.y;
+        let preview_offset = preview.state.offset.y;
+        let last = self.split_scroll_offsets;
+        // Entering the split view counts as scrolling the editor
+        let editor_scrolled =
+            last.is_none_or(|(last, _)| (editor_offset - last).abs() > SCROLL_SYNC_TOLERANCE);
+        let preview_scrolled =
+            last.is_some_and(|(_, last)| (preview_offset - last).abs() > SCROLL_SYNC_TOLERANCE);
+
+        let mut offsets = (editor_offset, preview_offset);
+        if editor_scrolled {
+            let target = scroll_fraction(editor) * max_scroll_offset(preview);
+            if (target - preview_offset).abs() > SCROLL_SYNC_TOLERANCE {
+                self.preview_scroll_restore = Some(target);
+                offsets.1 = target;
+                ctx.request_repaint();
+            }
+        } else if preview_scrolled {
+            let target = scroll_fraction(preview) * max_scroll_offset(editor);
+            if (target - editor_offset).abs() > SCROLL_SYNC_TOLERANCE {
+                self.editor_scroll_restore = Some(target);
+                offsets.0 = target;
+                ctx.request_repaint();
+            }
+        }
+        self.split_scroll_offsets = Some(offsets);
+    }
+
+    /// Renders the Markdown preview of a note in a scroll area of a fixed
+    /// height.
+    ///
+    /// Links open in the browser on click. Notes larger than
+    /// `LARGE_NOTE_BYTES` are not rendered, to keep typing fast.
+    ///
+    /// # Arguments
+    ///
+    /// * `ui` - The UI of the main content area or of the split view's right pane
+    /// * `note_id` - The ID of the note
+    /// * `height` - Height of the scroll area
+    ///
+    /// # Returns
+    ///
+    /// * `ScrollAreaOutput<()>` - Scroll position and sizes of the preview
+    pub fn render_preview_pane(
+        &mut self,
+        ui: &mut egui::Ui,
+        note_id: &str,
+        height: f32,
+    ) -> ScrollAreaOutput<()> {
+        let mut scroll_area = egui::ScrollArea::vertical()
+            .id_salt("markdown_preview")
+            .max_height(height)
+            .auto_shrink([false, false]);
+        if let Some(offset) = self.preview_scroll_restore.take() {
+            scroll_area = scroll_area.vertical_scroll_offset(offset);
+        }
+
+        let is_large = self
+            .notes
+            .get(note_id)
+            .is_some_and(|note| note.content.len() > LARGE_NOTE_BYTES);
+        let blocks = if is_large {
+            None
+        } else {
+            self.preview_blocks(note_id)
+        };
+
+        scroll_area.show(ui, |ui| {
+            if is_large {
+                ui.small("Large note: the preview is off to keep typing fast.");
+                return;
+            }
+            match blocks {
+                Some([]) => {
+                    ui.weak("Nothing to preview yet.");
+                }
+                Some(blocks) => {
+                    for block in blocks {
+                        render_block(ui, block);
+                    }
+                }
+                None => {}
+            }
+        })
+    }
+}
+
+/// Returns how far a scroll area is scrolled.
+///
+/// # Returns
+///
+/// * `f32` - From 0.0 at the top to 1.0 at the bottom
+fn scroll_fraction(output: &ScrollAreaOutput<()>) -> f32 {
+    let max = max_scroll_offset(output);
+    if max > 0.0 {
+        (output.state.offset.y / max).clamp(0.0, 1.0)
+    } else {
+        0.0
+    }
+}
+
+/// Returns the scroll offset of a scroll area scrolled to the bottom.
+fn max_scroll_offset(output: &ScrollAreaOutput<()>) -> f32 {
+    (output.content_size.y - output.inner_rect.height()).max(0.0)
+}
+
+/// Renders a block of the preview.
+///
+/// # Arguments
+///
+/// * `ui` - The preview UI
+/// * `block` - The block to render
+fn render_block(ui: &mut egui::Ui, block: &Block) {
+    match block {
+        Block::Heading { level, text } => {
+            ui.add_space(6.0);
+            let size = HEADING_SIZES[(*level).clamp(1, HEADING_SIZES.len()) - 1];
+            render_text(ui, text, |text| text.size(size).strong());
+            ui.add_space(2.0);
+        }
+        Block::Paragraph(text) => {
+            render_text(ui, text, |text| text);
+            ui.add_space(6.0);
+        }
+        Block::ListItem {
+            depth,
+            marker,
+            text,
+        } => {
+            ui.horizontal_top(|ui| {
+                ui.add_space(*depth as f32 * LIST_INDENT);
+                match marker {
+                    ListMarker::Bullet => {
+                        ui.label("•");
+                    }
+                    ListMarker::Number(number) => {
+                        ui.label(format!("{}.", number));
+                    }
+                    ListMarker::Task(checked) => {
+                        ui.add_enabled(false, egui::Checkbox::without_text(&mut checked.clone()));
+                    }
+                }
+                ui.vertical(|ui| render_text(ui, text, |text| text));
+            });
+        }
+        Block::Quote(text) => {
+            egui::Frame::none()
+                .fill(ui.visuals().faint_bg_color)
+                .inner_margin(egui::Margin::symmetric(10.0, 6.0))
+                .show(ui, |ui| {
+                    ui.set_width(ui.available_width());
+                    render_text(ui, text, |text| text.italics());
+                });
+            ui.add_space(6.0);
+        }
+        Block::Code { language, code } => {
+            let job = highlight::layout_job(ui, code, Some(highlight::fence_token(language)));
+            egui::Frame::none()
+                .fill(ui.visuals().extreme_bg_color)
+                .rounding(4.0)
+                .inner_margin(8.0)
+                .show(ui, |ui| {
+                    ui.set_width(ui.available_width());
+                    ui.add(egui::Label::new(job).selectable(true));
+                });
+            ui.add_space(6.0);
+        }
+        Block::Rule => {
+            ui.separator();
+        }
+    }
+}
+
+/// Renders the lines of a block with their inline formatting.
+///
+/// # Arguments
+///
+/// * `ui` - The preview UI
+/// * `text` - The text of the block
+/// * `style` - Formatting applied to every span, e.g. the heading size
+fn render_text(ui: &mut egui::Ui, text: &str, style: impl Fn(egui::RichText) -> egui::RichText) {
+    for line in text.lines() {
+        ui.horizontal_wrapped(|ui| {
+            ui.spacing_mut().item_spacing.x = 0.0;
+            for span in markdown::inlines(line) {
+                match span {
+                    Inline::Text(text) => {
+                        ui.label(style(egui::RichText::new(text)));
+                    }
+                    Inline::Strong(text) => {
+                        ui.label(style(egui::RichText::new(text)).strong());
+                    }
+                    Inline::Emphasis(text) => {
+                        ui.label(style(egui::RichText::new(text)).italics());
+                    }
+                    Inline::Code(text) => {
+                        ui.label(style(egui::RichText::new(text)).code());
+                    }
+                    Inline::Link { text, url } => {
+                        ui.hyperlink_to(style(egui::RichText::new(text)), url)
+                            .on_hover_text(url);
+                    }
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,94 @@
+//! # Profiling Module
+//!
+//! Measures the steps between launching the application and working with
+//! the notes: loading the user database, the first frame, verifying the
+//! password, deriving the key and loading the notes. Every measured step
+//! is written to the log and kept in memory for the startup profile window
+//! in the settings, so a slower unlock shows up in numbers instead of
+//! impressions.
+//!
+//! Steps run on background threads record themselves, so the durations
+//! don't include the time until the UI picks up their results.
+
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// Number of spans kept; each login adds a few.
+const MAX_SPANS: usize = 100;
+
+/// A measured step.
+#[derive(Debug, Clone)]
+pub struct Span {
+    /// What was measured, e.g. "Key derivation"
+    pub name: &'static str,
+    /// When the step started, counted from the launch
+    pub start: Duration,
+    /// How long the step took
+    pub duration: Duration,
+}
+
+/// The launch time and the spans recorded since.
+struct Profile {
+    /// When `init` was called
+    launched: Instant,
+    /// Recorded spans, oldest first
+    spans: Mutex<Vec<Span>>,
+}
+
+static PROFILE: OnceLock<Profile> = OnceLock::new();
+
+/// Returns the profile, starting it if `init` wasn't called.
+fn profile() -> &'static Profile {
+    PROFILE.get_or_init(|| Profile {
+        launched: Instant::now(),
+        spans: Mutex::new(Vec::new()),
+    })
+}
+
+/// Marks the launch of the application, which span start times count from.
+///
+/// Called at the start of `main`.
+pub fn init() {
+    profile();
+}
+
+/// Records a step that started at `started` and ends now.
+///
+/// # Arguments
+///
+/// * `name` - What was measured
+/// * `started` - When the step started
+pub fn record(name: &'static str, started: Instant) {
+    let profile = profile();
+    let span = Span {
+        name,
+        start: started.saturating_duration_since(profile.launched),
+        duration: started.elapsed(),
+    };
+    log::info!(
+        "Profile: {} took {:.1} ms (started {:.2} s after launch)",
+        span.name,
+        span.duration.as_secs_f64() * 1000.0,
+        span.start.as_secs_f64()
+    );
+
+    if let Ok(mut spans) = profile.spans.lock() {
+        if spans.len() >= MAX_SPANS {
+            spans.remove(0);
+        }
+        spans.push(span);
+    }
+}
+
+/// Returns the recorded spans.
+///
+/// # Returns
+///
+/// * `Vec<Span>` - The spans, oldest first
+pub fn spans() -> Vec<Span> {
+    profile()
+        .spans
+        .lock()
+        .map(|spans| spans.clone())
+        .unwrap_or_default()
+}
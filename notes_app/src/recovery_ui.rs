@@ -0,0 +1,321 @@
+//! # Recovery UI Module
+//!
+//! The Unreadable Notes dialog, opened once the notes are loaded if some of
+//! them failed to decrypt, and from the security panel. It sums up the
+//! failures by reason, see the load_failures module, and offers to restore
+//! each private note from its history or all of them from a migration
+//! bundle. A restored note replaces the damaged file at the next save;
+//! notes that are not restored stay on disk as they are.
+//!
+//! Shared vault notes have no history and are not in migration bundles, so
+//! they can only be diagnosed here.
+
+use crate::app::NotesApp;
+use crate::load_failures;
+use crate::migration::{self, BUNDLE_EXTENSION};
+use crate::note::Note;
+use chrono::{DateTime, Utc};
+use chrono_tz::Europe::Zurich;
+use eframe::egui;
+use std::collections::HashMap;
+
+/// State of the Unreadable Notes dialog.
+pub struct RecoveryDialog {
+    /// When the latest revision of each private note was kept, None if it
+    /// has no history, or why the history can't be read
+    histories: HashMap<String, Result<Option<DateTime<Utc>>, String>>,
+    /// Passphrase of the migration bundle to restore from
+    passphrase: String,
+    /// Restores done since the dialog was opened
+    restored: Vec<String>,
+    /// Why the last restore failed
+    error: Option<String>,
+}
+
+/// What to do after the dialog was drawn.
+enum RecoveryAction {
+    FromHistory(String),
+    FromBackup,
+    Diagnose(String),
+}
+
+impl NotesApp {
+    /// Opens the Unreadable Notes dialog, looking up the history of each
+    /// private note that failed to load.
+    pub fn open_recovery_dialog(&mut self) {
+        let (Some(user), Some(crypto_manager)) = (&self.current_user, &self.crypto_manager) else {
+            return;
+        };
+        let histories = self
+            .load_failures
+            .iter()
+            .filter(|failure| !failure.shared)
+            .map(|failure| {
+                let latest = self
+                    .storage_manager
+                    .load_note_history(&user.id, &failure.note_id, crypto_manager)
+                    .map(|history| history.latest_recorded_at())
+                    .map_err(|e| e.to_string());
+                (failure.note_id.clone(), latest)
+            })
+            .collect();
+        self.recovery_dialog = Some(RecoveryDialog {
+            histories,
+            passphrase: String::new(),
+            restored: Vec::new(),
+            error: None,
+        });
+    }
+
+    /// Renders the Unreadable Notes dialog, if open.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The egui context for rendering
+    pub fn render_recovery_dialog(&mut self, ctx: &egui::Context) {
+        let Some(dialog) = &mut self.recovery_dialog else {
+            return;
+        };
+        let failures = &self.load_failures;
+
+        let mut open = true;
+        let mut action = None;
+        egui::Window::new("⚠ Unreadable Notes")
+            .open(&mut open)
+            .collapsible(false)
+            .default_width(520.0)
+            .show(ctx, |ui| {
+                if failures.is_empty() {
+                    ui.colored_label(
+                        egui::Color32::from_rgb(0, 200, 0),
+                        "✔ All notes could be decrypted or were restored.",
+                    );
+                } else {
+                    ui.label(format!(
+                        "{} note(s) could not be decrypted when loading. Their files are \
+                         kept as they are until you restore them.",
+                        failures.len()
+                    ));
+                    ui.add_space(5.0);
+                    for (reason, count) in load_failures::count_by_reason(failures) {
+                        ui.strong(format!("{}: {}", reason.label(), count));
+                        ui.small(reason.explanation());
+                    }
+                    ui.separator();
+
+                    egui::ScrollArea::vertical()
+                        .max_height(240.0)
+                        .show(ui, |ui| {
+                            egui::Grid::new("recovery_grid")
+                                .num_columns(4)
+                                .striped(true)
+                                .show(ui, |ui| {
+                                    for failure in failures {
+                                        ui.monospace(&failure.note_id)
+                                            .on_hover_text(&failure.error);
+                                        ui.label(failure.reason.label());
+
+                                        let latest = dialog.histories.get(&failure.note_id);
+                                        match latest {
+                                            _ if failure.shared => {
+                                                ui.label("Shared, no history");
+                                            }
+                                            Some(Ok(Some(recorded_at))) => {
+                                                ui.label(format!(
+                                                    "Version of {}",
+                                                    recorded_at
+                                                        .with_timezone(&Zurich)
+                                                        .format("%d.%m.%Y %H:%M")
+                                                ));
+                                            }
+                                            Some(Err(error)) => {
+                                                ui.label("History unreadable").on_hover_text(error);
+                                            }
+                                            _ => {
+                                                ui.label("No history");
+                                            }
+                                        }
+
+                                        ui.horizontal(|ui| {
+                                            let restorable = matches!(latest, Some(Ok(Some(_))));
+                                            if ui
+                                                .add_enabled(
+                                                    restorable && !failure.shared,
+                                                    egui::Button::new("Restore"),
+                                                )
+                                                .on_hover_text(
+                                                    "Replace the file with the latest version \
+                                                     kept in the history, which may be older \
+                                                     than the damaged one",
+                                                )
+                                                .clicked()
+                                            {
+                                                action = Some(RecoveryAction::FromHistory(
+                                                    failure.note_id.clone(),
+                                                ));
+                                            }
+                                            if ui.button("Diagnostics").clicked() {
+                                                action = Some(RecoveryAction::Diagnose(
+                                                    failure.note_id.clone(),
+                                                ));
+                                            }
+                                        });
+                                        ui.end_row();
+                                    }
+                                });
+                        });
+
+                    ui.separator();
+                    ui.label("Restore from a migration bundle:");
+                    ui.horizontal(|ui| {
+                        ui.add(
+                            egui::TextEdit::singleline(&mut dialog.passphrase)
+                                .password(true)
+                                .hint_text("Passphrase")
+                                .desired_width(200.0),
+                        );
+                        if ui
+                            .add_enabled(
+                                !dialog.passphrase.is_empty(),
+                                egui::Button::new("Choose Bundle…"),
+                            )
+                            .clicked()
+                        {
+                            action = Some(RecoveryAction::FromBackup);
+                        }
+                    });
+                    ui.small(
+                        "Restores the private notes listed here that the bundle holds; \
+                         your other notes are not changed.",
+                    );
+                }
+
+                for summary in &dialog.restored {
+                    ui.colored_label(egui::Color32::from_rgb(0, 200, 0), format!("✔ {}", summary));
+                }
+                if let Some(error) = &dialog.error {
+                    ui.colored_label(egui::Color32::from_rgb(255, 100, 100), error);
+                }
+            });
+
+        if !open {
+            self.recovery_dialog = None;
+            return;
+        }
+        let result = match action {
+            Some(RecoveryAction::FromHistory(note_id)) => self.restore_from_history(&note_id),
+            Some(RecoveryAction::FromBackup) => self.restore_from_bundle(),
+            Some(RecoveryAction::Diagnose(note_id)) => {
+                self.open_note_diagnostics(&note_id);
+                return;
+            }
+            None => return,
+        };
+        let Some(dialog) = &mut self.recovery_dialog else {
+            return;
+        };
+        match result {
+            Ok(Some(summary)) => {
+                log::info!("{}", summary);
+                dialog.restored.push(summary);
+                dialog.error = None;
+            }
+            Ok(None) => {}
+            Err(e) => {
+                log::error!("Failed to restore unreadable notes: {}", e);
+                dialog.error = Some(e.to_string());
+            }
+        }
+    }
+
+    /// Restores an unreadable note from the latest version in its history.
+    ///
+    /// # Returns
+    ///
+    /// * `anyhow::Result<Option<String>>` - What was restored, or error
+    fn restore_from_history(&mut self, note_id: &str) -> anyhow::Result<Option<String>> {
+        let (Some(user), Some(crypto_manager)) = (&self.current_user, &self.crypto_manager) else {
+            anyhow::bail!("Not logged in");
+        };
+        let history = self
+            .storage_manager
+            .load_note_history(&user.id, note_id, crypto_manager)?;
+        let Some(revision) = history.revisions.last() else {
+            anyhow::bail!("The note has no history");
+        };
+        let title = revision.note.title.clone();
+        self.restore_unreadable_note(revision.note.clone());
+        Ok(Some(format!("Restored \"{}\" from its history", title)))
+    }
+
+    /// Asks for a migration bundle and restores the unreadable private
+    /// notes it holds.
+    ///
+    /// # Returns
+    ///
+    /// * `anyhow::Result<Option<String>>` - What was restored, None if no
+    ///   bundle was chosen, or error
+    fn restore_from_bundle(&mut self) -> anyhow::Result<Option<String>> {
+        let Some(path) = rfd::FileDialog::new()
+            .set_title("Restore from Migration Bundle")
+            .add_filter("Migration bundles", &[BUNDLE_EXTENSION])
+            .add_filter("All files", &["*"])
+            .pick_file()
+        else {
+            return Ok(None);
+        };
+        let passphrase = self
+            .recovery_dialog
+            .as_ref()
+            .map(|dialog| dialog.passphrase.clone())
+            .unwrap_or_default();
+        let bundle = migration::read_bundle(&path, &passphrase)?;
+
+        let wanted: Vec<String> = self
+            .load_failures
+            .iter()
+            .filter(|failure| !failure.shared)
+            .map(|failure| failure.note_id.clone())
+            .collect();
+        let found: Vec<Note> = bundle
+            .notes
+            .into_iter()
+            .filter(|note| wanted.contains(&note.id))
+            .collect();
+        if found.is_empty() {
+            anyhow::bail!(
+                "The bundle of {} holds none of the unreadable notes",
+                bundle.exported_at.with_timezone(&Zurich).format("%d.%m.%Y")
+            );
+        }
+
+        let count = found.len();
+        for note in found {
+            self.restore_unreadable_note(note);
+        }
+        Ok(Some(format!(
+            "Restored {} note(s) from the bundle of {}",
+            count,
+            bundle.exported_at.with_timezone(&Zurich).format("%d.%m.%Y")
+        )))
+    }
+
+    /// Puts a restored version of an unreadable note back into the list and
+    /// saves, which replaces the damaged file.
+    ///
+    /// # Arguments
+    ///
+    /// * `note` - The version to restore
+    fn restore_unreadable_note(&mut self, mut note: Note) {
+        note.shared = false;
+        self.unreadable_note_ids.remove(&note.id);
+        self.load_failures
+            .retain(|failure| failure.note_id != note.id || failure.shared);
+        if let Some(dialog) = &mut self.recovery_dialog {
+            dialog.histories.remove(&note.id);
+        }
+        self.search_index.note_changed(&note);
+        self.notes.insert(note.id.clone(), note);
+        self.save_notes();
+    }
+}
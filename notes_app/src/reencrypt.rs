@@ -0,0 +1,404 @@
+//! # Re-encryption Module
+//!
+//! Rewrites every note, history and attachment file of a vault on a
+//! background thread, for operations that change how the whole vault is
+//! encrypted: switching the encryption algorithm, changing the password
+//! (which derives a new key) and turning two-person unlock on or off
+//! (which switches between a split random key and a derived one). With many or large attachments this takes
+//! long enough that it must not block the UI.
+//!
+//! A [`ReencryptJob`] only rewrites the files. The settings, the journal
+//! and the shared vault key are small and written by the app once the job
+//! finished, together with the new password hash. While a job runs, notes
+//! are not saved, so a file is never written by the job and the app at
+//! the same time.
+//!
+//! Stopping an algorithm change is always safe, since every file names its
+//! algorithm in its header. Stopping a key change isn't: the files
+//! already rewritten need the new key. Cancelling it therefore starts a
+//! revert job that writes them back with the old key.
+
+use crate::crypto::{AeadAlgorithm, CryptoManager};
+use crate::storage::{StorageManager, SHARED_VAULT_OWNER};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// What a job re-encrypts for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReencryptKind {
+    /// Switching to another encryption algorithm
+    Algorithm(AeadAlgorithm),
+    /// Changing the password, which derives a new key
+    PasswordChange,
+    /// Turning two-person unlock on (true) or off (false), which replaces
+    /// the key
+    TwoPersonUnlock(bool),
+    /// Writing files back with the old key after a failed key change
+    PasswordRevert,
+}
+
+impl ReencryptKind {
+    /// Returns the title of the progress dialog.
+    pub fn title(self) -> &'static str {
+        match self {
+            ReencryptKind::Algorithm(_) => "Changing Encryption Algorithm",
+            ReencryptKind::PasswordChange => "Changing Password",
+            ReencryptKind::TwoPersonUnlock(true) => "Turning On Two-Person Unlock",
+            ReencryptKind::TwoPersonUnlock(false) => "Turning Off Two-Person Unlock",
+            ReencryptKind::PasswordRevert => "Undoing Key Change",
+        }
+    }
+
+    /// Returns whether the job can be stopped from the dialog.
+    pub fn cancellable(self) -> bool {
+        !matches!(self, ReencryptKind::PasswordRevert)
+    }
+
+    /// Returns whether the job stops at the first file that fails.
+    ///
+    /// A key change must be complete to be kept. A revert, like an
+    /// algorithm change, rewrites as many files as it can.
+    fn stops_on_error(self) -> bool {
+        self.changes_key()
+    }
+
+    /// Returns whether the job switches to a new key, which is derived or
+    /// split before any file is rewritten.
+    pub fn changes_key(self) -> bool {
+        matches!(
+            self,
+            ReencryptKind::PasswordChange | ReencryptKind::TwoPersonUnlock(_)
+        )
+    }
+}
+
+/// Progress reported by the re-encryption thread.
+enum ReencryptEvent {
+    /// The files were listed
+    Started(usize),
+    /// A file was rewritten
+    Rewritten,
+    /// A file could not be rewritten
+    Failed(String),
+    /// The job ended, handing back the user's crypto manager
+    Finished(Option<Box<CryptoManager>>),
+}
+
+/// A re-encryption running on a background thread.
+///
+/// The UI polls the job every frame with [`ReencryptJob::poll`] and shows
+/// the public counters as progress.
+pub struct ReencryptJob {
+    /// Events from the re-encryption thread
+    receiver: mpsc::Receiver<ReencryptEvent>,
+    /// Set to stop the thread after the current file
+    cancel: Arc<AtomicBool>,
+    /// The user's crypto manager, once the thread finished
+    crypto: Option<CryptoManager>,
+    /// What the job re-encrypts for
+    pub kind: ReencryptKind,
+    /// Number of files to rewrite, known once they are listed
+    pub total: Option<usize>,
+    /// Number of files processed so far
+    pub processed: usize,
+    /// Errors of files that could not be rewritten
+    pub errors: Vec<String>,
+    /// When the job started
+    pub started: Instant,
+    /// How long the job took, once finished
+    pub duration: Option<Duration>,
+    /// Whether the thread is done
+    pub finished: bool,
+    /// Outcome shown in the dialog, set once the app has finished the job
+    pub summary: Option<String>,
+}
+
+impl ReencryptJob {
+    /// Starts rewriting the files of a user, and of the shared vault, with
+    /// a new algorithm.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - The user whose files to rewrite
+    /// * `user_crypto` - The user's crypto manager, set to the new algorithm
+    /// * `shared_crypto` - The shared vault's crypto manager, set to the
+    ///   new algorithm, if the user is a member
+    /// * `algorithm` - The new algorithm
+    ///
+    /// # Returns
+    ///
+    /// * `Self` - The running job
+    pub fn change_algorithm(
+        user_id: &str,
+        user_crypto: CryptoManager,
+        shared_crypto: Option<CryptoManager>,
+        algorithm: AeadAlgorithm,
+    ) -> Self {
+        Self::spawn(
+            ReencryptKind::Algorithm(algorithm),
+            user_id,
+            user_crypto,
+            shared_crypto,
+            |_| Ok(()),
+        )
+    }
+
+    /// Starts a password change: derives the new key and rewrites the
+    /// user's files with it.
+    ///
+    /// The crypto manager handed back keeps the old key for decryption,
+    /// see `CryptoManager::begin_password_change`. If the key can't be
+    /// derived, none is handed back and nothing was changed.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - The user whose password changes
+    /// * `crypto` - The user's crypto manager
+    /// * `old_password` - The current password
+    /// * `new_password` - The new password
+    ///
+    /// # Returns
+    ///
+    /// * `Self` - The running job
+    pub fn change_password(
+        user_id: &str,
+        crypto: CryptoManager,
+        old_password: String,
+        new_password: String,
+    ) -> Self {
+        let owner = user_id.to_string();
+        Self::spawn(
+            ReencryptKind::PasswordChange,
+            user_id,
+            crypto,
+            None,
+            move |crypto| crypto.begin_password_change(&old_password, &new_password, &owner),
+        )
+    }
+
+    /// Starts turning two-person unlock on or off: replaces the key and
+    /// rewrites the user's files with it.
+    ///
+    /// As with `change_password`, the crypto manager handed back keeps the
+    /// old key for decryption, and none is handed back if the new key
+    /// couldn't be created.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - The user whose key changes
+    /// * `crypto` - The user's crypto manager
+    /// * `enable` - True to split the key, false to derive it from the
+    ///   password again
+    /// * `password` - The user's password
+    /// * `second_passphrase` - The passphrase of the second person
+    ///
+    /// # Returns
+    ///
+    /// * `Self` - The running job
+    pub fn change_two_person_unlock(
+        user_id: &str,
+        crypto: CryptoManager,
+        enable: bool,
+        password: String,
+        second_passphrase: String,
+    ) -> Self {
+        let owner = user_id.to_string();
+        Self::spawn(
+            ReencryptKind::TwoPersonUnlock(enable),
+            user_id,
+            crypto,
+            None,
+            move |crypto| {
+                if enable {
+                    crypto.begin_two_person_unlock(&owner, &password, &second_passphrase)
+                } else {
+                    crypto.begin_two_person_removal(&owner, &password, &second_passphrase)
+                }
+            },
+        )
+    }
+
+    /// Starts writing the user's files back with the old key after a
+    /// password or two-person unlock change failed or was cancelled.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - The user whose key change is undone
+    /// * `crypto` - The user's crypto manager after `revert_key_change`
+    ///
+    /// # Returns
+    ///
+    /// * `Self` - The running job
+    pub fn revert_password_change(user_id: &str, crypto: CryptoManager) -> Self {
+        Self::spawn(ReencryptKind::PasswordRevert, user_id, crypto, None, |_| {
+            Ok(())
+        })
+    }
+
+    /// Starts the re-encryption thread.
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - What the job re-encrypts for
+    /// * `user_id` - The user whose files to rewrite
+    /// * `crypto` - The user's crypto manager
+    /// * `shared_crypto` - The shared vault's crypto manager, if its files
+    ///   are rewritten too
+    /// * `prepare` - Run on the thread before any file is rewritten; if it
+    ///   fails, the job ends without handing back the crypto manager
+    fn spawn(
+        kind: ReencryptKind,
+        user_id: &str,
+        mut crypto: CryptoManager,
+        shared_crypto: Option<CryptoManager>,
+        prepare: impl FnOnce(&mut CryptoManager) -> anyhow::Result<()> + Send + 'static,
+    ) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let cancelled = Arc::clone(&cancel);
+        let user_id = user_id.to_string();
+
+        thread::spawn(move || {
+            if let Err(e) = prepare(&mut crypto) {
+                let _ = sender.send(ReencryptEvent::Failed(e.to_string()));
+                let _ = sender.send(ReencryptEvent::Finished(None));
+                return;
+            }
+
+            let storage_manager = StorageManager::new();
+            let mut owners = vec![user_id.as_str()];
+            if shared_crypto.is_some() {
+                owners.push(SHARED_VAULT_OWNER);
+            }
+            let mut files = Vec::new();
+            for owner in owners {
+                match storage_manager.list_encrypted_files(owner) {
+                    Ok(listed) => files.extend(listed.into_iter().map(|file| (owner, file))),
+                    Err(e) => {
+                        let _ = sender.send(ReencryptEvent::Failed(format!(
+                            "Could not list the files: {}",
+                            e
+                        )));
+                        if kind.stops_on_error() {
+                            let _ = sender.send(ReencryptEvent::Finished(Some(Box::new(crypto))));
+                            return;
+                        }
+                    }
+                }
+            }
+            let _ = sender.send(ReencryptEvent::Started(files.len()));
+
+            for (owner, file) in &files {
+                if cancelled.load(Ordering::Relaxed) {
+                    break;
+                }
+                let vault_crypto = match &shared_crypto {
+                    Some(shared_crypto) if *owner == SHARED_VAULT_OWNER => shared_crypto,
+                    _ => &crypto,
+                };
+                let event = match storage_manager.reencrypt_file(owner, file, vault_crypto) {
+                    Ok(()) => ReencryptEvent::Rewritten,
+                    Err(e) => {
+                        log::error!("{}", e);
+                        ReencryptEvent::Failed(e.to_string())
+                    }
+                };
+                let failed = matches!(event, ReencryptEvent::Failed(_));
+                // Keep going if the job was dropped: the files must end up
+                // consistent, see `NotesApp::settle_reencrypt_job`
+                let _ = sender.send(event);
+                if failed && kind.stops_on_error() {
+                    break;
+                }
+            }
+            log::info!("Re-encrypted the files of user {} ({:?})", user_id, kind);
+            let _ = sender.send(ReencryptEvent::Finished(Some(Box::new(crypto))));
+        });
+
+        Self {
+            receiver,
+            cancel,
+            crypto: None,
+            kind,
+            total: None,
+            processed: 0,
+            errors: Vec::new(),
+            started: Instant::now(),
+            duration: None,
+            finished: false,
+            summary: None,
+        }
+    }
+
+    /// Receives the progress made since the last poll.
+    ///
+    /// # Arguments
+    ///
+    /// * `wait` - Whether to block until the job finished
+    pub fn poll(&mut self, wait: bool) {
+        while !self.finished {
+            let event = if wait {
+                self.receiver
+                    .recv()
+                    .map_err(|_| mpsc::TryRecvError::Disconnected)
+            } else {
+                self.receiver.try_recv()
+            };
+            match event {
+                Ok(ReencryptEvent::Started(total)) => self.total = Some(total),
+                Ok(ReencryptEvent::Rewritten) => self.processed += 1,
+                Ok(ReencryptEvent::Failed(error)) => {
+                    self.errors.push(error);
+                    self.processed += 1;
+                }
+                Ok(ReencryptEvent::Finished(crypto)) => {
+                    self.crypto = crypto.map(|crypto| *crypto);
+                    self.duration = Some(self.started.elapsed());
+                    self.finished = true;
+                }
+                Err(mpsc::TryRecvError::Empty) => break,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    self.errors
+                        .push("Re-encryption stopped unexpectedly".to_string());
+                    self.duration = Some(self.started.elapsed());
+                    self.finished = true;
+                }
+            }
+        }
+    }
+
+    /// Takes the crypto manager handed back by the finished thread.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<CryptoManager>` - The manager, None if the job failed
+    ///   before changing anything or it was taken already
+    pub fn take_crypto(&mut self) -> Option<CryptoManager> {
+        self.crypto.take()
+    }
+
+    /// Asks the thread to stop after the current file.
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns whether the job was cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+
+    /// Returns the fraction of files processed.
+    ///
+    /// # Returns
+    ///
+    /// * `f32` - Between 0.0 and 1.0; 0.0 until the files are listed
+    pub fn fraction(&self) -> f32 {
+        match self.total {
+            Some(0) => 1.0,
+            Some(total) => self.processed as f32 / total as f32,
+            None => 0.0,
+        }
+    }
+}
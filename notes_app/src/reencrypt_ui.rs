@@ -0,0 +1,494 @@
+//! # Re-encryption UI Module
+//!
+//! Starts and finishes [`ReencryptJob`]s and shows their progress in a
+//! modal dialog. While a job runs, the rest of the window is dimmed and
+//! doesn't take input, so notes can't be edited, imported or deleted
+//! halfway through. Once the job finished, the dialog shows a summary
+//! until it is closed.
+
+use crate::app::NotesApp;
+use crate::crypto::{AeadAlgorithm, CryptoManager};
+use crate::reencrypt::{ReencryptJob, ReencryptKind};
+use eframe::egui;
+use std::time::Duration;
+
+/// How often the dialog is redrawn while a job runs.
+const PROGRESS_REPAINT_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Number of errors listed in the summary; the rest are only counted.
+const MAX_LISTED_ERRORS: usize = 20;
+
+impl NotesApp {
+    /// Switches the vault to another algorithm and starts re-encrypting the
+    /// existing files with it.
+    ///
+    /// New writes use the algorithm right away. Files not rewritten yet
+    /// stay readable, since every file names its algorithm in its header.
+    ///
+    /// # Arguments
+    ///
+    /// * `algorithm` - The new algorithm
+    pub fn start_algorithm_change(&mut self, algorithm: AeadAlgorithm) {
+        let (Some(crypto_manager), Some(user)) = (&mut self.crypto_manager, &self.current_user)
+        else {
+            return;
+        };
+        crypto_manager.set_algorithm(algorithm);
+        let user_crypto = crypto_manager.clone();
+        let user_id = user.id.clone();
+        if let Some(ref mut vault) = self.shared_vault {
+            vault.set_algorithm(algorithm);
+        }
+        self.save_settings();
+        self.save_notes();
+
+        let shared_crypto = self
+            .shared_vault
+            .as_ref()
+            .map(|vault| vault.crypto().clone());
+        self.reencrypt_job = Some(ReencryptJob::change_algorithm(
+            &user_id,
+            user_crypto,
+            shared_crypto,
+            algorithm,
+        ));
+    }
+
+    /// Receives the progress of the running re-encryption and finishes it
+    /// once the thread is done.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The egui context, to keep the progress moving
+    pub fn poll_reencrypt_job(&mut self, ctx: &egui::Context) {
+        let Some(ref mut job) = self.reencrypt_job else {
+            return;
+        };
+        if job.summary.is_some() {
+            return;
+        }
+
+        job.poll(false);
+        if !job.finished {
+            ctx.request_repaint_after(PROGRESS_REPAINT_INTERVAL);
+            return;
+        }
+        self.finish_reencrypt_job();
+    }
+
+    /// Waits for the running re-encryption before logging out or exiting.
+    ///
+    /// An algorithm change is stopped after the current file. A password
+    /// or two-person unlock change is completed, or undone if it fails;
+    /// stopping it halfway would leave files that only open with a key the
+    /// user can't log in with.
+    pub fn settle_reencrypt_job(&mut self) {
+        while let Some(ref mut job) = self.reencrypt_job {
+            if job.summary.is_some() {
+                break;
+            }
+            if matches!(job.kind, ReencryptKind::Algorithm(_)) {
+                job.cancel();
+            }
+            job.poll(true);
+            // Finishing a failed key change starts the revert job,
+            // which is waited for too
+            self.finish_reencrypt_job();
+        }
+    }
+
+    /// Completes a finished job and sets the summary shown in the dialog.
+    fn finish_reencrypt_job(&mut self) {
+        let Some(ref mut job) = self.reencrypt_job else {
+            return;
+        };
+        let crypto = job.take_crypto();
+        let cancelled = job.is_cancelled();
+        let processed = job.processed;
+        let total = job.total.unwrap_or(processed);
+        let duration = job.duration.unwrap_or_default();
+        let failures = job.errors.len();
+        let first_error = job.errors.first().cloned();
+
+        let summary = match job.kind {
+            ReencryptKind::Algorithm(algorithm) => {
+                log::info!(
+                    "Re-encrypted {} of {} files with {} in {:.1?} ({} failed{})",
+                    processed - failures,
+                    total,
+                    algorithm.label(),
+                    duration,
+                    failures,
+                    if cancelled { ", cancelled" } else { "" }
+                );
+                if cancelled {
+                    format!(
+                        "Stopped after {} of {} files. The other files keep their \
+                         previous algorithm and open as before.",
+                        processed, total
+                    )
+                } else if failures > 0 {
+                    format!(
+                        "{} of {} files re-encrypted with {}. {} could not be \
+                         rewritten and keep their previous algorithm.",
+                        processed - failures,
+                        total,
+                        algorithm.label(),
+                        failures
+                    )
+                } else {
+                    format!(
+                        "All {} files re-encrypted with {}.",
+                        total,
+                        algorithm.label()
+                    )
+                }
+            }
+            ReencryptKind::PasswordChange => {
+                let error = match first_error {
+                    Some(error) if !cancelled || crypto.is_none() => error,
+                    _ => "Cancelled".to_string(),
+                };
+                match crypto {
+                    // The key could not be derived, nothing was changed
+                    None => self.password_change_failed(error),
+                    Some(crypto) if cancelled || failures > 0 => {
+                        self.start_password_revert(crypto, password_not_changed(&error));
+                        return;
+                    }
+                    Some(crypto) => {
+                        self.crypto_manager = Some(crypto);
+                        match self.finish_password_change() {
+                            Ok(()) => {
+                                log::info!(
+                                    "Password changed, {} files re-encrypted in {:.1?}",
+                                    total,
+                                    duration
+                                );
+                                self.show_change_password_dialog = false;
+                                self.password_change_error = None;
+                                self.old_password_input.clear();
+                                self.new_password_input.clear();
+                                self.confirm_new_password_input.clear();
+                                format!(
+                                    "Password changed. All {} files were re-encrypted \
+                                     with the new key.",
+                                    total
+                                )
+                            }
+                            Err(e) => match self.crypto_manager.take() {
+                                Some(crypto) => {
+                                    let message = password_not_changed(&e.to_string());
+                                    self.start_password_revert(crypto, message);
+                                    return;
+                                }
+                                None => self.password_change_failed(e.to_string()),
+                            },
+                        }
+                    }
+                }
+            }
+            ReencryptKind::TwoPersonUnlock(enable) => {
+                let error = match first_error {
+                    Some(error) if !cancelled || crypto.is_none() => error,
+                    _ => "Cancelled".to_string(),
+                };
+                let not_changed = |error: &str| {
+                    format!(
+                        "Two-person unlock was not turned {}: {}.",
+                        if enable { "on" } else { "off" },
+                        error
+                    )
+                };
+                match crypto {
+                    None => self.two_person_change_failed(not_changed(&error)),
+                    Some(crypto) if cancelled || failures > 0 => {
+                        self.two_person_change_failed(not_changed(&error));
+                        self.start_password_revert(crypto, not_changed(&error));
+                        return;
+                    }
+                    Some(crypto) => {
+                        self.crypto_manager = Some(crypto);
+                        match self.finish_two_person_change() {
+                            Ok(()) => {
+                                log::info!(
+                                    "Two-person unlock turned {}, {} files re-encrypted in {:.1?}",
+                                    if enable { "on" } else { "off" },
+                                    total,
+                                    duration
+                                );
+                                self.two_person_dialog = None;
+                                format!(
+                                    "Two-person unlock is {}. All {} files were re-encrypted \
+                                     with the new key.",
+                                    if enable { "on" } else { "off" },
+                                    total
+                                )
+                            }
+                            Err(e) => {
+                                let message = not_changed(&e.to_string());
+                                match self.crypto_manager.take() {
+                                    Some(crypto) => {
+                                        self.two_person_change_failed(message.clone());
+                                        self.start_password_revert(crypto, message);
+                                        return;
+                                    }
+                                    None => self.two_person_change_failed(message),
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            ReencryptKind::PasswordRevert => {
+                let error = self.password_change_error.clone().unwrap_or_default();
+                self.crypto_manager = crypto;
+                let revert_result = self.reencrypt_user_state();
+                if failures == 0 && revert_result.is_ok() {
+                    if let Some(crypto_manager) = &mut self.crypto_manager {
+                        crypto_manager.finish_key_revert();
+                    }
+                    error
+                } else {
+                    // Both keys stay usable for this session
+                    let revert_error = revert_result
+                        .err()
+                        .map(|e| e.to_string())
+                        .or(first_error)
+                        .unwrap_or_default();
+                    log::error!("Failed to undo the key change: {}", revert_error);
+                    let error = format!(
+                        "{} Undoing the change failed too ({}), some files may only \
+                         open with the new key.",
+                        error, revert_error
+                    );
+                    self.password_change_error = Some(error.clone());
+                    error
+                }
+            }
+        };
+
+        self.show_status(summary.clone());
+        if let Some(ref mut job) = self.reencrypt_job {
+            job.summary = Some(summary);
+        }
+        // Edits made before the job started may still be waiting
+        if self.has_unsaved_changes {
+            self.save_notes();
+        }
+    }
+
+    /// Performs steps 3 and 4 of `handle_password_change` once the files
+    /// were re-encrypted with the new key.
+    ///
+    /// # Returns
+    ///
+    /// * `anyhow::Result<()>` - Ok if the password was changed; on Err the
+    ///   written hash was restored already
+    fn finish_password_change(&mut self) -> anyhow::Result<()> {
+        let Some(user) = self.current_user.clone() else {
+            anyhow::bail!("Not logged in");
+        };
+        let old_password = self.old_password_input.clone();
+        let new_password = self.new_password_input.clone();
+
+        self.reencrypt_user_state()?;
+        let Some(crypto_manager) = &self.crypto_manager else {
+            anyhow::bail!("Not logged in");
+        };
+        let previous_hash = crypto_manager.write_password_hash(&user.id, &new_password)?;
+        let user_result = match &mut self.user_manager {
+            Some(user_manager) => {
+                user_manager.change_password(&user.username, &old_password, &new_password)
+            }
+            None => Err(anyhow::anyhow!("Not logged in")),
+        };
+        if let Err(e) = user_result {
+            if let Err(restore_error) =
+                crypto_manager.restore_password_hash(&user.id, &previous_hash)
+            {
+                log::error!("Failed to restore the password hash: {}", restore_error);
+            }
+            return Err(e);
+        }
+
+        // Both stores agree now; only the recorded KDF parameters remain
+        if let Some(crypto_manager) = &mut self.crypto_manager {
+            if let Err(e) = crypto_manager.complete_kdf_upgrade(&user.id) {
+                log::warn!("Failed to record the key derivation parameters: {}", e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Starts writing the files back with the old key after a password or
+    /// two-person unlock change failed or was cancelled.
+    ///
+    /// # Arguments
+    ///
+    /// * `crypto` - The crypto manager holding the new key
+    /// * `message` - What was not changed and why, shown once the files
+    ///   are written back
+    fn start_password_revert(&mut self, mut crypto: CryptoManager, message: String) {
+        let Some(user) = &self.current_user else {
+            self.reencrypt_job = None;
+            return;
+        };
+        log::error!("{}", message);
+        self.password_change_error = Some(message);
+        crypto.revert_key_change();
+        self.crypto_manager = Some(crypto.clone());
+        self.reencrypt_job = Some(ReencryptJob::revert_password_change(&user.id, crypto));
+    }
+
+    /// Records a password change that failed before anything was changed.
+    ///
+    /// # Arguments
+    ///
+    /// * `error` - Why the change failed
+    ///
+    /// # Returns
+    ///
+    /// * `String` - The summary to show
+    fn password_change_failed(&mut self, error: String) -> String {
+        log::error!("Failed to change password: {}", error);
+        let message = password_not_changed(&error);
+        self.password_change_error = Some(message.clone());
+        message
+    }
+
+    /// Renders the progress of the running re-encryption, or its summary.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The egui context for rendering
+    pub fn render_reencrypt_dialog(&mut self, ctx: &egui::Context) {
+        let Some(ref job) = self.reencrypt_job else {
+            return;
+        };
+        let running = job.summary.is_none();
+
+        if running {
+            // Dim the window and swallow clicks meant for anything below
+            egui::Area::new("reencrypt_blocker".into())
+                .order(egui::Order::Foreground)
+                .fixed_pos(egui::Pos2::ZERO)
+                .show(ctx, |ui| {
+                    let screen = ctx.screen_rect();
+                    ui.painter()
+                        .rect_filled(screen, 0.0, egui::Color32::from_black_alpha(140));
+                    ui.allocate_rect(screen, egui::Sense::click_and_drag());
+                });
+        }
+
+        let mut cancel = false;
+        let mut close = false;
+        let response = egui::Window::new(job.kind.title())
+            .collapsible(false)
+            .resizable(false)
+            .order(egui::Order::Foreground)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .default_width(360.0)
+            .show(ctx, |ui| {
+                if let Some(summary) = &job.summary {
+                    ui.label(summary);
+                    if let Some(duration) = job.duration {
+                        ui.small(format!(
+                            "{} file(s) processed in {:.1}s",
+                            job.processed,
+                            duration.as_secs_f32()
+                        ));
+                    }
+                    if !job.errors.is_empty() {
+                        ui.add_space(5.0);
+                        ui.label(format!("{} error(s):", job.errors.len()));
+                        egui::ScrollArea::vertical()
+                            .max_height(150.0)
+                            .show(ui, |ui| {
+                                for error in job.errors.iter().take(MAX_LISTED_ERRORS) {
+                                    ui.colored_label(egui::Color32::from_rgb(255, 100, 100), error);
+                                }
+                                if job.errors.len() > MAX_LISTED_ERRORS {
+                                    ui.small(format!(
+                                        "… and {} more, see the log",
+                                        job.errors.len() - MAX_LISTED_ERRORS
+                                    ));
+                                }
+                            });
+                    }
+                    ui.add_space(5.0);
+                    if ui.button("Close").clicked() {
+                        close = true;
+                    }
+                    return;
+                }
+
+                match job.total {
+                    Some(total) => ui.label(format!(
+                        "Re-encrypting file {} of {}",
+                        (job.processed + 1).min(total),
+                        total
+                    )),
+                    None if job.kind.changes_key() => ui.label("Deriving the new key…"),
+                    None => ui.label("Listing files…"),
+                };
+                ui.add(
+                    egui::ProgressBar::new(job.fraction())
+                        .show_percentage()
+                        .animate(true),
+                );
+                ui.small(format!(
+                    "{:.0}s elapsed",
+                    job.started.elapsed().as_secs_f32()
+                ));
+                if !job.errors.is_empty() {
+                    ui.colored_label(
+                        egui::Color32::YELLOW,
+                        format!("{} file(s) failed so far", job.errors.len()),
+                    );
+                }
+
+                ui.add_space(5.0);
+                match job.kind {
+                    ReencryptKind::PasswordChange => {
+                        ui.small("Cancelling writes the files back with your current password.");
+                    }
+                    ReencryptKind::TwoPersonUnlock(_) => {
+                        ui.small("Cancelling writes the files back with your current key.");
+                    }
+                    ReencryptKind::PasswordRevert => {
+                        ui.small("Writing the files back with your current key.");
+                    }
+                    ReencryptKind::Algorithm(_) => {}
+                }
+                if job.kind.cancellable() {
+                    if job.is_cancelled() {
+                        ui.label("Stopping after the current file…");
+                    } else if ui.button("Cancel").clicked() {
+                        cancel = true;
+                    }
+                }
+            });
+
+        if let Some(response) = response {
+            if running {
+                ctx.move_to_top(response.response.layer_id);
+            }
+        }
+        if cancel {
+            job.cancel();
+        }
+        if close {
+            self.reencrypt_job = None;
+        }
+    }
+}
+
+/// Describes a password change that was not made.
+///
+/// # Arguments
+///
+/// * `error` - Why the change failed
+fn password_not_changed(error: &str) -> String {
+    format!("The password was not changed: {}.", error)
+}
@@ -0,0 +1,410 @@
+//! # Rename UI Module
+//!
+//! Renaming notes and keeping the wiki links to them working. Notes are
+//! renamed by clicking the title in the note header or with "Rename…" in
+//! the context menu. Renaming a note rewrites the `[[links]]` to its old
+//! title in every other note, see the wiki_links module, and lists what
+//! was rewritten in a report window.
+//!
+//! Links that can't be rewritten are listed as well, so they can be fixed
+//! by hand: links in notes being edited in another window, whose changes
+//! would overwrite the rewrite, and links to a title that another note
+//! still has after the rename, since they may mean that note.
+
+use crate::app::NotesApp;
+use crate::storage::SHARED_VAULT_OWNER;
+use crate::wiki_links;
+use eframe::egui;
+use std::collections::HashSet;
+
+/// State of the rename dialog.
+pub struct RenameDialog {
+    /// ID of the note to rename
+    pub note_id: String,
+    /// New title input
+    pub title: String,
+    /// Why the last attempt was refused
+    pub error: Option<String>,
+}
+
+/// Title being edited in place in the note header.
+pub struct TitleEdit {
+    /// ID of the note to rename
+    pub note_id: String,
+    /// New title input
+    pub title: String,
+    /// Why the last attempt was refused
+    pub error: Option<String>,
+    /// Whether the text field was given the focus
+    focused: bool,
+}
+
+/// What renaming a note did to the links pointing to it.
+pub struct RenameReport {
+    /// Title before the rename
+    pub old_title: String,
+    /// Title after the rename
+    pub new_title: String,
+    /// Notes whose links were rewritten: ID, title and number of links
+    pub rewritten: Vec<(String, String, usize)>,
+    /// Notes whose links were left unchanged: ID, title and the reason
+    pub flagged: Vec<(String, String, &'static str)>,
+}
+
+impl NotesApp {
+    /// Opens the rename dialog for a note.
+    ///
+    /// # Arguments
+    ///
+    /// * `note_id` - ID of the note to rename
+    pub fn open_rename_dialog(&mut self, note_id: &str) {
+        if let Some(note) = self.notes.get(note_id) {
+            self.rename_dialog = Some(RenameDialog {
+                note_id: note_id.to_string(),
+                title: note.title.clone(),
+                error: None,
+            });
+        }
+    }
+
+    /// Lists the private and shared notes other instances are editing.
+    ///
+    /// Saving leaves these notes alone, so changes to them would be lost.
+    ///
+    /// # Returns
+    ///
+    /// * `HashSet<String>` - IDs of the notes with a live lock of another instance
+    fn notes_being_edited_elsewhere(&self) -> HashSet<String> {
+        let Some(user) = &self.current_user else {
+            return HashSet::new();
+        };
+        let mut locked = self
+            .storage_manager
+            .notes_locked_elsewhere(&user.id, &self.instance_id);
+        locked.extend(
+            self.storage_manager
+                .notes_locked_elsewhere(SHARED_VAULT_OWNER, &self.instance_id),
+        );
+        locked
+    }
+
+    /// Gives a note a new title and rewrites the wiki links to it.
+    ///
+    /// The note stops following its first line, see `Note::auto_title`.
+    /// A report of the rewritten and unchanged links is shown if any note
+    /// links to the old title.
+    ///
+    /// # Arguments
+    ///
+    /// * `note_id` - ID of the note to rename
+    /// * `new_title` - The new title, trimmed
+    pub fn rename_note(&mut self, note_id: &str, new_title: &str) {
+        let new_title = new_title.trim().to_string();
+        let Some(note) = self.notes.get_mut(note_id) else {
+            return;
+        };
+        let old_title = std::mem::replace(&mut note.title, new_title.clone());
+        note.auto_title = false;
+        note.update_modified_time();
+        self.search_index.note_changed(note);
+        log::info!("Renamed note {}", note_id);
+
+        // A note that still has the old title may be what the links mean
+        let ambiguous =
+            !old_title.eq_ignore_ascii_case(&new_title) && self.note_title_exists(&old_title);
+        let locked = self.notes_being_edited_elsewhere();
+
+        let mut report = RenameReport {
+            old_title: old_title.clone(),
+            new_title: new_title.clone(),
+            rewritten: Vec::new(),
+            flagged: Vec::new(),
+        };
+        for note in self.notes.values_mut().filter(|note| note.id != note_id) {
+            let Some((content, count)) =
+                wiki_links::rewrite_links(&note.content, &old_title, &new_title)
+            else {
+                continue;
+            };
+            if ambiguous {
+                report.flagged.push((
+                    note.id.clone(),
+                    note.title.clone(),
+                    "another note still has the old title",
+                ));
+            } else if locked.contains(&note.id) {
+                report.flagged.push((
+                    note.id.clone(),
+                    note.title.clone(),
+                    "being edited in another window",
+                ));
+            } else {
+                note.content = content;
+                note.update_modified_time();
+                self.search_index.note_changed(note);
+                report
+                    .rewritten
+                    .push((note.id.clone(), note.title.clone(), count));
+            }
+        }
+        self.save_notes();
+
+        if report.rewritten.is_empty() && report.flagged.is_empty() {
+            self.show_status(format!("Renamed to \"{}\"", new_title));
+        } else {
+            log::info!(
+                "Rewrote links in {} notes, left {} unchanged",
+                report.rewritten.len(),
+                report.flagged.len()
+            );
+            self.rename_report = Some(report);
+        }
+    }
+
+    /// Renders the rename dialog, if open.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The egui context for rendering
+    pub fn render_rename_dialog(&mut self, ctx: &egui::Context) {
+        let Some(dialog) = &mut self.rename_dialog else {
+            return;
+        };
+
+        let mut open = true;
+        let mut confirm = false;
+        let mut cancel = false;
+
+        egui::Window::new("Rename Note")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let response = ui.add(
+                    egui::TextEdit::singleline(&mut dialog.title)
+                        .hint_text("Title")
+                        .desired_width(300.0),
+                );
+                if ui.memory(|memory| memory.focused().is_none()) {
+                    response.request_focus();
+                }
+                if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                    confirm = true;
+                }
+                ui.small("Links like [[Old Title]] in other notes are updated.");
+
+                if let Some(error) = &dialog.error {
+                    ui.colored_label(egui::Color32::RED, error.as_str());
+                }
+
+                ui.add_space(5.0);
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(!dialog.title.trim().is_empty(), egui::Button::new("Rename"))
+                        .clicked()
+                    {
+                        confirm = true;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        cancel = true;
+                    }
+                });
+            });
+
+        if !open || cancel || ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+            self.rename_dialog = None;
+            return;
+        }
+        if !confirm {
+            return;
+        }
+
+        let Some(dialog) = self.rename_dialog.take() else {
+            return;
+        };
+        if let Err(error) = self.try_rename_note(&dialog.note_id, &dialog.title) {
+            self.rename_dialog = Some(RenameDialog {
+                error: Some(error),
+                ..dialog
+            });
+        }
+    }
+
+    /// Renames a note if the new title is valid.
+    ///
+    /// # Arguments
+    ///
+    /// * `note_id` - ID of the note to rename
+    /// * `title` - The new title as entered
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - Ok if renamed or unchanged, or why the
+    ///   title was refused
+    fn try_rename_note(&mut self, note_id: &str, title: &str) -> Result<(), String> {
+        let title = title.trim();
+        let current = self
+            .notes
+            .get(note_id)
+            .map(|note| note.title.clone())
+            .unwrap_or_default();
+        if title.is_empty() {
+            return Err("The title can't be empty".to_string());
+        }
+        if !title.eq_ignore_ascii_case(current.trim()) && self.note_title_exists(title) {
+            return Err(format!("Another note is already called \"{}\"", title));
+        }
+        if title == current {
+            return Ok(());
+        }
+        if self.notes_being_edited_elsewhere().contains(note_id) {
+            return Err("The note is being edited in another window".to_string());
+        }
+        self.rename_note(note_id, title);
+        Ok(())
+    }
+
+    /// Renders the title in the note header. Clicking it turns it into a
+    /// text field; Enter or clicking elsewhere renames the note, Escape
+    /// cancels.
+    ///
+    /// # Arguments
+    ///
+    /// * `ui` - The header row
+    /// * `note_id` - ID of the shown note
+    /// * `display_title` - The title with the note's icon
+    pub fn render_note_title(&mut self, ui: &mut egui::Ui, note_id: &str, display_title: &str) {
+        if self
+            .title_edit
+            .as_ref()
+            .is_some_and(|edit| edit.note_id != note_id)
+        {
+            self.title_edit = None;
+        }
+
+        let Some(edit) = &mut self.title_edit else {
+            // Trashed notes are read-only
+            let editable = self
+                .notes
+                .get(note_id)
+                .is_some_and(|note| !note.is_trashed());
+            let response = ui.add(
+                egui::Label::new(egui::RichText::new(display_title).heading())
+                    .sense(egui::Sense::click()),
+            );
+            if editable {
+                let response = response
+                    .on_hover_cursor(egui::CursorIcon::Text)
+                    .on_hover_text("Click to rename");
+                if response.clicked() {
+                    self.title_edit = Some(TitleEdit {
+                        note_id: note_id.to_string(),
+                        title: self
+                            .notes
+                            .get(note_id)
+                            .map(|note| note.title.clone())
+                            .unwrap_or_default(),
+                        error: None,
+                        focused: false,
+                    });
+                }
+            }
+            return;
+        };
+
+        let response = ui.add(
+            egui::TextEdit::singleline(&mut edit.title)
+                .font(egui::TextStyle::Heading)
+                .hint_text("Title")
+                .desired_width(300.0),
+        );
+        if !edit.focused {
+            response.request_focus();
+            edit.focused = true;
+        }
+        if let Some(error) = &edit.error {
+            ui.colored_label(egui::Color32::RED, error.as_str());
+        }
+
+        if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+            self.title_edit = None;
+        } else if response.lost_focus() {
+            let title = edit.title.clone();
+            match self.try_rename_note(note_id, &title) {
+                Ok(()) => self.title_edit = None,
+                Err(error) => {
+                    if let Some(edit) = &mut self.title_edit {
+                        edit.error = Some(error);
+                        edit.focused = false;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Renders the report of the last rename, if any.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The egui context for rendering
+    pub fn render_rename_report(&mut self, ctx: &egui::Context) {
+        let Some(report) = &self.rename_report else {
+            return;
+        };
+
+        let mut open = true;
+        let mut close = false;
+        let mut open_note = None;
+
+        egui::Window::new("Links Updated")
+            .open(&mut open)
+            .collapsible(false)
+            .default_width(400.0)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "\"{}\" was renamed to \"{}\".",
+                    report.old_title, report.new_title
+                ));
+
+                if !report.rewritten.is_empty() {
+                    ui.add_space(5.0);
+                    ui.strong("Links rewritten");
+                    for (note_id, title, count) in &report.rewritten {
+                        ui.horizontal(|ui| {
+                            if ui.link(title).clicked() {
+                                open_note = Some(note_id.clone());
+                            }
+                            ui.small(if *count == 1 {
+                                "1 link".to_string()
+                            } else {
+                                format!("{} links", count)
+                            });
+                        });
+                    }
+                }
+
+                if !report.flagged.is_empty() {
+                    ui.add_space(5.0);
+                    ui.colored_label(ui.visuals().warn_fg_color, "Links to fix by hand");
+                    for (note_id, title, reason) in &report.flagged {
+                        ui.horizontal(|ui| {
+                            if ui.link(title).clicked() {
+                                open_note = Some(note_id.clone());
+                            }
+                            ui.small(*reason);
+                        });
+                    }
+                }
+
+                ui.add_space(5.0);
+                close = ui.button("Close").clicked();
+            });
+
+        if let Some(note_id) = open_note {
+            self.selected_note_id = Some(note_id);
+        }
+        if !open || close {
+            self.rename_report = None;
+        }
+    }
+}
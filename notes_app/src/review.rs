@@ -0,0 +1,109 @@
+//! # Review Module
+//!
+//! Scheduled re-reading of notes, for study notes and anything else worth
+//! revisiting. A note added to the review is due the next day; each time
+//! it is marked as reviewed, the wait until it is due again roughly
+//! doubles, up to [`REVIEW_INTERVALS_DAYS`]'s last step. Marking it for
+//! another look starts over with the shortest interval.
+//!
+//! Due dates are calendar days in Swiss time, like the rest of the app, so
+//! a note due today stays due until midnight and overdue notes stay due
+//! until they are reviewed.
+
+use chrono::{Days, NaiveDate, Utc};
+use chrono_tz::Europe::Zurich;
+use serde::{Deserialize, Serialize};
+
+/// Days until a note is due again, by the number of times in a row it was
+/// reviewed.
+pub const REVIEW_INTERVALS_DAYS: [u64; 8] = [1, 2, 4, 8, 16, 32, 64, 128];
+
+/// Review schedule of a note.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Review {
+    /// Index into [`REVIEW_INTERVALS_DAYS`] of the current interval
+    pub step: usize,
+    /// Day the note is due for review
+    pub due: NaiveDate,
+    /// Day the note was last reviewed, None if never
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_reviewed: Option<NaiveDate>,
+}
+
+impl Review {
+    /// Starts reviewing a note, due after the shortest interval.
+    ///
+    /// # Returns
+    ///
+    /// * `Self` - A new schedule
+    pub fn start() -> Self {
+        Self {
+            step: 0,
+            due: due_after(today(), 0),
+            last_reviewed: None,
+        }
+    }
+
+    /// Checks whether the note should be re-read today.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - True if due today or overdue
+    pub fn is_due(&self) -> bool {
+        self.due <= today()
+    }
+
+    /// Records a review and schedules the next one.
+    ///
+    /// # Arguments
+    ///
+    /// * `again` - True if the note needs another look soon, which starts
+    ///   over with the shortest interval; false to wait longer than last time
+    pub fn mark_reviewed(&mut self, again: bool) {
+        let today = today();
+        self.step = if again {
+            0
+        } else {
+            (self.step + 1).min(REVIEW_INTERVALS_DAYS.len() - 1)
+        };
+        self.due = due_after(today, self.step);
+        self.last_reviewed = Some(today);
+    }
+
+    /// Returns the interval the note would wait after being reviewed.
+    ///
+    /// # Returns
+    ///
+    /// * `u64` - Days until it is due again
+    pub fn next_interval_days(&self) -> u64 {
+        REVIEW_INTERVALS_DAYS[(self.step + 1).min(REVIEW_INTERVALS_DAYS.len() - 1)]
+    }
+
+    /// Describes when the note is due, for the note header.
+    ///
+    /// # Returns
+    ///
+    /// * `String` - E.g. "Due today", "Overdue since 03.05.2026" or "Next
+    ///   review on 12.05.2026"
+    pub fn due_label(&self) -> String {
+        let today = today();
+        if self.due == today {
+            "Due today".to_string()
+        } else if self.due < today {
+            format!("Overdue since {}", self.due.format("%d.%m.%Y"))
+        } else {
+            format!("Next review on {}", self.due.format("%d.%m.%Y"))
+        }
+    }
+}
+
+/// Returns the current day in Swiss time.
+fn today() -> NaiveDate {
+    Utc::now().with_timezone(&Zurich).date_naive()
+}
+
+/// Returns the day a note reviewed on `day` is due at the given step.
+fn due_after(day: NaiveDate, step: usize) -> NaiveDate {
+    day.checked_add_days(Days::new(REVIEW_INTERVALS_DAYS[step]))
+        .unwrap_or(day)
+}
@@ -0,0 +1,151 @@
+//! # Review UI Module
+//!
+//! Adding notes to the review from the context menu, the "Review" sidebar
+//! section listing the notes due today and the banner above a reviewed
+//! note to mark it as read. The schedule itself lives in the review module.
+//!
+//! Review changes don't count as edits: they are saved right away, like
+//! pinning, without touching the note's modification time.
+
+use crate::app::NotesApp;
+use crate::review::Review;
+use eframe::egui;
+
+impl NotesApp {
+    /// Adds a note to the review or takes it out again.
+    ///
+    /// # Arguments
+    ///
+    /// * `note_id` - The ID of the note
+    pub fn toggle_review(&mut self, note_id: &str) {
+        let Some(note) = self.notes.get_mut(note_id) else {
+            return;
+        };
+        note.review = match note.review {
+            Some(_) => None,
+            None => Some(Review::start()),
+        };
+        let message = match &note.review {
+            Some(review) => format!(
+                "\"{}\" added to the review, {}",
+                note.title,
+                review.due_label().to_lowercase()
+            ),
+            None => format!("\"{}\" removed from the review", note.title),
+        };
+        self.save_notes();
+        self.show_status(message);
+    }
+
+    /// Records that a note was re-read and schedules its next review.
+    ///
+    /// # Arguments
+    ///
+    /// * `note_id` - The ID of the note
+    /// * `again` - True to see the note again soon, false to wait longer
+    pub fn mark_reviewed(&mut self, note_id: &str, again: bool) {
+        let Some(review) = self
+            .notes
+            .get_mut(note_id)
+            .and_then(|note| note.review.as_mut())
+        else {
+            return;
+        };
+        review.mark_reviewed(again);
+        let message = format!("Reviewed, {}", review.due_label().to_lowercase());
+        self.save_notes();
+        self.show_status(message);
+    }
+
+    /// Returns the notes due for review among the given ones.
+    ///
+    /// # Arguments
+    ///
+    /// * `note_ids` - IDs of the listed notes
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<String>` - IDs of the due notes, longest overdue first
+    pub fn due_for_review(&self, note_ids: &[String]) -> Vec<String> {
+        let mut due: Vec<(&String, &Review)> = note_ids
+            .iter()
+            .filter_map(|id| {
+                let review = self.notes.get(id)?.review.as_ref()?;
+                review.is_due().then_some((id, review))
+            })
+            .collect();
+        due.sort_by_key(|(_, review)| review.due);
+        due.into_iter().map(|(id, _)| id.clone()).collect()
+    }
+
+    /// Renders the review line above a note that is being reviewed.
+    ///
+    /// A due note gets buttons to mark it as reviewed; other notes show
+    /// when they are due next.
+    ///
+    /// # Arguments
+    ///
+    /// * `ui` - The main content UI
+    /// * `note_id` - The ID of the displayed note
+    /// * `read_only` - Whether the note can't be changed, e.g. in the trash
+    pub fn render_review_banner(&mut self, ui: &mut egui::Ui, note_id: &str, read_only: bool) {
+        let Some(review) = self
+            .notes
+            .get(note_id)
+            .and_then(|note| note.review.as_ref())
+        else {
+            return;
+        };
+        let is_due = review.is_due();
+
+        let mut reviewed = None;
+        let mut stop = false;
+        ui.horizontal(|ui| {
+            let label = format!("🔁 {}", review.due_label());
+            if is_due {
+                ui.colored_label(egui::Color32::from_rgb(100, 180, 255), label);
+            } else {
+                ui.small(label);
+            }
+
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                ui.add_enabled_ui(!read_only, |ui| {
+                    if ui
+                        .small_button("Stop Reviewing")
+                        .on_hover_text("Take the note out of the review")
+                        .clicked()
+                    {
+                        stop = true;
+                    }
+                    if is_due {
+                        if ui
+                            .button("↺ Again")
+                            .on_hover_text("Show the note again tomorrow")
+                            .clicked()
+                        {
+                            reviewed = Some(true);
+                        }
+                        if ui
+                            .button("✓ Reviewed")
+                            .on_hover_text(format!(
+                                "Show the note again in {} days",
+                                review.next_interval_days()
+                            ))
+                            .clicked()
+                        {
+                            reviewed = Some(false);
+                        }
+                    }
+                });
+            });
+        });
+        ui.separator();
+
+        if let Some(again) = reviewed {
+            self.mark_reviewed(note_id, again);
+        }
+        if stop {
+            self.toggle_review(note_id);
+        }
+    }
+}
@@ -0,0 +1,62 @@
+//! # Sample Notes Module
+//!
+//! Creates the notes a new account starts with: a cheat sheet of the
+//! keyboard shortcuts, a short Markdown guide and an overview of how the
+//! notes are protected. The texts are templates in `assets/templates`,
+//! embedded at compile time; placeholders such as `{{shortcuts}}` are
+//! filled in when the account is registered.
+//!
+//! The sample notes are ordinary notes in the "Getting Started" notebook
+//! and can be edited or deleted like any other.
+
+use crate::keymap::{Keymap, ShortcutAction};
+use crate::note::Note;
+
+/// Notebook the sample notes are put in.
+const SAMPLE_NOTEBOOK: &str = "Getting Started";
+
+/// Title and template of each sample note, in the order they are created.
+const TEMPLATES: [(&str, &str); 3] = [
+    (
+        "How Your Notes Are Protected",
+        include_str!("../assets/templates/security.md"),
+    ),
+    (
+        "Markdown Guide",
+        include_str!("../assets/templates/markdown_guide.md"),
+    ),
+    (
+        "Keyboard Shortcuts",
+        include_str!("../assets/templates/shortcuts.md"),
+    ),
+];
+
+/// Creates the sample notes for a new account.
+///
+/// # Arguments
+///
+/// * `username` - Name of the new account
+/// * `keymap` - The account's shortcuts, listed in the cheat sheet
+///
+/// # Returns
+///
+/// * `Vec<Note>` - The sample notes
+pub fn sample_notes(username: &str, keymap: &Keymap) -> Vec<Note> {
+    let shortcuts = ShortcutAction::ALL
+        .iter()
+        .map(|&action| format!("- {}: {}", action.label(), keymap.shortcut(action)))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    TEMPLATES
+        .iter()
+        .map(|(title, template)| {
+            let mut note = Note::new(title.to_string());
+            note.content = template
+                .replace("{{username}}", username)
+                .replace("{{shortcuts}}", &shortcuts);
+            note.notebook = Some(SAMPLE_NOTEBOOK.to_string());
+            note
+        })
+        .collect()
+}
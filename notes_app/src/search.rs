@@ -0,0 +1,400 @@
+//! # Search Module
+//!
+//! Search queries for the sidebar and smart filters. A query combines free
+//! text, inline `#tags` and a modification date range; all parts that are
+//! set must match. Smart filters are named queries stored with the user's
+//! settings, so their note lists update as notes change.
+//!
+//! ## Field Operators
+//!
+//! Words of the form `key:value` in the search text match custom note
+//! fields instead of the note text: `client:acme` matches notes whose
+//! `client` field contains "acme". Values with spaces are quoted, as in
+//! `project:"new website"`. Field names and values are case-insensitive.
+//!
+//! ## Snippets
+//!
+//! The search results view shows, for every matching note, excerpts of the
+//! content around the matches of the free text, with the matches marked.
+
+use crate::note::Note;
+use crate::search_index;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+/// Date format used for the date range inputs.
+pub const DATE_INPUT_FORMAT: &str = "%d.%m.%Y";
+
+/// Characters of context shown on each side of a match in a snippet.
+const SNIPPET_CONTEXT_CHARS: usize = 40;
+
+/// An excerpt of a note's content around a search match.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snippet {
+    /// The excerpt on a single line, with "…" where it was cut off
+    pub text: String,
+    /// Byte ranges of the matched text within `text`
+    pub highlights: Vec<Range<usize>>,
+    /// Byte range of the match in the note content, empty if the snippet
+    /// only shows the beginning of the note
+    pub content_range: Range<usize>,
+}
+
+/// A search over title, content, tags and modification date.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchQuery {
+    /// Text that must appear in the title or content (case-insensitive),
+    /// possibly containing `key:value` field operators
+    pub text: String,
+    /// Tags that must all be present, lowercase without `#`
+    pub tags: Vec<String>,
+    /// First day (inclusive, Swiss time) the note may have been modified on
+    pub modified_from: Option<NaiveDate>,
+    /// Last day (inclusive, Swiss time) the note may have been modified on
+    pub modified_to: Option<NaiveDate>,
+}
+
+impl SearchQuery {
+    /// Returns whether no criteria are set, i.e. every note matches.
+    pub fn is_empty(&self) -> bool {
+        self.text.trim().is_empty()
+            && self.tags.is_empty()
+            && self.modified_from.is_none()
+            && self.modified_to.is_none()
+    }
+
+    /// Describes the criteria for the sidebar's filter summary.
+    ///
+    /// # Returns
+    ///
+    /// * `String` - E.g. `"budget" #work, modified 01.01.2026–31.01.2026`,
+    ///   empty if no criteria are set
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        let text = self.text.trim();
+        if !text.is_empty() {
+            parts.push(format!("\"{}\"", text));
+        }
+        if !self.tags.is_empty() {
+            parts.push(
+                self.tags
+                    .iter()
+                    .map(|tag| format!("#{}", tag))
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            );
+        }
+        let date = |date: NaiveDate| date.format(DATE_INPUT_FORMAT).to_string();
+        match (self.modified_from, self.modified_to) {
+            (Some(from), Some(to)) => parts.push(format!("modified {}–{}", date(from), date(to))),
+            (Some(from), None) => parts.push(format!("modified since {}", date(from))),
+            (None, Some(to)) => parts.push(format!("modified until {}", date(to))),
+            (None, None) => {}
+        }
+        parts.join(", ")
+    }
+
+    /// Returns the words of the free text, as stored in the search index.
+    ///
+    /// Field operators are left out, since fields are not indexed.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<String>` - Lowercase words that every matching note contains
+    pub fn text_terms(&self) -> Vec<String> {
+        let (text, _) = parse_text(&self.text);
+        search_index::words(&text).collect()
+    }
+
+    /// Returns the free text notes must contain, without field operators.
+    ///
+    /// # Returns
+    ///
+    /// * `String` - Lowercase text, empty if the query has none
+    pub fn free_text(&self) -> String {
+        parse_text(&self.text).0
+    }
+
+    /// Extracts excerpts around the matches of the free text in a note.
+    ///
+    /// Matches close to each other share one snippet. If the free text is
+    /// empty or only matches the title, the beginning of the content is
+    /// returned instead, so every note gets at least one snippet.
+    ///
+    /// # Arguments
+    ///
+    /// * `note` - A note matching the query
+    /// * `limit` - Maximum number of snippets
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<Snippet>` - Between one and `limit` snippets
+    pub fn snippets(&self, note: &Note, limit: usize) -> Vec<Snippet> {
+        let text = self.free_text();
+        let content = &note.content;
+        let mut snippets: Vec<Snippet> = Vec::new();
+        let mut covered_until = 0;
+
+        for range in find_matches(content, &text) {
+            if snippets.len() >= limit {
+                break;
+            }
+            if range.start < covered_until {
+                continue;
+            }
+
+            let start = content[..range.start]
+                .char_indices()
+                .rev()
+                .nth(SNIPPET_CONTEXT_CHARS - 1)
+                .map_or(0, |(index, _)| index);
+            let end = content[range.end..]
+                .char_indices()
+                .nth(SNIPPET_CONTEXT_CHARS)
+                .map_or(content.len(), |(index, _)| range.end + index);
+            covered_until = end;
+
+            snippets.push(snippet(content, start..end, &text, range));
+        }
+
+        if snippets.is_empty() {
+            let end = content
+                .char_indices()
+                .nth(2 * SNIPPET_CONTEXT_CHARS)
+                .map_or(content.len(), |(index, _)| index);
+            snippets.push(snippet(content, 0..end, "", 0..0));
+        }
+
+        snippets
+    }
+
+    /// Checks whether a note satisfies every criterion of the query.
+    ///
+    /// # Arguments
+    ///
+    /// * `note` - The note to check
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - True if the note matches
+    pub fn matches(&self, note: &Note) -> bool {
+        let (text, field_filters) = parse_text(&self.text);
+        if !text.is_empty()
+            && !note.title.to_lowercase().contains(&text)
+            && !note.content.to_lowercase().contains(&text)
+            && !note
+                .secrets
+                .iter()
+                .any(|entry| entry.searchable_text().to_lowercase().contains(&text))
+        {
+            return false;
+        }
+
+        for (key, value) in &field_filters {
+            if !note
+                .field(key)
+                .is_some_and(|field| field.to_lowercase().contains(value))
+            {
+                return false;
+            }
+        }
+
+        if !self.tags.is_empty() {
+            let note_tags = note.tags();
+            if !self.tags.iter().all(|tag| note_tags.contains(tag)) {
+                return false;
+            }
+        }
+
+        let modified = note.modified_at_local().date_naive();
+        if self.modified_from.is_some_and(|from| modified < from) {
+            return false;
+        }
+        if self.modified_to.is_some_and(|to| modified > to) {
+            return false;
+        }
+
+        true
+    }
+}
+
+/// A named search shown in the sidebar.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SmartFilter {
+    /// Display name
+    pub name: String,
+    /// The saved query
+    pub query: SearchQuery,
+}
+
+/// Finds all occurrences of a lowercase needle in a text, ignoring case.
+///
+/// # Arguments
+///
+/// * `text` - The text to search
+/// * `needle` - Lowercase text to find
+///
+/// # Returns
+///
+/// * `Vec<Range<usize>>` - Byte ranges of the non-overlapping matches in `text`
+pub fn find_matches(text: &str, needle: &str) -> Vec<Range<usize>> {
+    if needle.is_empty() {
+        return Vec::new();
+    }
+
+    // Lowercasing can change byte lengths, so remember for every byte of
+    // the lowercase text which character of the original it came from
+    let mut lower = String::with_capacity(text.len());
+    let mut origin = Vec::with_capacity(text.len());
+    for (index, c) in text.char_indices() {
+        for lower_c in c.to_lowercase() {
+            lower.push(lower_c);
+            origin.extend(std::iter::repeat_n(index, lower_c.len_utf8()));
+        }
+    }
+
+    lower
+        .match_indices(needle)
+        .map(|(start, matched)| {
+            let last = origin[start + matched.len() - 1];
+            let end = last + text[last..].chars().next().map_or(0, char::len_utf8);
+            origin[start]..end
+        })
+        .collect()
+}
+
+/// Builds a snippet from a part of the content.
+///
+/// # Arguments
+///
+/// * `content` - The note content
+/// * `range` - Byte range of the excerpt
+/// * `text` - Lowercase free text to highlight
+/// * `content_range` - Byte range of the match the snippet is for
+///
+/// # Returns
+///
+/// * `Snippet` - The excerpt on a single line with its highlights
+fn snippet(content: &str, range: Range<usize>, text: &str, content_range: Range<usize>) -> Snippet {
+    // Line breaks become spaces, which keeps the byte offsets intact
+    let mut excerpt: String = content[range.clone()]
+        .chars()
+        .map(|c| {
+            if c == '\n' || c == '\r' || c == '\t' {
+                ' '
+            } else {
+                c
+            }
+        })
+        .collect();
+
+    let mut highlights = find_matches(&excerpt, text);
+    if range.start > 0 {
+        excerpt.insert(0, '…');
+        let shift = '…'.len_utf8();
+        for highlight in &mut highlights {
+            *highlight = highlight.start + shift..highlight.end + shift;
+        }
+    }
+    if range.end < content.len() {
+        excerpt.push('…');
+    }
+
+    Snippet {
+        text: excerpt,
+        highlights,
+        content_range,
+    }
+}
+
+/// Splits search text into free text and `key:value` field operators.
+///
+/// # Arguments
+///
+/// * `text` - The search text
+///
+/// # Returns
+///
+/// * `(String, Vec<(String, String)>)` - Lowercase free text with operators
+///   removed, and the lowercase field operators
+fn parse_text(text: &str) -> (String, Vec<(String, String)>) {
+    let mut words = Vec::new();
+    let mut fields = Vec::new();
+
+    for token in tokenize(text) {
+        let operator = token.split_once(':').filter(|(key, value)| {
+            !key.is_empty()
+                && !value.is_empty()
+                && key
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+        });
+        match operator {
+            Some((key, value)) => {
+                fields.push((key.to_lowercase(), value.trim_matches('"').to_lowercase()))
+            }
+            None => words.push(token.trim_matches('"').to_lowercase()),
+        }
+    }
+
+    (words.join(" "), fields)
+}
+
+/// Splits text at whitespace, keeping double-quoted parts together.
+fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+
+    for c in text.chars() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                current.push(c);
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+
+    tokens
+}
+
+/// Parses a tag input like `#work, todo` into normalized tags.
+///
+/// # Arguments
+///
+/// * `input` - Tags separated by spaces or commas, with or without `#`
+///
+/// # Returns
+///
+/// * `Vec<String>` - Lowercase tags without `#`
+pub fn parse_tags(input: &str) -> Vec<String> {
+    input
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .map(|tag| tag.trim_start_matches('#').to_lowercase())
+        .filter(|tag| !tag.is_empty())
+        .collect()
+}
+
+/// Parses an optional date input in `dd.mm.yyyy` format.
+///
+/// # Arguments
+///
+/// * `input` - The text of the date field
+///
+/// # Returns
+///
+/// * `Option<NaiveDate>` - The date, or None if the field is empty or invalid
+pub fn parse_date(input: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(input.trim(), DATE_INPUT_FORMAT).ok()
+}
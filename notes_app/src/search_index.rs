@@ -0,0 +1,494 @@
+//! # Search Index Module
+//!
+//! In-memory full-text index over the titles and contents of the loaded
+//! notes, used to narrow down the notes the sidebar search has to scan.
+//!
+//! The index is maintained by a background worker fed with note-change
+//! events from the app. The worker waits until no changes arrived for a
+//! short while before indexing, so a burst of keystrokes is indexed once
+//! after typing pauses, and it yields between notes so indexing a large
+//! vault doesn't compete with the UI thread.
+//!
+//! The index only ever excludes notes it has indexed in their current
+//! version. Notes changed since (or not indexed yet) are always handed to
+//! the exact search, so a lagging index makes searching slower but never
+//! hides results.
+//!
+//! So large vaults don't have to be indexed from scratch at every login,
+//! the words of every note are saved encrypted as `search_index.enc` next
+//! to the notes whenever the notes are saved, and restored by the note
+//! loader before the first note arrives. Notes whose modification time
+//! matches the restored entry are not split again. The file is only a
+//! cache: if it is missing, outdated or can't be decrypted, the notes are
+//! indexed as they load, like before.
+
+use crate::crypto::CryptoManager;
+use crate::note::Note;
+use crate::search::SearchQuery;
+use crate::storage::StorageManager;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::{mpsc, Arc, RwLock};
+use std::thread;
+use std::time::Duration;
+
+/// How long the worker waits for further changes before indexing.
+const INDEX_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Version of the saved index, increased whenever `words` splits text
+/// differently so indexes saved by older versions are discarded.
+const INDEX_FORMAT_VERSION: u32 = 1;
+
+/// The indexed words of every note, as saved to `search_index.enc`.
+#[derive(Serialize, Deserialize)]
+pub struct IndexSnapshot {
+    /// Format of the saved words, see `INDEX_FORMAT_VERSION`
+    version: u32,
+    /// Note ID to the indexed version and its words
+    notes: HashMap<String, (DateTime<Utc>, HashSet<String>)>,
+}
+
+impl IndexSnapshot {
+    /// Checks whether the index was saved in the current format.
+    pub fn is_current_format(&self) -> bool {
+        self.version == INDEX_FORMAT_VERSION
+    }
+
+    /// Counts the notes the saved index doesn't cover in their current
+    /// version, and the saved notes that no longer exist.
+    ///
+    /// # Arguments
+    ///
+    /// * `notes` - All loaded notes
+    ///
+    /// # Returns
+    ///
+    /// * `usize` - Number of outdated, missing and stale entries
+    pub fn outdated_entries(&self, notes: &HashMap<String, Note>) -> usize {
+        let outdated = notes
+            .values()
+            .filter(|note| {
+                self.notes
+                    .get(&note.id)
+                    .is_none_or(|(indexed_at, _)| *indexed_at != note.modified_at)
+            })
+            .count();
+        let stale = self
+            .notes
+            .keys()
+            .filter(|id| !notes.contains_key(*id))
+            .count();
+        outdated + stale
+    }
+}
+
+/// A change to the indexed notes, sent to the worker.
+enum IndexEvent {
+    /// A note was added or its text changed
+    Update {
+        /// ID of the note
+        id: String,
+        /// Modification time of the indexed version
+        modified_at: DateTime<Utc>,
+        /// Title and content of the note
+        text: String,
+    },
+    /// A note was deleted
+    Remove(String),
+    /// All notes were unloaded, e.g. on logout
+    Clear,
+    /// A saved index was loaded at login
+    Restore(IndexSnapshot),
+    /// Loading finished; notes not in the set no longer exist
+    Retain(HashSet<String>),
+    /// The notes were saved, so the index should be saved too
+    Save {
+        /// Owner of the index file
+        user_id: String,
+        /// Key to encrypt the index with
+        crypto: Box<CryptoManager>,
+        /// Write even if nothing changed since the last save, e.g. after
+        /// the key changed
+        force: bool,
+    },
+}
+
+/// Inverted index from lowercase words to the notes containing them.
+#[derive(Default)]
+struct IndexData {
+    /// Word to IDs of the notes containing it
+    postings: HashMap<String, HashSet<String>>,
+    /// Note ID to the indexed version and its words
+    notes: HashMap<String, (DateTime<Utc>, HashSet<String>)>,
+}
+
+impl IndexData {
+    /// Checks whether a note is indexed in the given version.
+    fn is_current(&self, id: &str, modified_at: DateTime<Utc>) -> bool {
+        self.notes
+            .get(id)
+            .is_some_and(|(indexed_at, _)| *indexed_at == modified_at)
+    }
+
+    /// Replaces the indexed words of a note.
+    fn update(&mut self, id: String, modified_at: DateTime<Utc>, words: HashSet<String>) {
+        self.remove(&id);
+        for word in &words {
+            self.postings
+                .entry(word.clone())
+                .or_default()
+                .insert(id.clone());
+        }
+        self.notes.insert(id, (modified_at, words));
+    }
+
+    /// Removes a note from the index.
+    fn remove(&mut self, id: &str) {
+        let Some((_, words)) = self.notes.remove(id) else {
+            return;
+        };
+        for word in words {
+            if let Some(ids) = self.postings.get_mut(&word) {
+                ids.remove(id);
+                if ids.is_empty() {
+                    self.postings.remove(&word);
+                }
+            }
+        }
+    }
+}
+
+/// Handle to the search index and its background worker.
+///
+/// Dropping the handle stops the worker.
+pub struct SearchIndex {
+    /// Channel to the worker
+    sender: mpsc::Sender<IndexEvent>,
+    /// The index, written by the worker and read by the UI
+    data: Arc<RwLock<IndexData>>,
+}
+
+impl SearchIndex {
+    /// Creates an empty index and starts its worker thread.
+    ///
+    /// # Returns
+    ///
+    /// * `Self` - A new SearchIndex
+    pub fn spawn() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let data = Arc::new(RwLock::new(IndexData::default()));
+
+        let worker_data = Arc::clone(&data);
+        thread::Builder::new()
+            .name("search-index".to_string())
+            .spawn(move || run_worker(receiver, worker_data))
+            .expect("failed to start search index worker");
+
+        Self { sender, data }
+    }
+
+    /// Queues a new or changed note for indexing.
+    ///
+    /// # Arguments
+    ///
+    /// * `note` - The note in its current version
+    pub fn note_changed(&self, note: &Note) {
+        let _ = self.sender.send(IndexEvent::Update {
+            id: note.id.clone(),
+            modified_at: note.modified_at,
+            text: note.searchable_text(),
+        });
+    }
+
+    /// Queues a deleted note for removal from the index.
+    ///
+    /// # Arguments
+    ///
+    /// * `note_id` - The ID of the deleted note
+    pub fn note_removed(&self, note_id: &str) {
+        let _ = self.sender.send(IndexEvent::Remove(note_id.to_string()));
+    }
+
+    /// Removes all notes from the index.
+    pub fn clear(&self) {
+        let _ = self.sender.send(IndexEvent::Clear);
+    }
+
+    /// Adds the notes of a saved index that aren't indexed yet.
+    ///
+    /// # Arguments
+    ///
+    /// * `snapshot` - The index loaded by `StorageManager::load_search_index`
+    pub fn restore(&self, snapshot: IndexSnapshot) {
+        if !snapshot.is_current_format() {
+            log::info!("Discarding search index saved in an older format");
+            return;
+        }
+        let _ = self.sender.send(IndexEvent::Restore(snapshot));
+    }
+
+    /// Drops restored notes that no longer exist, once all notes are loaded.
+    ///
+    /// # Arguments
+    ///
+    /// * `note_ids` - IDs of all loaded notes
+    pub fn retain(&self, note_ids: HashSet<String>) {
+        let _ = self.sender.send(IndexEvent::Retain(note_ids));
+    }
+
+    /// Queues saving the index encrypted for a user.
+    ///
+    /// The worker writes the file once the queued changes are indexed, and
+    /// only if the index changed since it was last written.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - Owner of the index
+    /// * `crypto` - CryptoManager of the user
+    pub fn save(&self, user_id: &str, crypto: &CryptoManager) {
+        self.send_save(user_id, crypto, false);
+    }
+
+    /// Queues writing the index again, even if it didn't change, e.g. with
+    /// a new key after a password change.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - Owner of the index
+    /// * `crypto` - CryptoManager of the user
+    pub fn rewrite(&self, user_id: &str, crypto: &CryptoManager) {
+        self.send_save(user_id, crypto, true);
+    }
+
+    /// Indexes all notes from scratch and writes the index, e.g. when the
+    /// saved index is damaged.
+    ///
+    /// # Arguments
+    ///
+    /// * `notes` - All loaded notes
+    /// * `user_id` - Owner of the index
+    /// * `crypto` - CryptoManager of the user
+    pub fn rebuild<'a>(
+        &self,
+        notes: impl IntoIterator<Item = &'a Note>,
+        user_id: &str,
+        crypto: &CryptoManager,
+    ) {
+        self.clear();
+        for note in notes {
+            self.note_changed(note);
+        }
+        self.rewrite(user_id, crypto);
+    }
+
+    /// Sends a `Save` event to the worker.
+    fn send_save(&self, user_id: &str, crypto: &CryptoManager, force: bool) {
+        let _ = self.sender.send(IndexEvent::Save {
+            user_id: user_id.to_string(),
+            crypto: Box::new(crypto.clone()),
+            force,
+        });
+    }
+
+    /// Drops the notes that the index proves cannot match a query's text.
+    ///
+    /// Only notes indexed in their current version can be dropped. The
+    /// remaining notes still have to be checked with `SearchQuery::matches`.
+    /// If the worker is writing to the index right now, nothing is dropped
+    /// instead of blocking the UI.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - The search query
+    /// * `notes` - Notes to filter
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<&Note>` - Notes that may match the query
+    pub fn candidates<'a>(
+        &self,
+        query: &SearchQuery,
+        notes: impl IntoIterator<Item = &'a Note>,
+    ) -> Vec<&'a Note> {
+        let terms = query.text_terms();
+        let data = match self.data.try_read() {
+            Ok(data) if !terms.is_empty() => data,
+            _ => return notes.into_iter().collect(),
+        };
+
+        // Notes containing every term within one of their words
+        let mut matching: Option<HashSet<&str>> = None;
+        for term in &terms {
+            let ids: HashSet<&str> = data
+                .postings
+                .iter()
+                .filter(|(word, _)| word.contains(term.as_str()))
+                .flat_map(|(_, ids)| ids.iter().map(String::as_str))
+                .collect();
+            matching = Some(match matching {
+                Some(previous) => previous.intersection(&ids).copied().collect(),
+                None => ids,
+            });
+        }
+        let matching = matching.unwrap_or_default();
+
+        notes
+            .into_iter()
+            .filter(|note| {
+                !data.is_current(&note.id, note.modified_at) || matching.contains(note.id.as_str())
+            })
+            .collect()
+    }
+}
+
+/// Splits text into the words stored in the index.
+///
+/// Callers lowercase the text first, so words match case-insensitively.
+///
+/// # Arguments
+///
+/// * `text` - Lowercase text to split
+///
+/// # Returns
+///
+/// * `impl Iterator<Item = String>` - Runs of alphanumeric characters
+pub fn words(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_string)
+}
+
+/// Processes index events until the handle is dropped.
+fn run_worker(receiver: mpsc::Receiver<IndexEvent>, data: Arc<RwLock<IndexData>>) {
+    // Latest pending change per note, None for a removal
+    let mut pending: HashMap<String, Option<(DateTime<Utc>, String)>> = HashMap::new();
+    // Whether the index changed since it was last written
+    let mut dirty = false;
+
+    while let Ok(event) = receiver.recv() {
+        let mut next = Some(event);
+
+        // Collect changes until the app has been quiet for a moment
+        while let Some(event) = next.take() {
+            // Other events apply to the index including all changes sent
+            // before them
+            let is_change = matches!(event, IndexEvent::Update { .. } | IndexEvent::Remove(_));
+            if !is_change && !apply_pending(&mut pending, &data, &mut dirty) {
+                return;
+            }
+            match event {
+                IndexEvent::Update {
+                    id,
+                    modified_at,
+                    text,
+                } => {
+                    pending.insert(id, Some((modified_at, text)));
+                }
+                IndexEvent::Remove(id) => {
+                    pending.insert(id, None);
+                }
+                IndexEvent::Clear => {
+                    let Ok(mut index) = data.write() else {
+                        return;
+                    };
+                    *index = IndexData::default();
+                    dirty = false;
+                }
+                IndexEvent::Restore(snapshot) => {
+                    let Ok(mut index) = data.write() else {
+                        return;
+                    };
+                    for (id, (modified_at, words)) in snapshot.notes {
+                        if !index.notes.contains_key(&id) {
+                            index.update(id, modified_at, words);
+                        }
+                    }
+                }
+                IndexEvent::Retain(note_ids) => {
+                    let Ok(mut index) = data.write() else {
+                        return;
+                    };
+                    let removed: Vec<String> = index
+                        .notes
+                        .keys()
+                        .filter(|id| !note_ids.contains(*id))
+                        .cloned()
+                        .collect();
+                    dirty |= !removed.is_empty();
+                    for id in removed {
+                        index.remove(&id);
+                    }
+                }
+                IndexEvent::Save {
+                    user_id,
+                    crypto,
+                    force,
+                } => {
+                    if dirty || force {
+                        let Ok(index) = data.read() else {
+                            return;
+                        };
+                        let snapshot = IndexSnapshot {
+                            version: INDEX_FORMAT_VERSION,
+                            notes: index.notes.clone(),
+                        };
+                        drop(index);
+                        match StorageManager::new().save_search_index(&user_id, &snapshot, &crypto)
+                        {
+                            Ok(()) => dirty = false,
+                            Err(e) => log::error!("Failed to save the search index: {}", e),
+                        }
+                    }
+                }
+            }
+            match receiver.recv_timeout(INDEX_DEBOUNCE) {
+                Ok(event) => next = Some(event),
+                Err(mpsc::RecvTimeoutError::Timeout) => {}
+                Err(mpsc::RecvTimeoutError::Disconnected) => return,
+            }
+        }
+
+        if !apply_pending(&mut pending, &data, &mut dirty) {
+            return;
+        }
+    }
+}
+
+/// Indexes the pending changes, yielding between notes.
+///
+/// Notes already indexed in the same version, e.g. restored from the saved
+/// index, are not split again.
+///
+/// # Returns
+///
+/// * `bool` - False if the index lock was poisoned and the worker should stop
+fn apply_pending(
+    pending: &mut HashMap<String, Option<(DateTime<Utc>, String)>>,
+    data: &RwLock<IndexData>,
+    dirty: &mut bool,
+) -> bool {
+    for (id, change) in pending.drain() {
+        if let Some((modified_at, _)) = &change {
+            match data.read() {
+                Ok(index) if index.is_current(&id, *modified_at) => continue,
+                Ok(_) => {}
+                Err(_) => return false,
+            }
+        }
+        // Split outside the lock so readers are only blocked briefly
+        let change =
+            change.map(|(modified_at, text)| (modified_at, words(&text.to_lowercase()).collect()));
+        let Ok(mut index) = data.write() else {
+            return false;
+        };
+        match change {
+            Some((modified_at, words)) => index.update(id, modified_at, words),
+            None => index.remove(&id),
+        }
+        drop(index);
+        *dirty = true;
+        thread::yield_now();
+    }
+    true
+}
@@ -0,0 +1,271 @@
+//! # Search Results UI Module
+//!
+//! The search results window, opened with Enter in the sidebar search box.
+//! It lists the matching notes with excerpts around each match and the
+//! search text highlighted. Results are picked with the mouse or with the
+//! arrow keys and Enter, which opens the note with the match selected and
+//! scrolled into view. Notes in the trash are left out unless the window's
+//! "Include notes in the trash" box is ticked; they are then marked, so
+//! nothing is out of reach of the search just because it was deleted.
+
+use crate::app::NotesApp;
+use crate::note::Note;
+use crate::search::{self, Snippet};
+use crate::settings::EditorView;
+use eframe::egui;
+use std::ops::Range;
+
+/// Maximum number of notes listed in the results window.
+const MAX_RESULTS: usize = 200;
+
+/// Maximum number of snippets shown per note.
+const MAX_SNIPPETS_PER_NOTE: usize = 3;
+
+impl NotesApp {
+    /// Returns the notes matching the current search, newest first.
+    ///
+    /// The search index narrows down the candidates, which are then checked
+    /// against the full query.
+    ///
+    /// # Arguments
+    ///
+    /// * `include_trash` - Whether trashed notes can match too
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<&Note>` - The matching notes, sorted by modification time
+    pub fn matching_notes(&self, include_trash: bool) -> Vec<&Note> {
+        let mut notes: Vec<&Note> = self
+            .search_index
+            .candidates(
+                &self.search_query,
+                self.notes
+                    .values()
+                    .filter(|note| include_trash || !note.is_trashed()),
+            )
+            .into_iter()
+            .filter(|note| self.search_query.matches(note))
+            .collect();
+        notes.sort_by_key(|note| std::cmp::Reverse(note.modified_at));
+        notes
+    }
+
+    /// Opens the search results window for the current search.
+    pub fn open_search_results(&mut self) {
+        self.show_search_results = true;
+        self.search_result_selection = 0;
+    }
+
+    /// Opens a note and selects a range of its content in the editor.
+    ///
+    /// The preview has no cursor, so the preview view switches to the split
+    /// view to show the selection.
+    ///
+    /// # Arguments
+    ///
+    /// * `note_id` - The note to open
+    /// * `range` - Byte range of the content to select and scroll to
+    pub fn open_note_at(&mut self, note_id: &str, range: Range<usize>) {
+        self.selected_note_id = Some(note_id.to_string());
+        self.editor_jump = Some((note_id.to_string(), range));
+        if self.editor_view_of(note_id) == EditorView::Preview {
+            self.set_editor_view(EditorView::Split);
+        }
+    }
+
+    /// Renders the search results window.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The egui context
+    pub fn render_search_results(&mut self, ctx: &egui::Context) {
+        if !self.show_search_results {
+            return;
+        }
+
+        // Snippets are owned so the window can borrow the open flag
+        let text = self.search_query.free_text();
+        let notes = self.matching_notes(self.search_include_trash);
+        let total = notes.len();
+        let results: Vec<(String, String, bool, Vec<Snippet>)> = notes
+            .into_iter()
+            .take(MAX_RESULTS)
+            .map(|note| {
+                (
+                    note.id.clone(),
+                    note.display_title(),
+                    note.is_trashed(),
+                    self.search_query.snippets(note, MAX_SNIPPETS_PER_NOTE),
+                )
+            })
+            .collect();
+        let entry_count: usize = results
+            .iter()
+            .map(|(_, _, _, snippets)| snippets.len())
+            .sum();
+
+        // Arrow keys and Enter work while no text field has focus
+        let mut selection = self
+            .search_result_selection
+            .min(entry_count.saturating_sub(1));
+        let mut selection_moved = false;
+        let mut open_selected = false;
+        if ctx.memory(|memory| memory.focused().is_none()) {
+            ctx.input(|i| {
+                if i.key_pressed(egui::Key::ArrowDown) && selection + 1 < entry_count {
+                    selection += 1;
+                    selection_moved = true;
+                }
+                if i.key_pressed(egui::Key::ArrowUp) && selection > 0 {
+                    selection -= 1;
+                    selection_moved = true;
+                }
+                open_selected = i.key_pressed(egui::Key::Enter) && entry_count > 0;
+            });
+        }
+
+        let mut open_entry = None;
+        let include_trash = &mut self.search_include_trash;
+
+        egui::Window::new(format!("Search Results ({})", total))
+            .open(&mut self.show_search_results)
+            .default_width(450.0)
+            .default_height(400.0)
+            .show(ctx, |ui| {
+                ui.checkbox(include_trash, "Include notes in the trash")
+                    .on_hover_text("Trashed notes open read-only and can be restored");
+                if results.is_empty() {
+                    ui.label("No matching notes");
+                    return;
+                }
+                if total > MAX_RESULTS {
+                    ui.small(format!(
+                        "Showing the {} most recently modified notes",
+                        MAX_RESULTS
+                    ));
+                }
+                ui.small("↑ ↓ to select, Enter to open");
+                ui.separator();
+
+                egui::ScrollArea::vertical()
+                    .auto_shrink([false, false])
+                    .show(ui, |ui| {
+                        let mut index = 0;
+                        for (note_id, title, is_trashed, snippets) in &results {
+                            let title_job = highlighted_job(
+                                ui,
+                                title,
+                                &search::find_matches(title, &text),
+                                true,
+                            );
+                            let clicked = ui
+                                .horizontal(|ui| {
+                                    if *is_trashed {
+                                        ui.colored_label(ui.visuals().warn_fg_color, "🗑 Trash")
+                                            .on_hover_text("This note is in the trash");
+                                    }
+                                    ui.selectable_label(false, title_job).clicked()
+                                })
+                                .inner;
+                            if clicked {
+                                open_entry = Some((note_id.clone(), 0..0));
+                            }
+
+                            for snippet in snippets {
+                                let job =
+                                    highlighted_job(ui, &snippet.text, &snippet.highlights, false);
+                                let response = ui
+                                    .horizontal(|ui| {
+                                        ui.add_space(12.0);
+                                        if snippet.text.is_empty() {
+                                            ui.selectable_label(index == selection, "(empty note)")
+                                        } else {
+                                            ui.selectable_label(index == selection, job)
+                                        }
+                                    })
+                                    .inner;
+                                if selection_moved && index == selection {
+                                    response.scroll_to_me(None);
+                                }
+                                if response.clicked() || (open_selected && index == selection) {
+                                    open_entry =
+                                        Some((note_id.clone(), snippet.content_range.clone()));
+                                }
+                                index += 1;
+                            }
+                            ui.separator();
+                        }
+                    });
+            });
+
+        self.search_result_selection = selection;
+        if let Some((note_id, range)) = open_entry {
+            self.show_search_results = false;
+            self.open_note_at(&note_id, range);
+        }
+    }
+}
+
+/// Lays out text with some byte ranges highlighted.
+///
+/// # Arguments
+///
+/// * `ui` - The UI, for the text style
+/// * `text` - The text
+/// * `highlights` - Sorted, non-overlapping byte ranges to highlight
+/// * `strong` - Whether the text is a title, shown stronger
+///
+/// # Returns
+///
+/// * `egui::text::LayoutJob` - The laid-out text
+fn highlighted_job(
+    ui: &egui::Ui,
+    text: &str,
+    highlights: &[Range<usize>],
+    strong: bool,
+) -> egui::text::LayoutJob {
+    let font_id = egui::TextStyle::Body.resolve(ui.style());
+    let color = if strong {
+        ui.visuals().strong_text_color()
+    } else {
+        ui.visuals().text_color()
+    };
+    let mut job = highlight_job(text, highlights, font_id, color);
+    job.wrap.max_width = ui.available_width();
+    job
+}
+
+/// Lays out text in a font and color with some byte ranges highlighted.
+///
+/// # Arguments
+///
+/// * `text` - The text
+/// * `highlights` - Sorted, non-overlapping byte ranges to highlight
+/// * `font_id` - Font of the text
+/// * `color` - Color of the text outside the highlights
+///
+/// # Returns
+///
+/// * `egui::text::LayoutJob` - The text, not wrapped
+pub fn highlight_job(
+    text: &str,
+    highlights: &[Range<usize>],
+    font_id: egui::FontId,
+    color: egui::Color32,
+) -> egui::text::LayoutJob {
+    let plain = egui::TextFormat::simple(font_id.clone(), color);
+    let marked = egui::TextFormat {
+        background: egui::Color32::from_rgb(255, 210, 0),
+        ..egui::TextFormat::simple(font_id, egui::Color32::BLACK)
+    };
+
+    let mut job = egui::text::LayoutJob::default();
+    let mut position = 0;
+    for range in highlights {
+        job.append(&text[position..range.start], 0.0, plain.clone());
+        job.append(&text[range.clone()], 0.0, marked.clone());
+        position = range.end;
+    }
+    job.append(&text[position..], 0.0, plain);
+    job
+}
@@ -0,0 +1,181 @@
+//! # Secrets UI Module
+//!
+//! Renders the entries of a secrets note: small secrets such as passwords,
+//! PINs or API keys, each with a label, a user name and a URL. Secrets are
+//! masked until revealed, and every field has a copy button; copied
+//! secrets are cleared from the clipboard after the delay chosen in the
+//! settings, see the clipboard module.
+//!
+//! Entries are part of the note, so they are encrypted, saved, shared and
+//! migrated like the rest of it. Search finds entries by label, user name
+//! and URL, never by the secret.
+
+use crate::app::NotesApp;
+use crate::note::SecretEntry;
+use eframe::egui;
+
+/// Button pressed in the secrets panel, handled after the panel closure.
+enum SecretAction {
+    /// Copy a value that isn't secret, e.g. a user name
+    Copy(String),
+    /// Copy a secret, cleared from the clipboard later
+    CopySecret(String),
+    /// Open the URL of an entry in the browser
+    OpenUrl(String),
+    /// Remove the entry with this ID
+    Remove(String),
+    /// Add an empty entry
+    Add,
+}
+
+impl NotesApp {
+    /// Renders the collapsible panel with the entries of a secrets note.
+    ///
+    /// # Arguments
+    ///
+    /// * `ui` - The main content UI
+    /// * `note_id` - The ID of the displayed note
+    /// * `read_only` - Whether editing is disabled (trashed or locked notes);
+    ///   copying still works
+    pub fn render_secrets_panel(&mut self, ui: &mut egui::Ui, note_id: &str, read_only: bool) {
+        let revealed = &mut self.revealed_secrets;
+        let Some(note) = self.notes.get_mut(note_id) else {
+            return;
+        };
+
+        let mut changed = false;
+        let mut action = None;
+
+        egui::CollapsingHeader::new(format!("Secrets ({})", note.secrets.len()))
+            .id_salt("note_secrets")
+            .default_open(true)
+            .show(ui, |ui| {
+                for entry in note.secrets.iter_mut() {
+                    let is_revealed = revealed.contains(&entry.id);
+                    egui::Grid::new(("note_secret", entry.id.as_str()))
+                        .num_columns(3)
+                        .show(ui, |ui| {
+                            ui.label("Label");
+                            changed |= ui
+                                .add_enabled(
+                                    !read_only,
+                                    egui::TextEdit::singleline(&mut entry.label)
+                                        .hint_text("e.g. Router admin")
+                                        .desired_width(240.0),
+                                )
+                                .changed();
+                            if ui
+                                .add_enabled(!read_only, egui::Button::new("✕").small())
+                                .on_hover_text("Remove entry")
+                                .clicked()
+                            {
+                                action = Some(SecretAction::Remove(entry.id.clone()));
+                            }
+                            ui.end_row();
+
+                            ui.label("Username");
+                            changed |= ui
+                                .add_enabled(
+                                    !read_only,
+                                    egui::TextEdit::singleline(&mut entry.username)
+                                        .desired_width(240.0),
+                                )
+                                .changed();
+                            if ui.small_button("Copy").clicked() {
+                                action = Some(SecretAction::Copy(entry.username.clone()));
+                            }
+                            ui.end_row();
+
+                            ui.label("Secret");
+                            changed |= ui
+                                .add_enabled(
+                                    !read_only,
+                                    egui::TextEdit::singleline(&mut entry.secret)
+                                        .password(!is_revealed)
+                                        .font(egui::TextStyle::Monospace)
+                                        .desired_width(240.0),
+                                )
+                                .changed();
+                            ui.horizontal(|ui| {
+                                if ui
+                                    .selectable_label(is_revealed, "👁")
+                                    .on_hover_text(if is_revealed { "Hide" } else { "Show" })
+                                    .clicked()
+                                    && !revealed.remove(&entry.id)
+                                {
+                                    revealed.insert(entry.id.clone());
+                                }
+                                if ui
+                                    .add_enabled(
+                                        !entry.secret.is_empty(),
+                                        egui::Button::new("Copy").small(),
+                                    )
+                                    .clicked()
+                                {
+                                    action = Some(SecretAction::CopySecret(entry.secret.clone()));
+                                }
+                            });
+                            ui.end_row();
+
+                            ui.label("URL");
+                            changed |= ui
+                                .add_enabled(
+                                    !read_only,
+                                    egui::TextEdit::singleline(&mut entry.url).desired_width(240.0),
+                                )
+                                .changed();
+                            ui.horizontal(|ui| {
+                                if ui.small_button("Copy").clicked() {
+                                    action = Some(SecretAction::Copy(entry.url.clone()));
+                                }
+                                let is_web = entry.url.starts_with("https://")
+                                    || entry.url.starts_with("http://");
+                                if ui
+                                    .add_enabled(is_web, egui::Button::new("Open").small())
+                                    .clicked()
+                                {
+                                    action = Some(SecretAction::OpenUrl(entry.url.clone()));
+                                }
+                            });
+                            ui.end_row();
+                        });
+                    ui.add_space(6.0);
+                }
+
+                if ui
+                    .add_enabled(!read_only, egui::Button::new("Add Entry"))
+                    .clicked()
+                {
+                    action = Some(SecretAction::Add);
+                }
+            });
+
+        match &action {
+            Some(SecretAction::Remove(entry_id)) => {
+                note.secrets.retain(|entry| &entry.id != entry_id);
+                revealed.remove(entry_id);
+                changed = true;
+            }
+            Some(SecretAction::Add) => {
+                note.secrets.push(SecretEntry::new());
+                changed = true;
+            }
+            _ => {}
+        }
+
+        if changed {
+            note.update_modified_time();
+            self.search_index.note_changed(note);
+            self.has_unsaved_changes = true;
+            self.last_save_time = std::time::Instant::now();
+        }
+
+        match action {
+            Some(SecretAction::Copy(text)) => ui.ctx().copy_text(text),
+            Some(SecretAction::CopySecret(secret)) => self.copy_sensitive(ui.ctx(), &secret),
+            Some(SecretAction::OpenUrl(url)) => self.open_url(&url),
+            _ => {}
+        }
+        ui.separator();
+    }
+}
@@ -0,0 +1,256 @@
+//! # Secure File System Module
+//!
+//! File system helpers shared by all modules that write data files:
+//!
+//! - Owner-only access: mode `0o600` on Unix, and on Windows a protected
+//!   DACL that grants access to the current user only (inherited entries
+//!   such as `Users` or `Everyone` are removed)
+//! - Best-effort secure deletion: file contents are overwritten with random
+//!   bytes and flushed to disk before the file is unlinked, so the old
+//!   ciphertext does not simply linger in free blocks
+//!
+//! ## Deletion Limitations
+//!
+//! Overwriting in place only reliably destroys data when the storage writes
+//! blocks back where they were, as classic hard disks do. SSDs and other
+//! flash media remap writes for wear leveling, and copy-on-write or
+//! journaling file systems (APFS, Btrfs, ZFS), snapshots and backups may
+//! keep older copies of a file. On such storage the overwrite cannot
+//! guarantee that the old data is gone; full-disk encryption is the reliable
+//! protection there. Since everything the app stores is already encrypted,
+//! the overwrite is an additional layer, not the primary protection.
+
+use rand::RngCore;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Size of the random buffer used for overwriting.
+const OVERWRITE_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Writes a file and restricts access to the current user.
+///
+/// # Arguments
+///
+/// * `path` - Destination file
+/// * `data` - Bytes to write
+///
+/// # Returns
+///
+/// * `io::Result<()>` - Ok if successful, Err if writing or restricting failed
+pub fn write_private_file(path: &Path, data: impl AsRef<[u8]>) -> io::Result<()> {
+    fs::write(path, data)?;
+    restrict_to_owner(path)
+}
+
+/// Restricts access to a file to the current user.
+///
+/// Sets mode `0o600` on Unix systems and replaces the DACL with a single
+/// full-access entry for the current user on Windows. On other platforms
+/// this is a no-op.
+///
+/// # Arguments
+///
+/// * `path` - The file to restrict
+///
+/// # Returns
+///
+/// * `io::Result<()>` - Ok if successful, Err if the permissions could not be set
+pub fn restrict_to_owner(path: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mut perms = fs::metadata(path)?.permissions();
+        perms.set_mode(0o600); // Read/write for owner only
+        fs::set_permissions(path, perms)?;
+    }
+
+    #[cfg(windows)]
+    windows_acl::restrict_to_current_user(path)?;
+
+    #[cfg(not(any(unix, windows)))]
+    let _ = path;
+
+    Ok(())
+}
+
+/// Overwrites a file with random data and then deletes it.
+///
+/// Overwriting is best effort: if it fails, the failure is logged and the
+/// file is still removed.
+///
+/// # Arguments
+///
+/// * `path` - The file to delete
+///
+/// # Returns
+///
+/// * `io::Result<()>` - Ok if the file was removed, Err if unlinking failed
+pub fn secure_remove_file(path: &Path) -> io::Result<()> {
+    // Never follow symlinks, only the link itself is ours to delete
+    if fs::symlink_metadata(path)?.is_file() {
+        if let Err(e) = overwrite_file(path) {
+            log::warn!(
+                "Failed to overwrite {} before deletion: {}",
+                path.display(),
+                e
+            );
+        }
+    }
+
+    fs::remove_file(path)
+}
+
+/// Overwrites every file below a directory and then deletes the directory.
+///
+/// # Arguments
+///
+/// * `path` - The directory to delete
+///
+/// # Returns
+///
+/// * `io::Result<()>` - Ok if the directory was removed, Err otherwise
+pub fn secure_remove_dir_all(path: &Path) -> io::Result<()> {
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            secure_remove_dir_all(&entry.path())?;
+        } else {
+            secure_remove_file(&entry.path())?;
+        }
+    }
+
+    fs::remove_dir(path)
+}
+
+/// Replaces the contents of a file with random bytes of the same length.
+fn overwrite_file(path: &Path) -> io::Result<()> {
+    let mut file = OpenOptions::new().write(true).open(path)?;
+    let mut remaining = file.metadata()?.len();
+
+    let mut buffer = vec![0u8; OVERWRITE_CHUNK_SIZE];
+    let mut rng = rand::thread_rng();
+    while remaining > 0 {
+        let chunk = remaining.min(OVERWRITE_CHUNK_SIZE as u64) as usize;
+        rng.fill_bytes(&mut buffer[..chunk]);
+        file.write_all(&buffer[..chunk])?;
+        remaining -= chunk as u64;
+    }
+
+    // Make sure the random data reaches the disk before the unlink
+    file.sync_all()
+}
+
+/// Windows access control list handling.
+#[cfg(windows)]
+mod windows_acl {
+    use std::io;
+    use std::os::windows::ffi::OsStrExt;
+    use std::path::Path;
+    use std::ptr;
+    use windows_sys::Win32::Foundation::{CloseHandle, LocalFree, ERROR_SUCCESS, HANDLE};
+    use windows_sys::Win32::Security::Authorization::{
+        SetEntriesInAclW, SetNamedSecurityInfoW, EXPLICIT_ACCESS_W, NO_MULTIPLE_TRUSTEE,
+        SET_ACCESS, SE_FILE_OBJECT, TRUSTEE_IS_SID, TRUSTEE_IS_USER, TRUSTEE_W,
+    };
+    use windows_sys::Win32::Security::{
+        GetTokenInformation, TokenUser, ACL, DACL_SECURITY_INFORMATION, NO_INHERITANCE,
+        PROTECTED_DACL_SECURITY_INFORMATION, TOKEN_QUERY, TOKEN_USER,
+    };
+    use windows_sys::Win32::Storage::FileSystem::FILE_ALL_ACCESS;
+    use windows_sys::Win32::System::Threading::{GetCurrentProcess, OpenProcessToken};
+
+    /// Replaces the DACL of a file with a single entry for the current user.
+    ///
+    /// The DACL is marked protected so entries inherited from the parent
+    /// directory no longer apply.
+    pub fn restrict_to_current_user(path: &Path) -> io::Result<()> {
+        let token_user = current_user_token_info()?;
+        // SAFETY: the buffer holds a TOKEN_USER written by GetTokenInformation
+        let user_sid = unsafe { (*(token_user.as_ptr() as *const TOKEN_USER)).User.Sid };
+
+        let access = EXPLICIT_ACCESS_W {
+            grfAccessPermissions: FILE_ALL_ACCESS,
+            grfAccessMode: SET_ACCESS,
+            grfInheritance: NO_INHERITANCE,
+            Trustee: TRUSTEE_W {
+                pMultipleTrustee: ptr::null_mut(),
+                MultipleTrusteeOperation: NO_MULTIPLE_TRUSTEE,
+                TrusteeForm: TRUSTEE_IS_SID,
+                TrusteeType: TRUSTEE_IS_USER,
+                ptstrName: user_sid as *mut u16,
+            },
+        };
+
+        let mut acl: *mut ACL = ptr::null_mut();
+        // SAFETY: `access` is a valid entry and `acl` receives a LocalAlloc'd ACL
+        let status = unsafe { SetEntriesInAclW(1, &access, ptr::null(), &mut acl) };
+        if status != ERROR_SUCCESS {
+            return Err(io::Error::from_raw_os_error(status as i32));
+        }
+
+        let wide_path: Vec<u16> = path
+            .as_os_str()
+            .encode_wide()
+            .chain(std::iter::once(0))
+            .collect();
+
+        // SAFETY: `wide_path` is NUL-terminated and `acl` was created above
+        let status = unsafe {
+            SetNamedSecurityInfoW(
+                wide_path.as_ptr(),
+                SE_FILE_OBJECT,
+                DACL_SECURITY_INFORMATION | PROTECTED_DACL_SECURITY_INFORMATION,
+                ptr::null_mut(),
+                ptr::null_mut(),
+                acl,
+                ptr::null(),
+            )
+        };
+
+        // SAFETY: `acl` was allocated by SetEntriesInAclW
+        unsafe { LocalFree(acl as _) };
+
+        if status != ERROR_SUCCESS {
+            return Err(io::Error::from_raw_os_error(status as i32));
+        }
+        Ok(())
+    }
+
+    /// Reads the `TOKEN_USER` information of the current process.
+    ///
+    /// The buffer is returned as `u64`s so the `TOKEN_USER` at its start is
+    /// properly aligned.
+    fn current_user_token_info() -> io::Result<Vec<u64>> {
+        let mut token: HANDLE = ptr::null_mut();
+        // SAFETY: the pseudo handle of the current process is always valid
+        if unsafe { OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut token) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let mut length = 0u32;
+        // SAFETY: querying the required size with an empty buffer
+        unsafe { GetTokenInformation(token, TokenUser, ptr::null_mut(), 0, &mut length) };
+
+        let mut buffer = vec![0u64; (length as usize).div_ceil(8)];
+        // SAFETY: `buffer` is at least `length` bytes long
+        let ok = unsafe {
+            GetTokenInformation(
+                token,
+                TokenUser,
+                buffer.as_mut_ptr() as *mut _,
+                length,
+                &mut length,
+            )
+        };
+        let result = if ok == 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(buffer)
+        };
+
+        // SAFETY: `token` was opened above
+        unsafe { CloseHandle(token) };
+        result
+    }
+}
@@ -0,0 +1,286 @@
+//! # Session Monitor Module
+//!
+//! Watches the operating system session so the vault can be locked when
+//! the user walks away. The app only runs the monitor while a user is
+//! logged in with the lock enabled. A background thread polls every
+//! [`POLL_INTERVAL`] and reports:
+//!
+//! - **Resume from suspend**: the thread reads two clocks that are both
+//!   immune to changes of the system time, one that keeps counting while
+//!   the computer sleeps and one that stops. If the first advanced more
+//!   than [`SUSPEND_THRESHOLD`] further than the second between two polls,
+//!   the computer was suspended. Linux, Windows and macOS support this.
+//! - **Screen lock**: on Windows the input desktop cannot be opened while
+//!   the lock screen is shown; on Linux the `LockedHint` of the logind
+//!   session is read through `loginctl`. macOS and other platforms have no
+//!   screen lock detection.
+//!
+//! Each event also requests a repaint, so the app handles it even while
+//! idle. The monitor never touches the vault itself.
+
+use eframe::egui;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+/// How often the session state is polled.
+pub const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Time spent asleep between two polls that counts as a suspend.
+pub const SUSPEND_THRESHOLD: Duration = Duration::from_secs(30);
+
+/// A change of the OS session the vault should be locked for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SessionEvent {
+    /// The screen was locked
+    ScreenLocked,
+    /// The computer woke up from suspend or hibernation
+    Resumed,
+}
+
+impl SessionEvent {
+    /// Describes the event for the login screen.
+    ///
+    /// # Returns
+    ///
+    /// * `&'static str` - E.g. "the screen was locked"
+    pub fn description(&self) -> &'static str {
+        match self {
+            SessionEvent::ScreenLocked => "the screen was locked",
+            SessionEvent::Resumed => "the computer was suspended",
+        }
+    }
+}
+
+/// Background watcher of the OS session.
+pub struct SessionMonitor {
+    receiver: mpsc::Receiver<SessionEvent>,
+    /// Dropping it wakes the thread and ends it
+    _stop: mpsc::Sender<()>,
+}
+
+impl SessionMonitor {
+    /// Starts watching the session on a background thread.
+    ///
+    /// The thread ends as soon as the monitor is dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The egui context to wake up when an event occurs
+    ///
+    /// # Returns
+    ///
+    /// * `Self` - The running monitor
+    pub fn start(ctx: &egui::Context) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let (stop, stopped) = mpsc::channel::<()>();
+        let ctx = ctx.clone();
+
+        thread::spawn(move || {
+            let mut last_clocks = platform::clocks();
+            let mut was_locked = false;
+            // Stops asking once the platform turns out not to report it
+            let mut detect_lock = true;
+
+            // Waiting on the stop channel doubles as the poll interval
+            while let Err(mpsc::RecvTimeoutError::Timeout) = stopped.recv_timeout(POLL_INTERVAL) {
+                let clocks = platform::clocks();
+                let mut events = Vec::new();
+                if let (Some(last), Some(now)) = (last_clocks, clocks) {
+                    if now.asleep_since(&last) > SUSPEND_THRESHOLD {
+                        events.push(SessionEvent::Resumed);
+                    }
+                }
+                last_clocks = clocks;
+
+                if detect_lock {
+                    match platform::screen_locked() {
+                        Some(locked) => {
+                            if locked && !was_locked {
+                                events.push(SessionEvent::ScreenLocked);
+                            }
+                            was_locked = locked;
+                        }
+                        None => detect_lock = false,
+                    }
+                }
+
+                for event in events {
+                    log::info!("Session event: {}", event.description());
+                    if sender.send(event).is_err() {
+                        return;
+                    }
+                    ctx.request_repaint();
+                }
+            }
+        });
+
+        Self {
+            receiver,
+            _stop: stop,
+        }
+    }
+
+    /// Returns the next session event, if one occurred.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<SessionEvent>` - The oldest unhandled event, or None
+    pub fn poll(&self) -> Option<SessionEvent> {
+        self.receiver.try_recv().ok()
+    }
+}
+
+/// Readings of a clock that counts suspended time and one that does not.
+///
+/// Neither follows changes of the system time, so only a suspend makes
+/// them drift apart.
+#[derive(Debug, Clone, Copy)]
+struct Clocks {
+    /// Time since boot, including suspended time
+    total: Duration,
+    /// Time since boot the computer was awake
+    awake: Duration,
+}
+
+impl Clocks {
+    /// Returns how long the computer slept since the earlier reading.
+    fn asleep_since(&self, earlier: &Clocks) -> Duration {
+        let total = self.total.saturating_sub(earlier.total);
+        let awake = self.awake.saturating_sub(earlier.awake);
+        total.saturating_sub(awake)
+    }
+}
+
+/// Clocks and screen lock detection on Windows.
+#[cfg(windows)]
+mod platform {
+    use super::Clocks;
+    use std::time::Duration;
+    use windows_sys::Win32::System::StationsAndDesktops::{
+        CloseDesktop, OpenInputDesktop, DESKTOP_SWITCHDESKTOP,
+    };
+    use windows_sys::Win32::System::SystemInformation::GetTickCount64;
+    use windows_sys::Win32::System::WindowsProgramming::QueryUnbiasedInterruptTime;
+
+    /// Reads the tick count, which counts suspended time, and the unbiased
+    /// interrupt time, which does not.
+    pub fn clocks() -> Option<Clocks> {
+        let mut awake = 0u64;
+        // SAFETY: `awake` is a valid u64 to write to
+        if unsafe { QueryUnbiasedInterruptTime(&mut awake) } == 0 {
+            return None;
+        }
+        // SAFETY: plain call without arguments
+        let total = unsafe { GetTickCount64() };
+        Some(Clocks {
+            total: Duration::from_millis(total),
+            // Counted in units of 100 nanoseconds
+            awake: Duration::from_nanos(awake.saturating_mul(100)),
+        })
+    }
+
+    /// Checks whether the lock screen is shown.
+    ///
+    /// The lock screen runs on the secure desktop, which a normal process
+    /// cannot open as its input desktop.
+    pub fn screen_locked() -> Option<bool> {
+        // SAFETY: plain call without pointers; the handle is closed below
+        let desktop = unsafe { OpenInputDesktop(0, 0, DESKTOP_SWITCHDESKTOP) };
+        if desktop.is_null() {
+            return Some(true);
+        }
+        // SAFETY: `desktop` was opened above
+        unsafe { CloseDesktop(desktop) };
+        Some(false)
+    }
+}
+
+/// Clocks and screen lock detection on Linux.
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::Clocks;
+    use std::process::Command;
+
+    /// Reads `CLOCK_BOOTTIME`, which counts suspended time, and
+    /// `CLOCK_MONOTONIC`, which does not.
+    pub fn clocks() -> Option<Clocks> {
+        Some(Clocks {
+            total: super::unix_clock::read(libc::CLOCK_BOOTTIME)?,
+            awake: super::unix_clock::read(libc::CLOCK_MONOTONIC)?,
+        })
+    }
+
+    /// Checks whether the logind session is locked.
+    ///
+    /// Returns None if logind is not available, e.g. without systemd.
+    pub fn screen_locked() -> Option<bool> {
+        let session = std::env::var("XDG_SESSION_ID").unwrap_or_else(|_| "auto".to_string());
+        let output = Command::new("loginctl")
+            .args(["show-session", &session, "--property=LockedHint", "--value"])
+            .output()
+            .ok()
+            .filter(|output| output.status.success())?;
+
+        match String::from_utf8_lossy(&output.stdout).trim() {
+            "yes" => Some(true),
+            "no" => Some(false),
+            _ => None,
+        }
+    }
+}
+
+/// Clocks on macOS, which has no screen lock detection.
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::Clocks;
+
+    /// Reads `CLOCK_MONOTONIC`, which counts suspended time on macOS, and
+    /// `CLOCK_UPTIME_RAW`, which does not.
+    pub fn clocks() -> Option<Clocks> {
+        Some(Clocks {
+            total: super::unix_clock::read(libc::CLOCK_MONOTONIC)?,
+            awake: super::unix_clock::read(libc::CLOCK_UPTIME_RAW)?,
+        })
+    }
+
+    /// Screen lock detection is not supported here.
+    pub fn screen_locked() -> Option<bool> {
+        None
+    }
+}
+
+/// Platforms without suspend or screen lock detection.
+#[cfg(not(any(windows, target_os = "linux", target_os = "macos")))]
+mod platform {
+    use super::Clocks;
+
+    /// Suspend detection is not supported here.
+    pub fn clocks() -> Option<Clocks> {
+        None
+    }
+
+    /// Screen lock detection is not supported here.
+    pub fn screen_locked() -> Option<bool> {
+        None
+    }
+}
+
+/// Reading of POSIX clocks.
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+mod unix_clock {
+    use std::time::Duration;
+
+    /// Reads the given clock, or None if it is not available.
+    pub fn read(clock: libc::clockid_t) -> Option<Duration> {
+        let mut time = libc::timespec {
+            tv_sec: 0,
+            tv_nsec: 0,
+        };
+        // SAFETY: `time` is a valid timespec to write to
+        if unsafe { libc::clock_gettime(clock, &mut time) } != 0 {
+            return None;
+        }
+        Some(Duration::new(time.tv_sec as u64, time.tv_nsec as u32))
+    }
+}
@@ -0,0 +1,407 @@
+//! # Settings Module
+//!
+//! Defines the per-user preferences that are persisted (encrypted) next to
+//! the user's notes. Every field has a sensible default so that settings
+//! files written by older versions keep loading after new options are added.
+
+use crate::crypto::AeadAlgorithm;
+use crate::export::DEFAULT_FILENAME_TEMPLATE;
+use crate::keymap::Keymap;
+use crate::logging::LogLevel;
+use crate::search::SmartFilter;
+use crate::trusted_time::DEFAULT_TIME_SERVER;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::time::Duration;
+
+/// Smallest auto-save delay selectable in the settings dialog (seconds).
+pub const MIN_AUTO_SAVE_DELAY_SECS: u64 = 1;
+/// Largest auto-save delay selectable in the settings dialog (seconds).
+pub const MAX_AUTO_SAVE_DELAY_SECS: u64 = 300;
+
+/// Shortest longest-wait for auto-save selectable in the settings (seconds).
+pub const MIN_AUTO_SAVE_MAX_WAIT_SECS: u64 = 10;
+/// Longest longest-wait for auto-save selectable in the settings (seconds).
+pub const MAX_AUTO_SAVE_MAX_WAIT_SECS: u64 = 900;
+
+/// Shortest time a status message stays visible (seconds).
+pub const MIN_STATUS_MESSAGE_SECS: u64 = 1;
+/// Longest time a status message stays visible (seconds).
+pub const MAX_STATUS_MESSAGE_SECS: u64 = 60;
+
+/// Trash retention periods selectable in the settings dialog (days, 0 = never purge).
+pub const TRASH_RETENTION_CHOICES: [u32; 6] = [7, 14, 30, 60, 90, 0];
+
+/// Clipboard clearing delays selectable in the settings dialog (seconds, 0 = never).
+pub const CLIPBOARD_CLEAR_CHOICES: [u64; 5] = [10, 30, 60, 120, 0];
+
+/// Pinned note limits selectable in the settings dialog (0 = no limit).
+pub const MAX_PINNED_CHOICES: [usize; 6] = [0, 3, 5, 10, 20, 50];
+
+/// Storage quotas selectable in the settings dialog (MB, 0 = no quota).
+pub const STORAGE_QUOTA_CHOICES: [u64; 6] = [0, 100, 250, 500, 1024, 5120];
+
+/// Per-user application preferences.
+///
+/// Stored encrypted as `settings.enc` in the user's data directory and
+/// loaded right after a successful login.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UserSettings {
+    /// Whether notes are saved automatically after editing
+    pub auto_save_enabled: bool,
+    /// Delay in seconds between the last edit and the automatic save
+    pub auto_save_delay_secs: u64,
+    /// Longest time in seconds changes stay unsaved while typing goes on
+    /// without a pause
+    pub auto_save_max_wait_secs: u64,
+    /// Verbosity of the application log file
+    pub log_level: LogLevel,
+    /// Algorithm used when (re-)encrypting notes and settings
+    pub encryption_algorithm: AeadAlgorithm,
+    /// Saved searches shown in the sidebar
+    pub smart_filters: Vec<SmartFilter>,
+    /// Days a note stays in the trash before it is purged, 0 for forever
+    pub trash_retention_days: u32,
+    /// IDs of the sidebar sections the user collapsed
+    pub collapsed_sections: BTreeSet<String>,
+    /// Whether untitled notes take their first non-empty line as the title
+    pub auto_title: bool,
+    /// Whether the editor shows line numbers in a gutter
+    pub show_line_numbers: bool,
+    /// Whether the editor highlights the line holding the cursor
+    pub highlight_current_line: bool,
+    /// Whether the editor keeps the cursor line vertically centered while typing
+    pub typewriter_scrolling: bool,
+    /// Whether the vault locks when the screen is locked or the computer suspends
+    pub lock_on_session_lock: bool,
+    /// Whether the window is hidden from screenshots, recordings and screen sharing
+    pub block_screen_capture: bool,
+    /// Seconds until a copied secret is removed from the clipboard, 0 for never
+    pub clipboard_clear_secs: u64,
+    /// Whether the system clock is checked against a time server, see the
+    /// trusted_time module
+    pub verify_time: bool,
+    /// Host name of the time server, optionally with a port
+    pub time_server: String,
+    /// Space the user's files may take up in MB, 0 for no quota
+    pub storage_quota_mb: u64,
+    /// Template for the default file name of exported notes, see the export module
+    pub export_filename_template: String,
+    /// Whether the "All Notes" section is split into Today / Yesterday / This Week / Earlier
+    pub group_by_date: bool,
+    /// Whether clicking a note in the sidebar during a search opens it at
+    /// the first match of the search text
+    pub open_at_first_match: bool,
+    /// Whether the outline of the open note's headings is shown next to the editor
+    pub show_outline: bool,
+    /// Whether notes are shown as text, rendered Markdown or both side by
+    /// side, unless a note remembers its own view
+    pub editor_view: EditorView,
+    /// Note and scroll position open at the last logout, restored at login
+    pub last_session: LastSession,
+    /// Keyboard shortcuts changed from their defaults
+    pub keymap: Keymap,
+    /// Seconds a status message stays visible at the top of the note view
+    pub status_message_secs: u64,
+    /// Command run after each export, see the export_hook module
+    pub export_hook: ExportHook,
+    /// Order of the notes in the sidebar
+    pub note_sort: NoteSort,
+    /// Note IDs in the order the user dragged them to, for `NoteSort::Manual`
+    pub note_order: Vec<String>,
+    /// IDs of the pinned notes in the order of the Pinned section
+    pub pinned_order: Vec<String>,
+    /// Most notes that can be pinned, 0 for no limit
+    pub max_pinned: usize,
+    /// What pinning a note beyond `max_pinned` does
+    pub pin_overflow: PinOverflow,
+}
+
+impl Default for UserSettings {
+    fn default() -> Self {
+        Self {
+            auto_save_enabled: true,
+            auto_save_delay_secs: 2,
+            auto_save_max_wait_secs: 60,
+            log_level: LogLevel::Info,
+            encryption_algorithm: AeadAlgorithm::XChaCha20Poly1305,
+            smart_filters: Vec::new(),
+            trash_retention_days: 30,
+            collapsed_sections: BTreeSet::new(),
+            auto_title: true,
+            show_line_numbers: false,
+            highlight_current_line: false,
+            typewriter_scrolling: false,
+            lock_on_session_lock: true,
+            block_screen_capture: false,
+            clipboard_clear_secs: 30,
+            verify_time: false,
+            time_server: DEFAULT_TIME_SERVER.to_string(),
+            storage_quota_mb: 0,
+            export_filename_template: DEFAULT_FILENAME_TEMPLATE.to_string(),
+            group_by_date: false,
+            open_at_first_match: true,
+            show_outline: false,
+            editor_view: EditorView::Edit,
+            last_session: LastSession::default(),
+            keymap: Keymap::default(),
+            status_message_secs: 3,
+            export_hook: ExportHook::default(),
+            note_sort: NoteSort::Modified,
+            note_order: Vec::new(),
+            pinned_order: Vec::new(),
+            max_pinned: 0,
+            pin_overflow: PinOverflow::UnpinLowest,
+        }
+    }
+}
+
+impl UserSettings {
+    /// Returns the effective auto-save delay.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Duration>` - The delay, or `None` in manual-save-only mode
+    pub fn auto_save_delay(&self) -> Option<Duration> {
+        if self.auto_save_enabled {
+            Some(Duration::from_secs(
+                self.auto_save_delay_secs
+                    .clamp(MIN_AUTO_SAVE_DELAY_SECS, MAX_AUTO_SAVE_DELAY_SECS),
+            ))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the longest time changes stay unsaved while the user keeps
+    /// typing, never shorter than the auto-save delay.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Duration>` - The longest wait, or `None` in
+    ///   manual-save-only mode
+    pub fn auto_save_max_wait(&self) -> Option<Duration> {
+        let delay = self.auto_save_delay()?;
+        let max_wait = Duration::from_secs(
+            self.auto_save_max_wait_secs
+                .clamp(MIN_AUTO_SAVE_MAX_WAIT_SECS, MAX_AUTO_SAVE_MAX_WAIT_SECS),
+        );
+        Some(max_wait.max(delay))
+    }
+
+    /// Returns how long a status message stays visible.
+    ///
+    /// # Returns
+    ///
+    /// * `Duration` - The duration, within the range of the settings dialog
+    pub fn status_message_duration(&self) -> Duration {
+        Duration::from_secs(
+            self.status_message_secs
+                .clamp(MIN_STATUS_MESSAGE_SECS, MAX_STATUS_MESSAGE_SECS),
+        )
+    }
+
+    /// Returns how long a copied secret stays in the clipboard.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Duration>` - The delay, or `None` if it is never cleared
+    pub fn clipboard_clear_delay(&self) -> Option<Duration> {
+        (self.clipboard_clear_secs > 0).then(|| Duration::from_secs(self.clipboard_clear_secs))
+    }
+
+    /// Returns the storage quota.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<u64>` - The quota in bytes, or `None` without a quota
+    pub fn storage_quota(&self) -> Option<u64> {
+        (self.storage_quota_mb > 0).then(|| self.storage_quota_mb * 1024 * 1024)
+    }
+}
+
+/// How the open note is shown in the main content area.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EditorView {
+    /// Only the text editor (default)
+    Edit,
+    /// The editor and the Markdown preview side by side, scrolled together
+    Split,
+    /// Only the Markdown preview
+    Preview,
+}
+
+impl EditorView {
+    /// All views in the order of the note header.
+    pub const ALL: [EditorView; 3] = [EditorView::Edit, EditorView::Split, EditorView::Preview];
+
+    /// Human-readable name of the view.
+    pub fn label(&self) -> &'static str {
+        match self {
+            EditorView::Edit => "Edit",
+            EditorView::Split => "Split",
+            EditorView::Preview => "Preview",
+        }
+    }
+
+    /// Short explanation of the view, for tooltips.
+    pub fn description(&self) -> &'static str {
+        match self {
+            EditorView::Edit => "Edit the text of the note",
+            EditorView::Split => "Edit the text next to a live Markdown preview",
+            EditorView::Preview => "Show the note as rendered Markdown",
+        }
+    }
+}
+
+/// Order of the notes in the sidebar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NoteSort {
+    /// Last modified first (default)
+    Modified,
+    /// Last created first
+    Created,
+    /// By title, A to Z
+    TitleAscending,
+    /// By title, Z to A
+    TitleDescending,
+    /// In the order the notes were dragged to
+    Manual,
+}
+
+impl NoteSort {
+    /// All orders in the order of the sort selector.
+    pub const ALL: [NoteSort; 5] = [
+        NoteSort::Modified,
+        NoteSort::Created,
+        NoteSort::TitleAscending,
+        NoteSort::TitleDescending,
+        NoteSort::Manual,
+    ];
+
+    /// Human-readable name of the order.
+    pub fn label(&self) -> &'static str {
+        match self {
+            NoteSort::Modified => "Modified",
+            NoteSort::Created => "Created",
+            NoteSort::TitleAscending => "Title A-Z",
+            NoteSort::TitleDescending => "Title Z-A",
+            NoteSort::Manual => "Manual",
+        }
+    }
+}
+
+/// What pinning a note does once the pinned limit is reached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PinOverflow {
+    /// Unpin the note at the bottom of the Pinned section (default)
+    UnpinLowest,
+    /// Leave the note unpinned
+    Refuse,
+}
+
+impl PinOverflow {
+    /// All policies in the order of the settings dialog.
+    pub const ALL: [PinOverflow; 2] = [PinOverflow::UnpinLowest, PinOverflow::Refuse];
+
+    /// Human-readable name of the policy.
+    pub fn label(&self) -> &'static str {
+        match self {
+            PinOverflow::UnpinLowest => "Unpin the lowest note",
+            PinOverflow::Refuse => "Don't pin it",
+        }
+    }
+}
+
+/// Where the user left off, restored at the next login.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LastSession {
+    /// ID of the note that was open, if any
+    pub note_id: Option<String>,
+    /// Vertical scroll position of the editor, in points
+    pub scroll_offset: f32,
+}
+
+/// A command run with the path of every exported file.
+///
+/// Never exported with the settings nor taken from imported ones, so that
+/// a settings file can't make the app run a command.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExportHook {
+    /// Whether the command runs; off until the user turns it on
+    pub enabled: bool,
+    /// Program and arguments, the exported file's path is appended
+    pub command: String,
+}
+
+/// Returns the display name of a trash retention period.
+///
+/// # Arguments
+///
+/// * `days` - Retention in days, 0 for forever
+///
+/// # Returns
+///
+/// * `String` - E.g. "30 days" or "Never"
+pub fn trash_retention_label(days: u32) -> String {
+    match days {
+        0 => "Never".to_string(),
+        1 => "1 day".to_string(),
+        days => format!("{} days", days),
+    }
+}
+
+/// Returns the display name of a pinned note limit.
+///
+/// # Arguments
+///
+/// * `count` - Most pinned notes, 0 for no limit
+///
+/// # Returns
+///
+/// * `String` - E.g. "5 notes" or "No limit"
+pub fn max_pinned_label(count: usize) -> String {
+    match count {
+        0 => "No limit".to_string(),
+        1 => "1 note".to_string(),
+        count => format!("{} notes", count),
+    }
+}
+
+/// Returns the display name of a clipboard clearing delay.
+///
+/// # Arguments
+///
+/// * `secs` - Delay in seconds, 0 for never
+///
+/// # Returns
+///
+/// * `String` - E.g. "30 seconds", "2 minutes" or "Never"
+pub fn clipboard_clear_label(secs: u64) -> String {
+    match secs {
+        0 => "Never".to_string(),
+        60 => "1 minute".to_string(),
+        secs if secs % 60 == 0 => format!("{} minutes", secs / 60),
+        secs => format!("{} seconds", secs),
+    }
+}
+
+/// Returns the display name of a storage quota.
+///
+/// # Arguments
+///
+/// * `mb` - Quota in MB, 0 for no quota
+///
+/// # Returns
+///
+/// * `String` - E.g. "500 MB", "5 GB" or "No quota"
+pub fn storage_quota_label(mb: u64) -> String {
+    match mb {
+        0 => "No quota".to_string(),
+        mb if mb % 1024 == 0 => format!("{} GB", mb / 1024),
+        mb => format!("{} MB", mb),
+    }
+}
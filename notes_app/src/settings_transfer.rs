@@ -0,0 +1,389 @@
+//! # Settings Transfer Module
+//!
+//! Exports the preferences of an account to a file and imports them into
+//! another account or on another computer, without any notes. The file is
+//! encrypted by the passphrase module with the magic bytes "SNST", like a
+//! migration bundle, because smart filters and the export file name
+//! template can reveal what the notes are about.
+//!
+//! Everything in the settings window is transferred, including shortcuts,
+//! smart filters and intervals. The note that was open at the last logout
+//! belongs to the exporting account and stays behind, and so does the
+//! export hook, a command the app runs.
+//!
+//! Deriving the passphrase key takes seconds, so writing and reading the
+//! file run in a [`PassphraseTask`] while the dialog shows a spinner.
+
+use crate::app::NotesApp;
+use crate::passphrase::{self, PassphraseTask, SealedFormat, MIN_PASSPHRASE_LEN};
+use crate::secure_fs;
+use crate::settings::{ExportHook, LastSession, UserSettings};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// File extension of exported settings.
+pub const SETTINGS_EXTENSION: &str = "snset";
+
+/// Format of exported settings.
+const SETTINGS_FORMAT: SealedFormat = SealedFormat {
+    magic: b"SNST",
+    version: 1,
+    name: "settings file",
+};
+
+/// Content of a settings file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SettingsFile {
+    /// UTC timestamp of the export
+    exported_at: DateTime<Utc>,
+    /// The exported preferences
+    settings: UserSettings,
+}
+
+/// Encrypts preferences with a passphrase and writes them to a file.
+///
+/// # Arguments
+///
+/// * `path` - Destination file
+/// * `settings` - The preferences to export
+/// * `passphrase` - Passphrase protecting the file
+///
+/// # Returns
+///
+/// * `Result<()>` - Ok if the file was written, Err otherwise
+pub fn write_settings(path: &Path, settings: &UserSettings, passphrase: &str) -> Result<()> {
+    let file = SettingsFile {
+        exported_at: Utc::now(),
+        settings: UserSettings {
+            last_session: LastSession::default(),
+            export_hook: ExportHook::default(),
+            // Note IDs mean nothing to another account or computer
+            note_order: Vec::new(),
+            pinned_order: Vec::new(),
+            ..settings.clone()
+        },
+    };
+    let plaintext = serde_json::to_vec(&file)?;
+    let data = passphrase::seal(&SETTINGS_FORMAT, &plaintext, passphrase)?;
+    secure_fs::write_private_file(path, data)?;
+    Ok(())
+}
+
+/// Reads and decrypts preferences written by `write_settings`.
+///
+/// # Arguments
+///
+/// * `path` - The settings file
+/// * `passphrase` - Passphrase the file was written with
+///
+/// # Returns
+///
+/// * `Result<UserSettings>` - The preferences, or error if the file is not
+///   a settings file, the passphrase is wrong or the file was modified
+pub fn read_settings(path: &Path, passphrase: &str) -> Result<UserSettings> {
+    let data = fs::read(path)?;
+    let plaintext = passphrase::open(&SETTINGS_FORMAT, &data, passphrase)?;
+    let file: SettingsFile = serde_json::from_slice(&plaintext)?;
+    Ok(file.settings)
+}
+
+/// State of the settings export/import dialog.
+pub enum SettingsTransferDialog {
+    /// Exporting the settings to a file
+    Export {
+        /// Passphrase input
+        passphrase: String,
+        /// Passphrase confirmation input
+        confirm_passphrase: String,
+        /// Error of the last attempt
+        error: Option<String>,
+        /// Export in progress, yielding the written file
+        task: Option<PassphraseTask<PathBuf>>,
+    },
+    /// Importing settings from a file
+    Import {
+        /// Passphrase input
+        passphrase: String,
+        /// Error of the last attempt
+        error: Option<String>,
+        /// Import in progress
+        task: Option<PassphraseTask<UserSettings>>,
+    },
+}
+
+impl SettingsTransferDialog {
+    /// Creates the dialog for exporting the settings.
+    pub fn export() -> Self {
+        Self::Export {
+            passphrase: String::new(),
+            confirm_passphrase: String::new(),
+            error: None,
+            task: None,
+        }
+    }
+
+    /// Creates the dialog for importing settings.
+    pub fn import() -> Self {
+        Self::Import {
+            passphrase: String::new(),
+            error: None,
+            task: None,
+        }
+    }
+}
+
+/// Button pressed in the dialog, handled after the window closure.
+enum TransferAction {
+    Export,
+    Import,
+    Close,
+}
+
+impl NotesApp {
+    /// Renders the settings export/import dialog, if open.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The egui context for rendering
+    pub fn render_settings_transfer_dialog(&mut self, ctx: &egui::Context) {
+        self.poll_settings_transfer_task();
+        let Some(dialog) = &mut self.settings_transfer_dialog else {
+            return;
+        };
+
+        let mut action = None;
+        let mut open = true;
+        let title = match dialog {
+            SettingsTransferDialog::Export { .. } => "Export Settings",
+            SettingsTransferDialog::Import { .. } => "Import Settings",
+        };
+
+        egui::Window::new(title)
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .default_width(420.0)
+            .show(ctx, |ui| match dialog {
+                SettingsTransferDialog::Export {
+                    passphrase,
+                    confirm_passphrase,
+                    error,
+                    task,
+                } => {
+                    ui.label(
+                        "Saves your preferences, shortcuts and smart filters to an \
+                         encrypted file you can import into another account or on \
+                         another computer. Notes are not included.",
+                    );
+                    ui.add_space(5.0);
+
+                    ui.label(format!(
+                        "Passphrase (at least {} characters):",
+                        MIN_PASSPHRASE_LEN
+                    ));
+                    ui.add(egui::TextEdit::singleline(passphrase).password(true));
+                    ui.label("Confirm passphrase:");
+                    ui.add(egui::TextEdit::singleline(confirm_passphrase).password(true));
+
+                    if let Some(error) = error {
+                        ui.colored_label(egui::Color32::RED, error.as_str());
+                    }
+
+                    ui.add_space(5.0);
+                    ui.horizontal(|ui| {
+                        if ui
+                            .add_enabled(task.is_none(), egui::Button::new("Export…"))
+                            .on_hover_text("Takes a few seconds")
+                            .clicked()
+                        {
+                            action = Some(TransferAction::Export);
+                        }
+                        if ui.button("Cancel").clicked() {
+                            action = Some(TransferAction::Close);
+                        }
+                        if task.is_some() {
+                            ui.spinner();
+                            ui.label("Exporting…");
+                        }
+                    });
+                }
+                SettingsTransferDialog::Import {
+                    passphrase,
+                    error,
+                    task,
+                } => {
+                    ui.label(
+                        "Replaces your preferences, shortcuts and smart filters with \
+                         the ones in a settings file. Your notes are not changed.",
+                    );
+                    ui.add_space(5.0);
+
+                    ui.label("Passphrase of the file:");
+                    ui.add(egui::TextEdit::singleline(passphrase).password(true));
+
+                    if let Some(error) = error {
+                        ui.colored_label(egui::Color32::RED, error.as_str());
+                    }
+
+                    ui.add_space(5.0);
+                    ui.horizontal(|ui| {
+                        if ui
+                            .add_enabled(
+                                !passphrase.is_empty() && task.is_none(),
+                                egui::Button::new("Choose File…"),
+                            )
+                            .clicked()
+                        {
+                            action = Some(TransferAction::Import);
+                        }
+                        if ui.button("Cancel").clicked() {
+                            action = Some(TransferAction::Close);
+                        }
+                        if task.is_some() {
+                            ui.spinner();
+                            ui.label("Importing…");
+                        }
+                    });
+                }
+            });
+
+        if !open {
+            action = Some(TransferAction::Close);
+        }
+        match action {
+            Some(TransferAction::Export) => self.export_settings_file(ctx),
+            Some(TransferAction::Import) => self.import_settings_file(ctx),
+            Some(TransferAction::Close) => self.settings_transfer_dialog = None,
+            None => {}
+        }
+    }
+
+    /// Lets the user pick a destination and starts writing the settings
+    /// file.
+    fn export_settings_file(&mut self, ctx: &egui::Context) {
+        let Some(SettingsTransferDialog::Export {
+            passphrase,
+            confirm_passphrase,
+            error,
+            task,
+        }) = &mut self.settings_transfer_dialog
+        else {
+            return;
+        };
+        if passphrase.len() < MIN_PASSPHRASE_LEN {
+            *error = Some(format!(
+                "Passphrase must be at least {} characters long",
+                MIN_PASSPHRASE_LEN
+            ));
+            return;
+        }
+        if passphrase != confirm_passphrase {
+            *error = Some("Passphrases do not match".to_string());
+            return;
+        }
+        let Some(ref user) = self.current_user else {
+            return;
+        };
+
+        let Some(path) = rfd::FileDialog::new()
+            .set_title("Export Settings")
+            .set_file_name(format!(
+                "secure_notes_settings_{}.{}",
+                user.username, SETTINGS_EXTENSION
+            ))
+            .add_filter("Settings files", &[SETTINGS_EXTENSION])
+            .save_file()
+        else {
+            return;
+        };
+
+        let settings = self.settings.clone();
+        let passphrase = passphrase.clone();
+        *error = None;
+        *task = Some(PassphraseTask::spawn(ctx, move || {
+            write_settings(&path, &settings, &passphrase).map(|()| path)
+        }));
+    }
+
+    /// Lets the user pick a settings file and starts reading it.
+    fn import_settings_file(&mut self, ctx: &egui::Context) {
+        let Some(SettingsTransferDialog::Import {
+            passphrase,
+            error,
+            task,
+        }) = &mut self.settings_transfer_dialog
+        else {
+            return;
+        };
+
+        let Some(path) = rfd::FileDialog::new()
+            .set_title("Import Settings")
+            .add_filter("Settings files", &[SETTINGS_EXTENSION])
+            .add_filter("All files", &["*"])
+            .pick_file()
+        else {
+            return;
+        };
+
+        let passphrase = passphrase.clone();
+        *error = None;
+        *task = Some(PassphraseTask::spawn(ctx, move || {
+            read_settings(&path, &passphrase)
+        }));
+    }
+
+    /// Handles the result of a finished export or import.
+    ///
+    /// Applies imported settings, keeping the open note and the note order
+    /// of this account. On error the dialog stays open with the message.
+    fn poll_settings_transfer_task(&mut self) {
+        match &mut self.settings_transfer_dialog {
+            Some(SettingsTransferDialog::Export { error, task, .. }) => {
+                match task.as_ref().and_then(PassphraseTask::poll) {
+                    Some(Ok(path)) => {
+                        log::info!("Exported settings");
+                        self.settings_transfer_dialog = None;
+                        self.report_export(
+                            format!("Settings exported to {}", path.display()),
+                            &path,
+                        );
+                    }
+                    Some(Err(e)) => {
+                        log::error!("Settings export failed: {}", e);
+                        *error = Some(format!("Export failed: {}", e));
+                        *task = None;
+                    }
+                    None => {}
+                }
+            }
+            Some(SettingsTransferDialog::Import { error, task, .. }) => {
+                match task.as_ref().and_then(PassphraseTask::poll) {
+                    Some(Ok(settings)) => {
+                        log::info!("Imported settings");
+                        self.settings_transfer_dialog = None;
+                        self.apply_settings(UserSettings {
+                            last_session: self.settings.last_session.clone(),
+                            note_order: self.settings.note_order.clone(),
+                            pinned_order: self.settings.pinned_order.clone(),
+                            ..settings
+                        });
+                        // The imported pinned limit may be lower
+                        self.enforce_pin_limit();
+                        self.show_status("Settings imported".to_string());
+                    }
+                    Some(Err(e)) => {
+                        log::warn!("Failed to import settings: {}", e);
+                        *error = Some(e.to_string());
+                        *task = None;
+                    }
+                    None => {}
+                }
+            }
+            None => {}
+        }
+    }
+}
@@ -8,6 +8,25 @@
 //! Provides secure dialogs for sensitive operations with proper validation and confirmation.
 
 use crate::app::NotesApp;
+use crate::capture_protection;
+use crate::crypto::AeadAlgorithm;
+use crate::export;
+use crate::export_hook::HOOK_TIMEOUT;
+use crate::keymap::{Keymap, ShortcutAction};
+use crate::logging::{self, LogLevel};
+use crate::profiling;
+use crate::reencrypt::ReencryptJob;
+use crate::settings::{
+    self, EditorView, PinOverflow, CLIPBOARD_CLEAR_CHOICES, MAX_AUTO_SAVE_DELAY_SECS,
+    MAX_AUTO_SAVE_MAX_WAIT_SECS, MAX_PINNED_CHOICES, MAX_STATUS_MESSAGE_SECS,
+    MIN_AUTO_SAVE_DELAY_SECS, MIN_AUTO_SAVE_MAX_WAIT_SECS, MIN_STATUS_MESSAGE_SECS,
+    STORAGE_QUOTA_CHOICES, TRASH_RETENTION_CHOICES,
+};
+use crate::settings_transfer::SettingsTransferDialog;
+use crate::shared_vault::SharedVault;
+use crate::shared_vault_ui;
+use crate::storage_report::{self, VaultUsage};
+use crate::trusted_time;
 use eframe::egui;
 
 impl NotesApp {
@@ -16,7 +35,15 @@ impl NotesApp {
     /// Displays account information and provides access to various account
     /// management functions including:
     /// - Account details (username, creation date)
-    /// - Data storage information
+    /// - Storage usage per vault (content and disk size, attachment share)
+    /// - Editor preferences (auto-save interval or manual-save-only mode,
+    ///   auto-titling of untitled notes, line numbers, current-line highlight)
+    /// - Keyboard shortcuts with export, import and reset
+    /// - Trash retention and reclaimable space
+    /// - Encryption algorithm selection
+    /// - Shared vault creation and members
+    /// - Troubleshooting (log level and log viewer)
+    /// - Moving the account to another computer
     /// - Password change functionality
     /// - Account deletion (danger zone)
     ///
@@ -34,6 +61,40 @@ impl NotesApp {
         let mut close_settings = false;
         let mut change_password = false;
         let mut delete_account = false;
+        let mut settings_changed = false;
+        let mut open_log_viewer = false;
+        let mut open_startup_profile = false;
+        let mut run_health_check = false;
+        let mut open_site_export = false;
+        let mut settings_transfer = None;
+        let mut open_migration_wizard = false;
+        let mut open_snapshot_viewer = false;
+        let mut empty_trash = false;
+        let mut retention_changed = false;
+        let mut pin_limit_changed = false;
+        let mut quota_changed = false;
+        let mut algorithm_changed = false;
+        let reencrypting = self.reencrypt_job.is_some();
+        let two_person_unlock = self.two_person_unlock_enabled();
+        let mut two_person_action = None;
+
+        // Trash usage, measured before the window borrows the settings flag
+        let trashed_ids = self.trashed_note_ids();
+        let reclaimable = self.current_user.as_ref().map_or(0, |user| {
+            self.storage_manager
+                .get_notes_size(&user.id, trashed_ids.iter().map(String::as_str))
+        });
+        let vault_status = self.shared_vault_status();
+        let mut vault_action = None;
+        if self.storage_report.is_none() {
+            self.storage_report = Some(self.measure_storage());
+        }
+        let storage_report = self.storage_report.clone().unwrap_or_default();
+        let mut refresh_storage = false;
+        let mut export_keymap = false;
+        let mut import_keymap = false;
+        let time_check = self.time_check.clone();
+        let mut check_time = false;
 
         egui::Window::new("Settings")
             .open(&mut self.show_user_settings)
@@ -52,10 +113,621 @@ impl NotesApp {
 
                     ui.separator();
 
-                    // Storage information - show data usage
-                    if let Ok(size) = self.storage_manager.get_user_data_size(&user.id) {
-                        ui.label(format!("Data size: {} bytes", size));
+                    // Storage information - content and disk usage per vault
+                    ui.horizontal(|ui| {
+                        ui.heading("Storage");
+                        if ui
+                            .small_button("⟳")
+                            .on_hover_text("Measure again")
+                            .clicked()
+                        {
+                            refresh_storage = true;
+                        }
+                    });
+                    render_vault_usage(ui, "Private notes", &storage_report.private);
+                    if let Some(shared) = &storage_report.shared {
+                        render_vault_usage(ui, "Shared vault", shared);
+                    }
+                    ui.label(format!(
+                        "Settings and other files: {}",
+                        storage_report::format_size(storage_report.other_file_bytes)
+                    ));
+                    ui.horizontal(|ui| {
+                        ui.label("Quota:");
+                        egui::ComboBox::from_id_salt("storage_quota")
+                            .selected_text(settings::storage_quota_label(
+                                self.settings.storage_quota_mb,
+                            ))
+                            .show_ui(ui, |ui| {
+                                for mb in STORAGE_QUOTA_CHOICES {
+                                    quota_changed |= ui
+                                        .selectable_value(
+                                            &mut self.settings.storage_quota_mb,
+                                            mb,
+                                            settings::storage_quota_label(mb),
+                                        )
+                                        .changed();
+                                }
+                            });
+                    });
+                    if let Some(usage) = self.quota_usage {
+                        ui.add(
+                            egui::ProgressBar::new(
+                                (usage.used_bytes as f32 / usage.quota_bytes as f32).min(1.0),
+                            )
+                            .text(format!(
+                                "{} of {} ({}%)",
+                                storage_report::format_size(usage.used_bytes),
+                                storage_report::format_size(usage.quota_bytes),
+                                usage.percent()
+                            )),
+                        );
+                    }
+                    ui.small(
+                        "Counts your private notes, attachments and settings. You are \
+                         warned at 80% and 90%; once the quota is used up, no more files \
+                         can be attached.",
+                    );
+                    ui.small(
+                        "Notes are encrypted but not compressed, so their files are \
+                         slightly larger than their content.",
+                    );
+
+                    ui.separator();
+
+                    // Editor preferences
+                    ui.heading("Editor");
+                    if ui
+                        .checkbox(&mut self.settings.auto_save_enabled, "Auto-save")
+                        .on_hover_text(format!(
+                            "When disabled, notes are only saved with {}",
+                            self.settings.keymap.shortcut(ShortcutAction::Save)
+                        ))
+                        .changed()
+                    {
+                        settings_changed = true;
+                    }
+
+                    ui.add_enabled_ui(self.settings.auto_save_enabled, |ui| {
+                        if ui
+                            .add(
+                                egui::Slider::new(
+                                    &mut self.settings.auto_save_delay_secs,
+                                    MIN_AUTO_SAVE_DELAY_SECS..=MAX_AUTO_SAVE_DELAY_SECS,
+                                )
+                                .logarithmic(true)
+                                .suffix(" s")
+                                .text("Save after a pause of"),
+                            )
+                            .on_hover_text("Saving waits until you stop typing this long")
+                            .changed()
+                        {
+                            settings_changed = true;
+                        }
+                        if ui
+                            .add(
+                                egui::Slider::new(
+                                    &mut self.settings.auto_save_max_wait_secs,
+                                    MIN_AUTO_SAVE_MAX_WAIT_SECS..=MAX_AUTO_SAVE_MAX_WAIT_SECS,
+                                )
+                                .logarithmic(true)
+                                .suffix(" s")
+                                .text("Save at least every"),
+                            )
+                            .on_hover_text(
+                                "Saves while you keep typing without a pause, so changes \
+                                 are never unsaved for longer",
+                            )
+                            .changed()
+                        {
+                            settings_changed = true;
+                        }
+                    });
+
+                    if !self.settings.auto_save_enabled {
+                        ui.small(
+                            "Manual save only: unsaved notes are marked in the editor header.",
+                        );
+                    }
+
+                    if ui
+                        .checkbox(
+                            &mut self.settings.auto_title,
+                            "Title untitled notes from their first line",
+                        )
+                        .on_hover_text(
+                            "Notes created without a title are named after their first \
+                             non-empty line until renamed",
+                        )
+                        .changed()
+                    {
+                        settings_changed = true;
+                    }
+
+                    ui.horizontal(|ui| {
+                        settings_changed |= ui
+                            .checkbox(&mut self.settings.show_line_numbers, "Line numbers")
+                            .changed();
+                        settings_changed |= ui
+                            .checkbox(
+                                &mut self.settings.highlight_current_line,
+                                "Highlight current line",
+                            )
+                            .changed();
+                        settings_changed |= ui
+                            .checkbox(
+                                &mut self.settings.typewriter_scrolling,
+                                "Typewriter scrolling",
+                            )
+                            .on_hover_text("Keep the line being typed in the middle of the editor")
+                            .changed();
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.label("Default view:");
+                        egui::ComboBox::from_id_salt("default_editor_view")
+                            .selected_text(self.settings.editor_view.label())
+                            .show_ui(ui, |ui| {
+                                for view in EditorView::ALL {
+                                    settings_changed |= ui
+                                        .selectable_value(
+                                            &mut self.settings.editor_view,
+                                            view,
+                                            view.label(),
+                                        )
+                                        .on_hover_text(view.description())
+                                        .changed();
+                                }
+                            });
+                    })
+                    .response
+                    .on_hover_text(
+                        "How notes open until you switch them; each note remembers the \
+                         view you last chose for it",
+                    );
+
+                    settings_changed |= ui
+                        .add(
+                            egui::Slider::new(
+                                &mut self.settings.status_message_secs,
+                                MIN_STATUS_MESSAGE_SECS..=MAX_STATUS_MESSAGE_SECS,
+                            )
+                            .suffix(" s")
+                            .text("Show status messages for"),
+                        )
+                        .on_hover_text("Earlier messages are listed under 🕘 in the sidebar")
+                        .changed();
+
+                    ui.separator();
+
+                    // Keyboard shortcuts - list, export and import
+                    ui.heading("Keyboard Shortcuts");
+                    egui::Grid::new("keyboard_shortcuts")
+                        .num_columns(2)
+                        .show(ui, |ui| {
+                            for action in ShortcutAction::ALL {
+                                ui.label(action.label());
+                                ui.monospace(self.settings.keymap.shortcut(action).to_string());
+                                ui.end_row();
+                            }
+                        });
+                    ui.horizontal(|ui| {
+                        if ui
+                            .button("Export…")
+                            .on_hover_text("Save the shortcuts to a JSON file")
+                            .clicked()
+                        {
+                            export_keymap = true;
+                        }
+                        if ui
+                            .button("Import…")
+                            .on_hover_text("Load shortcuts exported on another computer or account")
+                            .clicked()
+                        {
+                            import_keymap = true;
+                        }
+                        if ui
+                            .add_enabled(
+                                self.settings.keymap.is_customized(),
+                                egui::Button::new("Reset to Defaults"),
+                            )
+                            .clicked()
+                        {
+                            self.settings.keymap = Keymap::default();
+                            settings_changed = true;
+                        }
+                    });
+                    ui.small("To change a shortcut, edit an exported file and import it.");
+
+                    ui.separator();
+
+                    // Pinned notes - limit and what pinning beyond it does
+                    ui.heading("Pinned Notes");
+                    ui.horizontal(|ui| {
+                        ui.label("Pin at most:");
+                        egui::ComboBox::from_id_salt("max_pinned")
+                            .selected_text(settings::max_pinned_label(self.settings.max_pinned))
+                            .show_ui(ui, |ui| {
+                                for count in MAX_PINNED_CHOICES {
+                                    if ui
+                                        .selectable_value(
+                                            &mut self.settings.max_pinned,
+                                            count,
+                                            settings::max_pinned_label(count),
+                                        )
+                                        .changed()
+                                    {
+                                        pin_limit_changed = true;
+                                    }
+                                }
+                            });
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("When pinning one more:");
+                        egui::ComboBox::from_id_salt("pin_overflow")
+                            .selected_text(self.settings.pin_overflow.label())
+                            .show_ui(ui, |ui| {
+                                for overflow in PinOverflow::ALL {
+                                    if ui
+                                        .selectable_value(
+                                            &mut self.settings.pin_overflow,
+                                            overflow,
+                                            overflow.label(),
+                                        )
+                                        .changed()
+                                    {
+                                        pin_limit_changed = true;
+                                    }
+                                }
+                            });
+                    });
+                    ui.small(
+                        "Drag pinned notes in the Pinned section to reorder them. Lowering \
+                         the limit unpins the notes at the bottom.",
+                    );
+
+                    ui.separator();
+
+                    // Trash - retention and reclaimable space
+                    ui.heading("Trash");
+                    ui.horizontal(|ui| {
+                        ui.label("Purge deleted notes after:");
+                        egui::ComboBox::from_id_salt("trash_retention")
+                            .selected_text(settings::trash_retention_label(
+                                self.settings.trash_retention_days,
+                            ))
+                            .show_ui(ui, |ui| {
+                                for days in TRASH_RETENTION_CHOICES {
+                                    if ui
+                                        .selectable_value(
+                                            &mut self.settings.trash_retention_days,
+                                            days,
+                                            settings::trash_retention_label(days),
+                                        )
+                                        .changed()
+                                    {
+                                        retention_changed = true;
+                                    }
+                                }
+                            });
+                    });
+
+                    ui.label(format!(
+                        "{} note(s) in trash, {} bytes reclaimable",
+                        trashed_ids.len(),
+                        reclaimable
+                    ));
+                    if ui
+                        .add_enabled(!trashed_ids.is_empty(), egui::Button::new("Empty Trash"))
+                        .clicked()
+                    {
+                        empty_trash = true;
+                    }
+                    ui.small("Notes marked \"Keep forever\" are never purged automatically.");
+
+                    ui.separator();
+
+                    // Encryption algorithm - existing data is re-encrypted on change
+                    ui.heading("Encryption");
+                    ui.horizontal(|ui| {
+                        ui.label("Algorithm:");
+                        ui.add_enabled_ui(!reencrypting, |ui| {
+                            egui::ComboBox::from_id_salt("encryption_algorithm")
+                                .selected_text(self.settings.encryption_algorithm.label())
+                                .show_ui(ui, |ui| {
+                                    for algorithm in AeadAlgorithm::ALL {
+                                        if ui
+                                            .selectable_value(
+                                                &mut self.settings.encryption_algorithm,
+                                                algorithm,
+                                                algorithm.label(),
+                                            )
+                                            .changed()
+                                        {
+                                            algorithm_changed = true;
+                                        }
+                                    }
+                                });
+                        });
+                    });
+                    ui.small(
+                        "Changing the algorithm re-encrypts all notes and attachments \
+                         in the background.",
+                    );
+
+                    ui.separator();
+
+                    // Two-person unlock - the key is split between two passphrases
+                    ui.heading("Two-Person Unlock");
+                    if two_person_unlock {
+                        ui.label("On: unlocking needs your password and a second passphrase");
+                    } else {
+                        ui.label("Off");
                     }
+                    ui.add_enabled_ui(!reencrypting, |ui| {
+                        let (label, enable) = if two_person_unlock {
+                            ("Turn Off…", false)
+                        } else {
+                            ("Turn On…", true)
+                        };
+                        if ui.button(label).clicked() {
+                            two_person_action = Some(enable);
+                        }
+                    });
+                    ui.small(
+                        "Splits the key into two shares, so nobody can open the notes \
+                         with your password alone. Re-encrypts all notes and attachments.",
+                    );
+
+                    ui.separator();
+
+                    // Privacy - protection while the computer is unattended
+                    ui.heading("Privacy");
+                    settings_changed |= ui
+                        .checkbox(
+                            &mut self.settings.lock_on_session_lock,
+                            "Lock when the screen locks or the computer sleeps",
+                        )
+                        .on_hover_text(
+                            "Saves and logs out, so the password is needed again afterwards",
+                        )
+                        .changed();
+                    ui.add_enabled_ui(capture_protection::SUPPORTED, |ui| {
+                        settings_changed |= ui
+                            .checkbox(
+                                &mut self.settings.block_screen_capture,
+                                "Hide the window from screen capture and screen sharing",
+                            )
+                            .on_hover_text(
+                                "Screenshots, recordings and shared screens show the window \
+                                 as empty or black",
+                            )
+                            .on_disabled_hover_text("Only available on Windows and macOS")
+                            .changed();
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Clear copied secrets from the clipboard after:");
+                        egui::ComboBox::from_id_salt("clipboard_clear")
+                            .selected_text(settings::clipboard_clear_label(
+                                self.settings.clipboard_clear_secs,
+                            ))
+                            .show_ui(ui, |ui| {
+                                for secs in CLIPBOARD_CLEAR_CHOICES {
+                                    settings_changed |= ui
+                                        .selectable_value(
+                                            &mut self.settings.clipboard_clear_secs,
+                                            secs,
+                                            settings::clipboard_clear_label(secs),
+                                        )
+                                        .changed();
+                                }
+                            });
+                    });
+
+                    ui.separator();
+
+                    // Trusted time - the server is asked again once editing it ends
+                    ui.heading("Trusted Time");
+                    let response = ui
+                        .checkbox(
+                            &mut self.settings.verify_time,
+                            "Check the computer's clock against a time server",
+                        )
+                        .on_hover_text(
+                            "Note times and the login history then use the server's time, \
+                             even if the clock was changed",
+                        );
+                    settings_changed |= response.changed();
+                    check_time |= response.changed();
+                    ui.add_enabled_ui(self.settings.verify_time, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label("Time server:");
+                            let response = ui.add(
+                                egui::TextEdit::singleline(&mut self.settings.time_server)
+                                    .hint_text(trusted_time::DEFAULT_TIME_SERVER),
+                            );
+                            if response.lost_focus() {
+                                settings_changed = true;
+                                check_time = true;
+                            }
+                            if ui.button("Check Now").clicked() {
+                                check_time = true;
+                            }
+                        });
+                        match &time_check {
+                            Some(check) => {
+                                let text = format!(
+                                    "{} (checked {} with {}, network delay {} ms)",
+                                    check.describe_offset(),
+                                    check.format_checked_at(),
+                                    check.server,
+                                    check.round_trip.num_milliseconds()
+                                );
+                                if check.is_skewed() {
+                                    ui.colored_label(egui::Color32::from_rgb(255, 165, 0), text);
+                                } else {
+                                    ui.small(text);
+                                }
+                            }
+                            None if self.settings.verify_time => {
+                                ui.small("Not verified yet");
+                            }
+                            None => {}
+                        }
+                    });
+
+                    ui.separator();
+
+                    vault_action = shared_vault_ui::render_shared_vault_section(
+                        ui,
+                        &vault_status,
+                        &user.id,
+                        &mut self.shared_vault_username_input,
+                        &mut self.shared_vault_password_input,
+                    );
+
+                    ui.separator();
+
+                    // Export - default file names, saved once editing the template ends
+                    ui.heading("Export");
+                    ui.horizontal(|ui| {
+                        ui.label("File name:");
+                        let response = ui
+                            .text_edit_singleline(&mut self.settings.export_filename_template)
+                            .on_hover_text(format!(
+                                "Placeholders: {}",
+                                export::FILENAME_PLACEHOLDERS.join(" ")
+                            ));
+                        settings_changed |= response.lost_focus();
+                    });
+                    if let Some(note) = self
+                        .selected_note_id
+                        .as_ref()
+                        .and_then(|id| self.notes.get(id))
+                    {
+                        ui.small(format!(
+                            "Example: {}",
+                            export::render_file_name(
+                                &self.settings.export_filename_template,
+                                note,
+                                "txt"
+                            )
+                        ));
+                    }
+
+                    if ui
+                        .button("Publish as Website…")
+                        .on_hover_text("Write selected notes as a static HTML site")
+                        .clicked()
+                    {
+                        open_site_export = true;
+                    }
+
+                    // Export hook - opt-in, the command is saved once editing ends
+                    settings_changed |= ui
+                        .checkbox(
+                            &mut self.settings.export_hook.enabled,
+                            "Run a command after each export",
+                        )
+                        .on_hover_text(
+                            "Also after saving attachments, shortcuts, settings, link graphs \
+                             and migration bundles",
+                        )
+                        .changed();
+                    ui.add_enabled_ui(self.settings.export_hook.enabled, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label("Command:");
+                            let response = ui.add(
+                                egui::TextEdit::singleline(&mut self.settings.export_hook.command)
+                                    .hint_text("e.g. /usr/local/bin/backup.sh --quiet"),
+                            );
+                            settings_changed |= response.lost_focus();
+                        });
+                        ui.small(format!(
+                            "The exported file's path is added as the last argument. The \
+                             command runs with your permissions but without a shell, and is \
+                             stopped after {} seconds. It is not included in exported \
+                             settings.",
+                            HOOK_TIMEOUT.as_secs()
+                        ));
+                    });
+
+                    ui.separator();
+
+                    // Troubleshooting - log verbosity and viewer
+                    ui.heading("Troubleshooting");
+                    ui.horizontal(|ui| {
+                        ui.label("Log level:");
+                        egui::ComboBox::from_id_salt("log_level")
+                            .selected_text(self.settings.log_level.label())
+                            .show_ui(ui, |ui| {
+                                for level in LogLevel::ALL {
+                                    if ui
+                                        .selectable_value(
+                                            &mut self.settings.log_level,
+                                            level,
+                                            level.label(),
+                                        )
+                                        .changed()
+                                    {
+                                        settings_changed = true;
+                                    }
+                                }
+                            });
+                    });
+
+                    ui.horizontal(|ui| {
+                        if ui.button("Open Log Viewer").clicked() {
+                            open_log_viewer = true;
+                        }
+                        if ui.button("Startup Profile").clicked() {
+                            open_startup_profile = true;
+                        }
+                        if ui
+                            .button("Run Health Check")
+                            .on_hover_text(
+                                "Check the vault's directories, permissions, metadata, \
+                                 orphaned files and search index",
+                            )
+                            .clicked()
+                        {
+                            run_health_check = true;
+                        }
+                    });
+                    ui.small(format!("Log files: {}", logging::log_dir().display()));
+
+                    ui.separator();
+
+                    // Migration - notes are hardware-bound and can't simply be copied
+                    ui.heading("Migration");
+                    ui.horizontal(|ui| {
+                        if ui.button("Move to Another Computer").clicked() {
+                            open_migration_wizard = true;
+                        }
+                        if ui
+                            .button("Open Snapshot Read-Only…")
+                            .on_hover_text(
+                                "Check what a migration bundle contains before restoring it",
+                            )
+                            .clicked()
+                        {
+                            open_snapshot_viewer = true;
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        if ui
+                            .button("Export Settings…")
+                            .on_hover_text(
+                                "Preferences, shortcuts and smart filters, without notes",
+                            )
+                            .clicked()
+                        {
+                            settings_transfer = Some(SettingsTransferDialog::export());
+                        }
+                        if ui.button("Import Settings…").clicked() {
+                            settings_transfer = Some(SettingsTransferDialog::import());
+                        }
+                    });
 
                     ui.separator();
 
@@ -81,12 +753,96 @@ impl NotesApp {
             });
 
         // Handle button actions outside the window closure
+        if settings_changed {
+            logging::set_level(self.settings.log_level);
+            self.save_settings();
+        }
+
+        if check_time {
+            self.time_verifier = None;
+        }
+
+        if algorithm_changed {
+            self.start_algorithm_change(self.settings.encryption_algorithm);
+        }
+
+        if let Some(enable) = two_person_action {
+            self.open_two_person_dialog(enable);
+        }
+
+        if quota_changed {
+            self.save_settings();
+            self.check_storage_quota(true);
+        }
+
+        if retention_changed {
+            self.save_settings();
+            self.purge_expired_trash();
+        }
+
+        if pin_limit_changed {
+            self.save_settings();
+            self.enforce_pin_limit();
+        }
+
+        if let Some(action) = vault_action {
+            self.handle_shared_vault_action(action);
+        }
+
+        if empty_trash {
+            self.delete_notes_permanently(&trashed_ids);
+        }
+
+        if open_log_viewer {
+            self.show_log_viewer = true;
+        }
+
+        if open_startup_profile {
+            self.show_startup_profile = true;
+        }
+
+        if run_health_check {
+            self.run_health_check();
+        }
+
+        if open_site_export {
+            self.open_site_export();
+        }
+
+        if settings_transfer.is_some() {
+            self.settings_transfer_dialog = settings_transfer;
+        }
+
+        if open_migration_wizard {
+            self.migration_wizard.reset();
+            self.show_migration_wizard = true;
+        }
+
+        if open_snapshot_viewer {
+            self.snapshot_viewer.reset();
+            self.show_snapshot_viewer = true;
+        }
+
+        if refresh_storage {
+            self.storage_report = None;
+        }
+
+        if export_keymap {
+            self.export_keymap();
+        }
+
+        if import_keymap {
+            self.import_keymap();
+        }
+
         if close_settings {
             self.show_user_settings = false;
         }
 
         if change_password {
             self.show_change_password_dialog = true;
+            // May still describe an undone two-person unlock change
+            self.password_change_error = None;
         }
 
         if delete_account {
@@ -158,7 +914,8 @@ impl NotesApp {
                     ui.add_space(15.0);
 
                     // Validation logic for enabling submit button
-                    let can_submit = !self.old_password_input.is_empty()
+                    let can_submit = self.reencrypt_job.is_none()
+                        && !self.old_password_input.is_empty()
                         && !self.new_password_input.is_empty()
                         && self.new_password_input.len() >= 6
                         && self.new_password_input == self.confirm_new_password_input;
@@ -194,18 +951,24 @@ impl NotesApp {
                         ui.colored_label(egui::Color32::YELLOW, "Passwords do not match");
                     }
 
+                    if let Some(error) = &self.password_change_error {
+                        ui.add_space(10.0);
+                        ui.colored_label(egui::Color32::from_rgb(255, 100, 100), error);
+                    }
+
                     ui.add_space(10.0);
                 });
             });
 
-        // Handle actions outside the window closure
+        // Handle actions outside the window closure; the dialog is closed
+        // once the password change has finished
         if submit_change {
             self.handle_password_change();
-            close_dialog = true;
         }
 
-        if close_dialog {
+        if close_dialog || !self.show_change_password_dialog {
             self.show_change_password_dialog = false;
+            self.password_change_error = None;
             self.old_password_input.clear();
             self.new_password_input.clear();
             self.confirm_new_password_input.clear();
@@ -291,50 +1054,272 @@ impl NotesApp {
         }
     }
 
-    /// Handles the password change operation.
+    /// Renders the in-app log viewer.
     ///
-    /// Coordinates the password change process across multiple systems:
-    /// 1. Updates the cryptographic manager with new password
-    /// 2. Updates the user manager's password hash
-    /// 3. Re-initializes encryption with the new password
+    /// Shows the most recent log records kept in memory, filtered by a
+    /// minimum severity. Useful for troubleshooting without having to
+    /// locate the log file on disk. The records never contain note content
+    /// or credentials, so they can be copied into bug reports as-is.
     ///
-    /// This ensures that both authentication and encryption systems
-    /// are updated consistently. If any step fails, appropriate error
-    /// messages are logged.
-    ///
-    /// # Security Considerations
+    /// # Arguments
     ///
-    /// - Old password is verified before making changes
-    /// - New password is validated for strength requirements
-    /// - Encryption keys are re-derived with the new password
-    /// - All password hashes are updated atomically
-    pub fn handle_password_change(&mut self) {
-        if let (Some(ref mut crypto_manager), Some(ref user)) =
-            (&mut self.crypto_manager, &self.current_user)
-        {
-            match crypto_manager.change_password(
-                &self.old_password_input,
-                &self.new_password_input,
-                &user.id,
-            ) {
-                Ok(_) => {
-                    // Also update the user manager
-                    if let Some(ref mut user_manager) = self.user_manager {
-                        let _ = user_manager.change_password(
-                            &user.username,
-                            &self.old_password_input,
-                            &self.new_password_input,
-                        );
+    /// * `ctx` - The egui context for rendering
+    pub fn render_log_viewer(&mut self, ctx: &egui::Context) {
+        if !self.show_log_viewer {
+            return;
+        }
+
+        let entries = logging::recent_entries();
+        let min_level = self.log_viewer_level.to_filter();
+
+        egui::Window::new("Log Viewer")
+            .open(&mut self.show_log_viewer)
+            .default_width(700.0)
+            .default_height(400.0)
+            .show(ctx, |ui| {
+                let visible: Vec<_> = entries
+                    .iter()
+                    .filter(|entry| entry.level <= min_level)
+                    .collect();
+
+                ui.horizontal(|ui| {
+                    ui.label("Show:");
+                    egui::ComboBox::from_id_salt("log_viewer_level")
+                        .selected_text(self.log_viewer_level.label())
+                        .show_ui(ui, |ui| {
+                            for level in LogLevel::ALL {
+                                ui.selectable_value(
+                                    &mut self.log_viewer_level,
+                                    level,
+                                    level.label(),
+                                );
+                            }
+                        });
+
+                    if ui.button("Copy").clicked() {
+                        let text = visible
+                            .iter()
+                            .map(|entry| {
+                                format!(
+                                    "{} {:<5} [{}] {}",
+                                    entry.timestamp, entry.level, entry.target, entry.message
+                                )
+                            })
+                            .collect::<Vec<_>>()
+                            .join("\n");
+                        ui.ctx().copy_text(text);
+                    }
+
+                    if ui.button("Clear").clicked() {
+                        logging::clear_entries();
                     }
-                    println!("Password changed successfully!");
+
+                    ui.small(format!("{} of {} records", visible.len(), entries.len()));
+                });
+
+                ui.separator();
+
+                egui::ScrollArea::vertical()
+                    .auto_shrink([false, false])
+                    .stick_to_bottom(true)
+                    .show(ui, |ui| {
+                        for entry in visible {
+                            let color = match entry.level {
+                                log::Level::Error => egui::Color32::from_rgb(255, 100, 100),
+                                log::Level::Warn => egui::Color32::from_rgb(255, 165, 0),
+                                log::Level::Info => egui::Color32::LIGHT_GRAY,
+                                log::Level::Debug | log::Level::Trace => egui::Color32::GRAY,
+                            };
+                            ui.colored_label(
+                                color,
+                                egui::RichText::new(format!(
+                                    "{} {:<5} {}",
+                                    entry.timestamp, entry.level, entry.message
+                                ))
+                                .monospace(),
+                            );
+                        }
+                    });
+            });
+    }
+
+    /// Renders the startup profile window.
+    ///
+    /// Lists how long loading the users, the first frame, verifying the
+    /// password, deriving the key and loading the notes took, each time
+    /// they ran since the launch. The same timings are in the log file.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The egui context for rendering
+    pub fn render_startup_profile(&mut self, ctx: &egui::Context) {
+        if !self.show_startup_profile {
+            return;
+        }
+
+        let spans = profiling::spans();
+        let longest = spans
+            .iter()
+            .map(|span| span.duration.as_secs_f32())
+            .fold(0.0, f32::max);
+
+        egui::Window::new("Startup Profile")
+            .open(&mut self.show_startup_profile)
+            .default_width(500.0)
+            .show(ctx, |ui| {
+                if spans.is_empty() {
+                    ui.label("Nothing measured yet.");
+                    return;
                 }
-                Err(e) => {
-                    eprintln!("Failed to change password: {}", e);
+
+                if ui.button("Copy").clicked() {
+                    let text = spans
+                        .iter()
+                        .map(|span| {
+                            format!(
+                                "{:<24} {:>8.2} s {:>10.1} ms",
+                                span.name,
+                                span.start.as_secs_f64(),
+                                span.duration.as_secs_f64() * 1000.0
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    ui.ctx().copy_text(text);
                 }
+                ui.separator();
+
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    egui::Grid::new("startup_profile")
+                        .num_columns(4)
+                        .striped(true)
+                        .show(ui, |ui| {
+                            ui.strong("Step");
+                            ui.strong("Started");
+                            ui.strong("Duration");
+                            ui.end_row();
+
+                            for span in &spans {
+                                ui.label(span.name);
+                                ui.label(format!("{:.2} s", span.start.as_secs_f64()));
+                                ui.label(format!("{:.1} ms", span.duration.as_secs_f64() * 1000.0));
+                                let fraction = if longest > 0.0 {
+                                    span.duration.as_secs_f32() / longest
+                                } else {
+                                    0.0
+                                };
+                                ui.add(egui::ProgressBar::new(fraction).desired_width(120.0));
+                                ui.end_row();
+                            }
+                        });
+                });
+                ui.small("Started counts from the launch of the application.");
+            });
+    }
+
+    /// Starts changing the current user's password.
+    ///
+    /// The password is stored in two places: the crypto manager's
+    /// `auth.hash`, whose password also derives the encryption key, and the
+    /// user manager's `users.json`, checked at login. Both must change
+    /// together, and since the key changes, all data must be re-encrypted
+    /// with it. The steps are:
+    ///
+    /// 1. Check everything that can be checked without changing anything:
+    ///    the current password against both stores, the new password's
+    ///    length, and that no notes are loading, unreadable or open in
+    ///    another window
+    /// 2. Derive the new key, keeping the old one for decryption, and
+    ///    re-encrypt notes and attachments on a `ReencryptJob`
+    /// 3. Re-encrypt settings and the journal and re-wrap the shared vault
+    ///    key, see `finish_password_change`
+    /// 4. Write the new `auth.hash`, then the new `users.json`
+    ///
+    /// If any step after 1 fails or the job is cancelled, the written hash
+    /// is restored and the data is re-encrypted with the old key again. The
+    /// error is shown in the dialog, which stays open until the change is
+    /// done.
+    pub fn handle_password_change(&mut self) {
+        match self.start_password_change() {
+            Ok(()) => self.password_change_error = None,
+            Err(e) => {
+                log::error!("Failed to change password: {}", e);
+                self.password_change_error = Some(e.to_string());
             }
         }
     }
 
+    /// Performs steps 1 and 2 described in `handle_password_change`.
+    fn start_password_change(&mut self) -> anyhow::Result<()> {
+        let (Some(user), Some(user_manager)) = (self.current_user.clone(), &self.user_manager)
+        else {
+            anyhow::bail!("Not logged in");
+        };
+        let old_password = self.old_password_input.clone();
+        let new_password = self.new_password_input.clone();
+
+        // Step 1: nothing has been changed if any of these fail
+        user_manager
+            .authenticate(&user.username, &old_password)
+            .map_err(|_| anyhow::anyhow!("Current password is incorrect"))?;
+        if new_password.len() < 6 {
+            anyhow::bail!("New password must be at least 6 characters long");
+        }
+        if new_password.len() > 128 {
+            anyhow::bail!("New password must be less than 128 characters");
+        }
+        self.check_key_change_allowed(&user.id)?;
+        // Unsaved edits are re-encrypted by the job, so write them first
+        self.save_notes();
+
+        // Step 2
+        let Some(crypto_manager) = &self.crypto_manager else {
+            anyhow::bail!("Not logged in");
+        };
+        self.reencrypt_job = Some(ReencryptJob::change_password(
+            &user.id,
+            crypto_manager.clone(),
+            old_password,
+            new_password,
+        ));
+        Ok(())
+    }
+
+    /// Checks that the vault can be re-encrypted with a new key: no other
+    /// job is running, every note is loaded and readable, and no note is
+    /// open in another window.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - The current user
+    ///
+    /// # Returns
+    ///
+    /// * `anyhow::Result<()>` - Ok if the key can change, Err saying why not
+    pub fn check_key_change_allowed(&self, user_id: &str) -> anyhow::Result<()> {
+        if self.reencrypt_job.is_some() {
+            anyhow::bail!("The vault is being re-encrypted, try again when done");
+        }
+        if self.note_loader.is_some() || self.import_job.is_some() {
+            anyhow::bail!("Notes are still being loaded or imported, try again when done");
+        }
+        if !self.unreadable_note_ids.is_empty() {
+            anyhow::bail!(
+                "{} note(s) could not be decrypted and would be lost with the old \
+                 key, see Security Info",
+                self.unreadable_note_ids.len()
+            );
+        }
+        if !self
+            .storage_manager
+            .notes_locked_elsewhere(user_id, &self.instance_id)
+            .is_empty()
+        {
+            anyhow::bail!("Notes are open in another window, close it first");
+        }
+        Ok(())
+    }
+
     /// Handles the complete account deletion process.
     ///
     /// Performs a comprehensive cleanup of all user data:
@@ -363,11 +1348,20 @@ impl NotesApp {
     pub fn handle_account_deletion(&mut self) {
         if let Some(ref user) = self.current_user.clone() {
             // Delete user data from storage
-            let _ = self.storage_manager.delete_user_data(&user.id);
+            if let Err(e) = self.storage_manager.delete_user_data(&user.id) {
+                log::error!("Failed to delete the user's notes: {}", e);
+            }
+
+            // Leave the shared vault, which is deleted with its last member
+            if let Err(e) = SharedVault::remove_member(&self.storage_manager, &user.id) {
+                log::error!("Failed to leave the shared vault: {}", e);
+            }
 
             // Delete cryptographic data and keys
             if let Some(ref crypto_manager) = self.crypto_manager {
-                let _ = crypto_manager.delete_user_crypto_data(&user.id);
+                if let Err(e) = crypto_manager.delete_user_crypto_data(&user.id) {
+                    log::error!("Failed to delete the user's keys: {}", e);
+                }
             }
 
             // Delete user account from user manager
@@ -375,10 +1369,47 @@ impl NotesApp {
                 let _ = user_manager.delete_user(&user.username);
             }
 
-            println!("Account deleted successfully");
+            log::info!("Account deleted successfully");
 
             // Immediately logout to clear all session data
             self.logout();
         }
     }
 }
+
+/// Renders the storage usage of one vault in the settings window.
+///
+/// # Arguments
+///
+/// * `ui` - The settings window UI
+/// * `label` - Name of the vault
+/// * `usage` - The measured usage
+fn render_vault_usage(ui: &mut egui::Ui, label: &str, usage: &VaultUsage) {
+    ui.label(format!(
+        "{}: {} {}, {} of content, {} on disk",
+        label,
+        usage.note_count,
+        if usage.note_count == 1 {
+            "note"
+        } else {
+            "notes"
+        },
+        storage_report::format_size(usage.content_bytes()),
+        storage_report::format_size(usage.file_bytes()),
+    ));
+
+    let mut details = Vec::new();
+    if let Some(ratio) = usage.disk_ratio() {
+        details.push(format!("on disk {:.0}% of content", ratio * 100.0));
+    }
+    if let Some(share) = usage.attachment_share() {
+        details.push(format!(
+            "attachments {} ({:.0}% of disk)",
+            storage_report::format_size(usage.attachment_file_bytes),
+            share * 100.0
+        ));
+    }
+    if !details.is_empty() {
+        ui.small(format!("    {}", details.join(", ")));
+    }
+}
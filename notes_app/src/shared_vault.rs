@@ -0,0 +1,317 @@
+//! # Shared Vault Module
+//!
+//! A vault of notes shared by the users of this computer, e.g. a family or
+//! a small team using the same account of the operating system. Private
+//! vaults stay separate: the shared notes are encrypted with a random data
+//! key of their own, and that key is stored once per member, encrypted
+//! ("wrapped") with the member's personal key. Every member opens the
+//! vault with their own password, and nobody learns anyone's password.
+//!
+//! ```text
+//! ~/.config/secure_notes/shared/
+//! ├── members/
+//! │   └── <user_id>.key    (data key wrapped with the member's key)
+//! ├── notes/               (same layout as a user directory)
+//! ├── attachments/
+//! └── locks/
+//! ```
+//!
+//! Adding a member needs that user's password once, typed in by them, to
+//! derive their key. Removing a member deletes their wrapped key; the data
+//! key itself is not replaced, so a removed member who kept a copy of it
+//! could still read notes written later.
+//!
+//! Other members may add, edit or delete shared notes while this instance
+//! runs, so shared notes are never pruned by a full save. Only notes that
+//! changed since they were loaded or last saved are written.
+
+use crate::crypto::{AeadAlgorithm, BlobContext, CryptoManager};
+use crate::note::Note;
+use crate::secure_fs;
+use crate::storage::{StorageManager, SHARED_VAULT_OWNER};
+use anyhow::{anyhow, bail, Result};
+use chacha20poly1305::aead::{rand_core::RngCore, OsRng};
+use secure_core::Key;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+
+/// The unlocked shared vault of a logged-in member.
+pub struct SharedVault {
+    /// The data key, wrapped for new members
+    key: Key,
+    /// Crypto manager for the shared notes, using the data key
+    crypto: CryptoManager,
+    /// Fingerprints of the shared notes as they are on disk, by note ID
+    saved_versions: HashMap<String, u64>,
+}
+
+impl SharedVault {
+    /// Checks whether a shared vault was created on this computer.
+    ///
+    /// # Arguments
+    ///
+    /// * `storage` - The storage manager
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - True if the vault has at least one member
+    pub fn exists(storage: &StorageManager) -> bool {
+        !Self::member_ids(storage).is_empty()
+    }
+
+    /// Lists the IDs of the users the vault is shared with.
+    ///
+    /// # Arguments
+    ///
+    /// * `storage` - The storage manager
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<String>` - Member user IDs, sorted
+    pub fn member_ids(storage: &StorageManager) -> Vec<String> {
+        let Ok(entries) = fs::read_dir(members_dir(storage)) else {
+            return Vec::new();
+        };
+
+        let mut ids: Vec<String> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().and_then(|e| e.to_str()) == Some("key"))
+            .filter_map(|path| Some(path.file_stem()?.to_str()?.to_string()))
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    /// Creates the shared vault with a new data key and the user as its
+    /// first member.
+    ///
+    /// # Arguments
+    ///
+    /// * `storage` - The storage manager
+    /// * `user_id` - The creating user
+    /// * `user_crypto` - The creating user's crypto manager
+    /// * `algorithm` - Algorithm used for newly encrypted shared notes
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self>` - The unlocked vault, or error if a vault exists
+    ///   already or the key cannot be written
+    pub fn create(
+        storage: &StorageManager,
+        user_id: &str,
+        user_crypto: &CryptoManager,
+        algorithm: AeadAlgorithm,
+    ) -> Result<Self> {
+        if Self::exists(storage) {
+            bail!("A shared vault already exists on this computer");
+        }
+
+        let mut key = Key::default();
+        OsRng.fill_bytes(&mut key);
+        let vault = Self {
+            key,
+            crypto: CryptoManager::with_key(key, algorithm),
+            saved_versions: HashMap::new(),
+        };
+        vault.write_wrapped_key(storage, user_id, user_crypto, &key)?;
+
+        log::info!("Created shared vault for user {}", user_id);
+        Ok(vault)
+    }
+
+    /// Unlocks the shared vault with a member's key.
+    ///
+    /// # Arguments
+    ///
+    /// * `storage` - The storage manager
+    /// * `user_id` - The logged-in user
+    /// * `user_crypto` - The user's crypto manager
+    /// * `algorithm` - Algorithm used for newly encrypted shared notes
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Option<Self>>` - The unlocked vault, None if the user is
+    ///   not a member, or error if the wrapped key cannot be decrypted
+    pub fn open(
+        storage: &StorageManager,
+        user_id: &str,
+        user_crypto: &CryptoManager,
+        algorithm: AeadAlgorithm,
+    ) -> Result<Option<Self>> {
+        let wrapped = match fs::read(member_key_path(storage, user_id)) {
+            Ok(wrapped) => wrapped,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        let key = user_crypto.decrypt(&wrapped, BlobContext::VaultKey { user_id })?;
+        if key.len() != 32 {
+            return Err(anyhow!("Invalid shared vault key"));
+        }
+        let key = Key::clone_from_slice(&key);
+
+        Ok(Some(Self {
+            key,
+            crypto: CryptoManager::with_key(key, algorithm),
+            saved_versions: HashMap::new(),
+        }))
+    }
+
+    /// Returns the crypto manager for the shared notes and attachments.
+    pub fn crypto(&self) -> &CryptoManager {
+        &self.crypto
+    }
+
+    /// Selects the algorithm used for newly encrypted shared notes.
+    ///
+    /// # Arguments
+    ///
+    /// * `algorithm` - The algorithm, following the user's setting
+    pub fn set_algorithm(&mut self, algorithm: AeadAlgorithm) {
+        self.crypto.set_algorithm(algorithm);
+    }
+
+    /// Wraps the data key for a user, adding them as a member.
+    ///
+    /// Also used to re-wrap the key for an existing member whose own key
+    /// changed, e.g. after a password change.
+    ///
+    /// # Arguments
+    ///
+    /// * `storage` - The storage manager
+    /// * `user_id` - The user to add
+    /// * `user_crypto` - A crypto manager initialized with that user's key
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - Ok if the wrapped key was written
+    pub fn add_member(
+        &self,
+        storage: &StorageManager,
+        user_id: &str,
+        user_crypto: &CryptoManager,
+    ) -> Result<()> {
+        self.write_wrapped_key(storage, user_id, user_crypto, &self.key)?;
+        log::info!("Shared vault key wrapped for user {}", user_id);
+        Ok(())
+    }
+
+    /// Removes a member by deleting their wrapped key.
+    ///
+    /// Once the last member is gone nobody can decrypt the vault anymore,
+    /// so its notes are deleted as well.
+    ///
+    /// # Arguments
+    ///
+    /// * `storage` - The storage manager
+    /// * `user_id` - The member to remove
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - Ok if the member was removed or wasn't one
+    pub fn remove_member(storage: &StorageManager, user_id: &str) -> Result<()> {
+        let key_path = member_key_path(storage, user_id);
+        if key_path.exists() {
+            secure_fs::secure_remove_file(&key_path)?;
+            log::info!("Removed user {} from the shared vault", user_id);
+        }
+
+        let vault_dir = storage.shared_vault_dir();
+        if !Self::exists(storage) && vault_dir.exists() {
+            secure_fs::secure_remove_dir_all(&vault_dir)?;
+            log::info!("Deleted the shared vault, it has no members left");
+        }
+        Ok(())
+    }
+
+    /// Records a shared note as it was read from disk.
+    ///
+    /// # Arguments
+    ///
+    /// * `note` - The loaded note
+    pub fn note_loaded(&mut self, note: &Note) {
+        self.saved_versions
+            .insert(note.id.clone(), fingerprint(note));
+    }
+
+    /// Writes the shared notes that changed since they were loaded or saved.
+    ///
+    /// # Arguments
+    ///
+    /// * `storage` - The storage manager
+    /// * `notes` - All shared notes in memory
+    /// * `locked` - IDs of notes locked by another instance, not written
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - Ok if every changed note was written
+    pub fn save_notes<'a>(
+        &mut self,
+        storage: &StorageManager,
+        notes: impl IntoIterator<Item = &'a Note>,
+        locked: &HashSet<String>,
+    ) -> Result<()> {
+        for note in notes {
+            let version = fingerprint(note);
+            if locked.contains(&note.id) || self.saved_versions.get(&note.id) == Some(&version) {
+                continue;
+            }
+            storage.save_user_note(SHARED_VAULT_OWNER, note, &self.crypto)?;
+            self.saved_versions.insert(note.id.clone(), version);
+        }
+        Ok(())
+    }
+
+    /// Deletes a shared note and its attachments from disk.
+    ///
+    /// # Arguments
+    ///
+    /// * `storage` - The storage manager
+    /// * `note_id` - ID of the note to delete
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - Ok if the files were deleted or didn't exist
+    pub fn delete_note(&mut self, storage: &StorageManager, note_id: &str) -> Result<()> {
+        storage.delete_user_note(SHARED_VAULT_OWNER, note_id)?;
+        self.saved_versions.remove(note_id);
+        Ok(())
+    }
+
+    /// Encrypts the data key with a user's key and writes it.
+    fn write_wrapped_key(
+        &self,
+        storage: &StorageManager,
+        user_id: &str,
+        user_crypto: &CryptoManager,
+        key: &Key,
+    ) -> Result<()> {
+        let wrapped = user_crypto.encrypt(key, BlobContext::VaultKey { user_id })?;
+        fs::create_dir_all(members_dir(storage))?;
+        secure_fs::write_private_file(&member_key_path(storage, user_id), wrapped)?;
+        Ok(())
+    }
+}
+
+/// Returns the directory holding the wrapped keys of the members.
+fn members_dir(storage: &StorageManager) -> PathBuf {
+    storage.shared_vault_dir().join("members")
+}
+
+/// Returns the path of a member's wrapped key.
+fn member_key_path(storage: &StorageManager, user_id: &str) -> PathBuf {
+    members_dir(storage).join(format!("{}.key", user_id))
+}
+
+/// Hashes everything saved of a note, to tell whether it changed.
+fn fingerprint(note: &Note) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    serde_json::to_string(note)
+        .unwrap_or_default()
+        .hash(&mut hasher);
+    hasher.finish()
+}
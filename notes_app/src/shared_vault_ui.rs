@@ -0,0 +1,257 @@
+//! # Shared Vault UI Module
+//!
+//! The "Shared Vault" section of the settings window: creating the vault,
+//! listing its members, adding a member (who types in their own password
+//! to derive their key) and removing members. The section is drawn inside
+//! the settings window, which borrows the app, so it works on a snapshot
+//! of the vault state and returns the chosen action instead of running it.
+
+use crate::app::NotesApp;
+use crate::crypto::CryptoManager;
+use crate::shared_vault::SharedVault;
+use anyhow::{anyhow, bail, Result};
+use eframe::egui;
+
+/// State of the shared vault as shown in the settings window.
+pub enum SharedVaultStatus {
+    /// No shared vault exists on this computer
+    Missing,
+    /// A vault exists, but the current user is not a member or it could
+    /// not be unlocked
+    Locked,
+    /// The vault is unlocked; members as (user ID, username)
+    Open(Vec<(String, String)>),
+}
+
+/// Action chosen in the shared vault section.
+pub enum SharedVaultAction {
+    /// Create the vault with the current user as its first member
+    Create,
+    /// Add the user whose name and password were entered
+    AddMember,
+    /// Remove a member, or leave the vault if it is the current user
+    RemoveMember(String),
+}
+
+impl NotesApp {
+    /// Takes a snapshot of the shared vault for the settings window.
+    ///
+    /// # Returns
+    ///
+    /// * `SharedVaultStatus` - Whether the vault exists and its members
+    pub fn shared_vault_status(&self) -> SharedVaultStatus {
+        if self.shared_vault.is_some() {
+            let members = SharedVault::member_ids(&self.storage_manager)
+                .into_iter()
+                .map(|id| {
+                    let name = self
+                        .user_manager
+                        .as_ref()
+                        .and_then(|manager| manager.username_of(&id))
+                        .unwrap_or("(deleted user)")
+                        .to_string();
+                    (id, name)
+                })
+                .collect();
+            SharedVaultStatus::Open(members)
+        } else if SharedVault::exists(&self.storage_manager) {
+            SharedVaultStatus::Locked
+        } else {
+            SharedVaultStatus::Missing
+        }
+    }
+
+    /// Runs an action chosen in the shared vault section.
+    ///
+    /// # Arguments
+    ///
+    /// * `action` - The action to run
+    pub fn handle_shared_vault_action(&mut self, action: SharedVaultAction) {
+        let result = match action {
+            SharedVaultAction::Create => self.create_shared_vault(),
+            SharedVaultAction::AddMember => self.add_shared_vault_member(),
+            SharedVaultAction::RemoveMember(user_id) => self.remove_shared_vault_member(&user_id),
+        };
+
+        self.show_status(match result {
+            Ok(message) => message,
+            Err(e) => {
+                log::error!("Shared vault action failed: {}", e);
+                format!("Shared vault: {}", e)
+            }
+        });
+    }
+
+    /// Creates the shared vault with the current user as its only member.
+    fn create_shared_vault(&mut self) -> Result<String> {
+        let (Some(crypto_manager), Some(user)) = (&self.crypto_manager, &self.current_user) else {
+            bail!("Not logged in");
+        };
+
+        let vault = SharedVault::create(
+            &self.storage_manager,
+            &user.id,
+            crypto_manager,
+            self.settings.encryption_algorithm,
+        )?;
+        self.shared_vault = Some(vault);
+        Ok("Shared vault created".to_string())
+    }
+
+    /// Adds the user whose credentials were entered to the shared vault.
+    ///
+    /// Derives that user's key, which takes as long as their login.
+    fn add_shared_vault_member(&mut self) -> Result<String> {
+        let username = self.shared_vault_username_input.trim().to_string();
+        let password = std::mem::take(&mut self.shared_vault_password_input);
+        let (Some(vault), Some(user_manager)) = (&self.shared_vault, &self.user_manager) else {
+            bail!("The shared vault is locked");
+        };
+
+        let user = user_manager.authenticate(&username, &password)?;
+        if SharedVault::member_ids(&self.storage_manager).contains(&user.id) {
+            bail!("{} is already a member", user.username);
+        }
+
+        let mut member_crypto = CryptoManager::new();
+        member_crypto
+            .initialize_for_user(&user.id, &password)
+            .map_err(|e| anyhow!("Could not derive the key of {}: {}", user.username, e))?;
+        if member_crypto.awaiting_second_passphrase() {
+            bail!(
+                "{} uses two-person unlock and can't be added with the password alone",
+                user.username
+            );
+        }
+        vault.add_member(&self.storage_manager, &user.id, &member_crypto)?;
+
+        self.shared_vault_username_input.clear();
+        Ok(format!("{} can now open the shared vault", user.username))
+    }
+
+    /// Removes a member from the shared vault.
+    ///
+    /// Leaving the vault also drops the shared notes from the note list.
+    fn remove_shared_vault_member(&mut self, user_id: &str) -> Result<String> {
+        let Some(user) = &self.current_user else {
+            bail!("Not logged in");
+        };
+        let leaving = user.id == user_id;
+
+        SharedVault::remove_member(&self.storage_manager, user_id)?;
+        if !leaving {
+            return Ok("Member removed from the shared vault".to_string());
+        }
+
+        if self
+            .selected_note_id
+            .as_ref()
+            .and_then(|id| self.notes.get(id))
+            .is_some_and(|note| note.shared)
+        {
+            self.release_note_lock();
+            self.selected_note_id = None;
+        }
+        let shared_ids: Vec<String> = self
+            .notes
+            .values()
+            .filter(|note| note.shared)
+            .map(|note| note.id.clone())
+            .collect();
+        for note_id in &shared_ids {
+            self.notes.remove(note_id);
+            self.search_index.note_removed(note_id);
+        }
+        self.shared_vault = None;
+        Ok("You left the shared vault".to_string())
+    }
+}
+
+/// Renders the shared vault section of the settings window.
+///
+/// # Arguments
+///
+/// * `ui` - The settings window UI
+/// * `status` - Snapshot of the vault, see `NotesApp::shared_vault_status`
+/// * `current_user_id` - ID of the logged-in user
+/// * `username_input` - Name of the user to add
+/// * `password_input` - Password of the user to add
+///
+/// # Returns
+///
+/// * `Option<SharedVaultAction>` - The action chosen this frame, if any
+pub fn render_shared_vault_section(
+    ui: &mut egui::Ui,
+    status: &SharedVaultStatus,
+    current_user_id: &str,
+    username_input: &mut String,
+    password_input: &mut String,
+) -> Option<SharedVaultAction> {
+    let mut action = None;
+
+    ui.heading("Shared Vault");
+    match status {
+        SharedVaultStatus::Missing => {
+            ui.label("Share notes with other users of this computer.");
+            ui.small(
+                "Each member opens the shared vault with their own password. \
+                 Your private notes stay private.",
+            );
+            if ui.button("Create Shared Vault").clicked() {
+                action = Some(SharedVaultAction::Create);
+            }
+        }
+        SharedVaultStatus::Locked => {
+            ui.label("A shared vault exists on this computer.");
+            ui.small("Ask one of its members to add you in their settings.");
+        }
+        SharedVaultStatus::Open(members) => {
+            ui.label("Members:");
+            for (id, name) in members {
+                ui.horizontal(|ui| {
+                    if id == current_user_id {
+                        ui.label(format!("• {} (you)", name));
+                        if members.len() > 1
+                            && ui
+                                .small_button("Leave")
+                                .on_hover_text("Shared notes are no longer shown to you")
+                                .clicked()
+                        {
+                            action = Some(SharedVaultAction::RemoveMember(id.clone()));
+                        }
+                    } else {
+                        ui.label(format!("• {}", name));
+                        if ui.small_button("Remove").clicked() {
+                            action = Some(SharedVaultAction::RemoveMember(id.clone()));
+                        }
+                    }
+                });
+            }
+
+            ui.add_space(4.0);
+            ui.label("Add a member (they enter their own password):");
+            ui.horizontal(|ui| {
+                ui.label("Username:");
+                ui.text_edit_singleline(username_input);
+            });
+            ui.horizontal(|ui| {
+                ui.label("Password:");
+                ui.add(egui::TextEdit::singleline(password_input).password(true));
+            });
+            let can_add = !username_input.trim().is_empty() && !password_input.is_empty();
+            if ui
+                .add_enabled(can_add, egui::Button::new("Add Member"))
+                .on_hover_text("Takes a few seconds, like logging in")
+                .clicked()
+            {
+                action = Some(SharedVaultAction::AddMember);
+            }
+            ui.small(
+                "Right-click a note and choose \"Move to Shared Vault\" to share it. \
+                 Removed members could still read notes they saw before.",
+            );
+        }
+    }
+
+    action
+}
@@ -0,0 +1,109 @@
+//! # Shell Open Module
+//!
+//! Opens folders in the operating system's file manager, used to show
+//! where an export was written, and `mailto:` links in the default mail
+//! app:
+//!
+//! - Windows: `explorer`
+//! - macOS: `open`
+//! - Other systems: `xdg-open`, which hands the folder to the desktop's
+//!   default file manager and the link to its default mail app
+//!
+//! Also keeps other programs the app starts from opening a console window
+//! on Windows.
+
+use std::ffi::OsStr;
+use std::io;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::thread;
+
+/// Program that opens a path with the desktop's default application.
+#[cfg(windows)]
+const OPENER: &str = "explorer";
+#[cfg(target_os = "macos")]
+const OPENER: &str = "open";
+#[cfg(not(any(windows, target_os = "macos")))]
+const OPENER: &str = "xdg-open";
+
+/// Opens a folder in the file manager.
+///
+/// Returns as soon as the opener was started; it is reaped on a background
+/// thread. Explorer exits with 1 even when it succeeded, so the exit status
+/// isn't checked.
+///
+/// # Arguments
+///
+/// * `folder` - The folder to open
+///
+/// # Returns
+///
+/// * `io::Result<()>` - Ok if the opener was started, Err if the folder
+///   doesn't exist or the opener isn't installed
+pub fn open_folder(folder: &Path) -> io::Result<()> {
+    if !folder.is_dir() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{} is not a folder", folder.display()),
+        ));
+    }
+
+    start_opener(folder.as_os_str())
+}
+
+/// Opens a `mailto:` link in the default mail app.
+///
+/// Like [`open_folder`], returns as soon as the opener was started.
+///
+/// # Arguments
+///
+/// * `url` - The `mailto:` link to open
+///
+/// # Returns
+///
+/// * `io::Result<()>` - Ok if the opener was started, Err if the link
+///   isn't a `mailto:` link or the opener isn't installed
+pub fn open_mailto(url: &str) -> io::Result<()> {
+    if !url.starts_with("mailto:") {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Not a mailto: link",
+        ));
+    }
+    start_opener(url.as_ref())
+}
+
+/// Starts the opener for a path or link and reaps it on a background thread.
+fn start_opener(target: &OsStr) -> io::Result<()> {
+    let mut child = Command::new(OPENER)
+        .arg(target)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| io::Error::new(e.kind(), format!("Could not start {}: {}", OPENER, e)))?;
+    thread::spawn(move || {
+        let _ = child.wait();
+    });
+    Ok(())
+}
+
+/// Keeps a program from opening a console window on Windows.
+///
+/// # Arguments
+///
+/// * `command` - The command about to be started
+#[cfg(windows)]
+pub fn hide_console(command: &mut Command) {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+    command.creation_flags(CREATE_NO_WINDOW);
+}
+
+/// Keeps a program from opening a console window on Windows.
+///
+/// # Arguments
+///
+/// * `command` - The command about to be started
+#[cfg(not(windows))]
+pub fn hide_console(_command: &mut Command) {}
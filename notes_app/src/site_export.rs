@@ -0,0 +1,364 @@
+//! # Site Export Module
+//!
+//! Renders selected notes into a static website that can be published
+//! while the vault itself stays encrypted:
+//!
+//! ```text
+//! site/
+//! ├── index.html             # Titles of all published notes
+//! └── notes/
+//!     └── groceries-1a2b3c4d.html
+//! ```
+//!
+//! Only the title, the Markdown content and the modification date of the
+//! selected notes are written; properties, comments, attachments and
+//! notebooks stay behind, and secrets notes can't be published. A
+//! `[[wiki link]]` to another published note becomes a link to its page;
+//! a link to a note that isn't published is shown as plain text.
+//!
+//! The pages are plain HTML with the style sheet inline, no scripts, so
+//! they can be opened from disk or served by any web server. The same
+//! rendering gives the standalone page of a note exported as HTML.
+
+use crate::markdown::{self, Block, Inline, ListMarker};
+use crate::note::Note;
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use chrono_tz::Europe::Zurich;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+/// Folder of the note pages inside the site.
+const NOTES_FOLDER: &str = "notes";
+
+/// Style sheet included in every page.
+const STYLE: &str = "body{font-family:system-ui,sans-serif;max-width:46em;margin:2em auto;\
+padding:0 1em;line-height:1.6;color:#222}a{color:#0b62a4}pre{background:#f4f4f4;\
+padding:.8em;overflow-x:auto}code{background:#f4f4f4;padding:0 .2em}blockquote{margin:0;\
+padding-left:1em;border-left:3px solid #ccc;color:#555}.meta{color:#777;font-size:.9em}\
+.item{margin:.2em 0}ul.index{padding-left:1.2em}footer{margin-top:3em;color:#999;\
+font-size:.8em}th{text-align:left;padding-right:1em}";
+
+/// Writes the site for the selected notes.
+///
+/// # Arguments
+///
+/// * `folder` - Empty or missing folder to write the site into
+/// * `site_title` - Title of the index page
+/// * `notes` - The notes to publish
+///
+/// # Returns
+///
+/// * `Result<usize>` - Number of note pages written, Err if the folder is
+///   not empty, a secrets note was passed or writing failed
+pub fn write_site(folder: &Path, site_title: &str, notes: &[&Note]) -> Result<usize> {
+    if notes.iter().any(|note| !note.kind.is_text()) {
+        return Err(anyhow!("Secrets notes can't be published"));
+    }
+    if folder.exists() && fs::read_dir(folder)?.next().is_some() {
+        return Err(anyhow!(
+            "{} is not empty, choose an empty folder",
+            folder.display()
+        ));
+    }
+    fs::create_dir_all(folder.join(NOTES_FOLDER))?;
+
+    let mut notes = notes.to_vec();
+    notes.sort_by_key(|note| note.title.to_lowercase());
+    let pages: HashMap<String, String> = notes
+        .iter()
+        .map(|note| (note.title.trim().to_lowercase(), page_name(note)))
+        .collect();
+
+    for note in &notes {
+        let path = folder.join(NOTES_FOLDER).join(page_name(note));
+        fs::write(path, render_note_page(note, site_title, &pages))?;
+    }
+    fs::write(folder.join("index.html"), render_index(site_title, &notes))?;
+    log::info!(
+        "Published {} notes as a website in {}",
+        notes.len(),
+        folder.display()
+    );
+    Ok(notes.len())
+}
+
+/// Returns the file name of a note's page, e.g. "groceries-1a2b3c4d.html".
+///
+/// The start of the note ID keeps pages of notes with the same title apart.
+fn page_name(note: &Note) -> String {
+    let mut slug = String::new();
+    for c in note.title.chars().flat_map(char::to_lowercase) {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+        if slug.len() >= 60 {
+            break;
+        }
+    }
+    let slug = slug.trim_end_matches('-');
+    let id: String = note.id.chars().take(8).collect();
+    if slug.is_empty() {
+        format!("note-{}.html", id)
+    } else {
+        format!("{}-{}.html", slug, id)
+    }
+}
+
+/// Renders the index page listing all published notes.
+fn render_index(site_title: &str, notes: &[&Note]) -> String {
+    let mut body = format!("<h1>{}</h1>\n<ul class=\"index\">\n", escape(site_title));
+    for note in notes {
+        let _ = writeln!(
+            body,
+            "<li><a href=\"{}/{}\">{}</a> <span class=\"meta\">{}</span></li>",
+            NOTES_FOLDER,
+            escape(&page_name(note)),
+            escape(&note.title),
+            note.modified_at_local().format("%d.%m.%Y")
+        );
+    }
+    body.push_str("</ul>\n");
+    page(site_title, &body, "Published")
+}
+
+/// Renders the page of one note.
+fn render_note_page(note: &Note, site_title: &str, pages: &HashMap<String, String>) -> String {
+    let mut body = format!(
+        "<p class=\"meta\"><a href=\"../index.html\">← {}</a></p>\n<h1>{}</h1>\n\
+         <p class=\"meta\">Updated {}</p>\n",
+        escape(site_title),
+        escape(&note.title),
+        note.modified_at_local().format("%d.%m.%Y %H:%M")
+    );
+    for block in markdown::parse(&note.content) {
+        render_block(&mut body, &block, pages);
+    }
+    page(
+        &format!("{} – {}", note.title, site_title),
+        &body,
+        "Published",
+    )
+}
+
+/// Renders a note as a standalone HTML page for the note export.
+///
+/// Unlike a published page, the metadata is included: the times, the
+/// notebook, the properties, links to the exported attachments and the
+/// entries of a secrets note, with their secrets in plain text like in
+/// the text export. Wiki links are shown as plain text.
+///
+/// # Arguments
+///
+/// * `note` - The note to export
+/// * `attachment_paths` - Exported attachments, relative to the page
+///
+/// # Returns
+///
+/// * `String` - The HTML document
+pub fn note_document(note: &Note, attachment_paths: &[String]) -> String {
+    let mut body = format!(
+        "<h1>{}</h1>\n<p class=\"meta\">Created {} · Modified {}</p>\n",
+        escape(&note.title),
+        note.format_created_time(),
+        note.format_modified_time()
+    );
+
+    let mut fields: Vec<(&str, String)> = Vec::new();
+    if let Some(notebook) = &note.notebook {
+        fields.push(("Notebook", escape(notebook)));
+    }
+    for (key, value) in &note.fields {
+        fields.push((key, escape(value)));
+    }
+    if !attachment_paths.is_empty() {
+        let links: Vec<String> = attachment_paths
+            .iter()
+            .map(|path| format!("<a href=\"{}\">{}</a>", escape(path), escape(path)))
+            .collect();
+        fields.push(("Attachments", links.join(", ")));
+    }
+    if !fields.is_empty() {
+        body.push_str("<table class=\"meta\">\n");
+        for (key, value) in fields {
+            let _ = writeln!(body, "<tr><th>{}</th><td>{}</td></tr>", escape(key), value);
+        }
+        body.push_str("</table>\n");
+    }
+
+    let no_pages = HashMap::new();
+    for block in markdown::parse(&note.content) {
+        render_block(&mut body, &block, &no_pages);
+    }
+    for entry in &note.secrets {
+        let _ = writeln!(
+            body,
+            "<h3>{}</h3>\n<table>\n<tr><th>Username</th><td>{}</td></tr>\n\
+             <tr><th>Secret</th><td><code>{}</code></td></tr>\n\
+             <tr><th>URL</th><td>{}</td></tr>\n</table>",
+            escape(&entry.label),
+            escape(&entry.username),
+            escape(&entry.secret),
+            escape(&entry.url)
+        );
+    }
+    page(&note.title, &body, "Exported")
+}
+
+/// Wraps a page body into an HTML document, with the date in the footer
+/// after `footer`, e.g. "Published".
+fn page(title: &str, body: &str, footer: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>{}</title>\n<style>{}</style>\n</head>\n<body>\n{}<footer>{} {}</footer>\n\
+         </body>\n</html>\n",
+        escape(title),
+        STYLE,
+        body,
+        footer,
+        Utc::now().with_timezone(&Zurich).format("%d.%m.%Y")
+    )
+}
+
+/// Appends the HTML of a Markdown block.
+fn render_block(html: &mut String, block: &Block, pages: &HashMap<String, String>) {
+    match block {
+        Block::Heading { level, text } => {
+            // The note title is the page's h1
+            let level = (level + 1).min(6);
+            let _ = writeln!(
+                html,
+                "<h{}>{}</h{}>",
+                level,
+                render_lines(text, pages),
+                level
+            );
+        }
+        Block::Paragraph(text) => {
+            let _ = writeln!(html, "<p>{}</p>", render_lines(text, pages));
+        }
+        Block::ListItem {
+            depth,
+            marker,
+            text,
+        } => {
+            let marker = match marker {
+                ListMarker::Bullet => "•".to_string(),
+                ListMarker::Number(number) => format!("{}.", number),
+                ListMarker::Task(true) => "☑".to_string(),
+                ListMarker::Task(false) => "☐".to_string(),
+            };
+            let _ = writeln!(
+                html,
+                "<p class=\"item\" style=\"margin-left:{}em\">{} {}</p>",
+                depth * 2,
+                marker,
+                render_lines(text, pages)
+            );
+        }
+        Block::Quote(text) => {
+            let _ = writeln!(
+                html,
+                "<blockquote>{}</blockquote>",
+                render_lines(text, pages)
+            );
+        }
+        Block::Code { language, code } => {
+            let _ = writeln!(
+                html,
+                "<pre><code class=\"language-{}\">{}</code></pre>",
+                escape(language),
+                escape(code)
+            );
+        }
+        Block::Rule => html.push_str("<hr>\n"),
+    }
+}
+
+/// Renders the lines of a block, keeping line breaks.
+fn render_lines(text: &str, pages: &HashMap<String, String>) -> String {
+    text.lines()
+        .map(|line| render_inlines(line, pages))
+        .collect::<Vec<_>>()
+        .join("<br>\n")
+}
+
+/// Renders the formatting and links of a line.
+fn render_inlines(line: &str, pages: &HashMap<String, String>) -> String {
+    let mut html = String::new();
+    for span in markdown::inlines(line) {
+        match span {
+            Inline::Text(text) => html.push_str(&render_wiki_links(text, pages)),
+            Inline::Strong(text) => {
+                let _ = write!(html, "<strong>{}</strong>", escape(text));
+            }
+            Inline::Emphasis(text) => {
+                let _ = write!(html, "<em>{}</em>", escape(text));
+            }
+            Inline::Code(text) => {
+                let _ = write!(html, "<code>{}</code>", escape(text));
+            }
+            Inline::Link { text, url } => {
+                let _ = write!(
+                    html,
+                    "<a href=\"{}\" rel=\"noopener noreferrer\">{}</a>",
+                    escape(url),
+                    escape(text)
+                );
+            }
+        }
+    }
+    html
+}
+
+/// Renders text, turning wiki links to published notes into links.
+///
+/// Links to notes that aren't published show their label, or the title
+/// they point to, as plain text.
+fn render_wiki_links(text: &str, pages: &HashMap<String, String>) -> String {
+    let mut html = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("[[") {
+        let Some(len) = rest[start + 2..].find("]]") else {
+            break;
+        };
+        let inner = &rest[start + 2..start + 2 + len];
+        html.push_str(&escape(&rest[..start]));
+
+        let (target, label) = match inner.split_once('|') {
+            Some((target, label)) => (target, label.trim()),
+            None => (inner, inner.split('#').next().unwrap_or_default().trim()),
+        };
+        let title = target.split('#').next().unwrap_or_default().trim();
+        match pages.get(&title.to_lowercase()) {
+            Some(page) => {
+                let _ = write!(html, "<a href=\"{}\">{}</a>", escape(page), escape(label));
+            }
+            None => html.push_str(&escape(label)),
+        }
+        rest = &rest[start + 2 + len + 2..];
+    }
+    html.push_str(&escape(rest));
+    html
+}
+
+/// Escapes text for HTML content and attribute values.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,179 @@
+//! # Site Export UI Module
+//!
+//! The "Publish as Website" dialog: the user picks the notes to publish
+//! and a title for the site, then an empty folder the site module writes
+//! the pages into. Notes in the trash and secrets notes are not offered.
+
+use crate::app::NotesApp;
+use crate::site_export;
+use eframe::egui;
+use std::collections::HashSet;
+
+/// State of the "Publish as Website" dialog.
+#[derive(Default)]
+pub struct SiteExportDialog {
+    /// IDs of the notes to publish
+    selected: HashSet<String>,
+    /// Text the note list is filtered by
+    filter: String,
+    /// Title of the index page
+    site_title: String,
+    /// Why the last export failed
+    error: Option<String>,
+}
+
+impl NotesApp {
+    /// Opens the "Publish as Website" dialog with the open note selected.
+    pub fn open_site_export(&mut self) {
+        let selected = self
+            .selected_note_id
+            .iter()
+            .filter(|id| {
+                self.notes
+                    .get(*id)
+                    .is_some_and(|note| note.kind.is_text() && !note.is_trashed())
+            })
+            .cloned()
+            .collect();
+        self.site_export_dialog = Some(SiteExportDialog {
+            selected,
+            site_title: "My Notes".to_string(),
+            ..SiteExportDialog::default()
+        });
+    }
+
+    /// Renders the "Publish as Website" dialog, if open.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The egui context for rendering
+    pub fn render_site_export_dialog(&mut self, ctx: &egui::Context) {
+        let Some(dialog) = &mut self.site_export_dialog else {
+            return;
+        };
+
+        let mut notes: Vec<(&String, &String)> = self
+            .notes
+            .values()
+            .filter(|note| note.kind.is_text() && !note.is_trashed())
+            .map(|note| (&note.id, &note.title))
+            .collect();
+        notes.sort_by_key(|(_, title)| title.to_lowercase());
+        let filter = dialog.filter.to_lowercase();
+        let visible: Vec<(&String, &String)> = notes
+            .iter()
+            .filter(|(_, title)| title.to_lowercase().contains(&filter))
+            .copied()
+            .collect();
+
+        let mut open = true;
+        let mut publish = false;
+        egui::Window::new("🌐 Publish as Website")
+            .open(&mut open)
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                ui.label(
+                    "The selected notes are written as plain HTML pages, readable by \
+                     anyone with the files. Properties, comments and attachments are \
+                     left out.",
+                );
+                ui.add_space(5.0);
+                ui.horizontal(|ui| {
+                    ui.label("Site title:");
+                    ui.text_edit_singleline(&mut dialog.site_title);
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Filter:");
+                    ui.text_edit_singleline(&mut dialog.filter);
+                });
+                ui.horizontal(|ui| {
+                    if ui.small_button("Select all").clicked() {
+                        dialog
+                            .selected
+                            .extend(visible.iter().map(|(id, _)| (*id).clone()));
+                    }
+                    if ui.small_button("Select none").clicked() {
+                        for (id, _) in &visible {
+                            dialog.selected.remove(*id);
+                        }
+                    }
+                    ui.label(format!("{} selected", dialog.selected.len()));
+                });
+
+                egui::ScrollArea::vertical()
+                    .max_height(300.0)
+                    .auto_shrink([false, true])
+                    .show(ui, |ui| {
+                        for (id, title) in &visible {
+                            let mut checked = dialog.selected.contains(*id);
+                            if ui.checkbox(&mut checked, title.as_str()).changed() {
+                                if checked {
+                                    dialog.selected.insert((*id).clone());
+                                } else {
+                                    dialog.selected.remove(*id);
+                                }
+                            }
+                        }
+                        if visible.is_empty() {
+                            ui.weak("No notes match");
+                        }
+                    });
+                ui.small("Secrets notes and notes in the trash can't be published.");
+
+                if let Some(error) = &dialog.error {
+                    ui.colored_label(egui::Color32::from_rgb(255, 100, 100), error);
+                }
+                ui.separator();
+                if ui
+                    .add_enabled(
+                        !dialog.selected.is_empty() && !dialog.site_title.trim().is_empty(),
+                        egui::Button::new("Publish…"),
+                    )
+                    .on_hover_text("Choose an empty folder for the website")
+                    .clicked()
+                {
+                    publish = true;
+                }
+            });
+
+        if !open {
+            self.site_export_dialog = None;
+        } else if publish {
+            self.publish_site();
+        }
+    }
+
+    /// Lets the user choose a folder and writes the selected notes into it.
+    fn publish_site(&mut self) {
+        let Some(dialog) = &mut self.site_export_dialog else {
+            return;
+        };
+        let Some(folder) = rfd::FileDialog::new()
+            .set_title("Choose an Empty Folder for the Website")
+            .pick_folder()
+        else {
+            return;
+        };
+
+        // Notes deleted or trashed while the dialog was open are skipped
+        let notes: Vec<_> = dialog
+            .selected
+            .iter()
+            .filter_map(|id| self.notes.get(id))
+            .filter(|note| note.kind.is_text() && !note.is_trashed())
+            .collect();
+        match site_export::write_site(&folder, dialog.site_title.trim(), &notes) {
+            Ok(count) => {
+                self.site_export_dialog = None;
+                self.report_export(
+                    format!("Published {} note(s) to {}", count, folder.display()),
+                    &folder.join("index.html"),
+                );
+            }
+            Err(e) => {
+                log::error!("Failed to publish notes: {}", e);
+                dialog.error = Some(format!("Publishing failed: {}", e));
+            }
+        }
+    }
+}
@@ -0,0 +1,399 @@
+//! # Snapshot Viewer Module
+//!
+//! Opens a migration bundle read-only to check what an old backup contains
+//! before restoring it. The bundle is decrypted into memory only: nothing
+//! is written to disk and the live vault is never touched, so the viewer
+//! cannot conflict with the notes being edited. Each note of the snapshot
+//! is compared with its counterpart in the vault by ID.
+//!
+//! Closing the viewer or logging out drops the decrypted notes.
+
+use crate::app::NotesApp;
+use crate::migration::{self, MigrationBundle, BUNDLE_EXTENSION};
+use crate::note::Note;
+use crate::storage_report::format_size;
+use eframe::egui;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// A migration bundle opened for viewing.
+pub struct Snapshot {
+    /// Location of the bundle
+    pub path: PathBuf,
+    /// The decrypted bundle
+    pub bundle: MigrationBundle,
+}
+
+/// How a note of the snapshot relates to the live vault.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotNoteStatus {
+    /// No note with this ID is in the vault, e.g. it was deleted since
+    NotInVault,
+    /// The vault has the same version
+    Unchanged,
+    /// The snapshot has a newer version than the vault
+    NewerInSnapshot,
+    /// The note was changed in the vault after the snapshot
+    ChangedSince,
+}
+
+impl SnapshotNoteStatus {
+    /// Compares a note of the snapshot with the vault.
+    ///
+    /// # Arguments
+    ///
+    /// * `note` - The note of the snapshot
+    /// * `live_notes` - The notes of the vault, by ID
+    ///
+    /// # Returns
+    ///
+    /// * `Self` - The status of the note
+    pub fn of(note: &Note, live_notes: &HashMap<String, Note>) -> Self {
+        match live_notes.get(&note.id) {
+            None => SnapshotNoteStatus::NotInVault,
+            Some(live) if live.modified_at == note.modified_at => SnapshotNoteStatus::Unchanged,
+            Some(live) if live.modified_at < note.modified_at => {
+                SnapshotNoteStatus::NewerInSnapshot
+            }
+            Some(_) => SnapshotNoteStatus::ChangedSince,
+        }
+    }
+
+    /// Returns the text shown next to the note.
+    pub fn label(&self) -> &'static str {
+        match self {
+            SnapshotNoteStatus::NotInVault => "Not in vault",
+            SnapshotNoteStatus::Unchanged => "Unchanged",
+            SnapshotNoteStatus::NewerInSnapshot => "Newer in snapshot",
+            SnapshotNoteStatus::ChangedSince => "Changed since",
+        }
+    }
+
+    /// Returns the color of the label.
+    pub fn color(&self) -> egui::Color32 {
+        match self {
+            SnapshotNoteStatus::NotInVault => egui::Color32::LIGHT_BLUE,
+            SnapshotNoteStatus::Unchanged => egui::Color32::GRAY,
+            SnapshotNoteStatus::NewerInSnapshot => egui::Color32::YELLOW,
+            SnapshotNoteStatus::ChangedSince => egui::Color32::LIGHT_GRAY,
+        }
+    }
+}
+
+/// State of the snapshot viewer window.
+#[derive(Default)]
+pub struct SnapshotViewer {
+    /// Bundle passphrase input
+    pub passphrase: String,
+    /// The opened snapshot, None until a bundle was opened
+    pub snapshot: Option<Snapshot>,
+    /// ID of the note shown in the viewer
+    pub selected_note_id: Option<String>,
+    /// Filter for the note list
+    pub filter: String,
+    /// Error of the last attempt to open a bundle
+    pub error: Option<String>,
+}
+
+impl SnapshotViewer {
+    /// Closes the snapshot and forgets the entered passphrase.
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// Button pressed in the viewer, handled after the window closure.
+enum ViewerAction {
+    Open,
+    OpenAnother,
+    Close,
+}
+
+impl NotesApp {
+    /// Shows a bundle that was already opened, e.g. by the migration wizard.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Location of the bundle
+    /// * `bundle` - The decrypted bundle
+    pub fn view_snapshot(&mut self, path: PathBuf, bundle: MigrationBundle) {
+        self.snapshot_viewer.reset();
+        self.snapshot_viewer.snapshot = Some(Snapshot { path, bundle });
+        self.show_snapshot_viewer = true;
+    }
+
+    /// Renders the snapshot viewer.
+    ///
+    /// Before a bundle is opened it asks for the passphrase; afterwards it
+    /// lists the notes of the snapshot with their status compared to the
+    /// vault and shows the selected note read-only.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The egui context for rendering
+    pub fn render_snapshot_viewer(&mut self, ctx: &egui::Context) {
+        if !self.show_snapshot_viewer {
+            return;
+        }
+
+        let mut action = None;
+        let live_notes = &self.notes;
+        let viewer = &mut self.snapshot_viewer;
+
+        egui::Window::new("Snapshot (Read-Only)")
+            .open(&mut self.show_snapshot_viewer)
+            .collapsible(false)
+            .default_size([720.0, 480.0])
+            .show(ctx, |ui| {
+                let Some(snapshot) = &viewer.snapshot else {
+                    ui.label(
+                        "Open a migration bundle to see what it contains. Nothing is \
+                         restored and your notes are not changed.",
+                    );
+                    ui.add_space(5.0);
+                    ui.label("Passphrase:");
+                    ui.add(egui::TextEdit::singleline(&mut viewer.passphrase).password(true));
+                    if let Some(error) = &viewer.error {
+                        ui.colored_label(egui::Color32::RED, error.as_str());
+                    }
+                    ui.add_space(5.0);
+                    if ui
+                        .add_enabled(
+                            !viewer.passphrase.is_empty(),
+                            egui::Button::new("Open Snapshot…"),
+                        )
+                        .clicked()
+                    {
+                        action = Some(ViewerAction::Open);
+                    }
+                    return;
+                };
+
+                let bundle = &snapshot.bundle;
+                let statuses: HashMap<&str, SnapshotNoteStatus> = bundle
+                    .notes
+                    .iter()
+                    .map(|note| (note.id.as_str(), SnapshotNoteStatus::of(note, live_notes)))
+                    .collect();
+                let count_of = |status: SnapshotNoteStatus| {
+                    statuses.values().filter(|s| **s == status).count()
+                };
+
+                ui.colored_label(
+                    egui::Color32::YELLOW,
+                    format!(
+                        "🔒 Snapshot of {} from {}, read-only",
+                        bundle.username,
+                        bundle.exported_at.format("%d.%m.%Y %H:%M")
+                    ),
+                );
+                ui.small(snapshot.path.display().to_string());
+                ui.label(format!(
+                    "{} note(s): {} not in vault, {} newer in snapshot, {} changed since, \
+                     {} unchanged",
+                    bundle.notes.len(),
+                    count_of(SnapshotNoteStatus::NotInVault),
+                    count_of(SnapshotNoteStatus::NewerInSnapshot),
+                    count_of(SnapshotNoteStatus::ChangedSince),
+                    count_of(SnapshotNoteStatus::Unchanged),
+                ));
+                ui.separator();
+
+                let mut notes: Vec<&Note> = bundle
+                    .notes
+                    .iter()
+                    .filter(|note| matches_filter(note, &viewer.filter))
+                    .collect();
+                notes.sort_by_key(|note| note.title.to_lowercase());
+
+                ui.horizontal_top(|ui| {
+                    ui.vertical(|ui| {
+                        ui.set_width(240.0);
+                        ui.add(
+                            egui::TextEdit::singleline(&mut viewer.filter)
+                                .hint_text("Filter…")
+                                .desired_width(f32::INFINITY),
+                        );
+                        egui::ScrollArea::vertical()
+                            .id_salt("snapshot_note_list")
+                            .max_height(340.0)
+                            .show(ui, |ui| {
+                                for note in &notes {
+                                    let status = statuses[note.id.as_str()];
+                                    let title = if note.is_trashed() {
+                                        format!("🗑 {}", note.title)
+                                    } else {
+                                        note.title.clone()
+                                    };
+                                    let selected =
+                                        viewer.selected_note_id.as_deref() == Some(&note.id);
+                                    if ui.selectable_label(selected, title).clicked() {
+                                        viewer.selected_note_id = Some(note.id.clone());
+                                    }
+                                    ui.small(
+                                        egui::RichText::new(status.label()).color(status.color()),
+                                    );
+                                }
+                                if notes.is_empty() {
+                                    ui.label("No matching notes");
+                                }
+                            });
+                    });
+
+                    ui.separator();
+
+                    ui.vertical(|ui| {
+                        let selected = viewer
+                            .selected_note_id
+                            .as_ref()
+                            .and_then(|id| bundle.notes.iter().find(|note| &note.id == id));
+                        match selected {
+                            Some(note) => render_snapshot_note(
+                                ui,
+                                note,
+                                statuses[note.id.as_str()],
+                                live_notes,
+                            ),
+                            None => {
+                                ui.label("Select a note to view it.");
+                            }
+                        }
+                    });
+                });
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.button("Open Another…").clicked() {
+                        action = Some(ViewerAction::OpenAnother);
+                    }
+                    if ui.button("Close").clicked() {
+                        action = Some(ViewerAction::Close);
+                    }
+                    ui.small("To restore notes, import the bundle with Move to Another Computer.");
+                });
+            });
+
+        match action {
+            Some(ViewerAction::Open) => self.open_snapshot(),
+            Some(ViewerAction::OpenAnother) => self.snapshot_viewer.reset(),
+            Some(ViewerAction::Close) => self.show_snapshot_viewer = false,
+            None => {}
+        }
+
+        if !self.show_snapshot_viewer {
+            self.snapshot_viewer.reset();
+        }
+    }
+
+    /// Asks for a bundle and decrypts it into the viewer.
+    fn open_snapshot(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .set_title("Open Snapshot")
+            .add_filter("Migration bundles", &[BUNDLE_EXTENSION])
+            .add_filter("All files", &["*"])
+            .pick_file()
+        else {
+            return;
+        };
+
+        let viewer = &mut self.snapshot_viewer;
+        match migration::read_bundle(&path, &viewer.passphrase) {
+            Ok(bundle) => {
+                log::info!("Opened snapshot {} read-only", path.display());
+                viewer.reset();
+                viewer.snapshot = Some(Snapshot { path, bundle });
+            }
+            Err(e) => {
+                log::warn!("Failed to open snapshot: {}", e);
+                viewer.error = Some(e.to_string());
+            }
+        }
+    }
+}
+
+/// Checks whether a note matches the filter of the note list.
+fn matches_filter(note: &Note, filter: &str) -> bool {
+    let filter = filter.trim().to_lowercase();
+    filter.is_empty()
+        || note.title.to_lowercase().contains(&filter)
+        || note.content.to_lowercase().contains(&filter)
+}
+
+/// Renders a note of the snapshot read-only.
+///
+/// # Arguments
+///
+/// * `ui` - The viewer UI
+/// * `note` - The note of the snapshot
+/// * `status` - Its status compared to the vault
+/// * `live_notes` - The notes of the vault, by ID
+fn render_snapshot_note(
+    ui: &mut egui::Ui,
+    note: &Note,
+    status: SnapshotNoteStatus,
+    live_notes: &HashMap<String, Note>,
+) {
+    ui.heading(&note.title);
+    ui.horizontal(|ui| {
+        ui.small(format!(
+            "Created {} · Modified {}",
+            note.format_created_time(),
+            note.format_modified_time()
+        ));
+        ui.small(egui::RichText::new(status.label()).color(status.color()));
+    });
+    if let Some(live) = live_notes.get(&note.id) {
+        if status != SnapshotNoteStatus::Unchanged {
+            ui.small(format!(
+                "In the vault: \"{}\", modified {}",
+                live.title,
+                live.format_modified_time()
+            ));
+        }
+    }
+    if note.is_trashed() {
+        ui.small("In the trash when the snapshot was taken");
+    }
+    if let Some(notebook) = &note.notebook {
+        ui.small(format!("Notebook: {}", notebook));
+    }
+
+    if !note.fields.is_empty() {
+        egui::Grid::new("snapshot_note_fields")
+            .num_columns(2)
+            .show(ui, |ui| {
+                for (key, value) in &note.fields {
+                    ui.label(key);
+                    ui.label(value);
+                    ui.end_row();
+                }
+            });
+    }
+
+    if !note.attachments.is_empty() {
+        ui.small(format!(
+            "Attachments (files not included in the snapshot): {}",
+            note.attachments
+                .iter()
+                .map(|a| format!("{} ({})", a.file_name, format_size(a.size)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+    if !note.comments.is_empty() {
+        ui.small(format!("{} comment(s)", note.comments.len()));
+    }
+
+    ui.separator();
+    egui::ScrollArea::vertical()
+        .id_salt("snapshot_note_content")
+        .max_height(280.0)
+        .show(ui, |ui| {
+            // A &str buffer can be selected and copied, but not edited
+            ui.add(
+                egui::TextEdit::multiline(&mut note.content.as_str())
+                    .desired_width(f32::INFINITY)
+                    .font(egui::TextStyle::Monospace),
+            );
+        });
+}
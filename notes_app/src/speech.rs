@@ -0,0 +1,307 @@
+//! # Speech Module
+//!
+//! Reads text aloud with the speech engine of the operating system:
+//!
+//! - **Windows**: `System.Speech` through PowerShell
+//! - **macOS**: the `say` command
+//! - **Linux**: `espeak-ng`, `espeak` or Speech Dispatcher's `spd-say`,
+//!   whichever is installed first
+//!
+//! The text is spoken one paragraph at a time, one engine process per
+//! paragraph, so pausing stops the current paragraph and resuming starts
+//! it again. The text is passed on standard input rather than as an
+//! argument, so note content never shows up in the process list.
+
+use crate::shell_open::hide_console;
+use anyhow::{anyhow, Result};
+use std::io::Write;
+use std::process::{Child, Command, Stdio};
+use std::sync::OnceLock;
+
+/// Longest piece of text spoken by one engine process, in characters.
+/// Longer paragraphs are split at sentence ends.
+const MAX_CHUNK_CHARS: usize = 600;
+
+/// A speech engine program and how to call it.
+struct Engine {
+    /// Program name, looked up in `PATH`
+    program: &'static str,
+    /// Arguments making the program read the text from standard input
+    args: &'static [&'static str],
+    /// Arguments of a second call that silences the engine, for engines
+    /// that keep speaking after their process is killed
+    stop_args: Option<&'static [&'static str]>,
+}
+
+#[cfg(windows)]
+const ENGINES: &[Engine] = &[Engine {
+    program: "powershell",
+    args: &[
+        "-NoProfile",
+        "-NonInteractive",
+        "-Command",
+        "[Console]::InputEncoding = [Text.Encoding]::UTF8; \
+         Add-Type -AssemblyName System.Speech; \
+         (New-Object System.Speech.Synthesis.SpeechSynthesizer).Speak([Console]::In.ReadToEnd())",
+    ],
+    stop_args: None,
+}];
+
+#[cfg(target_os = "macos")]
+const ENGINES: &[Engine] = &[Engine {
+    program: "say",
+    args: &["-f", "-"],
+    stop_args: None,
+}];
+
+#[cfg(not(any(windows, target_os = "macos")))]
+const ENGINES: &[Engine] = &[
+    Engine {
+        program: "espeak-ng",
+        args: &["--stdin"],
+        stop_args: None,
+    },
+    Engine {
+        program: "espeak",
+        args: &["--stdin"],
+        stop_args: None,
+    },
+    Engine {
+        program: "spd-say",
+        args: &["--wait", "--pipe-mode"],
+        stop_args: Some(&["--cancel"]),
+    },
+];
+
+/// Returns the speech engine of this computer, looked up once.
+fn engine() -> Option<&'static Engine> {
+    static ENGINE: OnceLock<Option<&'static Engine>> = OnceLock::new();
+    *ENGINE.get_or_init(|| {
+        let engine = ENGINES.iter().find(|engine| on_path(engine.program));
+        match engine {
+            Some(engine) => log::info!("Using {} for reading aloud", engine.program),
+            None => log::info!("No speech engine found, reading aloud is unavailable"),
+        }
+        engine
+    })
+}
+
+/// Checks whether reading aloud is available on this computer.
+///
+/// # Returns
+///
+/// * `bool` - True if a speech engine was found
+pub fn is_available() -> bool {
+    engine().is_some()
+}
+
+/// Whether a speaker is reading, paused or done.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpeechState {
+    /// A paragraph is being spoken
+    Speaking,
+    /// Stopped in the middle, resumes at the current paragraph
+    Paused,
+    /// All text was spoken, or the engine failed
+    Finished,
+}
+
+/// Reads a text aloud, paragraph by paragraph.
+pub struct Speaker {
+    /// ID of the note being read
+    pub note_id: String,
+    /// The text split into pieces spoken by one process each
+    chunks: Vec<String>,
+    /// Index of the piece being spoken or to speak next
+    position: usize,
+    /// Engine process speaking the current piece
+    child: Option<Child>,
+    /// Whether playback is paused
+    paused: bool,
+}
+
+impl Speaker {
+    /// Starts reading a text aloud.
+    ///
+    /// # Arguments
+    ///
+    /// * `note_id` - ID of the note the text is from
+    /// * `text` - The text to read
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self>` - The speaking speaker, or error if no speech engine
+    ///   is installed, the text is empty or the engine could not start
+    pub fn start(note_id: &str, text: &str) -> Result<Self> {
+        let chunks = split_into_chunks(text);
+        if chunks.is_empty() {
+            return Err(anyhow!("There is no text to read"));
+        }
+        let mut speaker = Self {
+            note_id: note_id.to_string(),
+            chunks,
+            position: 0,
+            child: None,
+            paused: false,
+        };
+        speaker.speak_current()?;
+        Ok(speaker)
+    }
+
+    /// Pauses reading; the current paragraph is started again on `resume`.
+    pub fn pause(&mut self) {
+        self.paused = true;
+        self.silence();
+    }
+
+    /// Resumes reading at the paragraph that was paused.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - Error if the engine could not start
+    pub fn resume(&mut self) -> Result<()> {
+        self.paused = false;
+        self.speak_current()
+    }
+
+    /// Advances to the next paragraph once the current one is spoken.
+    ///
+    /// Called every frame while the speaker exists.
+    ///
+    /// # Returns
+    ///
+    /// * `SpeechState` - The state after advancing
+    pub fn poll(&mut self) -> SpeechState {
+        if self.paused {
+            return SpeechState::Paused;
+        }
+        let done = match &mut self.child {
+            Some(child) => !matches!(child.try_wait(), Ok(None)),
+            None => true,
+        };
+        if done {
+            self.child = None;
+            self.position += 1;
+            if self.position >= self.chunks.len() || self.speak_current().is_err() {
+                return SpeechState::Finished;
+            }
+        }
+        SpeechState::Speaking
+    }
+
+    /// Checks whether reading is paused.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - True between `pause` and `resume`
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Returns how far reading has got.
+    ///
+    /// # Returns
+    ///
+    /// * `(usize, usize)` - The current paragraph, counted from 1, and the
+    ///   number of paragraphs
+    pub fn progress(&self) -> (usize, usize) {
+        (
+            (self.position + 1).min(self.chunks.len()),
+            self.chunks.len(),
+        )
+    }
+
+    /// Starts an engine process for the current piece.
+    fn speak_current(&mut self) -> Result<()> {
+        self.silence();
+        let engine = engine().ok_or_else(|| {
+            anyhow!("No speech engine found. On Linux, install espeak-ng or speech-dispatcher")
+        })?;
+        let Some(text) = self.chunks.get(self.position) else {
+            return Ok(());
+        };
+
+        let mut command = Command::new(engine.program);
+        command
+            .args(engine.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        hide_console(&mut command);
+        let mut child = command
+            .spawn()
+            .map_err(|e| anyhow!("Could not start {}: {}", engine.program, e))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            // Closing stdin tells the engine the text is complete
+            if let Err(e) = stdin.write_all(text.as_bytes()) {
+                log::warn!("Failed to pass text to the speech engine: {}", e);
+            }
+        }
+        self.child = Some(child);
+        Ok(())
+    }
+
+    /// Stops the engine process speaking the current piece, if any.
+    fn silence(&mut self) {
+        let Some(mut child) = self.child.take() else {
+            return;
+        };
+        let _ = child.kill();
+        let _ = child.wait();
+        if let Some(engine) = engine() {
+            if let Some(stop_args) = engine.stop_args {
+                let _ = Command::new(engine.program)
+                    .args(stop_args)
+                    .stdout(Stdio::null())
+                    .stderr(Stdio::null())
+                    .status();
+            }
+        }
+    }
+}
+
+impl Drop for Speaker {
+    fn drop(&mut self) {
+        self.silence();
+    }
+}
+
+/// Splits a text into pieces at blank lines, and pieces longer than
+/// `MAX_CHUNK_CHARS` further at sentence ends.
+fn split_into_chunks(text: &str) -> Vec<String> {
+    let mut chunks = Vec::new();
+    for paragraph in text.split("\n\n") {
+        let paragraph = paragraph.trim();
+        if paragraph.is_empty() {
+            continue;
+        }
+
+        let mut chunk = String::new();
+        for sentence in paragraph.split_inclusive(['.', '!', '?']) {
+            if !chunk.is_empty()
+                && chunk.chars().count() + sentence.chars().count() > MAX_CHUNK_CHARS
+            {
+                chunks.push(std::mem::take(&mut chunk).trim().to_string());
+            }
+            chunk.push_str(sentence);
+        }
+        if !chunk.trim().is_empty() {
+            chunks.push(chunk.trim().to_string());
+        }
+    }
+    chunks
+}
+
+/// Checks whether a program is in one of the `PATH` directories.
+fn on_path(program: &str) -> bool {
+    let Some(paths) = std::env::var_os("PATH") else {
+        return false;
+    };
+    std::env::split_paths(&paths).any(|dir| {
+        if cfg!(windows) {
+            dir.join(format!("{}.exe", program)).is_file()
+        } else {
+            dir.join(program).is_file()
+        }
+    })
+}
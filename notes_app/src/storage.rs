@@ -8,11 +8,91 @@
 //! Provides secure, user-isolated storage with encryption integration
 //! and legacy data migration capabilities.
 
-use crate::crypto::CryptoManager;
-use crate::note::Note;
-use anyhow::Result;
-use std::collections::HashMap;
+use crate::crypto::{BlobContext, CryptoManager};
+use crate::journal::Journal;
+use crate::load_failures::{self, FailureReason, LoadFailure};
+use crate::login_history::{LoginRecord, MAX_LOGIN_RECORDS};
+use crate::note::{Attachment, Note};
+use crate::note_history::NoteHistory;
+use crate::note_lock::NoteLock;
+use crate::search_index::IndexSnapshot;
+use crate::secure_fs;
+use crate::settings::UserSettings;
+use crate::trusted_time;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use rayon::prelude::*;
+use secure_core::Cipher;
+use std::collections::{HashMap, HashSet};
 use std::fs;
+use std::io::Read;
+use std::time::SystemTime;
+
+/// Storage owner ID of the shared vault, used in place of a user ID.
+///
+/// Its notes, attachments and locks are laid out like a user's, but in
+/// the `shared/` directory. User IDs are UUIDs, so they never collide.
+pub const SHARED_VAULT_OWNER: &str = "shared";
+
+/// Usage of a user's files compared with their storage quota.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuotaUsage {
+    /// Size of the user's files in bytes
+    pub used_bytes: u64,
+    /// The quota in bytes
+    pub quota_bytes: u64,
+}
+
+impl QuotaUsage {
+    /// Returns the used share of the quota.
+    ///
+    /// # Returns
+    ///
+    /// * `u64` - Percent of the quota in use, above 100 if it is exceeded
+    pub fn percent(&self) -> u64 {
+        self.used_bytes.saturating_mul(100) / self.quota_bytes.max(1)
+    }
+
+    /// Checks whether the quota is used up.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - True if the files take up the whole quota or more
+    pub fn exceeded(&self) -> bool {
+        self.used_bytes >= self.quota_bytes
+    }
+
+    /// Checks whether more data fits into the quota.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - Size of the data to add
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - True if the files stay within the quota with the data
+    pub fn fits(&self, bytes: u64) -> bool {
+        self.used_bytes.saturating_add(bytes) <= self.quota_bytes
+    }
+}
+
+/// One note found in the legacy notes file.
+#[derive(Debug, Clone)]
+pub struct LegacyNoteEntry {
+    /// Title of the note, or its key if the entry could not be parsed
+    pub label: String,
+    /// The note, or why it could not be read or migrated
+    pub result: Result<Note, String>,
+}
+
+/// Result of upgrading a user's files to the current format and key.
+#[derive(Debug, Clone, Default)]
+pub struct UpgradeReport {
+    /// Number of files rewritten
+    pub upgraded: usize,
+    /// Files that needed a rewrite but could not be rewritten, with why
+    pub failed: Vec<String>,
+}
 
 /// Manages encrypted storage operations for user notes and data.
 ///
@@ -44,9 +124,28 @@ impl StorageManager {
     /// ~/.config/secure_notes/          (or platform equivalent)
     /// ├── users/
     /// │   ├── user1_id/
-    /// │   │   └── notes.enc
+    /// │   │   ├── notes/
+    /// │   │   │   ├── <note_id>.enc
+    /// │   │   │   └── ...
+    /// │   │   ├── attachments/
+    /// │   │   │   └── <note_id>/
+    /// │   │   │       └── <attachment_id>.enc
+    /// │   │   ├── locks/
+    /// │   │   │   └── <note_id>.lock   (note being edited, see note_lock)
+    /// │   │   ├── history/
+    /// │   │   │   └── <note_id>.enc    (earlier versions, see note_history)
+    /// │   │   ├── settings.enc
+    /// │   │   ├── journal.enc          (change journal, see journal)
+    /// │   │   ├── search_index.enc     (saved search index, see search_index)
+    /// │   │   └── logins.json          (recent login attempts, see login_history)
     /// │   └── user2_id/
-    /// │       └── notes.enc
+    /// │       └── ...
+    /// ├── shared/                      (shared vault, see shared_vault)
+    /// │   ├── members/
+    /// │   │   └── <user_id>.key
+    /// │   ├── notes/
+    /// │   ├── attachments/
+    /// │   └── locks/
     /// └── notes.enc.backup             (legacy backup)
     /// ```
     pub fn new() -> Self {
@@ -58,14 +157,24 @@ impl StorageManager {
 
     /// Saves encrypted notes for a specific user.
     ///
-    /// Serializes the notes to JSON, encrypts the data using the provided
-    /// crypto manager, and saves it to the user's storage directory.
-    /// Sets secure file permissions on Unix systems.
+    /// Writes every note to its own encrypted file in the user's `notes/`
+    /// directory and removes the files, history and attachments of the
+    /// notes in `deleted`. Other files missing from `notes` are kept: they
+    /// belong to notes that could not be decrypted, or that another instance
+    /// created since this one loaded. A single-file `notes.enc` bundle
+    /// written by older versions is removed once all notes have been written
+    /// in the per-note layout.
+    ///
+    /// Notes locked by another instance are left as they are on disk, since
+    /// that instance may have saved newer content than the copy in `notes`,
+    /// and are not removed even if they are in `deleted`.
     ///
     /// # Arguments
     ///
     /// * `user_id` - Unique identifier for the user
     /// * `notes` - HashMap of note IDs to Note objects to save
+    /// * `deleted` - IDs of notes deleted since the last save
+    /// * `locked` - IDs of notes locked by another instance, not written
     /// * `crypto` - CryptoManager instance for encryption
     ///
     /// # Returns
@@ -84,249 +193,1491 @@ impl StorageManager {
     /// - All data is encrypted before writing to disk
     /// - User-specific storage isolation
     /// - Secure file permissions (0o600 on Unix)
-    /// - Atomic write operations where possible
     pub fn save_user_notes(
         &self,
         user_id: &str,
         notes: &HashMap<String, Note>,
+        deleted: &HashSet<String>,
+        locked: &HashSet<String>,
         crypto: &CryptoManager,
     ) -> Result<()> {
-        let json_data = serde_json::to_string(notes)?;
-        let encrypted_data = crypto.encrypt(json_data.as_bytes())?;
+        self.write_user_notes(user_id, notes, locked, crypto)?;
 
-        // Create user-specific directory
-        let user_dir = self.data_dir.join("users").join(user_id);
-        fs::create_dir_all(&user_dir)?;
+        // Remove files of deleted notes
+        let is_deleted =
+            |id: &str| deleted.contains(id) && !notes.contains_key(id) && !locked.contains(id);
+        let notes_dir = self.user_notes_dir(user_id);
+        for entry in fs::read_dir(&notes_dir)? {
+            let path = entry?.path();
+            let is_stale = path.extension().and_then(|e| e.to_str()) == Some("enc")
+                && path
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .is_some_and(is_deleted);
+            if is_stale {
+                secure_fs::secure_remove_file(&path)?;
+            }
+        }
 
-        let notes_file = user_dir.join("notes.enc");
-        fs::write(&notes_file, encrypted_data)?;
+        // Their earlier versions
+        for entry in read_dir_if_exists(&self.user_history_dir(user_id))? {
+            if enc_file_stem(&entry).is_some_and(|note_id| is_deleted(&note_id)) {
+                secure_fs::secure_remove_file(&entry)?;
+            }
+        }
+
+        // And the attachments of deleted notes
+        let attachments_dir = self.user_attachments_dir(user_id);
+        if attachments_dir.exists() {
+            for entry in fs::read_dir(&attachments_dir)? {
+                let path = entry?.path();
+                let is_stale = path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(is_deleted);
+                if is_stale && path.is_dir() {
+                    secure_fs::secure_remove_dir_all(&path)?;
+                }
+            }
+        }
 
-        // Set secure file permissions on Unix systems
-        #[cfg(unix)]
-        {
-            use std::os::unix::fs::PermissionsExt;
-            let mut perms = fs::metadata(&notes_file)?.permissions();
-            perms.set_mode(0o600); // Read/write for owner only
-            fs::set_permissions(&notes_file, perms)?;
+        // All notes now live in per-note files, drop the old bundle
+        let bundle_file = self.owner_dir(user_id).join("notes.enc");
+        if bundle_file.exists() {
+            secure_fs::secure_remove_file(&bundle_file)?;
+            log::info!("Migrated notes of user {} to per-note files", user_id);
         }
 
-        println!("Saved {} notes for user {}", notes.len(), user_id);
+        log::debug!("Saved {} notes for user {}", notes.len(), user_id);
         Ok(())
     }
 
-    /// Loads encrypted notes for a specific user.
+    /// Writes the given notes to their per-note files without removing others.
     ///
-    /// Reads the encrypted notes file for the specified user, decrypts
-    /// the data, and deserializes it back to a HashMap of notes.
-    /// Returns an empty HashMap if no notes file exists.
+    /// Used while notes are still being loaded, when the in-memory set is
+    /// incomplete and pruning missing notes would delete data.
     ///
     /// # Arguments
     ///
     /// * `user_id` - Unique identifier for the user
-    /// * `crypto` - CryptoManager instance for decryption
+    /// * `notes` - Notes to write
+    /// * `locked` - IDs of notes locked by another instance, not written
+    /// * `crypto` - CryptoManager instance for encryption
     ///
     /// # Returns
     ///
-    /// * `Result<HashMap<String, Note>>` - Notes HashMap or error
-    ///
-    /// # Errors
-    ///
-    /// * File reading fails
-    /// * Decryption operation fails (wrong key, corrupted data)
-    /// * JSON deserialization fails
-    /// * Invalid UTF-8 in decrypted data
-    ///
-    /// # Behavior
-    ///
-    /// - Returns empty HashMap if notes file doesn't exist
-    /// - Logs the number of notes loaded for debugging
-    /// - Handles missing files gracefully (new user scenario)
-    pub fn load_user_notes(
+    /// * `Result<()>` - Ok if successful, Err with details if failed
+    pub fn write_user_notes(
         &self,
         user_id: &str,
+        notes: &HashMap<String, Note>,
+        locked: &HashSet<String>,
         crypto: &CryptoManager,
-    ) -> Result<HashMap<String, Note>> {
-        let notes_file = self.data_dir.join("users").join(user_id).join("notes.enc");
+    ) -> Result<()> {
+        let notes_dir = self.user_notes_dir(user_id);
+        fs::create_dir_all(&notes_dir)?;
 
-        if !notes_file.exists() {
-            println!(
-                "No notes file found for user {}, starting with empty notes",
-                user_id
-            );
-            return Ok(HashMap::new());
+        for note in notes.values().filter(|note| !locked.contains(&note.id)) {
+            self.write_note_file(user_id, note, crypto)?;
         }
 
-        let encrypted_data = fs::read(&notes_file)?;
-        let decrypted_data = crypto.decrypt(&encrypted_data)?;
-        let json_str = String::from_utf8(decrypted_data)?;
-        let notes: HashMap<String, Note> = serde_json::from_str(&json_str)?;
+        Ok(())
+    }
+
+    /// Encrypts a single note and writes it to its per-note file.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - Unique identifier for the user
+    /// * `note` - The note to write
+    /// * `crypto` - CryptoManager instance for encryption
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - Ok if successful, Err with details if failed
+    pub fn save_user_note(&self, user_id: &str, note: &Note, crypto: &CryptoManager) -> Result<()> {
+        fs::create_dir_all(self.user_notes_dir(user_id))?;
+        self.write_note_file(user_id, note, crypto)
+    }
 
-        println!("Loaded {} notes for user {}", notes.len(), user_id);
-        Ok(notes)
+    /// Encrypts a single note and writes it to its per-note file.
+    ///
+    /// The user's `notes/` directory must already exist.
+    fn write_note_file(&self, user_id: &str, note: &Note, crypto: &CryptoManager) -> Result<()> {
+        let json_data = serde_json::to_string(note)?;
+        let encrypted_data = crypto.encrypt(
+            json_data.as_bytes(),
+            BlobContext::Note {
+                user_id,
+                note_id: &note.id,
+            },
+        )?;
+        secure_fs::write_private_file(
+            &self
+                .user_notes_dir(user_id)
+                .join(format!("{}.enc", note.id)),
+            &encrypted_data,
+        )?;
+        Ok(())
     }
 
-    /// Loads notes from the legacy storage format.
+    /// Securely deletes the file of a single note, its attachments and its lock.
     ///
-    /// This method supports loading notes from the old storage format
-    /// (before user-specific storage was implemented). Used primarily
-    /// for migration purposes.
+    /// Used where pruning by `save_user_notes` is not possible, e.g. in the
+    /// shared vault, whose other members may have added notes meanwhile.
     ///
     /// # Arguments
     ///
-    /// * `crypto` - CryptoManager instance for decryption
+    /// * `user_id` - Owner of the note
+    /// * `note_id` - ID of the note to delete
     ///
     /// # Returns
     ///
-    /// * `Result<HashMap<String, Note>>` - Notes HashMap or error
+    /// * `Result<()>` - Ok if the files were deleted or didn't exist
+    pub fn delete_user_note(&self, user_id: &str, note_id: &str) -> Result<()> {
+        let note_file = self
+            .user_notes_dir(user_id)
+            .join(format!("{}.enc", note_id));
+        if note_file.exists() {
+            secure_fs::secure_remove_file(&note_file)?;
+        }
+        let attachments_dir = self.user_attachments_dir(user_id).join(note_id);
+        if attachments_dir.exists() {
+            secure_fs::secure_remove_dir_all(&attachments_dir)?;
+        }
+        self.remove_note_lock(user_id, note_id)
+    }
+
+    /// Loads the earlier versions of a note.
     ///
-    /// # Legacy Format
+    /// # Arguments
     ///
-    /// The legacy format stored all notes in a single `notes.enc` file
-    /// in the root data directory, without user isolation.
-    pub fn load_notes(&self, crypto: &CryptoManager) -> Result<HashMap<String, Note>> {
-        let notes_file = self.data_dir.join("notes.enc");
-
-        if !notes_file.exists() {
-            return Ok(HashMap::new());
+    /// * `user_id` - Owner of the note
+    /// * `note_id` - ID of the note
+    /// * `crypto` - CryptoManager instance for decryption
+    ///
+    /// # Returns
+    ///
+    /// * `Result<NoteHistory>` - The revisions, empty if none were kept
+    ///   yet, or error if the history cannot be read or decrypted
+    pub fn load_note_history(
+        &self,
+        user_id: &str,
+        note_id: &str,
+        crypto: &CryptoManager,
+    ) -> Result<NoteHistory> {
+        let history_file = self.note_history_path(user_id, note_id);
+        if !history_file.exists() {
+            return Ok(NoteHistory::default());
         }
 
-        let encrypted_data = fs::read(&notes_file)?;
-        let decrypted_data = crypto.decrypt(&encrypted_data)?;
-        let json_str = String::from_utf8(decrypted_data)?;
-        let notes: HashMap<String, Note> = serde_json::from_str(&json_str)?;
-
-        Ok(notes)
+        let encrypted_data = fs::read(&history_file)?;
+        let decrypted_data = crypto.decrypt(
+            &encrypted_data,
+            BlobContext::NoteHistory { user_id, note_id },
+        )?;
+        Ok(serde_json::from_slice(&decrypted_data)?)
     }
 
-    /// Migrates notes from legacy storage format to user-specific storage.
+    /// Adds the version of a note currently on disk to its history.
     ///
-    /// Checks for the existence of legacy notes file and migrates the data
-    /// to the new user-specific storage format. The legacy file is backed up
-    /// rather than deleted to prevent data loss.
+    /// Must be called before the note file is overwritten.
     ///
     /// # Arguments
     ///
-    /// * `user_id` - Target user ID for migration
-    /// * `crypto` - CryptoManager instance for encryption/decryption
+    /// * `user_id` - Owner of the note
+    /// * `note_id` - ID of the note
+    /// * `crypto` - CryptoManager instance for encryption
     ///
     /// # Returns
     ///
-    /// * `Result<()>` - Ok if successful, Err if migration failed
+    /// * `Result<DateTime<Utc>>` - When the revision was recorded, or error
+    ///   if the note or its history cannot be read or written
+    pub fn record_note_revision(
+        &self,
+        user_id: &str,
+        note_id: &str,
+        crypto: &CryptoManager,
+    ) -> Result<DateTime<Utc>> {
+        let note = self.load_user_note(user_id, note_id, crypto)?;
+        let mut history = self.load_note_history(user_id, note_id, crypto)?;
+        history.push(note);
+
+        let json_data = serde_json::to_vec(&history)?;
+        let encrypted_data =
+            crypto.encrypt(&json_data, BlobContext::NoteHistory { user_id, note_id })?;
+        fs::create_dir_all(self.user_history_dir(user_id))?;
+        secure_fs::write_private_file(&self.note_history_path(user_id, note_id), &encrypted_data)?;
+        Ok(history
+            .latest_recorded_at()
+            .unwrap_or_else(trusted_time::now))
+    }
+
+    /// Encrypts the content of an attachment and writes it to its file.
     ///
-    /// # Migration Process
+    /// # Arguments
     ///
-    /// 1. Check for legacy `notes.enc` file
-    /// 2. Load notes using legacy format
-    /// 3. Save notes to user-specific location
-    /// 4. Backup legacy file as `notes.enc.backup`
-    /// 5. Log migration results
+    /// * `user_id` - Owner of the note
+    /// * `note_id` - ID of the note the file is attached to
+    /// * `attachment` - Metadata of the attachment
+    /// * `data` - The file content
+    /// * `crypto` - CryptoManager instance for encryption
     ///
-    /// # Safety
+    /// # Returns
     ///
-    /// - Original file is backed up, not deleted
-    /// - Migration only occurs if legacy file exists
-    /// - Empty legacy files are handled gracefully
-    /// - Errors don't affect existing user data
-    pub fn migrate_legacy_notes(&self, user_id: &str, crypto: &CryptoManager) -> Result<()> {
-        let legacy_file = self.data_dir.join("notes.enc");
-
-        if legacy_file.exists() {
-            println!("Found legacy notes file, migrating to user-specific storage...");
+    /// * `Result<()>` - Ok if successful, Err with details if failed
+    pub fn save_attachment(
+        &self,
+        user_id: &str,
+        note_id: &str,
+        attachment: &Attachment,
+        data: &[u8],
+        crypto: &CryptoManager,
+    ) -> Result<()> {
+        let path = self.attachment_path(user_id, note_id, &attachment.id);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
 
-            // Load legacy notes
-            let legacy_notes = self.load_notes(crypto)?;
+        let encrypted_data = crypto.encrypt(
+            data,
+            BlobContext::Attachment {
+                user_id,
+                note_id,
+                attachment_id: &attachment.id,
+            },
+        )?;
+        secure_fs::write_private_file(&path, &encrypted_data)?;
+        Ok(())
+    }
 
-            if !legacy_notes.is_empty() {
-                // Save to user-specific location
-                self.save_user_notes(user_id, &legacy_notes, crypto)?;
+    /// Reads and decrypts the content of an attachment.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - Owner of the note
+    /// * `note_id` - ID of the note the file is attached to
+    /// * `attachment` - Metadata of the attachment
+    /// * `crypto` - CryptoManager instance for decryption
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<u8>>` - The file content, or error if the file is
+    ///   missing or fails to decrypt
+    pub fn load_attachment(
+        &self,
+        user_id: &str,
+        note_id: &str,
+        attachment: &Attachment,
+        crypto: &CryptoManager,
+    ) -> Result<Vec<u8>> {
+        let encrypted_data = fs::read(self.attachment_path(user_id, note_id, &attachment.id))?;
+        crypto.decrypt(
+            &encrypted_data,
+            BlobContext::Attachment {
+                user_id,
+                note_id,
+                attachment_id: &attachment.id,
+            },
+        )
+    }
 
-                // Backup the legacy file instead of deleting it
-                let backup_file = self.data_dir.join("notes.enc.backup");
-                fs::rename(&legacy_file, &backup_file)?;
+    /// Checks whether the encrypted file of an attachment exists.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - Owner of the note
+    /// * `note_id` - ID of the note the file is attached to
+    /// * `attachment_id` - ID of the attachment
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - True if the file exists
+    pub fn attachment_exists(&self, user_id: &str, note_id: &str, attachment_id: &str) -> bool {
+        self.attachment_path(user_id, note_id, attachment_id)
+            .exists()
+    }
 
-                println!(
-                    "Migrated {} notes to user-specific storage",
-                    legacy_notes.len()
-                );
-                println!("Legacy file backed up as notes.enc.backup");
-            }
+    /// Securely deletes the file of an attachment.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - Owner of the note
+    /// * `note_id` - ID of the note the file is attached to
+    /// * `attachment_id` - ID of the attachment
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - Ok if the file was deleted or didn't exist
+    pub fn delete_attachment(
+        &self,
+        user_id: &str,
+        note_id: &str,
+        attachment_id: &str,
+    ) -> Result<()> {
+        let path = self.attachment_path(user_id, note_id, attachment_id);
+        if path.exists() {
+            secure_fs::secure_remove_file(&path)?;
         }
-
         Ok(())
     }
 
-    /// Deletes all data for a specific user.
-    ///
-    /// Removes the entire user directory and all contained files,
-    /// effectively deleting all stored data for the specified user.
-    /// This operation is irreversible.
+    /// Reads and decrypts a single note of a user from its per-note file.
     ///
     /// # Arguments
     ///
-    /// * `user_id` - User ID whose data should be deleted
+    /// * `user_id` - Unique identifier for the user
+    /// * `note_id` - ID of the note to load
+    /// * `crypto` - CryptoManager instance for decryption
     ///
     /// # Returns
     ///
-    /// * `Result<()>` - Ok if successful, Err if deletion failed
+    /// * `Result<Note>` - The note, or error if its file cannot be read or
+    ///   decrypted
+    pub fn load_user_note(
+        &self,
+        user_id: &str,
+        note_id: &str,
+        crypto: &CryptoManager,
+    ) -> Result<Note> {
+        let encrypted_data = fs::read(
+            self.user_notes_dir(user_id)
+                .join(format!("{}.enc", note_id)),
+        )?;
+        let json = crypto.decrypt(&encrypted_data, BlobContext::Note { user_id, note_id })?;
+        Ok(serde_json::from_slice(&json)?)
+    }
+
+    /// Decrypts all notes of a user in parallel, streaming them to a callback.
     ///
-    /// # Data Deleted
+    /// Each per-note file is decrypted on the rayon thread pool and handed to
+    /// `on_note` as soon as it is ready, so callers can show notes before
+    /// the whole vault has been processed. Notes that fail to decrypt are
+    /// logged and skipped instead of aborting the load. Notes still stored in
+    /// the single-file `notes.enc` bundle of older versions are read from it.
     ///
-    /// - Encrypted notes file
-    /// - Any other user-specific files in the directory
-    /// - The user directory itself
+    /// # Arguments
     ///
-    /// # Safety
+    /// * `user_id` - Unique identifier for the user
+    /// * `crypto` - CryptoManager instance for decryption
+    /// * `on_note` - Called once per decrypted note, possibly from several threads
     ///
-    /// - Only deletes data if user directory exists
-    /// - Logs successful deletions
-    /// - Handles non-existent directories gracefully
-    pub fn delete_user_data(&self, user_id: &str) -> Result<()> {
-        let user_dir = self.data_dir.join("users").join(user_id);
+    /// # Returns
+    ///
+    /// * `Result<Vec<LoadFailure>>` - The notes that could not be decrypted,
+    ///   and why; they are logged already
+    ///
+    /// # Errors
+    ///
+    /// * The notes directory cannot be read
+    /// * The legacy bundle cannot be read or decrypted
+    pub fn stream_user_notes(
+        &self,
+        user_id: &str,
+        crypto: &CryptoManager,
+        on_note: &(dyn Fn(Note) + Sync),
+    ) -> Result<Vec<LoadFailure>> {
+        let notes_dir = self.user_notes_dir(user_id);
+        let bundle_file = self.owner_dir(user_id).join("notes.enc");
 
-        if user_dir.exists() {
-            fs::remove_dir_all(&user_dir)?;
-            println!("Deleted all data for user {}", user_id);
+        let files: Vec<std::path::PathBuf> = match fs::read_dir(&notes_dir) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().and_then(|e| e.to_str()) == Some("enc"))
+                .collect(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        // A bundle left over from an interrupted migration still holds notes
+        // that have no per-note file yet
+        if bundle_file.exists() {
+            let encrypted_data = fs::read(&bundle_file)?;
+            let decrypted_data =
+                crypto.decrypt(&encrypted_data, BlobContext::NoteBundle { user_id })?;
+            let notes: HashMap<String, Note> = serde_json::from_slice(&decrypted_data)?;
+            log::debug!(
+                "Loaded {} notes from bundle for user {}",
+                notes.len(),
+                user_id
+            );
+
+            let migrated: HashSet<&str> = files
+                .iter()
+                .filter_map(|path| path.file_stem().and_then(|stem| stem.to_str()))
+                .collect();
+            notes
+                .into_values()
+                .filter(|note| !migrated.contains(note.id.as_str()))
+                .for_each(on_note);
+        } else if files.is_empty() {
+            log::info!(
+                "No notes found for user {}, starting with empty notes",
+                user_id
+            );
+            return Ok(Vec::new());
         }
 
-        Ok(())
+        let mut failed: Vec<LoadFailure> = files
+            .par_iter()
+            .filter_map(|path| {
+                let note_id = path
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .unwrap_or_default();
+                let data = match fs::read(path) {
+                    Ok(data) => data,
+                    Err(e) => {
+                        return Some(LoadFailure::new(
+                            note_id,
+                            FailureReason::Unreadable,
+                            &e.into(),
+                        ))
+                    }
+                };
+                let json = match crypto.decrypt(&data, BlobContext::Note { user_id, note_id }) {
+                    Ok(json) => json,
+                    Err(e) => {
+                        return Some(LoadFailure::new(
+                            note_id,
+                            FailureReason::of_ciphertext(&data),
+                            &e,
+                        ))
+                    }
+                };
+                match serde_json::from_slice::<Note>(&json) {
+                    Ok(note) => {
+                        on_note(note);
+                        None
+                    }
+                    Err(e) => Some(LoadFailure::new(
+                        note_id,
+                        FailureReason::DamagedContent,
+                        &e.into(),
+                    )),
+                }
+            })
+            .collect();
+        load_failures::settle(&mut failed, files.len());
+        load_failures::log_failures(user_id, &failed);
+
+        log::debug!(
+            "Loaded {} notes for user {} ({} failed)",
+            files.len() - failed.len(),
+            user_id,
+            failed.len()
+        );
+        Ok(failed)
     }
 
-    /// Calculates the total storage size for a user's data.
+    /// Rewrites the note and attachment files of a user that are stored in
+    /// an outdated ciphertext format.
     ///
-    /// Iterates through all files in the user's directory and sums
-    /// their sizes to provide storage usage information.
+    /// Runs before the notes are loaded, so no note is in memory yet and
+    /// nothing can be saved over a file while it is being upgraded. Files
+    /// that fail are left alone and reported, so a pending key derivation
+    /// upgrade can be postponed until they can be rewritten.
     ///
     /// # Arguments
     ///
-    /// * `user_id` - User ID to calculate storage for
+    /// * `user_id` - Unique identifier for the user
+    /// * `crypto` - CryptoManager instance, see `CryptoManager::needs_rewrite`
     ///
     /// # Returns
     ///
-    /// * `Result<u64>` - Total size in bytes, or error
-    ///
-    /// # Behavior
-    ///
-    /// - Returns 0 if user directory doesn't exist
-    /// - Only counts regular files, not directories
-    /// - Handles file system errors gracefully
-    /// - Useful for storage quotas and usage display
-    pub fn get_user_data_size(&self, user_id: &str) -> Result<u64> {
-        let user_dir = self.data_dir.join("users").join(user_id);
+    /// * `Result<UpgradeReport>` - The files rewritten and those that
+    ///   failed, or error if a directory cannot be read
+    pub fn upgrade_user_files(
+        &self,
+        user_id: &str,
+        crypto: &CryptoManager,
+    ) -> Result<UpgradeReport> {
+        let files = self.list_encrypted_files(user_id)?;
 
-        if !user_dir.exists() {
-            return Ok(0);
+        let results: Vec<Result<bool, String>> = files
+            .par_iter()
+            .map(|file| {
+                let context = file.context(user_id);
+                let path = &file.path;
+                fs::read(path)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|data| {
+                        if !crypto.needs_rewrite(&data) {
+                            return Ok(false);
+                        }
+                        let plaintext = crypto.decrypt(&data, context)?;
+                        secure_fs::write_private_file(path, crypto.encrypt(&plaintext, context)?)?;
+                        Ok(true)
+                    })
+                    .map_err(|e| format!("{}: {}", file.relative_path(), e))
+            })
+            .collect();
+
+        let mut report = UpgradeReport::default();
+        for result in results {
+            match result {
+                Ok(true) => report.upgraded += 1,
+                Ok(false) => {}
+                Err(failure) => report.failed.push(failure),
+            }
         }
 
-        let mut total_size = 0u64;
+        if report.upgraded > 0 {
+            log::info!(
+                "Upgraded {} files of user {} to the current format",
+                report.upgraded,
+                user_id
+            );
+        }
+        for failure in &report.failed {
+            log::warn!("Could not upgrade {}", failure);
+        }
+        Ok(report)
+    }
+
+    /// Re-encrypts one note or attachment file with the current key and
+    /// algorithm, for a key or algorithm change.
+    ///
+    /// A file removed in the meantime (e.g. a deleted attachment) is not an
+    /// error.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - Owner of the file, or `SHARED_VAULT_OWNER`
+    /// * `file` - The file, see `list_encrypted_files`
+    /// * `crypto` - CryptoManager instance that can still decrypt the file
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - Ok if the file was rewritten or is gone, or error
+    ///   naming the file
+    pub fn reencrypt_file(
+        &self,
+        user_id: &str,
+        file: &EncryptedFile,
+        crypto: &CryptoManager,
+    ) -> Result<()> {
+        let context = file.context(user_id);
+        let data = match fs::read(&file.path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(anyhow!("Could not read {}: {}", file.relative_path(), e)),
+        };
+        crypto
+            .decrypt(&data, context)
+            .and_then(|plaintext| crypto.encrypt(&plaintext, context))
+            .and_then(|data| Ok(secure_fs::write_private_file(&file.path, data)?))
+            .map_err(|e| anyhow!("Could not re-encrypt {}: {}", file.relative_path(), e))
+    }
 
-        for entry in fs::read_dir(&user_dir)? {
-            let entry = entry?;
-            let metadata = entry.metadata()?;
-            if metadata.is_file() {
-                total_size += metadata.len();
+    /// Lists the encrypted note, history and attachment files of a user.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - Unique identifier for the user, or `SHARED_VAULT_OWNER`
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<EncryptedFile>>` - Every `.enc` file in the notes,
+    ///   history and attachment directories, or error if a directory cannot
+    ///   be read
+    pub fn list_encrypted_files(&self, user_id: &str) -> Result<Vec<EncryptedFile>> {
+        let mut files = Vec::new();
+        for entry in read_dir_if_exists(&self.user_notes_dir(user_id))? {
+            if let Some(note_id) = enc_file_stem(&entry) {
+                files.push(EncryptedFile {
+                    path: entry,
+                    note_id,
+                    attachment_id: None,
+                    history: false,
+                });
+            }
+        }
+        for entry in read_dir_if_exists(&self.user_history_dir(user_id))? {
+            if let Some(note_id) = enc_file_stem(&entry) {
+                files.push(EncryptedFile {
+                    path: entry,
+                    note_id,
+                    attachment_id: None,
+                    history: true,
+                });
+            }
+        }
+        for note_dir in read_dir_if_exists(&self.user_attachments_dir(user_id))? {
+            let Some(note_id) = note_dir.file_name().and_then(|n| n.to_str()) else {
+                continue;
+            };
+            let note_id = note_id.to_string();
+            for entry in read_dir_if_exists(&note_dir)? {
+                if let Some(attachment_id) = enc_file_stem(&entry) {
+                    files.push(EncryptedFile {
+                        path: entry,
+                        note_id: note_id.clone(),
+                        attachment_id: Some(attachment_id),
+                        history: false,
+                    });
+                }
             }
         }
+        Ok(files)
+    }
 
-        Ok(total_size)
+    /// Describes the encrypted file of a note or attachment.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - Owner of the note, or `SHARED_VAULT_OWNER`
+    /// * `note_id` - ID of the note
+    /// * `attachment_id` - ID of the attachment, None for the note itself
+    ///
+    /// # Returns
+    ///
+    /// * `EncryptedFile` - Location and IDs of the file, which may not exist
+    pub fn encrypted_file(
+        &self,
+        user_id: &str,
+        note_id: &str,
+        attachment_id: Option<&str>,
+    ) -> EncryptedFile {
+        let path = match attachment_id {
+            Some(attachment_id) => self.attachment_path(user_id, note_id, attachment_id),
+            None => self
+                .user_notes_dir(user_id)
+                .join(format!("{}.enc", note_id)),
+        };
+        EncryptedFile {
+            path,
+            note_id: note_id.to_string(),
+            attachment_id: attachment_id.map(str::to_string),
+            history: false,
+        }
+    }
+
+    /// Checks whether the settings file of a user should be saved again,
+    /// see `CryptoManager::needs_rewrite`.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - Unique identifier for the user
+    /// * `crypto` - CryptoManager instance of the user
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - True if the file exists and is outdated
+    pub fn user_settings_outdated(&self, user_id: &str, crypto: &CryptoManager) -> bool {
+        fs::read(self.owner_dir(user_id).join("settings.enc"))
+            .is_ok_and(|data| crypto.needs_rewrite(&data))
+    }
+
+    /// Writes the advisory lock of a note, replacing any existing lock.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - Owner of the note
+    /// * `note_id` - ID of the locked note
+    /// * `lock` - The lock to write
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - Ok if successful, Err with details if failed
+    pub fn write_note_lock(&self, user_id: &str, note_id: &str, lock: &NoteLock) -> Result<()> {
+        fs::create_dir_all(self.user_locks_dir(user_id))?;
+        secure_fs::write_private_file(
+            &self.note_lock_path(user_id, note_id),
+            serde_json::to_vec(lock)?,
+        )?;
+        Ok(())
+    }
+
+    /// Reads the advisory lock of a note.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - Owner of the note
+    /// * `note_id` - ID of the note
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Option<NoteLock>>` - The lock, None if the note is not
+    ///   locked, or error if the lock file cannot be read
+    pub fn read_note_lock(&self, user_id: &str, note_id: &str) -> Result<Option<NoteLock>> {
+        match fs::read(self.note_lock_path(user_id, note_id)) {
+            Ok(data) => Ok(Some(serde_json::from_slice(&data)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Removes the advisory lock of a note.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - Owner of the note
+    /// * `note_id` - ID of the note
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - Ok if the lock was removed or didn't exist
+    pub fn remove_note_lock(&self, user_id: &str, note_id: &str) -> Result<()> {
+        match fs::remove_file(self.note_lock_path(user_id, note_id)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Lists the notes currently locked by other instances of the app.
+    ///
+    /// Stale and unreadable locks are ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - Owner of the notes
+    /// * `instance_id` - ID of this instance, whose own locks are ignored
+    ///
+    /// # Returns
+    ///
+    /// * `HashSet<String>` - IDs of the notes with a live lock of another instance
+    pub fn notes_locked_elsewhere(&self, user_id: &str, instance_id: &str) -> HashSet<String> {
+        let Ok(entries) = fs::read_dir(self.user_locks_dir(user_id)) else {
+            return HashSet::new();
+        };
+
+        entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter_map(|path| {
+                let note_id = path.file_stem()?.to_str()?.to_string();
+                let lock: NoteLock = serde_json::from_slice(&fs::read(&path).ok()?).ok()?;
+                (lock.instance_id != instance_id && !lock.is_stale()).then_some(note_id)
+            })
+            .collect()
+    }
+
+    /// Saves the encrypted preferences for a specific user.
+    ///
+    /// Serializes the settings to JSON, encrypts them with the user's
+    /// crypto manager and writes them to `settings.enc` in the user's
+    /// storage directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - Unique identifier for the user
+    /// * `settings` - The preferences to persist
+    /// * `crypto` - CryptoManager instance for encryption
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - Ok if successful, Err with details if failed
+    pub fn save_user_settings(
+        &self,
+        user_id: &str,
+        settings: &UserSettings,
+        crypto: &CryptoManager,
+    ) -> Result<()> {
+        let json_data = serde_json::to_string(settings)?;
+        let encrypted_data =
+            crypto.encrypt(json_data.as_bytes(), BlobContext::Settings { user_id })?;
+
+        let user_dir = self.data_dir.join("users").join(user_id);
+        fs::create_dir_all(&user_dir)?;
+
+        secure_fs::write_private_file(&user_dir.join("settings.enc"), &encrypted_data)?;
+        Ok(())
+    }
+
+    /// Loads the encrypted preferences for a specific user.
+    ///
+    /// Returns the default settings if the user has never changed any
+    /// preference (no `settings.enc` file yet).
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - Unique identifier for the user
+    /// * `crypto` - CryptoManager instance for decryption
+    ///
+    /// # Returns
+    ///
+    /// * `Result<UserSettings>` - The stored settings or defaults
+    ///
+    /// # Errors
+    ///
+    /// * File reading fails
+    /// * Decryption operation fails (wrong key, corrupted data)
+    /// * JSON deserialization fails
+    pub fn load_user_settings(
+        &self,
+        user_id: &str,
+        crypto: &CryptoManager,
+    ) -> Result<UserSettings> {
+        let settings_file = self
+            .data_dir
+            .join("users")
+            .join(user_id)
+            .join("settings.enc");
+
+        if !settings_file.exists() {
+            return Ok(UserSettings::default());
+        }
+
+        let encrypted_data = fs::read(&settings_file)?;
+        let decrypted_data = crypto.decrypt(&encrypted_data, BlobContext::Settings { user_id })?;
+        let settings: UserSettings = serde_json::from_slice(&decrypted_data)?;
+
+        Ok(settings)
+    }
+
+    /// Encrypts and saves the change journal of a user.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - Unique identifier for the user
+    /// * `journal` - The journal to persist
+    /// * `crypto` - CryptoManager instance for encryption
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - Ok if successful, Err with details if failed
+    pub fn save_journal(
+        &self,
+        user_id: &str,
+        journal: &Journal,
+        crypto: &CryptoManager,
+    ) -> Result<()> {
+        let json_data = serde_json::to_vec(journal)?;
+        let encrypted_data = crypto.encrypt(&json_data, BlobContext::Journal { user_id })?;
+
+        let user_dir = self.owner_dir(user_id);
+        fs::create_dir_all(&user_dir)?;
+        secure_fs::write_private_file(&user_dir.join("journal.enc"), &encrypted_data)?;
+        Ok(())
+    }
+
+    /// Loads the change journal of a user.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - Unique identifier for the user
+    /// * `crypto` - CryptoManager instance for decryption
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Option<Journal>>` - The journal, None if none was saved
+    ///   yet, or error if it cannot be read or decrypted
+    pub fn load_journal(&self, user_id: &str, crypto: &CryptoManager) -> Result<Option<Journal>> {
+        let journal_file = self.owner_dir(user_id).join("journal.enc");
+        if !journal_file.exists() {
+            return Ok(None);
+        }
+
+        let encrypted_data = fs::read(&journal_file)?;
+        let decrypted_data = crypto.decrypt(&encrypted_data, BlobContext::Journal { user_id })?;
+        Ok(Some(serde_json::from_slice(&decrypted_data)?))
+    }
+
+    /// Encrypts and saves the search index of a user.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - Unique identifier for the user
+    /// * `index` - The indexed words of the user's notes
+    /// * `crypto` - CryptoManager instance for encryption
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - Ok if successful, Err with details if failed
+    pub fn save_search_index(
+        &self,
+        user_id: &str,
+        index: &IndexSnapshot,
+        crypto: &CryptoManager,
+    ) -> Result<()> {
+        let json_data = serde_json::to_vec(index)?;
+        let encrypted_data = crypto.encrypt(&json_data, BlobContext::SearchIndex { user_id })?;
+
+        let user_dir = self.owner_dir(user_id);
+        fs::create_dir_all(&user_dir)?;
+        secure_fs::write_private_file(&user_dir.join("search_index.enc"), &encrypted_data)?;
+        Ok(())
+    }
+
+    /// Loads the saved search index of a user.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - Unique identifier for the user
+    /// * `crypto` - CryptoManager instance for decryption
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Option<IndexSnapshot>>` - The index, None if none was saved
+    ///   yet, or error if it cannot be read or decrypted
+    pub fn load_search_index(
+        &self,
+        user_id: &str,
+        crypto: &CryptoManager,
+    ) -> Result<Option<IndexSnapshot>> {
+        let index_file = self.owner_dir(user_id).join("search_index.enc");
+        if !index_file.exists() {
+            return Ok(None);
+        }
+
+        let encrypted_data = fs::read(&index_file)?;
+        let decrypted_data =
+            crypto.decrypt(&encrypted_data, BlobContext::SearchIndex { user_id })?;
+        Ok(Some(serde_json::from_slice(&decrypted_data)?))
+    }
+
+    /// Appends a login attempt to a user's login history.
+    ///
+    /// Only the most recent attempts are kept. The history is not
+    /// encrypted, since failed attempts are recorded without a key.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - Unique identifier for the user
+    /// * `record` - The attempt to record
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - Ok if the history was written
+    pub fn record_login(&self, user_id: &str, record: LoginRecord) -> Result<()> {
+        let mut history = self.load_login_history(user_id);
+        history.push(record);
+        let excess = history.len().saturating_sub(MAX_LOGIN_RECORDS);
+        history.drain(..excess);
+
+        let user_dir = self.owner_dir(user_id);
+        fs::create_dir_all(&user_dir)?;
+        secure_fs::write_private_file(
+            &user_dir.join("logins.json"),
+            serde_json::to_string_pretty(&history)?,
+        )?;
+        Ok(())
+    }
+
+    /// Loads the recent login attempts of a user.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - Unique identifier for the user
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<LoginRecord>` - Attempts, oldest first; empty if none were
+    ///   recorded or the file cannot be read
+    pub fn load_login_history(&self, user_id: &str) -> Vec<LoginRecord> {
+        fs::read(self.owner_dir(user_id).join("logins.json"))
+            .ok()
+            .and_then(|data| serde_json::from_slice(&data).ok())
+            .unwrap_or_default()
+    }
+
+    /// Reads the legacy notes file for the migration preview.
+    ///
+    /// The legacy format (before user-specific storage was implemented)
+    /// stored all notes in a single `notes.enc` file in the root data
+    /// directory, without user isolation. Every entry is parsed on its own,
+    /// so a partially corrupt file still yields the notes that are intact.
+    ///
+    /// # Arguments
+    ///
+    /// * `crypto` - CryptoManager instance for decryption
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Option<Vec<LegacyNoteEntry>>>` - One entry per note, None if
+    ///   there is no legacy file, or error if it cannot be decrypted at all
+    pub fn preview_legacy_notes(
+        &self,
+        crypto: &CryptoManager,
+    ) -> Result<Option<Vec<LegacyNoteEntry>>> {
+        let notes_file = self.data_dir.join("notes.enc");
+
+        if !notes_file.exists() {
+            return Ok(None);
+        }
+
+        let encrypted_data = fs::read(&notes_file)?;
+        let decrypted_data = crypto.decrypt(&encrypted_data, BlobContext::LegacyNotes)?;
+        let entries: serde_json::Map<String, serde_json::Value> =
+            serde_json::from_slice(&decrypted_data)?;
+
+        let mut entries: Vec<LegacyNoteEntry> = entries
+            .into_iter()
+            .map(|(key, value)| match serde_json::from_value::<Note>(value) {
+                Ok(note) => LegacyNoteEntry {
+                    label: note.title.clone(),
+                    result: Ok(note),
+                },
+                Err(e) => LegacyNoteEntry {
+                    label: key,
+                    result: Err(format!("Damaged entry: {}", e)),
+                },
+            })
+            .collect();
+        entries.sort_by(|a, b| a.label.cmp(&b.label));
+
+        Ok(Some(entries))
+    }
+
+    /// Migrates the readable legacy notes to user-specific storage.
+    ///
+    /// Each note is written to its own file so a failure only affects that
+    /// note. The legacy file is then renamed to `notes.enc.backup` rather
+    /// than deleted, so damaged entries are not lost.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - Target user ID for migration
+    /// * `entries` - Entries returned by `preview_legacy_notes`
+    /// * `crypto` - CryptoManager instance for encryption
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<LegacyNoteEntry>>` - The entries with their migration
+    ///   results, or error if the legacy file could not be backed up
+    pub fn migrate_legacy_notes(
+        &self,
+        user_id: &str,
+        entries: Vec<LegacyNoteEntry>,
+        crypto: &CryptoManager,
+    ) -> Result<Vec<LegacyNoteEntry>> {
+        let notes_dir = self.user_notes_dir(user_id);
+        fs::create_dir_all(&notes_dir)?;
+
+        let report: Vec<LegacyNoteEntry> = entries
+            .into_iter()
+            .map(|entry| {
+                let result = entry.result.and_then(|note| {
+                    self.write_note_file(user_id, &note, crypto)
+                        .map(|_| note)
+                        .map_err(|e| format!("Could not be saved: {}", e))
+                });
+                LegacyNoteEntry {
+                    label: entry.label,
+                    result,
+                }
+            })
+            .collect();
+
+        self.archive_legacy_notes()?;
+
+        let migrated = report.iter().filter(|entry| entry.result.is_ok()).count();
+        log::info!(
+            "Migrated {} of {} legacy notes to user-specific storage",
+            migrated,
+            report.len()
+        );
+        Ok(report)
+    }
+
+    /// Moves the legacy notes file aside as `notes.enc.backup`.
+    ///
+    /// Used after a migration and when the user declines to migrate, so the
+    /// migration is not offered again while the original data is kept.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - Ok if successful, Err if the file could not be renamed
+    pub fn archive_legacy_notes(&self) -> Result<()> {
+        let legacy_file = self.data_dir.join("notes.enc");
+        if legacy_file.exists() {
+            fs::rename(&legacy_file, self.data_dir.join("notes.enc.backup"))?;
+            log::info!("Legacy file backed up as notes.enc.backup");
+        }
+        Ok(())
+    }
+
+    /// Deletes all data for a specific user.
+    ///
+    /// Removes the entire user directory and all contained files,
+    /// effectively deleting all stored data for the specified user.
+    /// Every file is overwritten before it is unlinked (best effort, see
+    /// the `secure_fs` module for the limits on SSDs and copy-on-write file
+    /// systems). This operation is irreversible.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - User ID whose data should be deleted
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - Ok if successful, Err if deletion failed
+    ///
+    /// # Data Deleted
+    ///
+    /// - Encrypted note files
+    /// - Any other user-specific files in the directory
+    /// - The user directory itself
+    ///
+    /// # Safety
+    ///
+    /// - Only deletes data if user directory exists
+    /// - Logs successful deletions
+    /// - Handles non-existent directories gracefully
+    pub fn delete_user_data(&self, user_id: &str) -> Result<()> {
+        let user_dir = self.data_dir.join("users").join(user_id);
+
+        if user_dir.exists() {
+            secure_fs::secure_remove_dir_all(&user_dir)?;
+            log::info!("Deleted all data for user {}", user_id);
+        }
+
+        Ok(())
+    }
+
+    /// Lists the users that have a data directory.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<String>>` - Names of the directories in `users/`,
+    ///   which are user IDs, sorted
+    pub fn list_user_dirs(&self) -> Result<Vec<String>> {
+        let mut ids: Vec<String> = read_dir_if_exists(&self.data_dir.join("users"))?
+            .into_iter()
+            .filter(|path| path.is_dir())
+            .filter_map(|path| Some(path.file_name()?.to_str()?.to_string()))
+            .collect();
+        ids.sort();
+        Ok(ids)
+    }
+
+    /// Calculates the total storage size for a user's data.
+    ///
+    /// Iterates through all files in the user's directory and sums
+    /// their sizes to provide storage usage information.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - User ID to calculate storage for
+    ///
+    /// # Returns
+    ///
+    /// * `Result<u64>` - Total size in bytes, or error
+    ///
+    /// # Behavior
+    ///
+    /// - Returns 0 if user directory doesn't exist
+    /// - Counts the files in the user directory, its `notes/` and
+    ///   `history/` directories and the per-note `attachments/` directories
+    /// - Handles file system errors gracefully
+    /// - Useful for storage quotas and usage display
+    pub fn get_user_data_size(&self, user_id: &str) -> Result<u64> {
+        let user_dir = self.data_dir.join("users").join(user_id);
+
+        if !user_dir.exists() {
+            return Ok(0);
+        }
+
+        let mut total_size = 0u64;
+
+        let mut dirs = vec![
+            user_dir.clone(),
+            self.user_notes_dir(user_id),
+            self.user_history_dir(user_id),
+        ];
+        if let Ok(entries) = fs::read_dir(self.user_attachments_dir(user_id)) {
+            dirs.extend(
+                entries
+                    .filter_map(|entry| entry.ok())
+                    .map(|entry| entry.path()),
+            );
+        }
+
+        for dir in dirs {
+            if !dir.is_dir() {
+                continue;
+            }
+            for entry in fs::read_dir(&dir)? {
+                let entry = entry?;
+                let metadata = entry.metadata()?;
+                if metadata.is_file() {
+                    total_size += metadata.len();
+                }
+            }
+        }
+
+        Ok(total_size)
+    }
+
+    /// Returns when a file in a user's directory was last written.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - User ID whose directory to look through
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Option<SystemTime>>` - The latest modification time of any
+    ///   file, including subdirectories, None if there are no files
+    pub fn user_data_modified_at(&self, user_id: &str) -> Result<Option<SystemTime>> {
+        let mut latest = None;
+        let mut dirs = vec![self.data_dir.join("users").join(user_id)];
+        while let Some(dir) = dirs.pop() {
+            for path in read_dir_if_exists(&dir)? {
+                let metadata = fs::metadata(&path)?;
+                if metadata.is_dir() {
+                    dirs.push(path);
+                } else {
+                    latest = latest.max(Some(metadata.modified()?));
+                }
+            }
+        }
+        Ok(latest)
+    }
+
+    /// Counts a user's encrypted files that are not in the current
+    /// ciphertext format, see `CryptoManager::retire_legacy_formats`.
+    ///
+    /// Walks every `.enc` file in the user's directory, plus the
+    /// pre-multi-user `notes.enc` in the data directory while it hasn't been
+    /// migrated. Only the headers are read; nothing is decrypted.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - Unique identifier for the user
+    ///
+    /// # Returns
+    ///
+    /// * `Result<usize>` - Number of outdated files, or error if a file or
+    ///   directory cannot be read
+    pub fn count_outdated_files(&self, user_id: &str) -> Result<usize> {
+        let mut files = Vec::new();
+        let legacy_notes = self.data_dir.join("notes.enc");
+        if legacy_notes.exists() {
+            files.push(legacy_notes);
+        }
+        let mut dirs = vec![self.owner_dir(user_id)];
+        while let Some(dir) = dirs.pop() {
+            for path in read_dir_if_exists(&dir)? {
+                if path.is_dir() {
+                    dirs.push(path);
+                } else if path.extension().and_then(|e| e.to_str()) == Some("enc") {
+                    files.push(path);
+                }
+            }
+        }
+
+        let mut outdated = 0;
+        for path in files {
+            // Longer than any header, attachments can be large
+            let mut header = Vec::new();
+            fs::File::open(&path)?.take(16).read_to_end(&mut header)?;
+            if !Cipher::is_current_format(&header) {
+                outdated += 1;
+            }
+        }
+        Ok(outdated)
+    }
+
+    /// Measures a user's files against their storage quota.
+    ///
+    /// Counts the same files as `get_user_data_size`; the shared vault
+    /// belongs to no single user and doesn't count.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - User ID whose files to measure
+    /// * `quota_bytes` - The user's quota, None without a quota
+    ///
+    /// # Returns
+    ///
+    /// * `Option<QuotaUsage>` - The usage, None without a quota or if the
+    ///   files could not be measured
+    pub fn quota_usage(&self, user_id: &str, quota_bytes: Option<u64>) -> Option<QuotaUsage> {
+        let quota_bytes = quota_bytes?;
+        match self.get_user_data_size(user_id) {
+            Ok(used_bytes) => Some(QuotaUsage {
+                used_bytes,
+                quota_bytes,
+            }),
+            Err(e) => {
+                log::warn!("Could not measure storage for the quota: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Calculates the disk space used by specific notes of a user.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - User ID whose notes to measure
+    /// * `note_ids` - IDs of the notes to include
+    ///
+    /// # Returns
+    ///
+    /// * `u64` - Total size of the notes' encrypted files in bytes; notes
+    ///   without a file yet count as 0
+    pub fn get_notes_size<'a>(
+        &self,
+        user_id: &str,
+        note_ids: impl IntoIterator<Item = &'a str>,
+    ) -> u64 {
+        let notes_dir = self.user_notes_dir(user_id);
+        note_ids
+            .into_iter()
+            .filter_map(|id| fs::metadata(notes_dir.join(format!("{}.enc", id))).ok())
+            .map(|metadata| metadata.len())
+            .sum()
+    }
+
+    /// Calculates the disk space used by the attachment files of a user.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - User ID, or the shared vault owner
+    ///
+    /// # Returns
+    ///
+    /// * `u64` - Total size of the encrypted attachment files in bytes
+    pub fn get_attachment_files_size(&self, user_id: &str) -> u64 {
+        read_dir_if_exists(&self.user_attachments_dir(user_id))
+            .unwrap_or_default()
+            .iter()
+            .flat_map(|note_dir| read_dir_if_exists(note_dir).unwrap_or_default())
+            .filter_map(|path| fs::metadata(path).ok())
+            .filter(|metadata| metadata.is_file())
+            .map(|metadata| metadata.len())
+            .sum()
+    }
+
+    /// Returns the directory of the shared vault.
+    pub fn shared_vault_dir(&self) -> std::path::PathBuf {
+        self.owner_dir(SHARED_VAULT_OWNER)
+    }
+
+    /// Returns the directory holding the data of a user or the shared vault.
+    pub fn owner_dir(&self, owner_id: &str) -> std::path::PathBuf {
+        if owner_id == SHARED_VAULT_OWNER {
+            self.data_dir.join("shared")
+        } else {
+            self.data_dir.join("users").join(owner_id)
+        }
+    }
+
+    /// Returns the directory holding the note histories of a user.
+    fn user_history_dir(&self, user_id: &str) -> std::path::PathBuf {
+        self.owner_dir(user_id).join("history")
+    }
+
+    /// Returns the path of a note's history file.
+    fn note_history_path(&self, user_id: &str, note_id: &str) -> std::path::PathBuf {
+        self.user_history_dir(user_id)
+            .join(format!("{}.enc", note_id))
+    }
+
+    /// Returns the directory holding the per-note files of a user.
+    pub fn user_notes_dir(&self, user_id: &str) -> std::path::PathBuf {
+        self.owner_dir(user_id).join("notes")
+    }
+
+    /// Returns the directory holding the attachment directories of a user.
+    fn user_attachments_dir(&self, user_id: &str) -> std::path::PathBuf {
+        self.owner_dir(user_id).join("attachments")
+    }
+
+    /// Returns the directory holding the note lock files of a user.
+    fn user_locks_dir(&self, user_id: &str) -> std::path::PathBuf {
+        self.owner_dir(user_id).join("locks")
+    }
+
+    /// Returns the path of the lock file of a note.
+    fn note_lock_path(&self, user_id: &str, note_id: &str) -> std::path::PathBuf {
+        self.user_locks_dir(user_id)
+            .join(format!("{}.lock", note_id))
+    }
+
+    /// Returns the path of the encrypted file of an attachment.
+    fn attachment_path(
+        &self,
+        user_id: &str,
+        note_id: &str,
+        attachment_id: &str,
+    ) -> std::path::PathBuf {
+        self.user_attachments_dir(user_id)
+            .join(note_id)
+            .join(format!("{}.enc", attachment_id))
+    }
+}
+
+/// An encrypted note, note history or attachment file on disk.
+pub struct EncryptedFile {
+    /// Location of the file
+    pub path: std::path::PathBuf,
+    /// ID of the note, or of the note the file is attached to
+    pub note_id: String,
+    /// ID of the attachment, None for a note or history file
+    pub attachment_id: Option<String>,
+    /// Whether the file holds the earlier versions of the note
+    pub history: bool,
+}
+
+impl EncryptedFile {
+    /// Returns the context the file was encrypted with.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - Owner of the file
+    ///
+    /// # Returns
+    ///
+    /// * `BlobContext` - A note, note history or attachment context
+    pub fn context<'a>(&'a self, user_id: &'a str) -> BlobContext<'a> {
+        match &self.attachment_id {
+            Some(attachment_id) => BlobContext::Attachment {
+                user_id,
+                note_id: &self.note_id,
+                attachment_id,
+            },
+            None if self.history => BlobContext::NoteHistory {
+                user_id,
+                note_id: &self.note_id,
+            },
+            None => BlobContext::Note {
+                user_id,
+                note_id: &self.note_id,
+            },
+        }
+    }
+
+    /// Returns the path of the file relative to its vault directory.
+    ///
+    /// # Returns
+    ///
+    /// * `String` - E.g. "notes/<note_id>.enc"
+    pub fn relative_path(&self) -> String {
+        let name = self
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        match self.attachment_id {
+            Some(_) => format!("attachments/{}/{}", self.note_id, name),
+            None if self.history => format!("history/{}", name),
+            None => format!("notes/{}", name),
+        }
+    }
+}
+
+/// Lists the entries of a directory, or nothing if it doesn't exist.
+fn read_dir_if_exists(dir: &std::path::Path) -> Result<Vec<std::path::PathBuf>> {
+    match fs::read_dir(dir) {
+        Ok(entries) => Ok(entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .collect()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Returns the name of an `.enc` file without its extension, which is the
+/// ID of the note or attachment stored in it.
+fn enc_file_stem(path: &std::path::Path) -> Option<String> {
+    if path.extension().and_then(|e| e.to_str()) != Some("enc") {
+        return None;
     }
+    Some(path.file_stem()?.to_str()?.to_string())
 }
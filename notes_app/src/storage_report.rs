@@ -0,0 +1,169 @@
+//! # Storage Report Module
+//!
+//! Measures how much space the notes and attachments of a vault take up,
+//! for the storage section of the settings window. The content size is
+//! what gets encrypted (a note's saved JSON, an attachment's original
+//! file); the size on disk is that of the encrypted files. Their ratio
+//! shows the encryption overhead, and the gain once files are compressed.
+//!
+//! Measuring serializes every note, so the report is taken when the
+//! settings window opens rather than on every frame.
+
+use crate::app::NotesApp;
+use crate::note::Note;
+use crate::storage::{StorageManager, SHARED_VAULT_OWNER};
+
+/// Space used by the notes and attachments of one vault.
+#[derive(Debug, Clone, Default)]
+pub struct VaultUsage {
+    /// Number of notes, including trashed ones
+    pub note_count: usize,
+    /// Size of the notes before encryption in bytes
+    pub note_content_bytes: u64,
+    /// Size of the encrypted note files in bytes
+    pub note_file_bytes: u64,
+    /// Size of the attached files before encryption in bytes
+    pub attachment_content_bytes: u64,
+    /// Size of the encrypted attachment files in bytes
+    pub attachment_file_bytes: u64,
+}
+
+impl VaultUsage {
+    /// Measures the notes of a vault.
+    ///
+    /// # Arguments
+    ///
+    /// * `storage` - The storage manager
+    /// * `owner_id` - User ID, or `SHARED_VAULT_OWNER` for the shared vault
+    /// * `notes` - The notes of the vault in memory
+    ///
+    /// # Returns
+    ///
+    /// * `Self` - The measured usage
+    pub fn measure<'a>(
+        storage: &StorageManager,
+        owner_id: &str,
+        notes: impl IntoIterator<Item = &'a Note>,
+    ) -> Self {
+        let notes: Vec<&Note> = notes.into_iter().collect();
+        Self {
+            note_count: notes.len(),
+            note_content_bytes: notes
+                .iter()
+                .map(|note| serde_json::to_vec(note).map_or(0, |json| json.len() as u64))
+                .sum(),
+            note_file_bytes: storage
+                .get_notes_size(owner_id, notes.iter().map(|note| note.id.as_str())),
+            attachment_content_bytes: notes
+                .iter()
+                .flat_map(|note| &note.attachments)
+                .map(|attachment| attachment.size)
+                .sum(),
+            attachment_file_bytes: storage.get_attachment_files_size(owner_id),
+        }
+    }
+
+    /// Returns the size of the content before encryption.
+    pub fn content_bytes(&self) -> u64 {
+        self.note_content_bytes + self.attachment_content_bytes
+    }
+
+    /// Returns the size of the encrypted files.
+    pub fn file_bytes(&self) -> u64 {
+        self.note_file_bytes + self.attachment_file_bytes
+    }
+
+    /// Returns the size on disk relative to the content size.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<f64>` - Above 1.0 if the files are larger than their
+    ///   content, None for an empty vault
+    pub fn disk_ratio(&self) -> Option<f64> {
+        (self.content_bytes() > 0).then(|| self.file_bytes() as f64 / self.content_bytes() as f64)
+    }
+
+    /// Returns the share of the disk space used by attachments.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<f64>` - Between 0.0 and 1.0, None for an empty vault
+    pub fn attachment_share(&self) -> Option<f64> {
+        (self.file_bytes() > 0)
+            .then(|| self.attachment_file_bytes as f64 / self.file_bytes() as f64)
+    }
+}
+
+/// Storage used by the current user, as shown in the settings window.
+#[derive(Debug, Clone, Default)]
+pub struct StorageReport {
+    /// The user's private notes
+    pub private: VaultUsage,
+    /// The shared vault, if the user is a member
+    pub shared: Option<VaultUsage>,
+    /// Other files of the user such as settings, in bytes
+    pub other_file_bytes: u64,
+}
+
+impl NotesApp {
+    /// Measures the storage used by the current user.
+    ///
+    /// # Returns
+    ///
+    /// * `StorageReport` - The report, empty when logged out
+    pub fn measure_storage(&self) -> StorageReport {
+        let Some(user) = &self.current_user else {
+            return StorageReport::default();
+        };
+
+        let private = VaultUsage::measure(
+            &self.storage_manager,
+            &user.id,
+            self.notes.values().filter(|note| !note.shared),
+        );
+        let shared = self.shared_vault.as_ref().map(|_| {
+            VaultUsage::measure(
+                &self.storage_manager,
+                SHARED_VAULT_OWNER,
+                self.notes.values().filter(|note| note.shared),
+            )
+        });
+        let other_file_bytes = self
+            .storage_manager
+            .get_user_data_size(&user.id)
+            .unwrap_or_default()
+            .saturating_sub(private.file_bytes());
+
+        StorageReport {
+            private,
+            shared,
+            other_file_bytes,
+        }
+    }
+}
+
+/// Formats a size in bytes for display, e.g. "1.4 MB".
+///
+/// # Arguments
+///
+/// * `bytes` - The size in bytes
+///
+/// # Returns
+///
+/// * `String` - The size in B, KB, MB or GB (powers of 1024)
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
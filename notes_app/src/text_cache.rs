@@ -0,0 +1,240 @@
+//! # Text Cache Module
+//!
+//! Keeps what is derived from the text of the open note between frames,
+//! so notes of several megabytes stay responsive. egui redraws the window
+//! many times a second, also while nothing is typed (cursor blinking,
+//! hovering, the auto-save timer), and without these caches every frame
+//! scanned the whole note:
+//!
+//! - `EditorLayoutCache` keeps the highlighted layout of the editor text
+//!   and only rebuilds it when the text, the language or the style changes
+//! - `NoteTextStats` keeps the word count and the outline headings and
+//!   only recounts them when the note was modified
+//! - `PreviewBlocks` keeps the parsed Markdown of the preview and only
+//!   parses it again when the note was modified
+//!
+//! Notes larger than `LARGE_NOTE_BYTES` are laid out as plain text: syntax
+//! highlighting and link underlining take longer than a frame at that size.
+//! Links still open on Ctrl+click. The Markdown preview is off for them.
+
+use crate::app::NotesApp;
+use crate::highlight;
+use crate::markdown::{self, Block};
+use crate::note::Note;
+use crate::outline::{self, Heading};
+use chrono::{DateTime, Utc};
+use eframe::egui;
+use egui::text::LayoutJob;
+use std::sync::Arc;
+
+/// Size from which notes are laid out without highlighting, in bytes.
+pub const LARGE_NOTE_BYTES: usize = 512 * 1024;
+
+/// What the layout of the editor text depends on besides the text.
+#[derive(Debug, Clone, PartialEq)]
+struct LayoutStyle {
+    /// Language of a code note
+    language: Option<String>,
+    /// Font of unhighlighted text
+    font: egui::FontId,
+    /// Color of unhighlighted text
+    color: egui::Color32,
+    /// Whether the dark theme is active, which changes the highlighting colors
+    dark_mode: bool,
+}
+
+/// The laid-out text of the editor, reused while the text stays the same.
+///
+/// Holds a copy of the text to tell whether it changed: comparing takes a
+/// fraction of the time of highlighting it again. The copy is dropped with
+/// `clear` at logout.
+#[derive(Default)]
+pub struct EditorLayoutCache {
+    /// The text the job was built for
+    text: String,
+    /// Style the job was built with, None if nothing is cached
+    style: Option<LayoutStyle>,
+    /// The highlighted text, without wrapping set
+    job: LayoutJob,
+    /// Number of lines of the text
+    line_count: usize,
+}
+
+impl EditorLayoutCache {
+    /// Lays out the editor text, reusing the highlighting of the last call
+    /// if the text and style didn't change.
+    ///
+    /// # Arguments
+    ///
+    /// * `ui` - The editor UI, for the style and the fonts
+    /// * `text` - The text to lay out
+    /// * `wrap_width` - Width to wrap lines at
+    /// * `language` - The language of a code note, None for other notes
+    ///
+    /// # Returns
+    ///
+    /// * `Arc<egui::Galley>` - The laid-out text for the `TextEdit` layouter
+    pub fn layout(
+        &mut self,
+        ui: &egui::Ui,
+        text: &str,
+        wrap_width: f32,
+        language: Option<&str>,
+    ) -> Arc<egui::Galley> {
+        let text_style = if language.is_some() {
+            egui::TextStyle::Monospace
+        } else {
+            egui::TextStyle::Body
+        };
+        let style = LayoutStyle {
+            language: language.map(str::to_string),
+            font: text_style.resolve(ui.style()),
+            color: ui
+                .visuals()
+                .override_text_color
+                .unwrap_or_else(|| ui.visuals().widgets.inactive.text_color()),
+            dark_mode: ui.visuals().dark_mode,
+        };
+
+        if self.style.as_ref() != Some(&style) || self.text != text {
+            self.job = if text.len() <= LARGE_NOTE_BYTES
+                && highlight::needs_highlighting(text, language)
+            {
+                highlight::layout_job(ui, text, language)
+            } else {
+                LayoutJob::simple(
+                    text.to_string(),
+                    style.font.clone(),
+                    style.color,
+                    f32::INFINITY,
+                )
+            };
+            self.text.clear();
+            self.text.push_str(text);
+            self.line_count = text.bytes().filter(|&b| b == b'\n').count() + 1;
+            self.style = Some(style);
+        }
+
+        let mut job = self.job.clone();
+        job.wrap.max_width = wrap_width;
+        ui.fonts(|fonts| fonts.layout_job(job))
+    }
+
+    /// Returns the number of lines of the text laid out last.
+    ///
+    /// # Returns
+    ///
+    /// * `usize` - Line count, at least 1
+    pub fn line_count(&self) -> usize {
+        self.line_count.max(1)
+    }
+
+    /// Drops the cached text.
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// Identifies the text of a note at one point in time.
+#[derive(Debug, Clone, PartialEq)]
+struct NoteVersion {
+    /// ID of the note
+    note_id: String,
+    /// Modification time of the note
+    modified_at: DateTime<Utc>,
+    /// Length of the content, in bytes
+    content_len: usize,
+}
+
+impl NoteVersion {
+    /// Returns the current version of a note.
+    fn of(note: &Note) -> Self {
+        Self {
+            note_id: note.id.clone(),
+            modified_at: note.modified_at,
+            content_len: note.content.len(),
+        }
+    }
+}
+
+/// Word count and headings of a note, recounted when it is modified.
+pub struct NoteTextStats {
+    /// Version of the note when counted
+    version: NoteVersion,
+    /// Number of words of the content
+    pub word_count: usize,
+    /// Markdown headings of the content, see the outline module
+    pub headings: Vec<Heading>,
+}
+
+/// Markdown preview blocks of a note, parsed again when it is modified.
+pub struct PreviewBlocks {
+    /// Version of the note when parsed
+    version: NoteVersion,
+    /// The blocks of the content, see the markdown module
+    pub blocks: Vec<Block>,
+}
+
+impl NotesApp {
+    /// Returns the word count and headings of a note, counting them again
+    /// only if the note changed since the last call.
+    ///
+    /// # Arguments
+    ///
+    /// * `note_id` - The ID of the note
+    ///
+    /// # Returns
+    ///
+    /// * `Option<&NoteTextStats>` - The statistics, None if the note
+    ///   doesn't exist
+    pub fn note_text_stats(&mut self, note_id: &str) -> Option<&NoteTextStats> {
+        let note = self.notes.get(note_id)?;
+        let version = NoteVersion::of(note);
+        if self
+            .note_text_stats
+            .as_ref()
+            .is_none_or(|stats| stats.version != version)
+        {
+            self.note_text_stats = Some(NoteTextStats {
+                version,
+                word_count: note.word_count(),
+                headings: outline::headings(&note.content),
+            });
+        }
+        self.note_text_stats.as_ref()
+    }
+
+    /// Returns the Markdown preview blocks of a note, parsing the note
+    /// again only if it changed since the last call.
+    ///
+    /// A code note is a single code block in its language.
+    ///
+    /// # Arguments
+    ///
+    /// * `note_id` - The ID of the note
+    ///
+    /// # Returns
+    ///
+    /// * `Option<&[Block]>` - The blocks, None if the note doesn't exist
+    pub fn preview_blocks(&mut self, note_id: &str) -> Option<&[Block]> {
+        let note = self.notes.get(note_id)?;
+        let version = NoteVersion::of(note);
+        if self
+            .preview_blocks
+            .as_ref()
+            .is_none_or(|preview| preview.version != version)
+        {
+            let blocks = match &note.language {
+                Some(language) => vec![Block::Code {
+                    language: language.clone(),
+                    code: note.content.clone(),
+                }],
+                None => markdown::parse(&note.content),
+            };
+            self.preview_blocks = Some(PreviewBlocks { version, blocks });
+        }
+        self.preview_blocks
+            .as_ref()
+            .map(|preview| preview.blocks.as_slice())
+    }
+}
@@ -0,0 +1,313 @@
+//! # Trusted Time Module
+//!
+//! Verifies the system clock against a time server, so note timestamps and
+//! the login history can't be skewed by simply changing the clock.
+//!
+//! When enabled in the settings, a background thread asks the server for
+//! the time with SNTP (RFC 4330) after login and then every
+//! [`RECHECK_INTERVAL`]. The measured offset is kept for the whole process
+//! and [`now`] adds it to the local clock; the app takes its timestamps
+//! from there instead of `Utc::now()`. Until a server answered, or when
+//! verification is off, [`now`] is the local clock.
+//!
+//! The offset outlives a logout, so login attempts made later in the same
+//! run are recorded with the verified time as well. It is dropped when a
+//! user without verification logs in.
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, TimeDelta, Utc};
+use chrono_tz::Europe::Zurich;
+use eframe::egui;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs, UdpSocket};
+use std::sync::{mpsc, RwLock};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Time server used until the user picks another one.
+pub const DEFAULT_TIME_SERVER: &str = "pool.ntp.org";
+
+/// Port of time servers given without one.
+const NTP_PORT: u16 = 123;
+
+/// How long to wait for the server's reply.
+const QUERY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How often the time is checked again while logged in.
+pub const RECHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Difference between the local clock and the server above which the user
+/// is warned, in seconds.
+pub const MAX_CLOCK_SKEW_SECS: i64 = 120;
+
+/// Seconds from the NTP epoch (1900) to the Unix epoch (1970).
+const NTP_UNIX_OFFSET: u64 = 2_208_988_800;
+
+/// Offset of the verified time to the local clock, None if not verified.
+static OFFSET: RwLock<Option<TimeDelta>> = RwLock::new(None);
+
+/// Returns the current time, corrected by the verified offset if any.
+///
+/// # Returns
+///
+/// * `DateTime<Utc>` - The current time
+pub fn now() -> DateTime<Utc> {
+    Utc::now() + offset().unwrap_or_else(TimeDelta::zero)
+}
+
+/// Returns the verified offset to the local clock.
+///
+/// # Returns
+///
+/// * `Option<TimeDelta>` - Server time minus local time, positive if the
+///   local clock is behind; None if the time wasn't verified
+pub fn offset() -> Option<TimeDelta> {
+    *OFFSET.read().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Sets the offset [`now`] adds to the local clock.
+///
+/// # Arguments
+///
+/// * `offset` - The verified offset, or None to use the local clock
+pub fn set_offset(offset: Option<TimeDelta>) {
+    *OFFSET.write().unwrap_or_else(|e| e.into_inner()) = offset;
+}
+
+/// Result of asking a time server for the time.
+#[derive(Debug, Clone)]
+pub struct TimeCheck {
+    /// The server that answered
+    pub server: String,
+    /// Server time minus local time, positive if the local clock is behind
+    pub offset: TimeDelta,
+    /// Time the request and reply spent on the network
+    pub round_trip: TimeDelta,
+    /// Verified time of the check
+    pub checked_at: DateTime<Utc>,
+}
+
+impl TimeCheck {
+    /// Checks whether the local clock is off by more than
+    /// [`MAX_CLOCK_SKEW_SECS`].
+    pub fn is_skewed(&self) -> bool {
+        self.offset.abs() > TimeDelta::seconds(MAX_CLOCK_SKEW_SECS)
+    }
+
+    /// Describes how far the local clock is off, for the settings window.
+    ///
+    /// # Returns
+    ///
+    /// * `String` - E.g. "The computer's clock is 3 min behind"
+    pub fn describe_offset(&self) -> String {
+        if self.offset.abs() < TimeDelta::seconds(1) {
+            return "The computer's clock is right".to_string();
+        }
+        format!(
+            "The computer's clock is {} {}",
+            format_duration(self.offset.abs()),
+            if self.offset > TimeDelta::zero() {
+                "behind"
+            } else {
+                "ahead"
+            }
+        )
+    }
+
+    /// Returns the security warning for a skewed clock.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<String>` - The warning, None if the clock is close enough
+    pub fn warning(&self) -> Option<String> {
+        self.is_skewed().then(|| {
+            format!(
+                "{} according to {}; timestamps use the server's time",
+                self.describe_offset(),
+                self.server
+            )
+        })
+    }
+
+    /// Formats the time of the check for display in Swiss timezone.
+    ///
+    /// # Returns
+    ///
+    /// * `String` - Time as "DD.MM.YYYY HH:MM:SS"
+    pub fn format_checked_at(&self) -> String {
+        self.checked_at
+            .with_timezone(&Zurich)
+            .format("%d.%m.%Y %H:%M:%S")
+            .to_string()
+    }
+}
+
+/// Background checker of the local clock.
+pub struct TimeVerifier {
+    /// Results of the checks, one per [`RECHECK_INTERVAL`]
+    receiver: mpsc::Receiver<Result<TimeCheck>>,
+    /// Dropped together with the verifier, which ends the thread
+    _stop: mpsc::Sender<()>,
+}
+
+impl TimeVerifier {
+    /// Starts checking the time on a background thread, right away and
+    /// then every [`RECHECK_INTERVAL`].
+    ///
+    /// The thread ends on its own once the verifier is dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The egui context to wake up when a check is done
+    /// * `server` - Host name or address of the time server, optionally
+    ///   with a port
+    ///
+    /// # Returns
+    ///
+    /// * `Self` - The running verifier
+    pub fn start(ctx: &egui::Context, server: &str) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let (stop, stopped) = mpsc::channel::<()>();
+        let ctx = ctx.clone();
+        let host = server.to_string();
+
+        thread::spawn(move || loop {
+            if sender.send(query(&host)).is_err() {
+                break;
+            }
+            ctx.request_repaint();
+            if stopped.recv_timeout(RECHECK_INTERVAL) != Err(mpsc::RecvTimeoutError::Timeout) {
+                break;
+            }
+        });
+
+        Self {
+            receiver,
+            _stop: stop,
+        }
+    }
+
+    /// Returns the result of a finished check, if any.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Result<TimeCheck>>` - The latest result, None while waiting
+    pub fn poll(&self) -> Option<Result<TimeCheck>> {
+        self.receiver.try_iter().last()
+    }
+}
+
+/// Asks a time server for the time with a single SNTP request.
+///
+/// # Arguments
+///
+/// * `server` - Host name or address, optionally with a port
+///
+/// # Returns
+///
+/// * `Result<TimeCheck>` - The offset of the local clock, or Err if the
+///   server can't be reached or gave no usable answer
+pub fn query(server: &str) -> Result<TimeCheck> {
+    let address = resolve(server)?;
+    let local: SocketAddr = if address.is_ipv4() {
+        (Ipv4Addr::UNSPECIFIED, 0).into()
+    } else {
+        (Ipv6Addr::UNSPECIFIED, 0).into()
+    };
+    let socket = UdpSocket::bind(local)?;
+    socket.set_read_timeout(Some(QUERY_TIMEOUT))?;
+    socket.set_write_timeout(Some(QUERY_TIMEOUT))?;
+    socket.connect(address)?;
+
+    // Leap indicator 0, version 4, mode 3 (client)
+    let mut request = [0u8; 48];
+    request[0] = 0b00_100_011;
+    let sent = ntp_timestamp(SystemTime::now());
+    request[40..48].copy_from_slice(&sent.to_be_bytes());
+    socket.send(&request)?;
+
+    let mut reply = [0u8; 48];
+    let length = socket
+        .recv(&mut reply)
+        .map_err(|e| anyhow!("No answer from {}: {}", server, e))?;
+    let received = ntp_timestamp(SystemTime::now());
+
+    if length < reply.len() || reply[0] & 0b111 != 4 {
+        return Err(anyhow!("{} did not answer as a time server", server));
+    }
+    if reply[1] == 0 {
+        // Stratum 0 is a "kiss-o'-death", the code says why
+        let code = String::from_utf8_lossy(&reply[12..16]).into_owned();
+        return Err(anyhow!("{} refused the request ({})", server, code.trim()));
+    }
+    if reply[0] >> 6 == 3 {
+        return Err(anyhow!("The clock of {} is not synchronized", server));
+    }
+    if read_timestamp(&reply[24..32]) != sent {
+        return Err(anyhow!("{} answered a different request", server));
+    }
+    let server_received = read_timestamp(&reply[32..40]);
+    let server_sent = read_timestamp(&reply[40..48]);
+
+    // Differences taken with wrapping arithmetic stay right across the NTP
+    // era rollover in 2036
+    let outbound = server_received.wrapping_sub(sent) as i64;
+    let inbound = server_sent.wrapping_sub(received) as i64;
+    let offset = fixed_point_delta(outbound / 2 + inbound / 2);
+    let round_trip = fixed_point_delta(
+        (received.wrapping_sub(sent) as i64) - (server_sent.wrapping_sub(server_received) as i64),
+    );
+
+    Ok(TimeCheck {
+        server: server.to_string(),
+        offset,
+        round_trip,
+        checked_at: Utc::now() + offset,
+    })
+}
+
+/// Looks up the address of a time server.
+fn resolve(server: &str) -> Result<SocketAddr> {
+    let server = server.trim();
+    if server.is_empty() {
+        return Err(anyhow!("No time server set"));
+    }
+    // "host:port" first, then the host alone on the NTP port
+    server
+        .to_socket_addrs()
+        .or_else(|_| (server, NTP_PORT).to_socket_addrs())
+        .map_err(|e| anyhow!("Can't find the time server {}: {}", server, e))?
+        .next()
+        .ok_or_else(|| anyhow!("Can't find the time server {}", server))
+}
+
+/// Converts a system time to a 64-bit NTP timestamp, 32.32 fixed point
+/// seconds since 1900.
+fn ntp_timestamp(time: SystemTime) -> u64 {
+    let since_unix = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let seconds = since_unix.as_secs() + NTP_UNIX_OFFSET;
+    let fraction = (u64::from(since_unix.subsec_nanos()) << 32) / 1_000_000_000;
+    (seconds << 32) | fraction
+}
+
+/// Reads a big-endian NTP timestamp from a reply.
+fn read_timestamp(bytes: &[u8]) -> u64 {
+    let mut buffer = [0u8; 8];
+    buffer.copy_from_slice(bytes);
+    u64::from_be_bytes(buffer)
+}
+
+/// Converts a difference of NTP timestamps to a TimeDelta.
+fn fixed_point_delta(fixed: i64) -> TimeDelta {
+    TimeDelta::nanoseconds(((i128::from(fixed) * 1_000_000_000) >> 32) as i64)
+}
+
+/// Formats a clock difference, rounded, e.g. "3 s", "12 min" or "2 days".
+fn format_duration(duration: TimeDelta) -> String {
+    let seconds = duration.num_seconds();
+    match seconds {
+        0..=119 => format!("{} s", seconds),
+        120..=7199 => format!("{} min", (seconds + 30) / 60),
+        7200..=172_799 => format!("{} h", (seconds + 1800) / 3600),
+        _ => format!("{} days", (seconds + 43_200) / 86_400),
+    }
+}
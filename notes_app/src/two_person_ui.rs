@@ -0,0 +1,356 @@
+//! # Two-Person Unlock UI Module
+//!
+//! Turning two-person unlock on and off from the settings, and the second
+//! passphrase prompt shown after the password at login.
+//!
+//! With two-person unlock, the vault key is random and split into two
+//! shares by the crypto module: one opens with the password, the other
+//! with a second passphrase held by someone else. Turning it on or off
+//! replaces the key, so all files are re-encrypted by a `ReencryptJob`,
+//! like for a password change. Both passphrases are needed either way.
+
+use crate::app::NotesApp;
+use crate::auth::{AuthResult, AuthStage};
+use crate::login_history::LoginOutcome;
+use crate::reencrypt::ReencryptJob;
+use eframe::egui;
+use std::sync::mpsc;
+use std::thread;
+
+/// Minimum length of the second passphrase, as for passwords.
+const MIN_PASSPHRASE_LEN: usize = 6;
+
+/// State of the dialog turning two-person unlock on or off.
+pub struct TwoPersonDialog {
+    /// True to turn two-person unlock on, false to turn it off
+    enable: bool,
+    /// The user's password
+    password: String,
+    /// The second person's passphrase
+    passphrase: String,
+    /// The passphrase again, when turning it on
+    confirm_passphrase: String,
+    /// Why the last attempt failed
+    error: Option<String>,
+}
+
+impl NotesApp {
+    /// Returns whether the current user's key is split for two-person
+    /// unlock.
+    pub fn two_person_unlock_enabled(&self) -> bool {
+        match (&self.crypto_manager, &self.current_user) {
+            (Some(crypto_manager), Some(user)) => {
+                crypto_manager.two_person_unlock_enabled(&user.id)
+            }
+            _ => false,
+        }
+    }
+
+    /// Opens the dialog turning two-person unlock on or off.
+    ///
+    /// # Arguments
+    ///
+    /// * `enable` - True to turn it on, false to turn it off
+    pub fn open_two_person_dialog(&mut self, enable: bool) {
+        self.two_person_dialog = Some(TwoPersonDialog {
+            enable,
+            password: String::new(),
+            passphrase: String::new(),
+            confirm_passphrase: String::new(),
+            error: None,
+        });
+    }
+
+    /// Renders the dialog turning two-person unlock on or off, if open.
+    ///
+    /// The dialog stays open while the files are re-encrypted and closes
+    /// once the change is done.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The egui context for rendering
+    pub fn render_two_person_dialog(&mut self, ctx: &egui::Context) {
+        let Some(dialog) = &mut self.two_person_dialog else {
+            return;
+        };
+        let reencrypting = self.reencrypt_job.is_some();
+
+        let mut open = true;
+        let mut submit = false;
+        let title = if dialog.enable {
+            "🔐 Turn On Two-Person Unlock"
+        } else {
+            "🔐 Turn Off Two-Person Unlock"
+        };
+        egui::Window::new(title)
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .default_width(340.0)
+            .show(ctx, |ui| {
+                if dialog.enable {
+                    ui.label(
+                        "Unlocking will need your password and a second passphrase, \
+                         entered one after the other. Let the second person type the \
+                         passphrase; neither of you can open the notes alone.",
+                    );
+                    ui.small(
+                        "If the second passphrase is lost, the notes can't be opened \
+                         any more. Export a migration bundle first if in doubt.",
+                    );
+                } else {
+                    ui.label(
+                        "Unlocking will only need your password again. The second \
+                         person has to agree by entering the passphrase.",
+                    );
+                }
+                ui.add_space(10.0);
+
+                ui.label("Your password:");
+                ui.add(egui::TextEdit::singleline(&mut dialog.password).password(true));
+                ui.label("Second passphrase:");
+                let mut response =
+                    ui.add(egui::TextEdit::singleline(&mut dialog.passphrase).password(true));
+                if dialog.enable {
+                    ui.label("Confirm second passphrase:");
+                    response = ui.add(
+                        egui::TextEdit::singleline(&mut dialog.confirm_passphrase).password(true),
+                    );
+                }
+                ui.add_space(10.0);
+
+                let can_submit = !reencrypting
+                    && !dialog.password.is_empty()
+                    && dialog.passphrase.len() >= MIN_PASSPHRASE_LEN
+                    && (!dialog.enable || dialog.passphrase == dialog.confirm_passphrase);
+                let button = if dialog.enable { "Turn On" } else { "Turn Off" };
+                if ui
+                    .add_enabled(can_submit, egui::Button::new(button))
+                    .clicked()
+                    || (can_submit
+                        && response.lost_focus()
+                        && ui.input(|i| i.key_pressed(egui::Key::Enter)))
+                {
+                    submit = true;
+                }
+
+                if !dialog.passphrase.is_empty() && dialog.passphrase.len() < MIN_PASSPHRASE_LEN {
+                    ui.colored_label(
+                        egui::Color32::YELLOW,
+                        format!(
+                            "The passphrase must be at least {} characters",
+                            MIN_PASSPHRASE_LEN
+                        ),
+                    );
+                }
+                if dialog.enable
+                    && !dialog.confirm_passphrase.is_empty()
+                    && dialog.passphrase != dialog.confirm_passphrase
+                {
+                    ui.colored_label(egui::Color32::YELLOW, "Passphrases do not match");
+                }
+                if let Some(error) = &dialog.error {
+                    ui.colored_label(egui::Color32::from_rgb(255, 100, 100), error);
+                }
+            });
+
+        if !open && !reencrypting {
+            self.two_person_dialog = None;
+        } else if submit {
+            if let Err(e) = self.start_two_person_change() {
+                log::error!("Failed to change two-person unlock: {}", e);
+                if let Some(dialog) = &mut self.two_person_dialog {
+                    dialog.error = Some(e.to_string());
+                }
+            }
+        }
+    }
+
+    /// Checks the dialog's input and starts replacing the key.
+    fn start_two_person_change(&mut self) -> anyhow::Result<()> {
+        let (Some(dialog), Some(user), Some(user_manager)) = (
+            &self.two_person_dialog,
+            self.current_user.clone(),
+            &self.user_manager,
+        ) else {
+            anyhow::bail!("Not logged in");
+        };
+        let enable = dialog.enable;
+        let password = dialog.password.clone();
+        let passphrase = dialog.passphrase.clone();
+
+        // Nothing has been changed if any of these fail
+        user_manager
+            .authenticate(&user.username, &password)
+            .map_err(|_| anyhow::anyhow!("Your password is incorrect"))?;
+        if enable && passphrase == password {
+            anyhow::bail!("The second passphrase must differ from your password");
+        }
+        if passphrase.len() > 128 {
+            anyhow::bail!("The passphrase must be less than 128 characters");
+        }
+        self.check_key_change_allowed(&user.id)?;
+        // Unsaved edits are re-encrypted by the job, so write them first
+        self.save_notes();
+
+        let Some(crypto_manager) = &self.crypto_manager else {
+            anyhow::bail!("Not logged in");
+        };
+        self.reencrypt_job = Some(ReencryptJob::change_two_person_unlock(
+            &user.id,
+            crypto_manager.clone(),
+            enable,
+            password,
+            passphrase,
+        ));
+        if let Some(dialog) = &mut self.two_person_dialog {
+            dialog.error = None;
+        }
+        Ok(())
+    }
+
+    /// Writes the settings, journal and split key once the files were
+    /// re-encrypted with the new key.
+    ///
+    /// # Returns
+    ///
+    /// * `anyhow::Result<()>` - Ok if two-person unlock was turned on or
+    ///   off; on Err the split key file is unchanged
+    pub fn finish_two_person_change(&mut self) -> anyhow::Result<()> {
+        let Some(user) = self.current_user.clone() else {
+            anyhow::bail!("Not logged in");
+        };
+        self.reencrypt_user_state()?;
+        let Some(crypto_manager) = &mut self.crypto_manager else {
+            anyhow::bail!("Not logged in");
+        };
+        crypto_manager.complete_split_key_change(&user.id)?;
+        if let Err(e) = crypto_manager.complete_kdf_upgrade(&user.id) {
+            log::warn!("Failed to record the key derivation parameters: {}", e);
+        }
+        Ok(())
+    }
+
+    /// Records a two-person unlock change that failed, in the dialog.
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - What was not changed and why
+    ///
+    /// # Returns
+    ///
+    /// * `String` - The summary to show
+    pub fn two_person_change_failed(&mut self, message: String) -> String {
+        log::error!("{}", message);
+        if let Some(dialog) = &mut self.two_person_dialog {
+            dialog.error = Some(message.clone());
+        }
+        message
+    }
+
+    /// Renders the prompt for the second passphrase after the password of
+    /// a user with two-person unlock was accepted.
+    ///
+    /// # Arguments
+    ///
+    /// * `ui` - The login screen UI
+    pub fn render_second_passphrase_prompt(&mut self, ui: &mut egui::Ui) {
+        let Some((_, user)) = &self.pending_unlock else {
+            return;
+        };
+        ui.label(format!("Password of {} accepted.", user.username));
+        ui.label("Now the second person enters the passphrase:");
+        ui.add_space(10.0);
+
+        let response = ui.add(
+            egui::TextEdit::singleline(&mut self.second_passphrase_input)
+                .password(true)
+                .desired_width(200.0),
+        );
+        if !response.has_focus() && !response.lost_focus() {
+            response.request_focus();
+        }
+        ui.add_space(20.0);
+
+        let can_submit = !self.second_passphrase_input.is_empty();
+        let mut submit = false;
+        let mut cancel = false;
+        ui.horizontal(|ui| {
+            ui.add_space((ui.available_width() - 140.0).max(0.0) / 2.0);
+            if ui
+                .add_enabled(can_submit, egui::Button::new("Unlock"))
+                .clicked()
+                || (can_submit
+                    && response.lost_focus()
+                    && ui.input(|i| i.key_pressed(egui::Key::Enter)))
+            {
+                submit = true;
+            }
+            if ui.button("Cancel").clicked() {
+                cancel = true;
+            }
+        });
+
+        if let Some(error) = &self.authentication_error {
+            ui.add_space(10.0);
+            ui.colored_label(egui::Color32::RED, error);
+        }
+
+        if submit {
+            self.start_second_share_unlock();
+        } else if cancel {
+            self.pending_unlock = None;
+            self.second_passphrase_input.clear();
+            self.authentication_error = None;
+        }
+    }
+
+    /// Completes the login with the second passphrase on a background
+    /// thread, like `start_authentication`.
+    ///
+    /// A wrong passphrase ends the login; it starts over with the password.
+    fn start_second_share_unlock(&mut self) {
+        let Some((mut crypto_manager, user)) = self.pending_unlock.take() else {
+            return;
+        };
+        let passphrase = std::mem::take(&mut self.second_passphrase_input);
+
+        self.is_authenticating = true;
+        self.authentication_error = None;
+        self.auth_start_time = Some(std::time::Instant::now());
+        let (sender, receiver) = mpsc::channel();
+        self.auth_receiver = Some(receiver);
+
+        thread::spawn(move || {
+            let report = |stage: AuthStage| {
+                let _ = sender.send(AuthResult::Progress(stage));
+            };
+            let result =
+                match crypto_manager.unlock_second_share(&user.id, &passphrase, &|estimate| {
+                    report(AuthStage::DerivingKey(estimate))
+                }) {
+                    Ok(()) => {
+                        log::info!("Two-person unlock successful");
+                        crate::app::record_login(
+                            &user.id,
+                            LoginOutcome::Success,
+                            crypto_manager.hardware_check(),
+                        );
+                        AuthResult::Success(crypto_manager, user)
+                    }
+                    Err(e) => {
+                        log::warn!("Two-person unlock failed: {}", e);
+                        crate::app::record_login(
+                            &user.id,
+                            LoginOutcome::WrongPassword,
+                            crypto_manager.hardware_check(),
+                        );
+                        AuthResult::Error(format!("Login failed: {}", e))
+                    }
+                };
+            if sender.send(result).is_err() {
+                log::warn!("Failed to send authentication result - UI may have closed");
+            }
+        });
+    }
+}
@@ -7,15 +7,33 @@
 //! Handles user account creation, authentication, and management.
 //! Provides secure password hashing, user validation, and account operations
 //! with persistent storage of user credentials.
+//!
+//! The user database is protected by an HMAC-SHA256 tag stored next to it
+//! in `users.mac`, keyed with a random local key kept in the OS keychain
+//! (see [`crate::keyring`]). Offline edits of `users.json`, such as swapping
+//! password hashes, are detected when the database is loaded.
 
+use crate::{keyring, secure_fs};
 use anyhow::{anyhow, Result};
 use argon2::{password_hash::SaltString, Argon2, PasswordHash, PasswordHasher, PasswordVerifier};
-use chacha20poly1305::aead::OsRng;
+use chacha20poly1305::aead::{rand_core::RngCore, OsRng};
+use hmac::{Hmac, Mac};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use sha2::Sha256;
+use std::collections::{HashMap, HashSet};
 use std::fs;
+use std::sync::OnceLock;
 use uuid::Uuid;
 
+/// HMAC used to protect the user database.
+type HmacSha256 = Hmac<Sha256>;
+
+/// Length of the random user database integrity key in bytes.
+const INTEGRITY_KEY_LEN: usize = 32;
+
+/// Keychain name of the user database integrity key.
+const INTEGRITY_KEY_NAME: &str = "users-integrity-key";
+
 /// Represents a user account with authentication credentials.
 ///
 /// Contains all necessary information for user authentication and
@@ -143,6 +161,10 @@ pub struct UserManager {
     users_file: std::path::PathBuf,
     /// In-memory cache of all users
     users: HashMap<String, User>,
+    /// Set when the user database failed its integrity check on load
+    integrity_warning: Option<String>,
+    /// Integrity key, loaded from the keychain on first use
+    integrity_key: OnceLock<Vec<u8>>,
 }
 
 impl UserManager {
@@ -179,6 +201,8 @@ impl UserManager {
         let mut manager = Self {
             users_file,
             users: HashMap::new(),
+            integrity_warning: None,
+            integrity_key: OnceLock::new(),
         };
 
         manager.load_users()?;
@@ -198,24 +222,194 @@ impl UserManager {
     /// # Behavior
     ///
     /// - Creates empty database if file doesn't exist
+    /// - Verifies the integrity tag and records a warning if it is wrong or
+    ///   missing
+    /// - Signs databases written by versions without integrity tags
     /// - Logs the number of users loaded
     /// - Handles JSON parsing errors
     fn load_users(&mut self) -> Result<()> {
         if !self.users_file.exists() {
-            println!("Users file doesn't exist, starting with empty user database");
+            log::info!("Users file doesn't exist, starting with empty user database");
             return Ok(());
         }
 
         let content = fs::read_to_string(&self.users_file)?;
+        self.verify_integrity(content.as_bytes())?;
         self.users = serde_json::from_str(&content)?;
-        println!("Loaded {} users from database", self.users.len());
+        log::debug!("Loaded {} users from database", self.users.len());
         Ok(())
     }
 
+    /// Checks the user database against its stored integrity tag.
+    ///
+    /// A missing tag is only signed (trust on first use) if no integrity
+    /// key exists yet, i.e. the database was written by a version without
+    /// integrity protection. Otherwise the tag was removed, which is
+    /// treated like a mismatch. A mismatch, or a key that can't be read,
+    /// does not prevent loading, but is recorded so the auth dialog can
+    /// warn the user.
+    ///
+    /// # Arguments
+    ///
+    /// * `content` - Raw contents of users.json
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - Ok unless the tag or key files cannot be accessed
+    fn verify_integrity(&mut self, content: &[u8]) -> Result<()> {
+        let mac_file = self.mac_file();
+
+        let matches = if mac_file.exists() {
+            let stored_tag = fs::read_to_string(&mac_file)?;
+            self.tag_matches(content, &stored_tag)
+        } else {
+            match self.find_integrity_key() {
+                Ok(None) => {
+                    log::info!("User database has no integrity tag yet, signing it");
+                    secure_fs::write_private_file(&mac_file, self.compute_tag(content)?)?;
+                    return Ok(());
+                }
+                Ok(Some(key)) => {
+                    log::error!("User database integrity tag is missing");
+                    let _ = self.integrity_key.set(key);
+                    Ok(false)
+                }
+                Err(e) => Err(e),
+            }
+        };
+
+        match matches {
+            Ok(true) => {}
+            Ok(false) => {
+                log::error!("User database integrity check failed, users.json was modified");
+                self.integrity_warning = Some(
+                    "The user database was modified outside the application. \
+                     Password hashes or accounts may have been tampered with."
+                        .to_string(),
+                );
+            }
+            Err(e) => {
+                log::error!("User database integrity could not be checked: {}", e);
+                self.integrity_warning = Some(format!(
+                    "The integrity of the user database could not be checked, \
+                     the keychain may be locked: {}",
+                    e
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks the user database against a stored integrity tag.
+    ///
+    /// # Arguments
+    ///
+    /// * `content` - Raw contents of users.json
+    /// * `stored_tag` - Contents of users.mac
+    ///
+    /// # Returns
+    ///
+    /// * `Result<bool>` - Whether the tag matches, Err if the integrity key
+    ///   can't be read
+    fn tag_matches(&self, content: &[u8], stored_tag: &str) -> Result<bool> {
+        Ok(hex::decode(stored_tag.trim())
+            .ok()
+            .map(|tag| {
+                self.new_mac()
+                    .map(|mac| mac.chain_update(content).verify_slice(&tag))
+            })
+            .transpose()?
+            .is_some_and(|result| result.is_ok()))
+    }
+
+    /// Computes the hex-encoded integrity tag of the user database.
+    fn compute_tag(&self, content: &[u8]) -> Result<String> {
+        let tag = self
+            .new_mac()?
+            .chain_update(content)
+            .finalize()
+            .into_bytes();
+        Ok(hex::encode(tag))
+    }
+
+    /// Creates an HMAC instance keyed with the local integrity key.
+    ///
+    /// The key is read from the keychain once and cached for the lifetime
+    /// of the manager, so saving doesn't query the keychain every time.
+    fn new_mac(&self) -> Result<HmacSha256> {
+        let key = match self.integrity_key.get() {
+            Some(key) => key,
+            None => {
+                let key = self.load_integrity_key()?;
+                self.integrity_key.get_or_init(|| key)
+            }
+        };
+
+        HmacSha256::new_from_slice(key).map_err(|e| anyhow!("Invalid integrity key: {}", e))
+    }
+
+    /// Loads the integrity key from the keychain.
+    ///
+    /// The key is generated on first use, i.e. only if `find_integrity_key`
+    /// confirmed that none exists. A failed lookup is returned as an error,
+    /// so a keychain that is locked or unavailable never gets its key
+    /// replaced.
+    fn load_integrity_key(&self) -> Result<Vec<u8>> {
+        if let Some(key) = self.find_integrity_key()? {
+            return Ok(key);
+        }
+
+        let mut key = vec![0u8; INTEGRITY_KEY_LEN];
+        OsRng.fill_bytes(&mut key);
+        keyring::store_secret(INTEGRITY_KEY_NAME, &key)?;
+        Ok(key)
+    }
+
+    /// Looks up the integrity key without generating one.
+    ///
+    /// A key left in `users.key` by earlier versions is moved into the
+    /// keychain and the file is securely deleted.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Option<Vec<u8>>>` - The key, None if none was ever
+    ///   stored, or error if the keychain lookup failed
+    fn find_integrity_key(&self) -> Result<Option<Vec<u8>>> {
+        if let Some(key) = keyring::load_secret(INTEGRITY_KEY_NAME)? {
+            return Ok(Some(key));
+        }
+
+        let legacy_key_file = self.users_file.with_file_name("users.key");
+        if legacy_key_file.exists() {
+            let key = fs::read(&legacy_key_file)?;
+            let location = keyring::store_secret(INTEGRITY_KEY_NAME, &key)?;
+            secure_fs::secure_remove_file(&legacy_key_file)?;
+            log::info!("Moved user database integrity key to {:?}", location);
+            return Ok(Some(key));
+        }
+
+        Ok(None)
+    }
+
+    /// Returns the path of the integrity tag file.
+    fn mac_file(&self) -> std::path::PathBuf {
+        self.users_file.with_file_name("users.mac")
+    }
+
+    /// Returns the integrity warning recorded while loading the database.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<&str>` - Description of the problem, or None if the check passed
+    pub fn integrity_warning(&self) -> Option<&str> {
+        self.integrity_warning.as_deref()
+    }
+
     /// Saves users to the persistent storage file.
     ///
     /// Serializes the in-memory user cache to JSON and writes it to
-    /// the users.json file. Sets secure file permissions on Unix systems.
+    /// the users.json file, followed by its integrity tag. Sets secure file
+    /// permissions on both.
     ///
     /// # Returns
     ///
@@ -224,23 +418,15 @@ impl UserManager {
     /// # Security Features
     ///
     /// - Pretty-printed JSON for readability
-    /// - Secure file permissions (0o600 on Unix)
+    /// - Secure file permissions (0o600 on Unix, owner-only ACL on Windows)
     /// - Atomic write operations where possible
     /// - Logs successful saves
     fn save_users(&self) -> Result<()> {
         let content = serde_json::to_string_pretty(&self.users)?;
-        fs::write(&self.users_file, content)?;
-
-        // Set secure file permissions on Unix systems
-        #[cfg(unix)]
-        {
-            use std::os::unix::fs::PermissionsExt;
-            let mut perms = fs::metadata(&self.users_file)?.permissions();
-            perms.set_mode(0o600); // Read/write for owner only
-            fs::set_permissions(&self.users_file, perms)?;
-        }
+        secure_fs::write_private_file(&self.users_file, &content)?;
+        secure_fs::write_private_file(&self.mac_file(), self.compute_tag(content.as_bytes())?)?;
 
-        println!("Saved {} users to database", self.users.len());
+        log::debug!("Saved {} users to database", self.users.len());
         Ok(())
     }
 
@@ -334,7 +520,7 @@ impl UserManager {
         self.users.insert(username, user);
         self.save_users()?;
 
-        println!("Successfully created user account");
+        log::info!("Successfully created user account");
         Ok(())
     }
 
@@ -379,13 +565,62 @@ impl UserManager {
             .ok_or_else(|| anyhow!("Invalid username or password"))?;
 
         if user.verify_password(password)? {
-            println!("User {} authenticated successfully", username);
+            log::debug!("User {} authenticated successfully", user.id);
             Ok(user.clone())
         } else {
             Err(anyhow!("Invalid username or password"))
         }
     }
 
+    /// Looks up the username of a user ID.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - The ID of the user
+    ///
+    /// # Returns
+    ///
+    /// * `Option<&str>` - The username, or None if no such user exists
+    pub fn username_of(&self, user_id: &str) -> Option<&str> {
+        self.users
+            .values()
+            .find(|user| user.id == user_id)
+            .map(|user| user.username.as_str())
+    }
+
+    /// Looks up the user ID of a username.
+    ///
+    /// # Arguments
+    ///
+    /// * `username` - The username
+    ///
+    /// # Returns
+    ///
+    /// * `Option<&str>` - The user ID, or None if no such user exists
+    pub fn user_id_of(&self, username: &str) -> Option<&str> {
+        self.users.get(username).map(|user| user.id.as_str())
+    }
+
+    /// Reads the IDs of the registered users from the user database anew.
+    ///
+    /// Other instances may have registered or deleted accounts since this
+    /// manager loaded the database. Unlike loading, a missing database or
+    /// integrity tag and a tag mismatch are errors, so the result can be
+    /// trusted to tell that an account no longer exists.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<HashSet<String>>` - The user IDs, or error
+    pub fn reload_user_ids(&self) -> Result<HashSet<String>> {
+        let content = fs::read_to_string(&self.users_file)?;
+        let stored_tag = fs::read_to_string(self.mac_file())?;
+        if !self.tag_matches(content.as_bytes(), &stored_tag)? {
+            return Err(anyhow!("The user database failed its integrity check"));
+        }
+        let users: HashMap<String, User> = serde_json::from_str(&content)?;
+        Ok(users.values().map(|user| user.id.clone()).collect())
+    }
+
     /// Returns the total number of registered users.
     ///
     /// Useful for displaying statistics or implementing user limits.
@@ -423,7 +658,7 @@ impl UserManager {
 
         self.users.remove(username);
         self.save_users()?;
-        println!("User {} deleted successfully", username);
+        log::info!("User account deleted successfully");
         Ok(())
     }
 
@@ -497,10 +732,17 @@ impl UserManager {
         updated_user.password_hash = password_hash.to_string();
         updated_user.salt = salt.to_string();
 
-        self.users.insert(username.to_string(), updated_user);
-        self.save_users()?;
+        // Keep the old record in memory if the database can't be written,
+        // so memory and disk agree
+        let previous_user = self.users.insert(username.to_string(), updated_user);
+        if let Err(e) = self.save_users() {
+            if let Some(previous_user) = previous_user {
+                self.users.insert(username.to_string(), previous_user);
+            }
+            return Err(e);
+        }
 
-        println!("Password changed successfully for user {}", username);
+        log::info!("Password changed successfully");
         Ok(())
     }
 }
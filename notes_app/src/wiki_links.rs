@@ -0,0 +1,105 @@
+//! # Wiki Links Module
+//!
+//! Detection and rewriting of links between notes. A wiki link names the
+//! title of another note in double brackets: `[[Groceries]]`. A heading
+//! can follow after `#` and a label after `|`, as in
+//! `[[Groceries#Monday|this week's list]]`; titles match ignoring case.
+//! Links inside fenced code blocks are code, not links.
+//!
+//! Since links refer to titles, renaming a note rewrites the links to it,
+//! see `NotesApp::rename_note`.
+
+use crate::highlight;
+use std::ops::Range;
+
+/// Marker opening a wiki link.
+const LINK_OPEN: &str = "[[";
+/// Marker closing a wiki link.
+const LINK_CLOSE: &str = "]]";
+
+/// A wiki link in a text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WikiLink {
+    /// Byte range of the title the link points to, without the heading,
+    /// the label or surrounding spaces
+    pub target: Range<usize>,
+}
+
+/// Finds the wiki links of a text.
+///
+/// # Arguments
+///
+/// * `text` - The note content
+///
+/// # Returns
+///
+/// * `Vec<WikiLink>` - The links outside of fenced code blocks, in order
+pub fn find_links(text: &str) -> Vec<WikiLink> {
+    let code_blocks = highlight::fenced_blocks(text);
+    let mut links = Vec::new();
+    let mut position = 0;
+
+    while let Some(start) = text[position..].find(LINK_OPEN).map(|i| position + i) {
+        let inner_start = start + LINK_OPEN.len();
+        let Some(inner_len) = text[inner_start..].find(LINK_CLOSE) else {
+            break;
+        };
+        let inner = &text[inner_start..inner_start + inner_len];
+        position = inner_start + inner_len + LINK_CLOSE.len();
+
+        if inner.contains('\n') || code_blocks.iter().any(|(code, _)| code.contains(&start)) {
+            // Another link may open inside the skipped text
+            position = inner_start;
+            continue;
+        }
+
+        let target = inner.split(['#', '|']).next().unwrap_or_default();
+        let leading = target.len() - target.trim_start().len();
+        let target = target.trim();
+        if target.is_empty() {
+            continue;
+        }
+        let target_start = inner_start + leading;
+        links.push(WikiLink {
+            target: target_start..target_start + target.len(),
+        });
+    }
+
+    links
+}
+
+/// Points the wiki links to a title at another title.
+///
+/// Headings and labels of the links are kept.
+///
+/// # Arguments
+///
+/// * `text` - The note content
+/// * `old_title` - The title the links point to now
+/// * `new_title` - The title they should point to
+///
+/// # Returns
+///
+/// * `Option<(String, usize)>` - The rewritten text and the number of
+///   rewritten links, or None if no link points to `old_title`
+pub fn rewrite_links(text: &str, old_title: &str, new_title: &str) -> Option<(String, usize)> {
+    let old_title = old_title.trim();
+    let targets: Vec<Range<usize>> = find_links(text)
+        .into_iter()
+        .map(|link| link.target)
+        .filter(|target| text[target.clone()].eq_ignore_ascii_case(old_title))
+        .collect();
+    if targets.is_empty() {
+        return None;
+    }
+
+    let mut rewritten = String::with_capacity(text.len());
+    let mut position = 0;
+    for target in &targets {
+        rewritten.push_str(&text[position..target.start]);
+        rewritten.push_str(new_title.trim());
+        position = target.end;
+    }
+    rewritten.push_str(&text[position..]);
+    Some((rewritten, targets.len()))
+}
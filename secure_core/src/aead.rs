@@ -0,0 +1,691 @@
+//! # AEAD Module
+//!
+//! Authenticated encryption with the supported algorithms.
+//!
+//! [`seal`] and [`open`] work with a detached nonce, for formats that store
+//! the nonce themselves. [`Cipher`] produces self-describing blobs that
+//! carry the algorithm and nonce in a small header, so the algorithm can
+//! change without breaking existing data:
+//!
+//! ```text
+//! +-------+---------+-----------+-------+------------+
+//! | magic | version | algorithm | nonce | ciphertext |
+//! | 4 B   | 1 B     | 1 B       | 24 B* | ...        |
+//! +-------+---------+-----------+-------+------------+
+//! ```
+//!
+//! \* 24 bytes for XChaCha20Poly1305, 12 bytes for AES-256-GCM and
+//! ChaCha20Poly1305.
+//!
+//! Version 2 blobs bind the caller's associated data into the tag, version
+//! 1 blobs carry none. Blobs written before the header existed (bare
+//! `nonce || ciphertext`, always ChaCha20Poly1305) are still decrypted,
+//! until the caller turns the older formats off with
+//! [`Cipher::set_legacy_formats`] once all of its data is rewritten.
+
+use crate::kdf::Key;
+use aes_gcm::Aes256Gcm;
+use anyhow::{anyhow, Result};
+use chacha20poly1305::{
+    aead::{Aead, AeadCore, KeyInit, OsRng, Payload},
+    ChaCha20Poly1305, XChaCha20Poly1305,
+};
+use serde::{Deserialize, Serialize};
+
+/// Magic bytes at the start of every versioned ciphertext blob.
+const BLOB_MAGIC: &[u8; 4] = b"SNCB";
+/// Current version of the blob header layout (2: associated data bound).
+const BLOB_FORMAT_VERSION: u8 = 2;
+/// Header version written before associated data was bound into the tag.
+const BLOB_FORMAT_VERSION_NO_AAD: u8 = 1;
+/// Length of the versioned header (magic + version + algorithm id).
+const BLOB_HEADER_LEN: usize = BLOB_MAGIC.len() + 2;
+/// Nonce length of the legacy headerless format.
+const LEGACY_NONCE_LEN: usize = 12;
+
+/// Supported authenticated encryption algorithms.
+///
+/// The numeric id is written into every blob header, so existing ids must
+/// never be reused for a different algorithm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AeadAlgorithm {
+    /// ChaCha20Poly1305 (RFC 8439) with 12-byte nonces, kept for decrypting
+    /// older data
+    ChaCha20Poly1305,
+    /// AES-256 in Galois/Counter Mode, fast on CPUs with AES-NI
+    Aes256Gcm,
+    /// XChaCha20Poly1305 with random 24-byte nonces, the default
+    XChaCha20Poly1305,
+}
+
+impl AeadAlgorithm {
+    /// All algorithms offered for new data.
+    ///
+    /// Plain ChaCha20Poly1305 is not offered anymore: with 96-bit random
+    /// nonces and data rewritten on every save, XChaCha20's 192-bit nonces
+    /// leave no practical risk of a nonce collision.
+    pub const ALL: [AeadAlgorithm; 2] =
+        [AeadAlgorithm::XChaCha20Poly1305, AeadAlgorithm::Aes256Gcm];
+
+    /// Identifier stored in the blob header.
+    fn id(self) -> u8 {
+        match self {
+            AeadAlgorithm::ChaCha20Poly1305 => 1,
+            AeadAlgorithm::Aes256Gcm => 2,
+            AeadAlgorithm::XChaCha20Poly1305 => 3,
+        }
+    }
+
+    /// Looks up an algorithm by its header identifier.
+    fn from_id(id: u8) -> Option<Self> {
+        match id {
+            1 => Some(AeadAlgorithm::ChaCha20Poly1305),
+            2 => Some(AeadAlgorithm::Aes256Gcm),
+            3 => Some(AeadAlgorithm::XChaCha20Poly1305),
+            _ => None,
+        }
+    }
+
+    /// Length of the nonce used by the algorithm, in bytes.
+    ///
+    /// # Returns
+    ///
+    /// * `usize` - 24 for XChaCha20Poly1305, 12 otherwise
+    pub fn nonce_len(self) -> usize {
+        match self {
+            AeadAlgorithm::ChaCha20Poly1305 | AeadAlgorithm::Aes256Gcm => 12,
+            AeadAlgorithm::XChaCha20Poly1305 => 24,
+        }
+    }
+
+    /// Human-readable algorithm name.
+    ///
+    /// # Returns
+    ///
+    /// * `&'static str` - Name like "XChaCha20-Poly1305"
+    pub fn label(self) -> &'static str {
+        match self {
+            AeadAlgorithm::ChaCha20Poly1305 => "ChaCha20-Poly1305",
+            AeadAlgorithm::Aes256Gcm => "AES-256-GCM",
+            AeadAlgorithm::XChaCha20Poly1305 => "XChaCha20-Poly1305",
+        }
+    }
+}
+
+/// Layout of a ciphertext blob, as read from its header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlobFormat {
+    /// Versioned header with the given version number
+    Versioned(u8),
+    /// Headerless `nonce || ciphertext` of the first releases
+    Legacy,
+}
+
+impl BlobFormat {
+    /// Human-readable description of the format.
+    ///
+    /// # Returns
+    ///
+    /// * `String` - E.g. "Version 2 (associated data bound)"
+    pub fn label(self) -> String {
+        match self {
+            BlobFormat::Versioned(BLOB_FORMAT_VERSION) => {
+                format!("Version {} (associated data bound)", BLOB_FORMAT_VERSION)
+            }
+            BlobFormat::Versioned(BLOB_FORMAT_VERSION_NO_AAD) => {
+                format!(
+                    "Version {} (no associated data)",
+                    BLOB_FORMAT_VERSION_NO_AAD
+                )
+            }
+            BlobFormat::Versioned(version) => format!("Unknown version {}", version),
+            BlobFormat::Legacy => "Legacy (no header)".to_string(),
+        }
+    }
+
+    /// Checks whether `Cipher::decrypt` can read blobs of this format.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - False for versions written by newer releases
+    pub fn is_supported(self) -> bool {
+        match self {
+            BlobFormat::Versioned(version) => {
+                version == BLOB_FORMAT_VERSION || version == BLOB_FORMAT_VERSION_NO_AAD
+            }
+            BlobFormat::Legacy => true,
+        }
+    }
+}
+
+/// What the header of a blob says about it, see [`Cipher::inspect`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlobInfo {
+    /// Layout of the blob
+    pub format: BlobFormat,
+    /// Algorithm named in the header, None for an unknown algorithm id
+    pub algorithm: Option<AeadAlgorithm>,
+    /// The nonce
+    pub nonce: Vec<u8>,
+    /// Length of the ciphertext including its 16-byte tag
+    pub ciphertext_len: usize,
+}
+
+/// Encrypts data under a fresh random nonce.
+///
+/// # Arguments
+///
+/// * `algorithm` - The algorithm to encrypt with
+/// * `key` - The key
+/// * `plaintext` - The data to encrypt
+/// * `aad` - Associated data authenticated along with the plaintext
+///
+/// # Returns
+///
+/// * `Result<(Vec<u8>, Vec<u8>)>` - The nonce and the ciphertext with its
+///   tag, or error if encryption failed
+pub fn seal(
+    algorithm: AeadAlgorithm,
+    key: &Key,
+    plaintext: &[u8],
+    aad: &[u8],
+) -> Result<(Vec<u8>, Vec<u8>)> {
+    let payload = Payload {
+        msg: plaintext,
+        aad,
+    };
+
+    let (nonce, ciphertext) = match algorithm {
+        AeadAlgorithm::ChaCha20Poly1305 => {
+            let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+            let ciphertext = ChaCha20Poly1305::new(key).encrypt(&nonce, payload);
+            (nonce.to_vec(), ciphertext)
+        }
+        AeadAlgorithm::Aes256Gcm => {
+            let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+            let ciphertext = Aes256Gcm::new(key).encrypt(&nonce, payload);
+            (nonce.to_vec(), ciphertext)
+        }
+        AeadAlgorithm::XChaCha20Poly1305 => {
+            let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+            let ciphertext = XChaCha20Poly1305::new(key).encrypt(&nonce, payload);
+            (nonce.to_vec(), ciphertext)
+        }
+    };
+
+    let ciphertext = ciphertext.map_err(|e| anyhow!("Encryption failed: {}", e))?;
+    Ok((nonce, ciphertext))
+}
+
+/// Decrypts data produced by [`seal`].
+///
+/// # Arguments
+///
+/// * `algorithm` - The algorithm the data was encrypted with
+/// * `key` - The key
+/// * `nonce` - The nonce returned by `seal`
+/// * `ciphertext` - The ciphertext with its tag
+/// * `aad` - The associated data passed to `seal`
+///
+/// # Returns
+///
+/// * `Result<Vec<u8>>` - The plaintext, or error if the nonce has the wrong
+///   length or the key, associated data or ciphertext don't match
+pub fn open(
+    algorithm: AeadAlgorithm,
+    key: &Key,
+    nonce: &[u8],
+    ciphertext: &[u8],
+    aad: &[u8],
+) -> Result<Vec<u8>> {
+    if nonce.len() != algorithm.nonce_len() {
+        return Err(anyhow!(
+            "Invalid nonce length {} for {}",
+            nonce.len(),
+            algorithm.label()
+        ));
+    }
+
+    let payload = Payload {
+        msg: ciphertext,
+        aad,
+    };
+    match algorithm {
+        AeadAlgorithm::ChaCha20Poly1305 => {
+            ChaCha20Poly1305::new(key).decrypt(nonce.into(), payload)
+        }
+        AeadAlgorithm::Aes256Gcm => Aes256Gcm::new(key).decrypt(nonce.into(), payload),
+        AeadAlgorithm::XChaCha20Poly1305 => {
+            XChaCha20Poly1305::new(key).decrypt(nonce.into(), payload)
+        }
+    }
+    .map_err(|e| anyhow!("Decryption failed: {}", e))
+}
+
+/// A key and the algorithm for newly encrypted blobs.
+#[derive(Clone)]
+pub struct Cipher {
+    /// The encryption key
+    key: Key,
+    /// Algorithm used for newly encrypted blobs
+    algorithm: AeadAlgorithm,
+    /// Whether blobs without associated data (version 1 and legacy) are
+    /// still decrypted
+    legacy_formats: bool,
+}
+
+impl Cipher {
+    /// Creates a cipher.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The encryption key
+    /// * `algorithm` - Algorithm used for newly encrypted blobs
+    ///
+    /// # Returns
+    ///
+    /// * `Self` - A new Cipher
+    pub fn new(key: Key, algorithm: AeadAlgorithm) -> Self {
+        Self {
+            key,
+            algorithm,
+            legacy_formats: true,
+        }
+    }
+
+    /// Returns the algorithm used for newly encrypted blobs.
+    ///
+    /// # Returns
+    ///
+    /// * `AeadAlgorithm` - The current algorithm
+    pub fn algorithm(&self) -> AeadAlgorithm {
+        self.algorithm
+    }
+
+    /// Selects the algorithm used for newly encrypted blobs.
+    ///
+    /// Existing blobs keep decrypting with the algorithm in their header.
+    ///
+    /// # Arguments
+    ///
+    /// * `algorithm` - The algorithm to use for future encryptions
+    pub fn set_algorithm(&mut self, algorithm: AeadAlgorithm) {
+        self.algorithm = algorithm;
+    }
+
+    /// Selects whether blobs without associated data are still decrypted.
+    ///
+    /// Version 1 and legacy headerless blobs aren't bound to their owner
+    /// and role, so an old blob of another note or file could be swapped in
+    /// without detection. Turn them off once every blob has been rewritten
+    /// in the current format.
+    ///
+    /// # Arguments
+    ///
+    /// * `accepted` - False to reject every blob but the current version
+    pub fn set_legacy_formats(&mut self, accepted: bool) {
+        self.legacy_formats = accepted;
+    }
+
+    /// Encrypts data into a versioned blob.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The plaintext data to encrypt
+    /// * `aad` - Associated data that must be passed again to `decrypt`
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<u8>>` - Header, nonce and ciphertext, or error
+    pub fn encrypt(&self, data: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        let (nonce, ciphertext) = seal(self.algorithm, &self.key, data, aad)?;
+
+        let mut result = Vec::with_capacity(BLOB_HEADER_LEN + nonce.len() + ciphertext.len());
+        result.extend_from_slice(BLOB_MAGIC);
+        result.push(BLOB_FORMAT_VERSION);
+        result.push(self.algorithm.id());
+        result.extend_from_slice(&nonce);
+        result.extend_from_slice(&ciphertext);
+        Ok(result)
+    }
+
+    /// Decrypts a versioned blob, or a blob in the legacy headerless format.
+    ///
+    /// Version 1 and legacy blobs are rejected if turned off with
+    /// `set_legacy_formats`.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The encrypted blob
+    /// * `aad` - Associated data the blob was encrypted with
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<u8>>` - Decrypted plaintext data, or error if the blob
+    ///   is malformed or the key, associated data or blob don't match
+    pub fn decrypt(&self, data: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        if data.len() >= BLOB_HEADER_LEN && data.starts_with(BLOB_MAGIC) {
+            // A legacy nonce may start with the magic bytes by chance
+            let result = self.decrypt_versioned(data, aad);
+            if !self.legacy_formats {
+                return result;
+            }
+            return result.or_else(|e| self.decrypt_legacy(data).map_err(|_| e));
+        }
+
+        self.decrypt_legacy(data)
+    }
+
+    /// Checks whether a blob is in the format `encrypt` writes today.
+    ///
+    /// Callers rewrite blobs for which this returns false, so they can still
+    /// be read after support for older formats is dropped. Only the header
+    /// is inspected; the blob is not authenticated.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The encrypted blob
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - True if the blob carries the current header version
+    pub fn is_current_format(data: &[u8]) -> bool {
+        data.len() >= BLOB_HEADER_LEN
+            && data.starts_with(BLOB_MAGIC)
+            && data[BLOB_MAGIC.len()] == BLOB_FORMAT_VERSION
+    }
+
+    /// Reads the header of a blob without decrypting it.
+    ///
+    /// For diagnostics only: the header is not authenticated, and a legacy
+    /// blob whose nonce starts with the magic bytes by chance is reported
+    /// as versioned.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The encrypted blob
+    ///
+    /// # Returns
+    ///
+    /// * `Result<BlobInfo>` - Format, algorithm and nonce, or error if the
+    ///   blob is too short to hold them
+    pub fn inspect(data: &[u8]) -> Result<BlobInfo> {
+        let (format, algorithm, body) =
+            if data.len() >= BLOB_HEADER_LEN && data.starts_with(BLOB_MAGIC) {
+                (
+                    BlobFormat::Versioned(data[BLOB_MAGIC.len()]),
+                    AeadAlgorithm::from_id(data[BLOB_MAGIC.len() + 1]),
+                    &data[BLOB_HEADER_LEN..],
+                )
+            } else {
+                (
+                    BlobFormat::Legacy,
+                    Some(AeadAlgorithm::ChaCha20Poly1305),
+                    data,
+                )
+            };
+
+        let nonce_len = algorithm.map_or(0, AeadAlgorithm::nonce_len);
+        if body.len() < nonce_len {
+            return Err(anyhow!("Invalid encrypted data"));
+        }
+        let (nonce, ciphertext) = body.split_at(nonce_len);
+        Ok(BlobInfo {
+            format,
+            algorithm,
+            nonce: nonce.to_vec(),
+            ciphertext_len: ciphertext.len(),
+        })
+    }
+
+    /// Decrypts a blob carrying the versioned header.
+    fn decrypt_versioned(&self, data: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        let version = data[BLOB_MAGIC.len()];
+        let aad = match version {
+            BLOB_FORMAT_VERSION => aad,
+            BLOB_FORMAT_VERSION_NO_AAD if self.legacy_formats => &[],
+            BLOB_FORMAT_VERSION_NO_AAD => {
+                return Err(anyhow!(
+                    "Ciphertext format version {} is no longer accepted",
+                    version
+                ))
+            }
+            _ => return Err(anyhow!("Unsupported ciphertext format version {}", version)),
+        };
+
+        let algorithm_id = data[BLOB_MAGIC.len() + 1];
+        let algorithm = AeadAlgorithm::from_id(algorithm_id)
+            .ok_or_else(|| anyhow!("Unknown encryption algorithm id {}", algorithm_id))?;
+
+        let body = &data[BLOB_HEADER_LEN..];
+        if body.len() < algorithm.nonce_len() {
+            return Err(anyhow!("Invalid encrypted data"));
+        }
+        let (nonce, ciphertext) = body.split_at(algorithm.nonce_len());
+        open(algorithm, &self.key, nonce, ciphertext, aad)
+    }
+
+    /// Decrypts a legacy headerless `nonce || ciphertext` blob.
+    fn decrypt_legacy(&self, data: &[u8]) -> Result<Vec<u8>> {
+        if !self.legacy_formats {
+            return Err(anyhow!("Ciphertexts without a header are no longer accepted"));
+        }
+        if data.len() < LEGACY_NONCE_LEN {
+            return Err(anyhow!("Invalid encrypted data"));
+        }
+
+        let (nonce, ciphertext) = data.split_at(LEGACY_NONCE_LEN);
+        open(
+            AeadAlgorithm::ChaCha20Poly1305,
+            &self.key,
+            nonce,
+            ciphertext,
+            &[],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALGORITHMS: [AeadAlgorithm; 3] = [
+        AeadAlgorithm::ChaCha20Poly1305,
+        AeadAlgorithm::Aes256Gcm,
+        AeadAlgorithm::XChaCha20Poly1305,
+    ];
+
+    fn key(byte: u8) -> Key {
+        [byte; 32].into()
+    }
+
+    #[test]
+    fn seal_and_open_round_trip() {
+        for algorithm in ALGORITHMS {
+            let (nonce, ciphertext) = seal(algorithm, &key(1), b"secret", b"context").unwrap();
+            assert_eq!(nonce.len(), algorithm.nonce_len());
+            assert_ne!(ciphertext, b"secret");
+
+            let plaintext = open(algorithm, &key(1), &nonce, &ciphertext, b"context").unwrap();
+            assert_eq!(plaintext, b"secret");
+        }
+    }
+
+    #[test]
+    fn open_rejects_wrong_key_aad_and_tampering() {
+        for algorithm in ALGORITHMS {
+            let (nonce, mut ciphertext) = seal(algorithm, &key(1), b"secret", b"context").unwrap();
+
+            assert!(open(algorithm, &key(2), &nonce, &ciphertext, b"context").is_err());
+            assert!(open(algorithm, &key(1), &nonce, &ciphertext, b"other").is_err());
+
+            ciphertext[0] ^= 1;
+            assert!(open(algorithm, &key(1), &nonce, &ciphertext, b"context").is_err());
+        }
+    }
+
+    #[test]
+    fn open_rejects_wrong_nonce_length_without_panicking() {
+        let (nonce, ciphertext) = seal(AeadAlgorithm::Aes256Gcm, &key(1), b"secret", b"").unwrap();
+
+        assert!(open(
+            AeadAlgorithm::Aes256Gcm,
+            &key(1),
+            &nonce[1..],
+            &ciphertext,
+            b""
+        )
+        .is_err());
+        assert!(open(
+            AeadAlgorithm::XChaCha20Poly1305,
+            &key(1),
+            &nonce,
+            &ciphertext,
+            b""
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn cipher_round_trips_with_every_algorithm() {
+        for algorithm in ALGORITHMS {
+            let cipher = Cipher::new(key(1), algorithm);
+            let blob = cipher.encrypt(b"secret", b"context").unwrap();
+
+            assert!(blob.starts_with(BLOB_MAGIC));
+            assert_eq!(blob[BLOB_MAGIC.len() + 1], algorithm.id());
+            assert_eq!(cipher.decrypt(&blob, b"context").unwrap(), b"secret");
+        }
+    }
+
+    #[test]
+    fn cipher_decrypts_blobs_of_other_algorithms() {
+        let blob = Cipher::new(key(1), AeadAlgorithm::Aes256Gcm)
+            .encrypt(b"secret", b"context")
+            .unwrap();
+
+        let cipher = Cipher::new(key(1), AeadAlgorithm::XChaCha20Poly1305);
+        assert_eq!(cipher.decrypt(&blob, b"context").unwrap(), b"secret");
+    }
+
+    #[test]
+    fn cipher_rejects_wrong_aad_and_key() {
+        let blob = Cipher::new(key(1), AeadAlgorithm::XChaCha20Poly1305)
+            .encrypt(b"secret", b"context")
+            .unwrap();
+
+        let cipher = Cipher::new(key(1), AeadAlgorithm::XChaCha20Poly1305);
+        assert!(cipher.decrypt(&blob, b"other").is_err());
+
+        let other_key = Cipher::new(key(2), AeadAlgorithm::XChaCha20Poly1305);
+        assert!(other_key.decrypt(&blob, b"context").is_err());
+    }
+
+    #[test]
+    fn cipher_decrypts_version_1_blobs_without_aad() {
+        let (nonce, ciphertext) = seal(AeadAlgorithm::Aes256Gcm, &key(1), b"secret", b"").unwrap();
+        let mut blob = BLOB_MAGIC.to_vec();
+        blob.push(BLOB_FORMAT_VERSION_NO_AAD);
+        blob.push(AeadAlgorithm::Aes256Gcm.id());
+        blob.extend_from_slice(&nonce);
+        blob.extend_from_slice(&ciphertext);
+
+        let cipher = Cipher::new(key(1), AeadAlgorithm::XChaCha20Poly1305);
+        assert_eq!(cipher.decrypt(&blob, b"ignored").unwrap(), b"secret");
+    }
+
+    #[test]
+    fn cipher_decrypts_legacy_headerless_blobs() {
+        let (nonce, ciphertext) =
+            seal(AeadAlgorithm::ChaCha20Poly1305, &key(1), b"secret", b"").unwrap();
+        let blob = [nonce, ciphertext].concat();
+
+        let cipher = Cipher::new(key(1), AeadAlgorithm::XChaCha20Poly1305);
+        assert_eq!(cipher.decrypt(&blob, b"context").unwrap(), b"secret");
+    }
+
+    #[test]
+    fn cipher_rejects_swapped_old_blobs_once_legacy_formats_are_off() {
+        // Written for another note before associated data was bound
+        let (nonce, ciphertext) = seal(AeadAlgorithm::Aes256Gcm, &key(1), b"other", b"").unwrap();
+        let mut version_1 = BLOB_MAGIC.to_vec();
+        version_1.push(BLOB_FORMAT_VERSION_NO_AAD);
+        version_1.push(AeadAlgorithm::Aes256Gcm.id());
+        version_1.extend_from_slice(&nonce);
+        version_1.extend_from_slice(&ciphertext);
+        let (nonce, ciphertext) =
+            seal(AeadAlgorithm::ChaCha20Poly1305, &key(1), b"other", b"").unwrap();
+        let legacy = [nonce, ciphertext].concat();
+
+        let mut cipher = Cipher::new(key(1), AeadAlgorithm::XChaCha20Poly1305);
+        let current = cipher.encrypt(b"secret", b"note a").unwrap();
+        assert_eq!(cipher.decrypt(&version_1, b"note a").unwrap(), b"other");
+        assert_eq!(cipher.decrypt(&legacy, b"note a").unwrap(), b"other");
+
+        cipher.set_legacy_formats(false);
+        assert!(cipher.decrypt(&version_1, b"note a").is_err());
+        assert!(cipher.decrypt(&legacy, b"note a").is_err());
+        assert_eq!(cipher.decrypt(&current, b"note a").unwrap(), b"secret");
+    }
+
+    #[test]
+    fn only_current_blobs_are_current_format() {
+        let cipher = Cipher::new(key(1), AeadAlgorithm::XChaCha20Poly1305);
+        assert!(Cipher::is_current_format(
+            &cipher.encrypt(b"secret", b"context").unwrap()
+        ));
+
+        let (nonce, ciphertext) = seal(AeadAlgorithm::Aes256Gcm, &key(1), b"secret", b"").unwrap();
+        let mut version_1 = BLOB_MAGIC.to_vec();
+        version_1.push(BLOB_FORMAT_VERSION_NO_AAD);
+        version_1.push(AeadAlgorithm::Aes256Gcm.id());
+        version_1.extend_from_slice(&nonce);
+        version_1.extend_from_slice(&ciphertext);
+        assert!(!Cipher::is_current_format(&version_1));
+
+        let (nonce, ciphertext) =
+            seal(AeadAlgorithm::ChaCha20Poly1305, &key(1), b"secret", b"").unwrap();
+        assert!(!Cipher::is_current_format(&[nonce, ciphertext].concat()));
+        assert!(!Cipher::is_current_format(b"SNCB"));
+    }
+
+    #[test]
+    fn cipher_rejects_malformed_blobs() {
+        let cipher = Cipher::new(key(1), AeadAlgorithm::XChaCha20Poly1305);
+
+        assert!(cipher.decrypt(b"", b"").is_err());
+        assert!(cipher.decrypt(b"SNCB\x02\x03short", b"").is_err());
+        assert!(cipher
+            .decrypt(b"SNCB\x09\x03aaaaaaaaaaaaaaaaaaaaaaaaaaaaaa", b"")
+            .is_err());
+        assert!(cipher
+            .decrypt(b"SNCB\x02\x09aaaaaaaaaaaaaaaaaaaaaaaaaaaaaa", b"")
+            .is_err());
+    }
+
+    #[test]
+    fn inspect_reads_the_header_of_every_format() {
+        let blob = Cipher::new(key(1), AeadAlgorithm::XChaCha20Poly1305)
+            .encrypt(b"secret", b"context")
+            .unwrap();
+        let info = Cipher::inspect(&blob).unwrap();
+        assert_eq!(info.format, BlobFormat::Versioned(BLOB_FORMAT_VERSION));
+        assert_eq!(info.algorithm, Some(AeadAlgorithm::XChaCha20Poly1305));
+        assert_eq!(info.nonce, blob[BLOB_HEADER_LEN..BLOB_HEADER_LEN + 24]);
+        assert_eq!(info.ciphertext_len, b"secret".len() + 16);
+
+        let (nonce, ciphertext) =
+            seal(AeadAlgorithm::ChaCha20Poly1305, &key(1), b"secret", b"").unwrap();
+        let legacy = Cipher::inspect(&[nonce.clone(), ciphertext].concat()).unwrap();
+        assert_eq!(legacy.format, BlobFormat::Legacy);
+        assert_eq!(legacy.nonce, nonce);
+
+        assert!(info.format.is_supported());
+        assert!(legacy.format.is_supported());
+        let mut future = blob.clone();
+        future[BLOB_MAGIC.len()] = BLOB_FORMAT_VERSION + 1;
+        assert!(!Cipher::inspect(&future).unwrap().format.is_supported());
+
+        assert!(Cipher::inspect(&blob[..BLOB_HEADER_LEN + 4]).is_err());
+    }
+}
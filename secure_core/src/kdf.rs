@@ -0,0 +1,303 @@
+//! # Key Derivation Module
+//!
+//! Argon2id key derivation and password verification hashes.
+//!
+//! Keys are derived with explicit [`KdfParams`], which callers store next
+//! to their data so it keeps decrypting when the defaults are raised.
+//! Password verification hashes always use a random salt of their own:
+//! hashing with the key derivation salt and parameters would store the key
+//! itself in the hash string.
+
+use anyhow::{anyhow, Result};
+use argon2::{password_hash::SaltString, Argon2, PasswordHash, PasswordHasher, PasswordVerifier};
+use chacha20poly1305::aead::OsRng;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// A 256-bit symmetric key, usable with every [`crate::AeadAlgorithm`].
+pub type Key = chacha20poly1305::Key;
+
+/// Length of derived keys in bytes.
+const KEY_LEN: usize = 32;
+
+/// Scale factor between the benchmark run and the real derivation memory cost.
+const BENCHMARK_SCALE: u32 = 16;
+
+/// How many times the memory cost, iterations and lanes of
+/// [`KdfParams::CURRENT`] [`KdfParams::is_within_bounds`] accepts, leaving
+/// room for later defaults.
+const MAX_PARAMS_FACTOR: u32 = 4;
+
+/// Argon2id parameters used to derive a key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KdfParams {
+    /// Memory cost in KiB
+    pub memory_cost_kib: u32,
+    /// Number of iterations
+    pub iterations: u32,
+    /// Degree of parallelism (lanes)
+    pub parallelism: u32,
+}
+
+impl KdfParams {
+    /// Parameters used for newly derived keys.
+    pub const CURRENT: KdfParams = KdfParams {
+        memory_cost_kib: 131072, // 128 MB
+        iterations: 3,
+        parallelism: 4,
+    };
+
+    /// Parameters used by versions that did not record them.
+    pub const LEGACY: KdfParams = KdfParams {
+        memory_cost_kib: 131072,
+        iterations: 3,
+        parallelism: 4,
+    };
+
+    /// Defaults of the `argon2` crate (19 MiB, 2 iterations, 1 lane), which
+    /// data written through `Argon2::default()` was derived with.
+    pub const ARGON2_DEFAULT: KdfParams = KdfParams {
+        memory_cost_kib: argon2::Params::DEFAULT_M_COST,
+        iterations: argon2::Params::DEFAULT_T_COST,
+        parallelism: argon2::Params::DEFAULT_P_COST,
+    };
+
+    /// Returns whether these parameters are weaker than the current defaults.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - True if memory cost or iterations are below [`Self::CURRENT`]
+    pub fn is_outdated(&self) -> bool {
+        self.memory_cost_kib < Self::CURRENT.memory_cost_kib
+            || self.iterations < Self::CURRENT.iterations
+    }
+
+    /// Checks that deriving with these parameters is safe to attempt.
+    ///
+    /// Parameters read from unauthenticated data (e.g. the header of a
+    /// passphrase-protected file) must pass this check before deriving, or
+    /// a crafted header could make the derivation allocate terabytes or
+    /// never finish.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - True if there is at least one lane and no value exceeds
+    ///   [`Self::CURRENT`] by more than a small factor
+    pub fn is_within_bounds(&self) -> bool {
+        let max = |current: u32| current.saturating_mul(MAX_PARAMS_FACTOR);
+        self.memory_cost_kib <= max(Self::CURRENT.memory_cost_kib)
+            && (1..=max(Self::CURRENT.iterations)).contains(&self.iterations)
+            && (1..=max(Self::CURRENT.parallelism)).contains(&self.parallelism)
+    }
+
+    /// Builds the Argon2id instance for these parameters.
+    fn argon2(&self) -> Result<Argon2<'static>> {
+        let params = argon2::Params::new(
+            self.memory_cost_kib,
+            self.iterations,
+            self.parallelism,
+            Some(KEY_LEN),
+        )
+        .map_err(|e| anyhow!("Invalid key derivation parameters: {}", e))?;
+
+        Ok(Argon2::new(
+            argon2::Algorithm::Argon2id,
+            argon2::Version::V0x13,
+            params,
+        ))
+    }
+}
+
+/// Derives a key from a password with Argon2id.
+///
+/// # Arguments
+///
+/// * `password` - The password or passphrase
+/// * `salt` - Salt of at least 8 bytes
+/// * `params` - The Argon2 parameters to derive with
+///
+/// # Returns
+///
+/// * `Result<Key>` - The derived key, or error if the parameters or the
+///   salt are invalid
+pub fn derive_key(password: &str, salt: &[u8], params: &KdfParams) -> Result<Key> {
+    let mut key = [0u8; KEY_LEN];
+    params
+        .argon2()?
+        .hash_password_into(password.as_bytes(), salt, &mut key)
+        .map_err(|e| anyhow!("Key derivation failed: {}", e))?;
+    Ok(key.into())
+}
+
+/// Estimates how long [`derive_key`] takes on this machine.
+///
+/// Runs a single Argon2 pass with a fraction of the memory cost and
+/// extrapolates, which takes only a few tens of milliseconds.
+///
+/// # Arguments
+///
+/// * `params` - The parameters the derivation will use
+///
+/// # Returns
+///
+/// * `Duration` - Estimated key derivation time
+pub fn estimate_derivation_time(params: &KdfParams) -> Duration {
+    let benchmark = KdfParams {
+        memory_cost_kib: (params.memory_cost_kib / BENCHMARK_SCALE).max(8 * params.parallelism),
+        iterations: 1,
+        parallelism: params.parallelism,
+    };
+
+    let start = Instant::now();
+    let _ = derive_key("benchmark", b"benchmark_salt_16", &benchmark);
+    start.elapsed() * BENCHMARK_SCALE * params.iterations
+}
+
+/// Hashes a password for later verification.
+///
+/// Uses the `argon2` crate defaults and a fresh random salt, and returns
+/// the hash in PHC string format.
+///
+/// # Arguments
+///
+/// * `password` - The password to hash
+///
+/// # Returns
+///
+/// * `Result<String>` - The PHC hash string, or error if hashing failed
+pub fn hash_password(password: &str) -> Result<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Ok(Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map_err(|e| anyhow!("Failed to hash password: {}", e))?
+        .to_string())
+}
+
+/// Verifies a password against a hash from [`hash_password`].
+///
+/// # Arguments
+///
+/// * `password` - The password to check
+/// * `hash` - PHC hash string
+///
+/// # Returns
+///
+/// * `Result<()>` - Ok if the password matches, Err if it doesn't or the
+///   hash cannot be parsed
+pub fn verify_password(password: &str, hash: &str) -> Result<()> {
+    let parsed_hash =
+        PasswordHash::new(hash).map_err(|e| anyhow!("Failed to parse password hash: {}", e))?;
+    Argon2::default()
+        .verify_password(password.as_bytes(), &parsed_hash)
+        .map_err(|_| anyhow!("Password verification failed"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Cheap parameters so tests run quickly.
+    const TEST_PARAMS: KdfParams = KdfParams {
+        memory_cost_kib: 64,
+        iterations: 1,
+        parallelism: 1,
+    };
+
+    #[test]
+    fn derive_key_is_deterministic() {
+        let first = derive_key("password", b"some salt", &TEST_PARAMS).unwrap();
+        let second = derive_key("password", b"some salt", &TEST_PARAMS).unwrap();
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn derive_key_depends_on_password_salt_and_params() {
+        let key = derive_key("password", b"some salt", &TEST_PARAMS).unwrap();
+
+        assert_ne!(
+            key,
+            derive_key("Password", b"some salt", &TEST_PARAMS).unwrap()
+        );
+        assert_ne!(
+            key,
+            derive_key("password", b"other salt", &TEST_PARAMS).unwrap()
+        );
+
+        let more_iterations = KdfParams {
+            iterations: 2,
+            ..TEST_PARAMS
+        };
+        assert_ne!(
+            key,
+            derive_key("password", b"some salt", &more_iterations).unwrap()
+        );
+    }
+
+    #[test]
+    fn argon2_default_params_match_argon2_crate_defaults() {
+        let mut expected = [0u8; KEY_LEN];
+        Argon2::default()
+            .hash_password_into(b"password", b"some salt", &mut expected)
+            .unwrap();
+
+        let key = derive_key("password", b"some salt", &KdfParams::ARGON2_DEFAULT).unwrap();
+        assert_eq!(key.as_slice(), expected);
+    }
+
+    #[test]
+    fn derive_key_rejects_invalid_input() {
+        let no_memory = KdfParams {
+            memory_cost_kib: 0,
+            ..TEST_PARAMS
+        };
+        assert!(derive_key("password", b"some salt", &no_memory).is_err());
+        assert!(derive_key("password", b"short", &TEST_PARAMS).is_err());
+    }
+
+    #[test]
+    fn outdated_params_are_detected() {
+        assert!(!KdfParams::CURRENT.is_outdated());
+        assert!(KdfParams::ARGON2_DEFAULT.is_outdated());
+        assert!(TEST_PARAMS.is_outdated());
+    }
+
+    #[test]
+    fn only_bounded_params_are_within_bounds() {
+        assert!(KdfParams::CURRENT.is_within_bounds());
+        assert!(KdfParams::ARGON2_DEFAULT.is_within_bounds());
+        assert!(TEST_PARAMS.is_within_bounds());
+
+        let huge_memory = KdfParams {
+            memory_cost_kib: u32::MAX,
+            ..KdfParams::CURRENT
+        };
+        let endless = KdfParams {
+            iterations: u32::MAX,
+            ..KdfParams::CURRENT
+        };
+        let no_lanes = KdfParams {
+            parallelism: 0,
+            ..KdfParams::CURRENT
+        };
+        assert!(!huge_memory.is_within_bounds());
+        assert!(!endless.is_within_bounds());
+        assert!(!no_lanes.is_within_bounds());
+    }
+
+    #[test]
+    fn password_hash_verifies_only_the_right_password() {
+        let hash = hash_password("correct horse").unwrap();
+
+        assert!(verify_password("correct horse", &hash).is_ok());
+        assert!(verify_password("wrong horse", &hash).is_err());
+        assert!(verify_password("correct horse", "not a hash").is_err());
+    }
+
+    #[test]
+    fn password_hashes_use_random_salts() {
+        assert_ne!(
+            hash_password("password").unwrap(),
+            hash_password("password").unwrap()
+        );
+    }
+}
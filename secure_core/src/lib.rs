@@ -0,0 +1,23 @@
+//! # Secure Core
+//!
+//! Key derivation and authenticated encryption shared by the Secure Notes
+//! application (`notes_app`) and the `egui_test` prototype.
+//!
+//! - [`kdf`]: Argon2id key derivation with recorded parameters, derivation
+//!   time estimates and password verification hashes
+//! - [`aead`]: The supported AEAD algorithms, raw seal/open with a detached
+//!   nonce, and [`Cipher`] for self-describing versioned blobs and
+//!   inspecting their headers
+//! - [`shamir`]: Splitting a key into Shamir shares over GF(2^8) and
+//!   recombining it from enough of them
+//!
+//! Everything that depends on the application (where keys and metadata are
+//! stored, hardware binding, which associated data to bind) stays in the
+//! applications.
+
+pub mod aead;
+pub mod kdf;
+pub mod shamir;
+
+pub use aead::{AeadAlgorithm, BlobFormat, BlobInfo, Cipher};
+pub use kdf::{derive_key, KdfParams, Key};
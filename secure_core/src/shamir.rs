@@ -0,0 +1,227 @@
+//! # Shamir Secret Sharing Module
+//!
+//! Splits a secret, such as a vault key, into shares of which any
+//! `threshold` together give back the secret, while fewer reveal nothing
+//! about it. Each byte of the secret is the constant term of a random
+//! polynomial of degree `threshold - 1` over GF(2^8); a share holds the
+//! values of all polynomials at the share's index.
+//!
+//! The field arithmetic avoids lookup tables and data-dependent branches,
+//! so it doesn't leak the secret through timing.
+
+use anyhow::{anyhow, Result};
+use chacha20poly1305::aead::{rand_core::RngCore, OsRng};
+use serde::{Deserialize, Serialize};
+
+/// One share of a split secret.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Share {
+    /// Point the polynomials were evaluated at, 1 to 255
+    pub index: u8,
+    /// Value of each byte's polynomial at `index`
+    pub bytes: Vec<u8>,
+}
+
+/// Splits a secret into shares.
+///
+/// # Arguments
+///
+/// * `secret` - The secret to split
+/// * `threshold` - Number of shares needed to recombine it, at least 2
+/// * `count` - Number of shares to create, at least `threshold`
+///
+/// # Returns
+///
+/// * `Result<Vec<Share>>` - The shares with indices 1 to `count`, or error
+///   if the threshold or count is invalid
+pub fn split(secret: &[u8], threshold: u8, count: u8) -> Result<Vec<Share>> {
+    if threshold < 2 {
+        return Err(anyhow!("At least 2 shares must be needed to recombine"));
+    }
+    if count < threshold {
+        return Err(anyhow!(
+            "Cannot need {} of only {} shares",
+            threshold,
+            count
+        ));
+    }
+
+    // Coefficients of x^1 .. x^(threshold - 1) for every byte
+    let mut coefficients = vec![0u8; secret.len() * usize::from(threshold - 1)];
+    OsRng.fill_bytes(&mut coefficients);
+
+    let shares = (1..=count)
+        .map(|index| Share {
+            index,
+            bytes: secret
+                .iter()
+                .zip(coefficients.chunks(usize::from(threshold - 1)))
+                .map(|(&constant, higher)| {
+                    // Horner's method, from the highest coefficient down
+                    higher
+                        .iter()
+                        .rev()
+                        .chain(std::iter::once(&constant))
+                        .fold(0, |value, &coefficient| gf_mul(value, index) ^ coefficient)
+                })
+                .collect(),
+        })
+        .collect();
+    coefficients.fill(0);
+    Ok(shares)
+}
+
+/// Recombines a secret from enough of its shares.
+///
+/// Shares of a different secret or too few shares give a wrong result
+/// rather than an error, so callers verify the secret, e.g. by decrypting
+/// something with it.
+///
+/// # Arguments
+///
+/// * `shares` - At least `threshold` shares with distinct indices
+///
+/// # Returns
+///
+/// * `Result<Vec<u8>>` - The secret, or error if the shares don't fit
+///   together
+pub fn combine(shares: &[Share]) -> Result<Vec<u8>> {
+    let Some(first) = shares.first() else {
+        return Err(anyhow!("No shares to combine"));
+    };
+    for (position, share) in shares.iter().enumerate() {
+        if share.index == 0 {
+            return Err(anyhow!("Invalid share index 0"));
+        }
+        if share.bytes.len() != first.bytes.len() {
+            return Err(anyhow!("The shares belong to secrets of different lengths"));
+        }
+        if shares[..position]
+            .iter()
+            .any(|other| other.index == share.index)
+        {
+            return Err(anyhow!("Share {} was given twice", share.index));
+        }
+    }
+
+    // Lagrange interpolation at x = 0; subtraction is XOR in GF(2^8)
+    let weights: Vec<u8> = shares
+        .iter()
+        .map(|share| {
+            shares
+                .iter()
+                .filter(|other| other.index != share.index)
+                .fold(1, |weight, other| {
+                    gf_mul(
+                        weight,
+                        gf_mul(other.index, gf_inv(other.index ^ share.index)),
+                    )
+                })
+        })
+        .collect();
+
+    Ok((0..first.bytes.len())
+        .map(|position| {
+            shares
+                .iter()
+                .zip(&weights)
+                .fold(0, |secret, (share, &weight)| {
+                    secret ^ gf_mul(share.bytes[position], weight)
+                })
+        })
+        .collect())
+}
+
+/// Multiplies in GF(2^8) with the AES polynomial x^8 + x^4 + x^3 + x + 1.
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0;
+    for _ in 0..8 {
+        product ^= a & (b & 1).wrapping_neg();
+        let carry = a >> 7;
+        a = (a << 1) ^ (0x1b & carry.wrapping_neg());
+        b >>= 1;
+    }
+    product
+}
+
+/// Inverts a non-zero element of GF(2^8), as a^254.
+fn gf_inv(a: u8) -> u8 {
+    let mut result = 1;
+    let mut base = a;
+    let mut exponent = 254u8;
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = gf_mul(result, base);
+        }
+        base = gf_mul(base, base);
+        exponent >>= 1;
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &[u8] = b"a 32 byte secret key for testing";
+
+    #[test]
+    fn field_inverse_is_inverse() {
+        for a in 1..=255u8 {
+            assert_eq!(gf_mul(a, gf_inv(a)), 1, "inverse of {}", a);
+        }
+    }
+
+    #[test]
+    fn two_of_two_recombines() {
+        let shares = split(SECRET, 2, 2).unwrap();
+        assert_eq!(shares.len(), 2);
+        assert_ne!(shares[0].bytes, SECRET);
+        assert_eq!(combine(&shares).unwrap(), SECRET);
+
+        let reversed = [shares[1].clone(), shares[0].clone()];
+        assert_eq!(combine(&reversed).unwrap(), SECRET);
+    }
+
+    #[test]
+    fn any_threshold_subset_recombines() {
+        let shares = split(SECRET, 3, 5).unwrap();
+        for skip in 0..5 {
+            for also_skip in skip + 1..5 {
+                let subset: Vec<Share> = shares
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| *i != skip && *i != also_skip)
+                    .map(|(_, share)| share.clone())
+                    .collect();
+                assert_eq!(combine(&subset).unwrap(), SECRET);
+            }
+        }
+    }
+
+    #[test]
+    fn too_few_shares_give_a_different_secret() {
+        let shares = split(SECRET, 3, 3).unwrap();
+        assert_ne!(combine(&shares[..2]).unwrap(), SECRET);
+    }
+
+    #[test]
+    fn shares_are_random() {
+        assert_ne!(split(SECRET, 2, 2).unwrap(), split(SECRET, 2, 2).unwrap());
+    }
+
+    #[test]
+    fn invalid_parameters_and_shares_are_rejected() {
+        assert!(split(SECRET, 1, 2).is_err());
+        assert!(split(SECRET, 3, 2).is_err());
+
+        let shares = split(SECRET, 2, 2).unwrap();
+        assert!(combine(&[]).is_err());
+        assert!(combine(&[shares[0].clone(), shares[0].clone()]).is_err());
+        let shorter = Share {
+            index: 2,
+            bytes: shares[1].bytes[1..].to_vec(),
+        };
+        assert!(combine(&[shares[0].clone(), shorter]).is_err());
+    }
+}